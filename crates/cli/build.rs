@@ -0,0 +1,42 @@
+//! Embeds the commit the CLI is built from as `GIT_HASH`, recorded in trace provenance. A `GIT_HASH`
+//! environment variable wins (for packagers); without git or a repository, as when building from a
+//! source tarball, it is "unknown".
+
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_HASH");
+    let git_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../.git");
+    if git_dir.exists() {
+        // HEAD names the branch, whose ref (or packed-refs) moves with each commit
+        let head = std::fs::read_to_string(git_dir.join("HEAD")).unwrap_or_default();
+        let branch = head.strip_prefix("ref: ").map(str::trim);
+        for file in ["HEAD", "index", "packed-refs"].into_iter().chain(branch) {
+            println!("cargo:rerun-if-changed={}", git_dir.join(file).display());
+        }
+    }
+    let hash = std::env::var("GIT_HASH")
+        .ok()
+        .filter(|h| !h.is_empty())
+        .or_else(git_hash)
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GIT_HASH={hash}");
+}
+
+/// `git rev-parse HEAD`, with `-dirty` when the work tree has uncommitted changes.
+fn git_hash() -> Option<String> {
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .args(args)
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .output()
+            .ok()
+            .filter(|out| out.status.success())
+    };
+    let head = git(&["rev-parse", "HEAD"])?;
+    let hash = String::from_utf8(head.stdout).ok()?.trim().to_string();
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|out| !out.stdout.is_empty());
+    Some(if dirty { format!("{hash}-dirty") } else { hash })
+}
@@ -0,0 +1,214 @@
+//! `analyze`: studies built from repeated headless runs.
+//!
+//! `analyze sensitivity` perturbs each chosen parameter by ±`--percent` around its configured
+//! value, one at a time, and reports the normalized (elasticity) sensitivity of the trip time and
+//! the maximum temperature: the relative change of the output per relative change of the
+//! parameter, from a central difference. A value of -2 means a 1 % increase of the parameter
+//! shortens the trip time by about 2 %.
+
+use std::fmt;
+use std::io::Write;
+
+use anyhow::{bail, Result};
+use clap::{Subcommand, ValueEnum};
+use serde::Serialize;
+
+use crate::exit::{self, InvalidInput};
+use crate::param::{self, Param};
+use crate::{scenario_spec, RunArgs, RunSummary};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct AnalyzeArgs {
+    #[command(subcommand)]
+    pub command: AnalyzeCommand,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum AnalyzeCommand {
+    /// Rank parameters by their effect on trip time and maximum temperature
+    Sensitivity(SensitivityArgs),
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// Table
+    #[default]
+    Text,
+    /// A single JSON object
+    Json,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct SensitivityArgs {
+    /// Parameters to perturb (repeatable or comma-separated; `search --param` names)
+    #[arg(long = "param", value_enum, value_delimiter = ',', required = true)]
+    pub params: Vec<Param>,
+
+    /// Relative perturbation in percent, applied in both directions
+    #[arg(long, default_value_t = 5.0)]
+    pub percent: f64,
+
+    #[arg(value_enum, long, default_value_t = ReportFormat::Text)]
+    pub report: ReportFormat,
+
+    /// Scenario and baseline settings (defaults unless given)
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+/// Effect of one parameter.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ParamSensitivity {
+    pub param: String,
+    pub baseline: f64,
+    /// Trip times of the runs at -percent and +percent (`None`: that run did not trip)
+    pub trip_t_low_s: Option<f64>,
+    pub trip_t_high_s: Option<f64>,
+    /// Normalized trip-time sensitivity; `None` if the baseline or either perturbed run does not
+    /// trip
+    pub trip_time: Option<f64>,
+    /// Normalized maximum-temperature sensitivity
+    pub max_temp: f64,
+}
+
+impl ParamSensitivity {
+    /// Why `trip_time` is missing, for the table.
+    fn trip_time_cell(&self, percent: f64, baseline_trips: bool) -> String {
+        match (self.trip_time, self.trip_t_low_s, self.trip_t_high_s) {
+            (Some(s), ..) => format!("{s:+.3}"),
+            _ if !baseline_trips => "-".into(),
+            (None, None, None) => format!("no trip at ±{percent} %"),
+            (None, None, _) => format!("no trip at -{percent} %"),
+            (None, _, _) => format!("no trip at +{percent} %"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SensitivityReport {
+    pub percent: f64,
+    /// Trip time of the unperturbed run (`None`: it does not trip)
+    pub baseline_trip_t_s: Option<f64>,
+    pub baseline_max_temp_c: f64,
+    /// By decreasing magnitude of the trip-time sensitivity (parameters without one last), then
+    /// of the maximum-temperature sensitivity
+    pub params: Vec<ParamSensitivity>,
+}
+
+impl fmt::Display for SensitivityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.baseline_trip_t_s {
+            Some(t_s) => writeln!(f, "baseline trips at {t_s:.2} s")?,
+            None => writeln!(
+                f,
+                "baseline does not trip; only maximum-temperature sensitivities are reported"
+            )?,
+        }
+        writeln!(
+            f,
+            "baseline max temperature {:.2} °C",
+            self.baseline_max_temp_c
+        )?;
+        writeln!(f, "perturbation ±{} %", self.percent)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:<20} {:>12} {:>22} {:>10}",
+            "parameter", "baseline", "trip time", "max temp"
+        )?;
+        let trips = self.baseline_trip_t_s.is_some();
+        for p in &self.params {
+            writeln!(
+                f,
+                "{:<20} {:>12} {:>22} {:>+10.3}",
+                p.param,
+                p.baseline,
+                p.trip_time_cell(self.percent, trips),
+                p.max_temp
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Normalized central-difference sensitivity of `y` for a ±`frac` relative perturbation.
+fn elasticity(y0: f64, y_low: f64, y_high: f64, frac: f64) -> f64 {
+    (y_high - y_low) / y0 / (2.0 * frac)
+}
+
+pub fn sensitivity(args: &SensitivityArgs) -> Result<SensitivityReport> {
+    if !(args.percent > 0.0 && args.percent < 100.0) {
+        bail!(InvalidInput("--percent must be between 0 and 100".into()));
+    }
+    let frac = args.percent / 100.0;
+    let cfg = args.run.sim_config()?;
+    let spec = scenario_spec(&args.run, &cfg)?;
+    let base = param::run_with(&args.run, &[])?;
+    let base_trip = base.trip_t_s.filter(|_| base.tripped());
+
+    let mut params = Vec::new();
+    for (i, &p) in args.params.iter().enumerate() {
+        if args.params[..i].contains(&p) {
+            continue;
+        }
+        let Some(baseline) = p.baseline(&cfg, &spec) else {
+            bail!(InvalidInput(format!(
+                "scenario {:?} has no coolant event for {p} to change",
+                spec.name
+            )));
+        };
+        if baseline == 0.0 {
+            bail!(InvalidInput(format!(
+                "{p} is 0; a relative perturbation of it is undefined"
+            )));
+        }
+        let low = param::run_with(&args.run, &[(p, baseline * (1.0 - frac))])?;
+        let high = param::run_with(&args.run, &[(p, baseline * (1.0 + frac))])?;
+        let trip_t = |s: &RunSummary| s.trip_t_s.filter(|_| s.tripped());
+        let (trip_t_low_s, trip_t_high_s) = (trip_t(&low), trip_t(&high));
+        let trip_time = match (base_trip, trip_t_low_s, trip_t_high_s) {
+            (Some(t0), Some(lo), Some(hi)) => Some(elasticity(t0, lo, hi, frac)),
+            _ => None,
+        };
+        params.push(ParamSensitivity {
+            param: p.to_string(),
+            baseline,
+            trip_t_low_s,
+            trip_t_high_s,
+            trip_time,
+            max_temp: elasticity(base.max_temp_c, low.max_temp_c, high.max_temp_c, frac),
+        });
+    }
+    params.sort_by(|a, b| {
+        let mag = |s: Option<f64>| s.map_or(-1.0, f64::abs);
+        mag(b.trip_time)
+            .total_cmp(&mag(a.trip_time))
+            .then(b.max_temp.abs().total_cmp(&a.max_temp.abs()))
+    });
+    Ok(SensitivityReport {
+        percent: args.percent,
+        baseline_trip_t_s: base_trip,
+        baseline_max_temp_c: base.max_temp_c,
+        params,
+    })
+}
+
+pub fn execute(args: &AnalyzeArgs, stdout: &mut dyn Write) -> Result<u8> {
+    match &args.command {
+        AnalyzeCommand::Sensitivity(args) => {
+            let flags = args.run.single_run_flags();
+            if !flags.is_empty() {
+                bail!(InvalidInput(format!(
+                    "analyze runs headless; drop {}",
+                    flags.join(", ")
+                )));
+            }
+            let report = sensitivity(args)?;
+            match args.report {
+                ReportFormat::Text => write!(stdout, "{report}")?,
+                ReportFormat::Json => writeln!(stdout, "{}", serde_json::to_string(&report)?)?,
+            }
+        }
+    }
+    Ok(exit::OK)
+}
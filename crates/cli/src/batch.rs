@@ -0,0 +1,359 @@
+//! `batch`: Monte Carlo runs of one scenario over many seeds.
+//!
+//! Each run's outcome goes to `runs.csv` and the aggregate (trip probability with a Wilson score
+//! interval, trip-time percentiles) to `aggregate.json` in `--out-dir`; full traces are only kept
+//! with `--save-traces`.
+//!
+//! Runs are spread over `--jobs` worker threads. Every run builds its own plant, sensors,
+//! controller and safety state from its seed, so results do not depend on scheduling; they are
+//! collected back into seed order before anything is written.
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+use crate::exit::{self, InvalidInput};
+use crate::progress::Progress;
+use crate::{open_output, scenario_spec, RunArgs, RunSummary};
+
+/// z for a two-sided 95 % interval.
+pub(crate) const Z_95: f64 = 1.959_963_984_540_054;
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct BatchArgs {
+    /// Seeds to run: a count N (N consecutive seeds from --seed), a range A..B / A..=B, or a list
+    /// A,B,C
+    #[arg(long, default_value = "10")]
+    pub seeds: SeedSpec,
+
+    /// Also write each run's trace to this directory as seed_<seed>.<jsonl|csv>
+    #[arg(long, value_name = "DIR")]
+    pub save_traces: Option<PathBuf>,
+
+    /// Worker threads [default: number of logical cores]
+    #[arg(long)]
+    pub jobs: Option<NonZeroUsize>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SeedSpec {
+    /// This many consecutive seeds, starting from the run's base seed
+    Count(u64),
+    List(Vec<u64>),
+}
+
+impl SeedSpec {
+    pub fn seeds(&self, base: u64) -> Vec<u64> {
+        match self {
+            SeedSpec::Count(n) => (0..*n).map(|i| base.wrapping_add(i)).collect(),
+            SeedSpec::List(seeds) => seeds.clone(),
+        }
+    }
+}
+
+impl FromStr for SeedSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let num = |t: &str| {
+            t.trim()
+                .parse::<u64>()
+                .map_err(|_| format!("invalid seed {t:?}"))
+        };
+        let spec = if let Some((a, b)) = s.split_once("..=") {
+            SeedSpec::List((num(a)?..=num(b)?).collect())
+        } else if let Some((a, b)) = s.split_once("..") {
+            SeedSpec::List((num(a)?..num(b)?).collect())
+        } else if s.contains(',') {
+            SeedSpec::List(s.split(',').map(num).collect::<Result<_, _>>()?)
+        } else {
+            SeedSpec::Count(num(s)?)
+        };
+        match &spec {
+            SeedSpec::Count(0) => Err("at least one seed is required".to_string()),
+            SeedSpec::List(seeds) if seeds.is_empty() => Err(format!("{s:?} selects no seeds")),
+            _ => Ok(spec),
+        }
+    }
+}
+
+/// One line of `runs.csv`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RunOutcome {
+    pub seed: u64,
+    pub summary: RunSummary,
+}
+
+impl RunOutcome {
+    pub const CSV_HEADER: &'static str = "seed,tripped,reason,t_trip,max_temp,min_margin";
+
+    fn csv_line(&self) -> String {
+        let s = &self.summary;
+        let opt = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+        format!(
+            "{},{},{},{},{},{}",
+            self.seed,
+            s.tripped(),
+            s.reason.as_deref().unwrap_or(""),
+            opt(s.trip_t_s),
+            s.max_temp_c,
+            s.min_margin_c
+        )
+    }
+}
+
+/// Layout version of `runs.csv` and `aggregate.json`, recorded as `aggregate.json`'s `schema`
+/// (batches written before it was recorded are schema 1).
+pub const BATCH_SCHEMA: u32 = 2;
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Aggregate {
+    pub schema: u32,
+    /// Scenario name (or scenario file) the batch ran
+    pub scenario: String,
+    pub runs: usize,
+    pub trips: usize,
+    pub trip_probability: f64,
+    /// 95 % Wilson score interval for the trip probability
+    pub trip_probability_ci95: (f64, f64),
+    /// Trip-time percentiles over the runs that tripped; `None` if none did
+    pub trip_time_s: Option<Percentiles>,
+    pub reasons: BTreeMap<String, usize>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub struct Percentiles {
+    pub p5: f64,
+    pub p25: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p95: f64,
+}
+
+impl Percentiles {
+    /// `None` for an empty sample.
+    pub fn of(sorted: &[f64]) -> Option<Self> {
+        if sorted.is_empty() {
+            return None;
+        }
+        let p = |q| percentile(sorted, q);
+        Some(Self {
+            p5: p(0.05),
+            p25: p(0.25),
+            p50: p(0.50),
+            p75: p(0.75),
+            p95: p(0.95),
+        })
+    }
+}
+
+impl Aggregate {
+    pub fn of(scenario: &str, outcomes: &[RunOutcome]) -> Self {
+        let runs = outcomes.len();
+        let mut trip_times: Vec<f64> = outcomes.iter().filter_map(|o| o.summary.trip_t_s).collect();
+        trip_times.sort_by(f64::total_cmp);
+        let trips = outcomes.iter().filter(|o| o.summary.tripped()).count();
+        let mut reasons = BTreeMap::new();
+        for reason in outcomes.iter().filter_map(|o| o.summary.reason.clone()) {
+            *reasons.entry(reason).or_insert(0) += 1;
+        }
+        Self {
+            schema: BATCH_SCHEMA,
+            scenario: scenario.to_string(),
+            runs,
+            trips,
+            trip_probability: if runs > 0 {
+                trips as f64 / runs as f64
+            } else {
+                f64::NAN
+            },
+            trip_probability_ci95: wilson_interval(trips, runs, Z_95),
+            trip_time_s: Percentiles::of(&trip_times),
+            reasons,
+        }
+    }
+}
+
+/// Run every seed on `jobs` workers, returning the outcomes in seed order.
+fn run_seeds(args: &BatchArgs, seeds: &[u64], jobs: usize) -> Result<Vec<RunOutcome>> {
+    let progress = Progress::new("batch", &args.run);
+    let summaries = run_parallel(seeds, jobs, progress, |&seed| run_one(args, seed))?;
+    Ok(seeds
+        .iter()
+        .zip(summaries)
+        .map(|(&seed, summary)| RunOutcome { seed, summary })
+        .collect())
+}
+
+/// `run(item)` for every item on `jobs` workers, with the results in item order. `progress`
+/// reports the completed runs, the trips so far and the time left.
+pub(crate) fn run_parallel<T, F>(
+    items: &[T],
+    jobs: usize,
+    mut progress: Progress,
+    run: F,
+) -> Result<Vec<RunSummary>>
+where
+    T: Sync,
+    F: Fn(&T) -> Result<RunSummary> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let (tx, rx) = mpsc::channel();
+    let mut results: Vec<Option<Result<RunSummary>>> = items.iter().map(|_| None).collect();
+    let total = items.len();
+    let mut trips = 0;
+    thread::scope(|scope| {
+        for _ in 0..jobs.min(total) {
+            let tx = tx.clone();
+            let (next, run) = (&next, &run);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(item) = items.get(i) else {
+                    break;
+                };
+                if tx.send((i, run(item))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(tx);
+        for (n, (i, result)) in rx.iter().enumerate() {
+            let done = n + 1;
+            trips += usize::from(result.as_ref().is_ok_and(RunSummary::tripped));
+            results[i] = Some(result);
+            let per_run = progress.elapsed().as_secs_f64() / done as f64;
+            progress.update(|| {
+                format!(
+                    "{done}/{total} runs, {trips} tripped, ETA {:.0} s",
+                    per_run * (total - done) as f64
+                )
+            });
+        }
+    });
+    progress.finish(Some(format!(
+        "{total}/{total} runs, {trips} tripped in {:.1} s",
+        progress.elapsed().as_secs_f64()
+    )));
+    results
+        .into_iter()
+        .map(|result| result.expect("every item is run"))
+        .collect()
+}
+
+/// Worker threads for `--jobs` (default: the logical cores).
+pub(crate) fn job_count(jobs: Option<NonZeroUsize>) -> usize {
+    jobs.or_else(|| thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get)
+}
+
+fn run_one(args: &BatchArgs, seed: u64) -> Result<RunSummary> {
+    let run_args = RunArgs {
+        seed: Some(seed),
+        quiet: true,
+        ..args.run.clone()
+    };
+    match &args.save_traces {
+        Some(dir) => {
+            let ext = args.run.format.extension();
+            let gz = if args.run.compress { ".gz" } else { "" };
+            let path = dir.join(format!("seed_{seed}.{ext}{gz}"));
+            crate::run_to_file(&run_args, &path, &AtomicBool::new(false))
+        }
+        None => crate::run(&run_args, &mut io::sink()),
+    }
+}
+
+/// Wilson score interval for `successes` out of `n` Bernoulli trials.
+pub fn wilson_interval(successes: usize, n: usize, z: f64) -> (f64, f64) {
+    if n == 0 {
+        return (0.0, 1.0);
+    }
+    let n = n as f64;
+    let p = successes as f64 / n;
+    let z2 = z * z;
+    let denom = 1.0 + z2 / n;
+    let center = (p + z2 / (2.0 * n)) / denom;
+    let half = z / denom * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    ((center - half).max(0.0), (center + half).min(1.0))
+}
+
+/// Linear-interpolation percentile (`q` in 0..=1) of sorted, non-empty `values`.
+pub fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let pos = q.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f64)
+}
+
+pub fn execute(args: &BatchArgs, stdout: &mut dyn Write) -> Result<u8> {
+    if args.run.out.is_some() {
+        bail!(InvalidInput(
+            "batch writes runs.csv and aggregate.json to --out-dir; drop --out".into()
+        ));
+    }
+    let Some(out_dir) = &args.run.out_dir else {
+        bail!(InvalidInput(
+            "batch needs --out-dir DIR for runs.csv and aggregate.json".into()
+        ));
+    };
+    // --out-dir is batch's own output directory here
+    let flags: Vec<&str> = args
+        .run
+        .single_run_flags()
+        .into_iter()
+        .filter(|flag| *flag != "--out-dir")
+        .collect();
+    if !flags.is_empty() {
+        bail!(InvalidInput(format!(
+            "batch does not support {}",
+            flags.join(", ")
+        )));
+    }
+    let cfg = args.run.sim_config()?;
+    let spec = scenario_spec(&args.run, &cfg)?;
+    let scenario = match &args.run.scenario_file {
+        Some(path) if spec.name.is_empty() => path.display().to_string(),
+        _ => spec.name,
+    };
+    let seeds = args.seeds.seeds(cfg.run.seed);
+    let outcomes = run_seeds(args, &seeds, job_count(args.jobs))?;
+
+    let mut runs_csv = open_output(&out_dir.join("runs.csv"), args.run.force)?;
+    writeln!(runs_csv, "{}", RunOutcome::CSV_HEADER)?;
+    for outcome in &outcomes {
+        writeln!(runs_csv, "{}", outcome.csv_line())?;
+    }
+    runs_csv.flush()?;
+
+    let aggregate = Aggregate::of(&scenario, &outcomes);
+    let mut json = open_output(&out_dir.join("aggregate.json"), args.run.force)?;
+    serde_json::to_writer_pretty(&mut json, &aggregate)?;
+    writeln!(json)?;
+    json.flush()?;
+
+    let (lo, hi) = aggregate.trip_probability_ci95;
+    write!(
+        stdout,
+        "{} runs, {} tripped: p = {:.3} (95% CI {lo:.3}..{hi:.3})",
+        aggregate.runs, aggregate.trips, aggregate.trip_probability
+    )?;
+    if let Some(t) = &aggregate.trip_time_s {
+        write!(stdout, ", median trip time {:.2} s", t.p50)?;
+    }
+    writeln!(stdout, "; wrote {}", out_dir.display())?;
+    Ok(if args.run.fail_on_trip && aggregate.trips > 0 {
+        exit::FAILED_CHECK
+    } else {
+        exit::OK
+    })
+}
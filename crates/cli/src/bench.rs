@@ -0,0 +1,265 @@
+//! `bench`: simulation throughput on this machine.
+//!
+//! Each time step is run in three configurations: no trace output, a JSONL trace written to a
+//! sink, and the JSONL trace with diagnostics columns. Every configuration runs `--steps` steps of
+//! the PID loop and reports steps per second, plus the mean nanoseconds of the plant step, the
+//! three sensor reads, the safety evaluation and the PID update, timed around each call. The
+//! timing lives in this loop only; `simulate` is not instrumented. The safety layer is re-armed
+//! after a trip so every step exercises all four stages.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+use controller::{ControlInput, Pid};
+use safety::SafetyState;
+use serde::Serialize;
+use sim::Sensor;
+
+use crate::analyze::ReportFormat;
+use crate::config::SimConfig;
+use crate::exit::{self, InvalidInput};
+use crate::trace::{DiagColumns, RowReason, TraceFormat, TraceRow, TraceWriter};
+use crate::Scenario;
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct BenchArgs {
+    /// Steps timed per configuration
+    #[arg(long, default_value_t = 100_000)]
+    pub steps: u64,
+
+    /// Time steps to compare, in milliseconds (comma-separated) [default: the configured one]
+    #[arg(long, value_name = "MS", value_delimiter = ',')]
+    pub dt_ms: Vec<u64>,
+
+    /// Simulation description (TOML) supplying the plant, sensors, safety and PID settings
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Scenario whose initial conditions the loop starts from [default: the configured one]
+    #[arg(value_enum, long)]
+    pub scenario: Option<Scenario>,
+
+    #[arg(value_enum, long, default_value_t = ReportFormat::Text)]
+    pub report: ReportFormat,
+}
+
+/// What the benchmarked loop does with its trace rows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BenchOutput {
+    /// No rows are built or written
+    None,
+    /// JSONL rows serialized into a sink
+    Jsonl,
+    /// JSONL rows with the diagnostics columns
+    Diagnostics,
+}
+
+impl fmt::Display for BenchOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BenchOutput::None => "none",
+            BenchOutput::Jsonl => "jsonl",
+            BenchOutput::Diagnostics => "jsonl+diag",
+        })
+    }
+}
+
+/// Timings of one configuration.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BenchResult {
+    pub dt_ms: u64,
+    pub output: BenchOutput,
+    pub steps: u64,
+    pub steps_per_s: f64,
+    /// Mean nanoseconds per step of each stage
+    pub plant_ns: f64,
+    pub sensors_ns: f64,
+    pub safety_ns: f64,
+    pub pid_ns: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct BenchReport {
+    pub scenario: String,
+    pub results: Vec<BenchResult>,
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "scenario {}", self.scenario)?;
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:>6} {:<10} {:>10} {:>12} {:>10} {:>10} {:>10} {:>10}",
+            "dt ms", "output", "steps", "steps/s", "plant ns", "sensors ns", "safety ns", "pid ns"
+        )?;
+        for r in &self.results {
+            writeln!(
+                f,
+                "{:>6} {:<10} {:>10} {:>12.0} {:>10.1} {:>10.1} {:>10.1} {:>10.1}",
+                r.dt_ms,
+                r.output.to_string(),
+                r.steps,
+                r.steps_per_s,
+                r.plant_ns,
+                r.sensors_ns,
+                r.safety_ns,
+                r.pid_ns
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Run `steps` steps of the loop at `cfg`'s time step from `initial`, timing each stage.
+pub fn bench_loop(
+    cfg: &SimConfig,
+    initial: sim::PlantState,
+    steps: u64,
+    output: BenchOutput,
+) -> Result<BenchResult> {
+    let dt_s = (cfg.run.dt_ms as f64) / 1000.0;
+    let p = cfg.plant;
+    let mut x = initial;
+    let mut s_state = SafetyState::default();
+    let [seed1, seed2, seed3] = cfg.sensors.seeds(cfg.run.seed);
+    let mut sensors = [Sensor::new(seed1), Sensor::new(seed2), Sensor::new(seed3)];
+    for (i, sensor) in sensors.iter_mut().enumerate() {
+        cfg.sensors.get(i).apply(sensor);
+    }
+    let mut pid = Pid::new(cfg.pid);
+    let mut applied_prev = None;
+    let mut writer = (output != BenchOutput::None).then(|| {
+        TraceWriter::new(
+            io::sink(),
+            TraceFormat::Jsonl,
+            output == BenchOutput::Diagnostics,
+        )
+    });
+    let (mut plant_t, mut read_t, mut safety_t, mut pid_t) = Default::default();
+    let lap = |since: &mut Instant, total: &mut Duration| {
+        let now = Instant::now();
+        *total += now - *since;
+        *since = now;
+    };
+
+    let started = Instant::now();
+    for k in 0..steps {
+        let t_s = (k as f64) * dt_s;
+        let mut at = Instant::now();
+        let y = sensors.each_mut().map(|s| s.read_temp(x.temp_c, dt_s));
+        lap(&mut at, &mut read_t);
+        safety::evaluate(&cfg.safety, &mut s_state, y);
+        lap(&mut at, &mut safety_t);
+        let (sum, n) = y
+            .into_iter()
+            .filter(|y| y.is_finite())
+            .fold((0.0, 0.0), |(sum, n), y| (sum + y, n + 1.0));
+        let meas = if n > 0.0 { sum / n } else { x.temp_c };
+        at = Instant::now();
+        let ctrl_out = pid.step(ControlInput {
+            setpoint: cfg.run.setpoint,
+            measurement: meas,
+            feedforward: 0.0,
+            dt_s,
+            applied_prev,
+        });
+        lap(&mut at, &mut pid_t);
+        x.power = ctrl_out.command.clamp(0.0, 1.0);
+        applied_prev = Some(x.power);
+        at = Instant::now();
+        x.step(&p, dt_s);
+        lap(&mut at, &mut plant_t);
+
+        if let Some(writer) = &mut writer {
+            writer.write_row(&TraceRow {
+                t_s,
+                setpoint_c: cfg.run.setpoint,
+                true_temp_c: x.temp_c,
+                s1_c: y[0],
+                s2_c: y[1],
+                s3_c: y[2],
+                power: x.power,
+                coolant: x.coolant,
+                scram: s_state.scram,
+                reason: s_state.trip.map(RowReason::Trip),
+                diag: (output == BenchOutput::Diagnostics).then(|| DiagColumns::new(&ctrl_out)),
+            })?;
+        }
+        if s_state.scram {
+            s_state = SafetyState::default();
+        }
+    }
+    if let Some(writer) = writer {
+        writer.finish()?;
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+    let per_step = |total: Duration| total.as_nanos() as f64 / steps as f64;
+    Ok(BenchResult {
+        dt_ms: cfg.run.dt_ms,
+        output,
+        steps,
+        steps_per_s: steps as f64 / elapsed,
+        plant_ns: per_step(plant_t),
+        sensors_ns: per_step(read_t),
+        safety_ns: per_step(safety_t),
+        pid_ns: per_step(pid_t),
+    })
+}
+
+pub fn bench(args: &BenchArgs) -> Result<BenchReport> {
+    if args.steps == 0 {
+        bail!(InvalidInput("--steps must be at least 1".into()));
+    }
+    let cfg = match &args.config {
+        Some(path) => SimConfig::load(path)?,
+        None => SimConfig::default(),
+    };
+    let scenario = args.scenario.unwrap_or(cfg.run.scenario);
+    let mut cfg = cfg.for_scenario(&scenario)?;
+    let problems = cfg.problems();
+    if !problems.is_empty() {
+        bail!(InvalidInput(format!(
+            "invalid configuration: {}",
+            problems.join("; ")
+        )));
+    }
+    let dts = if args.dt_ms.is_empty() {
+        vec![cfg.run.dt_ms]
+    } else {
+        args.dt_ms.clone()
+    };
+    if dts.contains(&0) {
+        bail!(InvalidInput("--dt-ms must be at least 1".into()));
+    }
+
+    let spec = scenario.spec(cfg.run.seconds);
+    let mut results = Vec::new();
+    for dt_ms in dts {
+        cfg.run.dt_ms = dt_ms;
+        for output in [
+            BenchOutput::None,
+            BenchOutput::Jsonl,
+            BenchOutput::Diagnostics,
+        ] {
+            results.push(bench_loop(&cfg, spec.initial.state(), args.steps, output)?);
+        }
+    }
+    Ok(BenchReport {
+        scenario: spec.name,
+        results,
+    })
+}
+
+pub fn execute(args: &BenchArgs, stdout: &mut dyn Write) -> Result<u8> {
+    let report = bench(args)?;
+    match args.report {
+        ReportFormat::Text => write!(stdout, "{report}")?,
+        ReportFormat::Json => writeln!(stdout, "{}", serde_json::to_string(&report)?)?,
+    }
+    Ok(exit::OK)
+}
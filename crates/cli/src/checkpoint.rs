@@ -0,0 +1,181 @@
+//! Checkpoints of a running simulation (`run --checkpoint-every`), and `run --resume` from one.
+//!
+//! A checkpoint holds everything the loop carries from one step to the next: the plant, the three
+//! sensors with their random generators, the safety latch, the controller, the position in the
+//! scenario's events, the `--until` and decimation state and the summary so far, plus the trace
+//! header of the run, which records its configuration. Resuming restores all of it and continues
+//! with the next step, so the rows after the checkpoint are the ones the uninterrupted run would
+//! have written.
+//! The file is MessagePack (floats are stored exactly) and replaced atomically, so a run killed
+//! while writing one still leaves the previous checkpoint.
+
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use controller::{
+    ControlInput, ControlOutput, ControlStatus, Controller, Lqr, Mpc, Pid, PidTerms, Trust,
+};
+use safety::SafetyState;
+use serde::{Deserialize, Serialize};
+use sim::{PlantState, ScenarioSpec, Sensor};
+
+use crate::config::SimConfig;
+use crate::exit::InvalidInput;
+use crate::summary::RunSummary;
+use crate::trace::{Decimator, TraceMeta};
+use crate::until::Until;
+use crate::RunArgs;
+
+/// Layout version of the checkpoints written by this build.
+pub const CHECKPOINT_SCHEMA: u32 = 1;
+
+/// State of a run between two steps.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub schema: u32,
+    /// Header of the checkpointed run (configuration, scenario and run options)
+    pub meta: TraceMeta,
+    /// The scenario as of the checkpoint, with the commands received during the run
+    pub spec: ScenarioSpec,
+    /// Index of the next step to simulate
+    pub step: u64,
+    pub plant: PlantState,
+    pub sensors: [Sensor; 3],
+    pub safety: SafetyState,
+    pub controller: ControllerState,
+    /// Output of the last controller step (the diagnostics columns of the rows after a SCRAM)
+    pub ctrl_out: ControlOutput,
+    pub applied_prev: Option<f64>,
+    pub setpoint_override: Option<f64>,
+    /// Scenario events (in time order) already applied
+    pub next_event: usize,
+    pub alarm: bool,
+    /// Sensors the safety logic found valid at the last step
+    pub valid: [bool; 3],
+    pub until: Until,
+    pub decimator: Decimator,
+    pub summary: RunSummary,
+}
+
+/// The controller of a run, by kind, so its internal state can be saved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ControllerState {
+    Pid(Pid),
+    Lqr(Lqr),
+    Mpc(Mpc),
+}
+
+impl ControllerState {
+    pub fn as_dyn(&mut self) -> &mut dyn Controller {
+        match self {
+            Self::Pid(pid) => pid,
+            Self::Lqr(lqr) => lqr,
+            Self::Mpc(mpc) => mpc,
+        }
+    }
+
+    fn as_dyn_ref(&self) -> &dyn Controller {
+        match self {
+            Self::Pid(pid) => pid,
+            Self::Lqr(lqr) => lqr,
+            Self::Mpc(mpc) => mpc,
+        }
+    }
+}
+
+/// Whichever controller it holds, so a `Simulation` can drive any of them.
+impl Controller for ControllerState {
+    fn update(&mut self, setpoint: f64, measurement: f64, dt_s: f64) -> f64 {
+        self.as_dyn().update(setpoint, measurement, dt_s)
+    }
+
+    fn reset(&mut self) {
+        self.as_dyn().reset()
+    }
+
+    fn reset_to(&mut self, output: f64) {
+        self.as_dyn().reset_to(output)
+    }
+
+    fn track(&mut self, applied_output: f64, dt_s: f64) {
+        self.as_dyn().track(applied_output, dt_s)
+    }
+
+    fn terms(&self) -> PidTerms {
+        self.as_dyn_ref().terms()
+    }
+
+    fn status(&self) -> ControlStatus {
+        self.as_dyn_ref().status()
+    }
+
+    fn set_measurement_trust(&mut self, trust: Trust) {
+        self.as_dyn().set_measurement_trust(trust)
+    }
+
+    fn step(&mut self, input: ControlInput) -> ControlOutput {
+        self.as_dyn().step(input)
+    }
+}
+
+/// `--checkpoint-every`: seconds of simulated time, written as `10s` or `10`.
+pub fn parse_interval(s: &str) -> Result<f64, String> {
+    let number = s.strip_suffix('s').unwrap_or(s);
+    match number.trim().parse::<f64>() {
+        Ok(secs) if secs.is_finite() && secs > 0.0 => Ok(secs),
+        _ => Err(format!(
+            "invalid interval {s:?}, expected seconds of simulated time such as 10s"
+        )),
+    }
+}
+
+impl Checkpoint {
+    /// Write the checkpoint to `path`, through a temporary file renamed over the previous one.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let bytes = rmp_serde::to_vec_named(self).context("encoding the checkpoint")?;
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, bytes).with_context(|| format!("writing {}", path.display()))?;
+        fs::rename(&tmp, path).with_context(|| format!("writing {}", path.display()))
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let bytes =
+            fs::read(path).with_context(|| format!("reading checkpoint {}", path.display()))?;
+        let ckpt: Self = rmp_serde::from_slice(&bytes)
+            .map_err(|e| InvalidInput(format!("{} is not a checkpoint: {e}", path.display())))?;
+        if ckpt.schema > CHECKPOINT_SCHEMA {
+            bail!(InvalidInput(format!(
+                "checkpoint {} has schema {}, newer than this build supports (up to \
+                 {CHECKPOINT_SCHEMA}); upgrade reactor-safety-sim to resume it",
+                path.display(),
+                ckpt.schema
+            )));
+        }
+        Ok(ckpt)
+    }
+
+    /// The run options, configuration and scenario to continue with. `args` supplies the output
+    /// flags and, with `--seconds`, a new end time.
+    pub fn resume_args(&self, args: &RunArgs) -> Result<(RunArgs, SimConfig, ScenarioSpec)> {
+        let (Some(cfg), Some(options)) = (&self.meta.config, &self.meta.options) else {
+            bail!(InvalidInput(
+                "the checkpoint does not record the configuration of its run".into()
+            ));
+        };
+        let mut run_args = args.clone();
+        run_args.apply_options(options)?;
+        let mut cfg = cfg.clone();
+        if let Some(seconds) = args.seconds {
+            cfg.run.seconds = seconds;
+        }
+        if self.safety.scram && !run_args.continue_after_scram {
+            bail!(InvalidInput(
+                "the checkpointed run had already tripped, so there is nothing to resume".into()
+            ));
+        }
+        Ok((run_args, cfg, self.spec.clone()))
+    }
+}
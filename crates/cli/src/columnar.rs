@@ -0,0 +1,243 @@
+//! Parquet traces (`--format parquet`, cargo feature `parquet`): one file with a typed column per
+//! `TraceRow` field, named like the JSONL keys. Temperatures and terms are nullable f64 (`NaN`
+//! dropouts become nulls, as in JSONL), `scram`/`saturated` are booleans and `reason` is a
+//! dictionary-encoded string.
+
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use arrow_array::types::Int32Type;
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, DictionaryArray, Float64Array, RecordBatch, StringArray,
+};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+
+use crate::trace::{DiagColumns, RowReason, TraceRow};
+
+fn schema(diagnostics: bool) -> Schema {
+    let f64_field = |name| Field::new(name, DataType::Float64, true);
+    let mut fields = vec![
+        f64_field("t_s"),
+        f64_field("setpoint_c"),
+        f64_field("true_temp_c"),
+        f64_field("s1_c"),
+        f64_field("s2_c"),
+        f64_field("s3_c"),
+        f64_field("power"),
+        f64_field("coolant"),
+        Field::new("scram", DataType::Boolean, false),
+        Field::new(
+            "reason",
+            DataType::Dictionary(Box::new(DataType::Int32), Box::new(DataType::Utf8)),
+            true,
+        ),
+    ];
+    if diagnostics {
+        fields.extend([
+            f64_field("p_term"),
+            f64_field("i_term"),
+            f64_field("d_term"),
+            f64_field("ff_term"),
+            f64_field("out_presat"),
+            Field::new("saturated", DataType::Boolean, false),
+            Field::new("ctrl_status", DataType::Utf8, false),
+        ]);
+    }
+    Schema::new(fields)
+}
+
+/// Encode `rows` as a complete Parquet file.
+pub fn encode(rows: &[TraceRow], diagnostics: bool) -> io::Result<Vec<u8>> {
+    let f64_col = |get: fn(&TraceRow) -> f64| -> ArrayRef {
+        Arc::new(
+            rows.iter()
+                .map(|r| Some(get(r)).filter(|v| !v.is_nan()))
+                .collect::<Float64Array>(),
+        )
+    };
+    let diag = |r: &TraceRow| r.diag.clone().unwrap_or_else(empty_diag);
+    // Parquet keeps the reason name: a dictionary column of four values
+    let reasons: Vec<Option<String>> = rows.iter().map(TraceRow::reason_name).collect();
+    let diag_col = |get: fn(&DiagColumns) -> f64| -> ArrayRef {
+        Arc::new(
+            rows.iter()
+                .map(|r| Some(get(&diag(r))).filter(|v| !v.is_nan()))
+                .collect::<Float64Array>(),
+        )
+    };
+    let mut columns = vec![
+        f64_col(|r| r.t_s),
+        f64_col(|r| r.setpoint_c),
+        f64_col(|r| r.true_temp_c),
+        f64_col(|r| r.s1_c),
+        f64_col(|r| r.s2_c),
+        f64_col(|r| r.s3_c),
+        f64_col(|r| r.power),
+        f64_col(|r| r.coolant),
+        Arc::new(BooleanArray::from(
+            rows.iter().map(|r| r.scram).collect::<Vec<_>>(),
+        )),
+        Arc::new(
+            reasons
+                .iter()
+                .map(Option::as_deref)
+                .collect::<DictionaryArray<Int32Type>>(),
+        ),
+    ];
+    if diagnostics {
+        columns.extend([
+            diag_col(|d| d.p_term),
+            diag_col(|d| d.i_term),
+            diag_col(|d| d.d_term),
+            diag_col(|d| d.ff_term),
+            diag_col(|d| d.out_presat),
+            Arc::new(BooleanArray::from(
+                rows.iter().map(|r| diag(r).saturated).collect::<Vec<_>>(),
+            )),
+            Arc::new(StringArray::from(
+                rows.iter().map(|r| diag(r).ctrl_status).collect::<Vec<_>>(),
+            )),
+        ]);
+    }
+
+    let schema = Arc::new(schema(diagnostics));
+    let batch = RecordBatch::try_new(schema.clone(), columns).map_err(io::Error::other)?;
+    let mut buf = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buf, schema, None).map_err(io::Error::other)?;
+    writer.write(&batch).map_err(io::Error::other)?;
+    writer.close().map_err(io::Error::other)?;
+    Ok(buf)
+}
+
+fn empty_diag() -> DiagColumns {
+    DiagColumns {
+        p_term: f64::NAN,
+        i_term: f64::NAN,
+        d_term: f64::NAN,
+        ff_term: f64::NAN,
+        out_presat: f64::NAN,
+        saturated: false,
+        ctrl_status: String::new(),
+    }
+}
+
+/// Load a Parquet trace written by `encode`.
+pub fn read(path: &Path) -> Result<Vec<TraceRow>> {
+    let file = File::open(path).with_context(|| format!("reading trace {}", path.display()))?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)?.build()?;
+    let mut rows = Vec::new();
+    for batch in reader {
+        let batch = batch?;
+        let column = |name: &str| {
+            batch
+                .column_by_name(name)
+                .ok_or_else(|| anyhow!("missing column {name}"))
+        };
+        let f64s = |name: &str| -> Result<Vec<f64>> {
+            let col = column(name)?;
+            let col = col
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .ok_or_else(|| anyhow!("column {name} is not f64"))?;
+            Ok((0..col.len())
+                .map(|i| {
+                    if col.is_null(i) {
+                        f64::NAN
+                    } else {
+                        col.value(i)
+                    }
+                })
+                .collect())
+        };
+        let bools = |name: &str| -> Result<Vec<bool>> {
+            let col = column(name)?;
+            let col = col
+                .as_any()
+                .downcast_ref::<BooleanArray>()
+                .ok_or_else(|| anyhow!("column {name} is not bool"))?;
+            Ok((0..col.len()).map(|i| col.value(i)).collect())
+        };
+        let reasons = {
+            let col = column("reason")?;
+            let dict = col
+                .as_any()
+                .downcast_ref::<DictionaryArray<Int32Type>>()
+                .ok_or_else(|| anyhow!("column reason is not a dictionary"))?;
+            let values = dict
+                .values()
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .ok_or_else(|| anyhow!("column reason is not a string dictionary"))?;
+            (0..dict.len())
+                .map(|i| {
+                    (!dict.is_null(i))
+                        .then(|| values.value(dict.keys().value(i) as usize).to_string())
+                        .map(RowReason::Name)
+                })
+                .collect::<Vec<_>>()
+        };
+        let [t_s, setpoint_c, true_temp_c, s1_c, s2_c, s3_c, power, coolant] = [
+            "t_s",
+            "setpoint_c",
+            "true_temp_c",
+            "s1_c",
+            "s2_c",
+            "s3_c",
+            "power",
+            "coolant",
+        ]
+        .map(f64s);
+        let (t_s, setpoint_c, true_temp_c) = (t_s?, setpoint_c?, true_temp_c?);
+        let (s1_c, s2_c, s3_c, power, coolant) = (s1_c?, s2_c?, s3_c?, power?, coolant?);
+        let scram = bools("scram")?;
+
+        let diag = if batch.column_by_name("p_term").is_some() {
+            let [p, i, d, ff, presat] =
+                ["p_term", "i_term", "d_term", "ff_term", "out_presat"].map(f64s);
+            let (p, i, d, ff, presat) = (p?, i?, d?, ff?, presat?);
+            let saturated = bools("saturated")?;
+            let status = column("ctrl_status")?;
+            let status = status
+                .as_any()
+                .downcast_ref::<StringArray>()
+                .ok_or_else(|| anyhow!("column ctrl_status is not a string"))?;
+            Some(
+                (0..batch.num_rows())
+                    .map(|k| DiagColumns {
+                        p_term: p[k],
+                        i_term: i[k],
+                        d_term: d[k],
+                        ff_term: ff[k],
+                        out_presat: presat[k],
+                        saturated: saturated[k],
+                        ctrl_status: status.value(k).to_string(),
+                    })
+                    .collect::<Vec<_>>(),
+            )
+        } else {
+            None
+        };
+
+        for k in 0..batch.num_rows() {
+            rows.push(TraceRow {
+                t_s: t_s[k],
+                setpoint_c: setpoint_c[k],
+                true_temp_c: true_temp_c[k],
+                s1_c: s1_c[k],
+                s2_c: s2_c[k],
+                s3_c: s3_c[k],
+                power: power[k],
+                coolant: coolant[k],
+                scram: scram[k],
+                reason: reasons[k].clone(),
+                diag: diag.as_ref().map(|d| d[k].clone()),
+            });
+        }
+    }
+    Ok(rows)
+}
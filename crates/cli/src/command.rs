@@ -0,0 +1,117 @@
+//! `run --command-stream`: newline-delimited JSON commands on stdin, applied during the run
+//! through the same scenario events and fault windows a scenario file uses.
+//!
+//! Each line is `{"at": 42.0, "cmd": ...}` with `cmd` one of `{"set_setpoint": 380}`,
+//! `{"set_coolant": 0.1}`, `"scram"` or `{"fault": "s2:bias:20"}` (the `--fault` syntax without
+//! `@`). Without `--realtime` the whole script is read before the run and each command applies at
+//! the first step at or after `at` (0 if absent), so the run stays reproducible and the commands
+//! are part of the scenario recorded in the trace header. With `--realtime` commands are read as
+//! they arrive and apply at `at` or, if that has passed or is absent, at the next step. A line
+//! that cannot be understood is logged as a warning and skipped.
+
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use serde::Deserialize;
+use sim::{EventAction, ScenarioSpec, TimedEvent};
+
+use crate::fault::FaultArg;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExternalCommand {
+    Event(EventAction),
+    Fault(FaultArg),
+}
+
+/// One parsed line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimedCommand {
+    /// Simulated time (s) to apply the command at; `None`: as soon as possible
+    pub at_s: Option<f64>,
+    pub command: ExternalCommand,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Line {
+    #[serde(default)]
+    at: Option<f64>,
+    cmd: serde_json::Value,
+}
+
+impl TimedCommand {
+    /// Parse one input line; `Ok(None)` for a blank line.
+    pub fn parse(line: &str) -> Result<Option<Self>, String> {
+        if line.trim().is_empty() {
+            return Ok(None);
+        }
+        let Line { at, cmd } =
+            serde_json::from_str(line).map_err(|e| format!("not a command line: {e}"))?;
+        if let Some(at) = at.filter(|t| !(t.is_finite() && *t >= 0.0)) {
+            return Err(format!("\"at\" must be a time >= 0 s, got {at}"));
+        }
+        let command = match cmd.get("fault").and_then(|f| f.as_str()) {
+            Some(flag) if flag.contains('@') => {
+                return Err("a fault command takes its time from \"at\", not @".into())
+            }
+            Some(flag) => ExternalCommand::Fault(flag.parse()?),
+            None => match serde_json::from_value::<EventAction>(cmd.clone()) {
+                Ok(EventAction::SetCoolant(c)) if !(0.0..=1.0).contains(&c) => {
+                    return Err(format!("set_coolant = {c} must be within 0..=1"))
+                }
+                Ok(action) => ExternalCommand::Event(action),
+                Err(_) => return Err(format!("unknown command {cmd}")),
+            },
+        };
+        Ok(Some(Self { at_s: at, command }))
+    }
+
+    /// Add the command to `spec`, due at `at_s` or `now_s`, whichever is later.
+    pub fn apply(&self, spec: &mut ScenarioSpec, now_s: f64) {
+        let t_s = self.at_s.map_or(now_s, |at| at.max(now_s));
+        match self.command {
+            ExternalCommand::Event(action) => spec.events.push(TimedEvent { t_s, action }),
+            ExternalCommand::Fault(fault) => FaultArg {
+                from_s: t_s,
+                ..fault
+            }
+            .apply(spec),
+        }
+    }
+}
+
+/// Every command of `input`, skipping (and logging) lines that cannot be parsed.
+pub fn read_script(input: impl BufRead) -> Vec<TimedCommand> {
+    input
+        .lines()
+        .map_while(Result::ok)
+        .enumerate()
+        .filter_map(|(n, line)| parse_logged(n + 1, &line))
+        .collect()
+}
+
+/// Commands of `input` as they arrive, parsed on a background thread.
+pub fn spawn_reader(input: impl BufRead + Send + 'static) -> Receiver<TimedCommand> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for (n, line) in input.lines().map_while(Result::ok).enumerate() {
+            if let Some(command) = parse_logged(n + 1, &line) {
+                if tx.send(command).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+    rx
+}
+
+fn parse_logged(line_number: usize, line: &str) -> Option<TimedCommand> {
+    match TimedCommand::parse(line) {
+        Ok(command) => command,
+        Err(e) => {
+            tracing::warn!(line = line_number, "ignoring command: {e}");
+            None
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! Simulation description loaded with `--config <file.toml>`.
+//!
+//! The `[plant]`, `[pid]` and `[safety]` sections map directly onto the library config structs,
+//! `[sensors.s1]` .. `[sensors.s3]` onto the sensor settings and `[run]` onto the run flags. Every
+//! key is optional and falls back to the built-in default; unknown keys are an error naming the
+//! section they appeared in.
+//!
+//! `[scenarios.<name>.<section>]` tables hold overrides for runs of one built-in scenario, e.g.
+//! `[scenarios.overheat.safety]` with `trip_temp_c = 390.0`; their keys replace the ones above.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use controller::PidConfig;
+use safety::SafetyConfig;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sim::{PlantParams, ScenarioSpec, Sensor};
+
+use crate::exit::InvalidInput;
+use crate::Scenario;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimConfig {
+    pub run: RunConfig,
+    pub plant: PlantParams,
+    pub sensors: SensorsConfig,
+    pub pid: PidConfig,
+    pub safety: SafetyConfig,
+    /// `[scenarios.<name>]` sections by scenario name; applied by `for_scenario`, so a
+    /// configuration recorded in a trace already has them merged in
+    #[serde(skip)]
+    pub scenario_overrides: BTreeMap<String, toml::Table>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RunConfig {
+    pub scenario: Scenario,
+    /// Total simulation time in seconds
+    pub seconds: f64,
+    /// Fixed time step in milliseconds
+    pub dt_ms: u64,
+    /// RNG seed for deterministic runs
+    pub seed: u64,
+    /// Control setpoint temperature (°C)
+    pub setpoint: f64,
+}
+
+impl Default for RunConfig {
+    fn default() -> Self {
+        Self {
+            scenario: Scenario::Normal,
+            seconds: 120.0,
+            dt_ms: 50,
+            seed: 12345,
+            setpoint: 350.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SensorsConfig {
+    pub s1: SensorConfig,
+    pub s2: SensorConfig,
+    pub s3: SensorConfig,
+}
+
+/// Sensor names in flags and config sections, by index.
+pub const SENSOR_NAMES: [&str; 3] = ["s1", "s2", "s3"];
+
+/// Index of the sensor called `name` (`s1` .. `s3`).
+pub fn sensor_index(name: &str) -> Result<usize, String> {
+    SENSOR_NAMES
+        .iter()
+        .position(|s| *s == name)
+        .ok_or_else(|| format!("unknown sensor {name:?}, expected s1, s2 or s3"))
+}
+
+impl SensorsConfig {
+    pub fn get(&self, index: usize) -> &SensorConfig {
+        [&self.s1, &self.s2, &self.s3][index]
+    }
+
+    pub fn get_mut(&mut self, index: usize) -> &mut SensorConfig {
+        match index {
+            0 => &mut self.s1,
+            1 => &mut self.s2,
+            _ => &mut self.s3,
+        }
+    }
+
+    /// Noise seed of each sensor: its own `seed`, else one derived from the run seed.
+    pub fn seeds(&self, run_seed: u64) -> [u64; 3] {
+        let derived = [run_seed ^ 0xA1, run_seed ^ 0xB2, run_seed ^ 0xC3];
+        std::array::from_fn(|i| self.get(i).seed.unwrap_or(derived[i]))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SensorConfig {
+    /// Standard deviation of the Gaussian measurement noise (°C)
+    pub noise_std: f64,
+    /// Readings outside this range are reported invalid (°C)
+    pub valid_range: (f64, f64),
+    /// Seed of the noise generator; absent: derived from `run.seed`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+impl Default for SensorConfig {
+    fn default() -> Self {
+        let s = Sensor::new(0);
+        Self {
+            // Slightly lower noise than the bare sensor for clearer demos
+            noise_std: 0.15,
+            valid_range: s.valid_range,
+            seed: None,
+        }
+    }
+}
+
+impl SensorConfig {
+    pub fn apply(&self, sensor: &mut Sensor) {
+        sensor.noise_std = self.noise_std;
+        sensor.valid_range = self.valid_range;
+    }
+
+    /// Problem with `noise_std`, for the sensor called `name`.
+    pub fn noise_problem(&self, name: &str) -> Option<String> {
+        (!(self.noise_std.is_finite() && self.noise_std >= 0.0))
+            .then(|| format!("sensors.{name}.noise_std = {} must be >= 0", self.noise_std))
+    }
+}
+
+/// One `--noise` / `--sensor-seed` entry: `s2=0.5`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SensorValue<T> {
+    /// Sensor index, 0 for `s1`
+    pub sensor: usize,
+    pub value: T,
+}
+
+impl<T: FromStr> FromStr for SensorValue<T> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, value) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected SENSOR=VALUE, e.g. s2=0.5, got {s:?}"))?;
+        let sensor = sensor_index(name.trim())?;
+        let value = value
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid value {value:?} for {}", name.trim()))?;
+        Ok(SensorValue { sensor, value })
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for SensorValue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", SENSOR_NAMES[self.sensor], self.value)
+    }
+}
+
+impl SimConfig {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading config {}", path.display()))?;
+        Self::from_toml(&text)
+            .map_err(|e| InvalidInput(format!("in config {}: {e:#}", path.display())).into())
+    }
+
+    /// Parse a config file; sections and keys that are absent keep their defaults.
+    pub fn from_toml(text: &str) -> Result<Self> {
+        let mut table: toml::Table = toml::from_str(text).map_err(|e| anyhow!("{e}"))?;
+        let mut cfg = Self::default();
+        if let Some(value) = table.remove("scenarios") {
+            let toml::Value::Table(scenarios) = value else {
+                bail!("[scenarios] must contain [scenarios.<name>] tables");
+            };
+            for (name, value) in scenarios {
+                if Scenario::from_str(&name, false).is_err() {
+                    bail!("unknown scenario [scenarios.{name}], expected a built-in scenario name");
+                }
+                let toml::Value::Table(overrides) = value else {
+                    bail!(
+                        "[scenarios.{name}] must contain sections like [scenarios.{name}.safety]"
+                    );
+                };
+                if overrides
+                    .get("run")
+                    .and_then(toml::Value::as_table)
+                    .is_some_and(|run| run.contains_key("scenario"))
+                {
+                    bail!("[scenarios.{name}.run]: the scenario cannot be overridden");
+                }
+                // Catch unknown sections and keys now rather than when the scenario runs
+                Self::default().apply(overrides.clone(), &format!("scenarios.{name}."))?;
+                cfg.scenario_overrides.insert(name, overrides);
+            }
+        }
+        cfg.apply(table, "")?;
+        Ok(cfg)
+    }
+
+    /// Replace the values of the keys in `table`'s sections; `prefix` leads the section names in
+    /// errors.
+    fn apply(&mut self, table: toml::Table, prefix: &str) -> Result<()> {
+        for (name, value) in table {
+            let name = format!("{prefix}{name}");
+            match &name[prefix.len()..] {
+                "run" => self.run = merged(&name, &self.run, value)?,
+                "plant" => self.plant = merged(&name, &self.plant, value)?,
+                "pid" => self.pid = merged(&name, &self.pid, value)?,
+                "safety" => self.safety = merged(&name, &self.safety, value)?,
+                "sensors" => {
+                    let toml::Value::Table(sensors) = value else {
+                        bail!("[{name}] must contain [{name}.s1] .. [{name}.s3] tables");
+                    };
+                    for (sensor, value) in sensors {
+                        let Ok(index) = sensor_index(&sensor) else {
+                            bail!("unknown sensor [{name}.{sensor}], expected s1, s2 or s3");
+                        };
+                        let slot = self.sensors.get_mut(index);
+                        *slot = merged(&format!("{name}.{sensor}"), slot, value)?;
+                    }
+                }
+                _ => {
+                    let scenarios = if prefix.is_empty() {
+                        ", [scenarios.<name>]"
+                    } else {
+                        ""
+                    };
+                    bail!(
+                        "unknown section [{name}], expected one of [{prefix}run], \
+                         [{prefix}plant], [{prefix}sensors], [{prefix}pid], [{prefix}safety]\
+                         {scenarios}"
+                    )
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// This configuration for a run of `scenario`, with its `[scenarios.<name>]` overrides applied.
+    pub fn for_scenario(&self, scenario: &Scenario) -> Result<Self> {
+        let name = scenario
+            .to_possible_value()
+            .expect("no skipped scenarios")
+            .get_name()
+            .to_string();
+        let mut cfg = Self {
+            scenario_overrides: BTreeMap::new(),
+            ..self.clone()
+        };
+        cfg.run.scenario = *scenario;
+        if let Some(overrides) = self.scenario_overrides.get(&name) {
+            cfg.apply(overrides.clone(), &format!("scenarios.{name}."))?;
+        }
+        Ok(cfg)
+    }
+
+    /// The configuration as TOML, in the layout `from_toml` reads back.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).context("serializing config")
+    }
+
+    /// Every value problem in the configuration, each naming its `section.key`: the plant, PID and
+    /// safety checks of the library crates, run and sensor ranges, and cross-section checks (every
+    /// sensor must be able to read the trip temperature, which must lie above the setpoint).
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems: Vec<String> = Vec::new();
+        if let Err(errs) = self.plant.validate() {
+            problems.extend(errs.iter().map(ToString::to_string));
+        }
+        if let Err(errs) = self.pid.validate() {
+            problems.extend(errs.iter().map(ToString::to_string));
+        }
+        if let Err(errs) = self.safety.validate() {
+            problems.extend(errs.iter().map(ToString::to_string));
+        }
+
+        let run = &self.run;
+        if !(run.seconds.is_finite() && run.seconds > 0.0) {
+            problems.push(format!("run.seconds = {} must be > 0", run.seconds));
+        }
+        if run.dt_ms == 0 {
+            problems.push("run.dt_ms must be > 0".into());
+        }
+        if !run.setpoint.is_finite() {
+            problems.push("run.setpoint must be a finite number".into());
+        }
+
+        let trip = self.safety.trip_temp_c;
+        for (i, name) in SENSOR_NAMES.into_iter().enumerate() {
+            let sensor = self.sensors.get(i);
+            problems.extend(sensor.noise_problem(name));
+            let (lo, hi) = sensor.valid_range;
+            if !(lo.is_finite() && hi.is_finite()) || lo >= hi {
+                problems.push(format!(
+                    "sensors.{name}.valid_range: lower bound ({lo}) must be less than upper bound \
+                     ({hi})"
+                ));
+            } else if !(lo..=hi).contains(&trip) {
+                problems.push(format!(
+                    "safety.trip_temp_c = {trip} is outside sensors.{name}.valid_range \
+                     ({lo}..={hi}), so that sensor reads invalid before it can vote a trip"
+                ));
+            }
+        }
+        if run.setpoint.is_finite() && trip.is_finite() && run.setpoint >= trip {
+            problems.push(format!(
+                "run.setpoint = {} must be below safety.trip_temp_c ({trip})",
+                run.setpoint
+            ));
+        }
+        problems
+    }
+
+    /// Short fingerprint of this configuration together with the scenario it runs: 64-bit FNV-1a
+    /// of their JSON form, as 16 hex digits. Equal hashes mean the same inputs to the simulation.
+    pub fn config_hash(&self, spec: &ScenarioSpec) -> Result<String> {
+        let bytes = serde_json::to_vec(&(self, spec)).context("serializing config")?;
+        Ok(fnv1a_hex(&bytes))
+    }
+}
+
+/// 64-bit FNV-1a hash of `bytes`, as 16 hex digits.
+pub fn fnv1a_hex(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325_u64, |h, &b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{hash:016x}")
+}
+
+/// `current` with the keys of the section `value` replaced.
+fn merged<T: Serialize + DeserializeOwned>(
+    name: &str,
+    current: &T,
+    value: toml::Value,
+) -> Result<T> {
+    let toml::Value::Table(keys) = value else {
+        bail!("[{name}] must be a table");
+    };
+    let mut table = toml::Value::try_from(current).context("serializing config")?;
+    if let toml::Value::Table(table) = &mut table {
+        for (key, value) in keys {
+            table.insert(key, value);
+        }
+    }
+    table
+        .try_into()
+        .map_err(|e| anyhow!("[{name}]: {}", e.to_string().trim_end()))
+}
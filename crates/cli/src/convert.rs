@@ -0,0 +1,79 @@
+//! `convert`: rewrite a recorded trace in another format, e.g. a binary trace as JSONL for the
+//! tools that read text.
+//!
+//! The header is carried over to the formats that have one (JSONL and MessagePack); CSV and
+//! Parquet keep only the rows, with the trip reason as its name.
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::exit;
+use crate::trace::{self, read_trace_with_meta, TraceFormat, TraceMeta, TraceRow, TraceWriter};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct ConvertArgs {
+    /// Trace to read (any format `read_trace` understands, gzip-compressed or not)
+    pub input: PathBuf,
+
+    /// Trace to write
+    pub output: PathBuf,
+
+    /// Format of the output [default: from its extension, JSONL unless .csv/.msgpack]
+    #[arg(value_enum, long)]
+    pub format: Option<TraceFormat>,
+
+    /// Gzip-compress the output (implied by a .gz name)
+    #[arg(long)]
+    pub compress: bool,
+
+    /// Overwrite an existing output file
+    #[arg(long)]
+    pub force: bool,
+}
+
+/// Write `rows` (after `meta`, if the format takes it) to `out` in `format`.
+fn write_trace(
+    out: &mut dyn Write,
+    format: TraceFormat,
+    meta: Option<TraceMeta>,
+    rows: &[TraceRow],
+) -> Result<()> {
+    let diagnostics = rows.iter().any(|row| row.diag.is_some());
+    let mut writer = TraceWriter::new(out, format, diagnostics);
+    if let Some(meta) = meta {
+        writer = writer.with_meta(meta);
+    }
+    for row in rows {
+        writer.write_row(row)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+pub fn execute(args: &ConvertArgs, stdout: &mut dyn Write) -> Result<u8> {
+    let (meta, rows) = read_trace_with_meta(&args.input)?;
+    let format = args
+        .format
+        .unwrap_or_else(|| TraceFormat::from_path(&args.output));
+
+    let mut file = crate::open_output(&args.output, args.force)?;
+    let written = if args.compress || trace::is_gzip_path(&args.output) {
+        let mut gz = GzEncoder::new(file, Compression::default());
+        write_trace(&mut gz, format, meta, &rows).and_then(|()| Ok(gz.finish()?.flush()?))
+    } else {
+        write_trace(&mut file, format, meta, &rows)
+    };
+    written.with_context(|| format!("writing {}", args.output.display()))?;
+    writeln!(
+        stdout,
+        "{} rows -> {} ({})",
+        rows.len(),
+        args.output.display(),
+        format.extension()
+    )?;
+    Ok(exit::OK)
+}
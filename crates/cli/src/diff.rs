@@ -0,0 +1,256 @@
+//! `diff`: quantitative comparison of two recorded traces, e.g. a run with new controller gains
+//! against a baseline.
+//!
+//! Rows are aligned on the time grid of the first trace; the continuous channels of the second
+//! are linearly interpolated onto it, so traces recorded with different `--dt-ms` compare
+//! cleanly. Only the time span both traces cover is compared.
+
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::exit::{self, InvalidInput};
+use crate::trace::{read_trace, TraceRow};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum DiffFormat {
+    /// Human-readable block
+    #[default]
+    Text,
+    /// A single JSON object
+    Json,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct DiffArgs {
+    /// Baseline trace (.jsonl, .csv or .msgpack)
+    pub a: PathBuf,
+
+    /// Trace compared against the baseline
+    pub b: PathBuf,
+
+    /// Largest true-temperature difference (°C) still accepted
+    #[arg(long, default_value_t = 0.0)]
+    pub tol_temp: f64,
+
+    /// Largest trip-time difference (s) still accepted; the trip reasons must match regardless
+    #[arg(long, default_value_t = 0.0)]
+    pub tol_time: f64,
+
+    /// Temperature difference (°C) that counts as the traces diverging
+    #[arg(long, default_value_t = 0.01)]
+    pub epsilon: f64,
+
+    #[arg(value_enum, long, default_value_t = DiffFormat::Text)]
+    pub format: DiffFormat,
+}
+
+/// Time and reason of the first row with the SCRAM latched.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Trip {
+    pub t_s: f64,
+    pub reason: Option<String>,
+}
+
+impl Trip {
+    fn of(rows: &[TraceRow]) -> Option<Self> {
+        rows.iter().find(|r| r.scram).map(|r| Trip {
+            t_s: r.t_s,
+            reason: r.reason_name(),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TraceDiff {
+    /// Rows of the first trace inside the common time span
+    pub rows_compared: usize,
+    /// End of the common time span
+    pub compared_until_s: f64,
+    pub max_temp_diff_c: f64,
+    pub rms_temp_diff_c: f64,
+    pub max_power_diff: f64,
+    /// First time the true temperatures differ by more than `epsilon`
+    pub first_divergence_s: Option<f64>,
+    pub epsilon_c: f64,
+    pub trip_a: Option<Trip>,
+    pub trip_b: Option<Trip>,
+    /// `b` minus `a`, when both traces trip
+    pub trip_time_diff_s: Option<f64>,
+    pub max_temp_a_c: f64,
+    pub max_temp_b_c: f64,
+}
+
+impl TraceDiff {
+    /// Whether the temperatures agree within `tol_temp` and both traces trip for the same reason
+    /// within `tol_time` of each other (or neither trips).
+    pub fn within(&self, tol_temp: f64, tol_time: f64) -> bool {
+        let trips_agree = match (&self.trip_a, &self.trip_b) {
+            (None, None) => true,
+            (Some(a), Some(b)) => a.reason == b.reason && (b.t_s - a.t_s).abs() <= tol_time,
+            _ => false,
+        };
+        self.max_temp_diff_c <= tol_temp && trips_agree
+    }
+}
+
+/// Value of `channel` at `t_s`, linearly interpolated between the rows around it; `None` outside
+/// the rows' time span.
+fn interpolate(rows: &[TraceRow], t_s: f64, channel: fn(&TraceRow) -> f64) -> Option<f64> {
+    let i = rows.partition_point(|r| r.t_s < t_s);
+    let hi = rows.get(i)?;
+    if hi.t_s == t_s {
+        return Some(channel(hi));
+    }
+    let lo = rows.get(i.checked_sub(1)?)?;
+    let w = (t_s - lo.t_s) / (hi.t_s - lo.t_s);
+    Some(channel(lo) + (channel(hi) - channel(lo)) * w)
+}
+
+/// Compare `b` against `a` on `a`'s time grid.
+pub fn diff(a: &[TraceRow], b: &[TraceRow], epsilon: f64) -> TraceDiff {
+    let max_temp = |rows: &[TraceRow]| {
+        rows.iter()
+            .map(|r| r.true_temp_c)
+            .fold(f64::NEG_INFINITY, f64::max)
+    };
+    let mut d = TraceDiff {
+        rows_compared: 0,
+        compared_until_s: 0.0,
+        max_temp_diff_c: 0.0,
+        rms_temp_diff_c: 0.0,
+        max_power_diff: 0.0,
+        first_divergence_s: None,
+        epsilon_c: epsilon,
+        trip_a: Trip::of(a),
+        trip_b: Trip::of(b),
+        trip_time_diff_s: None,
+        max_temp_a_c: max_temp(a),
+        max_temp_b_c: max_temp(b),
+    };
+    if let (Some(ta), Some(tb)) = (&d.trip_a, &d.trip_b) {
+        d.trip_time_diff_s = Some(tb.t_s - ta.t_s);
+    }
+
+    let mut sum_sq = 0.0;
+    for row in a {
+        let (Some(temp_b), Some(power_b)) = (
+            interpolate(b, row.t_s, |r| r.true_temp_c),
+            interpolate(b, row.t_s, |r| r.power),
+        ) else {
+            continue;
+        };
+        let dt = (temp_b - row.true_temp_c).abs();
+        d.rows_compared += 1;
+        d.compared_until_s = row.t_s;
+        d.max_temp_diff_c = d.max_temp_diff_c.max(dt);
+        d.max_power_diff = d.max_power_diff.max((power_b - row.power).abs());
+        sum_sq += dt * dt;
+        if dt > epsilon && d.first_divergence_s.is_none() {
+            d.first_divergence_s = Some(row.t_s);
+        }
+    }
+    if d.rows_compared > 0 {
+        d.rms_temp_diff_c = (sum_sq / d.rows_compared as f64).sqrt();
+    }
+    d
+}
+
+impl fmt::Display for Trip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {:.2} s",
+            self.reason.as_deref().unwrap_or("unknown reason"),
+            self.t_s
+        )
+    }
+}
+
+impl fmt::Display for TraceDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let trip = |t: &Option<Trip>| t.as_ref().map_or("none".to_string(), Trip::to_string);
+        writeln!(
+            f,
+            "compared          {} rows up to {:.2} s",
+            self.rows_compared, self.compared_until_s
+        )?;
+        writeln!(
+            f,
+            "temperature diff  max {:.4} °C, rms {:.4} °C",
+            self.max_temp_diff_c, self.rms_temp_diff_c
+        )?;
+        writeln!(f, "power diff        max {:.4}", self.max_power_diff)?;
+        writeln!(
+            f,
+            "max temperature   {:.2} °C vs {:.2} °C ({:+.2} °C)",
+            self.max_temp_a_c,
+            self.max_temp_b_c,
+            self.max_temp_b_c - self.max_temp_a_c
+        )?;
+        write!(
+            f,
+            "trip              {} vs {}",
+            trip(&self.trip_a),
+            trip(&self.trip_b)
+        )?;
+        match self.trip_time_diff_s {
+            Some(dt) => writeln!(f, " ({dt:+.2} s)")?,
+            None => writeln!(f)?,
+        }
+        match self.first_divergence_s {
+            Some(t_s) => writeln!(
+                f,
+                "diverges          at {t_s:.2} s (|diff| > {} °C)",
+                self.epsilon_c
+            ),
+            None => writeln!(
+                f,
+                "diverges          never (|diff| <= {} °C)",
+                self.epsilon_c
+            ),
+        }
+    }
+}
+
+pub fn execute(args: &DiffArgs, stdout: &mut dyn Write) -> Result<u8> {
+    for (flag, value) in [
+        ("--tol-temp", args.tol_temp),
+        ("--tol-time", args.tol_time),
+        ("--epsilon", args.epsilon),
+    ] {
+        if !(value.is_finite() && value >= 0.0) {
+            bail!(InvalidInput(format!("{flag} must be a number >= 0")));
+        }
+    }
+    let a = read_trace(&args.a)?;
+    let b = read_trace(&args.b)?;
+    for (path, rows) in [(&args.a, &a), (&args.b, &b)] {
+        if rows.is_empty() {
+            bail!(InvalidInput(format!(
+                "{} contains no trace rows",
+                path.display()
+            )));
+        }
+    }
+    let d = diff(&a, &b, args.epsilon);
+    let ok = d.within(args.tol_temp, args.tol_time);
+    match args.format {
+        DiffFormat::Text => {
+            write!(stdout, "{d}")?;
+            writeln!(
+                stdout,
+                "result            {} (--tol-temp {} °C, --tol-time {} s)",
+                if ok { "within tolerance" } else { "DIFFERENT" },
+                args.tol_temp,
+                args.tol_time
+            )?;
+        }
+        DiffFormat::Json => writeln!(stdout, "{}", serde_json::to_string(&d)?)?,
+    }
+    Ok(if ok { exit::OK } else { exit::FAILED_CHECK })
+}
@@ -0,0 +1,72 @@
+//! Process exit codes and the mapping from errors to them.
+
+use std::fmt;
+use std::io;
+
+/// Completed: no trip, or a trip without `--fail-on-trip`.
+pub const OK: u8 = 0;
+/// Unexpected internal error.
+pub const INTERNAL: u8 = 1;
+/// The run tripped with `--fail-on-trip`, `replay` found differing trip decisions, `diff` found
+/// traces outside its tolerances, `verify` could not reproduce a trace, `search` found a
+/// non-monotonic outcome, or `golden check` found a scenario that no longer matches its recording.
+pub const FAILED_CHECK: u8 = 2;
+/// Invalid arguments, configuration or input file.
+pub const INVALID_INPUT: u8 = 3;
+/// Reading or writing a file (or stdout) failed.
+pub const IO: u8 = 4;
+/// A `--realtime` run was stopped with Ctrl-C (128 + SIGINT, as shells report it).
+pub const INTERRUPTED: u8 = 130;
+
+/// Shown at the end of `--help`.
+pub const HELP: &str = "\
+Exit codes:
+    0  completed (no trip, or a trip without --fail-on-trip)
+    1  unexpected internal error
+    2  tripped with --fail-on-trip, replay found differing trip decisions, diff found
+       traces outside its tolerances, verify could not reproduce a trace, search
+       found a non-monotonic outcome, or golden check found a changed scenario
+    3  invalid arguments, configuration or input file
+    4  I/O error
+  130  --realtime run stopped with Ctrl-C";
+
+/// The more severe of two exit codes, for a command made of several runs: an internal error over
+/// an I/O error over invalid input over a failed check over success.
+pub fn worst(a: u8, b: u8) -> u8 {
+    let rank = |code| {
+        [OK, FAILED_CHECK, INVALID_INPUT, IO, INTERNAL]
+            .iter()
+            .position(|c| *c == code)
+            .unwrap_or(usize::MAX)
+    };
+    if rank(b) > rank(a) {
+        b
+    } else {
+        a
+    }
+}
+
+/// Error for bad user input (arguments, config files, traces); exits with `INVALID_INPUT`.
+#[derive(Debug)]
+pub struct InvalidInput(pub String);
+
+impl fmt::Display for InvalidInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for InvalidInput {}
+
+/// Exit code for an error, from the first classifiable cause in its chain.
+pub fn code_for(err: &anyhow::Error) -> u8 {
+    for cause in err.chain() {
+        if cause.is::<InvalidInput>() {
+            return INVALID_INPUT;
+        }
+        if cause.is::<io::Error>() {
+            return IO;
+        }
+    }
+    INTERNAL
+}
@@ -0,0 +1,119 @@
+//! `--fault` flags: sensor faults injected on top of the scenario without writing a scenario file.
+
+use std::fmt;
+use std::str::FromStr;
+
+use sim::{FaultWindow, ScenarioSpec, SensorFault};
+
+use crate::config::sensor_index;
+
+/// One `--fault <sensor>:<kind>[:<value>][@<t>s]`, e.g. `s2:bias:+20`, `s1:dropout:50` or
+/// `s3:drift:0.5@30s`. Without `@` the fault is active from the start of the run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FaultArg {
+    /// Sensor index, 0 for `s1`
+    pub sensor: usize,
+    pub fault: SensorFault,
+    pub from_s: f64,
+}
+
+impl FaultArg {
+    /// Add the fault to `spec` as a window that overrides the scenario's own faults from
+    /// `from_s` to the end of the run.
+    pub fn apply(&self, spec: &mut ScenarioSpec) {
+        let schedule = match self.sensor {
+            0 => &mut spec.sensors.s1,
+            1 => &mut spec.sensors.s2,
+            _ => &mut spec.sensors.s3,
+        };
+        schedule.windows.push(FaultWindow {
+            from_s: self.from_s,
+            until_s: None,
+            fault: self.fault,
+        });
+    }
+}
+
+impl FromStr for FaultArg {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (body, from_s) = match s.split_once('@') {
+            Some((body, t)) => {
+                let t = t.trim();
+                let from_s = t
+                    .strip_suffix('s')
+                    .unwrap_or(t)
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|t| t.is_finite() && *t >= 0.0)
+                    .ok_or_else(|| format!("invalid start time {t:?}, expected e.g. @30s"))?;
+                (body, from_s)
+            }
+            None => (s, 0.0),
+        };
+
+        let mut parts = body.split(':').map(str::trim);
+        let sensor = sensor_index(parts.next().unwrap_or_default())?;
+        let kind = parts.next().ok_or("missing fault kind after the sensor")?;
+        let value = parts.next();
+        if parts.next().is_some() {
+            return Err(format!(
+                "{s:?} has too many fields, expected <sensor>:<kind>[:<value>][@<t>s]"
+            ));
+        }
+
+        let number = || -> Result<f64, String> {
+            let v = value.ok_or_else(|| format!("{kind} needs a value, e.g. {kind}:20"))?;
+            v.parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| format!("invalid {kind} value {v:?}, expected a number"))
+        };
+        let fault = match kind {
+            "none" => SensorFault::None,
+            "bias" => SensorFault::Bias { value: number()? },
+            "stuck" => SensorFault::Stuck { value: number()? },
+            "drift" => SensorFault::Drift { per_s: number()? },
+            "dropout" => {
+                let v = value.ok_or("dropout needs a period, e.g. dropout:50")?;
+                let n =
+                    v.parse::<u64>().ok().filter(|n| *n > 0).ok_or_else(|| {
+                        format!("invalid dropout period {v:?}, expected a count > 0")
+                    })?;
+                SensorFault::DropoutEvery { n }
+            }
+            other => {
+                return Err(format!(
+                    "unknown fault kind {other:?}, expected bias, stuck, drift, dropout or none"
+                ))
+            }
+        };
+        if matches!(fault, SensorFault::None) && value.is_some() {
+            return Err("none takes no value".to_string());
+        }
+        Ok(Self {
+            sensor,
+            fault,
+            from_s,
+        })
+    }
+}
+
+/// The canonical form of the flag, which parses back to the same fault.
+impl fmt::Display for FaultArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "s{}:", self.sensor + 1)?;
+        match self.fault {
+            SensorFault::None => write!(f, "none")?,
+            SensorFault::Bias { value } => write!(f, "bias:{value}")?,
+            SensorFault::Stuck { value } => write!(f, "stuck:{value}")?,
+            SensorFault::Drift { per_s } => write!(f, "drift:{per_s}")?,
+            SensorFault::DropoutEvery { n } => write!(f, "dropout:{n}")?,
+        }
+        if self.from_s > 0.0 {
+            write!(f, "@{}s", self.from_s)?;
+        }
+        Ok(())
+    }
+}
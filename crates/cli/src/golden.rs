@@ -0,0 +1,308 @@
+//! `golden`: regression protection against unintended behavior changes.
+//!
+//! `golden record --dir DIR` runs every built-in scenario at pinned settings (`PINNED_FLAGS`) and
+//! stores each trace in canonical form (CSV with every number rounded to `DECIMALS` places) as
+//! `<scenario>.csv`, with their FNV-1a hashes in `hashes.txt`. `golden check --dir DIR` re-runs
+//! them and compares value by value: formatting differences and rounding in the last printed digit
+//! pass, any change larger than `TOLERANCE` fails with the scenario, time and column where the new
+//! run first departs from the recording.
+//!
+//! Sensor noise is pinned to zero, so the recordings depend only on the simulation arithmetic and
+//! not on the random number stream, which may change between `rand` releases.
+
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::config::fnv1a_hex;
+use crate::exit::{self, InvalidInput};
+use crate::trace::{csv_header, parse_trace, split_csv, TraceFormat};
+use crate::{RunArgs, Scenario};
+
+/// `run` flags of every golden run, besides `--scenario`.
+pub const PINNED_FLAGS: [&str; 12] = [
+    "--seconds",
+    "60",
+    "--seed",
+    "12345",
+    "--noise",
+    "s1=0,s2=0,s3=0",
+    "--diagnostics",
+    "--continue-after-scram",
+    "--format",
+    "csv",
+    "--summary",
+    "none",
+];
+
+/// Decimal places of the numbers in a canonical trace.
+pub const DECIMALS: usize = 6;
+
+/// Largest difference between a recorded and a new value that still counts as equal: a few units
+/// in the last recorded place.
+pub const TOLERANCE: f64 = 1e-5;
+
+/// Name of the file listing the hash of every recorded trace.
+pub const HASHES_FILE: &str = "hashes.txt";
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct GoldenArgs {
+    #[command(subcommand)]
+    pub command: GoldenCommand,
+}
+
+#[derive(Subcommand, Clone, Debug)]
+pub enum GoldenCommand {
+    /// Run every built-in scenario and store its canonical trace
+    Record(GoldenDirArgs),
+    /// Re-run every built-in scenario and compare it with the recording
+    Check(GoldenDirArgs),
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct GoldenDirArgs {
+    /// Directory holding the recorded traces and hashes.txt
+    #[arg(long, value_name = "DIR")]
+    pub dir: PathBuf,
+}
+
+/// The pinned `run` arguments for `scenario`.
+pub fn pinned_args(scenario: &Scenario) -> RunArgs {
+    let name = scenario
+        .to_possible_value()
+        .expect("no skipped scenarios")
+        .get_name()
+        .to_string();
+    let argv = ["golden", "--quiet", "--scenario", &name]
+        .into_iter()
+        .chain(PINNED_FLAGS);
+    RunArgs::try_parse_from(argv).expect("the pinned flags parse")
+}
+
+/// Canonical trace of one run of `args` (which must write CSV).
+pub fn canonical_trace(args: &RunArgs) -> Result<String> {
+    let mut out = Vec::new();
+    crate::run(args, &mut out)?;
+    let rows = parse_trace(&String::from_utf8(out)?, TraceFormat::Csv)?;
+    let mut text = csv_header(args.diagnostics).join(",");
+    text.push('\n');
+    for row in &rows {
+        let cells: Vec<String> = row
+            .cells()
+            .into_iter()
+            .map(|(_, cell)| canonical_cell(cell))
+            .collect();
+        text.push_str(&cells.join(","));
+        text.push('\n');
+    }
+    Ok(text)
+}
+
+/// Numbers rounded to `DECIMALS` places (without a sign on zero); other cells unchanged.
+fn canonical_cell(cell: String) -> String {
+    match cell.parse::<f64>() {
+        Ok(v) => {
+            let rounded = format!("{v:.DECIMALS$}");
+            match rounded.strip_prefix('-') {
+                Some(abs) if abs.bytes().all(|b| b == b'0' || b == b'.') => abs.to_string(),
+                _ => rounded,
+            }
+        }
+        Err(_) => cell,
+    }
+}
+
+/// Where a new canonical trace first departs from the recorded one.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Divergence {
+    Columns {
+        recorded: String,
+        now: String,
+    },
+    RowCount {
+        recorded: usize,
+        now: usize,
+    },
+    Value {
+        /// Data row, from 1
+        row: usize,
+        t_s: String,
+        column: String,
+        recorded: String,
+        now: String,
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::Columns { recorded, now } => {
+                write!(f, "columns differ: recorded [{recorded}], now [{now}]")
+            }
+            Divergence::RowCount { recorded, now } => write!(
+                f,
+                "row count differs: recorded {recorded}, now {now} (values agree up to there)"
+            ),
+            Divergence::Value {
+                row,
+                t_s,
+                column,
+                recorded,
+                now,
+            } => write!(
+                f,
+                "first divergence at t = {t_s} s (row {row}), column {column}: recorded \
+                 {recorded}, now {now}"
+            ),
+        }
+    }
+}
+
+/// The first difference between two canonical traces beyond `TOLERANCE`, if any.
+pub fn compare(recorded: &str, now: &str) -> Option<Divergence> {
+    let mut recorded_lines = recorded.lines();
+    let mut now_lines = now.lines();
+    let recorded_header = recorded_lines.next().unwrap_or_default();
+    let now_header = now_lines.next().unwrap_or_default();
+    if recorded_header != now_header {
+        return Some(Divergence::Columns {
+            recorded: recorded_header.to_string(),
+            now: now_header.to_string(),
+        });
+    }
+    let columns = split_csv(recorded_header);
+    let recorded_rows: Vec<Vec<String>> = recorded_lines.map(split_csv).collect();
+    let now_rows: Vec<Vec<String>> = now_lines.map(split_csv).collect();
+    for (i, (old, new)) in recorded_rows.iter().zip(&now_rows).enumerate() {
+        let differs = |(a, b): &(&String, &String)| match (a.parse::<f64>(), b.parse::<f64>()) {
+            (Ok(a), Ok(b)) => (a - b).abs() > TOLERANCE,
+            _ => a != b,
+        };
+        if let Some(j) = old.iter().zip(new).position(|pair| differs(&pair)) {
+            return Some(Divergence::Value {
+                row: i + 1,
+                t_s: new.first().cloned().unwrap_or_default(),
+                column: columns.get(j).cloned().unwrap_or_default(),
+                recorded: old[j].clone(),
+                now: new[j].clone(),
+            });
+        }
+    }
+    (recorded_rows.len() != now_rows.len()).then_some(Divergence::RowCount {
+        recorded: recorded_rows.len(),
+        now: now_rows.len(),
+    })
+}
+
+/// Record the canonical trace of every built-in scenario in `dir`, replacing earlier recordings.
+pub fn record(dir: &Path) -> Result<Vec<String>> {
+    fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    let mut hashes = String::new();
+    let mut names = Vec::new();
+    for scenario in Scenario::value_variants() {
+        let args = pinned_args(scenario);
+        let name = scenario.spec(0.0).name;
+        let text = canonical_trace(&args)?;
+        let file = format!("{name}.csv");
+        fs::write(dir.join(&file), &text)
+            .with_context(|| format!("writing {}", dir.join(&file).display()))?;
+        hashes.push_str(&format!("{}  {file}\n", fnv1a_hex(text.as_bytes())));
+        names.push(name);
+    }
+    fs::write(dir.join(HASHES_FILE), hashes)
+        .with_context(|| format!("writing {}", dir.join(HASHES_FILE).display()))?;
+    Ok(names)
+}
+
+/// Outcome of checking one scenario.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioCheck {
+    pub scenario: String,
+    pub rows: usize,
+    pub divergence: Option<Divergence>,
+}
+
+/// Re-run every built-in scenario and compare it with its recording in `dir`.
+pub fn check(dir: &Path) -> Result<Vec<ScenarioCheck>> {
+    let hashes_path = dir.join(HASHES_FILE);
+    let hashes = fs::read_to_string(&hashes_path).with_context(|| {
+        format!(
+            "reading {} (record the traces with `golden record --dir {}`)",
+            hashes_path.display(),
+            dir.display()
+        )
+    })?;
+    let mut checks = Vec::new();
+    for scenario in Scenario::value_variants() {
+        let args = pinned_args(scenario);
+        let name = scenario.spec(0.0).name;
+        let file = format!("{name}.csv");
+        let Some(hash) = hashes
+            .lines()
+            .find_map(|line| line.strip_suffix(&format!("  {file}")))
+        else {
+            bail!(InvalidInput(format!(
+                "{} has no entry for {file}; re-record the golden traces",
+                hashes_path.display()
+            )));
+        };
+        let path = dir.join(&file);
+        let recorded =
+            fs::read_to_string(&path).with_context(|| format!("reading {}", path.display()))?;
+        if fnv1a_hex(recorded.as_bytes()) != hash {
+            bail!(InvalidInput(format!(
+                "{} does not match its hash in {HASHES_FILE}; it was edited by hand or only \
+                 partly recorded",
+                path.display()
+            )));
+        }
+        let now = canonical_trace(&args)?;
+        checks.push(ScenarioCheck {
+            scenario: name,
+            rows: now.lines().count().saturating_sub(1),
+            divergence: compare(&recorded, &now),
+        });
+    }
+    Ok(checks)
+}
+
+pub fn execute(args: &GoldenArgs, stdout: &mut dyn Write) -> Result<u8> {
+    match &args.command {
+        GoldenCommand::Record(args) => {
+            let names = record(&args.dir)?;
+            writeln!(
+                stdout,
+                "recorded {} golden traces in {}: {}",
+                names.len(),
+                args.dir.display(),
+                names.join(", ")
+            )?;
+            Ok(exit::OK)
+        }
+        GoldenCommand::Check(args) => {
+            let checks = check(&args.dir)?;
+            for c in &checks {
+                match &c.divergence {
+                    None => writeln!(stdout, "{:<16} ok ({} rows)", c.scenario, c.rows)?,
+                    Some(d) => writeln!(stdout, "{:<16} DIFFERS: {d}", c.scenario)?,
+                }
+            }
+            let failed = checks.iter().filter(|c| c.divergence.is_some()).count();
+            if failed == 0 {
+                return Ok(exit::OK);
+            }
+            writeln!(
+                stdout,
+                "{failed} of {} scenarios no longer match the golden traces; if the change is \
+                 intended, re-record them with `golden record --dir {}`",
+                checks.len(),
+                args.dir.display()
+            )?;
+            Ok(exit::FAILED_CHECK)
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! `inspect`: summary statistics of a recorded trace, or the provenance block of its header.
+
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::{bail, Result};
+
+use crate::exit::InvalidInput;
+use crate::trace::{read_trace, read_trace_with_meta, TraceHeader, TraceMeta, TraceRow};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct InspectArgs {
+    /// Recorded trace (.jsonl, .csv or .msgpack)
+    pub trace: PathBuf,
+
+    /// Print the command line, crate versions, build commit and host that wrote the trace instead
+    #[arg(long)]
+    pub provenance: bool,
+}
+
+/// Minimum, mean and maximum of the finite values of one column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ColumnStats {
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+
+impl ColumnStats {
+    fn of(values: impl Iterator<Item = f64>) -> Self {
+        let (mut min, mut max, mut sum, mut n) = (f64::INFINITY, f64::NEG_INFINITY, 0.0, 0usize);
+        for v in values.filter(|v| v.is_finite()) {
+            min = min.min(v);
+            max = max.max(v);
+            sum += v;
+            n += 1;
+        }
+        let mean = if n > 0 { sum / n as f64 } else { f64::NAN };
+        Self { min, mean, max }
+    }
+}
+
+impl fmt::Display for ColumnStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min {:.3}  mean {:.3}  max {:.3}",
+            self.min, self.mean, self.max
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceStats {
+    pub rows: usize,
+    pub start_t_s: f64,
+    pub end_t_s: f64,
+    pub true_temp_c: ColumnStats,
+    pub power: ColumnStats,
+    /// Readings that are not finite (dropouts), per sensor
+    pub sensor_dropouts: [usize; 3],
+    /// Time and reason of the first SCRAM row
+    pub trip: Option<(f64, Option<String>)>,
+}
+
+impl TraceStats {
+    /// `None` for an empty trace.
+    pub fn of(rows: &[TraceRow]) -> Option<Self> {
+        let (first, last) = (rows.first()?, rows.last()?);
+        let dropouts = |f: fn(&TraceRow) -> f64| rows.iter().filter(|r| !f(r).is_finite()).count();
+        Some(Self {
+            rows: rows.len(),
+            start_t_s: first.t_s,
+            end_t_s: last.t_s,
+            true_temp_c: ColumnStats::of(rows.iter().map(|r| r.true_temp_c)),
+            power: ColumnStats::of(rows.iter().map(|r| r.power)),
+            sensor_dropouts: [
+                dropouts(|r| r.s1_c),
+                dropouts(|r| r.s2_c),
+                dropouts(|r| r.s3_c),
+            ],
+            trip: rows
+                .iter()
+                .find(|r| r.scram)
+                .map(|r| (r.t_s, r.reason.as_ref().map(ToString::to_string))),
+        })
+    }
+}
+
+impl fmt::Display for TraceStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "rows         {}", self.rows)?;
+        writeln!(
+            f,
+            "time         {:.2} .. {:.2} s",
+            self.start_t_s, self.end_t_s
+        )?;
+        writeln!(f, "true_temp_c  {}", self.true_temp_c)?;
+        writeln!(f, "power        {}", self.power)?;
+        let [d1, d2, d3] = self.sensor_dropouts;
+        writeln!(f, "dropouts     s1 {d1}  s2 {d2}  s3 {d3}")?;
+        match &self.trip {
+            Some((t_s, Some(reason))) => write!(f, "scram        at {t_s:.2} s ({reason})"),
+            Some((t_s, None)) => write!(f, "scram        at {t_s:.2} s"),
+            None => write!(f, "scram        no"),
+        }
+    }
+}
+
+pub fn execute(args: &InspectArgs, stdout: &mut dyn Write) -> Result<()> {
+    if args.provenance {
+        return provenance(args, stdout);
+    }
+    let rows = read_trace(&args.trace)?;
+    let Some(stats) = TraceStats::of(&rows) else {
+        bail!(InvalidInput(format!(
+            "{} contains no trace rows",
+            args.trace.display()
+        )));
+    };
+    writeln!(stdout, "{stats}")?;
+    Ok(())
+}
+
+/// `inspect --provenance`: the header's provenance with the configuration hash.
+fn provenance(args: &InspectArgs, stdout: &mut dyn Write) -> Result<()> {
+    let (meta, _) = read_trace_with_meta(&args.trace)?;
+    let Some(TraceMeta {
+        provenance: Some(provenance),
+        header: TraceHeader {
+            config_hash,
+            schema,
+            ..
+        },
+        ..
+    }) = meta
+    else {
+        bail!(InvalidInput(format!(
+            "{} records no provenance (only JSONL and binary traces written since it was added do)",
+            args.trace.display()
+        )));
+    };
+    writeln!(stdout, "{provenance}")?;
+    writeln!(stdout, "config hash  {config_hash}")?;
+    writeln!(stdout, "trace schema {schema}")?;
+    Ok(())
+}
@@ -0,0 +1,1080 @@
+//! Command-line front end of the simulator: argument definitions, the subcommands and the
+//! simulation loop, kept in a library so tests (and other front ends) can drive a run without
+//! spawning the binary.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use controller::{Lqr, LqrConfig, Mpc, MpcConfig, Pid, PidConfig, SetpointProfile};
+use engine::{Simulation, StepEvent};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sim::{ScenarioSpec, Sensor};
+
+pub mod analyze;
+pub mod batch;
+pub mod bench;
+pub mod checkpoint;
+#[cfg(feature = "parquet")]
+pub mod columnar;
+pub mod command;
+pub mod config;
+pub mod convert;
+pub mod diff;
+pub mod exit;
+pub mod fault;
+pub mod golden;
+pub mod inspect;
+pub mod list;
+pub mod logging;
+pub mod msgpack;
+pub mod multi;
+pub mod param;
+pub mod progress;
+pub mod provenance;
+pub mod realtime;
+pub mod replay;
+pub mod scenario;
+pub mod schema;
+pub mod search;
+pub mod stats;
+pub mod stream;
+pub mod summary;
+pub mod sweep;
+pub mod trace;
+pub mod until;
+pub mod validate;
+pub mod verify;
+
+pub use summary::RunSummary;
+
+use crate::trace::{
+    Decimator, DiagColumns, RowReason, TraceFormat, TraceMeta, TraceRow, TraceWriter,
+};
+use checkpoint::{Checkpoint, ControllerState};
+use config::SimConfig;
+use exit::InvalidInput;
+use progress::Progress;
+use provenance::Provenance;
+use summary::{Outcome, SummaryFormat};
+use until::{Until, UntilCondition};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scenario {
+    Normal,
+    Overheat,
+    LossOfCooling,
+    SensorDisagree,
+}
+
+/// Controller output preloaded when a SCRAM latches, so a later restart is bumpless.
+#[derive(Clone, Debug, PartialEq, ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScramPreload {
+    /// Restart from zero output
+    Zero,
+    /// Restart from the power command applied just before the trip
+    PreTrip,
+}
+
+/// Closed-loop controller driving the power command.
+#[derive(Clone, Debug, PartialEq, ValueEnum, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ControllerKind {
+    /// Tuned PID (default)
+    Pid,
+    /// LQR state feedback with integral action, derived from the plant parameters
+    Lqr,
+    /// Model predictive control keeping the predicted temperature below the trip margin
+    Mpc,
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "reactor-safety-sim",
+    version,
+    about = "Generic safety-critical control simulation (portfolio)",
+    args_conflicts_with_subcommands = true,
+    after_help = exit::HELP
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Deprecated: `run` flags given without a subcommand still start a run
+    #[command(flatten)]
+    pub legacy_run: RunArgs,
+
+    #[command(flatten)]
+    pub log: logging::LogArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run one simulation and write its trace
+    Run(RunArgs),
+    /// Re-evaluate the safety logic over a recorded trace and check the trip decisions match
+    Replay(replay::ReplayArgs),
+    /// Run the simulation over consecutive seeds and summarize each run
+    Batch(batch::BatchArgs),
+    /// Print summary statistics of a recorded trace
+    Inspect(inspect::InspectArgs),
+    /// Check configuration and scenario files and report every problem, without running
+    Validate(validate::ValidateArgs),
+    /// Describe the built-in scenarios (and, with --detail, how they end)
+    ListScenarios(list::ListScenariosArgs),
+    /// Compare two recorded traces and check they agree within tolerances
+    Diff(diff::DiffArgs),
+    /// Re-run a trace from the configuration in its header and check it reproduces exactly
+    Verify(verify::VerifyArgs),
+    /// Bisect one parameter for the value at which a scenario starts (or stops) tripping
+    Search(search::SearchArgs),
+    /// Run a scenario over a grid of parameter values and tabulate the outcomes as CSV
+    Sweep(sweep::SweepArgs),
+    /// Studies over repeated runs (sensitivity of the trip time to each parameter)
+    Analyze(analyze::AnalyzeArgs),
+    /// Merge the outputs of several batch runs and report per scenario
+    Stats(stats::StatsArgs),
+    /// Print the JSON Schema of the JSONL trace (header line and rows)
+    Schema,
+    /// Record golden traces of the built-in scenarios, or check a build still reproduces them
+    Golden(golden::GoldenArgs),
+    /// Time the simulation loop (steps per second and per-stage cost) on this machine
+    Bench(bench::BenchArgs),
+    /// Rewrite a trace in another format (e.g. a binary trace as JSONL)
+    Convert(convert::ConvertArgs),
+}
+
+/// Arguments of `run` (and of the deprecated flat invocation).
+#[derive(Parser, Clone, Debug)]
+#[command(group(clap::ArgGroup::new("output")))]
+pub struct RunArgs {
+    /// Simulation description (TOML); the flags below override its values
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Print the effective configuration (defaults, --config and flags merged) as TOML and exit
+    #[arg(long)]
+    pub print_config: bool,
+
+    /// Scenario to run [default: normal]; a comma-separated list runs each in turn, with
+    /// --out-dir
+    #[arg(value_enum, long, value_delimiter = ',')]
+    pub scenario: Vec<Scenario>,
+
+    /// Scenario description (TOML, or YAML for .yaml/.yml) with initial conditions, sensor fault
+    /// windows and timed events, instead of a built-in --scenario
+    #[arg(long, value_name = "FILE", conflicts_with = "scenario")]
+    pub scenario_file: Option<PathBuf>,
+
+    /// Inject a sensor fault on top of the scenario, as <sensor>:<kind>[:<value>][@<t>s] with
+    /// kinds bias, stuck, drift (°C/s), dropout (every n-th reading) and none; repeatable, later
+    /// flags win. E.g. "s2:bias:+20", "s1:dropout:50", "s3:drift:0.5@30s"
+    #[arg(long = "fault", value_name = "SPEC")]
+    pub faults: Vec<fault::FaultArg>,
+
+    /// Total simulation time in seconds [default: 120]
+    #[arg(long)]
+    pub seconds: Option<f64>,
+
+    /// Fixed time step in milliseconds [default: 50]
+    #[arg(long)]
+    pub dt_ms: Option<u64>,
+
+    /// Control setpoint temperature (°C) [default: 350]
+    #[arg(long)]
+    pub setpoint: Option<f64>,
+
+    /// Time-varying setpoint as <t_s>:<°C> points, linearly interpolated (overrides --setpoint),
+    /// e.g. "0:300,30:300,90:380"
+    #[arg(long, value_name = "POINTS")]
+    pub setpoint_profile: Option<SetpointProfile>,
+
+    /// Trip temperature (°C) for SCRAM [default: 420]
+    #[arg(long)]
+    pub trip_temp: Option<f64>,
+
+    /// RNG seed for deterministic runs [default: 12345]
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Measurement noise standard deviation (°C) per sensor, e.g. "s2=0.5" or "s1=0.1,s3=0.1"
+    /// [default: 0.15 each, or the configuration's]
+    #[arg(long, value_name = "SENSOR=STD", value_delimiter = ',')]
+    pub noise: Vec<config::SensorValue<f64>>,
+
+    /// Seed of a sensor's noise, e.g. "s2=999" [default: derived from --seed]
+    #[arg(long, value_name = "SENSOR=SEED", value_delimiter = ',')]
+    pub sensor_seed: Vec<config::SensorValue<u64>>,
+
+    /// Emit controller diagnostics (P/I/D term contributions) as extra trace columns
+    #[arg(long)]
+    pub diagnostics: bool,
+
+    /// Controller output to preload when a SCRAM latches
+    #[arg(value_enum, long, default_value = "zero")]
+    pub scram_preload: ScramPreload,
+
+    /// Controller driving the power command
+    #[arg(value_enum, long, default_value = "pid")]
+    pub controller: ControllerKind,
+
+    /// LQR weight on the temperature error
+    #[arg(long, default_value_t = 1.0)]
+    pub lqr_q: f64,
+
+    /// LQR weight on the power deviation
+    #[arg(long, default_value_t = 1000.0)]
+    pub lqr_r: f64,
+
+    /// Derive PID gains from the plant model for this 2% settling time (s) instead of the defaults
+    #[arg(long, value_name = "SETTLING_S", conflicts_with_all = ["kp", "ki", "kd"])]
+    pub auto_gains: Option<f64>,
+
+    /// PID proportional gain (overrides pid.kp)
+    #[arg(
+        long,
+        help_heading = "Parameter overrides",
+        allow_negative_numbers = true
+    )]
+    pub kp: Option<f64>,
+
+    /// PID integral gain (overrides pid.ki)
+    #[arg(
+        long,
+        help_heading = "Parameter overrides",
+        allow_negative_numbers = true
+    )]
+    pub ki: Option<f64>,
+
+    /// PID derivative gain (overrides pid.kd)
+    #[arg(
+        long,
+        help_heading = "Parameter overrides",
+        allow_negative_numbers = true
+    )]
+    pub kd: Option<f64>,
+
+    /// PID output upper limit (overrides pid.out_max)
+    #[arg(
+        long,
+        help_heading = "Parameter overrides",
+        allow_negative_numbers = true
+    )]
+    pub out_max: Option<f64>,
+
+    /// Plant heat capacity (overrides plant.thermal_mass)
+    #[arg(
+        long,
+        help_heading = "Parameter overrides",
+        allow_negative_numbers = true
+    )]
+    pub plant_thermal_mass: Option<f64>,
+
+    /// Plant cooling coefficient (overrides plant.k_cool)
+    #[arg(
+        long,
+        help_heading = "Parameter overrides",
+        allow_negative_numbers = true
+    )]
+    pub plant_k_cool: Option<f64>,
+
+    /// Plant heating per unit power (overrides plant.k_power)
+    #[arg(
+        long,
+        help_heading = "Parameter overrides",
+        allow_negative_numbers = true
+    )]
+    pub plant_k_power: Option<f64>,
+
+    /// Ambient temperature (°C) the plant cools towards (overrides plant.ambient_c)
+    #[arg(
+        long,
+        help_heading = "Parameter overrides",
+        allow_negative_numbers = true
+    )]
+    pub plant_ambient: Option<f64>,
+
+    /// MPC keeps the predicted temperature this far below the trip temperature (°C)
+    #[arg(long, default_value_t = 5.0)]
+    pub mpc_margin: f64,
+
+    /// Trace output format
+    #[arg(value_enum, long, default_value = "jsonl")]
+    pub format: TraceFormat,
+
+    /// Write the trace to this file instead of stdout (parent directories are created)
+    #[arg(long, value_name = "PATH", group = "output")]
+    pub out: Option<PathBuf>,
+
+    /// Write the trace of each --scenario to DIR/<scenario>.<format> and print a combined summary
+    /// table (for batch: the directory for runs.csv and aggregate.json)
+    #[arg(
+        long,
+        value_name = "DIR",
+        group = "output",
+        conflicts_with = "scenario_file"
+    )]
+    pub out_dir: Option<PathBuf>,
+
+    /// Write only every Nth row, plus the first and last rows and every row where the trip or
+    /// alarm state changes (the summary still covers every step)
+    #[arg(long, value_name = "N")]
+    pub decimate: Option<NonZeroU64>,
+
+    /// Gzip-compress the --out file (implied by a .gz extension) or the --out-dir traces
+    #[arg(long)]
+    pub compress: bool,
+
+    /// Overwrite the --out file (or --out-dir files) if it already exists
+    #[arg(long, requires = "output")]
+    pub force: bool,
+
+    /// End-of-run summary, on stdout with --out and on stderr otherwise
+    #[arg(value_enum, long, default_value = "text")]
+    pub summary: SummaryFormat,
+
+    /// Keep simulating after a SCRAM (power held at 0, safety evaluation still running) until
+    /// --seconds elapse, instead of ending the run on the trip
+    #[arg(long)]
+    pub continue_after_scram: bool,
+
+    /// End the run early once COND holds: settled:BAND:SECS (true temperature within ±BAND °C of
+    /// the setpoint for SECS), max-temp:C (true temperature above C) or alarms:N (N sensor
+    /// disagreement alarms raised); repeat to stop on whichever holds first
+    #[arg(long, value_name = "COND")]
+    pub until: Vec<UntilCondition>,
+
+    /// Pace the run so simulated time advances at SPEED × wall-clock time, flushing every row;
+    /// Ctrl-C ends the run early with its summary
+    #[arg(long, value_name = "SPEED", num_args = 0..=1, default_missing_value = "1.0")]
+    pub realtime: Option<f64>,
+
+    /// Also stream the trace as JSONL (header, then rows) to every client connecting to
+    /// HOST:PORT or unix:PATH
+    #[arg(long, value_name = "ADDR")]
+    pub listen: Option<stream::ListenAddr>,
+
+    /// With --listen: wait for this many clients before starting the run
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "listen")]
+    pub wait_for_clients: usize,
+
+    /// Read newline-delimited JSON commands (setpoint, coolant, fault, scram) from stdin and apply
+    /// them during the run; without --realtime the whole script is read first
+    #[arg(long)]
+    pub command_stream: bool,
+
+    /// Save the state of the run every INTERVAL of simulated time (e.g. 10s) and when it is
+    /// interrupted, so `--resume` can continue it
+    #[arg(
+        long,
+        value_name = "INTERVAL",
+        value_parser = checkpoint::parse_interval,
+        requires = "checkpoint_out"
+    )]
+    pub checkpoint_every: Option<f64>,
+
+    /// Checkpoint file of --checkpoint-every (replaced by each new checkpoint)
+    #[arg(long, value_name = "FILE", requires = "checkpoint_every")]
+    pub checkpoint_out: Option<PathBuf>,
+
+    /// Continue the run saved in a --checkpoint-out file with the step after it; the configuration
+    /// and run options come from the checkpoint, --seconds may extend the end time
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = [
+            "config", "print_config", "scenario", "scenario_file", "faults", "dt_ms", "setpoint",
+            "setpoint_profile", "trip_temp", "seed", "noise", "sensor_seed", "diagnostics",
+            "scram_preload", "controller", "lqr_q", "lqr_r", "auto_gains", "kp", "ki", "kd",
+            "out_max", "plant_thermal_mass", "plant_k_cool", "plant_k_power", "plant_ambient",
+            "mpc_margin", "decimate", "continue_after_scram", "until", "out_dir",
+        ]
+    )]
+    pub resume: Option<PathBuf>,
+
+    /// Exit with code 2 if the run trips (for batch: if any run trips)
+    #[arg(long)]
+    pub fail_on_trip: bool,
+
+    /// Print no progress reports on stderr
+    #[arg(long)]
+    pub quiet: bool,
+
+    /// Wall-clock seconds between progress reports on stderr
+    #[arg(long, value_name = "SECS", default_value_t = 1.0)]
+    pub progress_every: f64,
+}
+
+impl RunArgs {
+    /// Effective simulation description: built-in defaults, then `--config`, then explicit flags.
+    pub fn sim_config(&self) -> Result<SimConfig> {
+        let cfg = match &self.config {
+            Some(path) => SimConfig::load(path)?,
+            None => SimConfig::default(),
+        };
+        let scenario = match self.scenario.as_slice() {
+            [] => cfg.run.scenario,
+            [scenario] => *scenario,
+            _ => bail!(InvalidInput(
+                "--scenario lists several scenarios; give --out-dir to run each of them".into()
+            )),
+        };
+        let mut cfg = if self.scenario_file.is_some() {
+            cfg
+        } else {
+            cfg.for_scenario(&scenario)?
+        };
+        if let Some(seconds) = self.seconds {
+            cfg.run.seconds = seconds;
+        }
+        if let Some(dt_ms) = self.dt_ms {
+            cfg.run.dt_ms = dt_ms;
+        }
+        if let Some(seed) = self.seed {
+            cfg.run.seed = seed;
+        }
+        if let Some(setpoint) = self.setpoint {
+            cfg.run.setpoint = setpoint;
+        }
+        if let Some(trip_temp) = self.trip_temp {
+            cfg.safety.trip_temp_c = trip_temp;
+        }
+        for noise in &self.noise {
+            cfg.sensors.get_mut(noise.sensor).noise_std = noise.value;
+        }
+        for seed in &self.sensor_seed {
+            cfg.sensors.get_mut(seed.sensor).seed = Some(seed.value);
+        }
+        for (key, value) in self.param_overrides() {
+            let slot = match key {
+                "pid.kp" => &mut cfg.pid.kp,
+                "pid.ki" => &mut cfg.pid.ki,
+                "pid.kd" => &mut cfg.pid.kd,
+                "pid.out_max" => &mut cfg.pid.out_max,
+                "plant.thermal_mass" => &mut cfg.plant.thermal_mass,
+                "plant.k_cool" => &mut cfg.plant.k_cool,
+                "plant.k_power" => &mut cfg.plant.k_power,
+                "plant.ambient_c" => &mut cfg.plant.ambient_c,
+                _ => unreachable!("unhandled override {key}"),
+            };
+            *slot = value;
+        }
+        Ok(cfg)
+    }
+
+    /// The PID and plant override flags that were given, keyed by the config field they replace.
+    pub fn param_overrides(&self) -> Vec<(&'static str, f64)> {
+        [
+            ("pid.kp", self.kp),
+            ("pid.ki", self.ki),
+            ("pid.kd", self.kd),
+            ("pid.out_max", self.out_max),
+            ("plant.thermal_mass", self.plant_thermal_mass),
+            ("plant.k_cool", self.plant_k_cool),
+            ("plant.k_power", self.plant_k_power),
+            ("plant.ambient_c", self.plant_ambient),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+    }
+}
+
+/// The `run` flags that shape the trace beyond the `SimConfig` and the scenario, recorded in the
+/// trace header so `verify` can re-run it.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RunOptions {
+    pub controller: ControllerKind,
+    pub scram_preload: ScramPreload,
+    pub lqr_q: f64,
+    pub lqr_r: f64,
+    pub mpc_margin: f64,
+    pub auto_gains: Option<f64>,
+    /// (t_s, °C) points of `--setpoint-profile`
+    pub setpoint_profile: Option<Vec<(f64, f64)>>,
+    pub diagnostics: bool,
+    pub decimate: Option<NonZeroU64>,
+    pub continue_after_scram: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub until: Vec<UntilCondition>,
+}
+
+impl RunArgs {
+    /// Flags given that only a single `run` can honor (where its trace goes, pacing, live clients
+    /// and commands), for the subcommands running many headless runs to reject.
+    pub fn single_run_flags(&self) -> Vec<&'static str> {
+        [
+            ("--out", self.out.is_some()),
+            ("--out-dir", self.out_dir.is_some()),
+            ("--realtime", self.realtime.is_some()),
+            ("--listen", self.listen.is_some()),
+            ("--command-stream", self.command_stream),
+            ("--checkpoint-every", self.checkpoint_every.is_some()),
+            ("--resume", self.resume.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(flag, given)| given.then_some(flag))
+        .collect()
+    }
+
+    pub fn options(&self) -> RunOptions {
+        RunOptions {
+            controller: self.controller.clone(),
+            scram_preload: self.scram_preload.clone(),
+            lqr_q: self.lqr_q,
+            lqr_r: self.lqr_r,
+            mpc_margin: self.mpc_margin,
+            auto_gains: self.auto_gains,
+            setpoint_profile: self.setpoint_profile.as_ref().map(|p| p.points().to_vec()),
+            diagnostics: self.diagnostics,
+            decimate: self.decimate,
+            continue_after_scram: self.continue_after_scram,
+            until: self.until.clone(),
+        }
+    }
+
+    /// Set the flags recorded in `options`.
+    pub fn apply_options(&mut self, options: &RunOptions) -> Result<()> {
+        self.controller = options.controller.clone();
+        self.scram_preload = options.scram_preload.clone();
+        self.lqr_q = options.lqr_q;
+        self.lqr_r = options.lqr_r;
+        self.mpc_margin = options.mpc_margin;
+        self.auto_gains = options.auto_gains;
+        self.setpoint_profile = match &options.setpoint_profile {
+            Some(points) => Some(
+                SetpointProfile::new(points.clone())
+                    .map_err(|e| InvalidInput(format!("invalid setpoint profile: {e}")))?,
+            ),
+            None => None,
+        };
+        self.diagnostics = options.diagnostics;
+        self.decimate = options.decimate;
+        self.continue_after_scram = options.continue_after_scram;
+        self.until = options.until.clone();
+        Ok(())
+    }
+}
+
+/// Dispatch a parsed command line; human-readable output goes to `stdout`. Returns the process
+/// exit code (see `exit`); errors map to theirs with `exit::code_for`.
+pub fn execute(cli: &Cli, stdout: &mut dyn Write) -> Result<u8> {
+    match &cli.command {
+        Some(Command::Run(args)) => run_command(args, stdout),
+        Some(Command::Replay(args)) => replay::execute(args, stdout),
+        Some(Command::Batch(args)) => batch::execute(args, stdout),
+        Some(Command::Inspect(args)) => inspect::execute(args, stdout).map(|()| exit::OK),
+        Some(Command::Validate(args)) => validate::execute(args, stdout),
+        Some(Command::ListScenarios(args)) => list::execute(args, stdout),
+        Some(Command::Diff(args)) => diff::execute(args, stdout),
+        Some(Command::Verify(args)) => verify::execute(args, stdout),
+        Some(Command::Search(args)) => search::execute(args, stdout),
+        Some(Command::Sweep(args)) => sweep::execute(args, stdout),
+        Some(Command::Analyze(args)) => analyze::execute(args, stdout),
+        Some(Command::Stats(args)) => stats::execute(args, stdout),
+        Some(Command::Schema) => schema::execute(stdout).map(|()| exit::OK),
+        Some(Command::Golden(args)) => golden::execute(args, stdout),
+        Some(Command::Bench(args)) => bench::execute(args, stdout),
+        Some(Command::Convert(args)) => convert::execute(args, stdout),
+        None => {
+            eprintln!(
+                "warning: running without a subcommand is deprecated and will be removed in the \
+                 next release; use `reactor-safety-sim run [OPTIONS]`"
+            );
+            run_command(&cli.legacy_run, stdout)
+        }
+    }
+}
+
+/// `run`: the trace goes to `--out` if given (summary on `stdout`), else to `stdout` (summary on
+/// stderr); with `--out-dir`, each `--scenario` is run into its own file (see `multi`).
+pub fn run_command(args: &RunArgs, stdout: &mut dyn Write) -> Result<u8> {
+    if args.print_config {
+        write!(stdout, "{}", args.sim_config()?.to_toml()?)?;
+        return Ok(exit::OK);
+    }
+    if args.compress && args.out.is_none() && args.out_dir.is_none() {
+        bail!(InvalidInput(
+            "--compress applies to the --out file; pipe stdout through gzip instead".into()
+        ));
+    }
+    if let Some(dir) = &args.out_dir {
+        return multi::execute(args, dir, stdout);
+    }
+    let stop = &realtime::INTERRUPTED;
+    let summary = match &args.out {
+        Some(path) => {
+            let summary = run_to_file(args, path, stop)?;
+            summary.write(args.summary, stdout)?;
+            summary
+        }
+        None => {
+            let summary = run_until(args, &mut BufWriter::new(&mut *stdout), stop)?;
+            summary.write(args.summary, &mut io::stderr().lock())?;
+            summary
+        }
+    };
+    Ok(if stop.load(Ordering::SeqCst) {
+        exit::INTERRUPTED
+    } else if args.fail_on_trip && summary.tripped() {
+        exit::FAILED_CHECK
+    } else {
+        exit::OK
+    })
+}
+
+/// `run_until` into the trace file at `path`, gzip-compressed with `--compress` or a `.gz` name.
+pub fn run_to_file(args: &RunArgs, path: &Path, stop: &AtomicBool) -> Result<RunSummary> {
+    let mut file = open_output(path, args.force)?;
+    if !(args.compress || trace::is_gzip_path(path)) {
+        return run_until(args, &mut file, stop);
+    }
+    let mut gz = GzEncoder::new(file, Compression::default());
+    let summary = run_until(args, &mut gz, stop)?;
+    gz.finish()
+        .and_then(|mut file| file.flush())
+        .with_context(|| format!("writing {}", path.display()))?;
+    Ok(summary)
+}
+
+/// Create the trace file, refusing to clobber an existing one unless `force`.
+pub fn open_output(path: &Path, force: bool) -> Result<BufWriter<File>> {
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("creating directory {}", parent.display()))?;
+    }
+    let mut opts = OpenOptions::new();
+    opts.write(true);
+    if force {
+        opts.create(true).truncate(true);
+    } else {
+        opts.create_new(true);
+    }
+    match opts.open(path) {
+        Ok(file) => Ok(BufWriter::new(file)),
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(e).with_context(|| {
+            format!(
+                "{} already exists (use --force to overwrite)",
+                path.display()
+            )
+        }),
+        Err(e) => Err(e).with_context(|| format!("creating {}", path.display())),
+    }
+}
+
+/// Run one simulation and write the trace to `out` in the requested format.
+pub fn run(args: &RunArgs, out: &mut dyn Write) -> Result<RunSummary> {
+    run_until(args, out, &AtomicBool::new(false))
+}
+
+/// `run`, ending early (after the step in progress, with the trace flushed and outcome
+/// `Interrupted`) once `stop` is set, as the Ctrl-C handler does.
+pub fn run_until(args: &RunArgs, out: &mut dyn Write, stop: &AtomicBool) -> Result<RunSummary> {
+    if let Some(path) = &args.resume {
+        let ckpt = Checkpoint::load(path)?;
+        let (run_args, cfg, spec) = ckpt.resume_args(args)?;
+        return simulate_from(&run_args, cfg, spec, out, stop, Some(ckpt));
+    }
+    let cfg = args.sim_config()?;
+    let mut spec = scenario_spec(args, &cfg)?;
+    if args.command_stream && args.realtime.is_none() {
+        for command in command::read_script(io::stdin().lock()) {
+            command.apply(&mut spec, 0.0);
+        }
+    }
+    simulate(args, cfg, spec, out, stop)
+}
+
+/// The scenario a run simulates: `--scenario-file` or the built-in one selected by `cfg`, with the
+/// `--fault` flags applied.
+pub fn scenario_spec(args: &RunArgs, cfg: &SimConfig) -> Result<ScenarioSpec> {
+    let mut spec = match &args.scenario_file {
+        Some(path) => scenario::load(path)?,
+        None => cfg.run.scenario.spec(cfg.run.seconds),
+    };
+    for fault in &args.faults {
+        fault.apply(&mut spec);
+    }
+    Ok(spec)
+}
+
+/// The simulation loop of `run_until`, for an already merged configuration and scenario (as
+/// `verify` takes them from a trace header). `args` supplies the remaining run options.
+pub fn simulate(
+    args: &RunArgs,
+    cfg: SimConfig,
+    spec: ScenarioSpec,
+    out: &mut dyn Write,
+    stop: &AtomicBool,
+) -> Result<RunSummary> {
+    simulate_from(args, cfg, spec, out, stop, None)
+}
+
+/// `simulate`, continuing from `resume` (with `cfg` and `spec` from it) if given.
+pub fn simulate_from(
+    args: &RunArgs,
+    mut cfg: SimConfig,
+    spec: ScenarioSpec,
+    out: &mut dyn Write,
+    stop: &AtomicBool,
+    resume: Option<Checkpoint>,
+) -> Result<RunSummary> {
+    let started = Instant::now();
+    let problems = cfg.problems();
+    if !problems.is_empty() {
+        bail!(InvalidInput(format!(
+            "invalid configuration: {}",
+            problems.join("; ")
+        )));
+    }
+    let _run = tracing::info_span!("run", scenario = %spec.name, seed = cfg.run.seed).entered();
+    let dt_s = (cfg.run.dt_ms as f64) / 1000.0;
+    let steps = (cfg.run.seconds / dt_s).ceil() as u64;
+    // A resumed run continues with the step after its checkpoint
+    let first_step = resume.as_ref().map_or(0, |ckpt| ckpt.step);
+    let mut pacer = match args.realtime {
+        Some(speed) if !(speed.is_finite() && speed > 0.0) => {
+            bail!(InvalidInput("--realtime speed must be > 0".into()))
+        }
+        Some(speed) => Some(realtime::Pacer::new(speed).starting_at(first_step as f64 * dt_s)),
+        None => None,
+    };
+    // Steps between two checkpoints
+    let checkpoint_steps = args
+        .checkpoint_every
+        .map(|every_s| ((every_s / dt_s).round() as u64).max(1));
+
+    let profile = args
+        .setpoint_profile
+        .clone()
+        .unwrap_or_else(|| SetpointProfile::constant(cfg.run.setpoint));
+
+    // Paced runs take commands as they arrive (see `command`)
+    let live_commands = (args.command_stream && args.realtime.is_some())
+        .then(|| command::spawn_reader(io::BufReader::new(io::stdin())));
+
+    // Plant
+    let p = cfg.plant;
+
+    // Safety
+    let s_cfg = cfg.safety;
+
+    // Sensors
+    let seeds = cfg.sensors.seeds(cfg.run.seed);
+    let sensors = std::array::from_fn(|i| {
+        let mut sensor = Sensor::new(seeds[i]);
+        cfg.sensors.get(i).apply(&mut sensor);
+        sensor
+    });
+
+    // --auto-gains replaces the configured gains, so the header records the ones in effect
+    if let (ControllerKind::Pid, Some(settling_s), None) =
+        (&args.controller, args.auto_gains, &resume)
+    {
+        cfg.pid = auto_gains(&cfg, spec.initial.coolant, settling_s)?;
+    }
+
+    // Controller (model-based designs use the scenario's initial coolant)
+    let ctrl = match &resume {
+        Some(ckpt) => ckpt.controller.clone(),
+        None => build_controller(args, &cfg, spec.initial.coolant)?,
+    };
+
+    let mut writer = TraceWriter::new(out, args.format, args.diagnostics);
+    let mut server = None;
+    // Checkpoints carry the header, so it is built for them even when the trace has none
+    let mut checkpoint_meta = None;
+    if args.format.has_header() || args.listen.is_some() || checkpoint_steps.is_some() {
+        let meta = match &resume {
+            // The header of the checkpointed run, with the end time given to --resume
+            Some(ckpt) => TraceMeta {
+                header: trace::TraceHeader {
+                    config_hash: cfg
+                        .config_hash(ckpt.meta.scenario_spec.as_ref().unwrap_or(&spec))?,
+                    ..ckpt.meta.header.clone()
+                },
+                config: Some(cfg.clone()),
+                resumed: Some(trace::ResumePoint {
+                    step: ckpt.step,
+                    t_s: ckpt.step as f64 * dt_s,
+                }),
+                provenance: Some(Provenance::current()),
+                ..ckpt.meta.clone()
+            },
+            None => TraceMeta::new(args, &cfg, &spec, profile.at(0.0))?,
+        };
+        if checkpoint_steps.is_some() {
+            checkpoint_meta = Some(meta.clone());
+        }
+        if let Some(addr) = &args.listen {
+            let listening = stream::TraceServer::bind(addr, &meta.header_line()?)?;
+            if !args.quiet {
+                eprintln!("streaming the trace on {}", listening.addr());
+            }
+            listening.wait_for_clients(args.wait_for_clients);
+            server = Some(listening);
+        }
+        if args.format.has_header() {
+            writer = writer.with_meta(meta);
+        }
+    }
+    let mut decimator = Decimator::new(args.decimate.map_or(1, NonZeroU64::get));
+    // Last row skipped by the decimator, written at the end so the trace keeps its final row
+    let mut skipped = None;
+    let mut progress = Progress::new("run", args);
+    let mut until = Until::new(&args.until);
+    let mut summary = RunSummary {
+        max_temp_c: f64::NEG_INFINITY,
+        min_margin_c: f64::INFINITY,
+        faults: args.faults.iter().map(ToString::to_string).collect(),
+        overrides: args
+            .param_overrides()
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect(),
+        ..Default::default()
+    };
+
+    let mut sim = Simulation::new(spec, p, sensors, s_cfg, ctrl, profile, dt_s);
+    sim.preload_pre_trip = args.scram_preload == ScramPreload::PreTrip;
+    if let Some(ckpt) = resume {
+        sim.step = ckpt.step;
+        sim.state = ckpt.plant;
+        sim.sensors = ckpt.sensors;
+        // Every fault of a run comes from its schedules, which last set the faults restored
+        sim.scheduled_faults = sim.sensors.each_ref().map(|s| s.fault);
+        sim.safety = ckpt.safety;
+        sim.ctrl_out = ckpt.ctrl_out;
+        sim.applied_prev = ckpt.applied_prev;
+        sim.setpoint_override = ckpt.setpoint_override;
+        sim.next_event = ckpt.next_event;
+        sim.alarm = ckpt.alarm;
+        sim.valid = ckpt.valid;
+        until = ckpt.until;
+        decimator = ckpt.decimator;
+        summary = ckpt.summary;
+    }
+
+    // Trace rows (one per step)
+    for k in first_step..steps {
+        let t_s = (k as f64) * dt_s;
+        let _step = tracing::debug_span!("step", t_s).entered();
+
+        // Commands received since the last step join the scenario; they are due at or after this
+        // step, so they sort after every event already applied
+        if let Some(commands) = &live_commands {
+            let received: Vec<_> = commands.try_iter().collect();
+            if !received.is_empty() {
+                sim.edit_scenario(|spec| {
+                    for command in received {
+                        command.apply(spec, t_s);
+                    }
+                });
+            }
+        }
+
+        let sample = sim.step();
+        for event in &sample.events {
+            match *event {
+                StepEvent::Scenario(action) => tracing::info!(?action, "scenario event"),
+                StepEvent::Fault { sensor, fault } => {
+                    let sensor = config::SENSOR_NAMES[sensor];
+                    tracing::debug!(sensor, ?fault, "sensor fault switched");
+                }
+                StepEvent::Alarm(raised) => tracing::debug!(raised, "sensor disagreement alarm"),
+                StepEvent::Valid { .. } | StepEvent::Trip => {}
+            }
+        }
+
+        // Emit trace
+        let [y1, y2, y3] = sample.readings;
+        let row = TraceRow {
+            t_s,
+            setpoint_c: sample.setpoint_c,
+            true_temp_c: sample.true_temp_c,
+            s1_c: y1,
+            s2_c: y2,
+            s3_c: y3,
+            power: sample.power,
+            coolant: sample.coolant,
+            scram: sample.scram,
+            reason: sample.trip.map(RowReason::Trip),
+            diag: args.diagnostics.then(|| DiagColumns::new(&sample.control)),
+        };
+        let hottest = [y1, y2, y3]
+            .into_iter()
+            .filter(|y| y.is_finite())
+            .fold(f64::NEG_INFINITY, f64::max);
+        let coldest = [y1, y2, y3]
+            .into_iter()
+            .filter(|y| y.is_finite())
+            .fold(f64::INFINITY, f64::min);
+        summary.steps += 1;
+        summary.end_t_s = t_s;
+        summary.final_temp_c = sample.true_temp_c;
+        summary.max_temp_c = summary.max_temp_c.max(sample.true_temp_c);
+        summary.min_margin_c = summary.min_margin_c.min(s_cfg.trip_temp_c - hottest);
+        if hottest >= coldest {
+            summary.max_sensor_spread_c = summary.max_sensor_spread_c.max(hottest - coldest);
+        }
+        if row.scram && !summary.tripped() {
+            summary.outcome = Outcome::Tripped;
+            summary.trip_t_s = Some(t_s);
+            summary.reason = row.reason_name();
+        }
+        progress.update(|| {
+            let done_s = t_s + dt_s;
+            format!(
+                "{done_s:.1}/{:.1} s simulated ({:.0} %), max {:.1} °C",
+                cfg.run.seconds,
+                100.0 * done_s / cfg.run.seconds,
+                summary.max_temp_c
+            )
+        });
+        if decimator.keep(k, row.scram, sample.alarm) {
+            writer.write_row(&row)?;
+            if let Some(server) = &server {
+                server.send(&serde_json::to_string(&row)?);
+            }
+            skipped = None;
+        } else {
+            skipped = Some(row);
+        }
+
+        let setpoint = sample.setpoint_c;
+        if let Some(condition) = until.step(t_s, sample.true_temp_c, setpoint, sim.alarm) {
+            tracing::info!(%condition, "run ended by --until");
+            summary.stopped_by = Some(condition.to_string());
+            if !summary.tripped() {
+                summary.outcome = Outcome::Stopped;
+            }
+            break;
+        }
+        if sample.scram && !args.continue_after_scram {
+            // stop early for clarity
+            break;
+        }
+        if let Some(pacer) = &mut pacer {
+            writer.flush()?;
+            pacer.wait_until(t_s + dt_s);
+        }
+        let stopping = stop.load(Ordering::SeqCst);
+        if let (Some(every), Some(path)) = (checkpoint_steps, &args.checkpoint_out) {
+            if (k + 1) % every == 0 || k + 1 == steps || stopping {
+                // The trace on disk then holds every row the checkpoint has seen
+                writer.flush()?;
+                Checkpoint {
+                    schema: checkpoint::CHECKPOINT_SCHEMA,
+                    meta: checkpoint_meta.clone().expect("built when checkpointing"),
+                    spec: sim.scenario().clone(),
+                    step: k + 1,
+                    plant: sim.state,
+                    sensors: sim.sensors.clone(),
+                    safety: sim.safety.clone(),
+                    controller: sim.controller.clone(),
+                    ctrl_out: sim.ctrl_out,
+                    applied_prev: sim.applied_prev,
+                    setpoint_override: sim.setpoint_override,
+                    next_event: sim.next_event,
+                    alarm: sim.alarm,
+                    valid: sim.valid,
+                    until: until.clone(),
+                    decimator: decimator.clone(),
+                    summary: summary.clone(),
+                }
+                .save(path)?;
+                tracing::debug!(step = k + 1, "checkpoint saved");
+            }
+        }
+        if stopping {
+            if !summary.tripped() {
+                summary.outcome = Outcome::Interrupted;
+            }
+            break;
+        }
+    }
+
+    if let Some(row) = skipped {
+        writer.write_row(&row)?;
+        if let Some(server) = &server {
+            server.send(&serde_json::to_string(&row)?);
+        }
+    }
+    writer.finish()?;
+    if let Some(server) = server {
+        server.finish();
+    }
+    progress.finish(None);
+    summary.wall_clock_s = started.elapsed().as_secs_f64();
+    Ok(summary)
+}
+
+/// `cfg.pid` with the gains of `--auto-gains` for a settling time of `settling_s` at the coolant
+/// fraction `coolant`; the rest of the `[pid]` settings (limits, filters, action) are kept.
+fn auto_gains(cfg: &SimConfig, coolant: f64, settling_s: f64) -> Result<PidConfig> {
+    if !(settling_s.is_finite() && settling_s > 0.0) {
+        bail!(InvalidInput(
+            "--auto-gains must be a positive settling time in seconds".into()
+        ));
+    }
+    let gains = controller::tuning::suggest_gains(&cfg.plant, coolant, settling_s);
+    // The trace header records the gains; the log has them for runs without one
+    tracing::info!(settling_s, gains.kp, gains.ki, gains.kd, "auto-gains");
+    Ok(PidConfig {
+        kp: gains.kp,
+        ki: gains.ki,
+        kd: gains.kd,
+        ..cfg.pid
+    })
+}
+
+/// The controller selected by `args`; model-based designs are linearized at `coolant`.
+fn build_controller(args: &RunArgs, cfg: &SimConfig, coolant: f64) -> Result<ControllerState> {
+    let p = &cfg.plant;
+    Ok(match args.controller {
+        ControllerKind::Pid => {
+            let pid_cfg = cfg.pid;
+            if let Err(errs) = pid_cfg.validate() {
+                let msgs: Vec<String> = errs.iter().map(|e| e.to_string()).collect();
+                bail!(InvalidInput(format!(
+                    "invalid PID configuration: {}",
+                    msgs.join("; ")
+                )));
+            }
+            ControllerState::Pid(Pid::new(pid_cfg))
+        }
+        ControllerKind::Lqr => {
+            if !(args.lqr_q >= 0.0
+                && args.lqr_r > 0.0
+                && args.lqr_q.is_finite()
+                && args.lqr_r.is_finite())
+            {
+                bail!(InvalidInput(
+                    "invalid LQR weights: q must be >= 0 and r > 0".into()
+                ));
+            }
+            let lqr_cfg = LqrConfig {
+                q: args.lqr_q,
+                r: args.lqr_r,
+                ..Default::default()
+            };
+            ControllerState::Lqr(Lqr::new(p, coolant, lqr_cfg))
+        }
+        ControllerKind::Mpc => {
+            let mpc_cfg = MpcConfig {
+                temp_limit_c: Some(cfg.safety.trip_temp_c - args.mpc_margin),
+                max_rate_per_s: Some(0.5),
+                ..Default::default()
+            };
+            ControllerState::Mpc(Mpc::new(p, coolant, mpc_cfg))
+        }
+    })
+}
@@ -0,0 +1,141 @@
+//! `list-scenarios`: the built-in scenarios, described from their `ScenarioSpec`s.
+
+use std::io::{self, Write};
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use sim::{EventAction, ScenarioSpec};
+
+use crate::exit::{self, InvalidInput};
+use crate::fault::FaultArg;
+use crate::summary::{Outcome, SummaryFormat};
+use crate::trace::TraceFormat;
+use crate::{RunArgs, RunSummary, Scenario};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct ListScenariosArgs {
+    /// Also run each scenario headless and report whether (and when) it trips
+    #[arg(long)]
+    pub detail: bool,
+
+    /// Settings the scenarios are described and run with (defaults unless given); --scenario
+    /// limits the list to those scenarios
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+/// The lines describing `spec`'s initial conditions, sensor faults and events.
+pub fn describe(spec: &ScenarioSpec) -> Vec<(&'static str, String)> {
+    let i = spec.initial;
+    let mut lines = vec![(
+        "initial",
+        format!("{} °C, power {}, coolant {}", i.temp_c, i.power, i.coolant),
+    )];
+
+    let mut faults = Vec::new();
+    for (sensor, (_, schedule)) in spec.sensors.all().into_iter().enumerate() {
+        for w in &schedule.windows {
+            let flag = FaultArg {
+                sensor,
+                fault: w.fault,
+                from_s: w.from_s,
+            };
+            faults.push(match w.until_s {
+                Some(until_s) => format!("{flag} until {until_s} s"),
+                None => flag.to_string(),
+            });
+        }
+    }
+    lines.push(("faults", list_or_none(faults)));
+
+    let events = spec
+        .sorted_events()
+        .iter()
+        .map(|e| {
+            let action = match e.action {
+                EventAction::SetCoolant(c) => format!("coolant -> {c}"),
+                EventAction::SetSetpoint(sp) => format!("setpoint -> {sp} °C"),
+                EventAction::Scram => "manual SCRAM".to_string(),
+            };
+            format!("{:.1} s {action}", e.t_s)
+        })
+        .collect();
+    lines.push(("events", list_or_none(events)));
+    lines
+}
+
+fn list_or_none(items: Vec<String>) -> String {
+    if items.is_empty() {
+        "none".into()
+    } else {
+        items.join(", ")
+    }
+}
+
+/// One-line qualitative outcome of a run.
+pub fn outcome(summary: &RunSummary) -> String {
+    match (summary.tripped(), summary.trip_t_s) {
+        (true, Some(t_s)) => format!(
+            "trips ({}) at {t_s:.2} s",
+            summary.reason.as_deref().unwrap_or("unknown reason")
+        ),
+        _ if summary.outcome == Outcome::Stopped => format!(
+            "stopped ({}) at {:.2} s",
+            summary.stopped_by.as_deref().unwrap_or("--until"),
+            summary.end_t_s
+        ),
+        _ => format!(
+            "no trip in {:.0} s (max {:.1} °C)",
+            summary.end_t_s, summary.max_temp_c
+        ),
+    }
+}
+
+pub fn execute(args: &ListScenariosArgs, stdout: &mut dyn Write) -> Result<u8> {
+    let mut flags = args.run.single_run_flags();
+    if args.run.scenario_file.is_some() {
+        flags.insert(0, "--scenario-file");
+    }
+    if !flags.is_empty() {
+        bail!(InvalidInput(format!(
+            "list-scenarios describes the built-in scenarios; drop {}",
+            flags.join(", ")
+        )));
+    }
+    let scenarios: Vec<Scenario> = if args.run.scenario.is_empty() {
+        Scenario::value_variants().to_vec()
+    } else {
+        args.run.scenario.clone()
+    };
+    for (n, scenario) in scenarios.into_iter().enumerate() {
+        let run_args = RunArgs {
+            scenario: vec![scenario],
+            ..args.run.clone()
+        };
+        let cfg = run_args.sim_config()?;
+        let mut spec = scenario.spec(cfg.run.seconds);
+        for fault in &args.run.faults {
+            fault.apply(&mut spec);
+        }
+        if n > 0 {
+            writeln!(stdout)?;
+        }
+        writeln!(stdout, "{} - {}", spec.name, spec.description)?;
+        for (key, value) in describe(&spec) {
+            writeln!(stdout, "  {key:<8} {value}")?;
+        }
+        if args.detail {
+            let run_args = RunArgs {
+                format: TraceFormat::Csv,
+                summary: SummaryFormat::None,
+                decimate: None,
+                compress: false,
+                quiet: true,
+                ..run_args
+            };
+            let summary = crate::run(&run_args, &mut io::sink())?;
+            writeln!(stdout, "  {:<8} {}", "outcome", outcome(&summary))?;
+        }
+    }
+    Ok(exit::OK)
+}
@@ -0,0 +1,73 @@
+//! Diagnostic log through `tracing`: a `run` span per simulation and a `step` span per time step
+//! (carrying the simulated time), with events for scenario actions, sensor faults switching,
+//! alarms and trips, and controller saturation from the library crates. Warnings only unless more
+//! is asked for with `--log-level` or `RUST_LOG` (which takes full filter directives, e.g.
+//! `safety=debug`); written to stderr or `--log-file`, never to stdout, which may carry the trace.
+
+use std::fs::File;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::EnvFilter;
+
+use crate::exit::InvalidInput;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    /// Also sensor faults switching, alarms and controller saturation
+    Debug,
+    /// Also every step's span and dropped sensor readings
+    Trace,
+}
+
+#[derive(clap::Args, Clone, Debug, Default)]
+pub struct LogArgs {
+    /// Diagnostic log level (overrides RUST_LOG) [default: warn]
+    #[arg(long, value_enum, global = true)]
+    pub log_level: Option<LogLevel>,
+
+    /// Write the diagnostic log to this file (truncated) instead of stderr
+    #[arg(long, value_name = "FILE", global = true)]
+    pub log_file: Option<PathBuf>,
+}
+
+impl LogArgs {
+    /// `--log-level`, else `RUST_LOG`, else warnings only.
+    fn filter(&self) -> Result<EnvFilter> {
+        if let Some(level) = self.log_level {
+            let name = level.to_possible_value().expect("no skipped levels");
+            return Ok(EnvFilter::new(name.get_name()));
+        }
+        match std::env::var("RUST_LOG") {
+            Ok(directives) => EnvFilter::try_new(&directives)
+                .map_err(|e| InvalidInput(format!("RUST_LOG={directives:?}: {e}")).into()),
+            Err(_) => Ok(EnvFilter::new("warn")),
+        }
+    }
+}
+
+/// Install the global subscriber. Call once, before the first run.
+pub fn init(args: &LogArgs) -> Result<()> {
+    let filter = args.filter()?;
+    let (writer, ansi) = match &args.log_file {
+        Some(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("creating log file {}", path.display()))?;
+            (BoxMakeWriter::new(Mutex::new(file)), false)
+        }
+        None => (BoxMakeWriter::new(io::stderr), io::stderr().is_terminal()),
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(ansi)
+        .try_init()
+        .map_err(|e| anyhow::anyhow!("cannot install the log subscriber: {e}"))
+}
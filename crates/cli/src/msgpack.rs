@@ -0,0 +1,44 @@
+//! Binary traces (`--format msgpack`) for high-rate runs, where JSONL serialization dominates.
+//!
+//! The file starts with the four bytes `MAGIC`, followed by records: a little-endian `u32` byte
+//! count, then one MessagePack map. The first record may be the header `{"_meta": {...}}`, as in
+//! JSONL; every other record is a `TraceRow` with the JSONL field names, so the CLI and the GUI
+//! read both encodings with the same `trace::TraceReader`. Dropouts stay `NaN` (MessagePack has no
+//! use for null there).
+
+use std::io::{self, BufRead, Read, Write};
+
+use serde::Serialize;
+
+/// Leading bytes of every binary trace.
+pub const MAGIC: [u8; 4] = *b"RSSB";
+
+/// Append one length-prefixed record.
+pub fn write_record<W: Write>(out: &mut W, value: &impl Serialize) -> io::Result<()> {
+    let bytes = rmp_serde::to_vec_named(value)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let len = u32::try_from(bytes.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "trace record over 4 GiB"))?;
+    out.write_all(&len.to_le_bytes())?;
+    out.write_all(&bytes)
+}
+
+/// Read the next length-prefixed record; None at the end of the trace, an `UnexpectedEof` error
+/// for a record cut off.
+pub fn read_record<R: BufRead + ?Sized>(source: &mut R) -> io::Result<Option<Vec<u8>>> {
+    if source.fill_buf()?.is_empty() {
+        return Ok(None);
+    }
+    let mut len = [0; 4];
+    source.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    let mut record = Vec::with_capacity(len.min(1 << 20));
+    (&mut *source).take(len as u64).read_to_end(&mut record)?;
+    if record.len() < len {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            format!("cut off after {} of {len} bytes", record.len()),
+        ));
+    }
+    Ok(Some(record))
+}
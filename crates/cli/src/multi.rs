@@ -0,0 +1,132 @@
+//! `run --scenario a,b,... --out-dir DIR`: one run per scenario, each writing its own trace (with
+//! its own header) to `DIR/<scenario>.<format>`, then a combined summary table.
+//!
+//! Every run gets the configuration of its scenario, `[scenarios.<name>]` overrides included. A
+//! run that fails (an unwritable file, an invalid override) is reported in the table without
+//! stopping the others; the exit code is the worst over all runs.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use anyhow::{bail, Result};
+use serde::Serialize;
+
+use crate::exit::{self, InvalidInput};
+use crate::summary::SummaryFormat;
+use crate::{list, RunArgs, RunSummary, Scenario};
+
+/// Result of one scenario's run.
+#[derive(Clone, Debug, Serialize)]
+pub struct ScenarioRun {
+    pub scenario: String,
+    pub trace: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<RunSummary>,
+    /// Why the run failed, with its causes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Exit code of this run on its own
+    #[serde(skip)]
+    pub code: u8,
+}
+
+/// Trace file of `scenario` in `dir`: `<scenario>.<format extension>`, plus `.gz` with
+/// `--compress`.
+pub fn trace_path(dir: &Path, scenario: &Scenario, args: &RunArgs) -> PathBuf {
+    let name = scenario.spec(0.0).name;
+    let gz = if args.compress { ".gz" } else { "" };
+    dir.join(format!("{name}.{}{gz}", args.format.extension()))
+}
+
+/// Run every `--scenario` of `args` into `dir`, in the order given.
+pub fn run_all(args: &RunArgs, dir: &Path) -> Result<Vec<ScenarioRun>> {
+    let scenarios = if args.scenario.is_empty() {
+        vec![args.sim_config()?.run.scenario]
+    } else {
+        args.scenario.clone()
+    };
+    let mut runs = Vec::new();
+    for scenario in scenarios {
+        let name = scenario.spec(0.0).name;
+        let trace = trace_path(dir, &scenario, args);
+        let run_args = RunArgs {
+            scenario: vec![scenario],
+            out_dir: None,
+            ..args.clone()
+        };
+        let (summary, error, code) =
+            match crate::run_to_file(&run_args, &trace, &AtomicBool::new(false)) {
+                Ok(summary) => {
+                    let code = if args.fail_on_trip && summary.tripped() {
+                        exit::FAILED_CHECK
+                    } else {
+                        exit::OK
+                    };
+                    (Some(summary), None, code)
+                }
+                Err(e) => {
+                    tracing::warn!(scenario = %name, "run failed: {e:#}");
+                    (None, Some(format!("{e:#}")), exit::code_for(&e))
+                }
+            };
+        runs.push(ScenarioRun {
+            scenario: name,
+            trace,
+            summary,
+            error,
+            code,
+        });
+    }
+    Ok(runs)
+}
+
+/// The combined summary: one row per scenario (`Text`) or one JSON object per line (`Json`).
+pub fn write_summary(
+    runs: &[ScenarioRun],
+    format: SummaryFormat,
+    out: &mut dyn Write,
+) -> Result<()> {
+    match format {
+        SummaryFormat::None => {}
+        SummaryFormat::Json => {
+            for run in runs {
+                writeln!(out, "{}", serde_json::to_string(run)?)?;
+            }
+        }
+        SummaryFormat::Text => {
+            writeln!(out, "--- scenario summary ---")?;
+            writeln!(out, "{:<16} {:<36} trace", "scenario", "outcome")?;
+            for run in runs {
+                let outcome = match (&run.summary, &run.error) {
+                    (Some(summary), _) => list::outcome(summary),
+                    (None, error) => format!("failed: {}", error.as_deref().unwrap_or("")),
+                };
+                writeln!(
+                    out,
+                    "{:<16} {outcome:<36} {}",
+                    run.scenario,
+                    run.trace.display()
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn execute(args: &RunArgs, dir: &Path, stdout: &mut dyn Write) -> Result<u8> {
+    let flags: Vec<&str> = args
+        .single_run_flags()
+        .into_iter()
+        .filter(|flag| *flag != "--out-dir")
+        .collect();
+    if !flags.is_empty() {
+        bail!(InvalidInput(format!(
+            "--out-dir runs the scenarios one after another and does not support {}",
+            flags.join(", ")
+        )));
+    }
+    let runs = run_all(args, dir)?;
+    write_summary(&runs, args.summary, stdout)?;
+    Ok(runs.iter().map(|run| run.code).fold(exit::OK, exit::worst))
+}
@@ -0,0 +1,145 @@
+//! Single run parameters that `search` and `sweep` vary by name.
+
+use std::fmt;
+use std::io;
+use std::sync::atomic::AtomicBool;
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use sim::{EventAction, ScenarioSpec};
+
+use crate::config::SimConfig;
+use crate::exit::InvalidInput;
+use crate::summary::SummaryFormat;
+use crate::trace::TraceFormat;
+use crate::{scenario_spec, simulate, RunArgs, RunSummary};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum Param {
+    /// Coolant fraction the scenario's coolant events drop to (loss-of-cooling: 0.05)
+    CoolantAfterLoss,
+    /// Coolant fraction at the start of the run
+    InitialCoolant,
+    /// Controller setpoint (°C)
+    Setpoint,
+    /// Safety trip temperature (°C)
+    TripTemp,
+    /// Sensor spread (°C) that trips
+    MaxSensorDelta,
+    /// Sensor spread (°C) that raises the disagreement alarm
+    AlarmSensorDelta,
+    Kp,
+    Ki,
+    Kd,
+    /// PID output limit
+    OutMax,
+    PlantThermalMass,
+    PlantKCool,
+    PlantKPower,
+    /// Plant ambient temperature (°C)
+    PlantAmbient,
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = self.to_possible_value().expect("no skipped variants");
+        f.write_str(value.get_name())
+    }
+}
+
+impl Param {
+    /// The value a run with `cfg` and `spec` uses (`None` for `coolant-after-loss` when the
+    /// scenario has no coolant event).
+    pub fn baseline(self, cfg: &SimConfig, spec: &ScenarioSpec) -> Option<f64> {
+        Some(match self {
+            Param::CoolantAfterLoss => {
+                return spec.sorted_events().iter().find_map(|e| match e.action {
+                    EventAction::SetCoolant(coolant) => Some(coolant),
+                    _ => None,
+                })
+            }
+            Param::InitialCoolant => spec.initial.coolant,
+            Param::Setpoint => cfg.run.setpoint,
+            Param::TripTemp => cfg.safety.trip_temp_c,
+            Param::MaxSensorDelta => cfg.safety.max_sensor_delta_c,
+            Param::AlarmSensorDelta => cfg.safety.alarm_sensor_delta_c,
+            Param::Kp => cfg.pid.kp,
+            Param::Ki => cfg.pid.ki,
+            Param::Kd => cfg.pid.kd,
+            Param::OutMax => cfg.pid.out_max,
+            Param::PlantThermalMass => cfg.plant.thermal_mass,
+            Param::PlantKCool => cfg.plant.k_cool,
+            Param::PlantKPower => cfg.plant.k_power,
+            Param::PlantAmbient => cfg.plant.ambient_c,
+        })
+    }
+}
+
+/// Run `run` headless with each parameter in `values` replaced.
+pub fn run_with(run: &RunArgs, values: &[(Param, f64)]) -> Result<RunSummary> {
+    let mut run = RunArgs {
+        format: TraceFormat::Csv,
+        summary: SummaryFormat::None,
+        decimate: None,
+        compress: false,
+        quiet: true,
+        ..run.clone()
+    };
+    for &(param, value) in values {
+        let flag = match param {
+            Param::Setpoint => &mut run.setpoint,
+            Param::TripTemp => &mut run.trip_temp,
+            Param::Kp => &mut run.kp,
+            Param::Ki => &mut run.ki,
+            Param::Kd => &mut run.kd,
+            Param::OutMax => &mut run.out_max,
+            Param::PlantThermalMass => &mut run.plant_thermal_mass,
+            Param::PlantKCool => &mut run.plant_k_cool,
+            Param::PlantKPower => &mut run.plant_k_power,
+            Param::PlantAmbient => &mut run.plant_ambient,
+            _ => continue,
+        };
+        *flag = Some(value);
+    }
+    let mut cfg = run.sim_config()?;
+    let mut spec = scenario_spec(&run, &cfg)?;
+    for &(param, value) in values {
+        match param {
+            Param::CoolantAfterLoss => {
+                let mut found = false;
+                for event in &mut spec.events {
+                    if let EventAction::SetCoolant(coolant) = &mut event.action {
+                        *coolant = value;
+                        found = true;
+                    }
+                }
+                if !found {
+                    bail!(InvalidInput(format!(
+                        "scenario {:?} has no coolant event for coolant-after-loss to change",
+                        spec.name
+                    )));
+                }
+            }
+            Param::InitialCoolant => spec.initial.coolant = value,
+            Param::MaxSensorDelta => cfg.safety.max_sensor_delta_c = value,
+            Param::AlarmSensorDelta => cfg.safety.alarm_sensor_delta_c = value,
+            _ => {}
+        }
+    }
+    let mut msgs: Vec<String> = match spec.validate() {
+        Ok(()) => Vec::new(),
+        Err(errs) => errs.iter().map(|e| e.to_string()).collect(),
+    };
+    if let Err(errs) = cfg.safety.validate() {
+        msgs.extend(errs.iter().map(|e| e.to_string()));
+    }
+    if !msgs.is_empty() {
+        let at: Vec<String> = values.iter().map(|(p, v)| format!("{p} = {v}")).collect();
+        bail!(InvalidInput(format!(
+            "{}: {}",
+            at.join(", "),
+            msgs.join("; ")
+        )));
+    }
+    simulate(&run, cfg, spec, &mut io::sink(), &AtomicBool::new(false))
+}
@@ -0,0 +1,90 @@
+//! Progress reports on stderr for long runs, batches and sweeps.
+//!
+//! On a terminal the report is one line redrawn in place; otherwise (a pipe, a CI log) each
+//! report is a plain line, so logs contain no control characters. Reports are at least
+//! `--progress-every` seconds of wall-clock time apart, so short runs print nothing. `--quiet`
+//! turns them off. Nothing is ever written to stdout, which may carry the trace.
+
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+use crate::RunArgs;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    Off,
+    /// Redraw one line with `\r`
+    Terminal,
+    /// One plain line per report
+    Lines,
+}
+
+#[derive(Debug)]
+pub struct Progress {
+    label: &'static str,
+    mode: Mode,
+    every: Duration,
+    started: Instant,
+    last: Option<Instant>,
+}
+
+impl Progress {
+    /// Reports prefixed with `label`, as `args.quiet` and `args.progress_every` select.
+    pub fn new(label: &'static str, args: &RunArgs) -> Self {
+        let mode = if args.quiet {
+            Mode::Off
+        } else if io::stderr().is_terminal() {
+            Mode::Terminal
+        } else {
+            Mode::Lines
+        };
+        Self {
+            label,
+            mode,
+            every: Duration::from_secs_f64(args.progress_every.max(0.0)),
+            started: Instant::now(),
+            last: None,
+        }
+    }
+
+    /// Wall-clock time since the progress started.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Report `message()` if the last report is at least the interval ago.
+    pub fn update(&mut self, message: impl FnOnce() -> String) {
+        if self.mode == Mode::Off {
+            return;
+        }
+        let now = Instant::now();
+        let since = now - self.last.unwrap_or(self.started);
+        if since < self.every {
+            return;
+        }
+        self.last = Some(now);
+        self.write(&message(), false);
+    }
+
+    /// End the report: a last line with `message` if given, else clear a redrawn line.
+    pub fn finish(&mut self, message: Option<String>) {
+        match (self.mode, message) {
+            (Mode::Off, _) => {}
+            (_, Some(message)) => self.write(&message, true),
+            (Mode::Terminal, None) if self.last.is_some() => {
+                let _ = write!(io::stderr().lock(), "\r\x1b[K");
+            }
+            _ => {}
+        }
+    }
+
+    fn write(&self, message: &str, last: bool) {
+        let mut err = io::stderr().lock();
+        let _ = match self.mode {
+            Mode::Off => Ok(()),
+            Mode::Terminal if last => writeln!(err, "\r{}: {message}\x1b[K", self.label),
+            Mode::Terminal => write!(err, "\r{}: {message}\x1b[K", self.label),
+            Mode::Lines => writeln!(err, "{}: {message}", self.label),
+        };
+    }
+}
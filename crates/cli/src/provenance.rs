@@ -0,0 +1,73 @@
+//! Provenance of a trace: the command line, crate versions, build commit and host that wrote it,
+//! recorded in the header so an old trace can be traced back to the build that produced it.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Commit the CLI was built from (see `build.rs`), "unknown" without git.
+pub const GIT_HASH: &str = env!("GIT_HASH");
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// Command line of the process, program name first
+    pub argv: Vec<String>,
+    /// Version of each crate of the simulator, by crate name
+    pub versions: BTreeMap<String, String>,
+    pub git_hash: String,
+    pub os: String,
+    pub arch: String,
+}
+
+impl Provenance {
+    /// The provenance of traces written by this process.
+    pub fn current() -> Self {
+        Self {
+            argv: env::args_os()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            versions: [
+                ("cli", env!("CARGO_PKG_VERSION")),
+                ("sim", sim::VERSION),
+                ("safety", safety::VERSION),
+                ("controller", controller::VERSION),
+                ("engine", engine::VERSION),
+                ("trace", ::trace::VERSION),
+            ]
+            .into_iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect(),
+            git_hash: GIT_HASH.to_string(),
+            os: env::consts::OS.to_string(),
+            arch: env::consts::ARCH.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Arguments with spaces (or empty ones) are quoted so the line can be pasted into a shell
+        let argv: Vec<String> = self
+            .argv
+            .iter()
+            .map(|arg| {
+                if arg.is_empty() || arg.contains(char::is_whitespace) {
+                    format!("'{arg}'")
+                } else {
+                    arg.clone()
+                }
+            })
+            .collect();
+        let versions: Vec<String> = self
+            .versions
+            .iter()
+            .map(|(name, version)| format!("{name} {version}"))
+            .collect();
+        writeln!(f, "argv         {}", argv.join(" "))?;
+        writeln!(f, "versions     {}", versions.join(", "))?;
+        writeln!(f, "git commit   {}", self.git_hash)?;
+        write!(f, "host         {}/{}", self.os, self.arch)
+    }
+}
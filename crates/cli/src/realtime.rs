@@ -0,0 +1,62 @@
+//! `--realtime`: pace a run against the wall clock, and stop it cleanly on Ctrl-C.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Set by the Ctrl-C handler; `run_command` passes it to `run_until` as the stop flag.
+pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// What the Ctrl-C handler does: ask the current run to stop after the step in progress, so the
+/// trace is flushed and the summary written.
+pub fn on_interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Lag (wall-clock) beyond which the pacer warns that it cannot keep up.
+const LAG_WARNING: Duration = Duration::from_millis(100);
+
+/// Sleeps so that simulated time advances at `speed` × wall-clock time.
+#[derive(Debug)]
+pub struct Pacer {
+    start: Instant,
+    /// Simulated time due at `start` (non-zero for a resumed run)
+    origin_s: f64,
+    speed: f64,
+    warned: bool,
+}
+
+impl Pacer {
+    pub fn new(speed: f64) -> Self {
+        Self {
+            start: Instant::now(),
+            origin_s: 0.0,
+            speed,
+            warned: false,
+        }
+    }
+
+    /// Pace a run whose simulated time starts at `sim_t_s` rather than 0.
+    pub fn starting_at(mut self, sim_t_s: f64) -> Self {
+        self.origin_s = sim_t_s;
+        self
+    }
+
+    /// Wait until `sim_t_s` simulated seconds are due. The deadline is absolute, so time spent
+    /// computing the step is taken off the sleep.
+    pub fn wait_until(&mut self, sim_t_s: f64) {
+        let due = self.start + Duration::from_secs_f64((sim_t_s - self.origin_s) / self.speed);
+        let now = Instant::now();
+        if let Some(remaining) = due.checked_duration_since(now) {
+            thread::sleep(remaining);
+        } else if !self.warned && now - due > LAG_WARNING {
+            self.warned = true;
+            eprintln!(
+                "warning: --realtime {} cannot keep up (behind by {:.2} s at t = {sim_t_s:.2} s); \
+                 the run continues as fast as possible",
+                self.speed,
+                (now - due).as_secs_f64()
+            );
+        }
+    }
+}
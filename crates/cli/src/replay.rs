@@ -0,0 +1,138 @@
+//! `replay`: feed the sensor columns of a recorded trace back through the safety logic and check
+//! that it reaches the same trip decisions, e.g. after changing `safety::evaluate`.
+
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use safety::{SafetyConfig, SafetyState};
+
+use crate::config::SimConfig;
+use crate::exit;
+use crate::trace::{read_trace, TraceRow};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct ReplayArgs {
+    /// Recorded trace (.jsonl, .csv or .msgpack)
+    pub trace: PathBuf,
+
+    /// Simulation description the trace was recorded with (for the [safety] section)
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Trip temperature (°C) the trace was recorded with [default: 420]
+    #[arg(long)]
+    pub trip_temp: Option<f64>,
+}
+
+/// Trip state of one trace row: latched flag and reason.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TripState {
+    pub scram: bool,
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Mismatch {
+    pub t_s: f64,
+    pub recorded: TripState,
+    pub replayed: TripState,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplayReport {
+    pub rows: usize,
+    pub mismatches: usize,
+    pub first_mismatch: Option<Mismatch>,
+    /// Time and reason of the first replayed trip
+    pub trip: Option<(f64, String)>,
+}
+
+impl ReplayReport {
+    pub fn matches(&self) -> bool {
+        self.mismatches == 0
+    }
+}
+
+impl fmt::Display for TripState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.scram, &self.reason) {
+            (false, _) => write!(f, "no scram"),
+            (true, Some(reason)) => write!(f, "scram {reason}"),
+            (true, None) => write!(f, "scram"),
+        }
+    }
+}
+
+impl fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "replayed {} rows: ", self.rows)?;
+        match &self.first_mismatch {
+            None => write!(f, "trip decisions match")?,
+            Some(m) => write!(
+                f,
+                "{} mismatches, first at t = {:.2} s (recorded {}, replayed {})",
+                self.mismatches, m.t_s, m.recorded, m.replayed
+            )?,
+        }
+        match &self.trip {
+            Some((t_s, reason)) => write!(f, "; replayed trip at t = {t_s:.2} s ({reason})"),
+            None => write!(f, "; no replayed trip"),
+        }
+    }
+}
+
+/// Re-evaluate `cfg` over the rows' sensor readings, comparing against the recorded trip state.
+pub fn replay(rows: &[TraceRow], cfg: &SafetyConfig) -> ReplayReport {
+    let mut state = SafetyState::default();
+    let mut report = ReplayReport {
+        rows: rows.len(),
+        ..Default::default()
+    };
+    for row in rows {
+        // Operator trips are inputs, not decisions of the safety logic
+        if row.reason_name().as_deref() == Some("Manual") {
+            safety::manual_scram(&mut state);
+        }
+        safety::evaluate(cfg, &mut state, [row.s1_c, row.s2_c, row.s3_c]);
+        let replayed = TripState {
+            scram: state.scram,
+            reason: state.reason.map(|r| format!("{r:?}")),
+        };
+        if let (None, true, Some(reason)) = (&report.trip, replayed.scram, &replayed.reason) {
+            report.trip = Some((row.t_s, reason.clone()));
+        }
+        let recorded = TripState {
+            scram: row.scram,
+            reason: row.reason_name(),
+        };
+        if recorded != replayed {
+            report.mismatches += 1;
+            report.first_mismatch.get_or_insert(Mismatch {
+                t_s: row.t_s,
+                recorded,
+                replayed,
+            });
+        }
+    }
+    report
+}
+
+pub fn execute(args: &ReplayArgs, stdout: &mut dyn Write) -> Result<u8> {
+    let mut cfg = match &args.config {
+        Some(path) => SimConfig::load(path)?,
+        None => SimConfig::default(),
+    };
+    if let Some(trip_temp) = args.trip_temp {
+        cfg.safety.trip_temp_c = trip_temp;
+    }
+    let rows = read_trace(&args.trace)?;
+    let report = replay(&rows, &cfg.safety);
+    writeln!(stdout, "{report}")?;
+    Ok(if report.matches() {
+        exit::OK
+    } else {
+        exit::FAILED_CHECK
+    })
+}
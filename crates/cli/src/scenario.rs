@@ -0,0 +1,93 @@
+//! Scenario files loaded with `--scenario-file`: a `sim::ScenarioSpec` written as TOML, or as
+//! YAML when the file ends in `.yaml`/`.yml`. See `examples/` for the built-in scenarios in this
+//! form.
+
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use sim::{EventAction, FaultSchedule, InitialConditions, ScenarioSpec, SensorFault, TimedEvent};
+
+use crate::exit::InvalidInput;
+use crate::Scenario;
+
+/// Read, parse and validate a scenario file. Syntax and schema errors carry the line they were
+/// found on; value errors name the offending field.
+pub fn load(path: &Path) -> Result<ScenarioSpec> {
+    let spec = read(path)?;
+    if let Err(errs) = spec.validate() {
+        let msgs: Vec<String> = errs.iter().map(|e| e.to_string()).collect();
+        return Err(InvalidInput(format!(
+            "in scenario file {}: {}",
+            path.display(),
+            msgs.join("; ")
+        ))
+        .into());
+    }
+    Ok(spec)
+}
+
+/// Read and parse a scenario file without checking its values.
+pub fn read(path: &Path) -> Result<ScenarioSpec> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("reading scenario file {}", path.display()))?;
+    let yaml = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"));
+    let parsed = if yaml {
+        serde_yaml::from_str::<ScenarioSpec>(&text).map_err(|e| e.to_string())
+    } else {
+        toml::from_str::<ScenarioSpec>(&text).map_err(|e| e.to_string())
+    };
+    Ok(parsed.map_err(|e| {
+        InvalidInput(format!(
+            "in scenario file {}: {}",
+            path.display(),
+            e.trim_end()
+        ))
+    })?)
+}
+
+impl Scenario {
+    /// The built-in scenario as a spec, for a run of `seconds`.
+    pub fn spec(&self, seconds: f64) -> ScenarioSpec {
+        let initial = |coolant| InitialConditions {
+            coolant,
+            ..Default::default()
+        };
+        match self {
+            Scenario::Normal => ScenarioSpec {
+                name: "normal".into(),
+                description: "Nominal coolant flow; the controller holds the setpoint".into(),
+                initial: initial(0.6),
+                ..Default::default()
+            },
+            Scenario::Overheat => ScenarioSpec {
+                name: "overheat".into(),
+                description: "Undercooled plant heating towards the trip temperature".into(),
+                initial: initial(0.2),
+                ..Default::default()
+            },
+            Scenario::LossOfCooling => ScenarioSpec {
+                name: "loss-of-cooling".into(),
+                description: "Coolant flow collapses 30 % into the run".into(),
+                initial: initial(0.7),
+                events: vec![TimedEvent {
+                    // strictly after 30 % of the run, matching the GUI
+                    t_s: (seconds * 0.3).next_up(),
+                    action: EventAction::SetCoolant(0.05),
+                }],
+                ..Default::default()
+            },
+            Scenario::SensorDisagree => {
+                let mut spec = ScenarioSpec {
+                    name: "sensor-disagree".into(),
+                    description: "Sensor 2 biased by +20 °C".into(),
+                    initial: initial(0.6),
+                    ..Default::default()
+                };
+                spec.sensors.s2 = FaultSchedule::always(SensorFault::Bias { value: 20.0 });
+                spec
+            }
+        }
+    }
+}
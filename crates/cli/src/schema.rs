@@ -0,0 +1,15 @@
+//! `schema`: the JSON Schema (draft 2020-12) of a JSONL trace line, for consumers of traces that
+//! would otherwise guess field names and types. It is maintained by hand next to `TraceRow` and
+//! `TraceMeta`; the `schema` tests validate real traces against it, so the two cannot drift apart.
+
+use std::io::Write;
+
+use anyhow::Result;
+
+/// JSON Schema of one trace line: the `{"_meta": ...}` header or a row.
+pub const TRACE_JSON_SCHEMA: &str = include_str!("trace.schema.json");
+
+pub fn execute(stdout: &mut dyn Write) -> Result<()> {
+    stdout.write_all(TRACE_JSON_SCHEMA.as_bytes())?;
+    Ok(())
+}
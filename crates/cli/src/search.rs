@@ -0,0 +1,222 @@
+//! `search`: bisect one parameter of a scenario for the boundary between tripping and not
+//! tripping, e.g. the smallest residual coolant flow that still avoids a SCRAM.
+//!
+//! The range is first probed at `--probes` evenly spaced values. The outcome has to flip exactly
+//! once over them, otherwise the search stops and reports the probes instead of bisecting towards
+//! an arbitrary one of several boundaries. The bracketing pair is then bisected until it is
+//! narrower than `--tolerance`. Every run uses the same seed, so the outcome is a function of the
+//! parameter alone.
+
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+
+use crate::exit::{self, InvalidInput};
+use crate::list::outcome;
+use crate::param::{self, Param};
+use crate::{RunArgs, RunSummary};
+
+/// Closed interval `LO..HI` searched over.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParamRange {
+    pub lo: f64,
+    pub hi: f64,
+}
+
+impl FromStr for ParamRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (lo, hi) = s
+            .split_once("..")
+            .ok_or_else(|| format!("expected LO..HI, got {s:?}"))?;
+        let num = |t: &str| {
+            t.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| format!("invalid bound {t:?}"))
+        };
+        let range = ParamRange {
+            lo: num(lo)?,
+            hi: num(hi.trim_start_matches('='))?,
+        };
+        if range.lo >= range.hi {
+            return Err(format!("{s:?} is empty; LO must be below HI"));
+        }
+        Ok(range)
+    }
+}
+
+impl fmt::Display for ParamRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.lo, self.hi)
+    }
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct SearchArgs {
+    #[arg(value_enum, long)]
+    pub param: Param,
+
+    /// Values searched, LO..HI (both ends included)
+    #[arg(long, value_name = "LO..HI")]
+    pub range: ParamRange,
+
+    /// Stop once the bracketing values are at most this far apart
+    #[arg(long, default_value_t = 0.001)]
+    pub tolerance: f64,
+
+    /// Evenly spaced values run before bisecting, to check the outcome flips only once
+    #[arg(long, default_value_t = 9)]
+    pub probes: usize,
+
+    /// Scenario and settings of every run (defaults unless given); --param replaces one value
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+/// One headless run at `value`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Probe {
+    pub value: f64,
+    pub summary: RunSummary,
+}
+
+impl Probe {
+    pub fn tripped(&self) -> bool {
+        self.summary.tripped()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    /// The evenly spaced runs, in order
+    pub probes: Vec<Probe>,
+    /// Runs on either side of the boundary, at most `--tolerance` apart; `None` if the outcome
+    /// flips more than once over the probes
+    pub bracket: Option<(Probe, Probe)>,
+    /// Runs made, probes included
+    pub runs: usize,
+}
+
+/// Run `args.run` with `args.param` set to `value`.
+pub fn run_at(args: &SearchArgs, value: f64) -> Result<Probe> {
+    let summary = param::run_with(&args.run, &[(args.param, value)])?;
+    Ok(Probe { value, summary })
+}
+
+/// Probe the range, then bisect the bracketing pair down to the tolerance.
+pub fn search(args: &SearchArgs) -> Result<SearchResult> {
+    if !(args.tolerance.is_finite() && args.tolerance > 0.0) {
+        bail!(InvalidInput("--tolerance must be a number > 0".into()));
+    }
+    if args.probes < 2 {
+        bail!(InvalidInput("--probes must be at least 2".into()));
+    }
+    let ParamRange { lo, hi } = args.range;
+    let last = (args.probes - 1) as f64;
+    let probes = (0..args.probes)
+        .map(|i| run_at(args, lo + (hi - lo) * i as f64 / last))
+        .collect::<Result<Vec<_>>>()?;
+    let flips: Vec<usize> = (1..probes.len())
+        .filter(|&i| probes[i].tripped() != probes[i - 1].tripped())
+        .collect();
+    let mut runs = probes.len();
+    let (mut low, mut high) = match flips[..] {
+        [i] => (probes[i - 1].clone(), probes[i].clone()),
+        [] => bail!(InvalidInput(format!(
+            "the scenario {} everywhere in {} = {}; widen --range",
+            if probes[0].tripped() {
+                "trips"
+            } else {
+                "does not trip"
+            },
+            args.param,
+            args.range
+        ))),
+        _ => {
+            return Ok(SearchResult {
+                probes,
+                bracket: None,
+                runs,
+            })
+        }
+    };
+    while high.value - low.value > args.tolerance {
+        let mid = run_at(args, low.value + (high.value - low.value) / 2.0)?;
+        runs += 1;
+        if mid.tripped() == low.tripped() {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    Ok(SearchResult {
+        probes,
+        bracket: Some((low, high)),
+        runs,
+    })
+}
+
+pub fn execute(args: &SearchArgs, stdout: &mut dyn Write) -> Result<u8> {
+    let flags = args.run.single_run_flags();
+    if !flags.is_empty() {
+        bail!(InvalidInput(format!(
+            "search runs headless; drop {}",
+            flags.join(", ")
+        )));
+    }
+    if args.param == Param::Setpoint && args.run.setpoint_profile.is_some() {
+        bail!(InvalidInput(
+            "--param setpoint replaces a constant setpoint; drop --setpoint-profile".into()
+        ));
+    }
+    // Enough decimals to tell the bracketing values apart.
+    let digits = (-args.tolerance.log10()).ceil().max(0.0) as usize + 1;
+    writeln!(stdout, "parameter         {}", args.param)?;
+    writeln!(
+        stdout,
+        "range             {} ({} probes, tolerance {})",
+        args.range, args.probes, args.tolerance
+    )?;
+    let result = search(args)?;
+    let Some((low, high)) = result.bracket else {
+        writeln!(
+            stdout,
+            "NON-MONOTONIC: the outcome flips more than once over the probes; narrow --range or \
+             add --probes to locate each boundary"
+        )?;
+        for probe in &result.probes {
+            writeln!(
+                stdout,
+                "  {:>12.digits$}  {}",
+                probe.value,
+                outcome(&probe.summary)
+            )?;
+        }
+        return Ok(exit::FAILED_CHECK);
+    };
+    writeln!(stdout, "runs              {}", result.runs)?;
+    writeln!(
+        stdout,
+        "boundary          {:.digits$} (between {:.digits$} and {:.digits$})",
+        (low.value + high.value) / 2.0,
+        low.value,
+        high.value
+    )?;
+    for probe in [&low, &high] {
+        writeln!(stdout)?;
+        writeln!(
+            stdout,
+            "--- {} = {:.digits$}: {} ---",
+            args.param,
+            probe.value,
+            outcome(&probe.summary)
+        )?;
+        write!(stdout, "{}", probe.summary)?;
+    }
+    Ok(exit::OK)
+}
@@ -0,0 +1,341 @@
+//! `stats`: merge the outputs of several `batch` campaigns and report per scenario.
+//!
+//! Each batch directory holds `runs.csv` (one line per seed) and `aggregate.json`, whose `schema`
+//! and `scenario` fields say how to read it. Batches from before those fields were written are
+//! read as schema 1 under the scenario "unknown". Runs are grouped by scenario; the extremes name
+//! the seed and batch directory, so the interesting run can be re-created with `run --seed`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+
+use crate::batch::{wilson_interval, BATCH_SCHEMA, Z_95};
+use crate::exit::{self, InvalidInput};
+use crate::open_output;
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct StatsArgs {
+    /// Batch output directories (searched recursively for runs.csv) or runs.csv files
+    #[arg(required = true)]
+    pub paths: Vec<PathBuf>,
+
+    /// Bins of the trip-time histogram
+    #[arg(long, default_value_t = 10)]
+    pub bins: usize,
+
+    /// Also write the histograms as CSV (scenario, bin edges, count)
+    #[arg(long, value_name = "FILE")]
+    pub histogram_csv: Option<PathBuf>,
+
+    /// Overwrite an existing --histogram-csv file
+    #[arg(long, requires = "histogram_csv")]
+    pub force: bool,
+}
+
+/// One line of a batch's `runs.csv`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchRun {
+    pub seed: u64,
+    pub tripped: bool,
+    pub reason: Option<String>,
+    pub t_trip: Option<f64>,
+    pub max_temp: f64,
+    pub min_margin: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Batch {
+    /// Directory holding `runs.csv`
+    pub dir: PathBuf,
+    pub schema: u32,
+    pub scenario: String,
+    pub runs: Vec<BatchRun>,
+}
+
+/// Read the batch whose `runs.csv` is at `path`, with the sibling `aggregate.json` if present.
+pub fn read_batch(path: &Path) -> Result<Batch> {
+    let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let aggregate = dir.join("aggregate.json");
+    let (schema, scenario) = if aggregate.exists() {
+        let text = fs::read_to_string(&aggregate)
+            .with_context(|| format!("reading {}", aggregate.display()))?;
+        let json: serde_json::Value = serde_json::from_str(&text)
+            .map_err(|e| InvalidInput(format!("{} is not valid JSON: {e}", aggregate.display())))?;
+        let schema = json.get("schema").and_then(|v| v.as_u64()).unwrap_or(1);
+        if schema > u64::from(BATCH_SCHEMA) {
+            bail!(InvalidInput(format!(
+                "{} has batch schema {schema}, newer than this build supports (up to \
+                 {BATCH_SCHEMA}); upgrade reactor-safety-sim to read it",
+                aggregate.display()
+            )));
+        }
+        let scenario = json.get("scenario").and_then(|v| v.as_str());
+        (schema as u32, scenario.unwrap_or("unknown").to_string())
+    } else {
+        (1, "unknown".to_string())
+    };
+
+    let text = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+    let mut lines = text.lines();
+    let header: Vec<&str> = lines.next().unwrap_or("").split(',').collect();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| *h == name)
+            .ok_or_else(|| InvalidInput(format!("{} has no {name} column", path.display())))
+    };
+    let (seed, tripped, t_trip) = (column("seed")?, column("tripped")?, column("t_trip")?);
+    let (max_temp, min_margin) = (column("max_temp")?, column("min_margin")?);
+    let reason = column("reason").ok();
+
+    let mut runs = Vec::new();
+    for (n, line) in lines.enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+        let cells: Vec<&str> = line.split(',').collect();
+        let bad = || InvalidInput(format!("{} line {}: malformed row", path.display(), n + 2));
+        let cell = |i: usize| cells.get(i).copied().ok_or_else(bad);
+        let num = |i: usize| cell(i)?.parse::<f64>().map_err(|_| bad());
+        let opt = |i: usize| -> Result<Option<String>, InvalidInput> {
+            Ok(Some(cell(i)?).filter(|c| !c.is_empty()).map(str::to_owned))
+        };
+        runs.push(BatchRun {
+            seed: cell(seed)?.parse().map_err(|_| bad())?,
+            tripped: cell(tripped)?.parse().map_err(|_| bad())?,
+            reason: match reason {
+                Some(i) => opt(i)?,
+                None => None,
+            },
+            t_trip: match opt(t_trip)? {
+                Some(t) => Some(t.parse().map_err(|_| bad())?),
+                None => None,
+            },
+            max_temp: num(max_temp)?,
+            min_margin: num(min_margin)?,
+        });
+    }
+    Ok(Batch {
+        dir,
+        schema,
+        scenario,
+        runs,
+    })
+}
+
+/// The `runs.csv` files named by or below `paths`, sorted.
+pub fn find_batches(paths: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    fn walk(dir: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+        let entries = fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))?;
+        for entry in entries {
+            let path = entry?.path();
+            if path.is_dir() {
+                walk(&path, found)?;
+            } else if path.file_name().is_some_and(|n| n == "runs.csv") {
+                found.push(path);
+            }
+        }
+        Ok(())
+    }
+    let mut found = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            walk(path, &mut found)?;
+        } else if path.exists() {
+            found.push(path.clone());
+        } else {
+            bail!(InvalidInput(format!("{} does not exist", path.display())));
+        }
+    }
+    found.sort();
+    found.dedup();
+    if found.is_empty() {
+        bail!(InvalidInput(
+            "no runs.csv found under the given paths".into()
+        ));
+    }
+    Ok(found)
+}
+
+/// A run picked out by `stats`, with where to find it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Extreme {
+    pub value: f64,
+    pub seed: u64,
+    pub batch: PathBuf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bin {
+    pub lo: f64,
+    pub hi: f64,
+    pub count: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScenarioStats {
+    pub scenario: String,
+    pub batches: usize,
+    pub runs: usize,
+    pub trips: usize,
+    pub trip_probability: f64,
+    pub trip_probability_ci95: (f64, f64),
+    pub earliest_trip: Option<Extreme>,
+    pub latest_trip: Option<Extreme>,
+    pub hottest: Extreme,
+    pub smallest_margin: Extreme,
+    pub trip_time_histogram: Vec<Bin>,
+}
+
+/// `bins` equal-width bins over the range of `values` (one bin if they are all equal).
+pub fn histogram(values: &[f64], bins: usize) -> Vec<Bin> {
+    let (Some(lo), Some(hi)) = (
+        values.iter().copied().reduce(f64::min),
+        values.iter().copied().reduce(f64::max),
+    ) else {
+        return Vec::new();
+    };
+    let bins = if hi > lo { bins.max(1) } else { 1 };
+    let width = (hi - lo) / bins as f64;
+    let mut out: Vec<Bin> = (0..bins)
+        .map(|i| Bin {
+            lo: lo + width * i as f64,
+            hi: if i + 1 == bins {
+                hi
+            } else {
+                lo + width * (i + 1) as f64
+            },
+            count: 0,
+        })
+        .collect();
+    for &v in values {
+        let i = if width > 0.0 {
+            (((v - lo) / width) as usize).min(bins - 1)
+        } else {
+            0
+        };
+        out[i].count += 1;
+    }
+    out
+}
+
+/// Merge `batches` by scenario.
+pub fn aggregate(batches: &[Batch], bins: usize) -> Vec<ScenarioStats> {
+    let mut by_scenario: BTreeMap<&str, Vec<&Batch>> = BTreeMap::new();
+    for batch in batches {
+        by_scenario.entry(&batch.scenario).or_default().push(batch);
+    }
+    by_scenario
+        .into_iter()
+        .filter_map(|(scenario, batches)| {
+            let runs: Vec<(&BatchRun, &Path)> = batches
+                .iter()
+                .flat_map(|b| b.runs.iter().map(move |r| (r, b.dir.as_path())))
+                .collect();
+            let pick = |value: &dyn Fn(&BatchRun) -> Option<f64>, max: bool| {
+                runs.iter()
+                    .filter_map(|&(r, dir)| value(r).map(|v| (v, r, dir)))
+                    .reduce(|a, b| {
+                        let better = if max { b.0 > a.0 } else { b.0 < a.0 };
+                        if better {
+                            b
+                        } else {
+                            a
+                        }
+                    })
+                    .map(|(value, r, dir)| Extreme {
+                        value,
+                        seed: r.seed,
+                        batch: dir.to_path_buf(),
+                    })
+            };
+            let trip_time = |r: &BatchRun| r.t_trip.filter(|_| r.tripped);
+            let trip_times: Vec<f64> = runs.iter().filter_map(|(r, _)| trip_time(r)).collect();
+            let trips = runs.iter().filter(|(r, _)| r.tripped).count();
+            Some(ScenarioStats {
+                scenario: scenario.to_string(),
+                batches: batches.len(),
+                runs: runs.len(),
+                trips,
+                trip_probability: trips as f64 / runs.len() as f64,
+                trip_probability_ci95: wilson_interval(trips, runs.len(), Z_95),
+                earliest_trip: pick(&trip_time, false),
+                latest_trip: pick(&trip_time, true),
+                hottest: pick(&|r| Some(r.max_temp), true)?,
+                smallest_margin: pick(&|r| Some(r.min_margin), false)?,
+                trip_time_histogram: histogram(&trip_times, bins),
+            })
+        })
+        .collect()
+}
+
+fn write_stats(s: &ScenarioStats, out: &mut dyn Write) -> Result<()> {
+    let batches = if s.batches == 1 { "batch" } else { "batches" };
+    writeln!(
+        out,
+        "{}: {} runs in {} {batches}",
+        s.scenario, s.runs, s.batches
+    )?;
+    let (lo, hi) = s.trip_probability_ci95;
+    writeln!(
+        out,
+        "  trips            {} (p = {:.3}, 95% CI {lo:.3}..{hi:.3})",
+        s.trips, s.trip_probability
+    )?;
+    let at = |e: &Extreme| format!("seed {} ({})", e.seed, e.batch.display());
+    if let (Some(first), Some(last)) = (&s.earliest_trip, &s.latest_trip) {
+        writeln!(
+            out,
+            "  earliest trip    {:.2} s  {}",
+            first.value,
+            at(first)
+        )?;
+        writeln!(out, "  latest trip      {:.2} s  {}", last.value, at(last))?;
+    }
+    writeln!(
+        out,
+        "  hottest run      {:.2} °C  {}",
+        s.hottest.value,
+        at(&s.hottest)
+    )?;
+    writeln!(
+        out,
+        "  smallest margin  {:.2} °C  {}",
+        s.smallest_margin.value,
+        at(&s.smallest_margin)
+    )?;
+    if let Some(most) = s.trip_time_histogram.iter().map(|b| b.count).max() {
+        writeln!(out, "  trip times")?;
+        for b in &s.trip_time_histogram {
+            let bar = "#".repeat((b.count * 40).div_ceil(most.max(1)));
+            let edges = format!("{:.2}..{:.2} s", b.lo, b.hi);
+            writeln!(out, "    {edges:<20} {bar:<40}  {}", b.count)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn execute(args: &StatsArgs, stdout: &mut dyn Write) -> Result<u8> {
+    let batches = find_batches(&args.paths)?
+        .iter()
+        .map(|path| read_batch(path))
+        .collect::<Result<Vec<_>>>()?;
+    let stats = aggregate(&batches, args.bins);
+    for (n, s) in stats.iter().enumerate() {
+        if n > 0 {
+            writeln!(stdout)?;
+        }
+        write_stats(s, stdout)?;
+    }
+    if let Some(path) = &args.histogram_csv {
+        let mut csv = open_output(path, args.force)?;
+        writeln!(csv, "scenario,bin_lo,bin_hi,count")?;
+        for s in &stats {
+            for b in &s.trip_time_histogram {
+                writeln!(csv, "{},{},{},{}", s.scenario, b.lo, b.hi, b.count)?;
+            }
+        }
+        csv.flush()?;
+    }
+    Ok(exit::OK)
+}
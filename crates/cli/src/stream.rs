@@ -0,0 +1,337 @@
+//! `run --listen`: serve the trace to socket clients (a dashboard, another process) while the run
+//! writes it as usual.
+//!
+//! Every client receives the `_meta` header line on connect, then each JSONL row written from then
+//! on, whatever `--format` the trace itself uses. Each client has its own writer thread fed
+//! through a queue of `CLIENT_BUFFER_ROWS` rows; a client that falls that far behind, or accepts
+//! nothing for `CLIENT_WRITE_TIMEOUT`, is disconnected, so a slow reader never stalls the
+//! simulation.
+
+use std::fmt;
+use std::io::{self, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+
+/// Rows queued for one client before it is dropped as too slow.
+pub const CLIENT_BUFFER_ROWS: usize = 4096;
+
+/// A client that accepts no data for this long is dropped (and cannot hold up the end of the run).
+pub const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the accept thread checks for new clients and for the end of the run.
+const ACCEPT_POLL: Duration = Duration::from_millis(10);
+
+/// `--listen` address: `HOST:PORT` for TCP, `unix:PATH` for a Unix socket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListenAddr {
+    Tcp(String),
+    Unix(PathBuf),
+}
+
+impl FromStr for ListenAddr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix("unix:") {
+            Some("") => Err("expected unix:PATH".into()),
+            Some(_) if !cfg!(unix) => Err("Unix sockets are not supported on this platform".into()),
+            Some(path) => Ok(ListenAddr::Unix(path.into())),
+            None if s.contains(':') => Ok(ListenAddr::Tcp(s.into())),
+            None => Err(format!(
+                "expected HOST:PORT or unix:PATH, got {s:?} (port 0 picks a free one)"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListenAddr::Tcp(addr) => f.write_str(addr),
+            ListenAddr::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+enum Conn {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Listener {
+    fn bind(addr: &ListenAddr) -> io::Result<Self> {
+        let listener = match addr {
+            ListenAddr::Tcp(addr) => Listener::Tcp(TcpListener::bind(addr)?),
+            #[cfg(unix)]
+            ListenAddr::Unix(path) => Listener::Unix(UnixListener::bind(path)?, path.clone()),
+            #[cfg(not(unix))]
+            ListenAddr::Unix(_) => unreachable!("rejected when parsed"),
+        };
+        match &listener {
+            Listener::Tcp(l) => l.set_nonblocking(true)?,
+            #[cfg(unix)]
+            Listener::Unix(l, _) => l.set_nonblocking(true)?,
+        }
+        Ok(listener)
+    }
+
+    fn local_addr(&self) -> io::Result<ListenAddr> {
+        Ok(match self {
+            Listener::Tcp(l) => ListenAddr::Tcp(l.local_addr()?.to_string()),
+            #[cfg(unix)]
+            Listener::Unix(_, path) => ListenAddr::Unix(path.clone()),
+        })
+    }
+
+    /// A pending client, if any (the listener does not block).
+    fn accept(&self) -> io::Result<Option<(Conn, String)>> {
+        let accepted = match self {
+            Listener::Tcp(l) => l.accept().map(|(s, peer)| (Conn::Tcp(s), peer.to_string())),
+            #[cfg(unix)]
+            Listener::Unix(l, path) => l
+                .accept()
+                .map(|(s, _)| (Conn::Unix(s), path.display().to_string())),
+        };
+        match accepted {
+            Ok((conn, peer)) => {
+                // accepted sockets inherit non-blocking mode on some platforms
+                conn.set_nonblocking(false)?;
+                conn.set_write_timeout(CLIENT_WRITE_TIMEOUT)?;
+                Ok(Some((conn, peer)))
+            }
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Listener::Unix(_, path) = self {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl Conn {
+    fn set_nonblocking(&self, on: bool) -> io::Result<()> {
+        match self {
+            Conn::Tcp(s) => s.set_nonblocking(on),
+            #[cfg(unix)]
+            Conn::Unix(s) => s.set_nonblocking(on),
+        }
+    }
+
+    fn set_write_timeout(&self, timeout: Duration) -> io::Result<()> {
+        match self {
+            Conn::Tcp(s) => s.set_write_timeout(Some(timeout)),
+            #[cfg(unix)]
+            Conn::Unix(s) => s.set_write_timeout(Some(timeout)),
+        }
+    }
+
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(match self {
+            Conn::Tcp(s) => Conn::Tcp(s.try_clone()?),
+            #[cfg(unix)]
+            Conn::Unix(s) => Conn::Unix(s.try_clone()?),
+        })
+    }
+
+    fn shutdown(&self) {
+        let _ = match self {
+            Conn::Tcp(s) => s.shutdown(Shutdown::Both),
+            #[cfg(unix)]
+            Conn::Unix(s) => s.shutdown(Shutdown::Both),
+        };
+    }
+}
+
+impl Write for Conn {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Conn::Tcp(s) => s.write(buf),
+            #[cfg(unix)]
+            Conn::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Conn::Tcp(s) => s.flush(),
+            #[cfg(unix)]
+            Conn::Unix(s) => s.flush(),
+        }
+    }
+}
+
+struct Client {
+    peer: String,
+    rows: SyncSender<Arc<str>>,
+    /// Second handle on the socket, to unblock the writer when the client is dropped
+    conn: Conn,
+    writer: JoinHandle<()>,
+}
+
+impl Client {
+    fn start(mut conn: Conn, peer: String, header: &Arc<str>) -> io::Result<Self> {
+        let (rows, queue) = mpsc::sync_channel::<Arc<str>>(CLIENT_BUFFER_ROWS);
+        rows.try_send(Arc::clone(header))
+            .expect("an empty queue has room for the header");
+        let handle = conn.try_clone()?;
+        let writer = thread::spawn(move || {
+            for line in queue {
+                if conn.write_all(line.as_bytes()).is_err() {
+                    break;
+                }
+            }
+            let _ = conn.flush();
+        });
+        Ok(Client {
+            peer,
+            rows,
+            conn: handle,
+            writer,
+        })
+    }
+
+    /// Drop the client without waiting for its queue to drain.
+    fn disconnect(self) {
+        self.conn.shutdown();
+    }
+}
+
+/// Listening socket and the connected clients of one run.
+pub struct TraceServer {
+    addr: ListenAddr,
+    clients: Arc<Mutex<Vec<Client>>>,
+    stop: Arc<AtomicBool>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl TraceServer {
+    /// Listen on `addr`; clients receive `header` (one line, without the newline) first.
+    pub fn bind(addr: &ListenAddr, header: &str) -> Result<Self> {
+        let listener = Listener::bind(addr).with_context(|| format!("listening on {addr}"))?;
+        let addr = listener.local_addr()?;
+        let header: Arc<str> = format!("{header}\n").into();
+        let clients = Arc::new(Mutex::new(Vec::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+        let acceptor = {
+            let (clients, stop) = (Arc::clone(&clients), Arc::clone(&stop));
+            thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    match listener.accept() {
+                        Ok(Some((conn, peer))) => match Client::start(conn, peer, &header) {
+                            Ok(client) => {
+                                tracing::info!(peer = %client.peer, "stream client connected");
+                                lock(&clients).push(client);
+                            }
+                            Err(e) => tracing::warn!("cannot serve a stream client: {e}"),
+                        },
+                        Ok(None) => thread::sleep(ACCEPT_POLL),
+                        Err(e) => {
+                            tracing::warn!("accepting a stream client failed: {e}");
+                            thread::sleep(ACCEPT_POLL);
+                        }
+                    }
+                }
+            })
+        };
+        Ok(TraceServer {
+            addr,
+            clients,
+            stop,
+            acceptor: Some(acceptor),
+        })
+    }
+
+    /// Bound address (with the port picked for `HOST:0`).
+    pub fn addr(&self) -> &ListenAddr {
+        &self.addr
+    }
+
+    pub fn client_count(&self) -> usize {
+        lock(&self.clients).len()
+    }
+
+    /// Block until at least `n` clients are connected.
+    pub fn wait_for_clients(&self, n: usize) {
+        while self.client_count() < n {
+            thread::sleep(ACCEPT_POLL);
+        }
+    }
+
+    /// Queue `line` (without the newline) for every client, dropping those that have gone away
+    /// or fallen `CLIENT_BUFFER_ROWS` behind.
+    pub fn send(&self, line: &str) {
+        let mut clients = lock(&self.clients);
+        if clients.is_empty() {
+            return;
+        }
+        let line: Arc<str> = format!("{line}\n").into();
+        let mut i = 0;
+        while i < clients.len() {
+            match clients[i].rows.try_send(Arc::clone(&line)) {
+                Ok(()) => i += 1,
+                Err(TrySendError::Full(_)) => {
+                    let client = clients.swap_remove(i);
+                    tracing::warn!(
+                        peer = %client.peer,
+                        "stream client fell {CLIENT_BUFFER_ROWS} rows behind; disconnected"
+                    );
+                    client.disconnect();
+                }
+                Err(TrySendError::Disconnected(_)) => {
+                    let client = clients.swap_remove(i);
+                    tracing::info!(peer = %client.peer, "stream client went away");
+                    client.disconnect();
+                }
+            }
+        }
+    }
+
+    /// Stop accepting, deliver every queued row and close the connections.
+    pub fn finish(mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        if let Some(acceptor) = self.acceptor.take() {
+            let _ = acceptor.join();
+        }
+        for client in lock(&self.clients).drain(..) {
+            drop(client.rows);
+            let _ = client.writer.join();
+            client.conn.shutdown();
+        }
+    }
+}
+
+impl Drop for TraceServer {
+    fn drop(&mut self) {
+        // an error ended the run before `finish`: let the acceptor (and the listener) go
+        self.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+fn lock(clients: &Mutex<Vec<Client>>) -> std::sync::MutexGuard<'_, Vec<Client>> {
+    clients
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
@@ -0,0 +1,120 @@
+//! End-of-run summary: the figures people otherwise grep out of the last trace rows.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// How `run` reports its summary: on stdout when the trace goes to `--out`, else on stderr.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum SummaryFormat {
+    /// No summary
+    None,
+    /// A single JSON object on one line
+    Json,
+    /// Human-readable block
+    #[default]
+    Text,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// Ran for the full duration without a SCRAM
+    #[default]
+    Completed,
+    Tripped,
+    /// Stopped early by Ctrl-C
+    Interrupted,
+    /// Ended by an `--until` condition
+    Stopped,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub outcome: Outcome,
+    pub reason: Option<String>,
+    /// Time of the step on which the SCRAM latched
+    pub trip_t_s: Option<f64>,
+    /// `--until` condition that ended the run, in canonical form
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stopped_by: Option<String>,
+    pub steps: u64,
+    /// Simulated time of the last step
+    pub end_t_s: f64,
+    /// Highest true temperature over the run
+    pub max_temp_c: f64,
+    /// Largest spread between the valid sensor readings of one step
+    pub max_sensor_spread_c: f64,
+    pub final_temp_c: f64,
+    /// Smallest distance between the hottest valid sensor reading and the trip temperature
+    pub min_margin_c: f64,
+    /// `--fault` flags of the run, in canonical form
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub faults: Vec<String>,
+    /// Effective values of the `--kp` .. `--plant-ambient` flags, by config field
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub overrides: BTreeMap<String, f64>,
+    pub wall_clock_s: f64,
+}
+
+impl RunSummary {
+    pub fn tripped(&self) -> bool {
+        self.outcome == Outcome::Tripped
+    }
+
+    /// Write the summary in `format` (nothing for `None`).
+    pub fn write(&self, format: SummaryFormat, out: &mut dyn Write) -> Result<()> {
+        match format {
+            SummaryFormat::None => {}
+            SummaryFormat::Json => writeln!(out, "{}", serde_json::to_string(self)?)?,
+            SummaryFormat::Text => write!(out, "--- run summary ---\n{self}")?,
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.outcome, &self.reason, self.trip_t_s) {
+            (Outcome::Completed, ..) => writeln!(f, "outcome          completed")?,
+            (Outcome::Interrupted, ..) => writeln!(f, "outcome          interrupted")?,
+            (Outcome::Stopped, ..) => writeln!(
+                f,
+                "outcome          stopped ({}) at {:.2} s",
+                self.stopped_by.as_deref().unwrap_or("--until"),
+                self.end_t_s
+            )?,
+            (Outcome::Tripped, reason, t_s) => writeln!(
+                f,
+                "outcome          tripped ({}) at {:.2} s",
+                reason.as_deref().unwrap_or("unknown reason"),
+                t_s.unwrap_or(self.end_t_s)
+            )?,
+        }
+        writeln!(
+            f,
+            "steps            {} ({:.2} s simulated)",
+            self.steps, self.end_t_s
+        )?;
+        writeln!(f, "max temperature  {:.2} °C", self.max_temp_c)?;
+        writeln!(f, "max spread       {:.2} °C", self.max_sensor_spread_c)?;
+        writeln!(f, "final temp       {:.2} °C", self.final_temp_c)?;
+        writeln!(f, "min trip margin  {:.2} °C", self.min_margin_c)?;
+        if !self.faults.is_empty() {
+            writeln!(f, "injected faults  {}", self.faults.join(" "))?;
+        }
+        if !self.overrides.is_empty() {
+            let overrides: Vec<String> = self
+                .overrides
+                .iter()
+                .map(|(key, value)| format!("{key}={value}"))
+                .collect();
+            writeln!(f, "overrides        {}", overrides.join(" "))?;
+        }
+        writeln!(f, "wall clock       {:.3} s", self.wall_clock_s)
+    }
+}
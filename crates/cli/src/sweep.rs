@@ -0,0 +1,197 @@
+//! `sweep`: run a scenario over the full grid of one or more parameters (e.g. 5 values of kp ×
+//! 4 of trip-temp) and tabulate each combination's summary metrics as CSV.
+
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+
+use crate::batch::{job_count, run_parallel};
+use crate::exit::{self, InvalidInput};
+use crate::param::{self, Param};
+use crate::progress::Progress;
+use crate::{open_output, RunArgs, RunSummary};
+
+/// One `--sweep NAME=START..END:STEPS` axis: `steps` evenly spaced values, both ends included.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SweepSpec {
+    pub param: Param,
+    pub start: f64,
+    pub end: f64,
+    pub steps: usize,
+}
+
+impl SweepSpec {
+    pub fn values(&self) -> Vec<f64> {
+        if self.steps == 1 {
+            return vec![self.start];
+        }
+        let last = (self.steps - 1) as f64;
+        (0..self.steps)
+            .map(|i| self.start + (self.end - self.start) * i as f64 / last)
+            .collect()
+    }
+}
+
+impl FromStr for SweepSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let malformed = || format!("expected NAME=START..END:STEPS, got {s:?}");
+        let (name, rest) = s.split_once('=').ok_or_else(malformed)?;
+        let (range, steps) = rest.rsplit_once(':').ok_or_else(malformed)?;
+        let (start, end) = range.split_once("..").ok_or_else(malformed)?;
+        let param = Param::from_str(&name.trim().replace('_', "-"), true)
+            .map_err(|_| format!("unknown parameter {name:?}"))?;
+        let num = |t: &str| {
+            t.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| format!("invalid value {t:?} in {s:?}"))
+        };
+        let steps = steps
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| format!("STEPS must be a positive integer in {s:?}"))?;
+        Ok(SweepSpec {
+            param,
+            start: num(start)?,
+            end: num(end)?,
+            steps,
+        })
+    }
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct SweepArgs {
+    /// Parameter axis NAME=START..END:STEPS (repeatable; every combination is run). NAME is a
+    /// `search --param` value
+    #[arg(long = "sweep", value_name = "SPEC", required = true)]
+    pub sweeps: Vec<SweepSpec>,
+
+    /// Write the results CSV here instead of stdout
+    #[arg(long, value_name = "FILE")]
+    pub results: Option<PathBuf>,
+
+    /// Refuse grids with more combinations than this
+    #[arg(long, default_value_t = 1000)]
+    pub max_runs: usize,
+
+    /// Worker threads [default: number of logical cores]
+    #[arg(long)]
+    pub jobs: Option<NonZeroUsize>,
+
+    /// Scenario and settings of every run (defaults unless given); the swept values replace them
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+/// One combination of the grid and its run.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SweepRow {
+    /// Values in `--sweep` order
+    pub values: Vec<(Param, f64)>,
+    pub summary: RunSummary,
+}
+
+/// Every combination of the axes, the last axis varying fastest.
+pub fn grid(sweeps: &[SweepSpec]) -> Vec<Vec<(Param, f64)>> {
+    sweeps.iter().fold(vec![Vec::new()], |combos, spec| {
+        let values = spec.values();
+        combos
+            .iter()
+            .flat_map(|combo| {
+                values.iter().map(move |&v| {
+                    let mut combo = combo.clone();
+                    combo.push((spec.param, v));
+                    combo
+                })
+            })
+            .collect()
+    })
+}
+
+/// Run every combination of `args.sweeps` on `jobs` workers.
+pub fn sweep(args: &SweepArgs, jobs: usize) -> Result<Vec<SweepRow>> {
+    for (i, spec) in args.sweeps.iter().enumerate() {
+        if args.sweeps[..i].iter().any(|s| s.param == spec.param) {
+            bail!(InvalidInput(format!("{} is swept twice", spec.param)));
+        }
+    }
+    let runs = args
+        .sweeps
+        .iter()
+        .try_fold(1usize, |n, spec| n.checked_mul(spec.steps))
+        .filter(|&n| n <= args.max_runs);
+    if runs.is_none() {
+        bail!(InvalidInput(format!(
+            "the sweep has more than --max-runs {} combinations",
+            args.max_runs
+        )));
+    }
+    let combos = grid(&args.sweeps);
+    let progress = Progress::new("sweep", &args.run);
+    let summaries = run_parallel(&combos, jobs, progress, |values| {
+        param::run_with(&args.run, values)
+    })?;
+    Ok(combos
+        .into_iter()
+        .zip(summaries)
+        .map(|(values, summary)| SweepRow { values, summary })
+        .collect())
+}
+
+/// Metric columns after the swept parameters.
+pub const METRICS_HEADER: &str = "tripped,reason,t_trip,max_temp,min_margin,final_temp";
+
+fn csv_line(row: &SweepRow) -> String {
+    let s = &row.summary;
+    let mut cells: Vec<String> = row.values.iter().map(|(_, v)| v.to_string()).collect();
+    cells.extend([
+        s.tripped().to_string(),
+        s.reason.clone().unwrap_or_default(),
+        s.trip_t_s.map(|t| t.to_string()).unwrap_or_default(),
+        s.max_temp_c.to_string(),
+        s.min_margin_c.to_string(),
+        s.final_temp_c.to_string(),
+    ]);
+    cells.join(",")
+}
+
+pub fn execute(args: &SweepArgs, stdout: &mut dyn Write) -> Result<u8> {
+    let flags = args.run.single_run_flags();
+    if !flags.is_empty() {
+        bail!(InvalidInput(format!(
+            "sweep writes a results table (--results); drop {}",
+            flags.join(", ")
+        )));
+    }
+    let rows = sweep(args, job_count(args.jobs))?;
+    let mut out: Box<dyn Write + '_> = match &args.results {
+        Some(path) => Box::new(open_output(path, args.run.force)?),
+        None => Box::new(&mut *stdout),
+    };
+    let params: Vec<String> = args.sweeps.iter().map(|s| s.param.to_string()).collect();
+    writeln!(out, "{},{METRICS_HEADER}", params.join(","))?;
+    for row in &rows {
+        writeln!(out, "{}", csv_line(row))?;
+    }
+    out.flush()?;
+    drop(out);
+    if let Some(path) = &args.results {
+        let trips = rows.iter().filter(|r| r.summary.tripped()).count();
+        writeln!(
+            stdout,
+            "{} runs, {trips} tripped; wrote {}",
+            rows.len(),
+            path.display()
+        )?;
+    }
+    Ok(exit::OK)
+}
@@ -0,0 +1,606 @@
+//! The trace header and the writers and readers for each output format; the rows are the `trace`
+//! crate's, which the GUI reads and writes too.
+//!
+//! JSONL is the native format (a `{"_meta": ...}` header line, then one serde-serialized
+//! `TraceRow` per line, read by the GUI's replay loader). CSV mirrors the same columns for
+//! spreadsheet and pandas users: a header row, `NaN` as an empty cell and the trip reason quoted.
+//! MessagePack carries the JSONL records in binary (see `msgpack`). Any of them can be
+//! gzip-compressed (`.gz`). `read_trace` loads them all back.
+
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use flate2::read::GzDecoder;
+
+use crate::config::{SimConfig, SENSOR_NAMES};
+use crate::exit::InvalidInput;
+use crate::provenance::Provenance;
+use crate::{RunArgs, RunOptions};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use sim::ScenarioSpec;
+
+// The rows and the header fields every trace shares, with the GUI (see the `trace` crate)
+pub use ::trace::{
+    csv_header, DiagColumns, MetaLine, RowReason, TraceHeader, TraceRow, TRACE_SCHEMA,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum TraceFormat {
+    /// One JSON object per line
+    #[default]
+    Jsonl,
+    /// Comma-separated values with a header row
+    Csv,
+    /// Length-prefixed MessagePack records (the JSONL header and rows in binary)
+    Msgpack,
+    /// Apache Parquet, one typed column per field (written when the run ends)
+    #[cfg(feature = "parquet")]
+    Parquet,
+}
+
+impl TraceFormat {
+    /// Format implied by a file name: `.csv` (or `.csv.gz`) is CSV, `.msgpack` MessagePack,
+    /// anything else JSONL.
+    pub fn from_path(path: &Path) -> Self {
+        let path = if is_gzip_path(path) {
+            Path::new(path.file_stem().unwrap_or_default())
+        } else {
+            path
+        };
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("csv") => TraceFormat::Csv,
+            Some(ext) if ext.eq_ignore_ascii_case("msgpack") => TraceFormat::Msgpack,
+            #[cfg(feature = "parquet")]
+            Some(ext) if ext.eq_ignore_ascii_case("parquet") => TraceFormat::Parquet,
+            _ => TraceFormat::Jsonl,
+        }
+    }
+
+    /// File extension for traces in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            TraceFormat::Jsonl => "jsonl",
+            TraceFormat::Csv => "csv",
+            TraceFormat::Msgpack => "msgpack",
+            #[cfg(feature = "parquet")]
+            TraceFormat::Parquet => "parquet",
+        }
+    }
+
+    /// Whether traces in this format start with the `_meta` header.
+    pub fn has_header(self) -> bool {
+        matches!(self, TraceFormat::Jsonl | TraceFormat::Msgpack)
+    }
+}
+
+/// What produced a trace, written as the first JSONL line `{"_meta": {...}}` so an archived trace
+/// can be reproduced.
+#[derive(Clone, Debug, serde::Serialize, Deserialize)]
+pub struct TraceMeta {
+    /// Scenario, seed, step and limits; the config hash is that of the effective configuration
+    /// (see `SimConfig::config_hash`)
+    #[serde(flatten)]
+    pub header: TraceHeader,
+    /// Noise and seed each sensor ran with, by sensor name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sensors: Option<BTreeMap<String, SensorMeta>>,
+    /// Effective configuration, scenario and run options, from which `verify` re-runs the trace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<SimConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario_spec: Option<ScenarioSpec>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<RunOptions>,
+    /// Where a `run --resume` picked the run up: its rows start after this checkpoint
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resumed: Option<ResumePoint>,
+    /// Command line, crate versions, build commit and host of the process that wrote the trace
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+}
+
+/// The checkpoint a resumed trace continues from.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, Deserialize)]
+pub struct ResumePoint {
+    /// Index of the first step of the resumed run
+    pub step: u64,
+    /// Its simulated time
+    pub t_s: f64,
+}
+
+/// Effective settings of one sensor, as recorded in the trace header.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, Deserialize)]
+pub struct SensorMeta {
+    pub noise_std: f64,
+    pub seed: u64,
+}
+
+impl SensorMeta {
+    /// The settings of every sensor of a run with `cfg`.
+    pub fn of(cfg: &SimConfig) -> BTreeMap<String, SensorMeta> {
+        let seeds = cfg.sensors.seeds(cfg.run.seed);
+        SENSOR_NAMES
+            .into_iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let meta = SensorMeta {
+                    noise_std: cfg.sensors.get(i).noise_std,
+                    seed: seeds[i],
+                };
+                (name.to_string(), meta)
+            })
+            .collect()
+    }
+}
+
+impl TraceMeta {
+    /// The header of a run of `spec` under `cfg` with the flags of `args`, from the start with
+    /// `setpoint`. The GUI writes it for its recordings too.
+    pub fn new(
+        args: &RunArgs,
+        cfg: &SimConfig,
+        spec: &ScenarioSpec,
+        setpoint: f64,
+    ) -> Result<Self> {
+        let scenario = match &args.scenario_file {
+            Some(path) if spec.name.is_empty() => path.display().to_string(),
+            _ => spec.name.clone(),
+        };
+        Ok(TraceMeta {
+            header: TraceHeader {
+                schema: TRACE_SCHEMA,
+                crate_version: env!("CARGO_PKG_VERSION").to_string(),
+                scenario,
+                seed: cfg.run.seed,
+                dt_ms: cfg.run.dt_ms,
+                setpoint,
+                trip_temp: cfg.safety.trip_temp_c,
+                config_hash: cfg.config_hash(spec)?,
+            },
+            sensors: Some(SensorMeta::of(cfg)),
+            config: Some(cfg.clone()),
+            scenario_spec: Some(spec.clone()),
+            options: Some(args.options()),
+            resumed: None,
+            provenance: Some(Provenance::current()),
+        })
+    }
+
+    /// The header line `{"_meta": {...}}`, without the newline.
+    pub fn header_line(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&MetaLine { meta: self })
+    }
+
+    /// The header from the value under `_meta`, refusing a schema newer than `TRACE_SCHEMA`.
+    pub fn from_header(meta: serde_json::Value) -> Result<Self> {
+        let schema = meta
+            .get("schema")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| anyhow!("trace header has no schema number"))?;
+        if schema > u64::from(TRACE_SCHEMA) {
+            bail!(
+                "trace schema {schema} is newer than this build supports (up to {TRACE_SCHEMA}); \
+                 upgrade reactor-safety-sim to read it"
+            );
+        }
+        serde_json::from_value(meta).context("invalid trace header")
+    }
+}
+
+/// Rebuild a row from CSV cells under `header`; diagnostics are read when their columns exist.
+fn row_from_csv(header: &[String], cells: &[String]) -> Result<TraceRow> {
+    if cells.len() != header.len() {
+        bail!("{} cells for {} columns", cells.len(), header.len());
+    }
+    let cell = |name: &str| -> Result<String> {
+        header
+            .iter()
+            .position(|h| h == name)
+            .map(|i| cells[i].clone())
+            .ok_or_else(|| anyhow!("missing column {name}"))
+    };
+    let reason = cell("reason")?;
+    let has_diag = header.iter().any(|h| h == DiagColumns::CSV_COLUMNS[0]);
+    Ok(TraceRow {
+        t_s: parse_f64(&cell("t_s")?)?,
+        setpoint_c: parse_f64(&cell("setpoint_c")?)?,
+        true_temp_c: parse_f64(&cell("true_temp_c")?)?,
+        s1_c: parse_f64(&cell("s1_c")?)?,
+        s2_c: parse_f64(&cell("s2_c")?)?,
+        s3_c: parse_f64(&cell("s3_c")?)?,
+        power: parse_f64(&cell("power")?)?,
+        coolant: parse_f64(&cell("coolant")?)?,
+        scram: parse_bool(&cell("scram")?)?,
+        reason: (!reason.is_empty()).then_some(RowReason::Name(reason)),
+        diag: if has_diag {
+            Some(diag_from_csv(&cell)?)
+        } else {
+            None
+        },
+    })
+}
+
+fn diag_from_csv(cell: &dyn Fn(&str) -> Result<String>) -> Result<DiagColumns> {
+    Ok(DiagColumns {
+        p_term: parse_f64(&cell("p_term")?)?,
+        i_term: parse_f64(&cell("i_term")?)?,
+        d_term: parse_f64(&cell("d_term")?)?,
+        ff_term: parse_f64(&cell("ff_term")?)?,
+        out_presat: parse_f64(&cell("out_presat")?)?,
+        saturated: parse_bool(&cell("saturated")?)?,
+        ctrl_status: cell("ctrl_status")?,
+    })
+}
+
+/// Inverse of the CSV writer's numbers: an empty cell is NaN.
+fn parse_f64(cell: &str) -> Result<f64> {
+    if cell.is_empty() {
+        Ok(f64::NAN)
+    } else {
+        cell.parse::<f64>()
+            .with_context(|| format!("invalid number {cell:?}"))
+    }
+}
+
+fn parse_bool(cell: &str) -> Result<bool> {
+    cell.parse::<bool>()
+        .with_context(|| format!("invalid boolean {cell:?}"))
+}
+
+/// Split one CSV line into cells, undoing `csv_quoted`.
+pub fn split_csv(line: &str) -> Vec<String> {
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                cell.push('"');
+                chars.next();
+            }
+            ('"', _) => quoted = !quoted,
+            (',', false) => cells.push(std::mem::take(&mut cell)),
+            _ => cell.push(c),
+        }
+    }
+    cells.push(cell);
+    cells
+}
+
+/// Leading bytes of every gzip stream.
+pub const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Whether a trace file name asks for gzip compression.
+pub fn is_gzip_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+}
+
+/// A trace's bytes, from a file or from memory.
+pub type TraceSource<'a> = Box<dyn BufRead + Send + 'a>;
+
+/// One header or row of a trace: a line of a text trace, or a record of a binary one.
+#[derive(Clone, Debug)]
+pub enum TraceRecord {
+    /// Index and text of a non-empty line
+    Line(usize, String),
+    /// Index and bytes of a MessagePack record
+    Binary(usize, Vec<u8>),
+}
+
+impl TraceRecord {
+    /// Decode a JSONL line, or a MessagePack record with the same fields.
+    pub fn decode<T: DeserializeOwned>(&self) -> Result<T> {
+        Ok(match self {
+            TraceRecord::Line(_, line) => serde_json::from_str(line)?,
+            TraceRecord::Binary(_, bytes) => rmp_serde::from_slice(bytes)?,
+        })
+    }
+
+    /// Where the record is in the trace, `line 3` or `record 3`.
+    pub fn position(&self) -> String {
+        match self {
+            TraceRecord::Line(i, _) => format!("line {}", i + 1),
+            TraceRecord::Binary(i, _) => format!("record {}", i + 1),
+        }
+    }
+}
+
+/// Reads a trace record by record, so a long one need not be in memory at once: the records of
+/// a binary trace (see `msgpack`), else the non-empty lines of a text trace. Gzipped traces are
+/// gunzipped on the way.
+pub struct TraceReader<'a> {
+    source: TraceSource<'a>,
+    binary: bool,
+    // Lines or records read so far
+    count: usize,
+}
+
+impl<'a> TraceReader<'a> {
+    /// Start reading `source`, recognizing gzip and binary traces by their magic bytes.
+    pub fn new(source: TraceSource<'a>) -> io::Result<Self> {
+        let (head, mut source) = peek(source, GZIP_MAGIC.len())?;
+        if head == GZIP_MAGIC {
+            source = Box::new(BufReader::new(GzDecoder::new(source)));
+        }
+        let (head, mut source) = peek(source, crate::msgpack::MAGIC.len())?;
+        let binary = head == crate::msgpack::MAGIC;
+        if binary {
+            let mut magic = [0; 4];
+            source.read_exact(&mut magic)?;
+        }
+        Ok(Self {
+            source,
+            binary,
+            count: 0,
+        })
+    }
+
+    /// Whether the trace is binary (MessagePack records) rather than text.
+    pub fn is_binary(&self) -> bool {
+        self.binary
+    }
+
+    fn next_line(&mut self) -> Option<Result<TraceRecord>> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            match self.source.read_line(&mut line) {
+                Ok(0) => return None,
+                Ok(_) => self.count += 1,
+                Err(e) => return Some(Err(anyhow!("line {}: {e}", self.count + 1))),
+            }
+            if !line.trim().is_empty() {
+                line.truncate(line.trim_end().len());
+                return Some(Ok(TraceRecord::Line(self.count - 1, line)));
+            }
+        }
+    }
+
+    fn next_binary(&mut self) -> Option<Result<TraceRecord>> {
+        match crate::msgpack::read_record(&mut self.source) {
+            Ok(None) => None,
+            Ok(Some(record)) => {
+                self.count += 1;
+                Some(Ok(TraceRecord::Binary(self.count - 1, record)))
+            }
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                Some(Err(anyhow!("record {} is cut off", self.count + 1)))
+            }
+            Err(e) => Some(Err(anyhow!("record {}: {e}", self.count + 1))),
+        }
+    }
+}
+
+impl Iterator for TraceReader<'_> {
+    type Item = Result<TraceRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.binary {
+            self.next_binary()
+        } else {
+            self.next_line()
+        }
+    }
+}
+
+/// The first `n` bytes of `source` (fewer if it is shorter), and the source still starting with
+/// them.
+fn peek(mut source: TraceSource<'_>, n: usize) -> io::Result<(Vec<u8>, TraceSource<'_>)> {
+    let mut head = Vec::with_capacity(n);
+    (&mut source).take(n as u64).read_to_end(&mut head)?;
+    let source = Box::new(io::Cursor::new(head.clone()).chain(source));
+    Ok((head, source))
+}
+
+/// Load a trace written by `TraceWriter`; the format is taken from the file extension (ignoring
+/// `.gz`) and gzip-compressed files are recognized by their magic bytes.
+pub fn read_trace(path: &Path) -> Result<Vec<TraceRow>> {
+    Ok(read_trace_with_meta(path)?.1)
+}
+
+/// `read_trace`, also returning the header (`None` for CSV, Parquet and legacy JSONL). Binary
+/// traces are recognized by their magic bytes whatever the file is called.
+pub fn read_trace_with_meta(path: &Path) -> Result<(Option<TraceMeta>, Vec<TraceRow>)> {
+    #[cfg(feature = "parquet")]
+    if TraceFormat::from_path(path) == TraceFormat::Parquet {
+        return crate::columnar::read(path)
+            .map(|rows| (None, rows))
+            .map_err(|e| InvalidInput(format!("in trace {}: {e:#}", path.display())).into());
+    }
+    let file =
+        std::fs::File::open(path).with_context(|| format!("reading trace {}", path.display()))?;
+    let reader = TraceReader::new(Box::new(BufReader::new(file)))
+        .with_context(|| format!("reading trace {}", path.display()))?;
+    read_records(reader, TraceFormat::from_path(path))
+        .map_err(|e| InvalidInput(format!("in trace {}: {e:#}", path.display())).into())
+}
+
+/// Parse a text trace (JSONL or CSV), skipping the JSONL header.
+pub fn parse_trace(text: &str, format: TraceFormat) -> Result<Vec<TraceRow>> {
+    Ok(parse_trace_with_meta(text, format)?.1)
+}
+
+/// `parse_trace`, also returning the JSONL header if the trace has one.
+pub fn parse_trace_with_meta(
+    text: &str,
+    format: TraceFormat,
+) -> Result<(Option<TraceMeta>, Vec<TraceRow>)> {
+    read_records(TraceReader::new(Box::new(text.as_bytes()))?, format)
+}
+
+/// Header and rows of the trace `reader` reads; a text trace is in `format`.
+fn read_records(
+    reader: TraceReader<'_>,
+    format: TraceFormat,
+) -> Result<(Option<TraceMeta>, Vec<TraceRow>)> {
+    let mut records = reader.peekable();
+    let binary = records
+        .peek()
+        .is_some_and(|r| matches!(r, Ok(TraceRecord::Binary(..))));
+    match format {
+        _ if binary => {}
+        TraceFormat::Jsonl => {}
+        TraceFormat::Csv => {
+            let mut lines = records.map(|record| -> Result<_> {
+                match record? {
+                    TraceRecord::Line(i, line) => Ok((i, line)),
+                    TraceRecord::Binary(..) => unreachable!("CSV traces are text"),
+                }
+            });
+            let Some((_, header)) = lines.next().transpose()? else {
+                return Ok((None, Vec::new()));
+            };
+            let header = split_csv(&header);
+            let rows = lines
+                .map(|line| {
+                    let (i, line) = line?;
+                    row_from_csv(&header, &split_csv(&line))
+                        .with_context(|| format!("line {}", i + 1))
+                })
+                .collect::<Result<_>>()?;
+            return Ok((None, rows));
+        }
+        TraceFormat::Msgpack => bail!("MessagePack traces are binary; load them with read_trace"),
+        #[cfg(feature = "parquet")]
+        TraceFormat::Parquet => bail!("Parquet traces are binary; load them with read_trace"),
+    }
+
+    // JSONL and binary traces: an optional header, then the rows
+    let meta = match records.peek() {
+        Some(Ok(record)) => match record.decode::<MetaLine<serde_json::Value>>() {
+            Ok(MetaLine { meta }) => {
+                Some(TraceMeta::from_header(meta).with_context(|| record.position())?)
+            }
+            Err(_) => None,
+        },
+        _ => None,
+    };
+    if meta.is_some() {
+        records.next();
+    }
+    let rows = records
+        .map(|record| {
+            let record = record?;
+            record.decode().with_context(|| record.position())
+        })
+        .collect::<Result<_>>()?;
+    Ok((meta, rows))
+}
+
+/// `--decimate`: picks the rows worth writing when thinning a trace.
+#[derive(Clone, Debug, serde::Serialize, Deserialize)]
+pub struct Decimator {
+    every: u64,
+    /// (scram, alarm) of the previous step
+    last: Option<(bool, bool)>,
+}
+
+impl Decimator {
+    pub fn new(every: u64) -> Self {
+        Self {
+            every: every.max(1),
+            last: None,
+        }
+    }
+
+    /// Whether step `k` is written: every `every`-th step, the first one, and any step where the
+    /// trip or the sensor-disagreement alarm changes state. The caller keeps the final row.
+    pub fn keep(&mut self, k: u64, scram: bool, alarm: bool) -> bool {
+        let changed = self.last != Some((scram, alarm));
+        self.last = Some((scram, alarm));
+        changed || k.is_multiple_of(self.every)
+    }
+}
+
+/// Writes trace rows in the chosen format; the CSV header (or the JSONL `_meta` line, if set with
+/// `with_meta`) goes out before the first row. Parquet rows are buffered and written by `finish`.
+pub struct TraceWriter<W: Write> {
+    out: W,
+    format: TraceFormat,
+    diagnostics: bool,
+    meta: Option<TraceMeta>,
+    header_written: bool,
+    #[cfg(feature = "parquet")]
+    buffered: Vec<TraceRow>,
+}
+
+impl<W: Write> TraceWriter<W> {
+    pub fn new(out: W, format: TraceFormat, diagnostics: bool) -> Self {
+        Self {
+            out,
+            format,
+            diagnostics,
+            meta: None,
+            header_written: false,
+            #[cfg(feature = "parquet")]
+            buffered: Vec::new(),
+        }
+    }
+
+    /// Start a JSONL or binary trace with a `_meta` header; other formats ignore it.
+    pub fn with_meta(mut self, meta: TraceMeta) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        if self.header_written {
+            return Ok(());
+        }
+        self.header_written = true;
+        match self.format {
+            TraceFormat::Jsonl => match &self.meta {
+                Some(meta) => writeln!(self.out, "{}", meta.header_line()?),
+                None => Ok(()),
+            },
+            TraceFormat::Csv => writeln!(self.out, "{}", csv_header(self.diagnostics).join(",")),
+            TraceFormat::Msgpack => {
+                self.out.write_all(&crate::msgpack::MAGIC)?;
+                match &self.meta {
+                    Some(meta) => crate::msgpack::write_record(&mut self.out, &MetaLine { meta }),
+                    None => Ok(()),
+                }
+            }
+            #[cfg(feature = "parquet")]
+            TraceFormat::Parquet => Ok(()),
+        }
+    }
+
+    pub fn write_row(&mut self, row: &TraceRow) -> io::Result<()> {
+        self.write_header()?;
+        match self.format {
+            TraceFormat::Jsonl => {
+                serde_json::to_writer(&mut self.out, row)?;
+                writeln!(self.out)
+            }
+            TraceFormat::Csv => writeln!(self.out, "{}", row.csv_cells().join(",")),
+            TraceFormat::Msgpack => crate::msgpack::write_record(&mut self.out, row),
+            #[cfg(feature = "parquet")]
+            TraceFormat::Parquet => {
+                self.buffered.push(row.clone());
+                Ok(())
+            }
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    /// Write anything still buffered and flush.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.write_header()?;
+        #[cfg(feature = "parquet")]
+        if self.format == TraceFormat::Parquet {
+            let bytes = crate::columnar::encode(&self.buffered, self.diagnostics)?;
+            self.out.write_all(&bytes)?;
+        }
+        self.out.flush()
+    }
+}
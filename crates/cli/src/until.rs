@@ -0,0 +1,153 @@
+//! `--until` conditions: end a run as soon as it has shown what it was run for (the plant settled,
+//! got too hot, raised enough alarms) instead of simulating the full `--seconds`.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Slack on the settling time, so `settled:2:30` holds on the step 30 s after the first one in
+/// the band despite the rounding of `k × dt`.
+const SETTLE_EPS_S: f64 = 1e-9;
+
+/// One `--until <cond>`: `settled:BAND:SECS`, `max-temp:C` or `alarms:N`.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum UntilCondition {
+    /// True temperature within ±`band_c` of the setpoint for `for_s` seconds
+    Settled { band_c: f64, for_s: f64 },
+    /// True temperature above `c`
+    MaxTemp { c: f64 },
+    /// `count` sensor-disagreement alarms raised
+    Alarms { count: u32 },
+}
+
+impl FromStr for UntilCondition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':').map(str::trim);
+        let kind = parts.next().unwrap_or_default();
+        let values: Vec<&str> = parts.collect();
+        let number = |i: usize, what: &str| -> Result<f64, String> {
+            values[i]
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| format!("invalid {what} {:?}, expected a number", values[i]))
+        };
+        let arity = |n: usize, usage: &str| {
+            if values.len() == n {
+                Ok(())
+            } else {
+                Err(format!("{s:?}: expected {usage}"))
+            }
+        };
+        match kind {
+            "settled" => {
+                arity(2, "settled:BAND:SECS")?;
+                let (band_c, for_s) = (number(0, "band")?, number(1, "duration")?);
+                if band_c <= 0.0 || for_s < 0.0 {
+                    return Err(format!(
+                        "{s:?}: the band must be > 0 °C and the duration >= 0 s"
+                    ));
+                }
+                Ok(UntilCondition::Settled { band_c, for_s })
+            }
+            "max-temp" => {
+                arity(1, "max-temp:C")?;
+                Ok(UntilCondition::MaxTemp {
+                    c: number(0, "temperature")?,
+                })
+            }
+            "alarms" => {
+                arity(1, "alarms:N")?;
+                match values[0].parse::<u32>() {
+                    Ok(count) if count > 0 => Ok(UntilCondition::Alarms { count }),
+                    _ => Err(format!(
+                        "invalid alarm count {:?}, expected a whole number >= 1",
+                        values[0]
+                    )),
+                }
+            }
+            other => Err(format!(
+                "unknown condition {other:?}, expected settled:BAND:SECS, max-temp:C or alarms:N"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for UntilCondition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UntilCondition::Settled { band_c, for_s } => write!(f, "settled:{band_c}:{for_s}"),
+            UntilCondition::MaxTemp { c } => write!(f, "max-temp:{c}"),
+            UntilCondition::Alarms { count } => write!(f, "alarms:{count}"),
+        }
+    }
+}
+
+impl TryFrom<String> for UntilCondition {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<UntilCondition> for String {
+    fn from(condition: UntilCondition) -> Self {
+        condition.to_string()
+    }
+}
+
+/// The `--until` conditions of one run, with what they have seen so far.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct Until {
+    /// Each condition with, for `settled`, the time the temperature last entered the band
+    conditions: Vec<(UntilCondition, Option<f64>)>,
+    alarm: bool,
+    alarms_raised: u32,
+}
+
+impl Until {
+    pub fn new(conditions: &[UntilCondition]) -> Self {
+        Until {
+            conditions: conditions.iter().map(|&c| (c, None)).collect(),
+            alarm: false,
+            alarms_raised: 0,
+        }
+    }
+
+    /// Account for one step; the first condition (in flag order) that now holds, if any.
+    pub fn step(
+        &mut self,
+        t_s: f64,
+        temp_c: f64,
+        setpoint_c: f64,
+        alarm: bool,
+    ) -> Option<UntilCondition> {
+        if alarm && !self.alarm {
+            self.alarms_raised += 1;
+        }
+        self.alarm = alarm;
+        let mut met = None;
+        for (condition, in_band_since) in &mut self.conditions {
+            let holds = match *condition {
+                UntilCondition::Settled { band_c, for_s } => {
+                    if (temp_c - setpoint_c).abs() <= band_c {
+                        let since = *in_band_since.get_or_insert(t_s);
+                        t_s - since + SETTLE_EPS_S >= for_s
+                    } else {
+                        *in_band_since = None;
+                        false
+                    }
+                }
+                UntilCondition::MaxTemp { c } => temp_c > c,
+                UntilCondition::Alarms { count } => self.alarms_raised >= count,
+            };
+            if holds && met.is_none() {
+                met = Some(*condition);
+            }
+        }
+        met
+    }
+}
@@ -0,0 +1,118 @@
+//! `validate`: check configuration and scenario files without running a simulation.
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use clap::ValueEnum;
+
+use crate::config::SimConfig;
+use crate::exit::{self, InvalidInput};
+use crate::{scenario, Scenario};
+
+#[derive(clap::Args, Clone, Debug)]
+#[command(group(clap::ArgGroup::new("input").required(true).multiple(true)))]
+pub struct ValidateArgs {
+    /// Simulation description (TOML) as given to `run --config`
+    #[arg(long, value_name = "FILE", group = "input")]
+    pub config: Option<PathBuf>,
+
+    /// Scenario description (TOML, or YAML for .yaml/.yml) as given to `run --scenario-file`
+    #[arg(long, value_name = "FILE", group = "input")]
+    pub scenario_file: Option<PathBuf>,
+}
+
+/// Every problem in the given files, each prefixed with the file it was found in. A file that
+/// does not parse reports that error alone, as its values cannot be checked.
+pub fn problems(args: &ValidateArgs) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut report = |path: &Path, msgs: Vec<String>| {
+        problems.extend(msgs.into_iter().map(|m| format!("{}: {m}", path.display())));
+    };
+    let mut cfg = None;
+    if let Some(path) = &args.config {
+        match SimConfig::load(path) {
+            Ok(c) => {
+                let mut msgs = c.problems();
+                // Each scenario's overrides can bring problems of their own
+                for name in c.scenario_overrides.keys() {
+                    let scenario = Scenario::from_str(name, false).expect("checked by load");
+                    match c.for_scenario(&scenario) {
+                        Ok(merged) => {
+                            let own: Vec<String> = merged
+                                .problems()
+                                .into_iter()
+                                .filter(|p| !msgs.contains(p))
+                                .map(|p| format!("with [scenarios.{name}]: {p}"))
+                                .collect();
+                            msgs.extend(own);
+                        }
+                        Err(e) => msgs.push(format!("{e:#}")),
+                    }
+                }
+                report(path, msgs);
+                cfg = Some(c);
+            }
+            Err(e) => report(path, vec![parse_error(&e, path)]),
+        }
+    }
+    if let Some(path) = &args.scenario_file {
+        match scenario::read(path) {
+            Ok(spec) => {
+                let mut msgs: Vec<String> = match spec.validate() {
+                    Ok(()) => Vec::new(),
+                    Err(errs) => errs.iter().map(ToString::to_string).collect(),
+                };
+                // The setpoint events have to stay clear of the trip like the configured setpoint
+                let trip = cfg.unwrap_or_default().safety.trip_temp_c;
+                for (i, e) in spec.events.iter().enumerate() {
+                    if let sim::EventAction::SetSetpoint(sp) = e.action {
+                        if sp >= trip {
+                            msgs.push(format!(
+                                "events[{i}].action.set_setpoint = {sp} must be below \
+                                 safety.trip_temp_c ({trip})"
+                            ));
+                        }
+                    }
+                }
+                report(path, msgs);
+            }
+            Err(e) => report(path, vec![parse_error(&e, path)]),
+        }
+    }
+    problems
+}
+
+/// The load error without the "in <file>:" prefix, which `problems` adds itself.
+fn parse_error(err: &anyhow::Error, path: &Path) -> String {
+    let msg = match err.downcast_ref::<InvalidInput>() {
+        Some(InvalidInput(msg)) => msg.clone(),
+        None => format!("{err:#}"),
+    };
+    ["in config ", "in scenario file "]
+        .iter()
+        .find_map(|prefix| msg.strip_prefix(&format!("{prefix}{}: ", path.display())))
+        .map_or(msg.clone(), str::to_string)
+}
+
+/// Print every problem; the exit code is `exit::OK` only if there are none.
+pub fn execute(args: &ValidateArgs, stdout: &mut dyn Write) -> Result<u8> {
+    let problems = problems(args);
+    for p in &problems {
+        writeln!(stdout, "{p}")?;
+    }
+    let files = [&args.config, &args.scenario_file]
+        .into_iter()
+        .flatten()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    if problems.is_empty() {
+        writeln!(stdout, "ok: {files}")?;
+        Ok(exit::OK)
+    } else {
+        let s = if problems.len() == 1 { "" } else { "s" };
+        writeln!(stdout, "{} problem{s} in {files}", problems.len())?;
+        Ok(exit::INVALID_INPUT)
+    }
+}
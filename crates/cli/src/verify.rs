@@ -0,0 +1,148 @@
+//! `verify`: re-run a simulation from the configuration recorded in a trace header and check the
+//! new trace matches the recorded one value for value, so an archived trace (or a refactor of the
+//! simulation loop) can be checked for determinism.
+
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+
+use crate::exit::{self, InvalidInput};
+use crate::trace::{parse_trace, read_trace_with_meta, TraceFormat, TraceRow};
+use crate::{simulate, RunArgs, SummaryFormat};
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct VerifyArgs {
+    /// JSONL or binary trace written by `run` (its `_meta` header holds the configuration)
+    pub trace: PathBuf,
+
+    /// Re-run a trace recorded by a different major version of reactor-safety-sim
+    #[arg(long)]
+    pub allow_version_mismatch: bool,
+}
+
+/// First difference between the recorded and the re-run trace.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Mismatch {
+    /// Row `row` (0-based, header excluded) differs in `column`.
+    Value {
+        row: usize,
+        t_s: f64,
+        column: &'static str,
+        recorded: String,
+        rerun: String,
+    },
+    /// All common rows match but one trace is longer.
+    RowCount { recorded: usize, rerun: usize },
+}
+
+/// First mismatch between two traces, comparing every column in canonical form (see
+/// `TraceRow::cells`).
+pub fn first_mismatch(recorded: &[TraceRow], rerun: &[TraceRow]) -> Option<Mismatch> {
+    for (row, (a, b)) in recorded.iter().zip(rerun).enumerate() {
+        let (a_cells, b_cells) = (a.cells(), b.cells());
+        if a_cells.len() != b_cells.len() {
+            return Some(Mismatch::Value {
+                row,
+                t_s: a.t_s,
+                column: "diagnostics",
+                recorded: format!("{} columns", a_cells.len()),
+                rerun: format!("{} columns", b_cells.len()),
+            });
+        }
+        for ((column, x), (_, y)) in a_cells.into_iter().zip(b_cells) {
+            if x != y {
+                return Some(Mismatch::Value {
+                    row,
+                    t_s: a.t_s,
+                    column,
+                    recorded: x,
+                    rerun: y,
+                });
+            }
+        }
+    }
+    (recorded.len() != rerun.len()).then_some(Mismatch::RowCount {
+        recorded: recorded.len(),
+        rerun: rerun.len(),
+    })
+}
+
+fn major(version: &str) -> &str {
+    version.split('.').next().unwrap_or(version)
+}
+
+pub fn execute(args: &VerifyArgs, stdout: &mut dyn Write) -> Result<u8> {
+    let path = args.trace.display();
+    let (meta, recorded) = read_trace_with_meta(&args.trace)?;
+    let Some(meta) = meta else {
+        bail!(InvalidInput(format!(
+            "{path} has no _meta header; only JSONL traces written by `run` can be verified"
+        )));
+    };
+    let (Some(cfg), Some(spec), Some(options)) = (meta.config, meta.scenario_spec, meta.options)
+    else {
+        bail!(InvalidInput(format!(
+            "the header of {path} does not record the configuration needed to re-run it"
+        )));
+    };
+    let version = env!("CARGO_PKG_VERSION");
+    if major(&meta.header.crate_version) != major(version) && !args.allow_version_mismatch {
+        bail!(InvalidInput(format!(
+            "{path} was recorded by reactor-safety-sim {}, this is {version}; pass \
+             --allow-version-mismatch to verify it anyway",
+            meta.header.crate_version
+        )));
+    }
+    let hash = cfg.config_hash(&spec)?;
+    if hash != meta.header.config_hash {
+        writeln!(
+            stdout,
+            "MISMATCH: recorded configuration hashes to {hash}, the header says {}",
+            meta.header.config_hash
+        )?;
+        return Ok(exit::FAILED_CHECK);
+    }
+
+    let mut run_args = RunArgs::try_parse_from(["verify"])?;
+    run_args.apply_options(&options)?;
+    run_args.format = TraceFormat::Csv;
+    run_args.summary = SummaryFormat::None;
+    run_args.quiet = true;
+    let mut out = Vec::new();
+    simulate(&run_args, cfg, spec, &mut out, &AtomicBool::new(false))?;
+    let rerun = parse_trace(
+        std::str::from_utf8(&out).context("re-run trace is not UTF-8")?,
+        TraceFormat::Csv,
+    )?;
+
+    match first_mismatch(&recorded, &rerun) {
+        None => {
+            writeln!(stdout, "ok: {} rows of {path} reproduced", recorded.len())?;
+            Ok(exit::OK)
+        }
+        Some(Mismatch::Value {
+            row,
+            t_s,
+            column,
+            recorded,
+            rerun,
+        }) => {
+            writeln!(
+                stdout,
+                "MISMATCH at row {row} (t = {t_s} s), column {column}: recorded {recorded}, \
+                 re-run {rerun}"
+            )?;
+            Ok(exit::FAILED_CHECK)
+        }
+        Some(Mismatch::RowCount { recorded, rerun }) => {
+            writeln!(
+                stdout,
+                "MISMATCH: {path} has {recorded} rows, the re-run {rerun}"
+            )?;
+            Ok(exit::FAILED_CHECK)
+        }
+    }
+}
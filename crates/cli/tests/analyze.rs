@@ -0,0 +1,101 @@
+use clap::Parser;
+use cli::analyze::{sensitivity, AnalyzeArgs, AnalyzeCommand, SensitivityReport};
+use cli::{exit, Cli};
+
+const OVERHEAT: [&str; 8] = [
+    "--scenario",
+    "overheat",
+    "--setpoint",
+    "380",
+    "--trip-temp",
+    "390",
+    "--seconds",
+    "60",
+];
+
+fn report(params: &str, extra: &[&str]) -> SensitivityReport {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "analyze",
+        "sensitivity",
+        "--param",
+        params,
+    ];
+    argv.extend(OVERHEAT);
+    argv.extend_from_slice(extra);
+    let cli = Cli::try_parse_from(argv).unwrap();
+    let Some(cli::Command::Analyze(AnalyzeArgs {
+        command: AnalyzeCommand::Sensitivity(args),
+    })) = cli.command
+    else {
+        panic!("parsed as another command");
+    };
+    sensitivity(&args).unwrap()
+}
+
+#[test]
+fn cooling_coefficient_dominates_trip_time() {
+    let report = report("plant-ambient,ki,plant-k-cool", &[]);
+    assert_eq!(report.baseline_trip_t_s, Some(19.85));
+    let names: Vec<&str> = report.params.iter().map(|p| p.param.as_str()).collect();
+    assert_eq!(names[0], "plant-k-cool", "{report}");
+    let k_cool = &report.params[0];
+    assert_eq!(k_cool.baseline, 1.5);
+    // More cooling delays the trip.
+    assert!(k_cool.trip_time.unwrap() > 0.0, "{report}");
+    for p in &report.params[1..] {
+        assert!(p.trip_time.unwrap().abs() < k_cool.trip_time.unwrap().abs());
+    }
+}
+
+#[test]
+fn eliminated_trip_is_reported_not_nan() {
+    // ±2 % keeps the lowered trip temperature above the 380 °C setpoint
+    let report = report("trip-temp", &["--percent", "2"]);
+    let trip = &report.params[0];
+    assert_eq!(trip.trip_time, None);
+    assert_eq!(trip.trip_t_high_s, None);
+    assert!(trip.trip_t_low_s.is_some());
+    assert!(trip.max_temp.is_finite());
+    assert!(report.to_string().contains("no trip at +2 %"), "{report}");
+}
+
+#[test]
+fn json_report_lists_every_parameter() {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "analyze",
+        "sensitivity",
+        "--param",
+        "plant-k-cool",
+        "--param",
+        "trip-temp",
+        "--percent",
+        "2",
+        "--report",
+        "json",
+    ];
+    argv.extend(OVERHEAT);
+    let mut out = Vec::new();
+    let code = cli::execute(&Cli::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    assert_eq!(code, exit::OK);
+    let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    let params = json["params"].as_array().unwrap();
+    assert_eq!(params.len(), 2);
+    assert_eq!(params[0]["param"], "plant-k-cool");
+    assert!(params[1]["trip_time"].is_null());
+}
+
+#[test]
+fn zero_baseline_is_invalid_input() {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "analyze",
+        "sensitivity",
+        "--param",
+        "kd",
+    ];
+    argv.extend(OVERHEAT);
+    let err = cli::execute(&Cli::try_parse_from(argv).unwrap(), &mut Vec::new()).unwrap_err();
+    assert_eq!(exit::code_for(&err), exit::INVALID_INPUT);
+}
@@ -0,0 +1,148 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::Command;
+use cli::batch::{percentile, wilson_interval, SeedSpec};
+use common::scratch;
+
+fn batch(dir: &Path, extra: &[&str]) -> serde_json::Value {
+    Command::cargo_bin("cli")
+        .unwrap()
+        .args(["batch", "--seeds", "20", "--out-dir"])
+        .arg(dir)
+        .args(extra)
+        .assert()
+        .success();
+    let runs = fs::read_to_string(dir.join("runs.csv")).unwrap();
+    assert_eq!(runs.lines().count(), 1 + 20);
+    serde_json::from_str(&fs::read_to_string(dir.join("aggregate.json")).unwrap()).unwrap()
+}
+
+#[test]
+fn overheat_batch_always_trips() {
+    let dir = scratch("overheat");
+    let traces = dir.join("traces");
+    let agg = batch(
+        &dir,
+        &[
+            "--scenario",
+            "overheat",
+            "--setpoint",
+            "380",
+            "--trip-temp",
+            "390",
+            "--seconds",
+            "60",
+        ],
+    );
+    assert_eq!(agg["trips"], 20);
+    assert_eq!(agg["trip_probability"], 1.0);
+    let ci_lo = agg["trip_probability_ci95"][0].as_f64().unwrap();
+    assert!(ci_lo > 0.8 && ci_lo < 1.0, "{ci_lo}");
+
+    let p = |k: &str| agg["trip_time_s"][k].as_f64().unwrap();
+    assert!(p("p5") > 0.0);
+    assert!(p("p5") <= p("p25") && p("p25") <= p("p50"));
+    assert!(p("p50") <= p("p75") && p("p75") <= p("p95"));
+    assert!(p("p95") <= 60.0);
+    assert!(
+        !traces.exists(),
+        "traces are only written with --save-traces"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn normal_batch_never_trips() {
+    let dir = scratch("normal");
+    let traces = dir.join("traces");
+    let agg = batch(
+        &dir,
+        &[
+            "--scenario",
+            "normal",
+            "--seconds",
+            "30",
+            "--save-traces",
+            traces.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(agg["trips"], 0);
+    assert_eq!(agg["trip_probability"], 0.0);
+    assert!(agg["trip_time_s"].is_null());
+    assert_eq!(fs::read_dir(&traces).unwrap().count(), 20);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn seed_spec_forms() {
+    assert_eq!("3".parse::<SeedSpec>().unwrap().seeds(10), vec![10, 11, 12]);
+    assert_eq!("4..7".parse::<SeedSpec>().unwrap().seeds(0), vec![4, 5, 6]);
+    assert_eq!("4..=5".parse::<SeedSpec>().unwrap().seeds(0), vec![4, 5]);
+    assert_eq!(
+        "9, 2,7".parse::<SeedSpec>().unwrap().seeds(0),
+        vec![9, 2, 7]
+    );
+    assert!("0".parse::<SeedSpec>().is_err());
+    assert!("5..5".parse::<SeedSpec>().is_err());
+    assert!("a,b".parse::<SeedSpec>().is_err());
+}
+
+#[test]
+fn wilson_interval_matches_reference_values() {
+    // 20/20: lower bound 0.8389 (e.g. statsmodels proportion_confint, method="wilson")
+    let (lo, hi) = wilson_interval(20, 20, 1.96);
+    assert!((lo - 0.8389).abs() < 1e-3, "{lo}");
+    assert_eq!(hi, 1.0);
+    let (lo, hi) = wilson_interval(5, 10, 1.96);
+    assert!(
+        (lo - 0.2366).abs() < 1e-3 && (hi - 0.7634).abs() < 1e-3,
+        "{lo} {hi}"
+    );
+    assert_eq!(wilson_interval(0, 0, 1.96), (0.0, 1.0));
+}
+
+#[test]
+fn percentile_interpolates_between_samples() {
+    let v = [1.0, 2.0, 3.0, 4.0];
+    assert_eq!(percentile(&v, 0.0), 1.0);
+    assert_eq!(percentile(&v, 0.5), 2.5);
+    assert_eq!(percentile(&v, 1.0), 4.0);
+    assert_eq!(percentile(&[7.0], 0.95), 7.0);
+}
+
+#[test]
+fn parallel_batch_matches_serial_byte_for_byte() {
+    let runs_csv = |jobs: &str| {
+        let dir = scratch(&format!("jobs{jobs}"));
+        Command::cargo_bin("cli")
+            .unwrap()
+            .args([
+                "batch",
+                "--seeds",
+                "50",
+                "--seconds",
+                "20",
+                "--jobs",
+                jobs,
+                "--out-dir",
+            ])
+            .arg(&dir)
+            .args([
+                "--scenario",
+                "overheat",
+                "--setpoint",
+                "380",
+                "--trip-temp",
+                "390",
+            ])
+            .assert()
+            .success();
+        let csv = fs::read(dir.join("runs.csv")).unwrap();
+        fs::remove_dir_all(dir).unwrap();
+        csv
+    };
+    assert_eq!(runs_csv("1"), runs_csv("4"));
+}
@@ -0,0 +1,69 @@
+use clap::Parser;
+use cli::bench::{bench, BenchArgs, BenchOutput};
+use cli::{exit, Cli};
+
+fn parse(extra: &[&str]) -> BenchArgs {
+    let mut argv = vec!["reactor-safety-sim", "bench"];
+    argv.extend_from_slice(extra);
+    match Cli::try_parse_from(argv).unwrap().command {
+        Some(cli::Command::Bench(args)) => args,
+        _ => panic!("parsed as another command"),
+    }
+}
+
+#[test]
+fn every_configuration_and_stage_reports_a_throughput() {
+    let report = bench(&parse(&["--steps", "2000", "--dt-ms", "10,100"])).unwrap();
+    assert_eq!(report.scenario, "normal");
+    let configs: Vec<(u64, BenchOutput)> =
+        report.results.iter().map(|r| (r.dt_ms, r.output)).collect();
+    assert_eq!(
+        configs,
+        [
+            (10, BenchOutput::None),
+            (10, BenchOutput::Jsonl),
+            (10, BenchOutput::Diagnostics),
+            (100, BenchOutput::None),
+            (100, BenchOutput::Jsonl),
+            (100, BenchOutput::Diagnostics),
+        ]
+    );
+    for r in &report.results {
+        assert_eq!(r.steps, 2000);
+        assert!(r.steps_per_s > 0.0 && r.steps_per_s.is_finite(), "{r:?}");
+        for ns in [r.plant_ns, r.sensors_ns, r.safety_ns, r.pid_ns] {
+            assert!(ns > 0.0, "{r:?}");
+        }
+    }
+}
+
+#[test]
+fn bench_prints_a_table_or_json() {
+    let mut out = Vec::new();
+    let code = cli::execute(
+        &Cli::try_parse_from(["reactor-safety-sim", "bench", "--steps", "500"]).unwrap(),
+        &mut out,
+    )
+    .unwrap();
+    assert_eq!(code, exit::OK);
+    let text = String::from_utf8(out).unwrap();
+    assert!(text.contains("steps/s"), "{text}");
+    // header, blank line, column titles and one row per configuration
+    assert_eq!(text.lines().count(), 3 + 3, "{text}");
+
+    let mut out = Vec::new();
+    cli::bench::execute(&parse(&["--steps", "500", "--report", "json"]), &mut out).unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(results
+        .iter()
+        .all(|r| r["steps_per_s"].as_f64().unwrap() > 0.0));
+    assert_eq!(results[2]["output"], "diagnostics");
+}
+
+#[test]
+fn zero_steps_are_refused() {
+    let err = bench(&parse(&["--steps", "0"])).unwrap_err();
+    assert_eq!(exit::code_for(&err), exit::INVALID_INPUT);
+}
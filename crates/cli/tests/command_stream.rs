@@ -0,0 +1,90 @@
+use assert_cmd::Command;
+use cli::command::{ExternalCommand, TimedCommand};
+use cli::trace::{parse_trace, TraceFormat};
+use sim::{EventAction, SensorFault};
+
+const SCRIPT: &str = r#"{"at": 5.0, "cmd": {"set_setpoint": 320}}
+{"cmd": {"warp_drive": 9}}
+
+{"at": 8.0, "cmd": "scram"}
+"#;
+
+#[test]
+fn scripted_setpoint_change_and_scram_take_effect_at_their_rows() {
+    let assert = Command::cargo_bin("cli")
+        .unwrap()
+        .args(["run", "--scenario", "normal", "--seconds", "20"])
+        .args(["--summary", "none", "--quiet", "--command-stream"])
+        .env_remove("RUST_LOG")
+        .write_stdin(SCRIPT)
+        .assert()
+        .success();
+    let out = assert.get_output();
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("unknown command"), "{stderr}");
+    assert!(stderr.contains("line=2"), "{stderr}");
+
+    let rows = parse_trace(&String::from_utf8_lossy(&out.stdout), TraceFormat::Jsonl).unwrap();
+    for row in &rows {
+        let expected = if row.t_s < 5.0 { 350.0 } else { 320.0 };
+        assert_eq!(row.setpoint_c, expected, "t = {}", row.t_s);
+        assert_eq!(row.scram, row.t_s >= 8.0, "t = {}", row.t_s);
+    }
+    let last = rows.last().unwrap();
+    assert_eq!(last.t_s, 8.0);
+    assert_eq!(last.reason_name().as_deref(), Some("Manual"));
+}
+
+#[test]
+fn scripted_commands_are_recorded_in_the_trace_header() {
+    let assert = Command::cargo_bin("cli")
+        .unwrap()
+        .args(["run", "--scenario", "normal", "--seconds", "10"])
+        .args(["--summary", "none", "--quiet", "--command-stream"])
+        .write_stdin(r#"{"at": 2.5, "cmd": {"fault": "s2:bias:20"}}"#)
+        .assert()
+        .success();
+    let stdout = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    let header: serde_json::Value = serde_json::from_str(stdout.lines().next().unwrap()).unwrap();
+    let windows = &header["_meta"]["scenario_spec"]["sensors"]["s2"];
+    assert_eq!(windows[0]["from_s"], 2.5);
+    assert_eq!(windows[0]["fault"]["kind"], "bias");
+    // the biased sensor trips the run when the fault arrives
+    let rows = parse_trace(&stdout, TraceFormat::Jsonl).unwrap();
+    let last = rows.last().unwrap();
+    assert_eq!(last.t_s, 2.5);
+    assert_eq!(last.reason_name().as_deref(), Some("SensorDisagree"));
+}
+
+#[test]
+fn command_lines_parse_or_explain() {
+    let parse = |line: &str| TimedCommand::parse(line).map(Option::unwrap);
+    assert_eq!(
+        parse(r#"{"at": 1, "cmd": {"set_coolant": 0.25}}"#),
+        Ok(TimedCommand {
+            at_s: Some(1.0),
+            command: ExternalCommand::Event(EventAction::SetCoolant(0.25)),
+        })
+    );
+    let fault = parse(r#"{"cmd": {"fault": "s3:stuck:300"}}"#).unwrap();
+    assert_eq!(fault.at_s, None);
+    let ExternalCommand::Fault(fault) = fault.command else {
+        panic!("a fault command");
+    };
+    assert_eq!(fault.sensor, 2);
+    assert_eq!(fault.fault, SensorFault::Stuck { value: 300.0 });
+    assert_eq!(TimedCommand::parse("  "), Ok(None));
+
+    for (line, error) in [
+        (r#"{"cmd": "reboot"}"#, "unknown command"),
+        (r#"{"at": -1, "cmd": "scram"}"#, "\"at\" must be"),
+        (r#"{"cmd": {"set_coolant": 2}}"#, "within 0..=1"),
+        (r#"{"cmd": {"fault": "s1:bias:5@3s"}}"#, "from \"at\""),
+        (r#"{"cmd": {"fault": "s4:bias:5"}}"#, "unknown sensor"),
+        (r#"{"at": 1}"#, "not a command line"),
+        ("scram", "not a command line"),
+    ] {
+        let e = TimedCommand::parse(line).unwrap_err();
+        assert!(e.contains(error), "{line}: {e}");
+    }
+}
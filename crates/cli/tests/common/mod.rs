@@ -0,0 +1,13 @@
+//! Helpers shared by the integration tests, each of which includes them with `mod common;`.
+
+use std::fs;
+use std::path::PathBuf;
+
+/// Fresh, empty scratch directory per test (removed first so reruns start clean), unique to the
+/// test binary's process.
+pub fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rss-cli-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+use clap::Parser;
+use cli::trace::{read_trace, TraceFormat, TraceRow, GZIP_MAGIC};
+use cli::RunArgs;
+use common::scratch;
+
+fn write(path: &Path, extra: &[&str]) -> Vec<TraceRow> {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--scenario",
+        "sensor-disagree",
+        "--seconds",
+        "20",
+        "--seed",
+        "7",
+    ];
+    argv.extend_from_slice(extra);
+    let args = RunArgs::try_parse_from(argv).unwrap();
+    cli::run_to_file(&args, path, &AtomicBool::new(false)).unwrap();
+    read_trace(path).unwrap()
+}
+
+fn assert_same(a: &[TraceRow], b: &[TraceRow]) {
+    assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter().zip(b) {
+        // CSV stores the bare reason name, so rows are compared in their canonical text form
+        assert_eq!(a.cells(), b.cells());
+    }
+}
+
+#[test]
+fn gz_extension_compresses_and_reads_back() {
+    let dir = scratch("ext");
+    let plain = write(&dir.join("trace.jsonl"), &[]);
+    let path = dir.join("trace.jsonl.gz");
+    let compressed = write(&path, &[]);
+    assert!(fs::read(&path).unwrap().starts_with(&GZIP_MAGIC));
+    assert!(!plain.is_empty());
+    assert_same(&compressed, &plain);
+
+    let csv = write(&dir.join("trace.csv.gz"), &["--format", "csv"]);
+    assert_same(&csv, &plain);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn compress_flag_compresses_any_name() {
+    let dir = scratch("flag");
+    let plain = write(&dir.join("plain.jsonl"), &[]);
+    let path = dir.join("packed.jsonl");
+    let compressed = write(&path, &["--compress"]);
+    assert!(fs::read(&path).unwrap().starts_with(&GZIP_MAGIC));
+    assert_same(&compressed, &plain);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn format_ignores_the_gz_extension() {
+    assert_eq!(
+        TraceFormat::from_path(Path::new("out/t.csv.gz")),
+        TraceFormat::Csv
+    );
+    assert_eq!(
+        TraceFormat::from_path(Path::new("out/t.jsonl.gz")),
+        TraceFormat::Jsonl
+    );
+    assert_eq!(TraceFormat::from_path(Path::new("t.CSV")), TraceFormat::Csv);
+}
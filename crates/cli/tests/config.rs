@@ -0,0 +1,75 @@
+use clap::Parser;
+use cli::config::SimConfig;
+use cli::{RunArgs, Scenario};
+use controller::PidForm;
+
+const SAMPLE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/sample.toml");
+
+fn args(extra: &[&str]) -> RunArgs {
+    let mut argv = vec!["reactor-safety-sim", "--config", SAMPLE];
+    argv.extend_from_slice(extra);
+    RunArgs::try_parse_from(argv).unwrap()
+}
+
+#[test]
+fn config_file_sets_values_and_keeps_defaults_for_missing_keys() {
+    let cfg = args(&[]).sim_config().unwrap();
+    assert!(matches!(cfg.run.scenario, Scenario::Overheat));
+    assert_eq!(cfg.run.seconds, 60.0);
+    assert_eq!(cfg.run.dt_ms, 100);
+    assert_eq!(cfg.plant.thermal_mass, 120.0);
+    assert_eq!(cfg.plant.k_power, sim::PlantParams::default().k_power);
+    assert_eq!(cfg.sensors.s2.noise_std, 0.5);
+    assert_eq!(cfg.sensors.s1.noise_std, 0.15);
+    assert_eq!(cfg.pid.kp, 0.02);
+    assert_eq!(cfg.pid.form, PidForm::Velocity);
+    assert_eq!(cfg.pid.out_max, controller::PidConfig::default().out_max);
+    assert_eq!(cfg.safety.trip_temp_c, 430.0);
+}
+
+#[test]
+fn cli_flags_override_config_file() {
+    let cfg = args(&[
+        "--seconds",
+        "10",
+        "--trip-temp",
+        "425",
+        "--scenario",
+        "normal",
+    ])
+    .sim_config()
+    .unwrap();
+    assert!(matches!(cfg.run.scenario, Scenario::Normal));
+    assert_eq!(cfg.run.seconds, 10.0);
+    assert_eq!(cfg.safety.trip_temp_c, 425.0);
+    // values without a flag still come from the file
+    assert_eq!(cfg.run.dt_ms, 100);
+    assert_eq!(cfg.safety.max_sensor_delta_c, 12.0);
+}
+
+#[test]
+fn unknown_keys_name_key_and_section() {
+    let err = SimConfig::from_toml("[pid]\nkp = 0.1\nkpp = 0.2\n").unwrap_err();
+    let msg = err.to_string();
+    assert!(msg.contains("[pid]") && msg.contains("kpp"), "{msg}");
+
+    let err = SimConfig::from_toml("[sensors.s4]\nnoise_std = 1.0\n").unwrap_err();
+    assert!(err.to_string().contains("sensors.s4"), "{err}");
+
+    let err = SimConfig::from_toml("[plnt]\n").unwrap_err();
+    assert!(err.to_string().contains("[plnt]"), "{err}");
+}
+
+#[test]
+fn print_config_round_trips() {
+    let mut out = Vec::new();
+    cli::run_command(&args(&["--print-config", "--seed", "99"]), &mut out).unwrap();
+    let dumped = String::from_utf8(out).unwrap();
+
+    let reloaded = SimConfig::from_toml(&dumped).unwrap();
+    let original = args(&["--seed", "99"]).sim_config().unwrap();
+    assert_eq!(reloaded.run.seed, 99);
+    // dumping the reloaded config reproduces the text exactly
+    assert_eq!(reloaded.to_toml().unwrap(), dumped);
+    assert_eq!(original.to_toml().unwrap(), dumped);
+}
@@ -0,0 +1,59 @@
+use clap::Parser;
+use cli::trace::{parse_trace, Decimator, TraceFormat, TraceRow};
+use cli::RunArgs;
+
+fn run(extra: &[&str]) -> (Vec<TraceRow>, cli::RunSummary) {
+    let mut argv = vec!["reactor-safety-sim", "--format", "csv", "--seconds", "60"];
+    argv.extend_from_slice(extra);
+    let mut out = Vec::new();
+    let summary = cli::run(&RunArgs::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    let rows = parse_trace(&String::from_utf8(out).unwrap(), TraceFormat::Csv).unwrap();
+    (rows, summary)
+}
+
+#[test]
+fn decimator_keeps_every_nth_and_state_changes() {
+    let mut d = Decimator::new(4);
+    let kept: Vec<u64> = (0..10)
+        .filter(|&k| d.keep(k, k >= 6, k == 2 || k == 3))
+        .collect();
+    // 0, 4, 8 by period; 2 alarm on, 3 stays on; 4 alarm off; 6 trips
+    assert_eq!(kept, [0, 2, 4, 6, 8]);
+}
+
+#[test]
+fn decimated_trace_keeps_first_and_last_rows() {
+    let (full, full_summary) = run(&["--scenario", "normal"]);
+    let (rows, summary) = run(&["--scenario", "normal", "--decimate", "10"]);
+    assert_eq!(full.len(), 1200);
+    // rows 0, 10, .., 1190 and the final row 1199
+    assert_eq!(rows.len(), 121);
+    assert_eq!(rows[0].t_s, full[0].t_s);
+    assert_eq!(rows.last().unwrap().t_s, full.last().unwrap().t_s);
+    assert_eq!(summary.steps, full_summary.steps);
+}
+
+#[test]
+fn scram_transition_row_is_kept_for_any_period() {
+    let overheat = [
+        "--scenario",
+        "overheat",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+        "--continue-after-scram",
+    ];
+    let (full, full_summary) = run(&overheat);
+    let trip_t_s = full_summary.trip_t_s.expect("overheat trips");
+    for n in ["3", "7", "10", "64", "1000"] {
+        let (rows, summary) = run(&[&overheat[..], &["--decimate", n]].concat());
+        let first_scram = rows.iter().find(|r| r.scram).expect("scram row");
+        assert_eq!(first_scram.t_s, trip_t_s, "--decimate {n}");
+        assert_eq!(first_scram.reason_name().as_deref(), Some("OverTemp"));
+        assert!(rows.len() < full.len());
+        // the summary is computed from every step, not from the written rows
+        assert_eq!(summary.max_temp_c, full_summary.max_temp_c);
+        assert_eq!(summary.trip_t_s, full_summary.trip_t_s);
+    }
+}
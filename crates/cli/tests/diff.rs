@@ -0,0 +1,120 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+use clap::Parser;
+use cli::diff::diff;
+use cli::trace::{parse_trace, read_trace, TraceFormat};
+use cli::{exit, Cli, RunArgs};
+use common::scratch;
+
+fn record(path: &Path, extra: &[&str]) {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--scenario",
+        "overheat",
+        "--seconds",
+        "60",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+    ];
+    argv.extend_from_slice(extra);
+    let mut args = RunArgs::try_parse_from(argv).unwrap();
+    args.format = TraceFormat::from_path(path);
+    cli::run_to_file(&args, path, &AtomicBool::new(false)).unwrap();
+}
+
+fn diff_cmd(a: &Path, b: &Path, flags: &[&str]) -> (u8, String) {
+    let mut argv = vec!["reactor-safety-sim", "diff"];
+    argv.extend([a.to_str().unwrap(), b.to_str().unwrap()]);
+    argv.extend_from_slice(flags);
+    let mut out = Vec::new();
+    let code = cli::execute(&Cli::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    (code, String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn trace_against_itself_is_identical() {
+    let dir = scratch("self");
+    let a = dir.join("a.jsonl");
+    record(&a, &[]);
+    let (code, out) = diff_cmd(&a, &a, &[]);
+    assert_eq!(code, exit::OK, "{out}");
+    assert!(out.contains("max 0.0000 °C, rms 0.0000 °C"), "{out}");
+    assert!(out.contains("(+0.00 s)"), "{out}");
+    assert!(out.contains("diverges          never"), "{out}");
+
+    let rows = read_trace(&a).unwrap();
+    let d = diff(&rows, &rows, 0.0);
+    assert_eq!(d.rows_compared, rows.len());
+    assert_eq!((d.max_temp_diff_c, d.max_power_diff), (0.0, 0.0));
+    assert_eq!(d.first_divergence_s, None);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn different_seed_is_reported_and_fails() {
+    let dir = scratch("seed");
+    let (a, b) = (dir.join("a.csv"), dir.join("b.csv"));
+    record(&a, &[]);
+    record(&b, &["--seed", "2"]);
+    let (code, out) = diff_cmd(&a, &b, &[]);
+    assert_eq!(code, exit::FAILED_CHECK, "{out}");
+    assert!(out.contains("DIFFERENT"), "{out}");
+
+    let d = diff(&read_trace(&a).unwrap(), &read_trace(&b).unwrap(), 0.01);
+    assert!(d.max_temp_diff_c > 0.0 && d.rms_temp_diff_c > 0.0);
+    assert!(d.rms_temp_diff_c <= d.max_temp_diff_c);
+    assert!(d.first_divergence_s.is_some());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn different_dt_is_interpolated() {
+    let dir = scratch("dt");
+    let (a, b) = (dir.join("a.csv"), dir.join("b.csv"));
+    record(&a, &[]);
+    record(&b, &["--dt-ms", "100"]);
+    let (rows_a, rows_b) = (read_trace(&a).unwrap(), read_trace(&b).unwrap());
+    let d = diff(&rows_a, &rows_b, 0.01);
+    let end_b = rows_b.last().unwrap().t_s;
+    // every 50 ms row inside the 100 ms trace's span, half of them between two of its rows
+    assert_eq!(
+        d.rows_compared,
+        rows_a.iter().filter(|r| r.t_s <= end_b).count()
+    );
+    assert!(d.rows_compared > rows_b.len());
+    assert!(d.max_temp_diff_c < 1.0, "{d}");
+
+    let (code, out) = diff_cmd(&a, &b, &["--tol-temp", "1", "--tol-time", "0.5"]);
+    assert_eq!(code, exit::OK, "{out}");
+    let (code, _) = diff_cmd(&a, &b, &["--tol-temp", "1"]);
+    assert_eq!(code, exit::FAILED_CHECK, "trip times differ by one step");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn interpolation_is_exact_for_linear_channels() {
+    let csv = |dt: f64, n: usize| {
+        let mut text =
+            String::from("t_s,setpoint_c,true_temp_c,s1_c,s2_c,s3_c,power,coolant,scram,reason\n");
+        for k in 0..n {
+            let t = k as f64 * dt;
+            let temp = 300.0 + 2.0 * t;
+            text += &format!(
+                "{t},350,{temp},{temp},{temp},{temp},{},0.6,false,\n",
+                t / 10.0
+            );
+        }
+        parse_trace(&text, TraceFormat::Csv).unwrap()
+    };
+    let (fine, coarse) = (csv(0.25, 9), csv(1.0, 3));
+    let d = diff(&fine, &coarse, 1e-9);
+    assert_eq!(d.rows_compared, 9);
+    assert!(d.max_temp_diff_c < 1e-9 && d.max_power_diff < 1e-12, "{d}");
+    assert_eq!(d.first_divergence_s, None);
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use std::fs;
+
+use assert_cmd::Command;
+use cli::exit;
+use common::scratch;
+
+fn bin() -> Command {
+    Command::cargo_bin("cli").unwrap()
+}
+
+const OVERHEAT: [&str; 9] = [
+    "run",
+    "--scenario",
+    "overheat",
+    "--setpoint",
+    "380",
+    "--trip-temp",
+    "390",
+    "--seconds",
+    "60",
+];
+
+#[test]
+fn completed_run_exits_zero() {
+    bin()
+        .args(["run", "--seconds", "1"])
+        .assert()
+        .code(exit::OK as i32);
+}
+
+#[test]
+fn trip_fails_only_with_fail_on_trip() {
+    bin().args(OVERHEAT).assert().code(exit::OK as i32);
+    bin()
+        .args(OVERHEAT)
+        .arg("--fail-on-trip")
+        .assert()
+        .code(exit::FAILED_CHECK as i32);
+}
+
+#[test]
+fn bad_arguments_exit_with_invalid_input() {
+    bin()
+        .args(["run", "--scenario", "meltdown"])
+        .assert()
+        .code(exit::INVALID_INPUT as i32);
+    bin()
+        .args(["run", "--auto-gains", "0"])
+        .assert()
+        .code(exit::INVALID_INPUT as i32);
+}
+
+#[test]
+fn invalid_config_file_is_one_line_error() {
+    let dir = scratch("config");
+    let path = dir.join("bad.toml");
+    fs::write(&path, "[pid]\nkp = 0.1\nkpp = 0.2\n").unwrap();
+    let assert = bin()
+        .args(["run", "--config"])
+        .arg(&path)
+        .assert()
+        .code(exit::INVALID_INPUT as i32);
+    let stderr = String::from_utf8_lossy(&assert.get_output().stderr).into_owned();
+    let line = stderr.trim_end();
+    assert!(
+        line.starts_with("error: ") && line.contains("kpp"),
+        "{stderr}"
+    );
+    assert!(
+        !line.contains('\n') && !line.contains("backtrace"),
+        "{stderr}"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn io_failures_exit_with_io() {
+    let dir = scratch("io");
+    bin()
+        .args(["run", "--config"])
+        .arg(dir.join("missing.toml"))
+        .assert()
+        .code(exit::IO as i32);
+
+    // --out below a regular file: the parent directory cannot be created
+    let blocker = dir.join("blocker");
+    fs::write(&blocker, "").unwrap();
+    bin()
+        .args(["run", "--seconds", "1", "--out"])
+        .arg(blocker.join("trace.jsonl"))
+        .assert()
+        .code(exit::IO as i32);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn help_documents_exit_codes() {
+    let assert = bin().arg("--help").assert().code(exit::OK as i32);
+    let help = String::from_utf8_lossy(&assert.get_output().stdout).into_owned();
+    assert!(help.contains("Exit codes:"), "{help}");
+}
@@ -0,0 +1,89 @@
+use clap::Parser;
+use cli::fault::FaultArg;
+use cli::RunArgs;
+use sim::SensorFault;
+
+fn parse(s: &str) -> FaultArg {
+    s.parse().unwrap_or_else(|e| panic!("{s:?}: {e}"))
+}
+
+fn parse_err(s: &str) -> String {
+    s.parse::<FaultArg>().unwrap_err()
+}
+
+#[test]
+fn parses_fault_flags() {
+    assert_eq!(
+        parse("s2:bias:+20"),
+        FaultArg {
+            sensor: 1,
+            fault: SensorFault::Bias { value: 20.0 },
+            from_s: 0.0
+        }
+    );
+    assert_eq!(
+        parse("s1:dropout:50").fault,
+        SensorFault::DropoutEvery { n: 50 }
+    );
+    let drift = parse("s3:drift:0.5@30s");
+    assert_eq!(drift.sensor, 2);
+    assert_eq!(drift.fault, SensorFault::Drift { per_s: 0.5 });
+    assert_eq!(drift.from_s, 30.0);
+    assert_eq!(parse("s1:stuck:-5@12.5").from_s, 12.5);
+    assert_eq!(parse("s1:none@40s").fault, SensorFault::None);
+}
+
+#[test]
+fn display_round_trips() {
+    for s in [
+        "s2:bias:-3.5",
+        "s1:dropout:7@2s",
+        "s3:drift:0.5@30s",
+        "s1:none@40s",
+    ] {
+        let f = parse(s);
+        assert_eq!(f.to_string(), s);
+        assert_eq!(parse(&f.to_string()), f);
+    }
+    assert_eq!(parse("s2:bias:+20").to_string(), "s2:bias:20");
+}
+
+#[test]
+fn rejects_bad_fault_flags() {
+    for (s, expected) in [
+        ("s4:bias:20", "unknown sensor \"s4\""),
+        ("sensor2:bias:20", "expected s1, s2 or s3"),
+        ("s2:offset:20", "unknown fault kind \"offset\""),
+        ("s2", "missing fault kind"),
+        ("s2:bias", "bias needs a value"),
+        ("s2:bias:twenty", "invalid bias value \"twenty\""),
+        ("s2:drift:inf", "invalid drift value"),
+        ("s1:dropout:0", "invalid dropout period \"0\""),
+        ("s1:dropout:2.5", "invalid dropout period"),
+        ("s3:drift:0.5@soon", "invalid start time \"soon\""),
+        ("s3:drift:0.5@-1s", "invalid start time"),
+        ("s2:bias:20:1", "too many fields"),
+        ("s2:none:1", "none takes no value"),
+    ] {
+        let err = parse_err(s);
+        assert!(err.contains(expected), "{s:?}: {err}");
+    }
+}
+
+#[test]
+fn bias_flag_reproduces_sensor_disagree() {
+    let run = |argv: &[&str]| {
+        let mut full = vec!["reactor-safety-sim", "--format", "csv", "--seconds", "30"];
+        full.extend_from_slice(argv);
+        let mut out = Vec::new();
+        let summary = cli::run(&RunArgs::try_parse_from(full).unwrap(), &mut out).unwrap();
+        (String::from_utf8(out).unwrap(), summary)
+    };
+    let (expected, builtin) = run(&["--scenario", "sensor-disagree"]);
+    let (actual, flagged) = run(&["--scenario", "normal", "--fault", "s2:bias:+20"]);
+    assert_eq!(flagged.reason.as_deref(), Some("SensorDisagree"));
+    assert_eq!(flagged.trip_t_s, builtin.trip_t_s);
+    assert_eq!(actual, expected);
+    assert_eq!(flagged.faults, ["s2:bias:20"]);
+    assert!(builtin.faults.is_empty());
+}
@@ -0,0 +1,145 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::Command;
+use cli::config::fnv1a_hex;
+use cli::exit;
+use cli::golden::{compare, Divergence, HASHES_FILE};
+use common::scratch;
+
+const SHIPPED: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+
+fn copy_shipped(dir: &Path) {
+    for entry in fs::read_dir(SHIPPED).unwrap() {
+        let path = entry.unwrap().path();
+        fs::copy(&path, dir.join(path.file_name().unwrap())).unwrap();
+    }
+}
+
+fn golden(sub: &str, dir: &Path) -> (i32, String) {
+    let out = Command::cargo_bin("cli")
+        .unwrap()
+        .args(["golden", sub, "--dir"])
+        .arg(dir)
+        .output()
+        .unwrap();
+    let text =
+        String::from_utf8_lossy(&out.stdout).into_owned() + &String::from_utf8_lossy(&out.stderr);
+    (out.status.code().unwrap(), text)
+}
+
+/// `text` with the cell in `column` of data row `row` (from 1) replaced by `f(cell)`.
+fn edit_cell(text: &str, row: usize, column: &str, f: impl Fn(f64) -> String) -> String {
+    let mut lines: Vec<String> = text.lines().map(String::from).collect();
+    let j = lines[0].split(',').position(|c| c == column).unwrap();
+    let mut cells: Vec<String> = lines[row].split(',').map(String::from).collect();
+    cells[j] = f(cells[j].parse().unwrap());
+    lines[row] = cells.join(",");
+    lines.join("\n") + "\n"
+}
+
+#[test]
+fn shipped_golden_traces_still_match() {
+    let (code, out) = golden("check", Path::new(SHIPPED));
+    assert_eq!(code, i32::from(exit::OK), "{out}");
+    for scenario in ["normal", "overheat", "loss-of-cooling", "sensor-disagree"] {
+        assert!(
+            out.contains(&format!("{scenario:<16} ok (1200 rows)")),
+            "{out}"
+        );
+    }
+}
+
+#[test]
+fn recording_again_reproduces_the_shipped_files() {
+    let dir = scratch("record");
+    let (code, out) = golden("record", &dir);
+    assert_eq!(code, i32::from(exit::OK), "{out}");
+    assert!(out.contains("recorded 4 golden traces"), "{out}");
+    for file in [HASHES_FILE, "normal.csv", "sensor-disagree.csv"] {
+        assert_eq!(
+            fs::read_to_string(dir.join(file)).unwrap(),
+            fs::read_to_string(Path::new(SHIPPED).join(file)).unwrap(),
+            "{file}"
+        );
+    }
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn a_behavior_change_names_the_scenario_time_and_column() {
+    let dir = scratch("changed");
+    copy_shipped(&dir);
+    // as if the controller had changed: alter a recording and re-hash it
+    let path = dir.join("overheat.csv");
+    let text = edit_cell(&fs::read_to_string(&path).unwrap(), 401, "i_term", |v| {
+        format!("{:.6}", v + 0.001)
+    });
+    fs::write(&path, &text).unwrap();
+    let hashes = fs::read_to_string(dir.join(HASHES_FILE)).unwrap();
+    let hashes: String = hashes
+        .lines()
+        .map(|line| match line.strip_suffix("  overheat.csv") {
+            Some(_) => format!("{}  overheat.csv\n", fnv1a_hex(text.as_bytes())),
+            None => format!("{line}\n"),
+        })
+        .collect();
+    fs::write(dir.join(HASHES_FILE), hashes).unwrap();
+
+    let (code, out) = golden("check", &dir);
+    assert_eq!(code, i32::from(exit::FAILED_CHECK), "{out}");
+    assert!(out.contains("normal           ok"), "{out}");
+    assert!(
+        out.contains("overheat         DIFFERS: first divergence at t = 20.000000 s (row 401), column i_term"),
+        "{out}"
+    );
+    assert!(out.contains("1 of 4 scenarios no longer match"), "{out}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn a_hand_edited_recording_is_refused() {
+    let dir = scratch("edited");
+    copy_shipped(&dir);
+    let path = dir.join("normal.csv");
+    let text = fs::read_to_string(&path)
+        .unwrap()
+        .replace("350.000000", "351.000000");
+    fs::write(&path, text).unwrap();
+    let (code, out) = golden("check", &dir);
+    assert_eq!(code, i32::from(exit::INVALID_INPUT), "{out}");
+    assert!(out.contains("does not match its hash"), "{out}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn comparison_ignores_formatting_but_not_real_changes() {
+    let recorded = fs::read_to_string(Path::new(SHIPPED).join("normal.csv")).unwrap();
+    assert_eq!(compare(&recorded, &recorded), None);
+    // other number formatting, rounding noise in the last place
+    let reformatted = edit_cell(&recorded, 10, "true_temp_c", |v| v.to_string());
+    assert_eq!(compare(&recorded, &reformatted), None);
+    let last_digit = edit_cell(&recorded, 10, "true_temp_c", |v| format!("{:.6}", v + 2e-6));
+    assert_eq!(compare(&recorded, &last_digit), None);
+
+    let moved = edit_cell(&recorded, 10, "true_temp_c", |v| format!("{:.6}", v + 1e-4));
+    let Some(Divergence::Value { row, column, .. }) = compare(&recorded, &moved) else {
+        panic!("a change of 1e-4 is reported");
+    };
+    assert_eq!((row, column.as_str()), (10, "true_temp_c"));
+
+    let shorter: String = recorded
+        .lines()
+        .take(600)
+        .map(|l| format!("{l}\n"))
+        .collect();
+    assert_eq!(
+        compare(&recorded, &shorter),
+        Some(Divergence::RowCount {
+            recorded: 1200,
+            now: 599
+        })
+    );
+}
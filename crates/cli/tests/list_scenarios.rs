@@ -0,0 +1,56 @@
+use clap::{Parser, ValueEnum};
+use cli::{exit, Cli, Scenario};
+
+fn list(flags: &[&str]) -> String {
+    let argv = [&["reactor-safety-sim", "list-scenarios"][..], flags].concat();
+    let mut out = Vec::new();
+    let code = cli::execute(&Cli::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    assert_eq!(code, exit::OK);
+    String::from_utf8(out).unwrap()
+}
+
+fn block<'a>(text: &'a str, name: &str) -> &'a str {
+    text.split("\n\n")
+        .find(|b| b.starts_with(&format!("{name} - ")))
+        .unwrap_or_else(|| panic!("no {name} in:\n{text}"))
+}
+
+#[test]
+fn every_builtin_scenario_is_listed_from_its_spec() {
+    let text = list(&[]);
+    for scenario in Scenario::value_variants() {
+        let spec = scenario.spec(120.0);
+        let b = block(&text, &spec.name);
+        assert!(b.contains(&spec.description), "{b}");
+        assert!(
+            b.contains(&format!("coolant {}", spec.initial.coolant)),
+            "{b}"
+        );
+        assert!(!b.contains("outcome"), "{b}");
+    }
+    assert!(block(&text, "sensor-disagree").contains("faults   s2:bias:20"));
+    assert!(block(&text, "loss-of-cooling").contains("events   36.0 s coolant -> 0.05"));
+}
+
+#[test]
+fn detail_runs_each_scenario() {
+    let text = list(&["--detail"]);
+    assert!(block(&text, "normal").contains("outcome  no trip in 120 s"));
+    assert!(block(&text, "sensor-disagree").contains("outcome  trips (SensorDisagree) at 0.00 s"));
+}
+
+#[test]
+fn detail_reports_the_overheat_trip() {
+    let text = list(&["--detail", "--setpoint", "380", "--trip-temp", "390"]);
+    assert!(
+        block(&text, "overheat").contains("outcome  trips (OverTemp) at 19.85 s"),
+        "{text}"
+    );
+}
+
+#[test]
+fn scenario_flag_limits_the_list() {
+    let text = list(&["--scenario", "overheat", "--seconds", "200"]);
+    assert!(text.starts_with("overheat - "), "{text}");
+    assert!(!text.contains("normal - "), "{text}");
+}
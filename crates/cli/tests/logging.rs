@@ -0,0 +1,98 @@
+mod common;
+
+use std::fs;
+
+use assert_cmd::Command;
+use common::scratch;
+
+/// Debug log of a quiet run with `flags`.
+fn debug_log(name: &str, flags: &[&str]) -> String {
+    let dir = scratch(name);
+    let path = dir.join("debug.log");
+    Command::cargo_bin("cli")
+        .unwrap()
+        .args(["run", "--seconds", "10", "--summary", "none", "--quiet"])
+        .args(flags)
+        .args(["--log-level", "debug", "--log-file"])
+        .arg(&path)
+        .env_remove("RUST_LOG")
+        .assert()
+        .success();
+    let log = fs::read_to_string(&path).unwrap();
+    fs::remove_dir_all(dir).unwrap();
+    log
+}
+
+/// The single line logging `message`.
+fn event<'a>(log: &'a str, message: &str) -> &'a str {
+    let lines: Vec<&str> = log.lines().filter(|l| l.contains(message)).collect();
+    assert_eq!(lines.len(), 1, "{message:?} in:\n{log}");
+    lines[0]
+}
+
+#[test]
+fn sensor_disagree_run_logs_fault_and_trip_at_start() {
+    let log = debug_log("builtin", &["--scenario", "sensor-disagree"]);
+    let fault = event(&log, "sensor fault switched");
+    assert!(fault.contains("step{t_s=0.0}"), "{fault}");
+    assert!(fault.contains(r#"sensor="s2""#), "{fault}");
+    assert!(fault.contains("Bias { value: 20.0 }"), "{fault}");
+    let trip = event(&log, ": trip ");
+    assert!(trip.contains("step{t_s=0.0}"), "{trip}");
+    assert!(trip.contains("SensorDisagree"), "{trip}");
+    assert!(trip.contains("scenario=sensor-disagree"), "{trip}");
+}
+
+#[test]
+fn timed_fault_logs_activation_and_trip_at_its_time() {
+    let log = debug_log(
+        "timed",
+        &["--scenario", "normal", "--fault", "s2:bias:+20@5s"],
+    );
+    let fault = event(&log, "sensor fault switched");
+    assert!(fault.contains("step{t_s=5.0}"), "{fault}");
+    let trip = event(&log, ": trip ");
+    assert!(trip.contains("step{t_s=5.0}"), "{trip}");
+    assert!(trip.contains("SensorDisagree"), "{trip}");
+}
+
+#[test]
+fn rust_log_selects_events_and_default_logs_nothing() {
+    let run = |rust_log: Option<&str>| {
+        let mut cmd = Command::cargo_bin("cli").unwrap();
+        cmd.args(["run", "--scenario", "sensor-disagree", "--seconds", "5"])
+            .args(["--summary", "none", "--quiet"]);
+        match rust_log {
+            Some(filter) => cmd.env("RUST_LOG", filter),
+            None => cmd.env_remove("RUST_LOG"),
+        };
+        let out = cmd.assert().success().get_output().stderr.clone();
+        String::from_utf8(out).unwrap()
+    };
+    let log = run(Some("safety=info"));
+    assert!(log.contains("SensorDisagree"), "{log}");
+    assert!(!log.contains("sensor fault switched"), "{log}");
+    assert_eq!(run(None), "");
+}
+
+#[test]
+fn auto_gains_are_logged_at_info() {
+    let stderr = |flags: &[&str]| {
+        let out = Command::cargo_bin("cli")
+            .unwrap()
+            .args(["run", "--seconds", "1", "--summary", "none", "--quiet"])
+            .args(["--auto-gains", "60"])
+            .args(flags)
+            .env_remove("RUST_LOG")
+            .assert()
+            .success()
+            .get_output()
+            .stderr
+            .clone();
+        String::from_utf8(out).unwrap()
+    };
+    assert_eq!(stderr(&[]), "");
+    let log = stderr(&["--log-level", "info"]);
+    assert!(log.contains("auto-gains"), "{log}");
+    assert!(log.contains("settling_s=60"), "{log}");
+}
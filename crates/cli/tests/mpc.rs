@@ -0,0 +1,42 @@
+use std::process::Command;
+
+/// Trip temperature 10 °C above the default setpoint (350 °C), within the PID's overshoot.
+const TRIP_TEMP: &str = "360";
+
+/// Whether the built-in Overheat scenario trips when run with `controller`, and the peak true
+/// temperature of the run.
+fn overheat(controller: &str) -> (bool, f64) {
+    let out = Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["run", "--scenario", "overheat", "--trip-temp", TRIP_TEMP])
+        .args(["--controller", controller])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let mut tripped = false;
+    let mut peak = f64::NEG_INFINITY;
+    // Every line after the `_meta` header is a row
+    for line in String::from_utf8(out.stdout).unwrap().lines().skip(1) {
+        let row: serde_json::Value = serde_json::from_str(line).unwrap();
+        tripped |= row["scram"].as_bool().unwrap();
+        peak = peak.max(row["true_temp_c"].as_f64().unwrap());
+    }
+    (tripped, peak)
+}
+
+#[test]
+fn mpc_avoids_the_trip_the_pid_overshoots_into() {
+    let (pid_trip, pid_peak) = overheat("pid");
+    assert!(pid_trip, "PID peak {pid_peak:.2} °C");
+
+    // The default --mpc-margin keeps the predicted temperature 5 °C below the trip
+    let (mpc_trip, mpc_peak) = overheat("mpc");
+    assert!(!mpc_trip, "MPC peak {mpc_peak:.2} °C");
+    assert!(
+        (349.0..355.0).contains(&mpc_peak),
+        "MPC peak {mpc_peak:.2} °C"
+    );
+}
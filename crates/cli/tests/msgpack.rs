@@ -0,0 +1,99 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+use clap::Parser;
+use cli::trace::{read_trace_with_meta, TraceRow};
+use cli::{exit, Cli, RunArgs};
+use common::scratch;
+
+/// An overheat run with a dropping sensor (NaN readings), diagnostics and a trip record.
+fn record(path: &Path, format: &str) {
+    let argv = [
+        "reactor-safety-sim",
+        "--scenario",
+        "overheat",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+        "--seconds",
+        "30",
+        "--fault",
+        "s2:dropout:7",
+        "--diagnostics",
+        "--continue-after-scram",
+        "--format",
+        format,
+    ];
+    let args = RunArgs::try_parse_from(argv).unwrap();
+    cli::run_to_file(&args, path, &AtomicBool::new(false)).unwrap();
+}
+
+fn convert(from: &Path, to: &Path) {
+    let argv = ["reactor-safety-sim", "convert", from.to_str().unwrap()];
+    let cli = Cli::try_parse_from(argv.into_iter().chain([to.to_str().unwrap()])).unwrap();
+    assert_eq!(cli::execute(&cli, &mut Vec::new()).unwrap(), exit::OK);
+}
+
+fn assert_same(a: &[TraceRow], b: &[TraceRow]) {
+    assert_eq!(a.len(), b.len());
+    for (a, b) in a.iter().zip(b) {
+        assert_eq!(format!("{a:?}"), format!("{b:?}"));
+    }
+}
+
+#[test]
+fn jsonl_to_binary_and_back_is_exact() {
+    let dir = scratch("roundtrip");
+    let jsonl = dir.join("run.jsonl");
+    record(&jsonl, "jsonl");
+    convert(&jsonl, &dir.join("run.msgpack"));
+    convert(&dir.join("run.msgpack"), &dir.join("back.jsonl"));
+
+    let original = fs::read_to_string(&jsonl).unwrap();
+    assert!(original.contains("null"), "expected dropouts in the trace");
+    assert_eq!(
+        fs::read_to_string(dir.join("back.jsonl")).unwrap(),
+        original
+    );
+    let binary = fs::metadata(dir.join("run.msgpack")).unwrap().len();
+    assert!(binary < original.len() as u64, "{binary} bytes");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn binary_runs_read_back_like_jsonl_runs() {
+    let dir = scratch("run");
+    record(&dir.join("run.jsonl"), "jsonl");
+    record(&dir.join("run.msgpack"), "msgpack");
+    // the trace reader goes by the magic bytes, not the name
+    fs::copy(dir.join("run.msgpack"), dir.join("renamed.jsonl")).unwrap();
+
+    let (meta, rows) = read_trace_with_meta(&dir.join("run.jsonl")).unwrap();
+    for name in ["run.msgpack", "renamed.jsonl"] {
+        let (binary_meta, binary_rows) = read_trace_with_meta(&dir.join(name)).unwrap();
+        let (meta, binary_meta) = (meta.clone().unwrap(), binary_meta.expect("binary header"));
+        assert_eq!(binary_meta.header.config_hash, meta.header.config_hash);
+        assert_eq!(binary_meta.options, meta.options);
+        assert_same(&binary_rows, &rows);
+    }
+    assert!(rows.iter().any(|r| r.s2_c.is_nan()));
+    assert!(rows.iter().any(|r| r.reason.is_some() && r.diag.is_some()));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn truncated_binary_trace_is_invalid_input() {
+    let dir = scratch("truncated");
+    let path = dir.join("run.msgpack");
+    record(&path, "msgpack");
+    let bytes = fs::read(&path).unwrap();
+    fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+    let err = read_trace_with_meta(&path).unwrap_err();
+    assert_eq!(exit::code_for(&err), exit::INVALID_INPUT);
+    assert!(format!("{err:#}").contains("cut off"), "{err:#}");
+    fs::remove_dir_all(dir).unwrap();
+}
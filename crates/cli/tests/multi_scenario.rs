@@ -0,0 +1,115 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use clap::Parser;
+use cli::trace::read_trace_with_meta;
+use cli::{exit, RunArgs};
+use common::scratch;
+
+fn run(dir: &Path, extra: &[&str]) -> (u8, String) {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--quiet",
+        "--scenario",
+        "normal,overheat",
+        "--out-dir",
+        dir.to_str().unwrap(),
+    ];
+    argv.extend_from_slice(extra);
+    let mut stdout = Vec::new();
+    let code = cli::run_command(&RunArgs::try_parse_from(argv).unwrap(), &mut stdout).unwrap();
+    (code, String::from_utf8(stdout).unwrap())
+}
+
+/// Long enough for overheat to trip, not normal.
+const TRIPPING: [&str; 6] = ["--seconds", "30", "--setpoint", "380", "--trip-temp", "390"];
+
+/// Data rows of the combined summary table.
+fn table_rows(stdout: &str) -> Vec<&str> {
+    let mut lines = stdout.lines();
+    assert_eq!(lines.next(), Some("--- scenario summary ---"), "{stdout}");
+    assert!(lines.next().unwrap().starts_with("scenario "), "{stdout}");
+    lines.collect()
+}
+
+#[test]
+fn each_scenario_gets_its_own_trace_and_a_summary_row() {
+    let dir = scratch("two");
+    let (code, stdout) = run(&dir, &TRIPPING);
+    assert_eq!(code, exit::OK);
+
+    for name in ["normal", "overheat"] {
+        let (meta, rows) = read_trace_with_meta(&dir.join(format!("{name}.jsonl"))).unwrap();
+        assert_eq!(meta.expect("header").header.scenario, name);
+        assert!(!rows.is_empty());
+    }
+    let rows = table_rows(&stdout);
+    assert_eq!(rows.len(), 2, "{stdout}");
+    assert!(
+        rows[0].starts_with("normal ") && rows[0].contains("no trip"),
+        "{stdout}"
+    );
+    assert!(rows[1].starts_with("overheat ") && rows[1].contains("trips (OverTemp)"));
+    assert!(rows[1].ends_with("overheat.jsonl"), "{stdout}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn config_overrides_apply_to_their_scenario_only() {
+    let dir = scratch("overrides");
+    fs::create_dir_all(&dir).unwrap();
+    let config = dir.join("sim.toml");
+    fs::write(
+        &config,
+        "[run]\nseconds = 5.0\n\n[scenarios.overheat.run]\nseconds = 2.0\n\n\
+         [scenarios.overheat.safety]\ntrip_temp_c = 390.0\n",
+    )
+    .unwrap();
+    let (code, _) = run(&dir.join("out"), &["--config", config.to_str().unwrap()]);
+    assert_eq!(code, exit::OK);
+
+    let trace = |name: &str| read_trace_with_meta(&dir.join(format!("out/{name}.jsonl"))).unwrap();
+    let (meta, rows) = trace("normal");
+    assert_eq!((meta.unwrap().header.trip_temp, rows.len()), (420.0, 100));
+    let (meta, rows) = trace("overheat");
+    assert_eq!((meta.unwrap().header.trip_temp, rows.len()), (390.0, 40));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn a_failing_scenario_does_not_stop_the_others() {
+    let dir = scratch("failing");
+    fs::create_dir_all(&dir).unwrap();
+    // without --force the existing file cannot be replaced
+    fs::write(dir.join("normal.jsonl"), "keep me").unwrap();
+    let (code, stdout) = run(&dir, &[&TRIPPING[..], &["--fail-on-trip"]].concat());
+    assert_eq!(code, exit::IO, "the I/O error outranks the trip");
+
+    assert_eq!(
+        fs::read_to_string(dir.join("normal.jsonl")).unwrap(),
+        "keep me"
+    );
+    assert!(dir.join("overheat.jsonl").exists());
+    let rows = table_rows(&stdout);
+    assert_eq!(rows.len(), 2, "{stdout}");
+    assert!(rows[0].contains("failed: ") && rows[0].contains("already exists"));
+    assert!(rows[1].contains("trips (OverTemp)"), "{stdout}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn several_scenarios_need_out_dir() {
+    let args = RunArgs::try_parse_from([
+        "reactor-safety-sim",
+        "--scenario",
+        "normal,overheat",
+        "--seconds",
+        "1",
+    ])
+    .unwrap();
+    let err = cli::run(&args, &mut Vec::new()).unwrap_err();
+    assert_eq!(exit::code_for(&err), exit::INVALID_INPUT);
+    assert!(err.to_string().contains("--out-dir"), "{err}");
+}
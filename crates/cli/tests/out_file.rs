@@ -0,0 +1,63 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use clap::Parser;
+use cli::RunArgs;
+use common::scratch;
+
+fn args(out: &Path, extra: &[&str]) -> RunArgs {
+    let out = out.to_str().unwrap();
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--seconds",
+        "5",
+        "--dt-ms",
+        "250",
+        "--format",
+        "csv",
+        "--out",
+        out,
+    ];
+    argv.extend_from_slice(extra);
+    RunArgs::try_parse_from(argv).unwrap()
+}
+
+#[test]
+fn out_writes_trace_file_and_summary_to_stdout() {
+    let dir = scratch("write");
+    let path = dir.join("nested/trace.csv");
+    let mut stdout = Vec::new();
+    cli::run_command(&args(&path, &[]), &mut stdout).unwrap();
+
+    let trace = fs::read_to_string(&path).unwrap();
+    // header + one row per 250 ms step
+    assert_eq!(trace.lines().count(), 1 + 20);
+    let stdout = String::from_utf8(stdout).unwrap();
+    assert!(stdout.starts_with("--- run summary ---"), "{stdout}");
+    assert!(stdout.contains("steps            20 "), "{stdout}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn out_refuses_to_overwrite_without_force() {
+    let dir = scratch("force");
+    let path = dir.join("trace.csv");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(&path, "keep me\n").unwrap();
+
+    assert!(cli::run_command(&args(&path, &[]), &mut Vec::new()).is_err());
+    assert_eq!(fs::read_to_string(&path).unwrap(), "keep me\n");
+
+    cli::run_command(&args(&path, &["--force"]), &mut Vec::new()).unwrap();
+    let trace = fs::read_to_string(&path).unwrap();
+    assert!(trace.starts_with("t_s,"));
+    assert_eq!(trace.lines().count(), 1 + 20);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn force_requires_out() {
+    assert!(RunArgs::try_parse_from(["reactor-safety-sim", "--force"]).is_err());
+}
@@ -0,0 +1,90 @@
+use assert_cmd::Command;
+use clap::Parser;
+use cli::{exit, RunArgs};
+
+fn run(extra: &[&str]) -> (Vec<f64>, cli::RunSummary) {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--format",
+        "csv",
+        "--seconds",
+        "60",
+        "--summary",
+        "none",
+    ];
+    argv.extend_from_slice(extra);
+    let mut out = Vec::new();
+    let summary = cli::run(&RunArgs::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    let temps = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(|l| cli::trace::split_csv(l)[2].parse().unwrap())
+        .collect();
+    (temps, summary)
+}
+
+#[test]
+fn flags_override_config_values() {
+    let args = RunArgs::try_parse_from([
+        "reactor-safety-sim",
+        "--kp",
+        "0.04",
+        "--out-max",
+        "0.8",
+        "--plant-thermal-mass",
+        "150",
+        "--plant-ambient",
+        "20",
+    ])
+    .unwrap();
+    let cfg = args.sim_config().unwrap();
+    assert_eq!(cfg.pid.kp, 0.04);
+    assert_eq!(cfg.pid.ki, controller::PidConfig::default().ki);
+    assert_eq!(cfg.pid.out_max, 0.8);
+    assert_eq!(cfg.plant.thermal_mass, 150.0);
+    assert_eq!(cfg.plant.ambient_c, 20.0);
+    assert_eq!(cfg.plant.k_cool, sim::PlantParams::default().k_cool);
+}
+
+#[test]
+fn higher_kp_closes_the_error_faster() {
+    let (base, _) = run(&[]);
+    let (fast, summary) = run(&["--kp", "0.04"]);
+    let error = |temps: &[f64]| temps.iter().map(|t| (350.0 - t).abs()).sum::<f64>();
+    assert!(
+        error(&fast) < error(&base),
+        "{} >= {}",
+        error(&fast),
+        error(&base)
+    );
+    assert_eq!(summary.overrides.get("pid.kp"), Some(&0.04));
+}
+
+#[test]
+fn plant_override_changes_the_response() {
+    let (base, _) = run(&[]);
+    let (heavy, summary) = run(&["--plant-thermal-mass", "300"]);
+    // a heavier plant warms up more slowly from the same start
+    assert!(heavy[100] < base[100], "{} >= {}", heavy[100], base[100]);
+    assert_eq!(summary.overrides.len(), 1);
+}
+
+#[test]
+fn invalid_overrides_exit_with_invalid_input() {
+    for flags in [
+        ["--kp", "-0.1"],
+        ["--out-max", "-1"],
+        ["--plant-thermal-mass", "0"],
+        ["--plant-k-cool", "-2"],
+    ] {
+        let assert = Command::cargo_bin("cli")
+            .unwrap()
+            .args(["run", "--seconds", "1"])
+            .args(flags)
+            .assert()
+            .code(exit::INVALID_INPUT as i32);
+        let stderr = String::from_utf8_lossy(&assert.get_output().stderr).into_owned();
+        assert!(stderr.starts_with("error: invalid"), "{flags:?}: {stderr}");
+    }
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "parquet")]
+
+mod common;
+
+use std::fs;
+use std::sync::atomic::AtomicBool;
+
+use clap::Parser;
+use cli::trace::{parse_trace, read_trace, TraceFormat};
+use cli::RunArgs;
+use common::scratch;
+
+fn args(extra: &[&str]) -> RunArgs {
+    let mut argv = vec!["reactor-safety-sim", "--seed", "99"];
+    argv.extend_from_slice(extra);
+    RunArgs::try_parse_from(argv).unwrap()
+}
+
+#[test]
+fn parquet_matches_jsonl_of_the_same_seed() {
+    let dir = scratch("match");
+    let path = dir.join("trace.parquet");
+    let flags = [
+        "--scenario",
+        "sensor-disagree",
+        "--seconds",
+        "30",
+        "--diagnostics",
+        "--continue-after-scram",
+    ];
+    let mut jsonl = Vec::new();
+    cli::run(
+        &args(&[&flags[..], &["--format", "jsonl"]].concat()),
+        &mut jsonl,
+    )
+    .unwrap();
+    let pq = args(&[&flags[..], &["--format", "parquet"]].concat());
+    cli::run_to_file(&pq, &path, &AtomicBool::new(false)).unwrap();
+
+    let expected = parse_trace(&String::from_utf8(jsonl).unwrap(), TraceFormat::Jsonl).unwrap();
+    let actual = read_trace(&path).unwrap();
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(&expected) {
+        // Debug formatting compares NaN dropouts as equal
+        assert_eq!(format!("{a:?}"), format!("{e:?}"));
+    }
+    assert!(actual.iter().any(|r| r.reason.is_some()));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn parquet_is_much_smaller_than_jsonl() {
+    let dir = scratch("size");
+    let flags = ["--scenario", "normal", "--seconds", "500"];
+    let stop = AtomicBool::new(false);
+    let jsonl = dir.join("trace.jsonl");
+    let parquet = dir.join("trace.parquet");
+    let summary = cli::run_to_file(&args(&flags), &jsonl, &stop).unwrap();
+    assert_eq!(summary.steps, 10_000);
+    cli::run_to_file(
+        &args(&[&flags[..], &["--format", "parquet"]].concat()),
+        &parquet,
+        &stop,
+    )
+    .unwrap();
+    let jsonl_len = fs::metadata(&jsonl).unwrap().len();
+    let parquet_len = fs::metadata(&parquet).unwrap().len();
+    assert!(
+        parquet_len * 3 < jsonl_len,
+        "parquet {parquet_len} B vs jsonl {jsonl_len} B"
+    );
+    fs::remove_dir_all(dir).unwrap();
+}
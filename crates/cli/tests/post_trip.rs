@@ -0,0 +1,58 @@
+use clap::Parser;
+use cli::trace::{parse_trace, TraceFormat};
+use cli::RunArgs;
+
+fn run(extra: &[&str]) -> (Vec<cli::trace::TraceRow>, cli::RunSummary) {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--scenario",
+        "overheat",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+        "--seconds",
+        "120",
+        "--format",
+        "csv",
+    ];
+    argv.extend_from_slice(extra);
+    let mut out = Vec::new();
+    let summary = cli::run(&RunArgs::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    let rows = parse_trace(&String::from_utf8(out).unwrap(), TraceFormat::Csv).unwrap();
+    (rows, summary)
+}
+
+#[test]
+fn run_stops_at_the_trip_by_default() {
+    let (rows, summary) = run(&[]);
+    let trip_t_s = summary.trip_t_s.expect("overheat trips");
+    assert_eq!(rows.last().unwrap().t_s, trip_t_s);
+    assert_eq!(rows.iter().filter(|r| r.scram).count(), 1);
+}
+
+#[test]
+fn continue_after_scram_cools_down_for_the_full_duration() {
+    let (rows, summary) = run(&["--continue-after-scram"]);
+    assert_eq!(rows.len(), 2400);
+    assert_eq!(summary.steps, 2400);
+    let trip_t_s = summary.trip_t_s.expect("overheat trips");
+    let after: Vec<_> = rows.iter().filter(|r| r.t_s >= trip_t_s).collect();
+    assert!(after.len() > 1);
+    for row in &after {
+        assert!(row.scram);
+        assert_eq!(row.reason_name().as_deref(), Some("OverTemp"));
+        assert_eq!(row.power, 0.0);
+    }
+    // with the power off the plant cools monotonically towards ambient
+    for pair in after.windows(2) {
+        assert!(pair[1].true_temp_c < pair[0].true_temp_c);
+    }
+    let ambient = sim::PlantParams::default().ambient_c;
+    let last = after.last().unwrap().true_temp_c;
+    assert!(
+        last > ambient && last < after[0].true_temp_c - 20.0,
+        "{last}"
+    );
+    assert_eq!(summary.reason.as_deref(), Some("OverTemp"));
+}
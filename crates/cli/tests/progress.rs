@@ -0,0 +1,78 @@
+use assert_cmd::Command;
+
+fn stderr(args: &[&str]) -> String {
+    let assert = Command::cargo_bin("cli").unwrap().args(args).assert();
+    String::from_utf8(assert.get_output().stderr.clone()).unwrap()
+}
+
+const RUN: [&str; 8] = [
+    "run",
+    "--seconds",
+    "2",
+    "--dt-ms",
+    "250",
+    "--summary",
+    "none",
+    "--progress-every",
+];
+
+#[test]
+fn run_reports_plain_lines_when_stderr_is_not_a_terminal() {
+    let err = stderr(&[&RUN[..], &["0"]].concat());
+    let lines: Vec<&str> = err.lines().collect();
+    assert_eq!(lines.len(), 8, "{err}");
+    assert!(lines.iter().all(|l| l.starts_with("run: ")), "{err}");
+    assert!(
+        lines[7].starts_with("run: 2.0/2.0 s simulated (100 %)"),
+        "{err}"
+    );
+    assert!(!err.contains('\r') && !err.contains('\x1b'), "{err:?}");
+}
+
+#[test]
+fn quiet_run_writes_nothing_to_stderr() {
+    assert_eq!(stderr(&[&RUN[..], &["0", "--quiet"]].concat()), "");
+}
+
+#[test]
+fn batch_reports_runs_trips_and_eta() {
+    let dir = std::env::temp_dir().join(format!("rss-progress-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir_arg = dir.to_str().unwrap();
+    let batch = [
+        "batch",
+        "--seeds",
+        "4",
+        "--jobs",
+        "1",
+        "--scenario",
+        "overheat",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+        "--seconds",
+        "30",
+        "--progress-every",
+        "0",
+        "--out-dir",
+        dir_arg,
+    ];
+    let err = stderr(&batch);
+    let lines: Vec<&str> = err.lines().collect();
+    assert_eq!(lines.len(), 5, "{err}");
+    assert!(
+        lines[0].starts_with("batch: 1/4 runs, 1 tripped, ETA "),
+        "{err}"
+    );
+    assert!(
+        lines[4].starts_with("batch: 4/4 runs, 4 tripped in "),
+        "{err}"
+    );
+    assert!(!err.contains('\r') && !err.contains('\x1b'), "{err:?}");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    let err = stderr(&[&batch[..], &["--quiet"]].concat());
+    assert_eq!(err, "");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,110 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+
+use assert_cmd::Command;
+use cli::exit;
+use cli::provenance::GIT_HASH;
+use cli::trace::read_trace_with_meta;
+use common::scratch;
+
+fn bin() -> Command {
+    Command::cargo_bin("cli").unwrap()
+}
+
+fn record(path: &Path, extra: &[&str]) {
+    bin()
+        .args([
+            "run",
+            "--scenario",
+            "overheat",
+            "--seconds",
+            "5",
+            "--seed",
+            "42",
+        ])
+        .args(extra)
+        .arg("--out")
+        .arg(path)
+        .assert()
+        .success();
+}
+
+#[test]
+fn header_records_the_command_line_and_build() {
+    let dir = scratch("header");
+    let path = dir.join("trace.jsonl");
+    record(&path, &["--summary", "none"]);
+
+    let (meta, _) = read_trace_with_meta(&path).unwrap();
+    let provenance = meta.expect("header").provenance.expect("provenance");
+    let argv = &provenance.argv;
+    assert!(argv[0].contains("cli"), "{argv:?}");
+    assert_eq!(
+        argv[1..7],
+        ["run", "--scenario", "overheat", "--seconds", "5", "--seed"]
+    );
+    assert_eq!(argv.last().map(String::as_str), path.to_str());
+    let crates: Vec<&str> = provenance.versions.keys().map(String::as_str).collect();
+    assert_eq!(
+        crates,
+        ["cli", "controller", "engine", "safety", "sim", "trace"]
+    );
+    assert!(provenance.versions.values().all(|v| v == "0.1.0"));
+    assert_eq!(provenance.git_hash, GIT_HASH);
+    assert_eq!(provenance.os, std::env::consts::OS);
+    assert_eq!(provenance.arch, std::env::consts::ARCH);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn git_hash_is_a_commit_or_unknown() {
+    let hash = GIT_HASH.strip_suffix("-dirty").unwrap_or(GIT_HASH);
+    assert!(
+        hash == "unknown" || (hash.len() == 40 && hash.chars().all(|c| c.is_ascii_hexdigit())),
+        "{GIT_HASH}"
+    );
+}
+
+#[test]
+fn inspect_prints_the_provenance_block() {
+    let dir = scratch("inspect");
+    let path = dir.join("trace.msgpack");
+    record(&path, &["--format", "msgpack", "--summary", "none"]);
+
+    let out = bin()
+        .args(["inspect", "--provenance"])
+        .arg(&path)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+    let out = String::from_utf8(out).unwrap();
+    assert!(
+        out.contains("run --scenario overheat --seconds 5 --seed 42"),
+        "{out}"
+    );
+    assert!(
+        out.contains("versions     cli 0.1.0, controller 0.1.0, engine 0.1.0, safety 0.1.0, sim 0.1.0, trace 0.1.0"),
+        "{out}"
+    );
+    assert!(out.contains(&format!("git commit   {GIT_HASH}")), "{out}");
+    assert!(out.contains(std::env::consts::ARCH), "{out}");
+    assert!(out.contains("config hash  "), "{out}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn csv_traces_have_no_provenance_to_inspect() {
+    let dir = scratch("csv");
+    let path = dir.join("trace.csv");
+    record(&path, &["--format", "csv", "--summary", "none"]);
+    bin()
+        .args(["inspect", "--provenance"])
+        .arg(&path)
+        .assert()
+        .code(exit::INVALID_INPUT as i32);
+    fs::remove_dir_all(dir).unwrap();
+}
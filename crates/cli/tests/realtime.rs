@@ -0,0 +1,55 @@
+use std::sync::atomic::AtomicBool;
+
+use clap::Parser;
+use cli::summary::Outcome;
+use cli::RunArgs;
+
+fn args(extra: &[&str]) -> RunArgs {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--scenario",
+        "overheat",
+        "--seconds",
+        "20",
+        "--format",
+        "csv",
+    ];
+    argv.extend_from_slice(extra);
+    RunArgs::try_parse_from(argv).unwrap()
+}
+
+#[test]
+fn realtime_at_high_speed_produces_the_same_data() {
+    let mut fast = Vec::new();
+    let expected = cli::run(&args(&[]), &mut fast).unwrap();
+    let mut paced = Vec::new();
+    let summary = cli::run(&args(&["--realtime", "1000"]), &mut paced).unwrap();
+    assert_eq!(paced, fast);
+    assert_eq!(summary.steps, expected.steps);
+    assert_eq!(summary.max_temp_c, expected.max_temp_c);
+}
+
+#[test]
+fn realtime_speed_defaults_to_one() {
+    assert_eq!(args(&["--realtime"]).realtime, Some(1.0));
+    assert_eq!(args(&[]).realtime, None);
+}
+
+#[test]
+fn invalid_speed_is_rejected() {
+    let err = cli::run(&args(&["--realtime", "0"]), &mut Vec::new()).unwrap_err();
+    assert!(err.to_string().contains("--realtime"), "{err}");
+}
+
+#[test]
+fn interrupt_finalizes_the_trace_and_summary() {
+    let stop = AtomicBool::new(true);
+    let mut out = Vec::new();
+    let summary = cli::run_until(&args(&["--realtime", "1000"]), &mut out, &stop).unwrap();
+    assert_eq!(summary.outcome, Outcome::Interrupted);
+    assert_eq!(summary.steps, 1);
+    let text = String::from_utf8(out).unwrap();
+    // header and the one completed row, both whole lines
+    assert_eq!(text.lines().count(), 2);
+    assert!(text.ends_with('\n'));
+}
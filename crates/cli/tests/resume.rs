@@ -0,0 +1,166 @@
+mod common;
+
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+use clap::Parser;
+use cli::checkpoint::Checkpoint;
+use cli::trace::{parse_trace_with_meta, ResumePoint, TraceFormat};
+use cli::RunArgs;
+use common::scratch;
+
+/// A noisy run with a drifting sensor, so the generators and the fault schedule both matter.
+fn args(extra: &[&str]) -> RunArgs {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--scenario",
+        "normal",
+        "--seed",
+        "7",
+        "--noise",
+        "s1=0.5",
+        "--fault",
+        "s2:drift:0.2@20s",
+        "--diagnostics",
+    ];
+    argv.extend_from_slice(extra);
+    RunArgs::try_parse_from(argv).unwrap()
+}
+
+fn run(args: &RunArgs, stop: &AtomicBool) -> String {
+    let mut out = Vec::new();
+    cli::run_until(args, &mut out, stop).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+fn resume(ckpt: &Path, extra: &[&str]) -> String {
+    let mut argv = vec!["reactor-safety-sim", "--resume", ckpt.to_str().unwrap()];
+    argv.extend_from_slice(extra);
+    run(
+        &RunArgs::try_parse_from(argv).unwrap(),
+        &AtomicBool::new(false),
+    )
+}
+
+/// The row lines of a JSONL trace, without the header.
+fn rows(text: &str) -> Vec<&str> {
+    text.lines().skip(1).collect()
+}
+
+#[test]
+fn resumed_run_matches_the_uninterrupted_one() {
+    let dir = scratch("straight");
+    let ckpt = dir.join("run.ckpt");
+    let straight = run(&args(&["--seconds", "60"]), &AtomicBool::new(false));
+    let first = run(
+        &args(&[
+            "--seconds",
+            "30",
+            "--checkpoint-every",
+            "10s",
+            "--checkpoint-out",
+            ckpt.to_str().unwrap(),
+        ]),
+        &AtomicBool::new(false),
+    );
+    let continued = resume(&ckpt, &["--seconds", "60"]);
+
+    let straight = rows(&straight);
+    let (first, continued) = (rows(&first), rows(&continued));
+    assert_eq!(first.len(), 600);
+    assert_eq!(continued.len(), 600);
+    assert_eq!(&straight[..600], &first[..]);
+    assert_eq!(&straight[600..], &continued[..]);
+}
+
+#[test]
+fn resumed_run_with_an_invalid_sensor_matches_byte_for_byte() {
+    let dir = scratch("invalid");
+    let ckpt = dir.join("run.ckpt");
+    // s3 reads below its valid range from 5 s on, across the checkpoint at 10 s
+    let invalid = ["--fault", "s3:stuck:-50@5s"];
+    let straight = run(
+        &args(&[&invalid[..], &["--seconds", "20"]].concat()),
+        &AtomicBool::new(false),
+    );
+    run(
+        &args(
+            &[
+                &invalid[..],
+                &[
+                    "--seconds",
+                    "10",
+                    "--checkpoint-every",
+                    "10s",
+                    "--checkpoint-out",
+                    ckpt.to_str().unwrap(),
+                ],
+            ]
+            .concat(),
+        ),
+        &AtomicBool::new(false),
+    );
+    assert_eq!(Checkpoint::load(&ckpt).unwrap().valid, [true, true, false]);
+    let continued = resume(&ckpt, &["--seconds", "20"]);
+
+    let (straight, continued) = (rows(&straight), rows(&continued));
+    assert_eq!(continued.len(), 200);
+    assert_eq!(straight[200..].join("\n"), continued.join("\n"));
+}
+
+#[test]
+fn interrupted_run_checkpoints_the_step_it_stopped_after() {
+    let dir = scratch("interrupted");
+    let ckpt = dir.join("run.ckpt");
+    let straight = run(&args(&["--seconds", "60"]), &AtomicBool::new(false));
+    // Stopped before it starts, the run simulates one step and checkpoints it
+    let first = run(
+        &args(&[
+            "--seconds",
+            "60",
+            "--checkpoint-every",
+            "10s",
+            "--checkpoint-out",
+            ckpt.to_str().unwrap(),
+        ]),
+        &AtomicBool::new(true),
+    );
+    assert_eq!(rows(&first).len(), 1);
+    assert_eq!(Checkpoint::load(&ckpt).unwrap().step, 1);
+
+    let continued = resume(&ckpt, &[]);
+    let (meta, _) = parse_trace_with_meta(&continued, TraceFormat::Jsonl).unwrap();
+    let meta = meta.expect("header");
+    assert_eq!(meta.resumed, Some(ResumePoint { step: 1, t_s: 0.05 }));
+    assert_eq!(meta.header.seed, 7);
+    assert_eq!(rows(&straight)[1..], rows(&continued)[..]);
+}
+
+#[test]
+fn checkpoints_are_written_at_each_interval() {
+    let dir = scratch("interval");
+    let ckpt = dir.join("run.ckpt");
+    run(
+        &args(&[
+            "--seconds",
+            "25",
+            "--checkpoint-every",
+            "10",
+            "--checkpoint-out",
+            ckpt.to_str().unwrap(),
+        ]),
+        &AtomicBool::new(false),
+    );
+    // The last one is the end of the run
+    let saved = Checkpoint::load(&ckpt).unwrap();
+    assert_eq!(saved.step, 500);
+    assert_eq!(saved.summary.steps, 500);
+    assert!(!dir.join("run.ckpt.tmp").exists());
+}
+
+#[test]
+fn resume_rejects_configuration_flags() {
+    let err = RunArgs::try_parse_from(["reactor-safety-sim", "--resume", "x.ckpt", "--seed", "1"])
+        .unwrap_err();
+    assert!(err.to_string().contains("--seed"), "{err}");
+}
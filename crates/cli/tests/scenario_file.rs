@@ -0,0 +1,121 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use cli::RunArgs;
+
+const EXAMPLES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples");
+
+fn trace(argv: &[&str]) -> (String, cli::RunSummary) {
+    let mut full = vec!["reactor-safety-sim", "--format", "csv", "--seconds", "60"];
+    full.extend_from_slice(argv);
+    let args = RunArgs::try_parse_from(full).unwrap();
+    let mut out = Vec::new();
+    let summary = cli::run(&args, &mut out).unwrap();
+    (String::from_utf8(out).unwrap(), summary)
+}
+
+fn scenario_file(name: &str, text: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rss-scenario-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, text).unwrap();
+    path
+}
+
+fn load_err(path: &Path) -> String {
+    format!("{:#}", cli::scenario::load(path).unwrap_err())
+}
+
+#[test]
+fn example_files_reproduce_builtin_scenarios() {
+    for (builtin, file) in [
+        ("overheat", "overheat.toml"),
+        ("sensor-disagree", "sensor_disagree.toml"),
+    ] {
+        let path = format!("{EXAMPLES}/{file}");
+        // a low trip temperature so the overheat comparison covers the trip as well
+        let flags = ["--setpoint", "380", "--trip-temp", "390"];
+        let (expected, expected_summary) = trace(&[&["--scenario", builtin][..], &flags].concat());
+        let (actual, actual_summary) = trace(&[&["--scenario-file", &path][..], &flags].concat());
+        assert!(expected_summary.tripped(), "{builtin} should trip");
+        assert_eq!(actual, expected, "{file} differs from --scenario {builtin}");
+        assert_eq!(actual_summary.reason, expected_summary.reason);
+    }
+}
+
+#[test]
+fn timed_events_drive_the_run() {
+    let path = scenario_file(
+        "events.toml",
+        r#"
+[initial]
+coolant = 0.6
+
+[[events]]
+t_s = 10.0
+action = { set_setpoint = 320.0 }
+
+[[events]]
+t_s = 20.0
+action = "scram"
+"#,
+    );
+    let (csv, summary) = trace(&["--scenario-file", path.to_str().unwrap()]);
+    assert_eq!(summary.reason.as_deref(), Some("Manual"));
+    assert_eq!(summary.trip_t_s, Some(20.0));
+    let setpoints: Vec<f64> = csv
+        .lines()
+        .skip(1)
+        .map(|l| cli::trace::split_csv(l)[1].parse().unwrap())
+        .collect();
+    assert_eq!(setpoints[0], 350.0);
+    assert_eq!(*setpoints.last().unwrap(), 320.0);
+}
+
+#[test]
+fn malformed_files_report_the_line() {
+    let path = scenario_file(
+        "typo.toml",
+        "[initial]\ncoolant = 0.2\n\n[[sensors.s2]]\nfault = { kind = \"bias\", valeu = 20.0 }\n",
+    );
+    let msg = load_err(&path);
+    assert!(msg.contains("line 5") && msg.contains("valeu"), "{msg}");
+
+    let path = scenario_file("typo.yaml", "initial:\n  coolant: 0.2\n  pwer: 1.0\n");
+    let msg = load_err(&path);
+    assert!(msg.contains("line 3") && msg.contains("pwer"), "{msg}");
+}
+
+#[test]
+fn invalid_values_name_the_field() {
+    let path = scenario_file(
+        "invalid.toml",
+        r#"
+[[sensors.s1]]
+from_s = 30.0
+until_s = 10.0
+fault = { kind = "stuck", value = 300.0 }
+
+[[events]]
+t_s = 5.0
+action = { set_coolant = 1.5 }
+"#,
+    );
+    let msg = load_err(&path);
+    assert!(msg.contains("sensors.s1[0]"), "{msg}");
+    assert!(msg.contains("events[0].action.set_coolant"), "{msg}");
+}
+
+#[test]
+fn scenario_file_conflicts_with_scenario() {
+    let err = RunArgs::try_parse_from([
+        "reactor-safety-sim",
+        "--scenario",
+        "normal",
+        "--scenario-file",
+        "x.toml",
+    ])
+    .unwrap_err();
+    assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+}
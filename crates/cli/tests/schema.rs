@@ -0,0 +1,188 @@
+use assert_cmd::Command;
+use clap::Parser;
+use cli::schema::TRACE_JSON_SCHEMA;
+use cli::RunArgs;
+use safety::{TripDetail, TripRecord};
+use serde_json::Value;
+
+fn validator() -> jsonschema::Validator {
+    let schema: Value = serde_json::from_str(TRACE_JSON_SCHEMA).unwrap();
+    jsonschema::draft202012::new(&schema).unwrap()
+}
+
+fn trace(extra: &[&str]) -> Vec<Value> {
+    let mut argv = vec!["reactor-safety-sim", "--quiet"];
+    argv.extend_from_slice(extra);
+    let mut out = Vec::new();
+    cli::run(&RunArgs::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn assert_valid(validator: &jsonschema::Validator, lines: &[Value]) {
+    for (n, line) in lines.iter().enumerate() {
+        let errors: Vec<String> = validator.iter_errors(line).map(|e| e.to_string()).collect();
+        assert!(errors.is_empty(), "line {}: {errors:?}\n{line}", n + 1);
+    }
+}
+
+#[test]
+fn schema_subcommand_prints_the_schema() {
+    let assert = Command::cargo_bin("cli")
+        .unwrap()
+        .arg("schema")
+        .assert()
+        .success();
+    let printed: Value = serde_json::from_slice(&assert.get_output().stdout).unwrap();
+    assert_eq!(
+        printed["$schema"],
+        "https://json-schema.org/draft/2020-12/schema"
+    );
+    assert_eq!(
+        printed,
+        serde_json::from_str::<Value>(TRACE_JSON_SCHEMA).unwrap()
+    );
+}
+
+#[test]
+fn generated_traces_validate() {
+    let validator = validator();
+    let runs: [&[&str]; 3] = [
+        &["--scenario", "normal", "--seconds", "5"],
+        // dropouts write null readings; diagnostics add their columns to every row
+        &[
+            "--scenario",
+            "normal",
+            "--seconds",
+            "5",
+            "--fault",
+            "s1:dropout:3",
+            "--diagnostics",
+            "--noise",
+            "s2=0.5",
+        ],
+        &[
+            "--scenario",
+            "overheat",
+            "--setpoint",
+            "380",
+            "--trip-temp",
+            "390",
+            "--seconds",
+            "30",
+            "--continue-after-scram",
+        ],
+    ];
+    for argv in runs {
+        let lines = trace(argv);
+        assert!(lines[0].get("_meta").is_some());
+        assert_valid(&validator, &lines);
+    }
+}
+
+#[test]
+fn schema_covers_exactly_the_row_fields() {
+    let lines = trace(&["--seconds", "1", "--diagnostics"]);
+    let row = lines[1].as_object().unwrap();
+    let schema: Value = serde_json::from_str(TRACE_JSON_SCHEMA).unwrap();
+    let properties = schema["$defs"]["row"]["properties"].as_object().unwrap();
+    let mut documented: Vec<&String> = properties.keys().collect();
+    let mut written: Vec<&String> = row.keys().collect();
+    documented.sort();
+    written.sort();
+    assert_eq!(documented, written);
+
+    // every trip a row can carry is listed, by kind and by legacy reason name
+    let validator = validator();
+    let kinds = schema["$defs"]["trip"]["properties"]["kind"]["enum"]
+        .as_array()
+        .unwrap();
+    let names = schema["$defs"]["row"]["properties"]["reason"]["oneOf"][2]["enum"]
+        .as_array()
+        .unwrap();
+    for detail in [
+        TripDetail::OverTemp {
+            trip_temp_c: 420.0,
+            hottest_c: 421.5,
+        },
+        TripDetail::SensorInvalid {
+            valid_sensors: 1,
+            valid_range_c: (0.0, 2000.0),
+        },
+        TripDetail::SensorDisagree {
+            spread_c: 12.5,
+            max_sensor_delta_c: 10.0,
+        },
+        TripDetail::Manual {},
+    ] {
+        // a new variant fails to compile here until it is added to the schema and this list
+        match detail {
+            TripDetail::OverTemp { .. }
+            | TripDetail::SensorInvalid { .. }
+            | TripDetail::SensorDisagree { .. }
+            | TripDetail::Manual {} => {}
+        }
+        let trip = TripRecord {
+            detail,
+            channels: [true, false, true],
+        };
+        let value = serde_json::to_value(trip).unwrap();
+        assert!(kinds.contains(&value["kind"]), "{value}");
+        let mut row = lines[1].clone();
+        row.as_object_mut().unwrap().insert("reason".into(), value);
+        assert!(validator.is_valid(&row), "{row}");
+        let name = Value::from(format!("{:?}", detail.reason()));
+        assert!(names.contains(&name), "{name}");
+    }
+}
+
+#[test]
+fn schema_rejects_malformed_rows() {
+    let validator = validator();
+    let lines = trace(&["--seconds", "1", "--diagnostics"]);
+    let row = &lines[1];
+    assert!(validator.is_valid(row));
+    let broken = |edit: &dyn Fn(&mut serde_json::Map<String, Value>)| {
+        let mut row = row.clone();
+        edit(row.as_object_mut().unwrap());
+        row
+    };
+    for bad in [
+        broken(&|r| {
+            r.insert("extra".into(), Value::from(1));
+        }),
+        broken(&|r| {
+            r.remove("true_temp_c");
+        }),
+        broken(&|r| {
+            r.remove("i_term");
+        }),
+        broken(&|r| {
+            r.insert("reason".into(), Value::from("Meltdown"));
+        }),
+        broken(&|r| {
+            let trip = serde_json::json!({
+                "kind": "meltdown",
+                "channels": [true, true, true],
+                "detail": {}
+            });
+            r.insert("reason".into(), trip);
+        }),
+        broken(&|r| {
+            let trip = serde_json::json!({
+                "kind": "manual",
+                "channels": [false, false],
+                "detail": {}
+            });
+            r.insert("reason".into(), trip);
+        }),
+        broken(&|r| {
+            r.insert("s1_c".into(), Value::from("hot"));
+        }),
+    ] {
+        assert!(!validator.is_valid(&bad), "{bad}");
+    }
+}
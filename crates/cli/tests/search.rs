@@ -0,0 +1,109 @@
+use clap::Parser;
+use cli::search::{run_at, search, SearchArgs};
+use cli::{exit, Cli};
+
+#[derive(Parser)]
+struct Wrapper {
+    #[command(flatten)]
+    search: SearchArgs,
+}
+
+const COOLANT: [&str; 13] = [
+    "search",
+    "--param",
+    "coolant-after-loss",
+    "--range",
+    "0.0..0.6",
+    "--tolerance",
+    "0.001",
+    "--scenario",
+    "loss-of-cooling",
+    "--setpoint",
+    "380",
+    "--trip-temp",
+    "390",
+];
+
+fn args(argv: &[&str]) -> SearchArgs {
+    Wrapper::try_parse_from(argv).unwrap().search
+}
+
+#[test]
+fn residual_coolant_boundary_is_bracketed_within_tolerance() {
+    let args = args(&COOLANT);
+    let result = search(&args).unwrap();
+    let (low, high) = result.bracket.expect("one boundary");
+    assert!(low.tripped(), "less coolant trips");
+    assert!(!high.tripped());
+    assert!(low.value < high.value && high.value - low.value <= 0.001);
+    assert!((0.0..0.6).contains(&low.value));
+
+    // Manual runs just outside the bracket agree with it.
+    assert!(run_at(&args, low.value - 0.002).unwrap().tripped());
+    assert!(!run_at(&args, high.value + 0.002).unwrap().tripped());
+    // The scenario's own 5 % residual flow trips.
+    assert!(run_at(&args, 0.05).unwrap().tripped());
+}
+
+#[test]
+fn command_reports_boundary_and_both_runs() {
+    let mut out = Vec::new();
+    let mut argv = vec!["reactor-safety-sim"];
+    argv.extend(COOLANT);
+    let code = cli::execute(&Cli::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(code, exit::OK, "{out}");
+    assert!(out.contains("boundary          0.4479"), "{out}");
+    assert_eq!(out.matches("--- coolant-after-loss = ").count(), 2, "{out}");
+    assert!(out.contains("outcome          tripped (OverTemp)"), "{out}");
+    assert!(out.contains("outcome          completed"), "{out}");
+}
+
+#[test]
+fn non_monotonic_outcome_is_flagged() {
+    // A very light plant is regulated tightly and a very heavy one heats too slowly to trip
+    // within the run; only the masses in between overshoot the trip temperature.
+    let argv = [
+        "search",
+        "--param",
+        "plant-thermal-mass",
+        "--range",
+        "10..500",
+        "--scenario",
+        "overheat",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+    ];
+    let result = search(&args(&argv)).unwrap();
+    assert_eq!(result.bracket, None);
+    assert_eq!(result.runs, 9);
+    let outcomes: Vec<bool> = result.probes.iter().map(|p| p.tripped()).collect();
+    assert!(!outcomes[0] && outcomes[1] && !outcomes[8], "{outcomes:?}");
+
+    let mut out = Vec::new();
+    let mut argv = argv.to_vec();
+    argv.insert(0, "reactor-safety-sim");
+    let code = cli::execute(&Cli::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert_eq!(code, exit::FAILED_CHECK, "{out}");
+    assert!(out.contains("NON-MONOTONIC"), "{out}");
+}
+
+#[test]
+fn range_without_a_flip_is_invalid_input() {
+    let mut argv = COOLANT.to_vec();
+    argv[4] = "0.0..0.2";
+    let err = search(&args(&argv)).unwrap_err();
+    assert_eq!(exit::code_for(&err), exit::INVALID_INPUT);
+    assert!(err.to_string().contains("trips everywhere"), "{err}");
+}
+
+#[test]
+fn coolant_param_needs_a_coolant_event() {
+    let mut argv = COOLANT.to_vec();
+    argv[8] = "overheat";
+    let err = search(&args(&argv)).unwrap_err();
+    assert!(err.to_string().contains("no coolant event"), "{err}");
+}
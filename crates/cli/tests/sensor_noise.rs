@@ -0,0 +1,123 @@
+use clap::Parser;
+use cli::config::SensorValue;
+use cli::trace::{parse_trace, parse_trace_with_meta, TraceFormat, TraceRow};
+use cli::{exit, Cli, RunArgs, RunSummary};
+
+fn run(extra: &[&str]) -> (Vec<TraceRow>, RunSummary) {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--scenario",
+        "normal",
+        "--seconds",
+        "120",
+        "--format",
+        "csv",
+        "--quiet",
+    ];
+    argv.extend_from_slice(extra);
+    let mut out = Vec::new();
+    let summary = cli::run(&RunArgs::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    let rows = parse_trace(&String::from_utf8(out).unwrap(), TraceFormat::Csv).unwrap();
+    (rows, summary)
+}
+
+#[test]
+fn a_noisy_s2_widens_the_spread_and_trips_on_disagreement() {
+    for seed in ["1", "2", "3"] {
+        let (_, quiet) = run(&["--seed", seed]);
+        assert!(!quiet.tripped(), "seed {seed}: default noise never trips");
+        assert!(
+            quiet.max_sensor_spread_c < 2.0,
+            "{}",
+            quiet.max_sensor_spread_c
+        );
+
+        let (rows, noisy) = run(&["--seed", seed, "--noise", "s2=5.0"]);
+        assert!(
+            noisy.max_sensor_spread_c > 5.0 * quiet.max_sensor_spread_c,
+            "seed {seed}: {} vs {}",
+            noisy.max_sensor_spread_c,
+            quiet.max_sensor_spread_c
+        );
+        assert!(noisy.tripped(), "seed {seed}");
+        assert_eq!(noisy.reason.as_deref(), Some("SensorDisagree"));
+        assert_eq!(rows.last().unwrap().t_s, noisy.trip_t_s.unwrap());
+    }
+}
+
+#[test]
+fn sensor_seed_fixes_one_channel_whatever_the_run_seed() {
+    // noise of the reading taken at row k is measured against the state before the step
+    fn noise(rows: &[TraceRow], sensor: fn(&TraceRow) -> f64) -> Vec<f64> {
+        rows.windows(2)
+            .map(|w| sensor(&w[1]) - w[0].true_temp_c)
+            .collect()
+    }
+    let (a, _) = run(&["--seed", "1", "--sensor-seed", "s2=999"]);
+    let (b, _) = run(&["--seed", "2", "--sensor-seed", "s2=999"]);
+    let close = |x: Vec<f64>, y: Vec<f64>| x.iter().zip(&y).all(|(x, y)| (x - y).abs() < 1e-9);
+    assert!(close(noise(&a, |r| r.s2_c), noise(&b, |r| r.s2_c)));
+    assert!(!close(noise(&a, |r| r.s1_c), noise(&b, |r| r.s1_c)));
+}
+
+#[test]
+fn negative_noise_is_rejected() {
+    let cli =
+        Cli::try_parse_from(["reactor-safety-sim", "run", "--noise", "s1=0.1,s2=-1"]).unwrap();
+    let err = cli::execute(&cli, &mut Vec::new()).unwrap_err();
+    assert_eq!(exit::code_for(&err), exit::INVALID_INPUT);
+    assert!(
+        format!("{err:#}").contains("sensors.s2.noise_std = -1 must be >= 0"),
+        "{err:#}"
+    );
+}
+
+#[test]
+fn effective_sensor_settings_are_in_the_trace_header() {
+    let argv = [
+        "reactor-safety-sim",
+        "--seconds",
+        "1",
+        "--seed",
+        "100",
+        "--noise",
+        "s2=0.5",
+        "--sensor-seed",
+        "s3=7",
+    ];
+    let mut out = Vec::new();
+    cli::run(&RunArgs::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    let (meta, _) =
+        parse_trace_with_meta(&String::from_utf8(out).unwrap(), TraceFormat::Jsonl).unwrap();
+    let meta = meta.expect("a header line");
+    let sensors = meta.sensors.expect("sensor settings recorded");
+    let noise_and_seed = |name: &str| (sensors[name].noise_std, sensors[name].seed);
+    assert_eq!(noise_and_seed("s1"), (0.15, 100 ^ 0xA1));
+    assert_eq!(noise_and_seed("s2"), (0.5, 100 ^ 0xB2));
+    assert_eq!(noise_and_seed("s3"), (0.15, 7));
+    assert_eq!(meta.config.unwrap().sensors.s3.seed, Some(7));
+}
+
+#[test]
+fn sensor_values_parse_or_explain() {
+    assert_eq!(
+        "s2=0.5".parse(),
+        Ok(SensorValue {
+            sensor: 1,
+            value: 0.5
+        })
+    );
+    assert_eq!(
+        "s3=999".parse::<SensorValue<u64>>().unwrap().to_string(),
+        "s3=999"
+    );
+    for (arg, error) in [
+        ("s2", "expected SENSOR=VALUE"),
+        ("s4=1", "unknown sensor"),
+        ("s1=loud", "invalid value"),
+    ] {
+        let e = arg.parse::<SensorValue<f64>>().unwrap_err();
+        assert!(e.contains(error), "{arg}: {e}");
+    }
+    assert!("s1=-3".parse::<SensorValue<u64>>().is_err());
+}
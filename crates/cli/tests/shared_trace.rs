@@ -0,0 +1,77 @@
+use clap::Parser;
+use cli::RunArgs;
+use trace::{MetaLine, Sample, TraceHeader, TraceRow};
+
+fn run(extra: &[&str]) -> String {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--scenario",
+        "overheat",
+        "--seconds",
+        "20",
+        "--seed",
+        "42",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+    ];
+    argv.extend_from_slice(extra);
+    let mut out = Vec::new();
+    cli::run(&RunArgs::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn cli_rows_read_back_into_the_shared_types_unchanged() {
+    let text = run(&["--diagnostics"]);
+    let mut lines = text.lines();
+    let header: MetaLine<TraceHeader> = serde_json::from_str(lines.next().unwrap()).unwrap();
+    assert_eq!(
+        (header.meta.scenario.as_str(), header.meta.seed),
+        ("overheat", 42)
+    );
+
+    let mut rows = 0;
+    for line in lines {
+        // What the GUI reads, written again, is what the CLI wrote
+        let row: TraceRow = serde_json::from_str(line).unwrap();
+        assert_eq!(serde_json::to_string(&row).unwrap(), line);
+        rows += 1;
+    }
+    assert_eq!(
+        rows,
+        cli::trace::parse_trace(&text, cli::trace::TraceFormat::Jsonl)
+            .unwrap()
+            .len()
+    );
+}
+
+#[test]
+fn cli_rows_convert_to_plotted_samples() {
+    let text = run(&["--diagnostics"]);
+    let rows: Vec<TraceRow> = text
+        .lines()
+        .skip(1)
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    let tripped = rows.iter().position(|r| r.scram).expect("overheat trips");
+    let samples: Vec<Sample> = rows.iter().cloned().map(Sample::from).collect();
+    for (row, sample) in rows.iter().zip(&samples) {
+        assert_eq!(sample.t.to_bits(), row.t_s.to_bits());
+        assert_eq!(sample.true_temp.to_bits(), row.true_temp_c.to_bits());
+        assert_eq!(sample.setpoint.to_bits(), row.setpoint_c.to_bits());
+        let diag = row.diag.as_ref().unwrap();
+        assert_eq!(sample.p_term.to_bits(), diag.p_term.to_bits());
+        assert_eq!(sample.saturated, diag.ctrl_status.contains(['H', 'L']));
+    }
+    let reason = samples[tripped].reason.as_deref();
+    assert_eq!(reason, rows[tripped].reason.as_ref());
+    assert!(samples[..tripped].iter().all(|s| s.reason.is_none()));
+
+    // Without --diagnostics there are no controller terms to plot
+    let plain = run(&[]);
+    let row: TraceRow = serde_json::from_str(plain.lines().nth(1).unwrap()).unwrap();
+    let sample = Sample::from(row);
+    assert!(sample.p_term.is_nan() && !sample.saturated);
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use cli::stats::{histogram, read_batch};
+use cli::{exit, Cli};
+use common::scratch;
+
+fn execute(argv: &[&str]) -> anyhow::Result<String> {
+    let mut out = Vec::new();
+    let mut full = vec!["reactor-safety-sim"];
+    full.extend_from_slice(argv);
+    let code = cli::execute(&Cli::try_parse_from(full).unwrap(), &mut out)?;
+    assert_eq!(code, exit::OK);
+    Ok(String::from_utf8(out).unwrap())
+}
+
+fn overheat_batch(dir: &Path, seeds: &str) {
+    execute(&[
+        "batch",
+        "--seeds",
+        seeds,
+        "--out-dir",
+        dir.to_str().unwrap(),
+        "--scenario",
+        "overheat",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+        "--seconds",
+        "60",
+    ])
+    .unwrap();
+}
+
+#[test]
+fn two_batches_merge_per_scenario() {
+    let root = scratch("merge");
+    overheat_batch(&root.join("a"), "1..6");
+    overheat_batch(&root.join("b"), "6..11");
+    execute(&[
+        "batch",
+        "--seeds",
+        "3",
+        "--out-dir",
+        root.join("c").to_str().unwrap(),
+        "--scenario",
+        "normal",
+        "--seconds",
+        "20",
+    ])
+    .unwrap();
+
+    let hist = root.join("hist.csv");
+    let out = execute(&[
+        "stats",
+        root.to_str().unwrap(),
+        "--bins",
+        "4",
+        "--histogram-csv",
+        hist.to_str().unwrap(),
+    ])
+    .unwrap();
+    assert!(out.contains("overheat: 10 runs in 2 batches"), "{out}");
+    assert!(out.contains("  trips            10 (p = 1.000"), "{out}");
+    assert!(out.contains("normal: 3 runs in 1 batch"), "{out}");
+    assert!(out.contains("  trips            0 (p = 0.000"), "{out}");
+
+    // The hottest overheat run, found by hand from both runs.csv files.
+    let mut hottest = (f64::MIN, 0, PathBuf::new());
+    for dir in [root.join("a"), root.join("b")] {
+        let batch = read_batch(&dir.join("runs.csv")).unwrap();
+        assert_eq!((batch.schema, batch.scenario.as_str()), (2, "overheat"));
+        for run in batch.runs {
+            if run.max_temp > hottest.0 {
+                hottest = (run.max_temp, run.seed, dir.clone());
+            }
+        }
+    }
+    let line = format!(
+        "  hottest run      {:.2} °C  seed {} ({})",
+        hottest.0,
+        hottest.1,
+        hottest.2.display()
+    );
+    assert!(out.contains(&line), "{line}\n{out}");
+
+    let csv = fs::read_to_string(&hist).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "scenario,bin_lo,bin_hi,count");
+    let counts: usize = lines[1..]
+        .iter()
+        .map(|l| {
+            assert!(l.starts_with("overheat,"), "{l}");
+            l.rsplit(',').next().unwrap().parse::<usize>().unwrap()
+        })
+        .sum();
+    assert_eq!(counts, 10);
+}
+
+#[test]
+fn unversioned_batch_is_read_and_newer_schema_rejected() {
+    let root = scratch("schema");
+    let old = root.join("old");
+    fs::create_dir_all(&old).unwrap();
+    fs::write(
+        old.join("runs.csv"),
+        "seed,tripped,reason,t_trip,max_temp,min_margin\n\
+         7,true,OverTemp,20.5,391.2,-1.2\n\
+         8,false,,,385.0,4.5\n",
+    )
+    .unwrap();
+    let out = execute(&["stats", old.to_str().unwrap()]).unwrap();
+    assert!(out.contains("unknown: 2 runs in 1 batch"), "{out}");
+    assert!(out.contains("earliest trip    20.50 s  seed 7"), "{out}");
+    assert!(out.contains("smallest margin  -1.20 °C  seed 7"), "{out}");
+
+    let new = root.join("new");
+    fs::create_dir_all(&new).unwrap();
+    fs::copy(old.join("runs.csv"), new.join("runs.csv")).unwrap();
+    fs::write(new.join("aggregate.json"), r#"{"schema": 99}"#).unwrap();
+    let err = execute(&["stats", new.to_str().unwrap()]).unwrap_err();
+    assert_eq!(exit::code_for(&err), exit::INVALID_INPUT);
+    assert!(err.to_string().contains("schema 99"), "{err}");
+}
+
+#[test]
+fn histogram_bins_cover_the_range() {
+    let bins = histogram(&[1.0, 2.0, 2.5, 4.9, 5.0], 4);
+    assert_eq!(bins.len(), 4);
+    assert_eq!((bins[0].lo, bins[3].hi), (1.0, 5.0));
+    let counts: Vec<usize> = bins.iter().map(|b| b.count).collect();
+    assert_eq!(counts, [1, 2, 0, 2]);
+    let single = histogram(&[3.0, 3.0], 10);
+    assert_eq!(single.len(), 1);
+    assert_eq!(single[0].count, 2);
+    assert!(histogram(&[], 10).is_empty());
+}
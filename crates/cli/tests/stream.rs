@@ -0,0 +1,140 @@
+mod common;
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use cli::stream::{ListenAddr, TraceServer, CLIENT_BUFFER_ROWS};
+use cli::trace::read_trace;
+use cli::{exit, Cli};
+use common::scratch;
+
+fn retry<T>(mut attempt: impl FnMut() -> Option<T>) -> T {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if let Some(value) = attempt() {
+            return value;
+        }
+        assert!(Instant::now() < deadline, "timed out");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn client_receives_header_and_every_row_of_a_run() {
+    use std::os::unix::net::UnixStream;
+
+    let dir = scratch("run");
+    let socket = dir.join("trace.sock");
+    let out = dir.join("trace.csv");
+    let argv = [
+        "reactor-safety-sim",
+        "run",
+        "--scenario",
+        "overheat",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+        "--seconds",
+        "30",
+        "--summary",
+        "none",
+        "--quiet",
+        "--wait-for-clients",
+        "1",
+        "--listen",
+        &format!("unix:{}", socket.display()),
+        "--format",
+        "csv",
+        "--out",
+        out.to_str().unwrap(),
+    ];
+    let cli = Cli::try_parse_from(argv).unwrap();
+    let run = thread::spawn(move || cli::execute(&cli, &mut Vec::new()).unwrap());
+
+    let mut client = retry(|| UnixStream::connect(&socket).ok());
+    let mut received = String::new();
+    client.read_to_string(&mut received).unwrap();
+    assert_eq!(run.join().unwrap(), exit::OK);
+
+    let lines: Vec<&str> = received.lines().collect();
+    assert!(lines[0].starts_with(r#"{"_meta":"#), "{}", lines[0]);
+    assert!(
+        lines[0].contains(r#""scenario":"overheat""#),
+        "{}",
+        lines[0]
+    );
+    // the CSV --out trace holds the same rows
+    let rows = read_trace(&out).unwrap();
+    assert_eq!(lines.len(), 1 + rows.len());
+    assert_eq!(rows.len(), 398, "trips at 19.85 s");
+    let last: serde_json::Value = serde_json::from_str(lines.last().unwrap()).unwrap();
+    assert_eq!(last["scram"], true);
+    assert_eq!(last["reason"]["kind"], "over_temp");
+    assert!(!socket.exists(), "the socket file is removed after the run");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn tcp_clients_each_get_the_header_then_later_rows() {
+    let addr: ListenAddr = "127.0.0.1:0".parse().unwrap();
+    let server = TraceServer::bind(&addr, r#"{"_meta":{}}"#).unwrap();
+    let ListenAddr::Tcp(bound) = server.addr().clone() else {
+        panic!("bound a TCP address");
+    };
+    let first = TcpStream::connect(&bound).unwrap();
+    server.wait_for_clients(1);
+    server.send("row 1");
+    let second = TcpStream::connect(&bound).unwrap();
+    server.wait_for_clients(2);
+    server.send("row 2");
+    server.finish();
+
+    let lines =
+        |stream| -> Vec<String> { BufReader::new(stream).lines().map(Result::unwrap).collect() };
+    assert_eq!(lines(first), [r#"{"_meta":{}}"#, "row 1", "row 2"]);
+    assert_eq!(lines(second), [r#"{"_meta":{}}"#, "row 2"]);
+}
+
+#[test]
+fn client_that_stops_reading_is_dropped_without_stalling_the_sender() {
+    let addr: ListenAddr = "127.0.0.1:0".parse().unwrap();
+    let server = TraceServer::bind(&addr, "header").unwrap();
+    let ListenAddr::Tcp(bound) = server.addr().clone() else {
+        panic!("bound a TCP address");
+    };
+    let _stalled = TcpStream::connect(&bound).unwrap();
+    server.wait_for_clients(1);
+
+    let row = "x".repeat(4096);
+    let started = Instant::now();
+    let mut sent = 0;
+    while server.client_count() > 0 {
+        server.send(&row);
+        sent += 1;
+        assert!(sent < 100 * CLIENT_BUFFER_ROWS, "never dropped");
+    }
+    assert!(sent > CLIENT_BUFFER_ROWS);
+    assert!(started.elapsed() < Duration::from_secs(5));
+    server.finish();
+}
+
+#[test]
+fn listen_address_forms() {
+    assert_eq!(
+        "localhost:7000".parse(),
+        Ok(ListenAddr::Tcp("localhost:7000".into()))
+    );
+    assert!("7000".parse::<ListenAddr>().is_err());
+    assert!("unix:".parse::<ListenAddr>().is_err());
+    #[cfg(unix)]
+    assert_eq!(
+        "unix:/tmp/rss.sock".parse(),
+        Ok(ListenAddr::Unix("/tmp/rss.sock".into()))
+    );
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use std::fs;
+
+use assert_cmd::Command;
+use cli::replay::replay;
+use cli::trace::{parse_trace, TraceFormat};
+use common::scratch;
+
+fn bin() -> Command {
+    Command::cargo_bin("cli").unwrap()
+}
+
+fn stdout_of(cmd: &mut Command) -> String {
+    String::from_utf8(cmd.assert().success().get_output().stdout.clone()).unwrap()
+}
+
+const SUBCOMMANDS: [&str; 16] = [
+    "run",
+    "replay",
+    "batch",
+    "inspect",
+    "validate",
+    "list-scenarios",
+    "diff",
+    "verify",
+    "search",
+    "sweep",
+    "analyze",
+    "stats",
+    "schema",
+    "golden",
+    "bench",
+    "convert",
+];
+
+#[test]
+fn every_subcommand_has_help() {
+    for sub in SUBCOMMANDS {
+        let help = stdout_of(bin().args([sub, "--help"]));
+        assert!(help.contains("Usage:"), "{sub}: {help}");
+    }
+    let help = stdout_of(bin().arg("--help"));
+    for sub in SUBCOMMANDS {
+        assert!(help.contains(sub), "{help}");
+    }
+}
+
+#[test]
+fn run_writes_trace_to_stdout() {
+    let trace = stdout_of(bin().args(["run", "--seconds", "1", "--dt-ms", "250"]));
+    // `_meta` header + one row per step
+    assert_eq!(trace.lines().count(), 1 + 4);
+}
+
+#[test]
+fn flat_flags_still_run_with_deprecation_warning() {
+    let assert = bin()
+        .args(["--seconds", "1", "--dt-ms", "250"])
+        .assert()
+        .success();
+    let out = assert.get_output();
+    assert_eq!(String::from_utf8_lossy(&out.stdout).lines().count(), 1 + 4);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("deprecated"));
+}
+
+#[test]
+fn replay_and_inspect_a_recorded_trace() {
+    let dir = scratch("replay");
+    let path = dir.join("overheat.csv");
+    bin()
+        .args([
+            "run",
+            "--scenario",
+            "overheat",
+            "--seconds",
+            "180",
+            "--setpoint",
+            "380",
+        ])
+        .args(["--trip-temp", "390", "--format", "csv", "--out"])
+        .arg(&path)
+        .assert()
+        .success();
+
+    let replayed = stdout_of(bin().arg("replay").arg(&path).args(["--trip-temp", "390"]));
+    assert!(replayed.contains("trip decisions match"), "{replayed}");
+
+    let stats = stdout_of(bin().arg("inspect").arg(&path));
+    assert!(stats.contains("rows"), "{stats}");
+    assert!(stats.contains("OverTemp"), "{stats}");
+
+    // Replaying against the default 420 °C threshold misses the recorded trip
+    bin().arg("replay").arg(&path).assert().failure();
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn batch_writes_summary_files() {
+    let dir = scratch("batch");
+    let out = stdout_of(
+        bin()
+            .args([
+                "batch",
+                "--seeds",
+                "3",
+                "--seconds",
+                "1",
+                "--dt-ms",
+                "250",
+                "--out-dir",
+            ])
+            .arg(&dir),
+    );
+    assert!(out.contains("3 runs, 0 tripped"), "{out}");
+    assert!(dir.join("runs.csv").exists());
+    assert!(dir.join("aggregate.json").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn replay_flags_edited_trip_state() {
+    let trace = "t_s,setpoint_c,true_temp_c,s1_c,s2_c,s3_c,power,coolant,scram,reason\n\
+                 0,350,350,350,350,350,0.5,0.6,false,\n\
+                 0.05,350,350,350,350,350,0.5,0.6,true,\"OverTemp\"\n";
+    let rows = parse_trace(trace, TraceFormat::Csv).unwrap();
+    let report = replay(&rows, &safety::SafetyConfig::default());
+    assert_eq!(report.rows, 2);
+    assert_eq!(report.mismatches, 1);
+    assert_eq!(report.first_mismatch.unwrap().t_s, 0.05);
+}
@@ -0,0 +1,79 @@
+use std::fs;
+
+use assert_cmd::Command;
+use serde_json::Value;
+
+fn parse(bytes: &[u8]) -> Value {
+    let text = String::from_utf8_lossy(bytes);
+    let line = text.lines().last().expect("summary line");
+    serde_json::from_str(line).unwrap()
+}
+
+#[test]
+fn overheat_summary_reports_trip() {
+    let dir = std::env::temp_dir().join(format!("rss-summary-{}", std::process::id()));
+    let assert = Command::cargo_bin("cli")
+        .unwrap()
+        .args([
+            "run",
+            "--scenario",
+            "overheat",
+            "--setpoint",
+            "380",
+            "--trip-temp",
+            "390",
+        ])
+        .args(["--seconds", "60", "--summary", "json", "--out"])
+        .arg(dir.join("trace.jsonl"))
+        .assert()
+        .success();
+    // with --out the summary is the only thing on stdout
+    let s = parse(&assert.get_output().stdout);
+    assert_eq!(s["outcome"], "tripped");
+    assert_eq!(s["reason"], "OverTemp");
+    let trip_t = s["trip_t_s"].as_f64().unwrap();
+    assert!(trip_t > 0.0 && trip_t < 60.0);
+    assert_eq!(s["end_t_s"].as_f64().unwrap(), trip_t);
+    assert!(s["max_temp_c"].as_f64().unwrap() > 380.0);
+    assert!(s["min_margin_c"].as_f64().unwrap() <= 0.0);
+    assert!(s["wall_clock_s"].as_f64().unwrap() >= 0.0);
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn normal_summary_reports_completion_on_stderr() {
+    let assert = Command::cargo_bin("cli")
+        .unwrap()
+        .args([
+            "run",
+            "--scenario",
+            "normal",
+            "--seconds",
+            "10",
+            "--dt-ms",
+            "250",
+        ])
+        .args(["--summary", "json"])
+        .assert()
+        .success();
+    let out = assert.get_output();
+    // stdout stays pure trace: the `_meta` header and 40 rows
+    assert_eq!(String::from_utf8_lossy(&out.stdout).lines().count(), 1 + 40);
+    let s = parse(&out.stderr);
+    assert_eq!(s["outcome"], "completed");
+    assert!(s["reason"].is_null() && s["trip_t_s"].is_null());
+    assert_eq!(s["steps"], 40);
+    assert!(s["max_temp_c"].as_f64().unwrap() < 420.0);
+    let spread = s["max_sensor_spread_c"].as_f64().unwrap();
+    assert!(spread > 0.0 && spread < 5.0, "{spread}");
+}
+
+#[test]
+fn summary_none_prints_nothing() {
+    let assert = Command::cargo_bin("cli")
+        .unwrap()
+        .args(["run", "--seconds", "1", "--summary", "none"])
+        .assert()
+        .success();
+    assert!(assert.get_output().stderr.is_empty());
+}
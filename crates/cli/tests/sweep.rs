@@ -0,0 +1,106 @@
+use std::collections::HashSet;
+use std::fs;
+
+use clap::Parser;
+use cli::param::Param;
+use cli::sweep::SweepSpec;
+use cli::{exit, Cli};
+
+fn sweep(flags: &[&str]) -> anyhow::Result<(u8, String)> {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "sweep",
+        "--scenario",
+        "overheat",
+        "--seconds",
+        "60",
+        "--setpoint",
+        "380",
+    ];
+    argv.extend_from_slice(flags);
+    let mut out = Vec::new();
+    let code = cli::execute(&Cli::try_parse_from(argv).unwrap(), &mut out)?;
+    Ok((code, String::from_utf8(out).unwrap()))
+}
+
+#[test]
+fn two_by_two_sweep_writes_four_rows() {
+    let dir = std::env::temp_dir().join(format!("rss-sweep-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let results = dir.join("results.csv");
+    let (code, out) = sweep(&[
+        "--sweep",
+        "kp=0.02..0.06:2",
+        "--sweep",
+        "trip_temp=385..420:2",
+        "--jobs",
+        "2",
+        "--results",
+        results.to_str().unwrap(),
+    ])
+    .unwrap();
+    assert_eq!(code, exit::OK);
+    assert!(out.starts_with("4 runs, "), "{out}");
+
+    let text = fs::read_to_string(&results).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(
+        lines[0],
+        "kp,trip-temp,tripped,reason,t_trip,max_temp,min_margin,final_temp"
+    );
+    assert_eq!(lines.len(), 5, "{text}");
+    let mut combos = HashSet::new();
+    for line in &lines[1..] {
+        let cells: Vec<&str> = line.split(',').collect();
+        let kp: f64 = cells[0].parse().unwrap();
+        let trip: f64 = cells[1].parse().unwrap();
+        assert!(combos.insert((kp.to_bits(), trip.to_bits())), "{text}");
+        let tripped: bool = cells[2].parse().unwrap();
+        assert_eq!(tripped, !cells[4].is_empty(), "{line}");
+        let max_temp: f64 = cells[5].parse().unwrap();
+        assert!(max_temp > 300.0 && max_temp < 500.0, "{line}");
+        if tripped {
+            assert_eq!(cells[3], "OverTemp", "{line}");
+            assert!(max_temp >= trip - 1.0, "{line}");
+        } else {
+            assert!(max_temp < trip, "{line}");
+        }
+    }
+    assert_eq!(combos.len(), 4);
+    // The tight trip temperature trips, the loose one does not.
+    assert!(text.contains(",385,true,OverTemp,"), "{text}");
+    assert!(text.contains(",420,false,,"), "{text}");
+}
+
+#[test]
+fn csv_goes_to_stdout_without_results() {
+    let (code, out) = sweep(&["--sweep", "initial-coolant=0.2..0.2:1"]).unwrap();
+    assert_eq!(code, exit::OK);
+    assert_eq!(out.lines().count(), 2, "{out}");
+    assert!(out.starts_with("initial-coolant,tripped,"), "{out}");
+}
+
+#[test]
+fn max_runs_guards_the_grid() {
+    let err = sweep(&[
+        "--sweep",
+        "kp=0.01..0.1:10",
+        "--sweep",
+        "ki=0..0.01:10",
+        "--max-runs",
+        "99",
+    ])
+    .unwrap_err();
+    assert_eq!(exit::code_for(&err), exit::INVALID_INPUT);
+    assert!(err.to_string().contains("--max-runs 99"), "{err}");
+}
+
+#[test]
+fn sweep_spec_parses_and_spaces_values() {
+    let spec: SweepSpec = "plant_k_cool=1..2:5".parse().unwrap();
+    assert_eq!(spec.param, Param::PlantKCool);
+    assert_eq!(spec.values(), [1.0, 1.25, 1.5, 1.75, 2.0]);
+    assert!("kp=1..2".parse::<SweepSpec>().is_err());
+    assert!("kp=1..2:0".parse::<SweepSpec>().is_err());
+    assert!("nope=1..2:3".parse::<SweepSpec>().is_err());
+}
@@ -0,0 +1,64 @@
+use clap::Parser;
+use cli::trace::{csv_header, split_csv};
+use cli::RunArgs;
+
+fn run(extra: &[&str]) -> String {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--scenario",
+        "loss-of-cooling",
+        "--seconds",
+        "20",
+        "--diagnostics",
+    ];
+    argv.extend_from_slice(extra);
+    let args = RunArgs::try_parse_from(argv).unwrap();
+    let mut out = Vec::new();
+    cli::run(&args, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn csv_header_lists_base_and_diagnostics_columns() {
+    let csv = run(&["--format", "csv"]);
+    let header = csv.lines().next().unwrap();
+    assert_eq!(header, csv_header(true).join(","));
+    assert!(header.starts_with("t_s,setpoint_c,"));
+    assert!(header.ends_with(",ctrl_status"));
+    assert!(!csv_header(false).contains(&"p_term"));
+}
+
+#[test]
+fn csv_and_jsonl_traces_match_row_for_row() {
+    let csv = run(&["--format", "csv"]);
+    let jsonl = run(&["--format", "jsonl"]);
+
+    let mut lines = csv.lines();
+    let header = split_csv(lines.next().unwrap());
+    let csv_rows: Vec<Vec<String>> = lines.map(split_csv).collect();
+    let json_rows: Vec<serde_json::Value> = jsonl
+        .lines()
+        .skip(1) // `_meta` header
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(csv_rows.len(), json_rows.len());
+    assert!(!csv_rows.is_empty());
+
+    for (cells, json) in csv_rows.iter().zip(&json_rows) {
+        assert_eq!(cells.len(), header.len());
+        for (col, cell) in header.iter().zip(cells) {
+            let v = &json[col.as_str()];
+            if let Some(expected) = v.as_f64() {
+                let got: f64 = cell.parse().unwrap();
+                assert_eq!(got, expected, "column {col}");
+            } else if let Some(b) = v.as_bool() {
+                assert_eq!(cell, &b.to_string(), "column {col}");
+            } else if let Some(s) = v.as_str() {
+                assert_eq!(cell, s, "column {col}");
+            } else {
+                // null: the reason column before a trip, or NaN serialized by serde_json
+                assert!(cell.is_empty(), "column {col}: {cell:?}");
+            }
+        }
+    }
+}
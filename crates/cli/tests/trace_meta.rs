@@ -0,0 +1,112 @@
+use std::fs;
+
+use clap::Parser;
+use cli::trace::{parse_trace_with_meta, read_trace, TraceFormat, TRACE_SCHEMA};
+use cli::RunArgs;
+
+fn jsonl(extra: &[&str]) -> String {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--scenario",
+        "overheat",
+        "--seconds",
+        "10",
+        "--seed",
+        "42",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+    ];
+    argv.extend_from_slice(extra);
+    let mut out = Vec::new();
+    cli::run(&RunArgs::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn jsonl_starts_with_meta_header() {
+    let text = jsonl(&[]);
+    let first = text.lines().next().unwrap();
+    assert!(first.starts_with(r#"{"_meta":"#), "{first}");
+
+    let (meta, rows) = parse_trace_with_meta(&text, TraceFormat::Jsonl).unwrap();
+    let meta = meta.expect("header");
+    assert_eq!(meta.header.schema, TRACE_SCHEMA);
+    assert_eq!(meta.header.scenario, "overheat");
+    assert_eq!((meta.header.seed, meta.header.dt_ms), (42, 50));
+    assert_eq!(
+        (meta.header.setpoint, meta.header.trip_temp),
+        (380.0, 390.0)
+    );
+    assert_eq!(meta.header.crate_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(meta.header.config_hash.len(), 16);
+    assert_eq!(rows.len(), text.lines().count() - 1);
+}
+
+#[test]
+fn config_hash_tracks_the_inputs() {
+    let hash = |extra: &[&str]| {
+        let (meta, _) = parse_trace_with_meta(&jsonl(extra), TraceFormat::Jsonl).unwrap();
+        meta.unwrap().header.config_hash
+    };
+    assert_eq!(hash(&[]), hash(&[]));
+    assert_ne!(hash(&[]), hash(&["--kp", "0.01"]));
+    assert_ne!(hash(&[]), hash(&["--fault", "s2:bias:20"]));
+}
+
+#[test]
+fn legacy_traces_without_header_still_load() {
+    let text = jsonl(&[]);
+    let legacy: String = text.lines().skip(1).map(|l| format!("{l}\n")).collect();
+    let (meta, rows) = parse_trace_with_meta(&legacy, TraceFormat::Jsonl).unwrap();
+    assert!(meta.is_none());
+    let (_, with_header) = parse_trace_with_meta(&text, TraceFormat::Jsonl).unwrap();
+    assert_eq!(format!("{rows:?}"), format!("{with_header:?}"));
+}
+
+#[test]
+fn csv_has_no_meta_line() {
+    let text = jsonl(&["--format", "csv"]);
+    assert!(text.starts_with("t_s,"));
+}
+
+#[test]
+fn newer_schema_is_rejected() {
+    let text = jsonl(&[]).replacen(
+        &format!(r#""schema":{TRACE_SCHEMA}"#),
+        &format!(r#""schema":{}"#, TRACE_SCHEMA + 1),
+        1,
+    );
+    let dir = std::env::temp_dir().join(format!("rss-meta-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("future.jsonl");
+    fs::write(&path, text).unwrap();
+
+    let err = format!("{:#}", read_trace(&path).unwrap_err());
+    assert!(
+        err.contains(&format!("trace schema {} is newer", TRACE_SCHEMA + 1)),
+        "{err}"
+    );
+    assert!(err.contains("line 1"), "{err}");
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn auto_gains_keep_the_pid_settings_and_are_recorded() {
+    let dir = std::env::temp_dir().join(format!("rss-meta-auto-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("pid.toml");
+    fs::write(&path, "[pid]\nout_max = 0.8\nd_filter_tau_s = 2.0\n").unwrap();
+    let config = path.to_str().unwrap();
+
+    let text = jsonl(&["--config", config, "--auto-gains", "60"]);
+    let (meta, _) = parse_trace_with_meta(&text, TraceFormat::Jsonl).unwrap();
+    let pid = meta.unwrap().config.unwrap().pid;
+    // Designed at the overheat scenario's initial coolant
+    let coolant = cli::Scenario::Overheat.spec(10.0).initial.coolant;
+    let suggested = controller::tuning::suggest_gains(&Default::default(), coolant, 60.0);
+    assert_eq!((pid.kp, pid.ki, pid.kd), (suggested.kp, suggested.ki, 0.0));
+    assert_eq!((pid.out_max, pid.d_filter_tau_s), (0.8, 2.0));
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,132 @@
+use std::fs;
+use std::sync::atomic::AtomicBool;
+
+use clap::Parser;
+use cli::trace::{read_trace, read_trace_with_meta, RowReason, TraceRow};
+use cli::RunArgs;
+use safety::{TripDetail, TripReason, TripRecord};
+use serde_json::{json, Value};
+
+const LEGACY: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/legacy_schema2.jsonl"
+);
+
+#[test]
+fn each_trip_kind_serializes_to_a_structured_object() {
+    let cases = [
+        (
+            TripDetail::OverTemp {
+                trip_temp_c: 420.0,
+                hottest_c: 421.5,
+            },
+            [true, false, true],
+            json!({
+                "kind": "over_temp",
+                "channels": [true, false, true],
+                "detail": {"trip_temp_c": 420.0, "hottest_c": 421.5}
+            }),
+        ),
+        (
+            TripDetail::SensorInvalid {
+                valid_sensors: 1,
+                valid_range_c: (0.0, 2000.0),
+            },
+            [true, true, false],
+            json!({
+                "kind": "sensor_invalid",
+                "channels": [true, true, false],
+                "detail": {"valid_sensors": 1, "valid_range_c": [0.0, 2000.0]}
+            }),
+        ),
+        (
+            TripDetail::SensorDisagree {
+                spread_c: 12.5,
+                max_sensor_delta_c: 10.0,
+            },
+            [false, true, true],
+            json!({
+                "kind": "sensor_disagree",
+                "channels": [false, true, true],
+                "detail": {"spread_c": 12.5, "max_sensor_delta_c": 10.0}
+            }),
+        ),
+        (
+            TripDetail::Manual {},
+            [false; 3],
+            json!({"kind": "manual", "channels": [false, false, false], "detail": {}}),
+        ),
+    ];
+    for (detail, channels, expected) in cases {
+        let trip = TripRecord { detail, channels };
+        assert_eq!(serde_json::to_value(trip).unwrap(), expected);
+        let back: RowReason = serde_json::from_value(expected).unwrap();
+        assert_eq!(back, RowReason::Trip(trip));
+        assert_eq!(back.name(), format!("{:?}", detail.reason()));
+    }
+}
+
+#[test]
+fn legacy_string_reasons_are_still_read() {
+    let (meta, rows) = read_trace_with_meta(LEGACY.as_ref()).unwrap();
+    assert_eq!(meta.unwrap().header.schema, 2);
+    assert_eq!(rows.len(), 4);
+    assert_eq!(rows[1].reason, None);
+    for row in &rows[2..] {
+        assert_eq!(row.reason, Some(RowReason::Name("OverTemp".into())));
+        assert_eq!(row.reason_name().as_deref(), Some("OverTemp"));
+    }
+    assert!(rows[3].s2_c.is_nan());
+}
+
+#[test]
+fn new_traces_carry_the_trip_on_every_row_after_it() {
+    let dir = std::env::temp_dir().join(format!("rss-trip-reason-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("overheat.jsonl");
+    let argv = [
+        "reactor-safety-sim",
+        "--quiet",
+        "--scenario",
+        "overheat",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+        "--seconds",
+        "30",
+        "--continue-after-scram",
+    ];
+    let args = RunArgs::try_parse_from(argv).unwrap();
+    let summary = cli::run_to_file(&args, &path, &AtomicBool::new(false)).unwrap();
+    assert_eq!(summary.reason.as_deref(), Some("OverTemp"));
+
+    let text = fs::read_to_string(&path).unwrap();
+    let rows = read_trace(&path).unwrap();
+    let tripped: Vec<&TraceRow> = rows.iter().filter(|r| r.scram).collect();
+    assert!(tripped.len() > 1);
+    assert!(rows.iter().all(|r| r.scram == r.reason.is_some()));
+    let Some(RowReason::Trip(first)) = &tripped[0].reason else {
+        panic!("{:?}", tripped[0].reason);
+    };
+    assert_eq!(first.reason(), TripReason::OverTemp);
+    assert!(first.channels.iter().filter(|c| **c).count() >= 2);
+    let TripDetail::OverTemp {
+        trip_temp_c,
+        hottest_c,
+    } = first.detail
+    else {
+        panic!("{first:?}");
+    };
+    assert_eq!(trip_temp_c, 390.0);
+    assert!(hottest_c >= 390.0, "{hottest_c}");
+    // latched: the record of the trip step repeats unchanged
+    for row in &tripped {
+        assert_eq!(row.reason, Some(RowReason::Trip(*first)));
+    }
+
+    let last: Value = serde_json::from_str(text.lines().last().unwrap()).unwrap();
+    assert_eq!(last["reason"]["kind"], "over_temp");
+    assert_eq!(last["reason"]["detail"]["trip_temp_c"], 390.0);
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,151 @@
+use clap::Parser;
+use cli::summary::Outcome;
+use cli::trace::{parse_trace, TraceFormat, TraceRow};
+use cli::until::UntilCondition;
+use cli::{RunArgs, RunSummary};
+
+fn run(extra: &[&str]) -> (Vec<TraceRow>, RunSummary) {
+    let mut argv = vec![
+        "reactor-safety-sim",
+        "--seconds",
+        "120",
+        "--format",
+        "csv",
+        "--quiet",
+    ];
+    argv.extend_from_slice(extra);
+    let mut out = Vec::new();
+    let summary = cli::run(&RunArgs::try_parse_from(argv).unwrap(), &mut out).unwrap();
+    let rows = parse_trace(&String::from_utf8(out).unwrap(), TraceFormat::Csv).unwrap();
+    (rows, summary)
+}
+
+/// Check that the `--until` run ends on the row where the full run first satisfies `holds`.
+fn assert_stops_where(
+    base: &[&str],
+    until: &str,
+    holds: impl Fn(&[TraceRow], usize) -> bool,
+) -> RunSummary {
+    let (full, _) = run(base);
+    let expected = (0..full.len())
+        .find(|&i| holds(&full, i))
+        .unwrap_or_else(|| panic!("{until} never holds in the full run"));
+
+    let mut argv = base.to_vec();
+    argv.extend(["--until", until]);
+    let (rows, summary) = run(&argv);
+    assert_eq!(rows.len(), expected + 1, "{until}");
+    assert_eq!(summary.end_t_s, full[expected].t_s, "{until}");
+    assert_eq!(summary.outcome, Outcome::Stopped);
+    assert_eq!(summary.stopped_by.as_deref(), Some(until));
+    summary
+}
+
+#[test]
+fn settled_stops_once_the_temperature_has_stayed_in_the_band() {
+    let in_band = |row: &TraceRow| (row.true_temp_c - row.setpoint_c).abs() <= 0.5;
+    let summary = assert_stops_where(&["--scenario", "normal"], "settled:0.5:20", |rows, i| {
+        let start = rows[..=i]
+            .iter()
+            .rposition(|r| !in_band(r))
+            .map_or(0, |j| j + 1);
+        start <= i && rows[i].t_s - rows[start].t_s >= 20.0 - 1e-9
+    });
+    assert!(summary.end_t_s >= 20.0);
+}
+
+#[test]
+fn max_temp_stops_on_the_first_step_above_it() {
+    let overheat = [
+        "--scenario",
+        "overheat",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+    ];
+    let summary = assert_stops_where(&overheat, "max-temp:360", |rows, i| {
+        rows[i].true_temp_c > 360.0
+    });
+    assert!(!summary.tripped());
+}
+
+#[test]
+fn alarms_counts_rising_edges_of_the_disagreement_alarm() {
+    // s2 reads 7 °C high (alarm, no trip) from 2 s to 4 s and again from 6 s
+    let biased = [
+        "--scenario",
+        "normal",
+        "--fault",
+        "s2:bias:7@2s",
+        "--fault",
+        "s2:none@4s",
+        "--fault",
+        "s2:bias:7@6s",
+    ];
+    let alarm = |row: &TraceRow| {
+        let temps = [row.s1_c, row.s2_c, row.s3_c];
+        let hottest = temps.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        let coldest = temps.iter().copied().fold(f64::INFINITY, f64::min);
+        hottest - coldest > safety::SafetyConfig::default().alarm_sensor_delta_c
+    };
+    let summary = assert_stops_where(&biased, "alarms:2", |rows, i| {
+        let raised = (0..=i)
+            .filter(|&j| alarm(&rows[j]) && (j == 0 || !alarm(&rows[j - 1])))
+            .count();
+        raised >= 2
+    });
+    assert!(summary.end_t_s >= 6.0, "{}", summary.end_t_s);
+}
+
+#[test]
+fn first_condition_to_hold_names_the_cause() {
+    let (_, summary) = run(&[
+        "--scenario",
+        "overheat",
+        "--until",
+        "settled:0.1:1000",
+        "--until",
+        "max-temp:340",
+    ]);
+    assert_eq!(summary.stopped_by.as_deref(), Some("max-temp:340"));
+    assert!(summary.to_string().contains("stopped (max-temp:340) at"));
+}
+
+#[test]
+fn a_trip_before_the_condition_ends_the_run_as_usual() {
+    let (_, summary) = run(&["--scenario", "sensor-disagree", "--until", "settled:1:30"]);
+    assert!(summary.tripped());
+    assert_eq!(summary.stopped_by, None);
+}
+
+#[test]
+fn conditions_parse_or_explain() {
+    assert_eq!(
+        "settled:2:30".parse(),
+        Ok(UntilCondition::Settled {
+            band_c: 2.0,
+            for_s: 30.0
+        })
+    );
+    assert_eq!(
+        "max-temp:395.5".parse(),
+        Ok(UntilCondition::MaxTemp { c: 395.5 })
+    );
+    assert_eq!("alarms:3".parse(), Ok(UntilCondition::Alarms { count: 3 }));
+    for canonical in ["settled:2:30", "max-temp:395.5", "alarms:3"] {
+        let condition: UntilCondition = canonical.parse().unwrap();
+        assert_eq!(condition.to_string(), canonical);
+    }
+    for (arg, error) in [
+        ("settled:2", "settled:BAND:SECS"),
+        ("settled:0:30", "band must be > 0"),
+        ("max-temp:hot", "invalid temperature"),
+        ("alarms:0", "whole number >= 1"),
+        ("alarms:1.5", "whole number >= 1"),
+        ("timeout:30", "unknown condition"),
+    ] {
+        let e = arg.parse::<UntilCondition>().unwrap_err();
+        assert!(e.contains(error), "{arg}: {e}");
+    }
+}
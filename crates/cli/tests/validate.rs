@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::PathBuf;
+
+use cli::exit;
+use cli::validate::{self, ValidateArgs};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+const EXAMPLES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../examples");
+
+fn file(name: &str, text: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("rss-validate-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, text).unwrap();
+    path
+}
+
+fn validate(config: Option<PathBuf>, scenario_file: Option<PathBuf>) -> (u8, String) {
+    let args = ValidateArgs {
+        config,
+        scenario_file,
+    };
+    let mut out = Vec::new();
+    let code = validate::execute(&args, &mut out).unwrap();
+    (code, String::from_utf8(out).unwrap())
+}
+
+#[test]
+fn clean_files_pass() {
+    let (code, out) = validate(
+        Some(format!("{FIXTURES}/sample.toml").into()),
+        Some(format!("{EXAMPLES}/overheat.toml").into()),
+    );
+    assert_eq!(code, exit::OK, "{out}");
+    assert!(out.starts_with("ok: "), "{out}");
+}
+
+#[test]
+fn every_problem_is_reported_in_one_pass() {
+    let path = file(
+        "broken.toml",
+        "[run]\nsetpoint = 450.0\n\n[plant]\nthermal_mass = 0.0\n\n[pid]\nkp = -0.1\n",
+    );
+    let (code, out) = validate(Some(path.clone()), None);
+    assert_eq!(code, exit::INVALID_INPUT);
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 3 + 1, "{out}");
+    let at = |msg: &str| {
+        assert!(
+            lines
+                .iter()
+                .any(|l| *l == format!("{}: {msg}", path.display())),
+            "{msg} missing from:\n{out}"
+        )
+    };
+    at("plant.thermal_mass = 0 must be > 0");
+    at("pid.kp = -0.1 is negative; use reverse_acting to invert the control action");
+    at("run.setpoint = 450 must be below safety.trip_temp_c (420)");
+    assert_eq!(lines[3], format!("3 problems in {}", path.display()));
+}
+
+#[test]
+fn cross_checks_span_sections_and_files() {
+    let config = file(
+        "cross.toml",
+        "[safety]\ntrip_temp_c = 500.0\n\n[sensors.s3]\nvalid_range = [0.0, 450.0]\n",
+    );
+    let scenario = file(
+        "cross_scenario.toml",
+        "[[events]]\nt_s = 10.0\naction = { set_setpoint = 520.0 }\n\n[[events]]\nt_s = -1.0\naction = { set_coolant = 1.5 }\n",
+    );
+    let (code, out) = validate(Some(config), Some(scenario));
+    assert_eq!(code, exit::INVALID_INPUT);
+    assert!(out.contains("outside sensors.s3.valid_range"), "{out}");
+    assert!(out.contains("events[1].t_s = -1 must be >= 0"), "{out}");
+    assert!(out.contains("events[1].action.set_coolant = 1.5"), "{out}");
+    assert!(
+        out.contains("events[0].action.set_setpoint = 520 must be below safety.trip_temp_c (500)"),
+        "{out}"
+    );
+}
+
+#[test]
+fn syntax_errors_are_reported_per_file() {
+    let config = file("syntax.toml", "[run\nseed = 1\n");
+    let scenario = file("unknown.toml", "[initial]\ntemperature = 300.0\n");
+    let (code, out) = validate(Some(config.clone()), Some(scenario.clone()));
+    assert_eq!(code, exit::INVALID_INPUT);
+    assert!(out.starts_with(&format!("{}: ", config.display())), "{out}");
+    assert!(out.contains(&format!("{}: ", scenario.display())), "{out}");
+    assert!(out.contains("temperature"), "{out}");
+}
+
+/// Exit code and stderr of a one-second `run` with `extra` flags, which checks its resolved
+/// configuration the same way before simulating anything.
+fn run_exit_code(extra: &[&str]) -> (i32, String) {
+    let out = std::process::Command::new(env!("CARGO_BIN_EXE_cli"))
+        .args(["run", "--seconds", "1"])
+        .args(extra)
+        .output()
+        .unwrap();
+    (
+        out.status.code().unwrap(),
+        String::from_utf8(out.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn run_rejects_a_zero_time_step() {
+    let (code, stderr) = run_exit_code(&["--dt-ms", "0"]);
+    assert_eq!(code, i32::from(exit::INVALID_INPUT), "{stderr}");
+    assert!(stderr.contains("run.dt_ms must be > 0"), "{stderr}");
+}
+
+#[test]
+fn run_rejects_a_nan_setpoint() {
+    let (code, stderr) = run_exit_code(&["--setpoint", "nan"]);
+    assert_eq!(code, i32::from(exit::INVALID_INPUT), "{stderr}");
+    assert!(
+        stderr.contains("run.setpoint must be a finite number"),
+        "{stderr}"
+    );
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+use clap::Parser;
+use cli::trace::{read_trace, TraceFormat};
+use cli::verify::{first_mismatch, Mismatch};
+use cli::{exit, Cli, RunArgs};
+use common::scratch;
+
+fn record(path: &Path) {
+    let args = RunArgs::try_parse_from([
+        "reactor-safety-sim",
+        "--scenario",
+        "loss-of-cooling",
+        "--seconds",
+        "60",
+        "--setpoint",
+        "380",
+        "--trip-temp",
+        "390",
+        "--fault",
+        "s2:bias:3",
+        "--kp",
+        "0.03",
+        "--diagnostics",
+        "--decimate",
+        "5",
+    ])
+    .unwrap();
+    cli::run_to_file(&args, path, &AtomicBool::new(false)).unwrap();
+}
+
+fn verify(path: &Path, flags: &[&str]) -> Result<(u8, String), String> {
+    let mut argv = vec!["reactor-safety-sim", "verify", path.to_str().unwrap()];
+    argv.extend_from_slice(flags);
+    let mut out = Vec::new();
+    match cli::execute(&Cli::try_parse_from(argv).unwrap(), &mut out) {
+        Ok(code) => Ok((code, String::from_utf8(out).unwrap())),
+        Err(e) => Err(format!("{e:#}")),
+    }
+}
+
+/// Replace the `"key":value` of the JSONL line `index` (0 = header).
+fn tamper(path: &Path, index: usize, key: &str, value: &str) {
+    let text = fs::read_to_string(path).unwrap();
+    let mut lines: Vec<String> = text.lines().map(str::to_owned).collect();
+    let mut line: serde_json::Value = serde_json::from_str(&lines[index]).unwrap();
+    line.as_object_mut()
+        .unwrap()
+        .insert(key.to_owned(), serde_json::from_str(value).unwrap());
+    lines[index] = serde_json::to_string(&line).unwrap();
+    fs::write(path, lines.join("\n") + "\n").unwrap();
+}
+
+#[test]
+fn fresh_trace_is_reproduced() {
+    let dir = scratch("fresh");
+    let trace = dir.join("trace.jsonl");
+    record(&trace);
+    let (code, out) = verify(&trace, &[]).unwrap();
+    assert_eq!(code, exit::OK, "{out}");
+    assert!(out.starts_with("ok: "), "{out}");
+}
+
+#[test]
+fn tampered_value_is_reported_with_row_and_column() {
+    let dir = scratch("tampered");
+    let trace = dir.join("trace.jsonl");
+    record(&trace);
+    let rows = read_trace(&trace).unwrap();
+    // Line 101 holds data row 100.
+    tamper(&trace, 101, "power", "0.123");
+    let (code, out) = verify(&trace, &[]).unwrap();
+    assert_eq!(code, exit::FAILED_CHECK, "{out}");
+    assert!(
+        out.starts_with(&format!(
+            "MISMATCH at row 100 (t = {} s), column power: recorded 0.123,",
+            rows[100].t_s
+        )),
+        "{out}"
+    );
+}
+
+#[test]
+fn truncated_trace_is_a_row_count_mismatch() {
+    let rows = read_trace(&{
+        let dir = scratch("truncated");
+        let trace = dir.join("trace.jsonl");
+        record(&trace);
+        trace
+    })
+    .unwrap();
+    assert_eq!(first_mismatch(&rows, &rows), None);
+    assert_eq!(
+        first_mismatch(&rows[..10], &rows),
+        Some(Mismatch::RowCount {
+            recorded: 10,
+            rerun: rows.len()
+        })
+    );
+}
+
+#[test]
+fn edited_configuration_fails_the_hash_check() {
+    let dir = scratch("hash");
+    let trace = dir.join("trace.jsonl");
+    record(&trace);
+    let text = fs::read_to_string(&trace).unwrap();
+    let edited = text.replacen("\"trip_temp_c\":390.0", "\"trip_temp_c\":395.0", 1);
+    assert_ne!(edited, text);
+    fs::write(&trace, edited).unwrap();
+    let (code, out) = verify(&trace, &[]).unwrap();
+    assert_eq!(code, exit::FAILED_CHECK, "{out}");
+    assert!(out.contains("configuration hashes to"), "{out}");
+}
+
+#[test]
+fn other_major_version_needs_the_override() {
+    let dir = scratch("version");
+    let trace = dir.join("trace.jsonl");
+    record(&trace);
+    let text = fs::read_to_string(&trace).unwrap();
+    let version = format!("\"crate_version\":\"{}\"", env!("CARGO_PKG_VERSION"));
+    fs::write(
+        &trace,
+        text.replacen(&version, "\"crate_version\":\"99.0.0\"", 1),
+    )
+    .unwrap();
+    let err = verify(&trace, &[]).unwrap_err();
+    assert!(err.contains("--allow-version-mismatch"), "{err}");
+    let (code, out) = verify(&trace, &["--allow-version-mismatch"]).unwrap();
+    assert_eq!(code, exit::OK, "{out}");
+}
+
+#[test]
+fn csv_trace_without_header_is_invalid_input() {
+    let dir = scratch("csv");
+    let trace = dir.join("trace.csv");
+    let mut args = RunArgs::try_parse_from(["reactor-safety-sim", "--seconds", "5"]).unwrap();
+    args.format = TraceFormat::Csv;
+    cli::run_to_file(&args, &trace, &AtomicBool::new(false)).unwrap();
+    let err = verify(&trace, &[]).unwrap_err();
+    assert!(err.contains("no _meta header"), "{err}");
+}
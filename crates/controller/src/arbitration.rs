@@ -0,0 +1,168 @@
+//! Arbitration between the power and coolant control loops.
+//!
+//! Each loop computes its command independently; the `Arbitrator` then applies an ordered rule
+//! set to the pair and records which rule clipped which command, so the trace can explain why the
+//! applied commands differ from what the controllers asked for.
+
+use std::fmt;
+
+use crate::ControlOutput;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArbitrationRule {
+    /// Hard cap on power from the safety layer (runback), when one is active.
+    SafetyPowerCap,
+    /// Power may not increase while the coolant command is saturated high: cooling has no
+    /// headroom left to absorb more heat.
+    NoPowerRaiseWhileCoolantSaturated,
+    /// Coolant may not be commanded below this fraction while power is above zero.
+    CoolantFloor(f64),
+}
+
+impl ArbitrationRule {
+    fn name(&self) -> &'static str {
+        match self {
+            ArbitrationRule::SafetyPowerCap => "safety_power_cap",
+            ArbitrationRule::NoPowerRaiseWhileCoolantSaturated => {
+                "no_power_raise_coolant_saturated"
+            }
+            ArbitrationRule::CoolantFloor(_) => "coolant_floor",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Actuator {
+    Power,
+    Coolant,
+}
+
+/// One rule that changed one command.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Clip {
+    pub rule: ArbitrationRule,
+    pub actuator: Actuator,
+    pub from: f64,
+    pub to: f64,
+}
+
+/// Final actuator commands plus the clips that produced them, in rule order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Arbitration {
+    pub power: f64,
+    pub coolant: f64,
+    pub clips: Vec<Clip>,
+}
+
+/// Compact explanation for trace rows, e.g. `safety_power_cap:power 0.800->0.500`; "-" if no rule
+/// applied.
+impl fmt::Display for Arbitration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.clips.is_empty() {
+            return write!(f, "-");
+        }
+        for (i, c) in self.clips.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            let actuator = match c.actuator {
+                Actuator::Power => "power",
+                Actuator::Coolant => "coolant",
+            };
+            write!(f, "{}:{actuator} {:.3}->{:.3}", c.rule.name(), c.from, c.to)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ArbitratorConfig {
+    /// Rules in priority order; each sees the commands as left by the rules before it.
+    pub rules: Vec<ArbitrationRule>,
+}
+
+impl Default for ArbitratorConfig {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                ArbitrationRule::SafetyPowerCap,
+                ArbitrationRule::NoPowerRaiseWhileCoolantSaturated,
+            ],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Arbitrator {
+    cfg: ArbitratorConfig,
+    /// Final power command of the previous arbitration.
+    prev_power: Option<f64>,
+}
+
+impl Arbitrator {
+    pub fn new(cfg: ArbitratorConfig) -> Self {
+        Self {
+            cfg,
+            prev_power: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.prev_power = None;
+    }
+
+    /// Combine both loops' outputs into the final commands. `power_cap` is the safety runback
+    /// limit, if any.
+    pub fn arbitrate(
+        &mut self,
+        power: &ControlOutput,
+        coolant: &ControlOutput,
+        power_cap: Option<f64>,
+    ) -> Arbitration {
+        let mut result = Arbitration {
+            power: power.command,
+            coolant: coolant.command,
+            clips: Vec::new(),
+        };
+
+        for &rule in &self.cfg.rules {
+            let (actuator, limited) = match rule {
+                ArbitrationRule::SafetyPowerCap => (
+                    Actuator::Power,
+                    power_cap.map_or(result.power, |cap| result.power.min(cap)),
+                ),
+                ArbitrationRule::NoPowerRaiseWhileCoolantSaturated => {
+                    let limited = match self.prev_power {
+                        Some(prev) if coolant.status.saturated_high => result.power.min(prev),
+                        _ => result.power,
+                    };
+                    (Actuator::Power, limited)
+                }
+                ArbitrationRule::CoolantFloor(min) => {
+                    let limited = if result.power > 0.0 {
+                        result.coolant.max(min)
+                    } else {
+                        result.coolant
+                    };
+                    (Actuator::Coolant, limited)
+                }
+            };
+            let current = match actuator {
+                Actuator::Power => &mut result.power,
+                Actuator::Coolant => &mut result.coolant,
+            };
+            if limited != *current {
+                result.clips.push(Clip {
+                    rule,
+                    actuator,
+                    from: *current,
+                    to: limited,
+                });
+                *current = limited;
+            }
+        }
+
+        self.prev_power = Some(result.power);
+        result
+    }
+}
@@ -1,10 +1,99 @@
+use std::fmt;
+
+mod arbitration;
+mod lqr;
+pub mod metrics;
+mod mpc;
+mod setpoint;
+mod smith;
+pub mod tuning;
+
+pub use arbitration::{Actuator, Arbitration, ArbitrationRule, Arbitrator, ArbitratorConfig, Clip};
+pub use lqr::{Lqr, LqrConfig};
+pub use mpc::{Mpc, MpcConfig};
+pub use setpoint::{SetpointProfile, SetpointProfileError};
+pub use smith::SmithPredictor;
+
+/// Version of this crate, as recorded in trace provenance.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Algorithm used to compute the controller output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum PidForm {
+    /// Classic form: output = P + I + D computed from the accumulated integral.
+    #[default]
+    Positional,
+    /// Incremental form: each update computes a change Δu that is accumulated into the output.
+    /// Suited to integrating actuators and inherently bumpless on gain changes.
+    Velocity,
+}
+
+/// How far the controller may trust the measurement it is given (e.g. derived from safety alarms).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Trust {
+    #[default]
+    Full,
+    /// The measurement may be wrong (e.g. redundant sensors disagree).
+    Suspect,
+    /// No usable measurement.
+    None,
+}
+
+/// Controller behavior while the measurement is not fully trusted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DegradedAction {
+    /// Keep controlling on the measurement as usual.
+    Normal,
+    /// Hold the last output.
+    HoldOutput,
+    /// Open loop: output the feedforward (model-based) estimate set with `Pid::set_feedforward`.
+    Feedforward,
+}
+
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
 pub struct PidConfig {
     pub kp: f64,
     pub ki: f64,
     pub kd: f64,
     pub out_min: f64,
     pub out_max: f64,
+    /// Time constant of the first-order filter on the derivative term (seconds). 0 disables filtering.
+    pub d_filter_tau_s: f64,
+    /// Invert the control action (error = measurement - setpoint) instead of using negative gains.
+    pub reverse_acting: bool,
+    pub form: PidForm,
+    /// Velocity form only: keep the accumulated output across `reset` instead of clearing it.
+    pub keep_output_on_reset: bool,
+    /// Integral separation: only integrate while |error| is below this band. None always integrates.
+    pub integral_separation_band: Option<f64>,
+    /// Time constant (seconds) with which `Pid::track` pulls the internal output towards the applied one.
+    pub tracking_tau_s: f64,
+    /// Maximum change of the output per second. None disables rate limiting.
+    pub max_rate_per_s: Option<f64>,
+    /// Maximum increase of the output per second; falls back to `max_rate_per_s` when None.
+    pub max_rate_up_per_s: Option<f64>,
+    /// Maximum decrease of the output per second; falls back to `max_rate_per_s` when None.
+    pub max_rate_down_per_s: Option<f64>,
+    /// Actuator resolution: the output is rounded to the nearest multiple. None disables quantization.
+    /// The integral is pulled towards the quantized output with `tracking_tau_s`, so it settles
+    /// instead of chasing sub-resolution errors; a shorter `tracking_tau_s` widens the error band
+    /// it tolerates.
+    pub output_resolution: Option<f64>,
+    /// Clean slate on big setpoint moves: when the setpoint changes by more than this between
+    /// consecutive updates, the integral is zeroed (positional form) and the derivative history
+    /// cleared. None disables it. Gradual ramps never trigger it.
+    pub reset_integral_on_setpoint_change: Option<f64>,
+    /// Behavior while the measurement trust is `Trust::Suspect`.
+    pub on_suspect_measurement: DegradedAction,
+    /// Behavior while the measurement trust is `Trust::None`.
+    pub on_lost_measurement: DegradedAction,
 }
 
 impl Default for PidConfig {
@@ -15,15 +104,293 @@ impl Default for PidConfig {
             kd: 0.0,
             out_min: 0.0,
             out_max: 1.0,
+            d_filter_tau_s: 0.0,
+            reverse_acting: false,
+            form: PidForm::Positional,
+            keep_output_on_reset: false,
+            integral_separation_band: None,
+            tracking_tau_s: 1.0,
+            max_rate_per_s: None,
+            max_rate_up_per_s: None,
+            max_rate_down_per_s: None,
+            output_resolution: None,
+            reset_integral_on_setpoint_change: None,
+            on_suspect_measurement: DegradedAction::HoldOutput,
+            on_lost_measurement: DegradedAction::HoldOutput,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PidConfigError {
+    NonFinite { field: &'static str },
+    NegativeGain { field: &'static str, value: f64 },
+    InvertedLimits { out_min: f64, out_max: f64 },
+    NegativeFilterTau { value: f64 },
+    NonPositive { field: &'static str, value: f64 },
+}
+
+impl fmt::Display for PidConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PidConfigError::NonFinite { field } => write!(f, "pid.{field} must be a finite number"),
+            PidConfigError::NegativeGain { field, value } => write!(
+                f,
+                "pid.{field} = {value} is negative; use reverse_acting to invert the control action"
+            ),
+            PidConfigError::InvertedLimits { out_min, out_max } => write!(
+                f,
+                "pid.out_min ({out_min}) must be strictly less than pid.out_max ({out_max})"
+            ),
+            PidConfigError::NegativeFilterTau { value } => {
+                write!(f, "pid.d_filter_tau_s = {value} must be >= 0")
+            }
+            PidConfigError::NonPositive { field, value } => {
+                write!(f, "pid.{field} = {value} must be a finite number > 0")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PidConfigError {}
+
+impl PidConfig {
+    /// Check for gain/limit combinations that would make the controller silently misbehave.
+    /// Returns every problem found, not just the first.
+    pub fn validate(&self) -> Result<(), Vec<PidConfigError>> {
+        let mut errors = Vec::new();
+
+        let fields = [
+            ("kp", self.kp),
+            ("ki", self.ki),
+            ("kd", self.kd),
+            ("out_min", self.out_min),
+            ("out_max", self.out_max),
+            ("d_filter_tau_s", self.d_filter_tau_s),
+            ("tracking_tau_s", self.tracking_tau_s),
+        ];
+        for (field, value) in fields {
+            if !value.is_finite() {
+                errors.push(PidConfigError::NonFinite { field });
+            }
+        }
+
+        for (field, value) in [("kp", self.kp), ("ki", self.ki), ("kd", self.kd)] {
+            if value < 0.0 {
+                errors.push(PidConfigError::NegativeGain { field, value });
+            }
+        }
+
+        if self.out_min.is_finite() && self.out_max.is_finite() && self.out_min >= self.out_max {
+            errors.push(PidConfigError::InvertedLimits {
+                out_min: self.out_min,
+                out_max: self.out_max,
+            });
+        }
+
+        if self.d_filter_tau_s < 0.0 {
+            errors.push(PidConfigError::NegativeFilterTau {
+                value: self.d_filter_tau_s,
+            });
+        }
+
+        if self.tracking_tau_s.is_finite() && self.tracking_tau_s <= 0.0 {
+            errors.push(PidConfigError::NonPositive {
+                field: "tracking_tau_s",
+                value: self.tracking_tau_s,
+            });
+        }
+
+        for (field, rate) in [
+            ("max_rate_per_s", self.max_rate_per_s),
+            ("max_rate_up_per_s", self.max_rate_up_per_s),
+            ("max_rate_down_per_s", self.max_rate_down_per_s),
+        ] {
+            if let Some(rate) = rate {
+                if !(rate.is_finite() && rate > 0.0) {
+                    errors.push(PidConfigError::NonPositive { field, value: rate });
+                }
+            }
+        }
+
+        if let Some(band) = self.integral_separation_band {
+            if !(band.is_finite() && band > 0.0) {
+                errors.push(PidConfigError::NonPositive {
+                    field: "integral_separation_band",
+                    value: band,
+                });
+            }
+        }
+
+        if let Some(threshold) = self.reset_integral_on_setpoint_change {
+            if !(threshold.is_finite() && threshold > 0.0) {
+                errors.push(PidConfigError::NonPositive {
+                    field: "reset_integral_on_setpoint_change",
+                    value: threshold,
+                });
+            }
+        }
+
+        if let Some(res) = self.output_resolution {
+            if !(res.is_finite() && res > 0.0) {
+                errors.push(PidConfigError::NonPositive {
+                    field: "output_resolution",
+                    value: res,
+                });
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Breakdown of the most recent controller output, for telemetry and tuning.
+///
+/// In velocity form `p`, `i` and `d` are the per-step increments rather than absolute contributions.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PidTerms {
+    pub p: f64,
+    pub i: f64,
+    pub d: f64,
+    /// Feedforward contribution (see [`Pid::set_feedforward`]).
+    pub ff: f64,
+    /// Sum of all terms before saturation.
+    pub output_presat: f64,
+    /// True when the output limits clipped `output_presat`.
+    pub saturated: bool,
+}
+
+/// Regime the controller was in on the most recent update.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControlStatus {
+    /// The unsaturated output exceeded `out_max`.
+    pub saturated_high: bool,
+    /// The unsaturated output was below `out_min`.
+    pub saturated_low: bool,
+    /// The output change was clipped by `max_rate_per_s`.
+    pub rate_limited: bool,
+    /// The output was set by `Pid::set_manual` rather than computed.
+    pub in_manual: bool,
+    /// The internal state was pulled towards an externally applied output.
+    pub tracking: bool,
+    /// The setpoint or measurement was not finite; the previous output was held.
+    pub input_invalid: bool,
+    /// The time step was zero, negative or not finite; the previous output was held.
+    pub dt_invalid: bool,
+    /// The measurement was not trusted and the configured `DegradedAction` replaced feedback.
+    pub degraded: bool,
+}
+
+impl ControlStatus {
+    /// Compact status code for traces: one letter per active flag
+    /// (H = saturated high, L = saturated low, R = rate limited, M = manual, T = tracking,
+    /// I = invalid input, D = invalid dt, U = untrusted measurement), "-" if none.
+    pub fn code(&self) -> String {
+        let flags = [
+            (self.saturated_high, 'H'),
+            (self.saturated_low, 'L'),
+            (self.rate_limited, 'R'),
+            (self.in_manual, 'M'),
+            (self.tracking, 'T'),
+            (self.input_invalid, 'I'),
+            (self.dt_invalid, 'D'),
+            (self.degraded, 'U'),
+        ];
+        let code: String = flags
+            .iter()
+            .filter(|(on, _)| *on)
+            .map(|(_, c)| *c)
+            .collect();
+        if code.is_empty() {
+            "-".to_string()
+        } else {
+            code
+        }
+    }
+}
+
+/// Everything a controller step consumes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ControlInput {
+    pub setpoint: f64,
+    pub measurement: f64,
+    /// Output bias added before saturation (e.g. a model-based equilibrium estimate).
+    pub feedforward: f64,
+    pub dt_s: f64,
+    /// Output actually applied to the plant after the previous step, if known.
+    pub applied_prev: Option<f64>,
+}
+
+/// Everything a controller step produces.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControlOutput {
+    pub command: f64,
+    pub terms: PidTerms,
+    pub status: ControlStatus,
+}
+
+/// Common interface of the closed-loop controllers so the simulation loop can swap them.
+pub trait Controller {
+    /// Compute the actuator command for this step.
+    fn update(&mut self, setpoint: f64, measurement: f64, dt_s: f64) -> f64;
+    fn reset(&mut self);
+    /// Bumpless restart: the next update with zero error returns `output`.
+    fn reset_to(&mut self, output: f64);
+    /// Keep the internal state consistent with an applied output that differs from the returned one.
+    fn track(&mut self, applied_output: f64, dt_s: f64);
+    /// Term breakdown of the most recent update, for diagnostics.
+    fn terms(&self) -> PidTerms;
+    fn status(&self) -> ControlStatus;
+    /// Tell the controller how far to trust the measurement. Controllers without a degraded mode
+    /// ignore it.
+    fn set_measurement_trust(&mut self, _trust: Trust) {}
+
+    /// Structured step: tracks `applied_prev` when it differs from the previous command, then
+    /// updates. Controllers with their own model-based feedforward ignore `input.feedforward`.
+    fn step(&mut self, input: ControlInput) -> ControlOutput {
+        let mut tracked = false;
+        if let Some(applied) = input.applied_prev {
+            self.track(applied, input.dt_s);
+            tracked = self.status().tracking;
+        }
+        let command = self.update(input.setpoint, input.measurement, input.dt_s);
+        let mut status = self.status();
+        status.tracking |= tracked;
+        ControlOutput {
+            command,
+            terms: self.terms(),
+            status,
         }
     }
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pid {
     cfg: PidConfig,
     integral: f64,
+    /// Amount added to `integral` by the most recent positional update.
+    last_integration: f64,
     prev_error: Option<f64>,
+    /// Setpoint of the most recent valid update.
+    prev_setpoint: Option<f64>,
+    deriv_filtered: f64,
+    prev_deriv: f64,
+    /// Accumulated output of the velocity form (excluding feedforward); None until first update.
+    vel_output: Option<f64>,
+    feedforward: f64,
+    manual_output: Option<f64>,
+    trust: Trust,
+    last_output: Option<f64>,
+    last_terms: PidTerms,
+    status: ControlStatus,
 }
 
 impl Pid {
@@ -31,45 +398,492 @@ impl Pid {
         Self {
             cfg,
             integral: 0.0,
+            last_integration: 0.0,
             prev_error: None,
+            prev_setpoint: None,
+            deriv_filtered: 0.0,
+            prev_deriv: 0.0,
+            vel_output: None,
+            feedforward: 0.0,
+            manual_output: None,
+            trust: Trust::Full,
+            last_output: None,
+            last_terms: PidTerms::default(),
+            status: ControlStatus::default(),
         }
     }
 
+    /// Constant term added to the output on every update (before saturation).
+    pub fn set_feedforward(&mut self, ff: f64) {
+        self.feedforward = ff;
+    }
+
+    pub fn config(&self) -> &PidConfig {
+        &self.cfg
+    }
+
+    /// Replace the configuration without clearing the controller state.
+    /// In velocity form the change is bumpless; in positional form the integral term rescales with `ki`.
+    pub fn set_config(&mut self, cfg: PidConfig) {
+        self.cfg = cfg;
+    }
+
+    /// Bumpless gain change: replace the configuration so that the output does not jump. In
+    /// positional form the integral is preloaded so the new gains reproduce the last output for the
+    /// last error and derivative; from there only the new gains' response to changes of the error
+    /// moves the output. Velocity form is bumpless with `set_config` already. Before the first
+    /// update, or with `ki == 0` in the new configuration, this is `set_config`.
+    pub fn set_config_bumpless(&mut self, cfg: PidConfig) {
+        let old = self.cfg;
+        self.cfg = cfg;
+        let Some(out) = self.last_output else {
+            return;
+        };
+        if old.form != PidForm::Positional || cfg.form != PidForm::Positional || cfg.ki <= 0.0 {
+            return;
+        }
+        let error = self.prev_error.unwrap_or(0.0);
+        let deriv = if old.kd != 0.0 {
+            self.last_terms.d / old.kd
+        } else {
+            0.0
+        };
+        let out = out.clamp(cfg.out_min, cfg.out_max);
+        self.integral = (out - self.feedforward - cfg.kp * error - cfg.kd * deriv) / cfg.ki;
+    }
+
+    /// Current value of the integral accumulator (positional form).
+    pub fn integral(&self) -> f64 {
+        self.integral
+    }
+
+    /// Manual mode: while `Some`, `update` returns this output (clamped to the limits) and the
+    /// controller tracks it so that switching back to automatic with `None` is bumpless.
+    pub fn set_manual(&mut self, output: Option<f64>) {
+        self.manual_output = output.map(|u| u.clamp(self.cfg.out_min, self.cfg.out_max));
+    }
+
+    /// Degraded mode: while the measurement is not fully trusted, `update` applies the configured
+    /// `DegradedAction` instead of feedback and keeps the integral preloaded with the output, so
+    /// returning to `Trust::Full` is bumpless.
+    pub fn set_measurement_trust(&mut self, trust: Trust) {
+        self.trust = trust;
+    }
+
+    /// Status flags of the most recent `update` (and any `track` call after it).
+    pub fn status(&self) -> ControlStatus {
+        self.status
+    }
+
+    /// True when the most recent `update` rejected a non-finite setpoint or measurement.
+    pub fn last_input_invalid(&self) -> bool {
+        self.status.input_invalid
+    }
+
+    /// Term contributions of the most recent `update` call.
+    pub fn last_terms(&self) -> PidTerms {
+        self.last_terms
+    }
+
     pub fn reset(&mut self) {
         self.integral = 0.0;
         self.prev_error = None;
+        self.prev_setpoint = None;
+        self.deriv_filtered = 0.0;
+        self.prev_deriv = 0.0;
+        if !self.cfg.keep_output_on_reset {
+            self.vel_output = None;
+            self.last_output = None;
+        }
+        self.last_terms = PidTerms::default();
+        self.status = ControlStatus::default();
+    }
+
+    /// Bumpless restart: clear the derivative history and preload the integral so that the next
+    /// `update` with zero error returns exactly `output` (clamped to the output limits).
+    /// In positional form this needs `ki > 0`; with `ki == 0` the integral is simply cleared.
+    pub fn reset_to(&mut self, output: f64) {
+        let output = output.clamp(self.cfg.out_min, self.cfg.out_max);
+        self.prev_error = None;
+        self.deriv_filtered = 0.0;
+        self.prev_deriv = 0.0;
+        self.integral = if self.cfg.ki > 0.0 {
+            (output - self.feedforward) / self.cfg.ki
+        } else {
+            0.0
+        };
+        self.vel_output = Some(output - self.feedforward);
+        self.last_output = Some(output);
+        self.last_terms = PidTerms::default();
+        self.status = ControlStatus::default();
+    }
+
+    /// Output tracking (back-calculation): call after `update` when the output actually applied to
+    /// the plant differs from the one the controller returned (external clamp, runback, manual
+    /// override). The integrator is adjusted so the internal output converges to `applied_output`
+    /// with time constant `tracking_tau_s`, which keeps the controller from fighting the limit.
+    /// With a persistent error the converged output sits `ki * error * tracking_tau_s` above the
+    /// applied value, so smaller time constants track more tightly.
+    ///
+    /// Rate limits are applied relative to the tracked output, so an external cut (e.g. SCRAM)
+    /// bypasses the down-rate limit and the resumed output rises from it at the up rate.
+    pub fn track(&mut self, applied_output: f64, dt_s: f64) {
+        if dt_s.is_nan() || dt_s <= 0.0 || !applied_output.is_finite() {
+            return;
+        }
+        let alpha = (dt_s / self.cfg.tracking_tau_s).min(1.0);
+        match self.cfg.form {
+            PidForm::Positional => {
+                if self.cfg.ki > 0.0 {
+                    let mismatch = applied_output - self.last_terms.output_presat;
+                    self.integral += alpha * mismatch / self.cfg.ki;
+                }
+            }
+            PidForm::Velocity => {
+                let current = self.vel_output.unwrap_or(0.0);
+                let target = applied_output - self.feedforward;
+                self.vel_output = Some(current + alpha * (target - current));
+            }
+        }
+        self.last_output = Some(applied_output);
+        self.status.tracking = true;
     }
 
     /// Update PID given setpoint and measurement. Returns a saturated output [out_min, out_max].
+    /// Thin wrapper around `step` with the current feedforward and no applied-output tracking.
     pub fn update(&mut self, setpoint: f64, measurement: f64, dt_s: f64) -> f64 {
-        let error = setpoint - measurement;
+        self.step(ControlInput {
+            setpoint,
+            measurement,
+            feedforward: self.feedforward,
+            dt_s,
+            applied_prev: None,
+        })
+        .command
+    }
 
-        // Integral
-        self.integral += error * dt_s;
+    /// One controller step with all inputs and results in one place.
+    ///
+    /// `input.feedforward` replaces the feedforward set with `set_feedforward`. When
+    /// `input.applied_prev` differs from the previous command the controller first tracks it (see
+    /// `track`), so callers no longer need a separate `track` call after clamping.
+    ///
+    /// A non-finite setpoint or measurement leaves the controller state untouched and returns the
+    /// previous output (or `out_min` if there is none), flagging `ControlStatus::input_invalid`.
+    /// Likewise a `dt_s` that is zero, negative or not finite holds the output and flags
+    /// `ControlStatus::dt_invalid`. Irregular positive time steps are handled exactly.
+    pub fn step(&mut self, input: ControlInput) -> ControlOutput {
+        self.feedforward = input.feedforward;
+        let tracked = match input.applied_prev {
+            Some(applied) if self.last_output.is_some_and(|prev| prev != applied) => {
+                self.track(applied, input.dt_s);
+                self.status.tracking
+            }
+            _ => false,
+        };
+        let command = self.compute(input.setpoint, input.measurement, input.dt_s);
+        self.status.tracking |= tracked;
+        ControlOutput {
+            command,
+            terms: self.last_terms,
+            status: self.status,
+        }
+    }
+
+    fn compute(&mut self, setpoint: f64, measurement: f64, dt_s: f64) -> f64 {
+        let input_invalid = !setpoint.is_finite() || !measurement.is_finite();
+        let dt_invalid = !(dt_s.is_finite() && dt_s > 0.0);
+        let degraded_action = match self.trust {
+            Trust::Full => DegradedAction::Normal,
+            Trust::Suspect => self.cfg.on_suspect_measurement,
+            Trust::None => self.cfg.on_lost_measurement,
+        };
+        if degraded_action != DegradedAction::Normal {
+            let out = match degraded_action {
+                DegradedAction::Feedforward => self.feedforward,
+                _ => self.last_output.unwrap_or(self.cfg.out_min),
+            };
+            let out = self.manual_output.unwrap_or(out);
+            self.reset_to(out);
+            self.status = ControlStatus {
+                in_manual: self.manual_output.is_some(),
+                input_invalid,
+                dt_invalid,
+                degraded: true,
+                ..Default::default()
+            };
+            return self.last_output.unwrap_or(out);
+        }
+        if input_invalid || dt_invalid {
+            return self.hold_output(ControlStatus {
+                input_invalid,
+                dt_invalid,
+                ..Default::default()
+            });
+        }
+
+        if let (Some(threshold), Some(prev)) = (
+            self.cfg.reset_integral_on_setpoint_change,
+            self.prev_setpoint,
+        ) {
+            if (setpoint - prev).abs() > threshold {
+                self.integral = 0.0;
+                self.prev_error = None;
+                self.deriv_filtered = 0.0;
+                self.prev_deriv = 0.0;
+            }
+        }
+        self.prev_setpoint = Some(setpoint);
+
+        let error = if self.cfg.reverse_acting {
+            measurement - setpoint
+        } else {
+            setpoint - measurement
+        };
+
+        let prev_error = self.prev_error;
+        let deriv = self.derivative(error, dt_s);
+
+        let out = match self.cfg.form {
+            PidForm::Positional => self.update_positional(error, deriv, dt_s),
+            PidForm::Velocity => self.update_velocity(error, prev_error, deriv, dt_s),
+        };
+        self.finish(out, dt_s)
+    }
+
+    /// Return the previous output without touching the integral or derivative history.
+    fn hold_output(&mut self, flags: ControlStatus) -> f64 {
+        let out = self
+            .manual_output
+            .or(self.last_output)
+            .unwrap_or(self.cfg.out_min);
+        self.status = ControlStatus {
+            in_manual: self.manual_output.is_some(),
+            ..flags
+        };
+        out
+    }
+
+    /// Apply rate limiting and manual override to the saturated output and record the status.
+    fn finish(&mut self, saturated_out: f64, dt_s: f64) -> f64 {
+        let presat = self.last_terms.output_presat;
+        let mut out = saturated_out;
+
+        if let Some(res) = self.cfg.output_resolution {
+            out = ((out / res).round() * res).clamp(self.cfg.out_min, self.cfg.out_max);
+        }
+        let quantized = out;
+
+        let mut rate_limited = false;
+        if let Some(prev) = self.last_output {
+            let up = self.cfg.max_rate_up_per_s.or(self.cfg.max_rate_per_s);
+            let down = self.cfg.max_rate_down_per_s.or(self.cfg.max_rate_per_s);
+            let hi = up.map_or(f64::INFINITY, |r| prev + r * dt_s);
+            let lo = down.map_or(f64::NEG_INFINITY, |r| prev - r * dt_s);
+            let limited = out.clamp(lo, hi);
+            rate_limited = limited != out;
+            out = limited;
+        }
+
+        if let Some(manual) = self.manual_output {
+            out = manual;
+        }
+
+        #[cfg(feature = "tracing")]
+        let was_saturated = (self.status.saturated_high, self.status.saturated_low);
+        self.status = ControlStatus {
+            saturated_high: presat > self.cfg.out_max,
+            saturated_low: presat < self.cfg.out_min,
+            rate_limited,
+            in_manual: self.manual_output.is_some(),
+            tracking: false,
+            input_invalid: false,
+            dt_invalid: false,
+            degraded: false,
+        };
+        #[cfg(feature = "tracing")]
+        if (self.status.saturated_high, self.status.saturated_low) != was_saturated {
+            tracing::debug!(
+                high = self.status.saturated_high,
+                low = self.status.saturated_low,
+                output_presat = presat,
+                "output saturation changed"
+            );
+        }
+
+        // The returned output differs from what the algorithm computed: keep the internal
+        // state consistent with it so there is no windup and no bump later.
+        if out == quantized && out != saturated_out && !self.status.in_manual {
+            // Quantization only: back-calculate towards the quantized output so the controller
+            // settles on a level instead of cycling between adjacent ones.
+            let alpha = (dt_s / self.cfg.tracking_tau_s).min(1.0);
+            match self.cfg.form {
+                PidForm::Velocity => {
+                    let current = self.vel_output.unwrap_or(0.0);
+                    self.vel_output = Some(current + alpha * (out - saturated_out));
+                }
+                PidForm::Positional if self.cfg.ki > 0.0 => {
+                    self.integral += alpha * (out - saturated_out) / self.cfg.ki;
+                }
+                PidForm::Positional => {}
+            }
+        } else if out != saturated_out {
+            match self.cfg.form {
+                PidForm::Velocity => {
+                    self.vel_output = Some(out - self.feedforward);
+                    self.status.tracking = self.status.in_manual;
+                }
+                // Rate limiting: conditional integration (undo this step's integration) so the
+                // output keeps ramping at the permitted rate without winding up.
+                PidForm::Positional if !self.status.in_manual => {
+                    self.integral -= self.last_integration;
+                }
+                PidForm::Positional => self.track(out, dt_s),
+            }
+        }
 
-        // Derivative
-        let deriv = match self.prev_error {
-            Some(prev) if dt_s > 0.0 => (error - prev) / dt_s,
-            _ => 0.0,
+        self.last_output = Some(out);
+        out
+    }
+
+    /// Derivative of the error (optionally low-pass filtered). Updates the derivative history.
+    /// `dt_s` is known to be positive and finite here.
+    fn derivative(&mut self, error: f64, dt_s: f64) -> f64 {
+        let raw_deriv = match self.prev_error {
+            Some(prev) => (error - prev) / dt_s,
+            None => 0.0,
         };
         self.prev_error = Some(error);
+        if self.cfg.d_filter_tau_s > 0.0 {
+            // Exact discretization of the first-order filter for this step length: stays correct
+            // for irregular dt and degrades to the raw derivative when dt >> tau.
+            let alpha = -(-dt_s / self.cfg.d_filter_tau_s).exp_m1();
+            self.deriv_filtered += alpha * (raw_deriv - self.deriv_filtered);
+            self.deriv_filtered
+        } else {
+            raw_deriv
+        }
+    }
+
+    /// Integral separation: integration is suspended while the error is outside the band.
+    fn integrating(&self, error: f64) -> bool {
+        match self.cfg.integral_separation_band {
+            Some(band) => error.abs() < band,
+            None => true,
+        }
+    }
+
+    fn update_positional(&mut self, error: f64, deriv: f64, dt_s: f64) -> f64 {
+        // Integral
+        let integrating = self.integrating(error);
+        self.last_integration = if integrating { error * dt_s } else { 0.0 };
+        self.integral += self.last_integration;
 
-        let mut out = self.cfg.kp * error + self.cfg.ki * self.integral + self.cfg.kd * deriv;
+        let p = self.cfg.kp * error;
+        let i = self.cfg.ki * self.integral;
+        let d = self.cfg.kd * deriv;
+        let ff = self.feedforward;
+        let presat = p + i + d + ff;
+        let mut out = presat;
 
-        // Saturate output + simple anti-windup by clamping integral if saturated
+        // Saturate output + simple anti-windup by clamping integral if saturated.
+        // A separated (frozen) integral did not wind up this step, so it is left alone.
         if out > self.cfg.out_max {
             out = self.cfg.out_max;
             // prevent runaway integral
-            if error > 0.0 {
+            if integrating && error > 0.0 {
                 self.integral *= 0.98;
             }
         } else if out < self.cfg.out_min {
             out = self.cfg.out_min;
-            if error < 0.0 {
+            if integrating && error < 0.0 {
                 self.integral *= 0.98;
             }
         }
 
+        self.record_terms(p, i, d, ff, presat, out);
+        out
+    }
+
+    fn update_velocity(
+        &mut self,
+        error: f64,
+        prev_error: Option<f64>,
+        deriv: f64,
+        dt_s: f64,
+    ) -> f64 {
+        // A fresh controller starts from zero output and zero error, which makes the first
+        // increment equal the positional output. After a reset that kept the output, the first
+        // proportional increment is zero so the restart is bumpless.
+        let e_prev = match (prev_error, self.vel_output) {
+            (Some(prev), _) => prev,
+            (None, Some(_)) => error,
+            (None, None) => 0.0,
+        };
+        let u_prev = self.vel_output.unwrap_or(0.0);
+
+        let p = self.cfg.kp * (error - e_prev);
+        let i = if self.integrating(error) {
+            self.cfg.ki * error * dt_s
+        } else {
+            0.0
+        };
+        let d = self.cfg.kd * (deriv - self.prev_deriv);
+        self.prev_deriv = deriv;
+
+        let ff = self.feedforward;
+        let presat = u_prev + p + i + d + ff;
+        let out = presat.clamp(self.cfg.out_min, self.cfg.out_max);
+
+        // Accumulating the clamped value is what makes the velocity form immune to windup.
+        self.vel_output = Some(out - ff);
+
+        self.record_terms(p, i, d, ff, presat, out);
         out
     }
+
+    fn record_terms(&mut self, p: f64, i: f64, d: f64, ff: f64, presat: f64, out: f64) {
+        self.last_terms = PidTerms {
+            p,
+            i,
+            d,
+            ff,
+            output_presat: presat,
+            saturated: out != presat,
+        };
+    }
+}
+
+impl Controller for Pid {
+    fn update(&mut self, setpoint: f64, measurement: f64, dt_s: f64) -> f64 {
+        Pid::update(self, setpoint, measurement, dt_s)
+    }
+
+    fn reset(&mut self) {
+        Pid::reset(self)
+    }
+
+    fn reset_to(&mut self, output: f64) {
+        Pid::reset_to(self, output)
+    }
+
+    fn track(&mut self, applied_output: f64, dt_s: f64) {
+        Pid::track(self, applied_output, dt_s)
+    }
+
+    fn terms(&self) -> PidTerms {
+        self.last_terms
+    }
+
+    fn status(&self) -> ControlStatus {
+        self.status
+    }
+
+    fn set_measurement_trust(&mut self, trust: Trust) {
+        Pid::set_measurement_trust(self, trust)
+    }
+
+    fn step(&mut self, input: ControlInput) -> ControlOutput {
+        Pid::step(self, input)
+    }
 }
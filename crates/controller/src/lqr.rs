@@ -0,0 +1,205 @@
+//! Optimal state-feedback (LQR) controller with integral action for the first-order thermal plant.
+//!
+//! Linearizing `PlantState::step` around an operating point gives
+//! `dT/dt = a·(T - T_eq) + b·(u - u_eq)` with `a = -k_cool·coolant/thermal_mass` and
+//! `b = k_power/thermal_mass`. Augmenting the state with the integral of the tracking error and
+//! minimizing `∫ q·e² + qi·(∫e)² + r·Δu² dt` yields a 2x2 Riccati equation that has a closed-form
+//! solution, so no matrix library is needed:
+//!
+//! - `k_i = sqrt(qi / r)`
+//! - `k_p = (a + sqrt(a² + b²·q/r + 2·b·k_i)) / b`
+//!
+//! and the closed-loop characteristic polynomial is `s² + (b·k_p - a)·s + b·k_i`.
+//!
+//! Integrating the raw setpoint error would add a zero that makes every setpoint step overshoot.
+//! Instead the integrator sees the deviation from a reference model — the setpoint filtered
+//! through the nominal proportional loop `-(b·k_p - a)` — so with an exact model the integral
+//! stays at zero and the response is first order; it only acts on model mismatch and load
+//! disturbances, with the poles above.
+
+use crate::{ControlStatus, Controller, PidTerms};
+use sim::PlantParams;
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LqrConfig {
+    /// Weight on the temperature error (per °C²).
+    pub q: f64,
+    /// Weight on the integral of the temperature error.
+    pub qi: f64,
+    /// Weight on the power deviation from the equilibrium power.
+    pub r: f64,
+    pub out_min: f64,
+    pub out_max: f64,
+}
+
+impl Default for LqrConfig {
+    fn default() -> Self {
+        Self {
+            q: 1.0,
+            qi: 0.001,
+            r: 1000.0,
+            out_min: 0.0,
+            out_max: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lqr {
+    cfg: LqrConfig,
+    plant: PlantParams,
+    coolant: f64,
+    /// Linearized plant: dT/dt = a·T + b·u (deviation variables).
+    a: f64,
+    b: f64,
+    /// State-feedback gain on the temperature error.
+    k_p: f64,
+    /// Gain on the integral of the temperature error.
+    k_i: f64,
+    integral: f64,
+    /// Reference model output; `None` until the first update (or after a reset).
+    reference: Option<f64>,
+    last_terms: PidTerms,
+    status: ControlStatus,
+}
+
+impl Lqr {
+    /// Compute the LQR gains for the plant linearized at the given coolant fraction.
+    pub fn new(plant: &PlantParams, coolant: f64, cfg: LqrConfig) -> Self {
+        let a = -plant.k_cool * coolant / plant.thermal_mass;
+        let b = plant.k_power / plant.thermal_mass;
+        let k_i = (cfg.qi / cfg.r).sqrt();
+        let k_p = (a + (a * a + b * b * cfg.q / cfg.r + 2.0 * b * k_i).sqrt()) / b;
+        Self {
+            cfg,
+            plant: *plant,
+            coolant,
+            a,
+            b,
+            k_p,
+            k_i,
+            integral: 0.0,
+            reference: None,
+            last_terms: PidTerms::default(),
+            status: ControlStatus::default(),
+        }
+    }
+
+    /// (state-feedback gain, integral gain)
+    pub fn gains(&self) -> (f64, f64) {
+        (self.k_p, self.k_i)
+    }
+
+    /// Closed-loop poles as (re, im) pairs, roots of `s² + (b·k_p - a)·s + b·k_i`.
+    pub fn closed_loop_poles(&self) -> [(f64, f64); 2] {
+        let c1 = self.b * self.k_p - self.a;
+        let c0 = self.b * self.k_i;
+        let disc = c1 * c1 - 4.0 * c0;
+        if disc >= 0.0 {
+            let sq = disc.sqrt();
+            [((-c1 + sq) / 2.0, 0.0), ((-c1 - sq) / 2.0, 0.0)]
+        } else {
+            let im = (-disc).sqrt() / 2.0;
+            [(-c1 / 2.0, im), (-c1 / 2.0, -im)]
+        }
+    }
+
+    /// Equilibrium power that holds `setpoint` with the design coolant fraction.
+    fn feedforward(&self, setpoint: f64) -> f64 {
+        self.plant.k_cool * self.coolant * (setpoint - self.plant.ambient_c) / self.plant.k_power
+    }
+}
+
+impl Controller for Lqr {
+    fn update(&mut self, setpoint: f64, measurement: f64, dt_s: f64) -> f64 {
+        let inputs_ok = setpoint.is_finite() && measurement.is_finite();
+        let dt_ok = dt_s.is_finite() && dt_s > 0.0;
+        if !(inputs_ok && dt_ok) {
+            self.status = ControlStatus {
+                input_invalid: !inputs_ok,
+                dt_invalid: !dt_ok,
+                ..Default::default()
+            };
+            return self
+                .last_terms
+                .output_presat
+                .clamp(self.cfg.out_min, self.cfg.out_max);
+        }
+
+        let error = measurement - setpoint;
+        let ff = self.feedforward(setpoint);
+        let p = -self.k_p * error;
+        let lambda = self.b * self.k_p - self.a;
+        let reference = match self.reference {
+            Some(r) => r + (setpoint - r) * -(-lambda * dt_s).exp_m1(),
+            None => measurement,
+        };
+        let candidate = self.integral + (measurement - reference) * dt_s;
+        let presat = ff + p - self.k_i * candidate;
+        let out = presat.clamp(self.cfg.out_min, self.cfg.out_max);
+
+        // Conditional integration: do not integrate further into a saturated output.
+        let pushing_high = presat > self.cfg.out_max && error < 0.0;
+        let pushing_low = presat < self.cfg.out_min && error > 0.0;
+        if !(pushing_high || pushing_low) {
+            self.integral = candidate;
+        }
+        // The nominal loop cannot follow the reference while saturated; resynchronize it.
+        self.reference = Some(if out != presat {
+            measurement
+        } else {
+            reference
+        });
+
+        self.last_terms = PidTerms {
+            p,
+            i: -self.k_i * self.integral,
+            d: 0.0,
+            ff,
+            output_presat: presat,
+            saturated: out != presat,
+        };
+        self.status = ControlStatus {
+            saturated_high: presat > self.cfg.out_max,
+            saturated_low: presat < self.cfg.out_min,
+            ..Default::default()
+        };
+        out
+    }
+
+    fn reset(&mut self) {
+        self.integral = 0.0;
+        self.reference = None;
+        self.last_terms = PidTerms::default();
+        self.status = ControlStatus::default();
+    }
+
+    fn reset_to(&mut self, output: f64) {
+        let output = output.clamp(self.cfg.out_min, self.cfg.out_max);
+        if self.k_i > 0.0 {
+            self.integral = (self.last_terms.ff - output) / self.k_i;
+        }
+        self.reference = None;
+        self.status = ControlStatus::default();
+    }
+
+    fn track(&mut self, applied_output: f64, _dt_s: f64) {
+        let t = self.last_terms;
+        let returned = t.output_presat.clamp(self.cfg.out_min, self.cfg.out_max);
+        if self.k_i > 0.0 && applied_output.is_finite() && applied_output != returned {
+            self.integral = (t.ff + t.p - applied_output) / self.k_i;
+            self.reference = None;
+            self.status.tracking = true;
+        }
+    }
+
+    fn terms(&self) -> PidTerms {
+        self.last_terms
+    }
+
+    fn status(&self) -> ControlStatus {
+        self.status
+    }
+}
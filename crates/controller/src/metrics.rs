@@ -0,0 +1,123 @@
+//! Setpoint-tracking figures of a run: overshoot, settling time, steady-state error, integral
+//! absolute error and time spent at the output limits.
+//!
+//! Conventions:
+//! - The step is from the first temperature to the final setpoint (the setpoint of the last
+//!   point); percentages and the settling band are relative to its size.
+//! - Settling is to 2 % of the step and counts from the first point.
+//! - The error is `setpoint - temperature`, so a run that stays below the setpoint has a positive
+//!   steady-state error.
+//! - Points with a non-finite temperature or setpoint are left out.
+
+/// One step of a run, as far as the metrics are concerned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ResponsePoint {
+    pub t: f64,
+    pub temp: f64,
+    pub setpoint: f64,
+    /// Controller output clipped at a limit on this step
+    pub saturated: bool,
+}
+
+impl ResponsePoint {
+    fn error(&self) -> f64 {
+        self.setpoint - self.temp
+    }
+}
+
+/// Settling band, as a fraction of the step
+pub const SETTLING_BAND: f64 = 0.02;
+
+/// Trailing fraction of the run the steady-state error averages over
+pub const STEADY_STATE_FRACTION: f64 = 0.1;
+
+/// All the metrics of one run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControlMetrics {
+    /// Largest excursion above the setpoint (0 without one)
+    pub overshoot_c: f64,
+    /// The overshoot as a percentage of the step; None without a step
+    pub overshoot_pct: Option<f64>,
+    /// Time from the first point until the temperature stays within the band; None without a
+    /// step or when the run ends outside the band
+    pub settling_time_s: Option<f64>,
+    pub steady_state_error_c: f64,
+    /// Integral of |setpoint - temperature| over the run (°C·s)
+    pub iae: f64,
+    pub saturated_s: f64,
+}
+
+impl ControlMetrics {
+    /// The metrics of `points` (in time order); None with fewer than two usable points.
+    pub fn of(points: &[ResponsePoint]) -> Option<Self> {
+        let points: Vec<ResponsePoint> = points
+            .iter()
+            .filter(|p| p.temp.is_finite() && p.setpoint.is_finite())
+            .copied()
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+        let (overshoot_c, overshoot_pct) = overshoot(&points);
+        Some(Self {
+            overshoot_c,
+            overshoot_pct,
+            settling_time_s: settling_time(&points, SETTLING_BAND),
+            steady_state_error_c: steady_state_error(&points, STEADY_STATE_FRACTION),
+            iae: integral_absolute_error(&points),
+            saturated_s: time_saturated(&points),
+        })
+    }
+}
+
+/// Size of the step, final setpoint minus first temperature; None when there is none.
+fn step(points: &[ResponsePoint]) -> Option<f64> {
+    let (first, last) = (points.first()?, points.last()?);
+    let step = last.setpoint - first.temp;
+    (step != 0.0).then_some(step)
+}
+
+/// Largest excursion above the setpoint (°C, at least 0), and as a percentage of the step.
+pub fn overshoot(points: &[ResponsePoint]) -> (f64, Option<f64>) {
+    let above = points.iter().map(|p| -p.error()).fold(0.0_f64, f64::max);
+    (above, step(points).map(|step| 100.0 * above / step.abs()))
+}
+
+/// Time from the first point after which the temperature stays within `band` (a fraction of the
+/// step) of the setpoint.
+pub fn settling_time(points: &[ResponsePoint], band: f64) -> Option<f64> {
+    let tolerance = band * step(points)?.abs();
+    let t0 = points.first()?.t;
+    match points.iter().rposition(|p| p.error().abs() > tolerance) {
+        None => Some(0.0),
+        Some(last_out) => points.get(last_out + 1).map(|p| p.t - t0),
+    }
+}
+
+/// Mean error over the trailing `fraction` of the run's duration.
+pub fn steady_state_error(points: &[ResponsePoint], fraction: f64) -> f64 {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return f64::NAN;
+    };
+    let from = last.t - fraction * (last.t - first.t);
+    let tail = &points[points.partition_point(|p| p.t < from)..];
+    tail.iter().map(ResponsePoint::error).sum::<f64>() / tail.len() as f64
+}
+
+/// Integral of the absolute error over time, by the trapezoidal rule (°C·s).
+pub fn integral_absolute_error(points: &[ResponsePoint]) -> f64 {
+    points
+        .windows(2)
+        .map(|w| 0.5 * (w[0].error().abs() + w[1].error().abs()) * (w[1].t - w[0].t))
+        .sum()
+}
+
+/// Total time over which the output was saturated, each point counting until the next.
+pub fn time_saturated(points: &[ResponsePoint]) -> f64 {
+    points
+        .windows(2)
+        .filter(|w| w[0].saturated)
+        .map(|w| w[1].t - w[0].t)
+        .sum()
+}
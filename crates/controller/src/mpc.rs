@@ -0,0 +1,199 @@
+//! One-step-ahead model predictive controller for the scalar thermal plant.
+//!
+//! Each update predicts the temperature over the horizon holding the candidate power command `u`
+//! constant and picks the `u` minimizing a quadratic cost, subject to the output range, the rate
+//! limit and an optional temperature ceiling. The plant is scalar and linear in `u`, so every
+//! predicted temperature is affine in `u`: the unconstrained optimum and the ceiling both have
+//! closed forms and the constrained optimum is the optimum clamped to the feasible interval.
+//! An internal model runs alongside the plant; the measured-minus-model offset is added to the
+//! whole predicted trajectory, which removes steady-state offset under model mismatch.
+
+use crate::{ControlStatus, Controller, PidTerms};
+use sim::PlantParams;
+
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MpcConfig {
+    /// Prediction horizon (s).
+    pub horizon_s: f64,
+    /// Number of prediction steps across the horizon.
+    pub horizon_steps: usize,
+    /// Weight on the squared temperature error over the horizon.
+    pub q: f64,
+    /// Weight on the squared change of the command.
+    pub r_move: f64,
+    pub out_min: f64,
+    pub out_max: f64,
+    /// Maximum change of the command per second; `None` disables the rate constraint.
+    pub max_rate_per_s: Option<f64>,
+    /// Predicted temperature must stay at or below this value (°C); `None` disables the constraint.
+    pub temp_limit_c: Option<f64>,
+    /// Scales `k_power` in the prediction model, to study robustness to model mismatch.
+    pub model_mismatch: f64,
+}
+
+impl Default for MpcConfig {
+    fn default() -> Self {
+        Self {
+            horizon_s: 30.0,
+            horizon_steps: 30,
+            q: 1.0,
+            r_move: 10.0,
+            out_min: 0.0,
+            out_max: 1.0,
+            max_rate_per_s: None,
+            temp_limit_c: None,
+            model_mismatch: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mpc {
+    cfg: MpcConfig,
+    plant: PlantParams,
+    coolant: f64,
+    last_output: Option<f64>,
+    /// Internal model temperature, driven by the applied commands.
+    model_temp: Option<f64>,
+    last_terms: PidTerms,
+    status: ControlStatus,
+}
+
+impl Mpc {
+    pub fn new(plant: &PlantParams, coolant: f64, cfg: MpcConfig) -> Self {
+        Self {
+            cfg,
+            plant: PlantParams {
+                k_power: plant.k_power * cfg.model_mismatch,
+                ..*plant
+            },
+            coolant,
+            last_output: None,
+            model_temp: None,
+            last_terms: PidTerms::default(),
+            status: ControlStatus::default(),
+        }
+    }
+
+    /// Update the coolant fraction used for prediction.
+    pub fn set_coolant(&mut self, coolant: f64) {
+        self.coolant = coolant;
+    }
+
+    fn rate(&self, temp_c: f64, power: f64) -> f64 {
+        let p = &self.plant;
+        (p.k_power * power - p.k_cool * self.coolant * (temp_c - p.ambient_c)) / p.thermal_mass
+    }
+
+    /// Predicted temperatures over the horizon as `alpha[i] + beta[i]·u`, starting from the model
+    /// temperature.
+    fn predict_affine(&self, model_c: f64) -> Vec<(f64, f64)> {
+        let steps = self.cfg.horizon_steps.max(1);
+        let h = self.cfg.horizon_s / steps as f64;
+        let (mut t0, mut t1) = (model_c, model_c);
+        (0..steps)
+            .map(|_| {
+                t0 += self.rate(t0, 0.0) * h;
+                t1 += self.rate(t1, 1.0) * h;
+                (t0, t1 - t0)
+            })
+            .collect()
+    }
+}
+
+impl Controller for Mpc {
+    fn update(&mut self, setpoint: f64, measurement: f64, dt_s: f64) -> f64 {
+        let inputs_ok = setpoint.is_finite() && measurement.is_finite();
+        let dt_ok = dt_s.is_finite() && dt_s > 0.0;
+        if !(inputs_ok && dt_ok) {
+            self.status = ControlStatus {
+                input_invalid: !inputs_ok,
+                dt_invalid: !dt_ok,
+                ..Default::default()
+            };
+            return self.last_output.unwrap_or(self.cfg.out_min);
+        }
+
+        let model_c = self.model_temp.unwrap_or(measurement);
+        let offset = measurement - model_c;
+
+        let u_prev = self.last_output.unwrap_or(self.cfg.out_min);
+        let (lo, hi) = match (self.cfg.max_rate_per_s, self.last_output) {
+            (Some(rate), Some(prev)) => (
+                (prev - rate * dt_s).max(self.cfg.out_min),
+                (prev + rate * dt_s).min(self.cfg.out_max),
+            ),
+            _ => (self.cfg.out_min, self.cfg.out_max),
+        };
+
+        let steps = self.cfg.horizon_steps.max(1);
+        let h = self.cfg.horizon_s / steps as f64;
+        let pred = self.predict_affine(model_c);
+
+        // J(u) = r·(u - u_prev)² + Σ q·h·(alpha + beta·u + offset - sp)²
+        let mut num = self.cfg.r_move * u_prev;
+        let mut den = self.cfg.r_move;
+        for &(alpha, beta) in &pred {
+            num -= self.cfg.q * h * beta * (alpha + offset - setpoint);
+            den += self.cfg.q * h * beta * beta;
+        }
+        let unconstrained = if den > 0.0 { num / den } else { u_prev };
+
+        // Highest command keeping every predicted temperature at or below the ceiling.
+        let ceiling = self.cfg.temp_limit_c.map_or(f64::INFINITY, |lim| {
+            pred.iter()
+                .filter(|(_, beta)| *beta > 0.0)
+                .map(|(alpha, beta)| (lim - offset - alpha) / beta)
+                .fold(f64::INFINITY, f64::min)
+        });
+        // If even the lowest reachable command violates the ceiling it still minimizes the violation.
+        let upper = hi.min(ceiling).max(lo);
+        let out = unconstrained.clamp(lo, upper);
+
+        self.model_temp = Some(model_c + self.rate(model_c, out) * dt_s);
+        self.last_terms = PidTerms {
+            output_presat: out,
+            ..Default::default()
+        };
+        self.status = ControlStatus {
+            saturated_high: out >= self.cfg.out_max,
+            saturated_low: out <= self.cfg.out_min,
+            rate_limited: (out == lo && lo > self.cfg.out_min)
+                || (out == hi && hi < self.cfg.out_max),
+            ..Default::default()
+        };
+        self.last_output = Some(out);
+        out
+    }
+
+    fn reset(&mut self) {
+        self.last_output = None;
+        self.model_temp = None;
+        self.last_terms = PidTerms::default();
+        self.status = ControlStatus::default();
+    }
+
+    fn reset_to(&mut self, output: f64) {
+        self.last_output = Some(output.clamp(self.cfg.out_min, self.cfg.out_max));
+        self.model_temp = None;
+        self.status = ControlStatus::default();
+    }
+
+    fn track(&mut self, applied_output: f64, _dt_s: f64) {
+        if applied_output.is_finite() && self.last_output != Some(applied_output) {
+            self.last_output = Some(applied_output);
+            self.model_temp = None;
+            self.status.tracking = true;
+        }
+    }
+
+    fn terms(&self) -> PidTerms {
+        self.last_terms
+    }
+
+    fn status(&self) -> ControlStatus {
+        self.status
+    }
+}
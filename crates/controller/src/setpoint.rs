@@ -0,0 +1,110 @@
+//! Time-varying setpoint built from (time, value) points.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Piecewise-linear setpoint trajectory through (t_s, value) points.
+///
+/// Between points the value is interpolated linearly, so a hold is two points with the same
+/// value and a step is two points at the same time (the later one wins at that instant). Before
+/// the first point and after the last one the value is held.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SetpointProfile {
+    points: Vec<(f64, f64)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SetpointProfileError {
+    Empty,
+    NonFinite { index: usize },
+    TimeNotIncreasing { index: usize },
+    Parse { item: String },
+}
+
+impl fmt::Display for SetpointProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetpointProfileError::Empty => write!(f, "setpoint profile needs at least one point"),
+            SetpointProfileError::NonFinite { index } => {
+                write!(f, "setpoint profile point {index} is not finite")
+            }
+            SetpointProfileError::TimeNotIncreasing { index } => write!(
+                f,
+                "setpoint profile point {index} is earlier than the point before it"
+            ),
+            SetpointProfileError::Parse { item } => {
+                write!(
+                    f,
+                    "invalid setpoint profile point '{item}', expected <t_s>:<value>"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SetpointProfileError {}
+
+impl SetpointProfile {
+    /// Points must be finite with non-decreasing times.
+    pub fn new(points: Vec<(f64, f64)>) -> Result<Self, SetpointProfileError> {
+        if points.is_empty() {
+            return Err(SetpointProfileError::Empty);
+        }
+        for (i, &(t, v)) in points.iter().enumerate() {
+            if !(t.is_finite() && v.is_finite()) {
+                return Err(SetpointProfileError::NonFinite { index: i });
+            }
+            if i > 0 && t < points[i - 1].0 {
+                return Err(SetpointProfileError::TimeNotIncreasing { index: i });
+            }
+        }
+        Ok(Self { points })
+    }
+
+    pub fn constant(value: f64) -> Self {
+        Self {
+            points: vec![(0.0, value)],
+        }
+    }
+
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points
+    }
+
+    /// Setpoint at time `t_s`.
+    pub fn at(&self, t_s: f64) -> f64 {
+        // Index of the first point strictly after t_s.
+        let next = self.points.partition_point(|&(t, _)| t <= t_s);
+        if next == 0 {
+            return self.points[0].1;
+        }
+        let (t0, v0) = self.points[next - 1];
+        match self.points.get(next) {
+            Some(&(t1, v1)) => v0 + (v1 - v0) * (t_s - t0) / (t1 - t0),
+            None => v0,
+        }
+    }
+}
+
+/// Parses `t:value` pairs separated by commas, e.g. `0:300,30:300,90:380`.
+impl FromStr for SetpointProfile {
+    type Err = SetpointProfileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let points = s
+            .split(',')
+            .map(str::trim)
+            .filter(|item| !item.is_empty())
+            .map(|item| {
+                let parse_err = || SetpointProfileError::Parse {
+                    item: item.to_string(),
+                };
+                let (t, v) = item.split_once(':').ok_or_else(parse_err)?;
+                let t = t.trim().parse().map_err(|_| parse_err())?;
+                let v = v.trim().parse().map_err(|_| parse_err())?;
+                Ok((t, v))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(points)
+    }
+}
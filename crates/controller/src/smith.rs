@@ -0,0 +1,133 @@
+//! Smith predictor: dead-time compensation around a PID.
+//!
+//! An internal copy of the plant model is driven by the applied commands. The PID is fed the
+//! undelayed model temperature corrected by the difference between the measurement and the
+//! model temperature delayed by the known transport delay, so with an exact model it acts as if
+//! the delay did not exist, while model mismatch and disturbances still reach it (late).
+
+use std::collections::VecDeque;
+
+use crate::{ControlStatus, Controller, Pid, PidTerms, Trust};
+use sim::PlantParams;
+
+#[derive(Clone, Debug)]
+pub struct SmithPredictor {
+    pid: Pid,
+    plant: PlantParams,
+    coolant: f64,
+    delay_s: f64,
+    /// Undelayed model temperature; `None` until initialized from the first measurement.
+    model_temp: Option<f64>,
+    /// Model temperature history as (time, temperature), covering at least `delay_s`.
+    history: VecDeque<(f64, f64)>,
+    time_s: f64,
+    /// Command applied since the previous update; drives the model.
+    applied: Option<f64>,
+}
+
+impl SmithPredictor {
+    pub fn new(pid: Pid, plant: &PlantParams, coolant: f64, delay_s: f64) -> Self {
+        Self {
+            pid,
+            plant: *plant,
+            coolant,
+            delay_s: delay_s.max(0.0),
+            model_temp: None,
+            history: VecDeque::new(),
+            time_s: 0.0,
+            applied: None,
+        }
+    }
+
+    pub fn pid(&self) -> &Pid {
+        &self.pid
+    }
+
+    pub fn pid_mut(&mut self) -> &mut Pid {
+        &mut self.pid
+    }
+
+    /// Model temperature `delay_s` ago (the oldest known value during start-up).
+    fn delayed_model(&self) -> f64 {
+        let target = self.time_s - self.delay_s;
+        self.history
+            .iter()
+            .rev()
+            .find(|(t, _)| *t <= target + 1e-9)
+            .or(self.history.front())
+            .map_or(0.0, |&(_, y)| y)
+    }
+
+    fn clear_model(&mut self) {
+        self.model_temp = None;
+        self.history.clear();
+        self.time_s = 0.0;
+    }
+}
+
+impl Controller for SmithPredictor {
+    fn update(&mut self, setpoint: f64, measurement: f64, dt_s: f64) -> f64 {
+        if !(measurement.is_finite() && dt_s.is_finite() && dt_s > 0.0) {
+            // Let the PID report and hold; the model is not advanced.
+            return self.pid.update(setpoint, measurement, dt_s);
+        }
+
+        let p = &self.plant;
+        let model = match (self.model_temp, self.applied) {
+            (Some(y), Some(u)) => {
+                let dydt =
+                    (p.k_power * u - p.k_cool * self.coolant * (y - p.ambient_c)) / p.thermal_mass;
+                self.time_s += dt_s;
+                y + dydt * dt_s
+            }
+            (Some(y), None) => {
+                self.time_s += dt_s;
+                y
+            }
+            (None, _) => measurement,
+        };
+        self.model_temp = Some(model);
+        self.history.push_back((self.time_s, model));
+        // Keep one sample older than the delay so the lookup always has a value at or before it.
+        while self.history.len() > 1 && self.history[1].0 <= self.time_s - self.delay_s {
+            self.history.pop_front();
+        }
+
+        let feedback = model + (measurement - self.delayed_model());
+        let out = self.pid.update(setpoint, feedback, dt_s);
+        self.applied = Some(out);
+        out
+    }
+
+    fn reset(&mut self) {
+        self.pid.reset();
+        self.clear_model();
+        self.applied = None;
+    }
+
+    fn reset_to(&mut self, output: f64) {
+        self.pid.reset_to(output);
+        self.clear_model();
+        let cfg = self.pid.config();
+        self.applied = Some(output.clamp(cfg.out_min, cfg.out_max));
+    }
+
+    fn track(&mut self, applied_output: f64, dt_s: f64) {
+        if applied_output.is_finite() && self.applied != Some(applied_output) {
+            self.pid.track(applied_output, dt_s);
+            self.applied = Some(applied_output);
+        }
+    }
+
+    fn terms(&self) -> PidTerms {
+        self.pid.last_terms()
+    }
+
+    fn status(&self) -> ControlStatus {
+        self.pid.status()
+    }
+
+    fn set_measurement_trust(&mut self, trust: Trust) {
+        self.pid.set_measurement_trust(trust)
+    }
+}
@@ -0,0 +1,32 @@
+//! Analytic PI gain suggestions from the plant parameters.
+//!
+//! Assumptions:
+//! - The plant is the first-order model of `PlantState::step`,
+//!   `M·dT/dt = k_power·u - k_cool·c·(T - ambient)`, linearized at the coolant fraction `c`: gain
+//!   `k_power/(k_cool·c)` and time constant `tau = M/(k_cool·c)`. The model is linear in `T`, so
+//!   the operating temperature only sets the equilibrium power (supplied by the integral term),
+//!   not the gains.
+//! - The coolant stays at `c`; with more or less coolant the response is correspondingly slower or
+//!   faster than requested.
+//! - The output does not saturate during the transient. Saturation lengthens settling.
+//!
+//! The PI zero is placed on the plant pole (`kp/ki = tau`), which leaves a first-order closed
+//! loop with pole `kp·k_power/M`; its 2 % settling time is `4·M/(kp·k_power)`.
+
+use crate::PidConfig;
+use sim::PlantParams;
+
+/// Suggest PI gains giving a first-order, overshoot-free setpoint response that settles to 2 %
+/// in `desired_settling_s`, for the plant linearized at the coolant fraction `coolant`.
+pub fn suggest_gains(plant: &PlantParams, coolant: f64, desired_settling_s: f64) -> PidConfig {
+    let tau = plant.thermal_mass / (plant.k_cool * coolant);
+    let b = plant.k_power / plant.thermal_mass;
+    let kp = 4.0 / (b * desired_settling_s);
+    let ki = kp / tau;
+    PidConfig {
+        kp,
+        ki,
+        kd: 0.0,
+        ..Default::default()
+    }
+}
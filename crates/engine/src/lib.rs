@@ -0,0 +1,316 @@
+//! The closed loop of one run, stepped the same way by every front end: the CLI's `run` and the
+//! GUI's live mode drive a `Simulation`, so a run with the same configuration, scenario and seed
+//! produces the same samples bit for bit in both.
+//!
+//! One step:
+//! 1. scenario events due by now, and fault schedules that switch at this time;
+//! 2. a reading from each of the three sensors;
+//! 3. the safety logic on the readings (a new SCRAM restarts the controller);
+//! 4. after a SCRAM no power, else the controller on the average of the finite readings, its
+//!    command clamped to 0..=1;
+//! 5. the operator's coolant, if any, over the scenario's;
+//! 6. the plant over `dt_s`.
+
+use controller::{ControlInput, ControlOutput, Controller, Pid, SetpointProfile, Trust};
+use safety::{SafetyConfig, SafetyDecision, SafetyState, TripRecord};
+use sim::{EventAction, PlantParams, PlantState, ScenarioSpec, Sensor, SensorFault};
+
+/// Version of this crate, as recorded in trace provenance.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Something a step did besides moving the plant on, for logs and event lists.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StepEvent {
+    /// A scenario event came due and was applied
+    Scenario(EventAction),
+    /// A sensor's fault schedule switched it to `fault` (0..3 for s1..s3)
+    Fault { sensor: usize, fault: SensorFault },
+    /// The disagreement alarm was raised (true) or cleared
+    Alarm(bool),
+    /// The safety logic disqualified a sensor (false) or took it back (true)
+    Valid { sensor: usize, valid: bool },
+    /// A SCRAM latched, by the safety logic or a scenario event
+    Trip,
+}
+
+/// What one step produced.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    /// Time at the start of the step
+    pub t_s: f64,
+    pub setpoint_c: f64,
+    /// Plant temperature at the end of the step
+    pub true_temp_c: f64,
+    /// Readings of s1..s3 (NaN for a dropped one)
+    pub readings: [f64; 3],
+    /// Power applied over the step
+    pub power: f64,
+    pub coolant: f64,
+    pub scram: bool,
+    /// The latched trip, from the step that tripped on
+    pub trip: Option<TripRecord>,
+    pub alarm: bool,
+    /// Sensors the safety logic found valid
+    pub valid: [bool; 3],
+    /// The last controller output (the default after a SCRAM)
+    pub control: ControlOutput,
+    pub events: Vec<StepEvent>,
+}
+
+/// A run between two steps: plant, sensors, safety logic, controller and scenario, and where the
+/// run is in the scenario.
+///
+/// The fields are public so front ends can edit a run between steps (operator actions, new
+/// gains) and save and restore it (CLI checkpoints).
+#[derive(Clone, Debug)]
+pub struct Simulation<C = Pid> {
+    pub plant: PlantParams,
+    pub state: PlantState,
+    pub sensors: [Sensor; 3],
+    pub safety_cfg: SafetyConfig,
+    pub safety: SafetyState,
+    pub controller: C,
+    /// Output of the last controller step
+    pub ctrl_out: ControlOutput,
+    /// Power applied after the previous controller step
+    pub applied_prev: Option<f64>,
+    /// Preload the pre-trip power into the controller on SCRAM (otherwise zero), so a restart is
+    /// bumpless
+    pub preload_pre_trip: bool,
+    pub setpoint: SetpointProfile,
+    /// Setpoint set by the last scenario event that set one; wins over `setpoint`
+    pub setpoint_override: Option<f64>,
+    /// Operator's coolant fraction, over the scenario's (None: the scenario's)
+    pub manual_coolant: Option<f64>,
+    pub dt_s: f64,
+    /// Steps taken; the next one starts at `step * dt_s`
+    pub step: u64,
+    /// Scenario events (in time order) already applied
+    pub next_event: usize,
+    /// Faults the scenario's schedules last set. A schedule only sets its sensor's fault when it
+    /// switches, so a fault set from outside stays until the schedule moves on.
+    pub scheduled_faults: [SensorFault; 3],
+    pub alarm: bool,
+    pub valid: [bool; 3],
+    // The scenario, events sorted by time
+    scenario: ScenarioSpec,
+    // The run as its first step found it, for `reset`
+    start: Option<Box<Simulation<C>>>,
+}
+
+impl<C: Controller + Clone> Simulation<C> {
+    /// A run from t = 0 in the scenario's initial conditions, with the setpoint following
+    /// `setpoint` until a scenario event sets another.
+    pub fn new(
+        scenario: ScenarioSpec,
+        plant: PlantParams,
+        sensors: [Sensor; 3],
+        safety_cfg: SafetyConfig,
+        controller: C,
+        setpoint: SetpointProfile,
+        dt_s: f64,
+    ) -> Self {
+        let mut sim = Self {
+            plant,
+            state: PlantState::default(),
+            sensors,
+            safety_cfg,
+            safety: SafetyState::default(),
+            controller,
+            ctrl_out: ControlOutput::default(),
+            applied_prev: None,
+            preload_pre_trip: false,
+            setpoint,
+            setpoint_override: None,
+            manual_coolant: None,
+            dt_s,
+            step: 0,
+            next_event: 0,
+            scheduled_faults: [SensorFault::None; 3],
+            alarm: false,
+            valid: [true; 3],
+            scenario: ScenarioSpec::default(),
+            start: None,
+        };
+        sim.set_scenario(scenario);
+        sim
+    }
+
+    /// Run `spec` instead of the scenario given so far: its initial conditions now, its faults and
+    /// events as the run reaches them. Meant for before the first step.
+    pub fn set_scenario(&mut self, mut spec: ScenarioSpec) {
+        spec.events = spec.sorted_events();
+        self.state = spec.initial.state();
+        self.scenario = spec;
+        self.next_event = 0;
+        self.scheduled_faults = [SensorFault::None; 3];
+    }
+
+    pub fn scenario(&self) -> &ScenarioSpec {
+        &self.scenario
+    }
+
+    /// Change the scenario mid-run, e.g. with commands received while running. New events must
+    /// be due at or after the next step, so they sort after every event already applied.
+    pub fn edit_scenario(&mut self, edit: impl FnOnce(&mut ScenarioSpec)) {
+        edit(&mut self.scenario);
+        self.scenario.events = self.scenario.sorted_events();
+    }
+
+    /// Time at the start of the next step. Counted from the step number rather than summed, so
+    /// timed scenario changes land on the same step in every front end.
+    pub fn time(&self) -> f64 {
+        self.step as f64 * self.dt_s
+    }
+
+    /// The setpoint in force at the next step.
+    pub fn setpoint(&self) -> f64 {
+        self.setpoint_override
+            .unwrap_or_else(|| self.setpoint.at(self.time()))
+    }
+
+    /// Latch a SCRAM now, as the operator's SCRAM button does, with the controller restarted
+    /// right away. False when the plant had already tripped.
+    pub fn manual_scram(&mut self) -> bool {
+        if self.safety.scram {
+            return false;
+        }
+        safety::manual_scram(&mut self.safety);
+        self.on_trip();
+        true
+    }
+
+    /// Back to the run as it was when its first step started, random generators included, so
+    /// stepping again repeats it. Before the first step there is nothing to go back to.
+    pub fn reset(&mut self) {
+        if let Some(start) = self.start.take() {
+            *self = (*start).clone();
+            self.start = Some(start);
+        }
+    }
+
+    pub fn step(&mut self) -> Sample {
+        if self.step == 0 && self.start.is_none() {
+            self.start = Some(Box::new(self.clone()));
+        }
+        let t_s = self.time();
+        let dt_s = self.dt_s;
+        let was_scram = self.safety.scram;
+        let mut events = Vec::new();
+
+        // Scenario events and sensor faults due at this step
+        while let Some(event) = self.scenario.events.get(self.next_event) {
+            if event.t_s > t_s {
+                break;
+            }
+            let action = event.action;
+            match action {
+                EventAction::SetCoolant(coolant) => self.state.coolant = coolant,
+                EventAction::SetSetpoint(setpoint) => self.setpoint_override = Some(setpoint),
+                EventAction::Scram => safety::manual_scram(&mut self.safety),
+            }
+            events.push(StepEvent::Scenario(action));
+            self.next_event += 1;
+        }
+        for (sensor, (_, schedule)) in self.scenario.sensors.all().into_iter().enumerate() {
+            let fault = schedule.fault_at(t_s);
+            if fault != self.scheduled_faults[sensor] {
+                self.scheduled_faults[sensor] = fault;
+                self.sensors[sensor].fault = fault;
+                events.push(StepEvent::Fault { sensor, fault });
+            }
+        }
+        let setpoint = self.setpoint();
+
+        let temp_c = self.state.temp_c;
+        let readings = self.sensors.each_mut().map(|s| s.read_temp(temp_c, dt_s));
+
+        let decision = safety::evaluate(&self.safety_cfg, &mut self.safety, readings);
+        if decision.sensor_disagree_alarm != self.alarm {
+            self.alarm = decision.sensor_disagree_alarm;
+            events.push(StepEvent::Alarm(self.alarm));
+        }
+        for (sensor, valid) in decision.valid.into_iter().enumerate() {
+            if valid != self.valid[sensor] {
+                events.push(StepEvent::Valid { sensor, valid });
+            }
+        }
+        self.valid = decision.valid;
+        self.controller
+            .set_measurement_trust(measurement_trust(&decision));
+
+        if self.safety.scram && !was_scram {
+            self.on_trip();
+            events.push(StepEvent::Trip);
+        }
+
+        if self.safety.scram {
+            self.state.power = 0.0;
+        } else {
+            // Average of the readings there are
+            let mut sum = 0.0;
+            let mut n = 0.0;
+            for y in readings.into_iter().filter(|y| y.is_finite()) {
+                sum += y;
+                n += 1.0;
+            }
+            let measurement = if n > 0.0 { sum / n } else { temp_c };
+
+            self.ctrl_out = self.controller.step(ControlInput {
+                setpoint,
+                measurement,
+                feedforward: 0.0,
+                dt_s,
+                applied_prev: self.applied_prev,
+            });
+            self.state.power = self.ctrl_out.command.clamp(0.0, 1.0);
+            self.applied_prev = Some(self.state.power);
+        }
+
+        if let Some(coolant) = self.manual_coolant {
+            self.state.coolant = coolant;
+        }
+        self.state.step(&self.plant, dt_s);
+        self.step += 1;
+
+        Sample {
+            t_s,
+            setpoint_c: setpoint,
+            true_temp_c: self.state.temp_c,
+            readings,
+            power: self.state.power,
+            coolant: self.state.coolant,
+            scram: self.safety.scram,
+            trip: self.safety.trip,
+            alarm: decision.sensor_disagree_alarm,
+            valid: decision.valid,
+            control: self.ctrl_out,
+            events,
+        }
+    }
+
+    /// Controller handling when a SCRAM latches: restart from the preload, with no output or
+    /// applied power carried over.
+    fn on_trip(&mut self) {
+        let preload = if self.preload_pre_trip {
+            self.state.power
+        } else {
+            0.0
+        };
+        self.controller.reset_to(preload);
+        self.ctrl_out = ControlOutput::default();
+        self.applied_prev = None;
+    }
+}
+
+/// Controller trust in the averaged measurement, from the safety layer's alarms: none without a
+/// valid sensor, suspect while the sensors disagree (alarm, no trip yet).
+pub fn measurement_trust(decision: &SafetyDecision) -> Trust {
+    if decision.valid_sensors == 0 {
+        Trust::None
+    } else if decision.sensor_disagree_alarm {
+        Trust::Suspect
+    } else {
+        Trust::Full
+    }
+}
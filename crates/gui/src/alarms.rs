@@ -0,0 +1,567 @@
+//! The annunciator, the margin gauge and the sensor status lights: what the operator is warned of
+//! before (and when) the plant trips.
+
+use std::time::Duration;
+
+use eframe::egui;
+use safety::SafetyConfig;
+use trace::Sample;
+
+use crate::{App, OperatorAction};
+
+/// Where `voted` sits between `setpoint` (0) and `trip` (1), clamped to that span
+fn margin_fraction(voted: f64, setpoint: f64, trip: f64) -> f64 {
+    if trip > setpoint {
+        ((voted - setpoint) / (trip - setpoint)).clamp(0.0, 1.0)
+    } else if voted >= trip {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// Colour band of the margin gauge
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum MarginLevel {
+    Green,
+    Amber,
+    Red,
+}
+
+fn margin_level(fraction: f64, amber: f64, red: f64) -> MarginLevel {
+    if fraction >= red {
+        MarginLevel::Red
+    } else if fraction >= amber {
+        MarginLevel::Amber
+    } else {
+        MarginLevel::Green
+    }
+}
+
+/// How long a dropout keeps a sensor channel marked suspect
+const DROPOUT_HOLD_S: f64 = 1.0;
+
+/// Assessment of one sensor channel, as the top bar lights show it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum ChannelStatus {
+    /// Valid and agreeing with the others (green)
+    Ok,
+    /// Valid, but off the median by more than the alarm spread or lately dropped out (yellow)
+    Suspect,
+    /// Failing the validity check (red)
+    Invalid,
+    /// No reading yet (grey; the safety logic has no bypass to show)
+    Unknown,
+}
+
+/// Status of each channel, with its deviation from the median of the valid readings: suspect
+/// past `alarm_sensor_delta_c`, or when `dropped_lately`.
+fn channel_status(
+    cfg: &SafetyConfig,
+    readings: [f64; 3],
+    valid: [bool; 3],
+    dropped_lately: [bool; 3],
+) -> [(ChannelStatus, Option<f64>); 3] {
+    let mut good: Vec<f64> = (0..3).filter(|&i| valid[i]).map(|i| readings[i]).collect();
+    good.sort_by(f64::total_cmp);
+    let n = good.len();
+    let median = match n {
+        0 => None,
+        _ if n % 2 == 1 => Some(good[n / 2]),
+        _ => Some((good[n / 2 - 1] + good[n / 2]) / 2.0),
+    };
+    std::array::from_fn(|i| {
+        let deviation = median.filter(|_| valid[i]).map(|m| readings[i] - m);
+        let status = if !valid[i] {
+            ChannelStatus::Invalid
+        } else if dropped_lately[i] || deviation.is_some_and(|d| d.abs() > cfg.alarm_sensor_delta_c)
+        {
+            ChannelStatus::Suspect
+        } else {
+            ChannelStatus::Ok
+        };
+        (status, deviation)
+    })
+}
+
+/// Which sensors had a dropout within `hold_s` of the last sample.
+fn dropped_lately(samples: &[Sample], hold_s: f64) -> [bool; 3] {
+    let t_now = samples.last().map_or(0.0, |s| s.t);
+    let mut dropped = [false; 3];
+    for s in samples.iter().rev().take_while(|s| s.t >= t_now - hold_s) {
+        for (i, reading) in s.sensors().into_iter().enumerate() {
+            dropped[i] |= reading.is_nan();
+        }
+    }
+    dropped
+}
+
+/// A condition of the annunciator strip, in the order of its tiles
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Alarm {
+    HighTemp,
+    Rate,
+    Disagree,
+    Invalid,
+    LowFlow,
+    Trip,
+}
+
+impl Alarm {
+    const ALL: [Alarm; 6] = [
+        Alarm::HighTemp,
+        Alarm::Rate,
+        Alarm::Disagree,
+        Alarm::Invalid,
+        Alarm::LowFlow,
+        Alarm::Trip,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Alarm::HighTemp => "HIGH TEMP",
+            Alarm::Rate => "TEMP RISE",
+            Alarm::Disagree => "DISAGREE",
+            Alarm::Invalid => "SENSOR INVALID",
+            Alarm::LowFlow => "LOW FLOW",
+            Alarm::Trip => "TRIP",
+        }
+    }
+
+    /// Name in acknowledgements
+    fn key(self) -> &'static str {
+        match self {
+            Alarm::HighTemp => "high_temp",
+            Alarm::Rate => "rate",
+            Alarm::Disagree => "disagree",
+            Alarm::Invalid => "invalid",
+            Alarm::LowFlow => "low_flow",
+            Alarm::Trip => "trip",
+        }
+    }
+}
+
+/// How far below the trip temperature the high-temperature alarm comes on, unless a replay's
+/// header gives its alarm temperature
+const HIGH_TEMP_ALARM_MARGIN_C: f64 = 10.0;
+
+/// Rise of the voted temperature (°C/s) that raises the rate alarm, and the time it is measured
+/// over
+const RATE_ALARM_C_PER_S: f64 = 2.0;
+const RATE_WINDOW_S: f64 = 1.0;
+
+/// Coolant fraction below which the low-flow alarm is on
+const LOW_FLOW_ALARM: f64 = 0.2;
+
+/// Which of `Alarm::ALL` are on at the last of `samples`: the voted temperature at or above
+/// `high_temp_c` or rising past `RATE_ALARM_C_PER_S`, valid readings further apart than
+/// `alarm_sensor_delta_c`, a reading outside the valid range, low coolant, and the SCRAM.
+fn alarm_conditions(samples: &[Sample], cfg: &SafetyConfig, high_temp_c: f64) -> [bool; 6] {
+    let Some(last) = samples.last() else {
+        return [false; 6];
+    };
+    let voted = cfg.voted_temp(last.sensors());
+    let earlier = samples[samples.partition_point(|s| s.t < last.t - RATE_WINDOW_S)..]
+        .iter()
+        .find_map(|s| cfg.voted_temp(s.sensors()).map(|v| (s.t, v)));
+    let rising = match (earlier, voted) {
+        (Some((t0, v0)), Some(v)) if last.t > t0 => (v - v0) / (last.t - t0) >= RATE_ALARM_C_PER_S,
+        _ => false,
+    };
+    let valid: Vec<f64> = last
+        .sensors()
+        .into_iter()
+        .filter(|&v| cfg.reading_valid(v))
+        .collect();
+    let spread = valid.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+        - valid.iter().copied().fold(f64::INFINITY, f64::min);
+    Alarm::ALL.map(|alarm| match alarm {
+        Alarm::HighTemp => voted.is_some_and(|v| v >= high_temp_c),
+        Alarm::Rate => rising,
+        Alarm::Disagree => last.alarm || (valid.len() > 1 && spread > cfg.alarm_sensor_delta_c),
+        Alarm::Invalid => valid.len() < 3,
+        Alarm::LowFlow => last.coolant < LOW_FLOW_ALARM,
+        Alarm::Trip => last.scram,
+    })
+}
+
+/// One tile of the annunciator
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Tile {
+    active: bool,
+    acked: bool,
+}
+
+impl Tile {
+    /// On and not yet acknowledged
+    fn flashing(self) -> bool {
+        self.active && !self.acked
+    }
+}
+
+/// The annunciator's tiles, in `Alarm::ALL` order. Acknowledging is the operator's business, not
+/// the run's, so it is kept here.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Annunciator {
+    tiles: [Tile; 6],
+}
+
+impl Annunciator {
+    /// Take in the conditions now: a tile coming on flashes until acknowledged, one going off goes
+    /// dark.
+    fn update(&mut self, active: [bool; 6]) {
+        for (tile, on) in self.tiles.iter_mut().zip(active) {
+            if on && !tile.active {
+                tile.acked = false;
+            }
+            tile.active = on;
+        }
+    }
+
+    /// Tiles showing `active` steadily, as a replay does.
+    fn steady(active: [bool; 6]) -> Self {
+        Self {
+            tiles: active.map(|active| Tile {
+                active,
+                acked: true,
+            }),
+        }
+    }
+
+    /// Acknowledge `alarm`; false when its tile was not flashing.
+    fn ack(&mut self, alarm: Alarm) -> bool {
+        let tile = &mut self.tiles[alarm as usize];
+        let flashing = tile.flashing();
+        tile.acked |= flashing;
+        flashing
+    }
+}
+
+/// Half-period of a flashing tile
+const FLASH_S: f64 = 0.4;
+
+/// The annunciator strip: dark tiles for conditions that are off, flashing ones for those not yet
+/// acknowledged, steady ones for the rest. Returns the tile clicked, if it was flashing, and
+/// whether "Ack all" was pressed.
+fn annunciator_ui(ui: &mut egui::Ui, annunciator: &Annunciator) -> (Option<Alarm>, bool) {
+    let lit = ((ui.input(|i| i.time) / FLASH_S) as u64).is_multiple_of(2);
+    let mut clicked = None;
+    for (alarm, tile) in Alarm::ALL.into_iter().zip(annunciator.tiles) {
+        let on_color = if alarm == Alarm::Trip {
+            egui::Color32::from_rgb(200, 0, 0)
+        } else {
+            egui::Color32::from_rgb(230, 160, 0)
+        };
+        let (fill, text) = match (tile.active, tile.flashing() && !lit) {
+            (false, _) => (egui::Color32::from_gray(40), egui::Color32::GRAY),
+            (true, false) => (on_color, egui::Color32::BLACK),
+            (true, true) => (egui::Color32::from_gray(70), on_color),
+        };
+        let button = egui::Button::new(egui::RichText::new(alarm.label()).strong().color(text))
+            .fill(fill)
+            .min_size(egui::vec2(90.0, 0.0));
+        let state = match (tile.active, tile.acked) {
+            (false, _) => "off",
+            (true, false) => "on, not acknowledged (click to acknowledge)",
+            (true, true) => "on",
+        };
+        if ui.add(button).on_hover_text(state).clicked() && tile.flashing() {
+            clicked = Some(alarm);
+        }
+    }
+    let any_flashing = annunciator.tiles.iter().any(|t| t.flashing());
+    let ack_all = ui
+        .add_enabled(any_flashing, egui::Button::new("Ack all"))
+        .clicked();
+    if any_flashing {
+        ui.ctx()
+            .request_repaint_after(Duration::from_secs_f64(FLASH_S));
+    }
+    (clicked, ack_all)
+}
+
+impl App {
+    /// The sensor status lights: live from the last safety decision, in replay from the readings
+    /// checked against the current safety settings. None before the first sample.
+    pub(crate) fn channel_lights(&self) -> Option<[(ChannelStatus, Option<f64>); 3]> {
+        let readings = self.samples.last()?.sensors();
+        let valid = if self.replay_loaded {
+            readings.map(|v| self.live.engine.safety_cfg.reading_valid(v))
+        } else {
+            self.live.engine.valid
+        };
+        let dropped = dropped_lately(&self.samples, DROPOUT_HOLD_S);
+        Some(channel_status(
+            &self.live.engine.safety_cfg,
+            readings,
+            valid,
+            dropped,
+        ))
+    }
+
+    pub(crate) fn margin_gauge(&mut self, ui: &mut egui::Ui) {
+        let Some(last) = self.samples.last() else {
+            return;
+        };
+        let trip_temp = self.shown_trip_temp();
+        let setpoint = if self.replay_loaded {
+            let setpoint = self.replay_setpoint.or(self.legacy_setpoint);
+            setpoint.unwrap_or(self.setpoint)
+        } else {
+            self.setpoint
+        };
+        // Recorded setpoints win over the initial one
+        let setpoint = if last.setpoint.is_finite() {
+            last.setpoint
+        } else {
+            setpoint
+        };
+        let cfg = SafetyConfig {
+            trip_temp_c: trip_temp,
+            ..self.live.engine.safety_cfg
+        };
+        let temps = last.sensors();
+
+        let bar = if self.scram_now() {
+            egui::ProgressBar::new(1.0)
+                .fill(egui::Color32::RED)
+                .text("TRIPPED")
+        } else if let (Some(voted), Some(margin)) = (cfg.voted_temp(temps), cfg.trip_margin(temps))
+        {
+            let fraction = margin_fraction(voted, setpoint, trip_temp);
+            let fill = match margin_level(fraction, self.gauge_amber, self.gauge_red) {
+                MarginLevel::Green => egui::Color32::from_rgb(0, 150, 0),
+                MarginLevel::Amber => egui::Color32::from_rgb(230, 140, 0),
+                MarginLevel::Red => egui::Color32::RED,
+            };
+            egui::ProgressBar::new(fraction as f32)
+                .fill(fill)
+                .text(format!("margin: {margin:.1} °C"))
+        } else {
+            egui::ProgressBar::new(0.0).text("margin: — (fewer than two valid sensors)")
+        };
+        ui.horizontal(|ui| {
+            ui.label("Safety margin");
+            ui.add(bar.desired_width(360.0)).on_hover_text(format!(
+                "Voted (second-hottest valid) sensor between setpoint {setpoint:.1} °C and \
+                 trip {trip_temp:.1} °C"
+            ));
+            ui.menu_button("⚙", |ui| {
+                ui.label("Colour from this fraction of setpoint → trip:");
+                ui.add(
+                    egui::DragValue::new(&mut self.gauge_amber)
+                        .range(0.0..=1.0)
+                        .speed(0.01)
+                        .prefix("amber "),
+                );
+                ui.add(
+                    egui::DragValue::new(&mut self.gauge_red)
+                        .range(self.gauge_amber..=1.0)
+                        .speed(0.01)
+                        .prefix("red "),
+                );
+            });
+        });
+    }
+
+    /// The annunciator's tiles for what is shown: the live run's, taking in its conditions now, or
+    /// a replay's at the scrub position, which show steadily.
+    pub(crate) fn refresh_annunciator(&mut self) -> Annunciator {
+        let high_temp = self.shown_trip_temp() - HIGH_TEMP_ALARM_MARGIN_C;
+        let alarms = alarm_conditions(&self.samples, &self.live.engine.safety_cfg, high_temp);
+        if self.replay_loaded {
+            return Annunciator::steady(alarms);
+        }
+        self.annunciator.update(alarms);
+        self.annunciator
+    }
+
+    /// Acknowledge the live annunciator's flashing tiles among `alarms`, each logged as an
+    /// operator action.
+    fn acknowledge(&mut self, alarms: impl IntoIterator<Item = Alarm>) {
+        if self.replay_loaded {
+            return;
+        }
+        for alarm in alarms {
+            if self.annunciator.ack(alarm) {
+                self.record(OperatorAction::Acknowledge { alarm: alarm.key() });
+            }
+        }
+    }
+
+    /// The annunciator tiles; clicking one acknowledges it.
+    pub(crate) fn annunciator_bar(&mut self, ctx: &egui::Context, annunciator: &Annunciator) {
+        let (clicked, ack_all) = egui::TopBottomPanel::top("annunciator")
+            .show(ctx, |ui| {
+                ui.horizontal_wrapped(|ui| annunciator_ui(ui, annunciator))
+                    .inner
+            })
+            .inner;
+        if ack_all {
+            self.acknowledge(Alarm::ALL);
+        } else {
+            self.acknowledge(clicked);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cli::Scenario;
+    use safety::SafetyConfig;
+    use sim::{Sensor, SensorFault};
+
+    use crate::live::{LiveSettings, LiveSim};
+    use crate::tests::{live_sim, sample};
+
+    #[test]
+    fn margin_gauge_fills_from_setpoint_to_trip() {
+        assert_eq!(margin_fraction(380.0, 380.0, 420.0), 0.0);
+        assert_eq!(margin_fraction(410.0, 380.0, 420.0), 0.75);
+        assert_eq!(margin_fraction(300.0, 380.0, 420.0), 0.0);
+        assert_eq!(margin_fraction(425.0, 380.0, 420.0), 1.0);
+        // Setpoint at or above the trip: nothing to span
+        assert_eq!(margin_fraction(400.0, 420.0, 420.0), 0.0);
+        assert_eq!(margin_fraction(420.0, 420.0, 420.0), 1.0);
+
+        let level = |fraction| margin_level(fraction, 0.6, 0.85);
+        assert_eq!(level(0.59), MarginLevel::Green);
+        assert_eq!(level(0.6), MarginLevel::Amber);
+        assert_eq!(level(0.85), MarginLevel::Red);
+
+        // The gauge reads the safety crate's voted temperature and margin
+        let cfg = SafetyConfig::default();
+        let temps = [418.0, 395.0, 410.0];
+        assert_eq!(cfg.voted_temp(temps), Some(410.0));
+        assert_eq!(margin_fraction(410.0, 380.0, cfg.trip_temp_c), 0.75);
+        assert_eq!(cfg.trip_margin(temps), Some(10.0));
+    }
+
+    #[test]
+    fn channels_are_judged_against_the_median() {
+        let cfg = SafetyConfig::default();
+        let status = |readings, valid, dropped| channel_status(&cfg, readings, valid, dropped);
+
+        let lights = status([350.0, 356.0, 350.5], [true; 3], [false; 3]);
+        assert_eq!(
+            lights.map(|l| l.0),
+            [ChannelStatus::Ok, ChannelStatus::Suspect, ChannelStatus::Ok]
+        );
+        assert_eq!(lights.map(|l| l.1), [Some(-0.5), Some(5.5), Some(0.0)]);
+
+        // Two valid readings: the median is their mean
+        let lights = status(
+            [350.0, f64::NAN, 352.0],
+            [true, false, true],
+            [false, true, false],
+        );
+        assert_eq!(
+            lights.map(|l| l.0),
+            [ChannelStatus::Ok, ChannelStatus::Invalid, ChannelStatus::Ok]
+        );
+        assert_eq!(lights.map(|l| l.1), [Some(-1.0), None, Some(1.0)]);
+
+        // Back from a dropout, but not yet trusted
+        let lights = status([350.0, 350.2, 349.9], [true; 3], [false, true, false]);
+        assert_eq!(lights[1].0, ChannelStatus::Suspect);
+
+        let lights = status([f64::NAN; 3], [false; 3], [true; 3]);
+        assert!(lights.iter().all(|l| *l == (ChannelStatus::Invalid, None)));
+    }
+
+    #[test]
+    fn drifting_sensor_turns_suspect_before_the_disagreement_trip() {
+        let cfg = SafetyConfig::default();
+        let mut state = safety::SafetyState::default();
+        let mut sensors = [Sensor::new(1), Sensor::new(2), Sensor::new(3)];
+        for s in &mut sensors {
+            s.noise_std = 0.15;
+        }
+        sensors[1].fault = SensorFault::Drift { per_s: 2.0 };
+        let mut s2_suspect_at = None;
+        for step in 0..400 {
+            let readings = sensors.each_mut().map(|s| s.read_temp(350.0, 0.05));
+            let decision = safety::evaluate(&cfg, &mut state, readings);
+            if decision.scram {
+                assert_eq!(state.reason, Some(safety::TripReason::SensorDisagree));
+                assert!(
+                    s2_suspect_at.is_some_and(|at| at < step),
+                    "{s2_suspect_at:?} {step}"
+                );
+                return;
+            }
+            let lights = channel_status(&cfg, readings, decision.valid, [false; 3]);
+            assert_eq!(
+                (lights[0].0, lights[2].0),
+                (ChannelStatus::Ok, ChannelStatus::Ok)
+            );
+            if lights[1].0 == ChannelStatus::Suspect {
+                s2_suspect_at.get_or_insert(step);
+            }
+        }
+        panic!("no trip");
+    }
+
+    #[test]
+    fn recent_dropouts_are_remembered_for_a_while() {
+        let mut samples: Vec<Sample> = (0..=40)
+            .map(|i| sample(f64::from(i) * 0.05, false))
+            .collect();
+        samples[20].s1 = f64::NAN;
+        // The helper's sensor 2 is out throughout; sensor 1 dropped out 1 s before the end
+        assert_eq!(dropped_lately(&samples, 1.0), [true, true, false]);
+        assert_eq!(dropped_lately(&samples, 0.5), [false, true, false]);
+        assert_eq!(dropped_lately(&[], 1.0), [false; 3]);
+    }
+
+    #[test]
+    fn loss_of_cooling_flashes_low_flow_then_latches_the_trip() {
+        // The GUI's defaults but for the trip temperature, which the run reaches after the coolant
+        // is cut 18 s in
+        let settings = LiveSettings {
+            setpoint: 350.0,
+            ..live_sim(Scenario::Normal).settings
+        };
+        let mut sim = LiveSim::new(Scenario::LossOfCooling, 60.0, 0.05, 365.0, 12345, settings);
+        let cfg = sim.engine.safety_cfg;
+        let high_temp = cfg.trip_temp_c - HIGH_TEMP_ALARM_MARGIN_C;
+        let mut samples = Vec::new();
+        let mut annunciator = Annunciator::default();
+        let mut step_until = |sim: &mut LiveSim, annunciator: &mut Annunciator, alarm: Alarm| {
+            while !annunciator.tiles[alarm as usize].active {
+                assert!(sim.step_count() < sim.max_steps, "no {alarm:?}");
+                sim.step();
+                samples.append(&mut sim.samples);
+                annunciator.update(alarm_conditions(&samples, &cfg, high_temp));
+            }
+        };
+
+        step_until(&mut sim, &mut annunciator, Alarm::LowFlow);
+        assert!(annunciator.tiles[Alarm::LowFlow as usize].flashing());
+        assert!(!annunciator.tiles[Alarm::Trip as usize].active);
+        assert!(annunciator.ack(Alarm::LowFlow));
+        assert!(!annunciator.ack(Alarm::LowFlow));
+        sim.record(OperatorAction::Acknowledge {
+            alarm: Alarm::LowFlow.key(),
+        });
+        assert_eq!(sim.log.last().unwrap().text, "acknowledged low_flow");
+
+        step_until(&mut sim, &mut annunciator, Alarm::Trip);
+        let tile = |alarm: Alarm| annunciator.tiles[alarm as usize];
+        assert_eq!(
+            tile(Alarm::LowFlow),
+            Tile {
+                active: true,
+                acked: true
+            }
+        );
+        assert!(tile(Alarm::Trip).flashing());
+
+        // A replay at the same position shows the same tiles, none flashing
+        let replay = Annunciator::steady(alarm_conditions(&samples, &cfg, high_temp));
+        assert!(replay.tiles.iter().all(|t| !t.flashing()));
+        let active = |a: &Annunciator| a.tiles.map(|t| t.active);
+        assert_eq!(active(&replay), active(&annunciator));
+    }
+}
@@ -0,0 +1,913 @@
+//! The controls around the plots: the top bar, the settings side panel, the "Events" panel and
+//! the keyboard shortcuts.
+
+use clap::ValueEnum;
+use cli::config::SENSOR_NAMES;
+use cli::Scenario;
+use eframe::egui;
+use sim::{PlantParams, SensorFault};
+use trace::{RowReason, Sample};
+
+use crate::alarms::ChannelStatus;
+use crate::live::{AbDiff, EditTarget, LiveCommand, CHECKPOINT_RING};
+use crate::scenario_editor::{fault_editor, fault_text};
+use crate::{builtin_label, App, LogKind, OperatorAction, HAS_FILES};
+
+/// One actuator of the "Operator" panel. Returns the override to log when the operator switched
+/// mode or let go of the slider; switching to manual starts from the `current` value.
+fn actuator_row(
+    ui: &mut egui::Ui,
+    label: &str,
+    manual: &mut Option<f64>,
+    current: f64,
+    enabled: bool,
+) -> Option<Option<f64>> {
+    let mut logged = None;
+    ui.horizontal(|ui| {
+        ui.label(label);
+        ui.add_enabled_ui(enabled, |ui| {
+            if ui.selectable_label(manual.is_none(), "Auto").clicked() && manual.is_some() {
+                *manual = None;
+                logged = Some(None);
+            }
+            if ui.selectable_label(manual.is_some(), "Manual").clicked() && manual.is_none() {
+                *manual = Some(current);
+                logged = Some(*manual);
+            }
+        });
+    });
+    if let Some(value) = manual {
+        let slider = ui.add_enabled(enabled, egui::Slider::new(value, 0.0..=1.0));
+        if slider.drag_stopped() || (slider.changed() && !slider.dragged()) {
+            logged = Some(Some(*value));
+        }
+    }
+    logged
+}
+
+/// A reference level the user may type in: a checkbox enabling it and its value in °C,
+/// starting from `default`.
+pub(crate) fn optional_level(
+    ui: &mut egui::Ui,
+    label: &str,
+    value: &mut Option<f64>,
+    default: f64,
+) {
+    ui.horizontal(|ui| {
+        let mut on = value.is_some();
+        if ui.checkbox(&mut on, label).changed() {
+            *value = on.then_some(default);
+        }
+        if let Some(v) = value {
+            ui.add(egui::DragValue::new(v).speed(1.0).suffix(" °C"));
+        }
+    });
+}
+
+/// What a keyboard shortcut does
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Shortcut {
+    // Run or pause the live run, or play or pause the replay
+    RunPause,
+    Step,
+    Reset,
+    Load,
+    // Move the replay position by samples, or by seconds
+    ScrubSamples(isize),
+    ScrubSeconds(f64),
+    Export,
+    Help,
+}
+
+/// The shortcuts listed by the "?" overlay
+const SHORTCUTS: [(&str, &str); 9] = [
+    ("Space", "Run / pause (replay: play / pause)"),
+    ("N", "Step once"),
+    ("Ctrl+R", "Reset"),
+    ("L", "Open a trace…"),
+    ("← / →", "Replay: one sample back / forward"),
+    ("Shift+← / →", "Replay: one second back / forward"),
+    ("Shift+←", "Live: step back once"),
+    ("Ctrl+E", "Export…"),
+    ("?", "Show / hide this list"),
+];
+
+/// The shortcut pressed this frame, if any, consuming its key so no widget sees it as well.
+/// Shift+arrows are checked before the bare arrows, which would match them too.
+fn shortcut_pressed(input: &mut egui::InputState) -> Option<Shortcut> {
+    use egui::{Key, Modifiers};
+    let bindings = [
+        (Modifiers::COMMAND, Key::R, Shortcut::Reset),
+        (Modifiers::COMMAND, Key::E, Shortcut::Export),
+        (
+            Modifiers::SHIFT,
+            Key::ArrowLeft,
+            Shortcut::ScrubSeconds(-1.0),
+        ),
+        (
+            Modifiers::SHIFT,
+            Key::ArrowRight,
+            Shortcut::ScrubSeconds(1.0),
+        ),
+        (Modifiers::NONE, Key::ArrowLeft, Shortcut::ScrubSamples(-1)),
+        (Modifiers::NONE, Key::ArrowRight, Shortcut::ScrubSamples(1)),
+        (Modifiers::NONE, Key::Space, Shortcut::RunPause),
+        (Modifiers::NONE, Key::N, Shortcut::Step),
+        (Modifiers::NONE, Key::L, Shortcut::Load),
+        (Modifiers::NONE, Key::Questionmark, Shortcut::Help),
+        (Modifiers::NONE, Key::F1, Shortcut::Help),
+    ];
+    bindings
+        .into_iter()
+        .find(|(modifiers, key, _)| input.consume_key(*modifiers, *key))
+        .map(|(_, _, shortcut)| shortcut)
+}
+
+impl App {
+    /// Set a sensor's fault from the live run's next reading on.
+    fn inject_fault(&mut self, index: usize, fault: SensorFault) {
+        self.worker.send(LiveCommand::InjectFault(index, fault));
+    }
+
+    /// The "Faults" panel: per sensor, the active fault and one to inject.
+    fn faults_panel(&mut self, ui: &mut egui::Ui, enabled: bool) {
+        let active = self.live.engine.sensors.each_ref().map(|s| s.fault);
+        for (i, name) in SENSOR_NAMES.into_iter().enumerate() {
+            ui.horizontal(|ui| {
+                if active[i] == SensorFault::None {
+                    ui.label(format!("{name}: healthy"));
+                } else {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 140, 0),
+                        format!("{name}: {}", fault_text(&active[i])),
+                    );
+                }
+            });
+            ui.horizontal(|ui| {
+                fault_editor(ui, ("fault_kind", i), &mut self.fault_edit[i]);
+                if ui
+                    .add_enabled(enabled, egui::Button::new("Inject"))
+                    .clicked()
+                {
+                    self.inject_fault(i, self.fault_edit[i]);
+                }
+            });
+        }
+    }
+
+    /// "Apply now": switch the running controller to the edited gains without a bump in the output.
+    fn apply_pid_edit(&mut self) {
+        if self.pid_edit.validate().is_ok() {
+            let target = self.edit_target;
+            self.worker.send(LiveCommand::Pid(self.pid_edit, target));
+        }
+    }
+
+    /// The "Operator" panel: Auto/Manual for coolant and power, with a slider for the manual value.
+    /// A SCRAM holds power at zero whatever the operator sets, so the power slider greys out.
+    fn operator_panel(&mut self, ui: &mut egui::Ui, enabled: bool) {
+        let scram = self.live.engine.safety.scram;
+        let coolant = self.live.engine.state.coolant;
+        if let Some(value) = actuator_row(ui, "coolant", &mut self.manual_coolant, coolant, enabled)
+        {
+            self.record(OperatorAction::SetCoolant { value });
+        }
+        let power = self.live.engine.state.power;
+        if let Some(value) = actuator_row(
+            ui,
+            "power",
+            &mut self.manual_power,
+            power,
+            enabled && !scram,
+        ) {
+            self.record(OperatorAction::SetPower { value });
+        }
+        if scram && !self.replay_loaded {
+            ui.colored_label(egui::Color32::RED, "SCRAM holds power at 0");
+        }
+    }
+
+    /// The "Plant" section: model parameters within bounds that keep the closed loop sane.
+    fn plant_panel(&mut self, ui: &mut egui::Ui, enabled: bool) {
+        let mut changed = false;
+        let params = &mut self.plant_edit;
+        egui::Grid::new("plant_params")
+            .num_columns(2)
+            .show(ui, |ui| {
+                for (label, value, speed, range) in [
+                    ("ambient (°C)", &mut params.ambient_c, 0.5, -20.0..=60.0),
+                    ("thermal mass", &mut params.thermal_mass, 1.0, 10.0..=1000.0),
+                    ("k_power", &mut params.k_power, 5.0, 50.0..=2000.0),
+                    ("k_cool", &mut params.k_cool, 0.01, 0.05..=10.0),
+                ] {
+                    ui.label(label);
+                    changed |= ui
+                        .add_enabled(
+                            enabled,
+                            egui::DragValue::new(value).speed(speed).range(range),
+                        )
+                        .changed();
+                    ui.end_row();
+                }
+            });
+
+        if let Err(errs) = self.plant_edit.validate() {
+            for e in errs {
+                ui.colored_label(egui::Color32::RED, e.to_string());
+            }
+        }
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(enabled, egui::Button::new("Restore defaults"))
+                .clicked()
+            {
+                self.plant_edit = PlantParams::default();
+                changed = true;
+            }
+            // Live changes model degradation during a run, e.g. k_cool fouling
+            changed |= ui
+                .add_enabled(
+                    enabled,
+                    egui::Checkbox::new(&mut self.plant_apply_live, "apply live"),
+                )
+                .changed();
+        });
+        if self.plant_apply_live {
+            if changed && self.plant_edit.validate().is_ok() {
+                let target = self.edit_target;
+                self.worker
+                    .send(LiveCommand::Plant(self.plant_edit, target));
+            }
+        } else {
+            ui.small("Edits take effect on the next Reset.");
+        }
+    }
+
+    /// "Sensors" panel: noise and valid range of each sensor, and the disagreement limits of the
+    /// safety logic. Used from the next Reset.
+    fn sensors_panel(&mut self, ui: &mut egui::Ui, enabled: bool) {
+        egui::Grid::new("sensor_settings")
+            .num_columns(3)
+            .show(ui, |ui| {
+                ui.label("");
+                ui.label("noise σ (°C)");
+                ui.label("valid range (°C)");
+                ui.end_row();
+                for (i, name) in SENSOR_NAMES.into_iter().enumerate() {
+                    let sensor = self.sensors.get_mut(i);
+                    ui.label(name);
+                    ui.add_enabled(
+                        enabled,
+                        egui::DragValue::new(&mut sensor.noise_std)
+                            .speed(0.01)
+                            .range(0.0..=50.0),
+                    );
+                    let (lo, hi) = &mut sensor.valid_range;
+                    ui.horizontal(|ui| {
+                        ui.add_enabled(enabled, egui::DragValue::new(lo).speed(1.0));
+                        ui.label("..");
+                        ui.add_enabled(enabled, egui::DragValue::new(hi).speed(1.0));
+                    });
+                    ui.end_row();
+                }
+            });
+        egui::Grid::new("safety_limits")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let safety = &mut self.safety;
+                for (label, value) in [
+                    ("trip spread (°C)", &mut safety.max_sensor_delta_c),
+                    ("alarm spread (°C)", &mut safety.alarm_sensor_delta_c),
+                ] {
+                    ui.label(label);
+                    ui.add_enabled(
+                        enabled,
+                        egui::DragValue::new(value).speed(0.1).range(0.0..=500.0),
+                    );
+                    ui.end_row();
+                }
+            });
+        for problem in self.sim_config().problems() {
+            if problem.starts_with("sensors.") || problem.starts_with("safety.") {
+                ui.colored_label(egui::Color32::RED, problem);
+            }
+        }
+    }
+
+    /// The "Controller" section: gains and output limits, with the current term contributions.
+    fn controller_panel(&mut self, ui: &mut egui::Ui, enabled: bool) {
+        let before = self.pid_edit;
+        let cfg = &mut self.pid_edit;
+        egui::Grid::new("pid_gains").num_columns(2).show(ui, |ui| {
+            for (label, value, speed) in [
+                ("kp", &mut cfg.kp, 0.001),
+                ("ki", &mut cfg.ki, 0.0005),
+                ("kd", &mut cfg.kd, 0.001),
+                ("out min", &mut cfg.out_min, 0.01),
+                ("out max", &mut cfg.out_max, 0.01),
+            ] {
+                ui.label(label);
+                ui.add_enabled(
+                    enabled,
+                    egui::DragValue::new(value).speed(speed).max_decimals(4),
+                );
+                ui.end_row();
+            }
+        });
+
+        let valid = match self.pid_edit.validate() {
+            Ok(()) => true,
+            Err(errs) => {
+                for e in errs {
+                    ui.colored_label(egui::Color32::RED, e.to_string());
+                }
+                false
+            }
+        };
+        let changed = self.pid_edit.kp != before.kp
+            || self.pid_edit.ki != before.ki
+            || self.pid_edit.kd != before.kd
+            || self.pid_edit.out_min != before.out_min
+            || self.pid_edit.out_max != before.out_max;
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(enabled && valid, egui::Button::new("Apply now"))
+                .clicked()
+            {
+                self.apply_pid_edit();
+            }
+            ui.add_enabled(
+                enabled,
+                egui::Checkbox::new(&mut self.pid_apply_live, "apply while dragging"),
+            );
+        });
+        if changed && self.pid_apply_live {
+            self.apply_pid_edit();
+        }
+        ui.small("Edits take effect on the next Reset unless applied.");
+
+        let terms = self.live.engine.ctrl_out.terms;
+        ui.small(format!(
+            "P {:+.4}  I {:+.4}  D {:+.4}",
+            terms.p, terms.i, terms.d
+        ));
+    }
+
+    /// The "A/B comparison" section: a second live run differing in one setting, drawn as the
+    /// comparison ("B"), and which runs applied edits change.
+    fn ab_panel(&mut self, ui: &mut egui::Ui, enabled: bool) {
+        let mut on = self.ab_diff.is_some();
+        ui.add_enabled(
+            enabled,
+            egui::Checkbox::new(&mut on, "run B alongside A, differing in"),
+        );
+        match (on, self.ab_diff) {
+            (true, None) => self.ab_diff = Some(AbDiff::Kp(self.pid_edit.kp)),
+            (false, Some(_)) => self.ab_diff = None,
+            _ => {}
+        }
+        let Some(diff) = &mut self.ab_diff else {
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            let kinds = [
+                AbDiff::Seed(self.seed.wrapping_add(1)),
+                AbDiff::Kp(self.pid_edit.kp),
+                AbDiff::Scenario(self.scenario),
+            ];
+            egui::ComboBox::from_id_salt("ab_diff")
+                .selected_text(diff.kind())
+                .show_ui(ui, |ui| {
+                    for kind in kinds {
+                        let selected = diff.kind() == kind.kind();
+                        if ui.selectable_label(selected, kind.kind()).clicked() && !selected {
+                            *diff = kind;
+                        }
+                    }
+                });
+            match diff {
+                AbDiff::Seed(seed) => {
+                    ui.add_enabled(enabled, egui::DragValue::new(seed));
+                }
+                AbDiff::Kp(kp) => {
+                    ui.add_enabled(
+                        enabled,
+                        egui::DragValue::new(kp)
+                            .speed(0.001)
+                            .range(0.0..=f64::INFINITY)
+                            .max_decimals(4),
+                    );
+                }
+                AbDiff::Scenario(scenario) => {
+                    egui::ComboBox::from_id_salt("ab_scenario")
+                        .selected_text(builtin_label(*scenario))
+                        .show_ui(ui, |ui| {
+                            for &s in Scenario::value_variants() {
+                                ui.selectable_value(scenario, s, builtin_label(s));
+                            }
+                        });
+                }
+            }
+        });
+        ui.small("B starts with the next Run or Reset, and stops with A.");
+
+        ui.horizontal(|ui| {
+            ui.label("Applied edits change");
+            for (target, label) in [
+                (EditTarget::A, "A"),
+                (EditTarget::B, "B"),
+                (EditTarget::Both, "both"),
+            ] {
+                ui.radio_value(&mut self.edit_target, target, label);
+            }
+        });
+    }
+
+    /// Act on a keyboard shortcut; those for the other mode do nothing.
+    fn apply_shortcut(&mut self, shortcut: Shortcut) {
+        match shortcut {
+            Shortcut::RunPause if self.replay_loaded => self.replay_playing = !self.replay_playing,
+            Shortcut::RunPause => self.toggle_run(),
+            Shortcut::Step if self.replay_loaded => self.do_step_replay(),
+            Shortcut::Step => self.step_live(),
+            Shortcut::Reset => self.reset(),
+            Shortcut::Load => self.browse_replay(),
+            Shortcut::ScrubSamples(n) => {
+                self.seek_replay(self.replay_pos.saturating_add_signed(n));
+            }
+            Shortcut::ScrubSeconds(dt) if !self.replay_loaded => {
+                if dt < 0.0 {
+                    self.step_back_live();
+                }
+            }
+            Shortcut::ScrubSeconds(dt) => {
+                let t = self.samples.last().map_or(0.0, |s| s.t) + dt;
+                self.seek_replay(self.replay_all.partition_point(|s| s.t <= t));
+            }
+            Shortcut::Export if !self.samples.is_empty() => self.browse_export(),
+            Shortcut::Export => {}
+            Shortcut::Help => self.show_shortcuts = !self.show_shortcuts,
+        }
+    }
+
+    /// Apply the shortcut pressed this frame. Keys go to a focused text field or other widget
+    /// rather than to the shortcuts.
+    pub(crate) fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        if !ctx.wants_keyboard_input() {
+            if let Some(shortcut) = ctx.input_mut(shortcut_pressed) {
+                self.apply_shortcut(shortcut);
+                ctx.request_repaint();
+            }
+        }
+    }
+
+    /// The "?" list of keyboard shortcuts.
+    pub(crate) fn shortcuts_window(&mut self, ctx: &egui::Context) {
+        egui::Window::new("Keyboard shortcuts")
+            .open(&mut self.show_shortcuts)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::RIGHT_TOP, [-10.0, 40.0])
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcuts").striped(true).show(ui, |ui| {
+                    // Opening and exporting files have no controls in the browser
+                    let shown = SHORTCUTS
+                        .iter()
+                        .filter(|(keys, _)| HAS_FILES || !matches!(*keys, "L" | "Ctrl+E"));
+                    for &(keys, action) in shown {
+                        ui.strong(keys);
+                        ui.label(action);
+                        ui.end_row();
+                    }
+                });
+                ui.small("Ignored while a text field has focus; Ctrl is ⌘ on macOS.");
+            });
+    }
+
+    /// Mode, sensor lights, SCRAM state and button, and what the plots leave out.
+    pub(crate) fn top_bar(&mut self, ctx: &egui::Context, scram_time: Option<f64>) {
+        let mode_txt = if self.replay_loaded { "REPLAY" } else { "LIVE" };
+        let scram_now = self.scram_now();
+        let reason_txt = self.reason_text();
+        let (shown_points, total_points) = (self.plot_data.temp.len(), self.plotted().len());
+        let follow = self.follow;
+        let dropout_counts = self.plot_data.dropout_counts;
+        let readings = self.samples.last().map(Sample::sensors);
+        let lights = self.channel_lights();
+        let compare_trip = self.compare_path.as_ref().map(|_| {
+            let trip = self.compare_visible().iter().find(|s| s.scram);
+            let reason = match (trip, &self.compare_reason) {
+                (Some(s), first) => s
+                    .reason
+                    .as_deref()
+                    .or(first.as_ref())
+                    .map(RowReason::to_string),
+                (None, _) => None,
+            };
+            let reason = reason.unwrap_or_else(|| "—".to_string());
+            (trip.map(|s| s.t + self.compare_offset), reason)
+        });
+
+        egui::TopBottomPanel::top("top").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("Reactor Safety Sim (Portfolio GUI)");
+                if ui
+                    .small_button("?")
+                    .on_hover_text("Keyboard shortcuts")
+                    .clicked()
+                {
+                    self.show_shortcuts = !self.show_shortcuts;
+                }
+                ui.separator();
+                ui.label(format!("MODE: {mode_txt}"));
+                ui.separator();
+
+                for i in 0..3 {
+                    let (status, deviation) =
+                        lights.map_or((ChannelStatus::Unknown, None), |l| l[i]);
+                    let (color, what) = match status {
+                        ChannelStatus::Ok => (egui::Color32::GREEN, "valid, agreeing"),
+                        ChannelStatus::Suspect => (egui::Color32::YELLOW, "suspect"),
+                        ChannelStatus::Invalid => (egui::Color32::RED, "invalid"),
+                        ChannelStatus::Unknown => (egui::Color32::GRAY, "no reading yet"),
+                    };
+                    let reading = match readings.map(|r| r[i]) {
+                        Some(v) if !v.is_nan() => format!("{v:.1} °C"),
+                        Some(_) => "dropped out".to_string(),
+                        None => "—".to_string(),
+                    };
+                    let deviation = deviation.map_or("—".to_string(), |d| format!("{d:+.1} °C"));
+                    ui.label(egui::RichText::new(format!("● S{}", i + 1)).color(color))
+                        .on_hover_text(format!(
+                            "{}: {what}\nreading {reading}\nfrom median {deviation}",
+                            SENSOR_NAMES[i]
+                        ));
+                }
+                ui.separator();
+
+                let label = if scram_now { "SCRAM: ON" } else { "SCRAM: OFF" };
+                let color = if scram_now {
+                    egui::Color32::RED
+                } else {
+                    egui::Color32::GREEN
+                };
+                ui.colored_label(color, label);
+
+                let scram_button = egui::Button::new(
+                    egui::RichText::new("SCRAM")
+                        .strong()
+                        .size(18.0)
+                        .color(egui::Color32::WHITE),
+                )
+                .fill(egui::Color32::from_rgb(200, 0, 0));
+                if ui
+                    .add_enabled(!self.replay_loaded && !scram_now, scram_button)
+                    .on_hover_text("Trip the reactor now (manual SCRAM)")
+                    .on_disabled_hover_text(if self.replay_loaded {
+                        "A replay shows a recorded run; SCRAM acts on LIVE runs only"
+                    } else {
+                        "Already tripped; Reset starts a new run"
+                    })
+                    .clicked()
+                {
+                    self.manual_scram();
+                }
+
+                let compare = compare_trip.as_ref().map(|(t, reason)| {
+                    // Side by side: "A" for the primary trace, "B" for the comparison
+                    let t = t.map_or("—".to_string(), |t| format!("{t:.2}s"));
+                    format!("B: t_scram = {t}, reason = {reason}")
+                });
+                if let Some(t) = scram_time {
+                    ui.separator();
+                    let prefix = if compare.is_some() { "A: " } else { "" };
+                    ui.label(format!("{prefix}t_scram = {:.2}s", t));
+                    ui.separator();
+                    ui.label(format!("reason = {reason_txt}"));
+                } else if compare.is_some() {
+                    ui.separator();
+                    ui.label("A: t_scram = —");
+                }
+                if let Some(compare) = compare {
+                    ui.separator();
+                    ui.label(compare);
+                }
+                if dropout_counts.iter().any(|n| *n > 0) {
+                    let counts: Vec<String> = dropout_counts
+                        .iter()
+                        .enumerate()
+                        .map(|(i, n)| format!("S{} {n}", i + 1))
+                        .collect();
+                    ui.separator();
+                    // Following, the counts (like the plot) cover the window only
+                    let scope = if follow { " (window)" } else { "" };
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("dropouts{scope}: {}", counts.join(", ")),
+                    );
+                }
+                if shown_points < total_points {
+                    ui.separator();
+                    ui.label(format!("showing {shown_points} of {total_points} points"));
+                }
+            });
+        });
+    }
+
+    /// Scenario, settings, live and replay controls, exports and errors.
+    pub(crate) fn side_panel(&mut self, ctx: &egui::Context) {
+        egui::SidePanel::left("left")
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Scenario");
+
+                if self.replay_loaded {
+                    ui.add_enabled(false, egui::Label::new(self.scenario_label()));
+                } else {
+                    let mut picked = None;
+                    egui::ComboBox::from_id_salt("scenario")
+                        .selected_text(self.scenario_label())
+                        .show_ui(ui, |ui| {
+                            let custom = self.custom_scenario.is_some();
+                            for &s in Scenario::value_variants() {
+                                let selected = !custom && s == self.scenario;
+                                if ui.selectable_label(selected, builtin_label(s)).clicked() {
+                                    picked = Some(s);
+                                }
+                            }
+                            if custom {
+                                let _ = ui.selectable_label(true, self.scenario_label());
+                            }
+                        });
+
+                    // A built-in scenario takes over from the editor's
+                    if let Some(s) = picked {
+                        if s != self.scenario || self.custom_scenario.is_some() {
+                            self.scenario = s;
+                            self.custom_scenario = None;
+                            self.reset_live();
+                        }
+                    }
+                }
+                if ui.button("Scenario editor…").clicked() {
+                    self.show_scenario_editor = !self.show_scenario_editor;
+                }
+                if HAS_FILES {
+                    ui.horizontal(|ui| {
+                        let idle =
+                            self.config_open_dialog.is_none() && self.config_save_dialog.is_none();
+                        if ui
+                            .add_enabled(idle, egui::Button::new("Load config…"))
+                            .on_hover_text("A `run --config` TOML file; resets the live run")
+                            .clicked()
+                        {
+                            self.browse_config(false);
+                        }
+                        if ui
+                            .add_enabled(idle, egui::Button::new("Save config…"))
+                            .on_hover_text("These settings as a `run --config` TOML file")
+                            .clicked()
+                        {
+                            self.browse_config(true);
+                        }
+                    });
+                }
+
+                ui.separator();
+                ui.label("Simulation settings");
+
+                let live_enabled = !self.replay_loaded;
+                ui.add_enabled(
+                    live_enabled,
+                    egui::Slider::new(&mut self.seconds, 10.0..=300.0).text("seconds"),
+                );
+                ui.add_enabled(
+                    live_enabled,
+                    egui::Slider::new(&mut self.dt_ms, 1..=200).text("dt (ms)"),
+                );
+                ui.add_enabled(
+                    live_enabled,
+                    egui::Slider::new(&mut self.setpoint, 100.0..=600.0).text("setpoint (°C)"),
+                );
+                ui.add_enabled(
+                    live_enabled,
+                    egui::Slider::new(&mut self.trip_temp, 200.0..=900.0).text("trip temp (°C)"),
+                );
+                ui.add_enabled(
+                    live_enabled,
+                    egui::DragValue::new(&mut self.seed).prefix("seed: "),
+                );
+                ui.add_enabled(
+                    live_enabled,
+                    egui::Checkbox::new(
+                        &mut self.scram_preload_pre_trip,
+                        "preload pre-trip power on SCRAM",
+                    ),
+                );
+                ui.add_enabled(
+                    live_enabled,
+                    egui::Checkbox::new(
+                        &mut self.continue_after_scram,
+                        "continue after SCRAM (post-trip cooldown)",
+                    ),
+                );
+                ui.checkbox(&mut self.pause_on_trip, "pause on trip")
+                    .on_hover_text(
+                        "Pause the run or replay when it trips, zoomed to the seconds around it",
+                    );
+
+                egui::CollapsingHeader::new("Operator")
+                    .default_open(false)
+                    .show(ui, |ui| self.operator_panel(ui, live_enabled));
+                egui::CollapsingHeader::new("Faults")
+                    .default_open(false)
+                    .show(ui, |ui| self.faults_panel(ui, live_enabled));
+                egui::CollapsingHeader::new("Plant")
+                    .default_open(false)
+                    .show(ui, |ui| self.plant_panel(ui, live_enabled));
+                egui::CollapsingHeader::new("Sensors")
+                    .default_open(false)
+                    .show(ui, |ui| self.sensors_panel(ui, live_enabled));
+                egui::CollapsingHeader::new("Controller")
+                    .default_open(false)
+                    .show(ui, |ui| self.controller_panel(ui, live_enabled));
+                egui::CollapsingHeader::new("A/B comparison")
+                    .default_open(false)
+                    .show(ui, |ui| self.ab_panel(ui, live_enabled));
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    if ui.button("Reset").clicked() {
+                        self.reset();
+                    }
+
+                    let run_label = if self.running { "Pause" } else { "Run" };
+                    if ui
+                        .add_enabled(live_enabled, egui::Button::new(run_label))
+                        .clicked()
+                    {
+                        self.toggle_run();
+                    }
+
+                    if ui
+                        .add_enabled(live_enabled, egui::Button::new("Step"))
+                        .clicked()
+                    {
+                        self.step_live();
+                    }
+
+                    if ui
+                        .add_enabled(
+                            live_enabled && self.live.step_count() > 0,
+                            egui::Button::new("Step back"),
+                        )
+                        .on_hover_text("One step earlier, paused (Shift+←)")
+                        .clicked()
+                    {
+                        self.step_back_live();
+                    }
+                });
+                ui.add_enabled(
+                    live_enabled && !self.live_max_speed,
+                    egui::Slider::new(&mut self.live_speed, 0.1..=100.0)
+                        .logarithmic(true)
+                        .text("run speed ×"),
+                );
+                ui.add_enabled(
+                    live_enabled,
+                    egui::Checkbox::new(&mut self.live_max_speed, "max × (as fast as possible)"),
+                );
+                ui.horizontal(|ui| {
+                    ui.label("snapshot every");
+                    ui.add_enabled(
+                        live_enabled,
+                        egui::DragValue::new(&mut self.checkpoint_every)
+                            .range(1..=10_000)
+                            .suffix(" steps"),
+                    )
+                    .on_hover_text(format!(
+                        "\"Step back\" reaches back {CHECKPOINT_RING} snapshots at most"
+                    ));
+                });
+
+                self.replay_controls(ui);
+
+                self.export_controls(ui);
+
+                if let Some(err) = &self.last_error {
+                    ui.separator();
+                    ui.colored_label(egui::Color32::RED, err);
+                }
+
+                ui.separator();
+                ui.small(if HAS_FILES {
+                    "Tip: Use make_demo.ps1 then Load out/demo_overheat.jsonl, or drop a trace \
+                     onto the window."
+                } else {
+                    "Tip: Drop a trace (.jsonl, .jsonl.gz or .msgpack) onto the page to replay it."
+                });
+            });
+    }
+
+    /// The "Events" panel; clicking an event goes to its time.
+    pub(crate) fn events_panel(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("events")
+            .resizable(true)
+            .show(ctx, |ui| {
+                let (entries, t_now) = if self.replay_loaded {
+                    (&self.replay_log, self.t)
+                } else {
+                    (&self.log, f64::INFINITY)
+                };
+                let mut picked = None;
+                egui::CollapsingHeader::new(format!("Events ({})", entries.len()))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        egui::ScrollArea::vertical()
+                            .max_height(140.0)
+                            .auto_shrink([false, true])
+                            .stick_to_bottom(true)
+                            .show(ui, |ui| {
+                                if entries.is_empty() {
+                                    ui.weak("No events yet");
+                                }
+                                for entry in entries {
+                                    let mut text =
+                                        egui::RichText::new(entry.to_string()).monospace();
+                                    text = match entry.kind {
+                                        LogKind::Trip => text
+                                            .strong()
+                                            .color(egui::Color32::WHITE)
+                                            .background_color(egui::Color32::from_rgb(150, 0, 0)),
+                                        LogKind::Alarm => text.color(egui::Color32::YELLOW),
+                                        LogKind::Scenario | LogKind::Operator => text,
+                                    };
+                                    // Replay events the position has not reached yet
+                                    if entry.t_s > t_now {
+                                        text = text.weak();
+                                    }
+                                    let row = egui::Label::new(text).sense(egui::Sense::click());
+                                    if ui.add(row).on_hover_text("Go to this time").clicked() {
+                                        picked = Some(entry.t_s);
+                                    }
+                                }
+                            });
+                    });
+                if let Some(t) = picked {
+                    self.jump_to(t);
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use eframe::egui;
+
+    #[test]
+    fn shortcuts_are_read_from_the_pressed_keys() {
+        use egui::{Event, Key, Modifiers};
+        let ctx = egui::Context::default();
+        let press = |key, modifiers| {
+            let mut pressed = None;
+            let events = vec![Event::Key {
+                key,
+                physical_key: None,
+                pressed: true,
+                repeat: false,
+                modifiers,
+            }];
+            let input = egui::RawInput {
+                events,
+                modifiers,
+                ..Default::default()
+            };
+            let _ = ctx.run(input, |ctx| pressed = ctx.input_mut(shortcut_pressed));
+            pressed
+        };
+        assert_eq!(press(Key::Space, Modifiers::NONE), Some(Shortcut::RunPause));
+        assert_eq!(press(Key::N, Modifiers::NONE), Some(Shortcut::Step));
+        assert_eq!(press(Key::R, Modifiers::COMMAND), Some(Shortcut::Reset));
+        // Reset needs the modifier, and Ctrl+N is not a step
+        assert_eq!(press(Key::R, Modifiers::NONE), None);
+        assert_eq!(press(Key::N, Modifiers::COMMAND), None);
+        assert_eq!(
+            press(Key::ArrowLeft, Modifiers::NONE),
+            Some(Shortcut::ScrubSamples(-1))
+        );
+        assert_eq!(
+            press(Key::ArrowRight, Modifiers::SHIFT),
+            Some(Shortcut::ScrubSeconds(1.0))
+        );
+        assert_eq!(press(Key::E, Modifiers::COMMAND), Some(Shortcut::Export));
+        assert_eq!(press(Key::L, Modifiers::NONE), Some(Shortcut::Load));
+        assert_eq!(
+            press(Key::Questionmark, Modifiers::SHIFT),
+            Some(Shortcut::Help)
+        );
+    }
+}
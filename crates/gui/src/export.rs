@@ -0,0 +1,595 @@
+//! "Export…" and "Record to file": samples written as the CLI's traces, header included.
+
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::TryRecvError;
+use std::time::Duration;
+
+use clap::Parser;
+use cli::config::SimConfig;
+use controller::metrics::ControlMetrics;
+use eframe::egui;
+use serde::Serialize;
+use trace::{csv_header, DiagColumns, MetaLine, RowReason, Sample, TraceRow};
+use web_time::Instant;
+
+use crate::plots::control_metrics;
+use crate::{spawn_dialog, App, OperatorEvent, DIALOG_POLL, HAS_FILES};
+
+/// File format of "Export…"
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ExportFormat {
+    /// JSONL in the CLI's trace schema, starting with the `_meta` header
+    Jsonl,
+    /// CSV with the CLI's `--format csv` columns
+    Csv,
+}
+
+impl ExportFormat {
+    /// The format a save-dialog path asks for: CSV for `.csv`, JSONL otherwise.
+    fn for_path(path: &Path) -> Self {
+        let csv = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("csv"));
+        if csv {
+            ExportFormat::Csv
+        } else {
+            ExportFormat::Jsonl
+        }
+    }
+}
+
+/// `_meta` header of an exported or recorded JSONL trace: the CLI's header of a run of the
+/// settings (see `App::trace_meta`), and what only the GUI knows of the run.
+#[derive(Debug, Serialize)]
+pub(crate) struct ExportMeta {
+    #[serde(flatten)]
+    trace: cli::trace::TraceMeta,
+    // Operator actions of a live run, in time order
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    operator_events: Vec<OperatorEvent>,
+    // Controller metrics of the exported samples (none without a setpoint to measure against)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metrics: Option<ControlMetrics>,
+    // Reason written on tripped rows without one of their own (the header does not serialize it)
+    #[serde(skip)]
+    reason: Option<RowReason>,
+}
+
+/// The trace row of `s`; a tripped sample without a reason of its own takes `reason`.
+fn export_row(s: &Sample, reason: Option<&RowReason>) -> TraceRow {
+    TraceRow {
+        t_s: s.t,
+        setpoint_c: s.setpoint,
+        true_temp_c: s.true_temp,
+        s1_c: s.s1,
+        s2_c: s.s2,
+        s3_c: s.s3,
+        power: s.power,
+        coolant: s.coolant,
+        scram: s.scram,
+        reason: s.reason.as_deref().or(reason.filter(|_| s.scram)).cloned(),
+        diag: export_diag(s),
+    }
+}
+
+/// The CLI's `--diagnostics` columns, rebuilt from what a sample keeps; None for samples without
+/// controller terms (replays recorded without --diagnostics).
+fn export_diag(s: &Sample) -> Option<DiagColumns> {
+    if s.p_term.is_nan() {
+        return None;
+    }
+    // A saturated sample only says it was clipped; the low limit is the one at zero power
+    let ctrl_status = match (s.saturated, s.power <= 0.0) {
+        (false, _) => "-",
+        (true, false) => "H",
+        (true, true) => "L",
+    };
+    Some(DiagColumns {
+        p_term: s.p_term,
+        i_term: s.i_term,
+        d_term: s.d_term,
+        // The GUI's controller runs without feedforward
+        ff_term: 0.0,
+        out_presat: s.p_term + s.i_term + s.d_term,
+        saturated: s.saturated,
+        ctrl_status: ctrl_status.to_string(),
+    })
+}
+
+/// Write `samples` to `path` as JSONL (header first) or CSV, in the layout the CLI writes, so the
+/// GUI and the CLI's tools can read the file back.
+fn export_samples(
+    path: &Path,
+    samples: &[Sample],
+    format: ExportFormat,
+    meta: &ExportMeta,
+) -> Result<(), String> {
+    let fail = |e: &dyn std::fmt::Display| format!("Failed to write {}: {e}", path.display());
+    let file = fs::File::create(path).map_err(|e| fail(&e))?;
+    let mut out = BufWriter::new(file);
+    match format {
+        ExportFormat::Jsonl => {
+            let header = serde_json::to_string(&MetaLine { meta }).map_err(|e| fail(&e))?;
+            writeln!(out, "{header}").map_err(|e| fail(&e))?;
+            for s in samples {
+                let line = serde_json::to_string(&export_row(s, meta.reason.as_ref()))
+                    .map_err(|e| fail(&e))?;
+                writeln!(out, "{line}").map_err(|e| fail(&e))?;
+            }
+        }
+        ExportFormat::Csv => {
+            let diagnostics = samples.iter().any(|s| !s.p_term.is_nan());
+            writeln!(out, "{}", csv_header(diagnostics).join(",")).map_err(|e| fail(&e))?;
+            for s in samples {
+                let row = export_row(s, meta.reason.as_ref());
+                let mut cells = row.csv_cells();
+                // Samples without controller terms leave the diagnostics columns empty
+                if diagnostics && row.diag.is_none() {
+                    cells.resize(cells.len() + DiagColumns::CSV_COLUMNS.len(), String::new());
+                }
+                writeln!(out, "{}", cells.join(",")).map_err(|e| fail(&e))?;
+            }
+        }
+    }
+    out.flush().map_err(|e| fail(&e))
+}
+
+/// Longest a recording goes without flushing while samples arrive
+const RECORD_FLUSH: Duration = Duration::from_secs(1);
+
+/// "Record to file": a live run written out as it runs, as `run --config` with the settings
+/// would write it (until an operator action makes the run differ). Flushed at least every
+/// `RECORD_FLUSH`, so a crash loses at most about a second.
+pub(crate) struct Recorder {
+    path: PathBuf,
+    out: BufWriter<fs::File>,
+    meta: ExportMeta,
+    rows: usize,
+    flushed: Instant,
+}
+
+impl Recorder {
+    /// Start the file with the header of `meta`.
+    fn create(path: &Path, meta: ExportMeta) -> Result<Self, String> {
+        let file = fs::File::create(path).map_err(|e| record_failed(path, &e))?;
+        let mut recorder = Self {
+            path: path.to_path_buf(),
+            out: BufWriter::new(file),
+            meta,
+            rows: 0,
+            flushed: Instant::now(),
+        };
+        let header = serde_json::to_string(&MetaLine {
+            meta: &recorder.meta,
+        });
+        let header = header.map_err(|e| record_failed(path, &e))?;
+        writeln!(recorder.out, "{header}").map_err(|e| record_failed(path, &e))?;
+        recorder.out.flush().map_err(|e| record_failed(path, &e))?;
+        Ok(recorder)
+    }
+
+    pub(crate) fn append(&mut self, samples: &[Sample]) -> Result<(), String> {
+        let fail = |e: &dyn std::fmt::Display| record_failed(&self.path, e);
+        for s in samples {
+            // The rows of `run` without --diagnostics, as the header's options say
+            let row = TraceRow {
+                diag: None,
+                ..export_row(s, None)
+            };
+            let line = serde_json::to_string(&row).map_err(|e| fail(&e))?;
+            writeln!(self.out, "{line}").map_err(|e| fail(&e))?;
+        }
+        self.rows += samples.len();
+        if self.flushed.elapsed() >= RECORD_FLUSH {
+            self.out.flush().map_err(|e| fail(&e))?;
+            self.flushed = Instant::now();
+        }
+        Ok(())
+    }
+
+    /// Flush what is left; with operator `events`, write the header again to carry them, as an
+    /// export's does. Returns the number of rows written.
+    fn finish(mut self, events: &[OperatorEvent]) -> Result<usize, String> {
+        let path = self.path.clone();
+        let fail = |e: &dyn std::fmt::Display| record_failed(&path, e);
+        self.out.flush().map_err(|e| fail(&e))?;
+        drop(self.out);
+        if events.is_empty() {
+            return Ok(self.rows);
+        }
+        self.meta.operator_events = events.to_vec();
+        let text = fs::read_to_string(&path).map_err(|e| fail(&e))?;
+        let rows = text.split_once('\n').map_or("", |(_, rows)| rows);
+        let header = serde_json::to_string(&MetaLine { meta: &self.meta });
+        let header = header.map_err(|e| fail(&e))?;
+        // Replaced in one rename, so the recording is never left half rewritten
+        let partial = path.with_extension("partial");
+        fs::write(&partial, format!("{header}\n{rows}")).map_err(|e| fail(&e))?;
+        fs::rename(&partial, &path).map_err(|e| fail(&e))?;
+        Ok(self.rows)
+    }
+}
+
+fn record_failed(path: &Path, e: &dyn std::fmt::Display) -> String {
+    format!("Failed to record to {}: {e}", path.display())
+}
+
+impl App {
+    /// Start recording the live run just reset, when "Record to file" is ticked.
+    pub(crate) fn start_recording(&mut self) {
+        if !self.record {
+            return;
+        }
+        let recorder = self
+            .export_meta()
+            .and_then(|meta| Recorder::create(Path::new(&self.record_path), meta));
+        match recorder {
+            Ok(recorder) => self.recorder = Some(recorder),
+            Err(e) => self.last_error = Some(e),
+        }
+    }
+
+    /// Finish the recording, if one is going, with the operator's actions of the run.
+    pub(crate) fn stop_recording(&mut self) {
+        let Some(recorder) = self.recorder.take() else {
+            return;
+        };
+        let path = recorder.path.clone();
+        match recorder.finish(&self.events) {
+            Ok(n) => {
+                self.export_notice = Some(format!("Recorded {n} samples to {}", path.display()));
+            }
+            Err(e) => self.last_error = Some(e),
+        }
+    }
+
+    /// The header `run --config` writes for a run of the settings, as the live run has them (its
+    /// plant and gains are the last valid edits, its scenario the editor's when it runs one).
+    fn trace_meta(&self) -> Result<cli::trace::TraceMeta, String> {
+        let engine = &self.live.engine;
+        let cfg = SimConfig {
+            plant: engine.plant,
+            pid: *engine.controller.config(),
+            ..self.sim_config()
+        };
+        let spec = match &self.custom_scenario {
+            Some(spec) => (**spec).clone(),
+            None => self.scenario.spec(self.seconds),
+        };
+        let mut args = cli::RunArgs::try_parse_from(["run"]).map_err(|e| e.to_string())?;
+        args.scram_preload = if self.scram_preload_pre_trip {
+            cli::ScramPreload::PreTrip
+        } else {
+            cli::ScramPreload::Zero
+        };
+        args.continue_after_scram = self.continue_after_scram;
+        cli::trace::TraceMeta::new(&args, &cfg, &spec, self.setpoint).map_err(|e| format!("{e:#}"))
+    }
+
+    /// Header and trip reason of an export. A replay keeps its own setpoint, trip temperature and
+    /// reason where its header has them; its rows are not a run of the settings, so the header
+    /// leaves out the configuration `verify` would re-run.
+    fn export_meta(&self) -> Result<ExportMeta, String> {
+        let mut trace = self.trace_meta()?;
+        let reason = if self.replay_loaded {
+            trace.header.setpoint = self.replay_setpoint.unwrap_or(self.setpoint);
+            trace.header.trip_temp = self.replay_trip_temp.unwrap_or(self.trip_temp);
+            trace.sensors = None;
+            trace.config = None;
+            trace.scenario_spec = None;
+            trace.options = None;
+            self.replay_reason.clone()
+        } else {
+            self.live.engine.safety.trip.map(RowReason::Trip)
+        };
+        let operator_events = if self.replay_loaded {
+            Vec::new()
+        } else {
+            self.events.clone()
+        };
+        Ok(ExportMeta {
+            trace,
+            operator_events,
+            metrics: None,
+            reason,
+        })
+    }
+
+    /// "Export…": pick where to save the displayed samples, as JSONL or CSV.
+    pub(crate) fn browse_export(&mut self) {
+        if self.save_dialog.is_some() {
+            return;
+        }
+        let dialog = rfd::AsyncFileDialog::new()
+            .set_title("Export samples")
+            .set_file_name("export.jsonl")
+            .add_filter("JSONL trace", &["jsonl"])
+            .add_filter("CSV", &["csv"]);
+        self.save_dialog = Some(spawn_dialog(dialog.save_file()));
+    }
+
+    /// Export to the path picked in the save dialog once it closes; cancelling changes nothing.
+    pub(crate) fn poll_save_dialog(&mut self, ctx: &egui::Context) {
+        let Some(rx) = &self.save_dialog else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(Some(path)) => {
+                self.save_dialog = None;
+                self.export_to(&path);
+            }
+            Ok(None) | Err(TryRecvError::Disconnected) => self.save_dialog = None,
+            Err(TryRecvError::Empty) => ctx.request_repaint_after(DIALOG_POLL),
+        }
+    }
+
+    /// Write the displayed samples (or, in replay with the checkbox ticked, the whole trace).
+    fn export_to(&mut self, path: &Path) {
+        let samples = if self.replay_loaded && self.export_full_replay {
+            &self.replay_all
+        } else {
+            &self.samples
+        };
+        let format = ExportFormat::for_path(path);
+        let written = self.export_meta().and_then(|mut meta| {
+            meta.metrics = control_metrics(samples);
+            // Samples with controller terms export them, as `run --diagnostics` writes them
+            if let Some(options) = &mut meta.trace.options {
+                options.diagnostics = samples.iter().any(|s| !s.p_term.is_nan());
+            }
+            export_samples(path, samples, format, &meta)
+        });
+        let written = written.map(|()| samples.len());
+        match written {
+            Ok(n) => {
+                self.last_error = None;
+                self.export_notice = Some(format!("Exported {n} samples to {}", path.display()));
+            }
+            Err(e) => {
+                self.export_notice = None;
+                self.last_error = Some(e);
+            }
+        }
+    }
+
+    /// "Export" in the side panel: exports, plot images and recordings.
+    pub(crate) fn export_controls(&mut self, ui: &mut egui::Ui) {
+        if HAS_FILES {
+            ui.separator();
+            ui.label("Export (JSONL or CSV)");
+            ui.horizontal(|ui| {
+                let can_export = !self.samples.is_empty() && self.save_dialog.is_none();
+                if ui
+                    .add_enabled(can_export, egui::Button::new("Export…"))
+                    .clicked()
+                {
+                    self.browse_export();
+                }
+                ui.add_enabled(
+                    self.replay_loaded,
+                    egui::Checkbox::new(&mut self.export_full_replay, "full replay file"),
+                );
+            });
+            ui.horizontal(|ui| {
+                let can_export = !self.samples.is_empty() && self.png_dialog.is_none();
+                if ui
+                    .add_enabled(can_export, egui::Button::new("Export plot PNG…"))
+                    .on_hover_text("Temperature and actuator plots as shown, with the legend")
+                    .clicked()
+                {
+                    self.browse_png();
+                }
+                let [width, height] = &mut self.png_size;
+                ui.add(egui::DragValue::new(width).range(320..=7680));
+                ui.label("×");
+                ui.add(egui::DragValue::new(height).range(240..=4320));
+            });
+            ui.horizontal(|ui| {
+                let idle = !self.running;
+                ui.add_enabled(
+                    idle,
+                    egui::Checkbox::new(&mut self.record, "Record to file"),
+                )
+                .on_hover_text(
+                    "Write each live run to this JSONL file as it runs, from \"Run\" until \
+                         it pauses, trips or ends",
+                );
+                ui.add_enabled(
+                    idle && self.record,
+                    egui::TextEdit::singleline(&mut self.record_path).desired_width(160.0),
+                );
+            });
+            if let Some(recorder) = &self.recorder {
+                ui.small(format!(
+                    "● Recording to {} ({} samples)",
+                    recorder.path.display(),
+                    recorder.rows
+                ));
+            }
+        }
+        if let Some(notice) = &self.export_notice {
+            ui.small(notice);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use cli::Scenario;
+    use sim::{PlantParams, SensorFault};
+    use trace::TRACE_SCHEMA;
+
+    use crate::replay::load_trace_from_bytes;
+    use crate::tests::{export_dir, sample};
+    use crate::{write_config, OperatorAction};
+
+    fn meta(reason: Option<RowReason>) -> ExportMeta {
+        let app = App {
+            scenario: Scenario::Overheat,
+            seed: 42,
+            setpoint: 380.0,
+            trip_temp: 390.0,
+            ..App::default()
+        };
+        ExportMeta {
+            reason,
+            ..app.export_meta().unwrap()
+        }
+    }
+
+    #[test]
+    fn exported_jsonl_reloads_into_the_same_samples() {
+        let mut samples: Vec<Sample> = (0..4).map(|i| sample(i as f64 * 0.05, i == 3)).collect();
+        // A legacy replay without setpoint or controller terms
+        samples[1].setpoint = f64::NAN;
+        samples[1].p_term = f64::NAN;
+        samples[1].i_term = f64::NAN;
+        samples[1].d_term = f64::NAN;
+        samples[1].saturated = false;
+        samples[3].reason = Some(Arc::new(RowReason::Name("OverTemp".into())));
+        let dir = export_dir("jsonl");
+        let path = dir.join("export.jsonl");
+        let mut meta = meta(Some(RowReason::Name("OverTemp".into())));
+        meta.operator_events.push(OperatorEvent {
+            t_s: 0.1,
+            action: OperatorAction::InjectFault {
+                sensor: "s2",
+                fault: SensorFault::Bias { value: 30.0 },
+            },
+        });
+        meta.metrics = control_metrics(&samples);
+        export_samples(&path, &samples, ExportFormat::Jsonl, &meta).unwrap();
+
+        let bytes = fs::read(&path).unwrap();
+        let header = String::from_utf8_lossy(&bytes)
+            .lines()
+            .next()
+            .unwrap()
+            .to_string();
+        assert!(header.contains(r#""plant":{"ambient_c":25.0,"#), "{header}");
+        let injected = r#"{"t_s":0.1,"action":"inject_fault","sensor":"s2","fault":{"kind":"bias","value":30.0}}"#;
+        assert!(header.contains(injected), "{header}");
+        // Still 80 °C below the setpoint: no overshoot, not settled
+        let metrics = r#""metrics":{"overshoot_c":0.0,"overshoot_pct":0.0,"settling_time_s":null,"#;
+        assert!(header.contains(metrics), "{header}");
+        let trace = load_trace_from_bytes("export.jsonl", &bytes).unwrap();
+        // Debug renders NaN alike, where `==` would not
+        assert_eq!(format!("{:?}", trace.samples), format!("{samples:?}"));
+        let reason = trace.first_reason.as_ref().map(RowReason::name);
+        assert_eq!(reason.as_deref(), Some("OverTemp"));
+        let meta = trace.meta.expect("header");
+        assert_eq!(meta.schema, TRACE_SCHEMA);
+        assert_eq!((meta.setpoint, meta.trip_temp), (Some(380.0), Some(390.0)));
+        assert_eq!(meta.plant(), Some(PlantParams::default()));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn exported_csv_has_the_cli_columns() {
+        let samples = [sample(0.0, false), sample(0.15, true)];
+        let dir = export_dir("csv");
+        let path = dir.join("export.csv");
+        let reason = RowReason::Name("OverTemp".into());
+        export_samples(&path, &samples, ExportFormat::Csv, &meta(Some(reason))).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "t_s,setpoint_c,true_temp_c,s1_c,s2_c,s3_c,power,coolant,scram,reason,\
+                 p_term,i_term,d_term,ff_term,out_presat,saturated,ctrl_status",
+                "0,380,300,300.1,,299.9,0.7,0.2,false,,0,0.45,-0.01,0,0.44,false,\"-\"",
+                "0.15,380,300.05,300.25,,299.9,0,0.2,true,\"OverTemp\",\
+                 0.015,0.45,-0.01,0,0.455,true,\"L\"",
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn recordings_are_what_the_cli_writes_for_the_same_config() {
+        let dir = export_dir("record");
+        let mut app = App {
+            scenario: Scenario::Overheat,
+            ..App::default()
+        };
+        app.reset_live();
+        let config = dir.join("config.toml");
+        write_config(&config, &app.sim_config()).unwrap();
+        let cli_trace = dir.join("cli.jsonl");
+        let args = [
+            "cli",
+            "run",
+            "--config",
+            config.to_str().unwrap(),
+            "--summary",
+            "none",
+            "--out",
+            cli_trace.to_str().unwrap(),
+        ];
+        cli::execute(&cli::Cli::try_parse_from(args).unwrap(), &mut Vec::new()).unwrap();
+
+        let path = dir.join("recording.jsonl");
+        let mut recorder = Recorder::create(&path, app.export_meta().unwrap()).unwrap();
+        // The header is on disk before any row
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 1);
+        let mut sim = app.live.clone();
+        sim.running = true;
+        while sim.running {
+            sim.step();
+            if sim.samples.len() == 100 {
+                recorder.append(&std::mem::take(&mut sim.samples)).unwrap();
+            }
+        }
+        recorder.append(&sim.samples).unwrap();
+        recorder.finish(&[]).unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert!(bytes == fs::read(&cli_trace).unwrap(), "recording differs");
+        let (header, rows) = cli::trace::read_trace_with_meta(&path).unwrap();
+        assert_eq!(rows.len(), 1200);
+        assert_eq!(header.unwrap().header.scenario, "overheat");
+
+        // Operator actions are added to the header when the recording ends
+        let events = [OperatorEvent {
+            t_s: 0.1,
+            action: OperatorAction::ManualScram,
+        }];
+        let recorder = Recorder::create(&path, app.export_meta().unwrap()).unwrap();
+        assert_eq!(recorder.finish(&events).unwrap(), 0);
+        let header = fs::read_to_string(&path).unwrap();
+        assert!(
+            header.contains(r#""operator_events":[{"t_s":0.1,"#),
+            "{header}"
+        );
+        assert!(!dir.join("recording.partial").exists());
+
+        let missing = dir.join("no such dir").join("recording.jsonl");
+        let err = Recorder::create(&missing, meta(None)).err().unwrap();
+        assert!(err.starts_with("Failed to record to "), "{err}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn export_paths_pick_the_format_and_report_failures() {
+        assert_eq!(
+            ExportFormat::for_path(Path::new("a.CSV")),
+            ExportFormat::Csv
+        );
+        assert_eq!(
+            ExportFormat::for_path(Path::new("a.jsonl")),
+            ExportFormat::Jsonl
+        );
+        assert_eq!(ExportFormat::for_path(Path::new("a")), ExportFormat::Jsonl);
+        let missing = export_dir("missing").join("no/such/dir/x.jsonl");
+        let err = export_samples(&missing, &[], ExportFormat::Jsonl, &meta(None)).unwrap_err();
+        assert!(err.starts_with("Failed to write "), "{err}");
+    }
+}
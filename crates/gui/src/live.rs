@@ -0,0 +1,1180 @@
+//! Live runs: the simulation the panels drive, stepped on a worker thread (in the browser, between
+//! frames) so the window keeps drawing.
+
+use std::collections::VecDeque;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+use std::time::Duration;
+
+use cli::config::{SensorsConfig, SENSOR_NAMES};
+use cli::Scenario;
+use controller::{Pid, PidConfig, SetpointProfile};
+use eframe::egui;
+use engine::{Simulation, StepEvent};
+use safety::SafetyConfig;
+use sim::{EventAction, PlantParams, ScenarioSpec, Sensor, SensorFault};
+use trace::{RowReason, Sample};
+use web_time::Instant;
+
+use crate::scenario_editor::fault_text;
+use crate::{builtin_label, App, LogEntry, LogKind, OperatorAction, OperatorEvent};
+
+/// Settings a live run reads at every step, so they can change while it runs
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct LiveSettings {
+    pub(crate) setpoint: f64,
+    // Preload the pre-trip power into the controller on SCRAM (otherwise zero)
+    pub(crate) scram_preload_pre_trip: bool,
+    // Keep stepping the plant (power 0) after a SCRAM instead of stopping the run
+    pub(crate) continue_after_scram: bool,
+    // Operator overrides of the actuators (None: automatic)
+    pub(crate) manual_coolant: Option<f64>,
+    pub(crate) manual_power: Option<f64>,
+}
+
+/// A live run: the shared `engine::Simulation` (plant, controller, sensors and safety logic) and
+/// what the GUI keeps around it. The worker thread (`live_worker`) steps it and owns all of its
+/// state, random generators included; the UI shows the copy sent with the last batch.
+#[derive(Clone)]
+pub(crate) struct LiveSim {
+    pub(crate) settings: LiveSettings,
+    pub(crate) running: bool,
+    pub(crate) max_steps: u64,
+    pub(crate) engine: Simulation<Pid>,
+    // A scenario from the editor, run instead of the built-in one
+    custom: Option<Arc<ScenarioSpec>>,
+
+    // Produced since the last batch: samples, "Events" rows and operator actions
+    pub(crate) samples: Vec<Sample>,
+    pub(crate) log: Vec<LogEntry>,
+    events: Vec<OperatorEvent>,
+}
+
+impl LiveSim {
+    /// A run from t = 0 under `scenario`, with default plant parameters and controller gains.
+    pub(crate) fn new(
+        scenario: Scenario,
+        seconds: f64,
+        dt_s: f64,
+        trip_temp: f64,
+        seed: u64,
+        settings: LiveSettings,
+    ) -> Self {
+        let sensors = [seed ^ 0xA1, seed ^ 0xB2, seed ^ 0xC3].map(|seed| {
+            let mut sensor = Sensor::new(seed);
+            sensor.noise_std = 0.15;
+            sensor
+        });
+        let safety_cfg = SafetyConfig {
+            trip_temp_c: trip_temp,
+            ..Default::default()
+        };
+        let mut engine = Simulation::new(
+            scenario.spec(seconds),
+            PlantParams::default(),
+            sensors,
+            safety_cfg,
+            Pid::new(PidConfig::default()),
+            SetpointProfile::constant(settings.setpoint),
+            dt_s,
+        );
+        engine.preload_pre_trip = settings.scram_preload_pre_trip;
+        Self {
+            settings,
+            running: false,
+            max_steps: (seconds / dt_s).ceil() as u64,
+            engine,
+            custom: None,
+            samples: Vec::new(),
+            log: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Time at the start of the next step.
+    pub(crate) fn t(&self) -> f64 {
+        self.engine.time()
+    }
+
+    pub(crate) fn step_count(&self) -> u64 {
+        self.engine.step
+    }
+
+    /// The sensors and safety limits of a configuration file in place of the defaults: noise,
+    /// valid ranges and seeds of the sensors (faults kept), and the limits other than the trip
+    /// temperature.
+    fn configure(&mut self, safety: SafetyConfig, sensors: &SensorsConfig, seed: u64) {
+        self.engine.safety_cfg = SafetyConfig {
+            trip_temp_c: self.engine.safety_cfg.trip_temp_c,
+            ..safety
+        };
+        let seeds = sensors.seeds(seed);
+        for (i, sensor) in self.engine.sensors.iter_mut().enumerate() {
+            let fault = sensor.fault;
+            *sensor = Sensor::new(seeds[i]);
+            sensor.fault = fault;
+            sensors.get(i).apply(sensor);
+        }
+    }
+
+    /// Run `spec` from the scenario editor instead of the built-in scenario: its initial
+    /// conditions now, its faults and events as the run reaches them.
+    pub(crate) fn set_custom(&mut self, spec: Arc<ScenarioSpec>) {
+        self.engine.set_scenario((*spec).clone());
+        self.custom = Some(spec);
+    }
+
+    /// Set a sensor's fault from the next reading on, and log it. The scenario's fault schedules
+    /// only set a fault when they switch, so this one stays until the schedule moves on.
+    pub(crate) fn inject_fault(&mut self, index: usize, fault: SensorFault) {
+        self.engine.sensors[index].fault = fault;
+        self.record(OperatorAction::InjectFault {
+            sensor: SENSOR_NAMES[index],
+            fault,
+        });
+    }
+
+    /// Log an operator action at the current time, for the "Events" panel and exports.
+    pub(crate) fn record(&mut self, action: OperatorAction) {
+        self.log_event(LogKind::Operator, action.to_string());
+        self.events.push(OperatorEvent {
+            t_s: self.t(),
+            action,
+        });
+    }
+
+    fn log_event(&mut self, kind: LogKind, text: impl Into<String>) {
+        self.log.push(LogEntry {
+            t_s: self.t(),
+            kind,
+            text: text.into(),
+        });
+    }
+
+    /// A manual SCRAM from the "SCRAM" button, which the next step handles like any other trip.
+    fn manual_scram(&mut self) {
+        if self.engine.safety.scram {
+            return;
+        }
+        self.record(OperatorAction::ManualScram);
+        self.engine.preload_pre_trip = self.settings.scram_preload_pre_trip;
+        self.engine.manual_scram();
+        let trip = self
+            .engine
+            .safety
+            .trip
+            .map_or("—".to_string(), |t| t.to_string());
+        self.log_event(LogKind::Trip, format!("TRIP {trip}"));
+    }
+
+    /// A copy of the run's state, leaving out (rather than cloning) what it produced since the
+    /// last batch.
+    fn snapshot(&mut self) -> Self {
+        let samples = std::mem::take(&mut self.samples);
+        let log = std::mem::take(&mut self.log);
+        let events = std::mem::take(&mut self.events);
+        let copy = self.clone();
+        (self.samples, self.log, self.events) = (samples, log, events);
+        copy
+    }
+
+    pub(crate) fn step(&mut self) {
+        if self.step_count() >= self.max_steps {
+            self.running = false;
+            return;
+        }
+
+        // The settings may have changed since the last step. Manual power goes through the
+        // controller's manual mode, so going back to auto is bumpless; the operator's coolant
+        // overrides scenario coolant changes.
+        let engine = &mut self.engine;
+        engine.controller.set_manual(self.settings.manual_power);
+        engine.manual_coolant = self.settings.manual_coolant;
+        engine.preload_pre_trip = self.settings.scram_preload_pre_trip;
+        engine.setpoint = SetpointProfile::constant(self.settings.setpoint);
+        let t = engine.time();
+        let sample = engine.step();
+
+        for event in &sample.events {
+            let (kind, text) = match *event {
+                StepEvent::Scenario(action) => {
+                    let text = match action {
+                        EventAction::SetCoolant(coolant) => format!("coolant {coolant}"),
+                        EventAction::SetSetpoint(setpoint) => format!("setpoint {setpoint} °C"),
+                        EventAction::Scram => "SCRAM".to_string(),
+                    };
+                    (LogKind::Scenario, format!("scenario: {text}"))
+                }
+                StepEvent::Fault { sensor, fault } => {
+                    let name = SENSOR_NAMES[sensor];
+                    (
+                        LogKind::Scenario,
+                        format!("scenario: {name} {}", fault_text(&fault)),
+                    )
+                }
+                StepEvent::Alarm(raised) => {
+                    let change = if raised { "raised" } else { "cleared" };
+                    (LogKind::Alarm, format!("alarm {change}: sensors disagree"))
+                }
+                StepEvent::Valid { sensor, valid } => {
+                    let change = if valid { "valid again" } else { "disqualified" };
+                    let count = sample.valid.iter().filter(|v| **v).count();
+                    let name = SENSOR_NAMES[sensor];
+                    (
+                        LogKind::Alarm,
+                        format!("{name} {change} ({count} of 3 valid)"),
+                    )
+                }
+                StepEvent::Trip => {
+                    let trip = sample.trip.map_or("—".to_string(), |t| t.to_string());
+                    (LogKind::Trip, format!("TRIP {trip}"))
+                }
+            };
+            self.log.push(LogEntry { t_s: t, kind, text });
+        }
+
+        let [s1, s2, s3] = sample.readings;
+        let terms = sample.control.terms;
+        let status = sample.control.status;
+        self.samples.push(Sample {
+            t: sample.t_s,
+            true_temp: sample.true_temp_c,
+            s1,
+            s2,
+            s3,
+            power: sample.power,
+            coolant: sample.coolant,
+            scram: sample.scram,
+            setpoint: sample.setpoint_c,
+            p_term: terms.p,
+            i_term: terms.i,
+            d_term: terms.d,
+            saturated: status.saturated_high || status.saturated_low,
+            alarm: sample.alarm,
+            reason: sample.trip.map(|trip| Arc::new(RowReason::Trip(trip))),
+        });
+
+        if sample.scram && !self.settings.continue_after_scram {
+            self.running = false;
+        }
+    }
+}
+
+/// What the comparison run ("B") of A/B mode does differently from the primary run ("A")
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum AbDiff {
+    Seed(u64),
+    Kp(f64),
+    Scenario(Scenario),
+}
+
+impl AbDiff {
+    pub(crate) fn kind(self) -> &'static str {
+        match self {
+            AbDiff::Seed(_) => "seed",
+            AbDiff::Kp(_) => "kp",
+            AbDiff::Scenario(_) => "scenario",
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            AbDiff::Seed(seed) => format!("seed {seed}"),
+            AbDiff::Kp(kp) => format!("kp {kp}"),
+            AbDiff::Scenario(scenario) => builtin_label(scenario).to_string(),
+        }
+    }
+}
+
+/// Which live runs an edit applied during A/B mode changes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum EditTarget {
+    A,
+    B,
+    Both,
+}
+
+/// The live runs the worker steps: the primary one ("A") and, in A/B mode, the comparison run
+/// ("B"), stepped in lockstep with it
+#[derive(Clone)]
+pub(crate) struct LiveRuns {
+    pub(crate) a: LiveSim,
+    pub(crate) b: Option<LiveSim>,
+}
+
+impl LiveRuns {
+    /// One step of A, and of B unless its SCRAM stopped it (A's running decides for both).
+    fn step(&mut self) {
+        self.a.step();
+        if let Some(b) = &mut self.b {
+            if !b.engine.safety.scram || b.settings.continue_after_scram {
+                b.step();
+            }
+        }
+    }
+
+    fn snapshot(&mut self) -> LiveRuns {
+        LiveRuns {
+            a: self.a.snapshot(),
+            b: self.b.as_mut().map(LiveSim::snapshot),
+        }
+    }
+
+    /// The runs an edit for `target` changes.
+    fn targets(&mut self, target: EditTarget) -> impl Iterator<Item = &mut LiveSim> {
+        let a = (target != EditTarget::B).then_some(&mut self.a);
+        let b = self.b.as_mut().filter(|_| target != EditTarget::A);
+        a.into_iter().chain(b)
+    }
+
+    /// Act on a command from the UI (those about the worker itself are handled there). Operator
+    /// actions reach both runs, so only the difference sets them apart; A alone records them.
+    fn apply(&mut self, command: LiveCommand) {
+        match command {
+            LiveCommand::Run(run) => self.targets(EditTarget::Both).for_each(|s| s.running = run),
+            LiveCommand::Step => self.step(),
+            LiveCommand::Settings(settings) => {
+                self.targets(EditTarget::Both).for_each(|s| {
+                    // The operator's setpoint takes over from one a scenario event set
+                    if settings.setpoint != s.settings.setpoint {
+                        s.engine.setpoint_override = None;
+                    }
+                    s.settings = settings;
+                });
+            }
+            LiveCommand::Plant(params, target) => {
+                self.targets(target).for_each(|s| s.engine.plant = params);
+            }
+            LiveCommand::Pid(cfg, target) => {
+                self.targets(target)
+                    .for_each(|s| s.engine.controller.set_config_bumpless(cfg));
+            }
+            LiveCommand::InjectFault(index, fault) => {
+                self.targets(EditTarget::Both)
+                    .for_each(|s| s.inject_fault(index, fault));
+            }
+            LiveCommand::Record(action) => self.a.record(action),
+            LiveCommand::ManualScram => self
+                .targets(EditTarget::Both)
+                .for_each(|s| s.manual_scram()),
+            LiveCommand::Reset(..)
+            | LiveCommand::Speed(_)
+            | LiveCommand::StepBack
+            | LiveCommand::CheckpointEvery(_) => {}
+        }
+    }
+
+    /// A's "Events" rows since the last batch, with B's trips and alarms among them.
+    fn take_log(&mut self) -> Vec<LogEntry> {
+        let mut log = std::mem::take(&mut self.a.log);
+        if let Some(b) = &mut self.b {
+            let b_log = std::mem::take(&mut b.log).into_iter();
+            log.extend(
+                b_log
+                    .filter(|e| matches!(e.kind, LogKind::Trip | LogKind::Alarm))
+                    .map(|e| LogEntry {
+                        text: format!("B: {}", e.text),
+                        ..e
+                    }),
+            );
+            log.sort_by(|x, y| x.t_s.total_cmp(&y.t_s));
+        }
+        log
+    }
+}
+
+/// What the UI asks of the live run's worker thread
+pub(crate) enum LiveCommand {
+    // Start over with these runs; batches carry the generation from then on
+    Reset(u64, Box<LiveRuns>),
+    Run(bool),
+    Step,
+    // Simulated time per wall-clock time; None: as fast as possible
+    Speed(Option<f64>),
+    Settings(LiveSettings),
+    Plant(PlantParams, EditTarget),
+    // Switch to new gains without a bump in the output
+    Pid(PidConfig, EditTarget),
+    InjectFault(usize, SensorFault),
+    Record(OperatorAction),
+    ManualScram,
+    // Go back one step, paused (see `Checkpoints`)
+    StepBack,
+    CheckpointEvery(u64),
+}
+
+impl LiveCommand {
+    /// The command changes the runs other than by stepping them, so stepping forward from an
+    /// earlier snapshot would not get to the state after it.
+    fn edits(&self) -> bool {
+        !matches!(
+            self,
+            LiveCommand::Run(_)
+                | LiveCommand::Step
+                | LiveCommand::Speed(_)
+                | LiveCommand::StepBack
+                | LiveCommand::CheckpointEvery(_)
+        )
+    }
+}
+
+/// What the worker stepped since its last batch, and the runs' state after it
+struct LiveBatch {
+    generation: u64,
+    // Commands the worker had taken in, counted from the start
+    commands_done: u64,
+    samples: Vec<Sample>,
+    // B's samples in A/B mode
+    compare: Vec<Sample>,
+    log: Vec<LogEntry>,
+    events: Vec<OperatorEvent>,
+    runs: Box<LiveRuns>,
+    // The runs went back a step: what they produced from their time on is gone
+    rewound: bool,
+}
+
+/// Longest the worker steps before sending a batch, about one frame
+const LIVE_BATCH: Duration = Duration::from_millis(16);
+
+/// Speed of live runs until changed, as a multiple of realtime
+pub(crate) const LIVE_SPEED: f64 = 10.0;
+
+/// Steps between the worker's snapshots for "Step back" until changed
+pub(crate) const CHECKPOINT_EVERY: u64 = 100;
+
+/// Most snapshots kept; the oldest goes first, which bounds how far back "Step back" reaches
+pub(crate) const CHECKPOINT_RING: usize = 64;
+
+/// Snapshots of the live runs for "Step back": one every `every` steps and one after each edit, so
+/// that stepping forward from the latest one before a step gets to exactly the state the runs had
+/// there (the sensors' random generators included).
+struct Checkpoints {
+    every: u64,
+    // In step order
+    ring: VecDeque<LiveRuns>,
+}
+
+impl Checkpoints {
+    fn new(every: u64) -> Self {
+        Self {
+            every,
+            ring: VecDeque::new(),
+        }
+    }
+
+    /// Snapshot `runs` if an edit just changed them or their step is a multiple of `every`,
+    /// replacing a snapshot of the same step.
+    fn keep(&mut self, runs: &mut LiveRuns, edited: bool) {
+        let step = runs.a.step_count();
+        if !edited && !step.is_multiple_of(self.every) {
+            return;
+        }
+        self.forget_from(step);
+        self.ring.push_back(runs.snapshot());
+        if self.ring.len() > CHECKPOINT_RING {
+            self.ring.pop_front();
+        }
+    }
+
+    /// Drop the snapshots of `step` and later, which a reset or a step back made obsolete.
+    fn forget_from(&mut self, step: u64) {
+        while self.ring.back().is_some_and(|r| r.a.step_count() >= step) {
+            self.ring.pop_back();
+        }
+    }
+
+    /// `runs` as they were one step earlier, paused: the latest snapshot up to that step, stepped
+    /// forward to it. None at the start, or when the step is older than every snapshot kept.
+    fn step_back(&mut self, runs: &LiveRuns) -> Option<LiveRuns> {
+        let target = runs.a.step_count().checked_sub(1)?;
+        if self.ring.front()?.a.step_count() > target {
+            return None;
+        }
+        self.forget_from(target + 1);
+        let mut back = self.ring.back()?.clone();
+        while back.a.step_count() < target {
+            back.step();
+        }
+        // What the steps produced was sent the first time round
+        let mut back = back.snapshot();
+        back.apply(LiveCommand::Run(false));
+        Some(back)
+    }
+}
+
+/// What the live worker carries from one round to the next: the runs, the commands taken in, how
+/// fast the runs go and the snapshots for "Step back"
+struct WorkerLoop {
+    runs: LiveRuns,
+    generation: u64,
+    commands_done: u64,
+    speed: Option<f64>,
+    // Wall-clock time and step count a paced run counts from
+    paced_from: Option<(Instant, u64)>,
+    checkpoints: Checkpoints,
+}
+
+impl WorkerLoop {
+    fn new(mut runs: LiveRuns) -> Self {
+        let mut checkpoints = Checkpoints::new(CHECKPOINT_EVERY);
+        checkpoints.keep(&mut runs, true);
+        Self {
+            runs,
+            generation: 0,
+            commands_done: 0,
+            speed: Some(LIVE_SPEED),
+            paced_from: None,
+            checkpoints,
+        }
+    }
+
+    /// When the next step is due: never while paused, now at full speed.
+    fn next_step(&mut self) -> Option<Instant> {
+        let sim = &self.runs.a;
+        match self.speed {
+            _ if !sim.running => None,
+            None => Some(Instant::now()),
+            Some(speed) => {
+                let (t0, n0) = *self
+                    .paced_from
+                    .get_or_insert((Instant::now(), sim.step_count()));
+                let ahead = (sim.step_count() - n0) as f64 * sim.engine.dt_s / speed;
+                Some(t0 + Duration::from_secs_f64(ahead))
+            }
+        }
+    }
+
+    /// Act on `commands`, then step the runs while steps are due, for up to `LIVE_BATCH`. Returns
+    /// what that produced, or None when there were neither commands nor steps.
+    fn round(&mut self, commands: impl IntoIterator<Item = LiveCommand>) -> Option<LiveBatch> {
+        let mut changed = false;
+        let mut rewound = false;
+        for command in commands {
+            self.commands_done += 1;
+            changed = true;
+            let edits = command.edits();
+            match command {
+                LiveCommand::Reset(g, reset) => {
+                    self.generation = g;
+                    self.runs = *reset;
+                    // Pausing hands the same runs back, whose earlier snapshots still hold
+                    self.checkpoints.forget_from(self.runs.a.step_count());
+                    self.paced_from = None;
+                }
+                LiveCommand::StepBack => {
+                    if let Some(back) = self.checkpoints.step_back(&self.runs) {
+                        self.runs = back;
+                        rewound = true;
+                    }
+                    self.paced_from = None;
+                }
+                LiveCommand::CheckpointEvery(every) => self.checkpoints.every = every.max(1),
+                LiveCommand::Speed(s) => {
+                    self.speed = s;
+                    self.paced_from = None;
+                }
+                LiveCommand::Run(run) => {
+                    self.runs.apply(LiveCommand::Run(run));
+                    self.paced_from = None;
+                }
+                command => self.runs.apply(command),
+            }
+            self.checkpoints.keep(&mut self.runs, edits);
+        }
+
+        let deadline = Instant::now() + LIVE_BATCH;
+        let before = self.runs.a.step_count();
+        while let Some(due) = self.next_step() {
+            let now = Instant::now();
+            if due > now || now >= deadline {
+                break;
+            }
+            self.runs.step();
+            self.checkpoints.keep(&mut self.runs, false);
+        }
+
+        if !changed && self.runs.a.step_count() == before {
+            return None;
+        }
+        let runs = &mut self.runs;
+        let compare = runs.b.as_mut().map(|b| std::mem::take(&mut b.samples));
+        Some(LiveBatch {
+            generation: self.generation,
+            commands_done: self.commands_done,
+            samples: std::mem::take(&mut runs.a.samples),
+            compare: compare.unwrap_or_default(),
+            log: runs.take_log(),
+            events: std::mem::take(&mut runs.a.events),
+            runs: Box::new(runs.clone()),
+            rewound,
+        })
+    }
+}
+
+/// Step `runs` on the calling thread: run, pause or change them as `commands` say, and send what
+/// they produce to `batches` at least every `LIVE_BATCH` while running (and after every command).
+/// Returns when the UI hangs up.
+#[cfg(not(target_arch = "wasm32"))]
+fn live_worker(runs: LiveRuns, commands: Receiver<LiveCommand>, batches: Sender<LiveBatch>) {
+    let mut worker = WorkerLoop::new(runs);
+    loop {
+        let first = match worker.next_step() {
+            None => match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => return,
+            },
+            Some(due) => match commands.recv_timeout(due.saturating_duration_since(Instant::now()))
+            {
+                Ok(command) => Some(command),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => return,
+            },
+        };
+        let queued = std::iter::from_fn(|| commands.try_recv().ok());
+        if let Some(batch) = worker.round(first.into_iter().chain(queued)) {
+            if batches.send(batch).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// The UI's end of the live run's worker thread. Browsers give a page no threads, so there the
+/// worker's rounds run on the UI thread, one per frame, as the batches are drained.
+pub(crate) struct LiveWorker {
+    #[cfg(not(target_arch = "wasm32"))]
+    commands: Sender<LiveCommand>,
+    #[cfg(not(target_arch = "wasm32"))]
+    batches: Receiver<LiveBatch>,
+    #[cfg(target_arch = "wasm32")]
+    worker: WorkerLoop,
+    #[cfg(target_arch = "wasm32")]
+    queued: Vec<LiveCommand>,
+    // Bumped by every reset; batches of earlier runs are dropped
+    generation: u64,
+    // Commands sent so far, to tell when a batch reflects all of them
+    sent: u64,
+    // Settings and speed last sent, to send only changes
+    settings: LiveSettings,
+    speed: Option<f64>,
+    checkpoint_every: u64,
+}
+
+impl LiveWorker {
+    pub(crate) fn spawn(runs: LiveRuns) -> Self {
+        let settings = runs.a.settings;
+        #[cfg(not(target_arch = "wasm32"))]
+        let (commands, batches) = {
+            let (commands, worker_commands) = mpsc::channel();
+            let (worker_batches, batches) = mpsc::channel();
+            thread::spawn(move || live_worker(runs, worker_commands, worker_batches));
+            (commands, batches)
+        };
+        Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            commands,
+            #[cfg(not(target_arch = "wasm32"))]
+            batches,
+            #[cfg(target_arch = "wasm32")]
+            worker: WorkerLoop::new(runs),
+            #[cfg(target_arch = "wasm32")]
+            queued: Vec::new(),
+            generation: 0,
+            sent: 0,
+            settings,
+            speed: Some(LIVE_SPEED),
+            checkpoint_every: CHECKPOINT_EVERY,
+        }
+    }
+
+    pub(crate) fn send(&mut self, command: LiveCommand) {
+        // The worker only stops when this end is dropped
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = self.commands.send(command);
+        #[cfg(target_arch = "wasm32")]
+        self.queued.push(command);
+        self.sent += 1;
+    }
+
+    /// Replace the runs; nothing of the old ones arrives from now on.
+    fn reset(&mut self, runs: LiveRuns) {
+        self.generation += 1;
+        self.settings = runs.a.settings;
+        self.send(LiveCommand::Reset(self.generation, Box::new(runs)));
+    }
+
+    fn send_settings(&mut self, settings: LiveSettings) {
+        if settings != self.settings {
+            self.settings = settings;
+            self.send(LiveCommand::Settings(settings));
+        }
+    }
+
+    fn send_speed(&mut self, speed: Option<f64>) {
+        if speed != self.speed {
+            self.speed = speed;
+            self.send(LiveCommand::Speed(speed));
+        }
+    }
+
+    fn send_checkpoint_every(&mut self, every: u64) {
+        if every != self.checkpoint_every {
+            self.checkpoint_every = every;
+            self.send(LiveCommand::CheckpointEvery(every));
+        }
+    }
+
+    /// The batches of the current run that arrived since the last call.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn drain(&mut self) -> Vec<LiveBatch> {
+        let generation = self.generation;
+        self.batches
+            .try_iter()
+            .filter(|batch| batch.generation == generation)
+            .collect()
+    }
+
+    /// One round of the worker with the commands sent since the last call, and what it produced.
+    #[cfg(target_arch = "wasm32")]
+    fn drain(&mut self) -> Vec<LiveBatch> {
+        let queued = std::mem::take(&mut self.queued);
+        self.worker.round(queued).into_iter().collect()
+    }
+
+    /// The batch reflects every command sent so far.
+    fn caught_up(&self, batch: &LiveBatch) -> bool {
+        batch.commands_done == self.sent
+    }
+}
+
+impl App {
+    /// Drop what the live runs produced from the step they went back to on. Operator actions at
+    /// that step stay: the runs' state includes them.
+    fn rewind_to(&mut self, runs: &LiveRuns) {
+        let t = runs.a.t();
+        self.samples
+            .truncate(self.samples.partition_point(|s| s.t < t));
+        let t_b = runs.b.as_ref().map_or(t, |b| b.t());
+        self.compare_all
+            .truncate(self.compare_all.partition_point(|s| s.t < t_b));
+        self.log.retain(|e| e.t_s < t);
+        self.events.retain(|e| e.t_s <= t);
+        self.t = self.samples.last().map_or(0.0, |s| s.t);
+    }
+
+    pub(crate) fn reset_live(&mut self) {
+        self.stop_recording();
+        self.running = false;
+        self.t = 0.0;
+        self.manual_coolant = None;
+        self.manual_power = None;
+        let mut sim = LiveSim::new(
+            self.scenario,
+            self.seconds,
+            self.dt_ms as f64 / 1000.0,
+            self.trip_temp,
+            self.seed,
+            self.live_settings(),
+        );
+        if let Some(spec) = &self.custom_scenario {
+            sim.set_custom(spec.clone());
+        }
+
+        // Invalid edits are shown next to the fields; the run keeps the last valid parameters and
+        // gains
+        if self.plant_edit.validate().is_ok() {
+            sim.engine.plant = self.plant_edit;
+        } else {
+            sim.engine.plant = self.live.engine.plant;
+        }
+        let pid_cfg = if self.pid_edit.validate().is_ok() {
+            self.pid_edit
+        } else {
+            *self.live.engine.controller.config()
+        };
+        sim.engine.controller = Pid::new(pid_cfg);
+        sim.configure(self.safety, &self.sensors, self.seed);
+        let b = self.ab_diff.map(|diff| {
+            let (scenario, seed, kp) = match diff {
+                AbDiff::Seed(seed) => (self.scenario, seed, pid_cfg.kp),
+                AbDiff::Kp(kp) => (self.scenario, self.seed, kp),
+                AbDiff::Scenario(scenario) => (scenario, self.seed, pid_cfg.kp),
+            };
+            let mut b = LiveSim::new(
+                scenario,
+                self.seconds,
+                sim.engine.dt_s,
+                self.trip_temp,
+                seed,
+                sim.settings,
+            );
+            if let (Some(spec), false) = (&sim.custom, matches!(diff, AbDiff::Scenario(_))) {
+                b.set_custom(spec.clone());
+            }
+            b.engine.plant = sim.engine.plant;
+            b.engine.controller = Pid::new(PidConfig { kp, ..pid_cfg });
+            b.configure(self.safety, &self.sensors, seed);
+            b
+        });
+        self.worker.reset(LiveRuns {
+            a: sim.clone(),
+            b: b.clone(),
+        });
+        self.live = sim;
+        self.live_b = b;
+
+        if let Some(diff) = self.ab_diff {
+            self.clear_compare();
+            self.compare_path = Some(format!("live run, {}", diff.label()));
+            self.compare_live = true;
+        } else if self.compare_live {
+            self.clear_compare();
+        }
+
+        self.samples.clear();
+        self.plot_data.invalidate();
+        self.events.clear();
+        self.log.clear();
+        self.cursor_t = None;
+    }
+
+    /// The settings the live run reads at every step, as the panels have them.
+    fn live_settings(&self) -> LiveSettings {
+        LiveSettings {
+            setpoint: self.setpoint,
+            scram_preload_pre_trip: self.scram_preload_pre_trip,
+            continue_after_scram: self.continue_after_scram,
+            manual_coolant: self.manual_coolant,
+            manual_power: self.manual_power,
+        }
+    }
+
+    /// Stop the live run where it is, dropping whatever the worker has not delivered yet.
+    pub(crate) fn stop_live(&mut self) {
+        self.stop_recording();
+        self.running = false;
+        let mut runs = LiveRuns {
+            a: self.live.clone(),
+            b: self.live_b.clone(),
+        };
+        runs.apply(LiveCommand::Run(false));
+        self.worker.reset(runs);
+    }
+
+    /// Take in what the worker stepped since the last frame.
+    fn drain_live(&mut self) {
+        for batch in self.worker.drain() {
+            let caught_up = self.worker.caught_up(&batch);
+            if caught_up {
+                self.running = batch.runs.a.running;
+            }
+            if batch.rewound && !self.replay_loaded {
+                self.rewind_to(&batch.runs);
+            }
+            if !self.replay_loaded {
+                if let Some(recorder) = &mut self.recorder {
+                    if let Err(e) = recorder.append(&batch.samples) {
+                        self.last_error = Some(e);
+                        self.recorder = None;
+                    }
+                }
+                self.samples.extend(batch.samples);
+                self.t = self.samples.last().map_or(0.0, |s| s.t);
+            }
+            if self.compare_live {
+                self.compare_all.extend(batch.compare);
+            }
+            self.log.extend(batch.log);
+            self.events.extend(batch.events);
+            let LiveRuns { a, b } = *batch.runs;
+            // Paused, tripped or at its end: the recording is complete
+            if caught_up && !a.running {
+                self.stop_recording();
+            }
+            self.live = a;
+            self.live_b = b;
+        }
+    }
+
+    /// The "SCRAM" button: trip the live run at its next step.
+    pub(crate) fn manual_scram(&mut self) {
+        if self.replay_loaded || self.live.engine.safety.scram {
+            return;
+        }
+        self.worker.send(LiveCommand::ManualScram);
+    }
+
+    /// "Run" starts the live run over; "Pause" stops it where it is.
+    pub(crate) fn toggle_run(&mut self) {
+        if !self.running {
+            self.clear_replay();
+            self.reset_live();
+            self.running = true;
+            self.start_recording();
+        } else {
+            self.running = false;
+        }
+        self.worker.send(LiveCommand::Run(self.running));
+    }
+
+    /// "Step": one step of the live run.
+    pub(crate) fn step_live(&mut self) {
+        self.worker.send(LiveCommand::Step);
+    }
+
+    /// "Step back": the live run as it was one step earlier, paused.
+    pub(crate) fn step_back_live(&mut self) {
+        if !self.replay_loaded {
+            self.worker.send(LiveCommand::StepBack);
+        }
+    }
+
+    /// The worker steps live runs; show what it sent, and send the panels' latest settings.
+    pub(crate) fn sync_live(&mut self, ctx: &egui::Context) {
+        self.worker.send_settings(self.live_settings());
+        let speed = (!self.live_max_speed).then_some(self.live_speed);
+        self.worker.send_speed(speed);
+        self.worker.send_checkpoint_every(self.checkpoint_every);
+        self.drain_live();
+        if self.running {
+            ctx.request_repaint();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    use crate::tests::live_sim;
+
+    fn solo(a: LiveSim) -> LiveRuns {
+        LiveRuns { a, b: None }
+    }
+
+    /// Batches of the current run until one that reflects every command sent and passes `done`.
+    fn batches_until(worker: &mut LiveWorker, done: impl Fn(&LiveBatch) -> bool) -> Vec<LiveBatch> {
+        let give_up = Instant::now() + Duration::from_secs(30);
+        let mut batches = Vec::new();
+        loop {
+            for batch in worker.drain() {
+                let last = worker.caught_up(&batch) && done(&batch);
+                batches.push(batch);
+                if last {
+                    return batches;
+                }
+            }
+            assert!(Instant::now() < give_up, "worker stalled");
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn worker_steps_the_run_like_stepping_it_directly() {
+        let mut direct = live_sim(Scenario::Overheat);
+        direct.running = true;
+        let mut expected = Vec::new();
+        while direct.running {
+            direct.step();
+            expected.append(&mut direct.samples);
+        }
+        assert_eq!(expected.len(), 2000);
+
+        let mut worker = LiveWorker::spawn(solo(live_sim(Scenario::Overheat)));
+        worker.send_speed(None);
+        worker.send(LiveCommand::Run(true));
+        let batches = batches_until(&mut worker, |batch| !batch.runs.a.running);
+        let stepped: Vec<Sample> = batches.into_iter().flat_map(|b| b.samples).collect();
+        assert_eq!(format!("{stepped:?}"), format!("{expected:?}"));
+    }
+
+    #[test]
+    fn b_runs_in_lockstep_with_a_until_its_trip() {
+        let ab = |kp: f64| {
+            let mut sim = live_sim(Scenario::Overheat);
+            sim.engine.safety_cfg.trip_temp_c = 379.0;
+            sim.engine.controller = Pid::new(PidConfig {
+                kp,
+                ..PidConfig::default()
+            });
+            sim
+        };
+        let mut direct = ab(0.2);
+        direct.running = true;
+        let mut expected = Vec::new();
+        while direct.running {
+            direct.step();
+            expected.append(&mut direct.samples);
+        }
+        assert!(direct.engine.safety.scram);
+
+        // A runs on past its own trip, so B's comes first
+        let mut a = ab(0.0);
+        a.settings.continue_after_scram = true;
+        let mut worker = LiveWorker::spawn(LiveRuns {
+            a,
+            b: Some(ab(0.2)),
+        });
+        worker.send_speed(None);
+        worker.send(LiveCommand::Run(true));
+        let batches = batches_until(&mut worker, |batch| !batch.runs.a.running);
+        let last = batches.last().unwrap();
+        assert_eq!(last.runs.a.step_count(), 2000);
+        assert!(direct.step_count() < 2000);
+        assert_eq!(
+            last.runs.b.as_ref().unwrap().step_count(),
+            direct.step_count()
+        );
+        let log: Vec<&str> = batches
+            .iter()
+            .flat_map(|b| &b.log)
+            .map(|e| e.text.as_str())
+            .collect();
+        assert!(log.iter().any(|e| e.starts_with("B: TRIP")), "{log:?}");
+        let compare: Vec<Sample> = batches.into_iter().flat_map(|b| b.compare).collect();
+        assert_eq!(format!("{compare:?}"), format!("{expected:?}"));
+    }
+
+    #[test]
+    fn reset_sets_up_b_as_the_comparison() {
+        let mut app = App {
+            ab_diff: Some(AbDiff::Seed(7)),
+            ..App::default()
+        };
+        app.reset_live();
+        assert!(app.live_b.is_some());
+        assert!(app.compare_live);
+        assert_eq!(app.compare_path.as_deref(), Some("live run, seed 7"));
+
+        app.ab_diff = Some(AbDiff::Kp(0.05));
+        app.reset_live();
+        assert_eq!(
+            app.live_b.as_ref().map(|b| b.engine.controller.config().kp),
+            Some(0.05)
+        );
+        assert_eq!(
+            app.live_b.as_ref().map(|b| b.engine.scenario()),
+            Some(app.live.engine.scenario())
+        );
+
+        app.ab_diff = Some(AbDiff::Scenario(Scenario::LossOfCooling));
+        app.reset_live();
+        let b = app.live_b.as_ref().unwrap();
+        assert_eq!(b.engine.scenario().name, "loss-of-cooling");
+        assert_eq!(
+            b.engine.controller.config().kp,
+            app.live.engine.controller.config().kp
+        );
+
+        app.ab_diff = None;
+        app.reset_live();
+        assert!(app.live_b.is_none());
+        assert_eq!(
+            (app.compare_path.as_deref(), app.compare_live),
+            (None, false)
+        );
+    }
+
+    #[test]
+    fn reset_drops_what_the_old_run_still_sends() {
+        let mut worker = LiveWorker::spawn(solo(live_sim(Scenario::Normal)));
+        worker.send_speed(None);
+        worker.send(LiveCommand::Run(true));
+        batches_until(&mut worker, |batch| batch.runs.a.step_count() > 100);
+
+        worker.reset(solo(live_sim(Scenario::Normal)));
+        let batches = batches_until(&mut worker, |_| true);
+        assert!(batches.iter().all(|b| b.samples.is_empty()));
+        let sim = &batches.last().unwrap().runs.a;
+        assert_eq!((sim.step_count(), sim.running), (0, false));
+    }
+
+    #[test]
+    fn a_paused_run_sends_nothing() {
+        let mut worker = LiveWorker::spawn(solo(live_sim(Scenario::Normal)));
+        worker.send(LiveCommand::Run(true));
+        batches_until(&mut worker, |batch| batch.runs.a.step_count() > 0);
+        worker.send(LiveCommand::Run(false));
+        let paused_at = batches_until(&mut worker, |_| true)
+            .pop()
+            .unwrap()
+            .runs
+            .a
+            .step_count();
+
+        thread::sleep(Duration::from_millis(100));
+        assert!(worker.drain().is_empty());
+        worker.send(LiveCommand::Step);
+        let stepped = batches_until(&mut worker, |_| true).pop().unwrap();
+        assert_eq!(stepped.runs.a.step_count(), paused_at + 1);
+        assert_eq!(stepped.samples.len(), 1);
+    }
+
+    /// Take in the worker's batches until the live run is at `step`, with a sample for each step.
+    fn settle(app: &mut App, step: u64) {
+        let give_up = Instant::now() + Duration::from_secs(30);
+        while app.live.step_count() != step || app.samples.len() as u64 != step {
+            assert!(Instant::now() < give_up, "worker stalled");
+            thread::sleep(Duration::from_millis(1));
+            app.drain_live();
+        }
+    }
+
+    #[test]
+    fn stepping_back_and_forward_again_repeats_the_run() {
+        let mut app = App::default();
+        app.worker.send_checkpoint_every(25);
+        for _ in 0..80 {
+            app.step_live();
+        }
+        // An edit between two periodic snapshots gets one of its own
+        let settings = LiveSettings {
+            setpoint: 390.0,
+            ..app.live.settings
+        };
+        app.worker.send_settings(settings);
+        for _ in 0..20 {
+            app.step_live();
+        }
+        settle(&mut app, 100);
+        let original: Vec<String> = app.samples.iter().map(|s| format!("{s:?}")).collect();
+
+        for _ in 0..10 {
+            app.step_back_live();
+        }
+        settle(&mut app, 90);
+        assert!(!app.running);
+        assert_eq!(app.live.settings.setpoint, 390.0);
+        for _ in 0..10 {
+            app.step_live();
+        }
+        settle(&mut app, 100);
+        let again: Vec<String> = app.samples.iter().map(|s| format!("{s:?}")).collect();
+        assert_eq!(again, original);
+    }
+
+    #[test]
+    fn step_back_reaches_only_as_far_as_the_snapshots() {
+        let mut runs = solo(live_sim(Scenario::Normal));
+        let mut checkpoints = Checkpoints::new(10);
+        checkpoints.keep(&mut runs, true);
+        assert!(checkpoints.step_back(&runs).is_none());
+        for _ in 0..(CHECKPOINT_RING as u64 + 1) * 10 {
+            runs.step();
+            checkpoints.keep(&mut runs, false);
+        }
+        assert_eq!(checkpoints.ring.len(), CHECKPOINT_RING);
+        let back = checkpoints.step_back(&runs).unwrap();
+        assert_eq!(back.a.step_count(), runs.a.step_count() - 1);
+        assert!(back.a.samples.is_empty());
+
+        // The oldest snapshot is at step 20 now
+        let mut early = solo(live_sim(Scenario::Normal));
+        (0..15).for_each(|_| early.step());
+        assert!(checkpoints.step_back(&early).is_none());
+    }
+}
@@ -1,57 +1,55 @@
-use controller::{Pid, PidConfig};
-use eframe::egui;
-use egui_plot::{Line, Plot, PlotPoints};
-use safety::{SafetyConfig, SafetyState};
-use serde::Deserialize;
-use serde_json::Value;
-use sim::{PlantParams, PlantState, Sensor, SensorFault};
 use std::fs;
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Scenario {
-    Normal,
-    Overheat,
-    LossOfCooling,
-    SensorDisagree,
-}
-
-impl Scenario {
-    fn label(self) -> &'static str {
-        match self {
-            Scenario::Normal => "Normal",
-            Scenario::Overheat => "Overheat (low cooling)",
-            Scenario::LossOfCooling => "Loss of cooling (after 30%)",
-            Scenario::SensorDisagree => "Sensor disagree (bias on sensor 2)",
-        }
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+use clap::Parser;
+use cli::config::{SensorsConfig, SimConfig};
+use cli::Scenario;
+use controller::PidConfig;
+use eframe::egui;
+use safety::{SafetyConfig, TripRecord};
+use serde::Serialize;
+use sim::{PlantParams, ScenarioSpec, SensorFault};
+use trace::{RowReason, Sample};
+
+mod alarms;
+mod controls;
+mod export;
+mod live;
+mod plots;
+mod png;
+mod replay;
+mod scenario_editor;
+
+use alarms::Annunciator;
+use export::Recorder;
+use live::{
+    AbDiff, EditTarget, LiveCommand, LiveRuns, LiveSettings, LiveSim, LiveWorker, CHECKPOINT_EVERY,
+    LIVE_SPEED,
+};
+use plots::{PlotData, PlotLayout, SeriesVisibility, DEFAULT_PLOT_POINTS, LAYOUT_KEY};
+use png::PNG_SIZE;
+#[cfg(not(target_arch = "wasm32"))]
+use png::{render_trace, PngPane};
+use replay::{drop_hint, TraceLoading};
+use scenario_editor::fault_text;
+
+/// Menu label of a built-in scenario.
+fn builtin_label(scenario: Scenario) -> &'static str {
+    match scenario {
+        Scenario::Normal => "Normal",
+        Scenario::Overheat => "Overheat (low cooling)",
+        Scenario::LossOfCooling => "Loss of cooling (after 30%)",
+        Scenario::SensorDisagree => "Sensor disagree (bias on sensor 2)",
     }
 }
 
-#[derive(Clone, Debug)]
-struct Sample {
-    t: f64,
-    true_temp: f64,
-    s1: f64,
-    s2: f64,
-    s3: f64,
-    power: f64,
-    coolant: f64,
-    scram: bool,
-}
-
-#[derive(Debug, Deserialize)]
-struct CliLine {
-    t_s: f64,
-    true_temp_c: f64,
-    s1_c: f64,
-    s2_c: f64,
-    s3_c: f64,
-    power: f64,
-    coolant: f64,
-    scram: bool,
-    // JSONL: null al inicio, luego puede ser string u objeto
-    reason: Option<Value>,
-}
-
 struct App {
     // Settings
     scenario: Scenario,
@@ -60,37 +58,320 @@ struct App {
     setpoint: f64,
     trip_temp: f64,
     seed: u64,
-
-    // Live simulation state
+    // Preload the pre-trip power into the controller on SCRAM (otherwise zero)
+    scram_preload_pre_trip: bool,
+    // Keep stepping the plant (power 0) after a SCRAM instead of stopping the run
+    continue_after_scram: bool,
+    // Controller gains and limits being edited; used from the next Reset, or at once on "Apply now"
+    pid_edit: PidConfig,
+    // Apply the edited gains to the running controller as they are dragged
+    pid_apply_live: bool,
+    // Plant parameters being edited; used from the next Reset, or at once with `plant_apply_live`
+    plant_edit: PlantParams,
+    plant_apply_live: bool,
+    // Scenario from the editor that live runs use instead of `scenario` (None: the built-in one)
+    custom_scenario: Option<Arc<ScenarioSpec>>,
+    // Scenario editor: the spec being edited, whether the window shows, its file dialogs and the
+    // file it was last loaded from or saved to
+    scenario_edit: ScenarioSpec,
+    show_scenario_editor: bool,
+    scenario_open_dialog: Option<Receiver<Option<PathBuf>>>,
+    // Sensor settings and safety limits besides the trip temperature, as in the CLI's config
+    // files (`[sensors]`, `[safety]`); used from the next Reset
+    sensors: SensorsConfig,
+    safety: SafetyConfig,
+    // Dialogs of "Load config…" and "Save config…" while showing, and the last file of either
+    config_open_dialog: Option<Receiver<Option<PathBuf>>>,
+    config_save_dialog: Option<Receiver<Option<PathBuf>>>,
+    config_path: Option<PathBuf>,
+    scenario_save_dialog: Option<Receiver<Option<PathBuf>>>,
+    scenario_path: Option<PathBuf>,
+
+    // Live simulation: the state sent with the worker's last batch, and whether the run is
+    // running (as last asked, until the worker has caught up)
+    live: LiveSim,
+    // The comparison run, in A/B mode
+    live_b: Option<LiveSim>,
+    worker: LiveWorker,
     running: bool,
+    // Speed of live runs as a multiple of realtime, unless as fast as possible
+    live_speed: f64,
+    live_max_speed: bool,
+    // Steps between the snapshots "Step back" goes back from
+    checkpoint_every: u64,
+    // Time of the last sample shown
     t: f64,
-    dt_s: f64,
-    max_steps: u64,
-    step_count: u64,
-
-    plant_p: PlantParams,
-    plant_x: PlantState,
-    pid: Pid,
-    safety_cfg: SafetyConfig,
-    safety_state: SafetyState,
-    s1: Sensor,
-    s2: Sensor,
-    s3: Sensor,
 
     // Data shown in plots
     samples: Vec<Sample>,
+    // Series shown in the temperature plot; kept across resets
+    visible: SeriesVisibility,
+    // Plot panes shown and their arrangement
+    layout: PlotLayout,
+    // Decimated plot lines of `samples` and of the comparison, and the points per line
+    plot_data: PlotData,
+    compare_plot: PlotData,
+    plot_max_points: usize,
+    // Margin gauge colours: amber and red from these fractions of the setpoint-to-trip span
+    gauge_amber: f64,
+    gauge_red: f64,
+    // Follow mode: plot only the last `follow_window_s` seconds
+    follow: bool,
+    follow_window_s: f64,
+    // Give the plots their automatic bounds back (after following)
+    plot_reset: bool,
+    // Pause on reaching a trip and zoom the plots around it
+    pause_on_trip: bool,
+    // Time of the trip the plots are zoomed to, and of the last trip seen (each zooms once)
+    trip_focus: Option<f64>,
+    trip_seen: Option<f64>,
+    // Tiles of the live run's annunciator, with the operator's acknowledgements
+    annunciator: Annunciator,
+    // Lower and upper corner of what the temperature plot showed last frame
+    plot_view: Option<([f64; 2], [f64; 2])>,
+    // Operator actions of the live run (for exported headers)
+    events: Vec<OperatorEvent>,
+    // The "Events" panel of the live run, and of the loaded replay
+    log: Vec<LogEntry>,
+    replay_log: Vec<LogEntry>,
+    // Time picked in the "Events" panel of a live run, drawn as a line on the plots
+    cursor_t: Option<f64>,
+    // Fault being set up for each sensor in the "Faults" panel
+    fault_edit: [SensorFault; 3],
+    // Operator overrides of the actuators (None: automatic)
+    manual_coolant: Option<f64>,
+    manual_power: Option<f64>,
 
     // Replay
     replay_loaded: bool,
     replay_path: String,
     replay_all: Vec<Sample>,
+    // The rest of a trace file still being parsed
+    replay_loading: Option<TraceLoading>,
     replay_pos: usize,
     replay_playing: bool,
+    // Playback speed as a multiple of realtime (trace time per wall-clock time)
+    replay_multiplier: f64,
+    // "max ×": fast-forward `replay_speed` samples per frame instead, regardless of time
+    replay_max_speed: bool,
     replay_speed: usize, // samples per frame
-    replay_reason: Option<String>,
+    // Trace time playback has reached; samples at or before it are shown
+    replay_clock: f64,
+    // Reason of the trace's first tripped row, for exported rows that have none
+    replay_reason: Option<RowReason>,
+    // Reference lines from the trace header (None for legacy traces)
+    replay_setpoint: Option<f64>,
+    replay_trip_temp: Option<f64>,
+    // Plant parameters from the trace header, for the heat flows (None: not recorded)
+    replay_plant: Option<PlantParams>,
+    // Reference lines typed in for replays whose header lacks them (legacy traces)
+    legacy_setpoint: Option<f64>,
+    legacy_trip_temp: Option<f64>,
+    // Open-file dialog of "Browse…" while it is showing
+    open_dialog: Option<Receiver<Option<PathBuf>>>,
+    // Comparison trace ("B") drawn over the primary one, time-aligned by t_s
+    compare_path: Option<String>,
+    compare_all: Vec<Sample>,
+    compare_reason: Option<RowReason>,
+    // Seconds added to the comparison's times, to line up different trip times
+    compare_offset: f64,
+    // The comparison is the live A/B run rather than a loaded trace
+    compare_live: bool,
+    // A/B mode: what B does differently (None: no comparison run), and the runs "Apply now" and
+    // live plant edits change
+    ab_diff: Option<AbDiff>,
+    edit_target: EditTarget,
+    compare_dialog: Option<Receiver<Option<PathBuf>>>,
+    // Files of the last drop that were not loaded
+    drop_notice: Option<String>,
+    // Save-file dialog of "Export…" while it is showing
+    save_dialog: Option<Receiver<Option<PathBuf>>>,
+    // In replay, export the whole trace rather than the samples shown so far
+    export_full_replay: bool,
+    // Outcome of the last export that succeeded (failures go to `last_error`)
+    export_notice: Option<String>,
+    // "Record to file": write the next live runs to `record_path` as they run
+    record: bool,
+    record_path: String,
+    recorder: Option<Recorder>,
+    // The "?" list of keyboard shortcuts is showing
+    show_shortcuts: bool,
+    // Save-file dialog of "Export plot PNG…", and the image size in pixels
+    png_dialog: Option<Receiver<Option<PathBuf>>>,
+    png_size: [u32; 2],
     last_error: Option<String>,
 }
 
+/// Show a native file dialog without blocking the UI: the dialog's future is awaited on a helper
+/// thread, which sends the chosen path (None when cancelled) for `update` to poll.
+#[cfg(not(target_arch = "wasm32"))]
+fn spawn_dialog(
+    dialog: impl Future<Output = Option<rfd::FileHandle>> + Send + 'static,
+) -> Receiver<Option<PathBuf>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let picked = pollster::block_on(dialog).map(|file| file.path().to_path_buf());
+        let _ = tx.send(picked);
+    });
+    rx
+}
+
+/// Browser files have no path to go back to, so no dialog is shown there (`HAS_FILES` hides the
+/// buttons that would ask for one): the receiver reports a cancel.
+#[cfg(target_arch = "wasm32")]
+fn spawn_dialog(
+    _dialog: impl Future<Output = Option<rfd::FileHandle>> + 'static,
+) -> Receiver<Option<PathBuf>> {
+    let (tx, rx) = mpsc::channel();
+    let _ = tx.send(None);
+    rx
+}
+
+/// Whether files can be read and written by path: not in the browser, where traces come in by
+/// drag and drop and the controls for files are hidden
+const HAS_FILES: bool = cfg!(not(target_arch = "wasm32"));
+
+/// How often a pending file dialog is checked while the UI is otherwise idle
+const DIALOG_POLL: Duration = Duration::from_millis(100);
+
+/// A CLI configuration file, read and checked by the CLI's own code (`[scenarios.<name>]`
+/// overrides of the scenario it runs merged in, as `run --config` does).
+fn read_config(path: &Path) -> Result<SimConfig, String> {
+    let cfg = SimConfig::load(path).map_err(|e| format!("{e:#}"))?;
+    let cfg = cfg
+        .for_scenario(&cfg.run.scenario)
+        .map_err(|e| format!("in config {}: {e:#}", path.display()))?;
+    let problems = cfg.problems();
+    if !problems.is_empty() {
+        return Err(format!(
+            "in config {}: {}",
+            path.display(),
+            problems.join("; ")
+        ));
+    }
+    Ok(cfg)
+}
+
+fn write_config(path: &Path, cfg: &SimConfig) -> Result<(), String> {
+    let fail = |e: &dyn std::fmt::Display| format!("Failed to write {}: {e}", path.display());
+    let text = cfg.to_toml().map_err(|e| fail(&format!("{e:#}")))?;
+    fs::write(path, text).map_err(|e| fail(&e))
+}
+
+/// An operator action during a live run, shown in the event log and written to exported headers
+#[derive(Clone, Debug, Serialize)]
+struct OperatorEvent {
+    t_s: f64,
+    #[serde(flatten)]
+    action: OperatorAction,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum OperatorAction {
+    /// A fault set on a sensor from the "Faults" panel (`None` clears it)
+    InjectFault {
+        sensor: &'static str,
+        fault: SensorFault,
+    },
+    /// The "SCRAM" button
+    ManualScram,
+    /// Coolant fraction set from the "Operator" panel (None: back to automatic)
+    SetCoolant { value: Option<f64> },
+    /// Power fraction set from the "Operator" panel (None: back to the controller)
+    SetPower { value: Option<f64> },
+    /// An annunciator tile acknowledged (`Alarm::key`)
+    Acknowledge { alarm: &'static str },
+}
+
+impl std::fmt::Display for OperatorEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "t={:.2}s  {}", self.t_s, self.action)
+    }
+}
+
+impl std::fmt::Display for OperatorAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OperatorAction::InjectFault {
+                sensor,
+                fault: SensorFault::None,
+            } => write!(f, "fault cleared {sensor}"),
+            OperatorAction::InjectFault { sensor, fault } => {
+                write!(f, "fault injected {sensor} {}", fault_text(fault))
+            }
+            OperatorAction::ManualScram => f.write_str("manual SCRAM"),
+            OperatorAction::SetCoolant { value: Some(v) } => write!(f, "coolant manual {v:.2}"),
+            OperatorAction::SetCoolant { value: None } => f.write_str("coolant auto"),
+            OperatorAction::SetPower { value: Some(v) } => write!(f, "power manual {v:.2}"),
+            OperatorAction::SetPower { value: None } => f.write_str("power auto"),
+            OperatorAction::Acknowledge { alarm } => write!(f, "acknowledged {alarm}"),
+        }
+    }
+}
+
+/// A row of the "Events" panel
+#[derive(Clone, Debug, PartialEq)]
+struct LogEntry {
+    t_s: f64,
+    kind: LogKind,
+    text: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LogKind {
+    /// Something the scenario does (a fault from the start, loss of cooling, a timed event)
+    Scenario,
+    /// An `OperatorEvent`
+    Operator,
+    /// An alarm raised or cleared, or a sensor channel disqualified or back
+    Alarm,
+    /// A SCRAM latching (highlighted), clearing or changing its reason
+    Trip,
+}
+
+impl std::fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "t={:.2}s  {}", self.t_s, self.text)
+    }
+}
+
+/// Command line of the GUI binary; without `--render` it opens the window.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Parser)]
+#[command(name = "gui", version, about = "Reactor safety sim GUI")]
+struct Args {
+    /// Render the plots of this trace (JSONL or MessagePack, possibly gzipped) to `--out` and exit
+    /// instead of opening the window
+    #[arg(long, value_name = "TRACE", requires = "out")]
+    render: Option<PathBuf>,
+    /// PNG file `--render` writes
+    #[arg(long, value_name = "PNG", requires = "render")]
+    out: Option<PathBuf>,
+    /// Image width (pixels)
+    #[arg(
+        long,
+        default_value_t = PNG_SIZE[0],
+        value_parser = clap::value_parser!(u32).range(320..=7680)
+    )]
+    width: u32,
+    /// Image height (pixels)
+    #[arg(
+        long,
+        default_value_t = PNG_SIZE[1],
+        value_parser = clap::value_parser!(u32).range(240..=4320)
+    )]
+    height: u32,
+    /// Plots to draw, comma-separated
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        default_value = "temperatures,actuators"
+    )]
+    panes: Vec<PngPane>,
+}
+
 impl Default for App {
     fn default() -> Self {
         let scenario = Scenario::Normal;
@@ -100,84 +381,134 @@ impl Default for App {
         let trip_temp = 420.0;
         let seed = 12345;
 
-        let dt_s = dt_ms as f64 / 1000.0;
-        let max_steps = (seconds / dt_s).ceil() as u64;
+        let settings = LiveSettings {
+            setpoint,
+            scram_preload_pre_trip: false,
+            continue_after_scram: false,
+            manual_coolant: None,
+            manual_power: None,
+        };
+        let live = LiveSim::new(
+            scenario,
+            seconds,
+            dt_ms as f64 / 1000.0,
+            trip_temp,
+            seed,
+            settings,
+        );
 
-        let mut app = Self {
+        Self {
             scenario,
             seconds,
             dt_ms,
             setpoint,
             trip_temp,
             seed,
-
-            running: false,
-            t: 0.0,
-            dt_s,
-            max_steps,
-            step_count: 0,
-
-            plant_p: PlantParams::default(),
-            plant_x: PlantState::default(),
-            pid: Pid::new(PidConfig::default()),
-            safety_cfg: SafetyConfig {
-                trip_temp_c: trip_temp,
+            scram_preload_pre_trip: false,
+            continue_after_scram: false,
+            pid_edit: PidConfig::default(),
+            pid_apply_live: false,
+            plant_edit: PlantParams::default(),
+            plant_apply_live: false,
+            custom_scenario: None,
+            scenario_edit: ScenarioSpec {
+                name: "custom".to_string(),
                 ..Default::default()
             },
-            safety_state: SafetyState::default(),
-            s1: Sensor::new(seed ^ 0xA1),
-            s2: Sensor::new(seed ^ 0xB2),
-            s3: Sensor::new(seed ^ 0xC3),
+            show_scenario_editor: false,
+            scenario_open_dialog: None,
+            sensors: SensorsConfig::default(),
+            safety: SafetyConfig::default(),
+            config_open_dialog: None,
+            config_save_dialog: None,
+            config_path: None,
+            scenario_save_dialog: None,
+            scenario_path: None,
+
+            worker: LiveWorker::spawn(LiveRuns {
+                a: live.clone(),
+                b: None,
+            }),
+            live,
+            live_b: None,
+            running: false,
+            live_speed: LIVE_SPEED,
+            live_max_speed: false,
+            checkpoint_every: CHECKPOINT_EVERY,
+            t: 0.0,
 
             samples: Vec::new(),
+            visible: SeriesVisibility::default(),
+            layout: PlotLayout::default(),
+            plot_data: PlotData::default(),
+            compare_plot: PlotData::default(),
+            plot_max_points: DEFAULT_PLOT_POINTS,
+            gauge_amber: 0.6,
+            gauge_red: 0.85,
+            follow: false,
+            follow_window_s: 30.0,
+            plot_reset: false,
+            pause_on_trip: true,
+            trip_focus: None,
+            trip_seen: None,
+            annunciator: Annunciator::default(),
+            plot_view: None,
+            events: Vec::new(),
+            log: Vec::new(),
+            replay_log: Vec::new(),
+            cursor_t: None,
+            fault_edit: [SensorFault::None; 3],
+            manual_coolant: None,
+            manual_power: None,
 
             replay_loaded: false,
             replay_path: "out/demo_overheat.jsonl".to_string(),
             replay_all: Vec::new(),
+            replay_loading: None,
             replay_pos: 0,
             replay_playing: false,
+            replay_multiplier: 1.0,
+            replay_max_speed: false,
             replay_speed: 50,
+            replay_clock: 0.0,
             replay_reason: None,
+            replay_setpoint: None,
+            replay_trip_temp: None,
+            replay_plant: None,
+            legacy_setpoint: None,
+            legacy_trip_temp: None,
+            open_dialog: None,
+            compare_path: None,
+            compare_all: Vec::new(),
+            compare_reason: None,
+            compare_offset: 0.0,
+            compare_live: false,
+            ab_diff: None,
+            edit_target: EditTarget::Both,
+            compare_dialog: None,
+            drop_notice: None,
+            save_dialog: None,
+            export_full_replay: false,
+            export_notice: None,
+            record: false,
+            record_path: "recording.jsonl".to_string(),
+            recorder: None,
+            show_shortcuts: false,
+            png_dialog: None,
+            png_size: PNG_SIZE,
             last_error: None,
-        };
-
-        app.apply_scenario();
-        app
+        }
     }
 }
 
 impl App {
-    fn clear_replay(&mut self) {
-        self.replay_loaded = false;
-        self.replay_all.clear();
-        self.replay_pos = 0;
-        self.replay_playing = false;
-        self.replay_reason = None;
-        self.last_error = None;
-    }
-
-    fn reset_live(&mut self) {
-        self.running = false;
-        self.t = 0.0;
-        self.dt_s = self.dt_ms as f64 / 1000.0;
-        self.max_steps = (self.seconds / self.dt_s).ceil() as u64;
-        self.step_count = 0;
-
-        self.plant_p = PlantParams::default();
-        self.plant_x = PlantState::default();
-        self.pid = Pid::new(PidConfig::default());
-        self.safety_cfg = SafetyConfig {
-            trip_temp_c: self.trip_temp,
-            ..Default::default()
-        };
-        self.safety_state = SafetyState::default();
-
-        self.s1 = Sensor::new(self.seed ^ 0xA1);
-        self.s2 = Sensor::new(self.seed ^ 0xB2);
-        self.s3 = Sensor::new(self.seed ^ 0xC3);
-
-        self.samples.clear();
-        self.apply_scenario();
+    /// The app as the window or page opens, with the plot layout of the last session.
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = App::default();
+        if let Some(layout) = cc.storage.and_then(|s| eframe::get_value(s, LAYOUT_KEY)) {
+            app.layout = layout;
+        }
+        app
     }
 
     fn reset(&mut self) {
@@ -185,130 +516,118 @@ impl App {
         self.reset_live();
     }
 
-    fn apply_scenario(&mut self) {
-        match self.scenario {
-            Scenario::Normal => {
-                self.plant_x.coolant = 0.6;
-                self.s2.fault = SensorFault::None;
-            }
-            Scenario::Overheat => {
-                self.plant_x.coolant = 0.2;
-                self.s2.fault = SensorFault::None;
-            }
-            Scenario::LossOfCooling => {
-                self.plant_x.coolant = 0.7;
-                self.s2.fault = SensorFault::None;
-            }
-            Scenario::SensorDisagree => {
-                self.plant_x.coolant = 0.6;
-                self.s2.fault = SensorFault::Bias { value: 20.0 };
-            }
+    fn scenario_label(&self) -> String {
+        match &self.custom_scenario {
+            Some(spec) if spec.name.is_empty() => "Custom".to_string(),
+            Some(spec) => format!("Custom: {}", spec.name),
+            None => builtin_label(self.scenario).to_string(),
         }
-
-        self.s1.noise_std = 0.15;
-        self.s2.noise_std = 0.15;
-        self.s3.noise_std = 0.15;
     }
 
-    fn load_jsonl(&mut self, path: &str) {
-        self.last_error = None;
-
-        let text = match fs::read_to_string(path) {
-            Ok(t) => t,
-            Err(e) => {
-                self.last_error = Some(format!("Failed to read {path}: {e}"));
-                return;
-            }
-        };
-
-        let mut loaded: Vec<Sample> = Vec::new();
-        let mut first_reason: Option<String> = None;
-
-        for (i, raw) in text.lines().enumerate() {
-            let line = raw.trim();
-            if line.is_empty() {
-                continue;
-            }
-
-            let row: CliLine = match serde_json::from_str(line) {
-                Ok(v) => v,
-                Err(e) => {
-                    self.last_error = Some(format!("JSON parse error at line {}: {}", i + 1, e));
-                    return;
-                }
-            };
-
-            if first_reason.is_none() {
-                if let Some(r) = row.reason.as_ref() {
-                    first_reason = Some(r.to_string());
-                }
-            }
-
-            loaded.push(Sample {
-                t: row.t_s,
-                true_temp: row.true_temp_c,
-                s1: row.s1_c,
-                s2: row.s2_c,
-                s3: row.s3_c,
-                power: row.power,
-                coolant: row.coolant,
-                scram: row.scram,
-            });
-        }
-
-        if loaded.is_empty() {
-            self.last_error = Some(format!("No samples found in {path}"));
-            return;
+    /// The settings as a CLI configuration file (`run --config`). A run of the scenario editor's
+    /// scenario saves the built-in one selected under it.
+    fn sim_config(&self) -> SimConfig {
+        SimConfig {
+            run: cli::config::RunConfig {
+                scenario: self.scenario,
+                seconds: self.seconds,
+                dt_ms: self.dt_ms,
+                seed: self.seed,
+                setpoint: self.setpoint,
+            },
+            plant: self.plant_edit,
+            sensors: self.sensors,
+            pid: self.pid_edit,
+            safety: SafetyConfig {
+                trip_temp_c: self.trip_temp,
+                ..self.safety
+            },
+            ..SimConfig::default()
         }
+    }
 
-        // Enter replay mode
-        self.running = false;
-        self.clear_replay();
-        self.replay_loaded = true;
-        self.replay_reason = first_reason;
-
-        self.replay_all = loaded;
-        self.replay_pos = 0;
-        self.replay_playing = false;
-
-        // Start with an initial chunk so the plot isn't empty
-        self.samples.clear();
-        let initial = self.replay_speed.min(self.replay_all.len()).max(1);
-        self.samples.extend_from_slice(&self.replay_all[..initial]);
-        self.replay_pos = initial;
-
-        // Sync time marker
-        self.t = self.samples.last().map(|s| s.t).unwrap_or(0.0);
+    /// Take the settings of a configuration file, and start the live run over with them.
+    fn apply_config(&mut self, cfg: SimConfig) {
+        self.scenario = cfg.run.scenario;
+        self.custom_scenario = None;
+        self.seconds = cfg.run.seconds;
+        self.dt_ms = cfg.run.dt_ms;
+        self.seed = cfg.run.seed;
+        self.setpoint = cfg.run.setpoint;
+        self.plant_edit = cfg.plant;
+        self.sensors = cfg.sensors;
+        self.pid_edit = cfg.pid;
+        self.trip_temp = cfg.safety.trip_temp_c;
+        self.safety = cfg.safety;
+        self.reset();
     }
 
-    fn replay_tick(&mut self) {
-        if !(self.replay_loaded && self.replay_playing) {
+    /// "Load config…" / "Save config…": pick a CLI configuration file, starting next to the last.
+    fn browse_config(&mut self, save: bool) {
+        if self.config_open_dialog.is_some() || self.config_save_dialog.is_some() {
             return;
         }
-
-        if self.replay_pos >= self.replay_all.len() {
-            self.replay_playing = false;
-            return;
+        let mut dialog = rfd::AsyncFileDialog::new().add_filter("TOML config", &["toml"]);
+        if let Some(dir) = self.config_path.as_deref().and_then(Path::parent) {
+            dialog = dialog.set_directory(dir);
         }
+        if save {
+            let dialog = dialog.set_title("Save config").set_file_name("config.toml");
+            self.config_save_dialog = Some(spawn_dialog(dialog.save_file()));
+        } else {
+            let dialog = dialog.set_title("Load config");
+            self.config_open_dialog = Some(spawn_dialog(dialog.pick_file()));
+        }
+    }
 
-        let n = self.replay_speed.max(1);
-        let end = (self.replay_pos + n).min(self.replay_all.len());
-        self.samples
-            .extend_from_slice(&self.replay_all[self.replay_pos..end]);
-        self.replay_pos = end;
-
-        self.t = self.samples.last().map(|s| s.t).unwrap_or(self.t);
-
-        if self.replay_pos >= self.replay_all.len() {
-            self.replay_playing = false;
+    /// Load or save the configuration once its dialog closes; cancelling changes nothing.
+    fn poll_config_dialogs(&mut self, ctx: &egui::Context) {
+        for save in [false, true] {
+            let dialog = if save {
+                &mut self.config_save_dialog
+            } else {
+                &mut self.config_open_dialog
+            };
+            let Some(rx) = dialog else {
+                continue;
+            };
+            let path = match rx.try_recv() {
+                Ok(Some(path)) => path,
+                Ok(None) | Err(TryRecvError::Disconnected) => {
+                    *dialog = None;
+                    continue;
+                }
+                Err(TryRecvError::Empty) => {
+                    ctx.request_repaint_after(DIALOG_POLL);
+                    continue;
+                }
+            };
+            *dialog = None;
+            let done = if save {
+                write_config(&path, &self.sim_config())
+            } else {
+                read_config(&path).map(|cfg| self.apply_config(cfg))
+            };
+            match done {
+                Ok(()) => {
+                    self.last_error = None;
+                    self.config_path = Some(path);
+                }
+                Err(e) => self.last_error = Some(e),
+            }
         }
     }
 
-    fn scram_time_for_plot(&self) -> Option<f64> {
+    /// The margin gauge: the voted temperature from the setpoint (empty) to the trip temperature
+    /// (full), with the margin left; replays take both levels from the header where it has them.
+    /// Trip temperature of what is shown: the replay's (from its header, or typed in), or the
+    /// live run's.
+    fn shown_trip_temp(&self) -> f64 {
         if self.replay_loaded {
-            self.replay_all.iter().find(|s| s.scram).map(|s| s.t)
+            let trip = self.replay_trip_temp.or(self.legacy_trip_temp);
+            trip.unwrap_or(self.trip_temp)
         } else {
-            self.samples.iter().find(|s| s.scram).map(|s| s.t)
+            self.live.engine.safety_cfg.trip_temp_c
         }
     }
 
@@ -316,354 +635,368 @@ impl App {
         if self.replay_loaded {
             self.samples.last().map(|s| s.scram).unwrap_or(false)
         } else {
-            self.safety_state.scram
+            self.live.engine.safety.scram
         }
     }
 
+    /// Why the plant tripped: in replay as of the last sample shown ("—" before the trip comes
+    /// into view), live from the safety state.
     fn reason_text(&self) -> String {
-        if let Some(r) = self.replay_reason.as_ref() {
-            return r.clone();
+        if self.replay_loaded {
+            let reason = self.samples.last().and_then(|s| s.reason.as_deref());
+            return reason.map_or_else(|| "—".to_string(), RowReason::to_string);
         }
-        self.safety_state
-            .reason
+        self.live
+            .engine
+            .safety
+            .trip
             .as_ref()
-            .map(|r| format!("{r:?}"))
+            .map(TripRecord::to_string)
             .unwrap_or_else(|| "—".to_string())
     }
 
-    fn step_once_live(&mut self) {
-        if self.step_count >= self.max_steps {
-            self.running = false;
-            return;
-        }
-
-        let y1 = self.s1.read_temp(self.plant_x.temp_c, self.dt_s);
-        let y2 = self.s2.read_temp(self.plant_x.temp_c, self.dt_s);
-        let y3 = self.s3.read_temp(self.plant_x.temp_c, self.dt_s);
-
-        safety::evaluate(&self.safety_cfg, &mut self.safety_state, [y1, y2, y3]);
-
-        if self.safety_state.scram {
-            self.plant_x.power = 0.0;
-        } else {
-            let mut sum = 0.0;
-            let mut n = 0.0;
-            for y in [y1, y2, y3] {
-                if y.is_finite() {
-                    sum += y;
-                    n += 1.0;
-                }
-            }
-
-            let meas = if n > 0.0 {
-                sum / n
-            } else {
-                self.plant_x.temp_c
-            };
-            let u = self.pid.update(self.setpoint, meas, self.dt_s);
-            self.plant_x.power = u.clamp(0.0, 1.0);
-        }
-
-        if self.scenario == Scenario::LossOfCooling && self.t > (self.seconds * 0.3) {
-            self.plant_x.coolant = 0.05;
-        }
-
-        self.plant_x.step(&self.plant_p, self.dt_s);
-
-        self.samples.push(Sample {
-            t: self.t,
-            true_temp: self.plant_x.temp_c,
-            s1: y1,
-            s2: y2,
-            s3: y3,
-            power: self.plant_x.power,
-            coolant: self.plant_x.coolant,
-            scram: self.safety_state.scram,
-        });
-
-        self.t += self.dt_s;
-        self.step_count += 1;
-
-        if self.safety_state.scram {
-            self.running = false;
-        }
+    /// Log an operator action at the live run's current time.
+    fn record(&mut self, action: OperatorAction) {
+        self.worker.send(LiveCommand::Record(action));
     }
 
-    fn do_step_replay(&mut self) {
-        if !self.replay_loaded {
-            return;
-        }
-        if self.replay_pos >= self.replay_all.len() {
+    /// Move to a time picked in the "Events" panel: the replay position, or the cursor line of a
+    /// live run.
+    fn jump_to(&mut self, t: f64) {
+        if self.replay_loaded {
             self.replay_playing = false;
-            return;
+            let pos = self.replay_all.partition_point(|s| s.t <= t);
+            self.seek_replay(pos);
+        } else {
+            self.cursor_t = Some(t);
         }
-        let end = (self.replay_pos + 1).min(self.replay_all.len());
-        self.samples
-            .extend_from_slice(&self.replay_all[self.replay_pos..end]);
-        self.replay_pos = end;
-        self.t = self.samples.last().map(|s| s.t).unwrap_or(self.t);
     }
 }
 
 impl eframe::App for App {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, LAYOUT_KEY, &self.layout);
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.stop_recording();
+    }
+
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        self.replay_tick();
+        self.poll_open_dialog(ctx);
+        self.poll_replay_loading(ctx);
+        self.poll_compare_dialog(ctx);
+        self.poll_save_dialog(ctx);
+        self.poll_png_dialog(ctx);
+        self.poll_scenario_dialogs(ctx);
+        self.poll_config_dialogs(ctx);
+        self.handle_shortcuts(ctx);
+        self.shortcuts_window(ctx);
+        self.scenario_editor_window(ctx);
+        self.handle_dropped_files(ctx);
+        self.sync_live(ctx);
+        self.replay_tick(f64::from(ctx.input(|i| i.unstable_dt)));
         if self.replay_playing {
             ctx.request_repaint();
         }
 
-        let mode_txt = if self.replay_loaded { "REPLAY" } else { "LIVE" };
-        let scram_now = self.scram_now();
         let scram_time = self.scram_time_for_plot();
-        let reason_txt = self.reason_text();
-
-        egui::TopBottomPanel::top("top").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                ui.heading("Reactor Safety Sim (Portfolio GUI)");
-                ui.separator();
-                ui.label(format!("MODE: {mode_txt}"));
-                ui.separator();
-
-                let label = if scram_now { "SCRAM: ON" } else { "SCRAM: OFF" };
-                let color = if scram_now {
-                    egui::Color32::RED
-                } else {
-                    egui::Color32::GREEN
-                };
-                ui.colored_label(color, label);
-
-                if let Some(t) = scram_time {
-                    ui.separator();
-                    ui.label(format!("t_scram = {:.2}s", t));
-                    ui.separator();
-                    ui.label(format!("reason = {reason_txt}"));
-                }
-            });
-        });
-
-        egui::SidePanel::left("left")
-            .resizable(false)
-            .show(ctx, |ui| {
-                ui.label("Scenario");
-
-                if self.replay_loaded {
-                    ui.add_enabled(false, egui::Label::new(self.scenario.label()));
-                } else {
-                    let mut scenario_new = self.scenario;
-                    egui::ComboBox::from_id_salt("scenario")
-                        .selected_text(self.scenario.label())
-                        .show_ui(ui, |ui| {
-                            for s in [
-                                Scenario::Normal,
-                                Scenario::Overheat,
-                                Scenario::LossOfCooling,
-                                Scenario::SensorDisagree,
-                            ] {
-                                ui.selectable_value(&mut scenario_new, s, s.label());
-                            }
-                        });
-
-                    if scenario_new != self.scenario {
-                        self.scenario = scenario_new;
-                        self.reset_live();
-                    }
-                }
+        self.focus_on_trip(scram_time);
+        let annunciator = self.refresh_annunciator();
+        self.refresh_plots();
+
+        self.top_bar(ctx, scram_time);
+        self.annunciator_bar(ctx, &annunciator);
+        self.side_panel(ctx);
+        self.events_panel(ctx);
+        egui::CentralPanel::default().show(ctx, |ui| self.plot_panes(ui, scram_time));
+        drop_hint(ctx);
+    }
+}
 
-                ui.separator();
-                ui.label("Simulation settings");
-
-                let live_enabled = !self.replay_loaded;
-                ui.add_enabled(
-                    live_enabled,
-                    egui::Slider::new(&mut self.seconds, 10.0..=300.0).text("seconds"),
-                );
-                ui.add_enabled(
-                    live_enabled,
-                    egui::Slider::new(&mut self.dt_ms, 10..=200).text("dt (ms)"),
-                );
-                ui.add_enabled(
-                    live_enabled,
-                    egui::Slider::new(&mut self.setpoint, 100.0..=600.0).text("setpoint (°C)"),
-                );
-                ui.add_enabled(
-                    live_enabled,
-                    egui::Slider::new(&mut self.trip_temp, 200.0..=900.0).text("trip temp (°C)"),
-                );
-                ui.add_enabled(
-                    live_enabled,
-                    egui::DragValue::new(&mut self.seed).prefix("seed: "),
-                );
-
-                ui.separator();
-                ui.horizontal(|ui| {
-                    if ui.button("Reset").clicked() {
-                        self.reset();
-                    }
-
-                    let run_label = if self.running { "Pause" } else { "Run" };
-                    if ui
-                        .add_enabled(live_enabled, egui::Button::new(run_label))
-                        .clicked()
-                    {
-                        if !self.running {
-                            self.clear_replay();
-                            self.reset_live();
-                            self.apply_scenario();
-                            self.running = true;
-                        } else {
-                            self.running = false;
-                        }
-                    }
-
-                    if ui
-                        .add_enabled(live_enabled, egui::Button::new("Step"))
-                        .clicked()
-                    {
-                        self.step_once_live();
-                    }
-                });
-
-                ui.separator();
-                ui.label("Replay (JSONL)");
-                ui.horizontal(|ui| {
-                    ui.label("path:");
-                    ui.text_edit_singleline(&mut self.replay_path);
-                });
-
-                ui.horizontal(|ui| {
-                    if ui.button("Load").clicked() {
-                        let p = self.replay_path.clone();
-                        self.load_jsonl(&p);
-                    }
-
-                    if ui
-                        .button(if self.replay_playing {
-                            "Pause replay"
-                        } else {
-                            "Play replay"
-                        })
-                        .clicked()
-                        && self.replay_loaded
-                    {
-                        self.replay_playing = !self.replay_playing;
-                        ctx.request_repaint();
-                    }
-
-                    if ui.button("Step replay").clicked() && self.replay_loaded {
-                        self.do_step_replay();
-                    }
-                });
-
-                ui.add(
-                    egui::Slider::new(&mut self.replay_speed, 1..=500)
-                        .text("replay speed (samples/frame)"),
-                );
-
-                if self.replay_loaded {
-                    ui.small(format!(
-                        "Loaded: {}/{} samples",
-                        self.samples.len(),
-                        self.replay_all.len()
-                    ));
-                } else {
-                    ui.small("No replay loaded.");
-                }
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> eframe::Result<()> {
+    let args = Args::parse();
+    if let (Some(trace), Some(out)) = (&args.render, &args.out) {
+        let size = [args.width, args.height];
+        if let Err(e) = render_trace(trace, out, size, &args.panes) {
+            eprintln!("error: {e}");
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    let native_options = eframe::NativeOptions::default();
+    eframe::run_native(
+        "Reactor Safety Sim (Portfolio GUI)",
+        native_options,
+        Box::new(|cc| Ok(Box::new(App::new(cc)))),
+    )
+}
 
-                if let Some(err) = &self.last_error {
-                    ui.separator();
-                    ui.colored_label(egui::Color32::RED, err);
-                }
+/// The browser build (`trunk build`, see index.html): the app on the page's canvas. The live run
+/// steps on the page itself, with the same seeds and steps as the native one.
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use eframe::wasm_bindgen::JsCast;
+    use eframe::web_sys;
+
+    wasm_bindgen_futures::spawn_local(async {
+        let canvas = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id("the_canvas_id"))
+            .and_then(|e| e.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+            .expect("index.html has a canvas with id the_canvas_id");
+        let started = eframe::WebRunner::new()
+            .start(
+                canvas,
+                eframe::WebOptions::default(),
+                Box::new(|cc| Ok(Box::new(App::new(cc)))),
+            )
+            .await;
+        if let Err(e) = started {
+            web_sys::console::error_1(&e);
+        }
+    });
+}
 
-                ui.separator();
-                ui.small("Tip: Use make_demo.ps1 then Load out/demo_overheat.jsonl.");
-            });
-
-        egui::CentralPanel::default().show(ctx, |ui| {
-            if self.running && !self.replay_loaded {
-                for _ in 0..5 {
-                    if !self.running {
-                        break;
-                    }
-                    self.step_once_live();
-                }
-                ctx.request_repaint();
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use clap::ValueEnum;
+
+    use crate::replay::load_trace_from_bytes;
+
+    pub(crate) const TRACE: &str = concat!(
+        r#"{"_meta":{"schema":3,"setpoint":380.0,"trip_temp":390.0}}"#,
+        "\n",
+        r#"{"t_s":0.0,"setpoint_c":380.0,"true_temp_c":300.0,"s1_c":300.1,"s2_c":null,"s3_c":299.9,"power":1.0,"coolant":0.2,"scram":false,"reason":null}"#,
+        "\n",
+        r#"{"t_s":0.05,"setpoint_c":380.0,"true_temp_c":391.0,"s1_c":391.2,"s2_c":390.8,"s3_c":391.1,"power":0.0,"coolant":0.2,"scram":true,"reason":"OverTemp"}"#,
+        "\n",
+    );
+
+    #[test]
+    fn jsonl_bytes_load_with_their_header() {
+        let trace = load_trace_from_bytes("dropped.jsonl", TRACE.as_bytes()).unwrap();
+        assert_eq!(trace.samples.len(), 2);
+        assert!(trace.samples[0].s2.is_nan());
+        assert!(trace.samples[1].scram);
+        let reason = trace.first_reason.as_ref().map(RowReason::to_string);
+        assert_eq!(reason.as_deref(), Some("OverTemp"));
+        let meta = trace.meta.expect("header");
+        assert_eq!((meta.setpoint, meta.trip_temp), (Some(380.0), Some(390.0)));
+        assert_eq!((meta.scenario.as_deref(), meta.plant()), (None, None));
+        let trip = LogEntry {
+            t_s: 0.05,
+            kind: LogKind::Trip,
+            text: "TRIP OverTemp".to_string(),
+        };
+        assert_eq!(trace.events, [trip]);
+    }
 
-            if self.samples.is_empty() {
-                ui.label("No data yet. Run LIVE or Load a REPLAY file.");
-                return;
-            }
+    #[test]
+    fn operator_actions_read_as_log_rows() {
+        let injected = OperatorEvent {
+            t_s: 12.3,
+            action: OperatorAction::InjectFault {
+                sensor: "s2",
+                fault: SensorFault::Bias { value: 20.0 },
+            },
+        };
+        assert_eq!(
+            injected.to_string(),
+            "t=12.30s  fault injected s2 bias +20 °C"
+        );
+        let cleared = OperatorAction::InjectFault {
+            sensor: "s1",
+            fault: SensorFault::None,
+        };
+        assert_eq!(cleared.to_string(), "fault cleared s1");
+        assert_eq!(
+            OperatorAction::SetPower { value: None }.to_string(),
+            "power auto"
+        );
+    }
 
-            let t_end = self.samples.last().map(|s| s.t).unwrap_or(0.0);
+    pub(crate) fn sample(t: f64, scram: bool) -> Sample {
+        Sample {
+            t,
+            true_temp: 300.0 + t / 3.0,
+            s1: 300.1 + t,
+            s2: f64::NAN,
+            s3: 299.9,
+            power: if scram { 0.0 } else { 0.7 },
+            coolant: 0.2,
+            scram,
+            setpoint: 380.0,
+            p_term: 0.1 * t,
+            i_term: 0.45,
+            d_term: -0.01,
+            saturated: t > 0.1,
+            alarm: false,
+            reason: None,
+        }
+    }
 
-            let temp_points: PlotPoints = self.samples.iter().map(|s| [s.t, s.true_temp]).collect();
-            let power_points: PlotPoints = self.samples.iter().map(|s| [s.t, s.power]).collect();
-            let cool_points: PlotPoints = self.samples.iter().map(|s| [s.t, s.coolant]).collect();
+    pub(crate) fn export_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rss-gui-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
 
-            let s1_points: PlotPoints = self.samples.iter().map(|s| [s.t, s.s1]).collect();
-            let s2_points: PlotPoints = self.samples.iter().map(|s| [s.t, s.s2]).collect();
-            let s3_points: PlotPoints = self.samples.iter().map(|s| [s.t, s.s3]).collect();
+    pub(crate) fn live_sim(scenario: Scenario) -> LiveSim {
+        let settings = LiveSettings {
+            setpoint: 380.0,
+            scram_preload_pre_trip: false,
+            continue_after_scram: false,
+            manual_coolant: None,
+            manual_power: None,
+        };
+        LiveSim::new(scenario, 20.0, 0.01, 450.0, 42, settings)
+    }
 
-            let mut y_min = f64::INFINITY;
-            let mut y_max = f64::NEG_INFINITY;
-            for s in &self.samples {
-                for y in [s.true_temp, s.s1, s.s2, s.s3] {
-                    if y.is_finite() {
-                        y_min = y_min.min(y);
-                        y_max = y_max.max(y);
-                    }
-                }
-            }
-            if !y_min.is_finite() || !y_max.is_finite() {
-                y_min = 0.0;
-                y_max = 1.0;
-            }
-            let pad = ((y_max - y_min).abs() * 0.10).max(1.0);
-            let y0 = y_min - pad;
-            let y1 = y_max + pad;
-
-            ui.heading("Traces");
-            Plot::new("temp_plot").height(240.0).show(ui, |plot_ui| {
-                plot_ui.line(Line::new(temp_points).name("Temp (°C)"));
-                plot_ui.line(Line::new(s1_points).name("Sensor 1"));
-                plot_ui.line(Line::new(s2_points).name("Sensor 2"));
-                plot_ui.line(Line::new(s3_points).name("Sensor 3"));
-
-                if !self.replay_loaded && t_end > 0.0 {
-                    let setpoint_line: PlotPoints =
-                        vec![[0.0, self.setpoint], [t_end, self.setpoint]].into();
-                    let trip_line: PlotPoints =
-                        vec![[0.0, self.trip_temp], [t_end, self.trip_temp]].into();
-                    plot_ui.line(Line::new(setpoint_line).name("Setpoint"));
-                    plot_ui.line(Line::new(trip_line).name("Trip temp"));
-                }
+    #[test]
+    fn config_files_round_trip_through_the_cli_structs() {
+        let dir = export_dir("config");
+        let path = dir.join("tuned.toml");
+        let mut app = App {
+            scenario: Scenario::Overheat,
+            seconds: 42.0,
+            dt_ms: 20,
+            seed: 7,
+            setpoint: 360.0,
+            trip_temp: 400.0,
+            ..App::default()
+        };
+        app.pid_edit.kp = 0.05;
+        app.plant_edit.k_cool = 2.5;
+        app.sensors.s2.noise_std = 0.4;
+        app.safety.max_sensor_delta_c = 12.0;
+        let toml = app.sim_config().to_toml().unwrap();
+        write_config(&path, &app.sim_config()).unwrap();
+        assert!(
+            toml.contains("[run]\nscenario = \"overheat\"\nseconds = 42.0\n"),
+            "{toml}"
+        );
+
+        let mut loaded = App::default();
+        loaded.apply_config(read_config(&path).unwrap());
+        assert_eq!(loaded.sim_config().to_toml().unwrap(), toml);
+        assert_eq!(loaded.scenario, Scenario::Overheat);
+        // The reset live run has the sensor and safety settings
+        assert_eq!(loaded.live.engine.sensors[1].noise_std, 0.4);
+        assert_eq!(loaded.live.engine.safety_cfg.max_sensor_delta_c, 12.0);
+        assert_eq!(loaded.live.engine.safety_cfg.trip_temp_c, 400.0);
+
+        // The CLI's errors, naming the section and key
+        fs::write(&path, "[pid]\nkp = 0.1\nbogus = 1\n").unwrap();
+        let err = read_config(&path).unwrap_err();
+        assert!(err.contains("[pid]") && err.contains("bogus"), "{err}");
+        fs::write(&path, "[run]\nsetpoint = 500.0\n").unwrap();
+        let err = read_config(&path).unwrap_err();
+        assert!(
+            err.ends_with("run.setpoint = 500 must be below safety.trip_temp_c (420)"),
+            "{err}"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
 
-                if let Some(t) = scram_time {
-                    let vline: PlotPoints = vec![[t, y0], [t, y1]].into();
-                    plot_ui.line(Line::new(vline).name("SCRAM"));
-                }
-            });
-
-            Plot::new("act_plot").height(180.0).show(ui, |plot_ui| {
-                plot_ui.line(Line::new(power_points).name("Power (0..1)"));
-                plot_ui.line(Line::new(cool_points).name("Coolant (0..1)"));
-            });
-
-            ui.separator();
-            let last = self.samples.last().unwrap();
-            ui.label(format!(
-                "t={:.2}s  temp={:.2}°C  power={:.2}  coolant={:.2}",
-                last.t, last.true_temp, last.power, last.coolant
-            ));
-        });
+    #[test]
+    fn saved_configs_rerun_identically_in_the_cli() {
+        let dir = export_dir("config_run");
+        let path = dir.join("tuned.toml");
+        // Tuned here: the coolant is cut 18 s in, with a higher setpoint and gain and a noisier s2
+        let mut app = App {
+            scenario: Scenario::LossOfCooling,
+            seconds: 60.0,
+            setpoint: 410.0,
+            ..App::default()
+        };
+        app.pid_edit.kp = 0.2;
+        app.sensors.s2.noise_std = 0.5;
+        app.safety.max_sensor_delta_c = 12.0;
+        write_config(&path, &app.sim_config()).unwrap();
+
+        let trace = dir.join("cli.jsonl");
+        let args = [
+            "cli",
+            "run",
+            "--config",
+            path.to_str().unwrap(),
+            "--diagnostics",
+            "--summary",
+            "none",
+            "--out",
+            trace.to_str().unwrap(),
+        ];
+        cli::execute(&cli::Cli::try_parse_from(args).unwrap(), &mut Vec::new()).unwrap();
+        let cli_run = load_trace_from_bytes("cli.jsonl", &fs::read(&trace).unwrap()).unwrap();
+
+        let mut loaded = App::default();
+        loaded.apply_config(read_config(&path).unwrap());
+        let mut sim = loaded.live.clone();
+        sim.running = true;
+        while sim.running {
+            sim.step();
+        }
+        // Traces do not carry the disagreement alarm
+        let gui_run: Vec<Sample> = sim
+            .samples
+            .into_iter()
+            .map(|s| Sample { alarm: false, ..s })
+            .collect();
+        assert_eq!(gui_run.len(), 1200);
+        assert_eq!(format!("{gui_run:?}"), format!("{:?}", cli_run.samples));
+        fs::remove_dir_all(dir).unwrap();
     }
-}
 
-fn main() -> eframe::Result<()> {
-    let native_options = eframe::NativeOptions::default();
-    eframe::run_native(
-        "Reactor Safety Sim (Portfolio GUI)",
-        native_options,
-        Box::new(|_cc| Ok(Box::new(App::default()))),
-    )
+    #[test]
+    fn every_built_in_scenario_runs_identically_in_the_cli() {
+        let dir = export_dir("engine_parity");
+        for &scenario in Scenario::value_variants() {
+            let mut app = App {
+                scenario,
+                ..App::default()
+            };
+            app.reset_live();
+            let path = dir.join("config.toml");
+            write_config(&path, &app.sim_config()).unwrap();
+            let name = format!("{scenario:?}.jsonl");
+            let trace = dir.join(&name);
+            let args = [
+                "cli",
+                "run",
+                "--config",
+                path.to_str().unwrap(),
+                "--diagnostics",
+                "--summary",
+                "none",
+                "--out",
+                trace.to_str().unwrap(),
+            ];
+            cli::execute(&cli::Cli::try_parse_from(args).unwrap(), &mut Vec::new()).unwrap();
+            let cli_run = load_trace_from_bytes(&name, &fs::read(&trace).unwrap()).unwrap();
+
+            let mut sim = app.live.clone();
+            sim.running = true;
+            while sim.running {
+                sim.step();
+            }
+            let gui_run: Vec<Sample> = sim
+                .samples
+                .into_iter()
+                .map(|s| Sample { alarm: false, ..s })
+                .collect();
+            assert!(!gui_run.is_empty(), "{scenario:?}");
+            assert_eq!(
+                format!("{gui_run:?}"),
+                format!("{:?}", cli_run.samples),
+                "{scenario:?}"
+            );
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
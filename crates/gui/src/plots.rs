@@ -0,0 +1,1877 @@
+//! The plot panes: plot lines decimated from the samples, the panes drawing them, and the
+//! residuals and controller metrics of the time range they show.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use controller::metrics::{ControlMetrics, ResponsePoint};
+use eframe::egui;
+use egui_plot::{
+    Bar, BarChart, HLine, Line, LineStyle, MarkerShape, Plot, PlotBounds, PlotPoints, PlotUi,
+    Points, Polygon, VLine,
+};
+use safety::SafetyConfig;
+use serde::{Deserialize, Serialize};
+use sim::PlantParams;
+use trace::{RowReason, Sample};
+
+use crate::replay::samples_until;
+use crate::App;
+
+/// A plot pane of the central panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+enum Pane {
+    Temperatures,
+    Actuators,
+    ControllerTerms,
+    SafetyMargins,
+    HeatFlows,
+    PhasePlane,
+}
+
+impl Pane {
+    const ALL: [Pane; 6] = [
+        Pane::Temperatures,
+        Pane::Actuators,
+        Pane::ControllerTerms,
+        Pane::SafetyMargins,
+        Pane::HeatFlows,
+        Pane::PhasePlane,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Pane::Temperatures => "Temperatures",
+            Pane::Actuators => "Actuators",
+            Pane::ControllerTerms => "Controller terms",
+            Pane::SafetyMargins => "Safety margins",
+            Pane::HeatFlows => "Heat flows",
+            Pane::PhasePlane => "Phase plane",
+        }
+    }
+
+    /// Height of the pane when stacked, until it is dragged to another
+    fn default_height(self) -> f32 {
+        match self {
+            Pane::Temperatures | Pane::PhasePlane => 240.0,
+            Pane::Actuators => 180.0,
+            _ => 160.0,
+        }
+    }
+}
+
+/// Stacked pane heights the resize handles allow
+const PANE_HEIGHTS: std::ops::RangeInclusive<f32> = 80.0..=800.0;
+
+/// How the shown panes share the central panel
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum PaneArrangement {
+    Stacked,
+    Tabs,
+}
+
+/// The plot panes shown and how they are arranged; kept across restarts in eframe's storage
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct PlotLayout {
+    arrangement: PaneArrangement,
+    // Panes shown, in `Pane::ALL` order
+    shown: Vec<Pane>,
+    // Heights of the stacked panes that were resized
+    heights: BTreeMap<Pane, f32>,
+    // The pane shown as tabs
+    tab: Pane,
+}
+
+impl Default for PlotLayout {
+    fn default() -> Self {
+        Self {
+            arrangement: PaneArrangement::Stacked,
+            shown: vec![Pane::Temperatures, Pane::Actuators],
+            heights: BTreeMap::new(),
+            tab: Pane::Temperatures,
+        }
+    }
+}
+
+impl PlotLayout {
+    fn set_shown(&mut self, pane: Pane, shown: bool) {
+        self.shown.retain(|p| *p != pane);
+        if shown {
+            self.shown.push(pane);
+            self.shown.sort();
+        }
+    }
+
+    fn height(&self, pane: Pane) -> f32 {
+        self.heights
+            .get(&pane)
+            .copied()
+            .unwrap_or(pane.default_height())
+    }
+
+    /// The pane shown as tabs: the one picked, or the first shown once that one is hidden.
+    fn tab(&self) -> Option<Pane> {
+        (self.shown.contains(&self.tab))
+            .then_some(self.tab)
+            .or(self.shown.first().copied())
+    }
+}
+
+/// Key of the plot layout in eframe's storage
+pub(crate) const LAYOUT_KEY: &str = "plot_layout";
+
+/// Which series the temperature plot shows
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SeriesVisibility {
+    pub(crate) true_temp: bool,
+    pub(crate) sensors: [bool; 3],
+    // The band between the lowest and highest valid reading and their median, in place of the
+    // sensor lines
+    envelope: bool,
+    pub(crate) setpoint: bool,
+    // Trip temperature and the other protection levels
+    levels: bool,
+    // The comparison trace ("B")
+    pub(crate) compare: bool,
+}
+
+impl Default for SeriesVisibility {
+    fn default() -> Self {
+        Self {
+            true_temp: true,
+            sensors: [true; 3],
+            envelope: false,
+            setpoint: true,
+            levels: true,
+            compare: true,
+        }
+    }
+}
+
+/// Vertical extent of the temperature plot: the shown series and reference `levels`, padded by
+/// 10 % (at least 1 °C). Hidden series do not count.
+pub(crate) fn temp_range(
+    data: &PlotData,
+    compare: Option<&PlotData>,
+    vis: &SeriesVisibility,
+    levels: &[f64],
+) -> (f64, f64) {
+    let lines = [
+        (vis.true_temp, &data.temp),
+        (vis.sensors[0] && !vis.envelope, &data.sensors[0]),
+        (vis.sensors[1] && !vis.envelope, &data.sensors[1]),
+        (vis.sensors[2] && !vis.envelope, &data.sensors[2]),
+        (vis.setpoint, &data.setpoint),
+    ];
+    let shown = lines
+        .into_iter()
+        .filter(|(on, _)| *on)
+        .flat_map(|(_, line)| line.iter().map(|[_, y]| *y));
+    let band = data
+        .envelope
+        .iter()
+        .filter(|_| vis.envelope)
+        .flat_map(|[_, low, high]| [*low, *high]);
+    let b_temps = compare
+        .into_iter()
+        .flat_map(|b| b.temp.iter().map(|[_, y]| *y));
+    padded_range(
+        shown
+            .chain(band)
+            .chain(b_temps)
+            .chain(levels.iter().copied()),
+    )
+}
+
+/// Extent of the finite values `ys`, padded by 10 % (at least 1); 0..1 before padding if none.
+fn padded_range(ys: impl IntoIterator<Item = f64>) -> (f64, f64) {
+    let mut y_min = f64::INFINITY;
+    let mut y_max = f64::NEG_INFINITY;
+    for y in ys {
+        if y.is_finite() {
+            y_min = y_min.min(y);
+            y_max = y_max.max(y);
+        }
+    }
+    if !y_min.is_finite() || !y_max.is_finite() {
+        y_min = 0.0;
+        y_max = 1.0;
+    }
+    let pad = ((y_max - y_min).abs() * 0.10).max(1.0);
+    (y_min - pad, y_max + pad)
+}
+
+/// The samples of the last `window_s` seconds, up to the newest one (the scrub position in
+/// replay).
+fn follow_window(samples: &[Sample], window_s: f64) -> &[Sample] {
+    let t_now = samples.last().map_or(0.0, |s| s.t);
+    &samples[samples.partition_point(|s| s.t < t_now - window_s)..]
+}
+
+/// Points per plot line past which the data is decimated
+pub(crate) const DEFAULT_PLOT_POINTS: usize = 5000;
+
+/// Equal time buckets between the first and last sample
+struct Buckets {
+    t0: f64,
+    width: f64,
+    count: usize,
+}
+
+impl Buckets {
+    fn new(samples: &[Sample], count: usize) -> Self {
+        let t0 = samples.first().map_or(0.0, |s| s.t);
+        let t1 = samples.last().map_or(0.0, |s| s.t);
+        let count = count.max(1);
+        Self {
+            t0,
+            width: (t1 - t0) / count as f64,
+            count,
+        }
+    }
+
+    /// The bucket of time `t`; the last one includes the end time.
+    fn of(&self, t: f64) -> usize {
+        if self.width > 0.0 {
+            (((t - self.t0) / self.width) as usize).min(self.count - 1)
+        } else {
+            0
+        }
+    }
+}
+
+/// Index ranges of the samples in each (non-empty) time bucket when there are more than
+/// `max_points`, with `max_points / 2` buckets so each can keep two points; None otherwise.
+fn bucket_ranges(samples: &[Sample], max_points: usize) -> Option<Vec<Range<usize>>> {
+    if samples.len() <= max_points.max(2) {
+        return None;
+    }
+    let buckets = Buckets::new(samples, max_points / 2);
+    let mut ranges = Vec::with_capacity(buckets.count);
+    let mut start = 0;
+    let mut current = buckets.of(samples[0].t);
+    for (i, s) in samples.iter().enumerate().skip(1) {
+        let bucket = buckets.of(s.t);
+        if bucket != current {
+            ranges.push(start..i);
+            start = i;
+            current = bucket;
+        }
+    }
+    ranges.push(start..samples.len());
+    Some(ranges)
+}
+
+/// A plot line being built and the value it shows of each sample
+type LineOf<'a> = (&'a mut Vec<[f64; 2]>, &'a dyn Fn(&Sample) -> f64);
+
+/// Fill each line with its points over `samples`. With bucket `ranges`, each bucket is kept as
+/// its lowest and highest point in time order, so spikes and trips survive, and a bucket without
+/// any finite value keeps a NaN to break the line. Buckets are the outer loop so the samples are
+/// read from memory once for all lines.
+fn decimate(samples: &[Sample], ranges: Option<&[Range<usize>]>, lines: &mut [LineOf<'_>]) {
+    let Some(ranges) = ranges else {
+        for (line, value) in lines {
+            **line = samples.iter().map(|s| [s.t, value(s)]).collect();
+        }
+        return;
+    };
+    for (line, _) in lines.iter_mut() {
+        line.reserve(2 * ranges.len());
+    }
+    for range in ranges {
+        let chunk = &samples[range.clone()];
+        for (line, value) in lines.iter_mut() {
+            let mut lowest: Option<(usize, f64)> = None;
+            let mut highest: Option<(usize, f64)> = None;
+            for (i, y) in chunk.iter().map(*value).enumerate() {
+                if !y.is_finite() {
+                    continue;
+                }
+                if lowest.is_none_or(|(_, low)| y < low) {
+                    lowest = Some((i, y));
+                }
+                if highest.is_none_or(|(_, high)| y > high) {
+                    highest = Some((i, y));
+                }
+            }
+            match (lowest, highest) {
+                (Some(low), Some(high)) => {
+                    let (first, second) = if low.0 <= high.0 {
+                        (low, high)
+                    } else {
+                        (high, low)
+                    };
+                    line.push([chunk[first.0].t, first.1]);
+                    if second.0 != first.0 {
+                        line.push([chunk[second.0].t, second.1]);
+                    }
+                }
+                _ => line.push([chunk[0].t, f64::NAN]),
+            }
+        }
+    }
+}
+
+/// What the plots draw of a run, decimated and kept between frames. Rebuilt when samples are
+/// appended, the view is scrubbed (the sample count or time span changes) or on `invalidate`.
+#[derive(Default)]
+pub(crate) struct PlotData {
+    // Sample count, first and last time, points per line and plant parameters it was built for
+    source: Option<(usize, f64, f64, usize, PlantParams)>,
+    pub(crate) temp: Vec<[f64; 2]>,
+    pub(crate) sensors: [Vec<[f64; 2]>; 3],
+    pub(crate) setpoint: Vec<[f64; 2]>,
+    pub(crate) power: Vec<[f64; 2]>,
+    // Saturated stretches only (NaN breaks the line elsewhere)
+    pub(crate) power_sat: Vec<[f64; 2]>,
+    pub(crate) coolant: Vec<[f64; 2]>,
+    p_term: Vec<[f64; 2]>,
+    i_term: Vec<[f64; 2]>,
+    d_term: Vec<[f64; 2]>,
+    // Band of the valid readings, rows of (time, lowest, highest), and their median
+    envelope: Vec<[f64; 3]>,
+    median: Vec<[f64; 2]>,
+    // Temperature the over-temperature vote acts on, and the spread of the valid readings
+    voted: Vec<[f64; 2]>,
+    spread: Vec<[f64; 2]>,
+    // Heating by power, cooling by coolant and their net, as rates of temperature change (°C/s)
+    heating: Vec<[f64; 2]>,
+    cooling: Vec<[f64; 2]>,
+    net_heat: Vec<[f64; 2]>,
+    // Phase-plane trajectory, rows of (time, power, temperature), and its point at the trip
+    phase: Vec<[f64; 3]>,
+    phase_trip: Option<[f64; 2]>,
+    // Number of dropouts of each sensor, and where to mark them (at most one mark per bucket)
+    pub(crate) dropout_counts: [usize; 3],
+    pub(crate) dropout_marks: [Vec<f64>; 3],
+    pub(crate) alarms: Vec<(f64, f64)>,
+}
+
+impl PlotData {
+    pub(crate) fn build(samples: &[Sample], max_points: usize, plant: PlantParams) -> Self {
+        let ranges = bucket_ranges(samples, max_points);
+        let dropout_times = dropouts(samples);
+        let buckets = Buckets::new(samples, max_points / 2);
+        let dropout_marks = dropout_times.each_ref().map(|times| {
+            let mut marks = times.clone();
+            if ranges.is_some() {
+                marks.dedup_by(|later, earlier| buckets.of(*later) == buckets.of(*earlier));
+            }
+            marks
+        });
+        let mut data = Self {
+            source: Some(Self::source_of(samples, max_points, plant)),
+            dropout_counts: dropout_times.each_ref().map(Vec::len),
+            dropout_marks,
+            alarms: active_intervals(samples, |s| s.alarm),
+            ..Self::default()
+        };
+        // The GUI's runs judge readings by the default valid range
+        let cfg = SafetyConfig::default();
+        let heating = |s: &Sample| plant.k_power * s.power / plant.thermal_mass;
+        let cooling = |s: &Sample| {
+            plant.k_cool * s.coolant * (s.true_temp - plant.ambient_c) / plant.thermal_mass
+        };
+        data.envelope = envelope_band(samples, ranges.as_deref(), &cfg);
+        data.phase = phase_trajectory(samples, ranges.as_deref());
+        data.phase_trip = samples
+            .iter()
+            .find(|s| s.scram)
+            .map(|s| [s.power, s.true_temp]);
+        let [s1, s2, s3] = &mut data.sensors;
+        let mut lines: [LineOf<'_>; 17] = [
+            (&mut data.temp, &|s| s.true_temp),
+            (s1, &|s| s.s1),
+            (s2, &|s| s.s2),
+            (s3, &|s| s.s3),
+            (&mut data.setpoint, &|s| s.setpoint),
+            (&mut data.power, &|s| s.power),
+            (&mut data.power_sat, &|s| {
+                if s.saturated {
+                    s.power
+                } else {
+                    f64::NAN
+                }
+            }),
+            (&mut data.coolant, &|s| s.coolant),
+            (&mut data.p_term, &|s| s.p_term),
+            (&mut data.i_term, &|s| s.i_term),
+            (&mut data.d_term, &|s| s.d_term),
+            (&mut data.median, &|s| valid_median(&cfg, s.sensors())),
+            (&mut data.voted, &|s| {
+                cfg.voted_temp(s.sensors()).unwrap_or(f64::NAN)
+            }),
+            (&mut data.spread, &|s| {
+                valid_spread(&cfg, s.sensors()).unwrap_or(f64::NAN)
+            }),
+            (&mut data.heating, &heating),
+            (&mut data.cooling, &cooling),
+            (&mut data.net_heat, &|s| heating(s) - cooling(s)),
+        ];
+        decimate(samples, ranges.as_deref(), &mut lines);
+        data
+    }
+
+    fn source_of(
+        samples: &[Sample],
+        max_points: usize,
+        plant: PlantParams,
+    ) -> (usize, f64, f64, usize, PlantParams) {
+        let t0 = samples.first().map_or(0.0, |s| s.t);
+        let t1 = samples.last().map_or(0.0, |s| s.t);
+        (samples.len(), t0, t1, max_points, plant)
+    }
+
+    fn refresh(&mut self, samples: &[Sample], max_points: usize, plant: PlantParams) {
+        if self.source != Some(Self::source_of(samples, max_points, plant)) {
+            *self = Self::build(samples, max_points, plant);
+        }
+    }
+
+    /// Drop the cache, for when the samples were replaced rather than appended to.
+    pub(crate) fn invalidate(&mut self) {
+        self.source = None;
+    }
+}
+
+/// The valid readings (dropouts and readings out of the valid range left out), lowest first.
+fn valid_readings(cfg: &SafetyConfig, temps: [f64; 3]) -> Vec<f64> {
+    let mut valid: Vec<f64> = temps
+        .into_iter()
+        .filter(|v| cfg.reading_valid(*v))
+        .collect();
+    valid.sort_by(f64::total_cmp);
+    valid
+}
+
+/// Spread (highest − lowest) of the valid readings, which the disagreement trip compares against
+/// `max_sensor_delta_c`; None with fewer than two valid readings.
+fn valid_spread(cfg: &SafetyConfig, temps: [f64; 3]) -> Option<f64> {
+    match valid_readings(cfg, temps)[..] {
+        [low, .., high] => Some(high - low),
+        _ => None,
+    }
+}
+
+/// Median of the valid readings; NaN without any.
+fn valid_median(cfg: &SafetyConfig, temps: [f64; 3]) -> f64 {
+    match valid_readings(cfg, temps)[..] {
+        [] => f64::NAN,
+        [only] => only,
+        [low, high] => (low + high) / 2.0,
+        [_, middle, _] => middle,
+        _ => unreachable!("three sensors"),
+    }
+}
+
+/// The band between the lowest and highest valid reading, as rows of (time, lowest, highest):
+/// one per sample, or with bucket `ranges` the extremes over each bucket at its first and last
+/// time. Fewer than two valid readings leave a NaN row, which breaks the band.
+fn envelope_band(
+    samples: &[Sample],
+    ranges: Option<&[Range<usize>]>,
+    cfg: &SafetyConfig,
+) -> Vec<[f64; 3]> {
+    let extremes = |s: &Sample| match valid_readings(cfg, s.sensors())[..] {
+        [low, .., high] => Some((low, high)),
+        _ => None,
+    };
+    let Some(ranges) = ranges else {
+        return samples
+            .iter()
+            .map(|s| {
+                let (low, high) = extremes(s).unwrap_or((f64::NAN, f64::NAN));
+                [s.t, low, high]
+            })
+            .collect();
+    };
+    let mut band = Vec::with_capacity(2 * ranges.len());
+    for range in ranges {
+        let chunk = &samples[range.clone()];
+        let (low, high) = chunk
+            .iter()
+            .filter_map(extremes)
+            .reduce(|(l0, h0), (l1, h1)| (l0.min(l1), h0.max(h1)))
+            .unwrap_or((f64::NAN, f64::NAN));
+        band.push([chunk[0].t, low, high]);
+        if let [_, .., last] = chunk {
+            band.push([last.t, low, high]);
+        }
+    }
+    band
+}
+
+/// The run in the phase plane, as rows of (time, power, temperature): every sample, or with
+/// bucket `ranges` the coolest and hottest sample of each bucket in time order, so the
+/// trajectory keeps its excursions as the temperature line does.
+fn phase_trajectory(samples: &[Sample], ranges: Option<&[Range<usize>]>) -> Vec<[f64; 3]> {
+    let row = |s: &Sample| [s.t, s.power, s.true_temp];
+    let Some(ranges) = ranges else {
+        return samples.iter().map(row).collect();
+    };
+    let mut rows = Vec::with_capacity(2 * ranges.len());
+    for range in ranges {
+        let chunk = &samples[range.clone()];
+        let by_temp = |a: &&Sample, b: &&Sample| a.true_temp.total_cmp(&b.true_temp);
+        let coolest = chunk.iter().min_by(by_temp).map(row);
+        let hottest = chunk.iter().max_by(by_temp).map(row);
+        let (Some(coolest), Some(hottest)) = (coolest, hottest) else {
+            continue;
+        };
+        let (first, second) = if coolest[0] <= hottest[0] {
+            (coolest, hottest)
+        } else {
+            (hottest, coolest)
+        };
+        rows.push(first);
+        if second != first {
+            rows.push(second);
+        }
+    }
+    rows
+}
+
+/// Time spans over which `active` holds: each run of samples lasts from its first sample to the
+/// sample after it (or its own last sample at the end of the data).
+fn active_intervals(samples: &[Sample], active: impl Fn(&Sample) -> bool) -> Vec<(f64, f64)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, s) in samples.iter().enumerate() {
+        match (active(s), start) {
+            (true, None) => start = Some(s.t),
+            (false, Some(t0)) => {
+                spans.push((t0, s.t));
+                start = None;
+            }
+            _ => {}
+        }
+        if i + 1 == samples.len() {
+            if let Some(t0) = start {
+                spans.push((t0, s.t));
+            }
+        }
+    }
+    spans
+}
+
+/// Times at which each sensor had no reading (a dropout: NaN live, `null` in a trace file).
+pub(crate) fn dropouts(samples: &[Sample]) -> [Vec<f64>; 3] {
+    let mut times: [Vec<f64>; 3] = Default::default();
+    for s in samples {
+        for (i, reading) in s.sensors().into_iter().enumerate() {
+            if !reading.is_finite() {
+                times[i].push(s.t);
+            }
+        }
+    }
+    times
+}
+
+/// The samples from `t0` to `t1`, e.g. the time range a plot shows.
+fn samples_within(samples: &[Sample], (t0, t1): (f64, f64)) -> &[Sample] {
+    let end = samples.partition_point(|s| s.t <= t1);
+    &samples[samples[..end].partition_point(|s| s.t < t0)..end]
+}
+
+/// Seconds before and after the trip shown when "pause on trip" zooms to it
+const TRIP_FOCUS_BEFORE_S: f64 = 10.0;
+const TRIP_FOCUS_AFTER_S: f64 = 2.0;
+
+/// What the callout of "pause on trip" shows, from the first tripped sample.
+#[derive(Debug, PartialEq)]
+struct TripCallout {
+    t: f64,
+    reason: String,
+    readings: [f64; 3],
+    // Channels that voted for the trip (none known for a reason recorded by name only)
+    channels: [bool; 3],
+    // Distances to the over-temperature and disagreement trips at that sample
+    trip_margin: Option<f64>,
+    spread_margin: Option<f64>,
+}
+
+fn trip_callout(samples: &[Sample], cfg: &SafetyConfig, trip_temp: f64) -> Option<TripCallout> {
+    let sample = samples.iter().find(|s| s.scram)?;
+    let reason = sample.reason.as_deref();
+    let readings = sample.sensors();
+    Some(TripCallout {
+        t: sample.t,
+        reason: reason.map_or_else(|| "unknown reason".to_string(), RowReason::to_string),
+        readings,
+        channels: match reason {
+            Some(RowReason::Trip(trip)) => trip.channels,
+            _ => [false; 3],
+        },
+        trip_margin: cfg.voted_temp(readings).map(|v| trip_temp - v),
+        spread_margin: valid_spread(cfg, readings).map(|d| cfg.max_sensor_delta_c - d),
+    })
+}
+
+/// The trip callout; true when "Back to full view" was clicked.
+fn trip_callout_ui(ui: &mut egui::Ui, callout: &TripCallout) -> bool {
+    let margin = |m: Option<f64>| m.map_or_else(|| "—".to_string(), |m| format!("{m:.2} °C"));
+    egui::Frame::group(ui.style())
+        .fill(egui::Color32::from_rgba_unmultiplied(200, 0, 0, 24))
+        .show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                ui.colored_label(egui::Color32::RED, format!("TRIP at {:.2} s", callout.t));
+                ui.label(&callout.reason);
+            });
+            ui.horizontal(|ui| {
+                for (i, reading) in callout.readings.iter().enumerate() {
+                    let text = if reading.is_nan() {
+                        format!("S{}: dropout", i + 1)
+                    } else {
+                        format!("S{}: {reading:.2} °C", i + 1)
+                    };
+                    if callout.channels[i] {
+                        ui.strong(format!("{text} (voted)"));
+                    } else {
+                        ui.label(text);
+                    }
+                }
+            });
+            ui.label(format!(
+                "Trip margin {}   Disagreement margin {}",
+                margin(callout.trip_margin),
+                margin(callout.spread_margin)
+            ));
+            ui.button("Back to full view").clicked()
+        })
+        .inner
+}
+
+/// Overshoot, settling and the other setpoint-tracking metrics of `samples`; None without at
+/// least two samples carrying a setpoint.
+pub(crate) fn control_metrics(samples: &[Sample]) -> Option<ControlMetrics> {
+    let points: Vec<ResponsePoint> = samples
+        .iter()
+        .map(|s| ResponsePoint {
+            t: s.t,
+            temp: s.true_temp,
+            setpoint: s.setpoint,
+            saturated: s.saturated,
+        })
+        .collect();
+    ControlMetrics::of(&points)
+}
+
+/// The "Controller metrics" table.
+fn metrics_table(ui: &mut egui::Ui, metrics: Option<&ControlMetrics>) {
+    let Some(m) = metrics else {
+        ui.weak("No setpoint recorded over this range");
+        return;
+    };
+    let or_dash = |value: Option<f64>, unit: &str| {
+        value.map_or_else(|| "—".to_string(), |v| format!("{v:.2} {unit}"))
+    };
+    let rows = [
+        (
+            "Max overshoot",
+            format!(
+                "{:.2} °C ({})",
+                m.overshoot_c,
+                or_dash(m.overshoot_pct, "%")
+            ),
+        ),
+        ("2% settling time", or_dash(m.settling_time_s, "s")),
+        (
+            "Steady-state error",
+            format!("{:.3} °C", m.steady_state_error_c),
+        ),
+        ("IAE", format!("{:.1} °C·s", m.iae)),
+        ("Time saturated", format!("{:.2} s", m.saturated_s)),
+    ];
+    egui::Grid::new("control_metrics")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            for (name, value) in rows {
+                ui.label(name);
+                ui.monospace(value);
+                ui.end_row();
+            }
+        });
+}
+
+/// Residuals of one sensor channel, reading − true temperature, for the "Sensor residuals" panel.
+/// Replays record the true temperature too, so both modes measure against it.
+#[derive(Debug, Default)]
+struct Residuals {
+    values: Vec<f64>,
+    // Samples left out because the reading (or the truth) was NaN
+    missing: usize,
+}
+
+impl Residuals {
+    fn of(samples: &[Sample], channel: usize) -> Self {
+        let mut residuals = Self::default();
+        for s in samples {
+            let r = s.sensors()[channel] - s.true_temp;
+            if r.is_finite() {
+                residuals.values.push(r);
+            } else {
+                residuals.missing += 1;
+            }
+        }
+        residuals
+    }
+
+    fn mean(&self) -> Option<f64> {
+        let n = self.values.len();
+        (n > 0).then(|| self.values.iter().sum::<f64>() / n as f64)
+    }
+
+    /// Sample standard deviation; needs two residuals.
+    fn std_dev(&self) -> Option<f64> {
+        let mean = self.mean()?;
+        let n = self.values.len();
+        let squares: f64 = self.values.iter().map(|r| (r - mean).powi(2)).sum();
+        (n > 1).then(|| (squares / (n - 1) as f64).sqrt())
+    }
+
+    /// Equal-width bins over the range of the residuals, about √n of them (at most
+    /// `RESIDUAL_MAX_BINS`): the lower edge, the bin width and the count in each bin.
+    fn histogram(&self) -> Option<(f64, f64, Vec<usize>)> {
+        let lo = self.values.iter().copied().reduce(f64::min)?;
+        let hi = self.values.iter().copied().reduce(f64::max)?;
+        let bins = (self.values.len() as f64).sqrt().round() as usize;
+        let bins = bins.clamp(1, RESIDUAL_MAX_BINS);
+        // All residuals equal: one bin around them
+        let (lo, width, bins) = if hi > lo {
+            (lo, (hi - lo) / bins as f64, bins)
+        } else {
+            (lo - 0.05, 0.1, 1)
+        };
+        let mut counts = vec![0; bins];
+        for r in &self.values {
+            let bin = ((r - lo) / width) as usize;
+            counts[bin.min(bins - 1)] += 1;
+        }
+        Some((lo, width, counts))
+    }
+}
+
+/// Most bins a residual histogram is split into
+const RESIDUAL_MAX_BINS: usize = 50;
+
+/// The bar under a stacked plot pane, which resizes it when dragged. Returns whether it did.
+fn resize_handle(ui: &mut egui::Ui, height: &mut f32) -> bool {
+    let size = egui::vec2(ui.available_width(), 6.0);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::drag());
+    let response = response.on_hover_cursor(egui::CursorIcon::ResizeVertical);
+    let stroke = if response.hovered() || response.dragged() {
+        ui.visuals().widgets.hovered.fg_stroke
+    } else {
+        ui.visuals().widgets.noninteractive.bg_stroke
+    };
+    ui.painter().hline(rect.x_range(), rect.center().y, stroke);
+    let dragged = response.dragged() && response.drag_delta().y != 0.0;
+    if dragged {
+        *height =
+            (*height + response.drag_delta().y).clamp(*PANE_HEIGHTS.start(), *PANE_HEIGHTS.end());
+    }
+    dragged
+}
+
+/// One channel of the "Sensor residuals" panel: mean and standard deviation over the histogram.
+fn residual_histogram(ui: &mut egui::Ui, channel: usize, residuals: &Residuals) {
+    let stat = |v: Option<f64>| v.map_or("—".to_string(), |v| format!("{v:+.3} °C"));
+    let mut text = format!(
+        "S{}: mean {}  σ {}",
+        channel + 1,
+        stat(residuals.mean()),
+        stat(residuals.std_dev()).replace('+', "")
+    );
+    if residuals.missing > 0 {
+        text.push_str(&format!("  ({} NaN left out)", residuals.missing));
+    }
+    ui.label(text);
+    let bars: Vec<Bar> = residuals
+        .histogram()
+        .map(|(lo, width, counts)| {
+            let bar = |(k, n): (usize, usize)| {
+                Bar::new(lo + (k as f64 + 0.5) * width, n as f64).width(width)
+            };
+            counts.into_iter().enumerate().map(bar).collect()
+        })
+        .unwrap_or_default();
+    Plot::new(("residuals_plot", channel))
+        .height(140.0)
+        .allow_scroll(false)
+        .show(ui, |plot_ui| {
+            plot_ui.bar_chart(BarChart::new(bars).name(format!("S{} residual (°C)", channel + 1)));
+        });
+}
+
+/// Shade the post-trip region red, with a boundary line at the trip, and alarm spans lighter.
+/// Segments the phase-plane trajectory is drawn in, fading from the oldest to the newest
+const PHASE_SEGMENTS: usize = 16;
+
+/// The phase-plane trajectory (power, temperature), older stretches fainter, with markers at its
+/// start, its newest point and the trip.
+fn draw_phase(plot_ui: &mut PlotUi, phase: &[[f64; 3]], trip: Option<[f64; 2]>) {
+    let color = egui::Color32::from_rgb(31, 119, 180);
+    let segment = phase.len().div_ceil(PHASE_SEGMENTS).max(1);
+    let count = phase.len().div_ceil(segment);
+    for (i, start) in (0..phase.len()).step_by(segment).enumerate() {
+        // Each stretch starts where the one before ended, so the line stays connected
+        let stretch = &phase[start.saturating_sub(1)..(start + segment).min(phase.len())];
+        let opacity = 0.15 + 0.85 * (i + 1) as f32 / count as f32;
+        let line: PlotPoints = stretch.iter().map(|[_, p, y]| [*p, *y]).collect();
+        plot_ui.line(
+            Line::new(line)
+                .name("Trajectory")
+                .color(color.gamma_multiply(opacity)),
+        );
+    }
+    let marker = |point: [f64; 2], name: &str, shape, color| {
+        Points::new(vec![point])
+            .name(name)
+            .shape(shape)
+            .radius(6.0)
+            .filled(true)
+            .color(color)
+    };
+    if let (Some(first), Some(last)) = (phase.first(), phase.last()) {
+        let start = marker(
+            [first[1], first[2]],
+            "Start",
+            MarkerShape::Circle,
+            egui::Color32::from_rgb(0, 160, 0),
+        );
+        plot_ui.points(start);
+        let now = marker([last[1], last[2]], "Now", MarkerShape::Diamond, color);
+        plot_ui.points(now);
+    }
+    if let Some(point) = trip {
+        let trip = marker(point, "Trip", MarkerShape::Cross, egui::Color32::RED);
+        plot_ui.points(trip);
+    }
+}
+
+/// The sensor envelope: the band of the valid readings, red where it is wider than
+/// `max_delta` (which trips the disagreement check), and a thin line `max_delta` above its
+/// bottom edge, the most the top edge may reach.
+fn draw_envelope(plot_ui: &mut PlotUi, band: &[[f64; 3]], max_delta: f64) {
+    // Plot polygons have to be convex, so the band is drawn as one quad per segment
+    for pair in band.windows(2) {
+        let [[t0, low0, high0], [t1, low1, high1]] = [pair[0], pair[1]];
+        if ![low0, high0, low1, high1].iter().all(|v| v.is_finite()) {
+            continue;
+        }
+        let quad = vec![[t0, low0], [t1, low1], [t1, high1], [t0, high0]];
+        let (name, fill) = if high0 - low0 > max_delta || high1 - low1 > max_delta {
+            (
+                "Sensor envelope (over limit)",
+                egui::Color32::from_rgba_unmultiplied(255, 0, 0, 90),
+            )
+        } else {
+            (
+                "Sensor envelope",
+                egui::Color32::from_rgba_unmultiplied(100, 150, 250, 70),
+            )
+        };
+        plot_ui.polygon(
+            Polygon::new(PlotPoints::from(quad))
+                .name(name)
+                .fill_color(fill)
+                .stroke(egui::Stroke::NONE),
+        );
+    }
+    let limit: PlotPoints = band
+        .iter()
+        .map(|[t, low, _]| [*t, low + max_delta])
+        .collect();
+    plot_ui.line(
+        Line::new(limit)
+            .name("Disagreement limit")
+            .color(egui::Color32::from_rgb(200, 60, 60))
+            .width(0.5)
+            .style(LineStyle::dashed_dense()),
+    );
+}
+
+fn shade_trip_and_alarms(
+    plot_ui: &mut PlotUi,
+    scram: Option<(f64, f64)>,
+    alarms: &[(f64, f64)],
+    (y0, y1): (f64, f64),
+) {
+    let band = |(t0, t1): (f64, f64)| -> PlotPoints {
+        vec![[t0, y0], [t1, y0], [t1, y1], [t0, y1]].into()
+    };
+    for &span in alarms {
+        plot_ui.polygon(
+            Polygon::new(band(span))
+                .name("Sensor alarm")
+                .fill_color(egui::Color32::from_rgba_unmultiplied(255, 200, 0, 30))
+                .stroke(egui::Stroke::NONE),
+        );
+    }
+    if let Some(span) = scram {
+        plot_ui.polygon(
+            Polygon::new(band(span))
+                .name("Post-SCRAM")
+                .fill_color(egui::Color32::from_rgba_unmultiplied(255, 0, 0, 40))
+                .stroke(egui::Stroke::NONE),
+        );
+        plot_ui.vline(VLine::new(span.0).name("SCRAM").color(egui::Color32::RED));
+    }
+}
+
+impl App {
+    /// Horizontal reference levels of the temperature plot. Recorded (possibly time-varying)
+    /// setpoints are a series; legacy replays have none, but may carry the initial setpoint in
+    /// their header or have it typed in. Live runs use the sliders; replays use the levels from
+    /// the header.
+    pub(crate) fn reference_levels(&self, has_setpoint: bool) -> Vec<(&'static str, f64)> {
+        let vis = &self.visible;
+        let mut levels = Vec::new();
+        if vis.setpoint && !has_setpoint && self.replay_loaded {
+            levels.extend(
+                self.replay_setpoint
+                    .or(self.legacy_setpoint)
+                    .map(|v| ("Setpoint", v)),
+            );
+        }
+        if vis.levels && self.replay_loaded {
+            let trip = self.replay_trip_temp.or(self.legacy_trip_temp);
+            levels.extend(trip.map(|v| ("Trip temp", v)));
+        } else if vis.levels {
+            levels.push(("Trip temp", self.trip_temp));
+        }
+        levels
+    }
+
+    /// The comparison samples up to the primary view's time, so scrubbing and playback move both.
+    pub(crate) fn compare_visible(&self) -> &[Sample] {
+        let t_end = self.samples.last().map_or(f64::NEG_INFINITY, |s| s.t);
+        samples_until(&self.compare_all, self.compare_offset, t_end)
+    }
+
+    /// The samples the plots show: all of them, or the follow window.
+    pub(crate) fn plotted(&self) -> &[Sample] {
+        if self.follow {
+            follow_window(&self.samples, self.follow_window_s)
+        } else {
+            &self.samples
+        }
+    }
+
+    /// The comparison samples over the time span of `plotted`.
+    fn compare_plotted(&self) -> &[Sample] {
+        let compare = self.compare_visible();
+        match self.plotted().first() {
+            Some(first) if self.follow => {
+                &compare[compare.partition_point(|s| s.t + self.compare_offset < first.t)..]
+            }
+            _ => compare,
+        }
+    }
+
+    // A replay only shows the trip once the view reaches it (see `seek_replay`)
+    pub(crate) fn scram_time_for_plot(&self) -> Option<f64> {
+        self.samples.iter().find(|s| s.scram).map(|s| s.t)
+    }
+
+    /// "Pause on trip": the first frame showing a trip pauses the live run or the replay and zooms
+    /// the plots to it. Each trip does so once; a view without one (after a reset, or scrubbed
+    /// back) drops the zoom.
+    pub(crate) fn focus_on_trip(&mut self, scram_time: Option<f64>) {
+        let Some(t) = scram_time else {
+            self.trip_focus = None;
+            self.trip_seen = None;
+            return;
+        };
+        if self.trip_seen == Some(t) {
+            return;
+        }
+        self.trip_seen = Some(t);
+        if !self.pause_on_trip {
+            return;
+        }
+        self.trip_focus = Some(t);
+        if self.replay_loaded {
+            self.replay_playing = false;
+        } else if self.running {
+            self.stop_live();
+        }
+    }
+
+    /// Plant parameters the heat flows are worked out with: the replay's from its header (the
+    /// defaults when it has none), or the live run's.
+    fn plot_plant(&self) -> PlantParams {
+        if self.replay_loaded {
+            self.replay_plant.unwrap_or_default()
+        } else {
+            self.live.engine.plant
+        }
+    }
+
+    /// Rebuild the plot lines of what is plotted, and of the comparison, for this frame.
+    pub(crate) fn refresh_plots(&mut self) {
+        let mut plot_data = std::mem::take(&mut self.plot_data);
+        let plant = self.plot_plant();
+        plot_data.refresh(self.plotted(), self.plot_max_points, plant);
+        self.plot_data = plot_data;
+        let mut compare_plot = std::mem::take(&mut self.compare_plot);
+        compare_plot.refresh(self.compare_plotted(), self.plot_max_points, plant);
+        self.compare_plot = compare_plot;
+    }
+
+    /// The plot panes with their controls, and what is shown below them.
+    pub(crate) fn plot_panes(&mut self, ui: &mut egui::Ui, scram_time: Option<f64>) {
+        if self.samples.is_empty() {
+            ui.label("No data yet. Run LIVE or Load a REPLAY file.");
+            return;
+        }
+
+        let t_end = self.samples.last().map(|s| s.t).unwrap_or(0.0);
+
+        self.margin_gauge(ui);
+
+        ui.heading("Traces");
+        ui.horizontal_wrapped(|ui| {
+            let vis = &mut self.visible;
+            ui.checkbox(&mut vis.true_temp, "Temp");
+            let shown = vis.sensors.iter().filter(|on| **on).count();
+            let mut all = shown == 3;
+            let group =
+                egui::Checkbox::new(&mut all, "Sensors").indeterminate(shown == 1 || shown == 2);
+            if ui.add(group).changed() {
+                vis.sensors = [all; 3];
+            }
+            for (i, on) in vis.sensors.iter_mut().enumerate() {
+                ui.checkbox(on, format!("S{}", i + 1));
+            }
+            ui.checkbox(&mut vis.envelope, "Envelope").on_hover_text(
+                "Band from the lowest to the highest valid reading, with their median, in \
+                 place of the sensor lines",
+            );
+            ui.checkbox(&mut vis.setpoint, "Setpoint");
+            ui.checkbox(&mut vis.levels, "Trip levels");
+            if self.compare_path.is_some() {
+                ui.checkbox(&mut vis.compare, "B");
+            }
+            ui.separator();
+            let follow_box = ui
+                .checkbox(&mut self.follow, "Follow (window s)")
+                .on_hover_text("Show only the last seconds, up to now or the replay position");
+            let window = ui.add_enabled(
+                self.follow,
+                egui::Slider::new(&mut self.follow_window_s, 5.0..=600.0).logarithmic(true),
+            );
+            ui.separator();
+            let points = ui
+                .add(
+                    egui::DragValue::new(&mut self.plot_max_points)
+                        .range(500..=200_000)
+                        .speed(100)
+                        .prefix("max points/line: "),
+                )
+                .on_hover_text("Longer data is drawn as the min and max of equal time buckets");
+            if follow_box.changed() && !self.follow {
+                self.plot_reset = true;
+            }
+            // The plot data for this frame is already built; show the change right away
+            if follow_box.changed() || window.changed() || points.changed() {
+                ui.ctx().request_repaint();
+            }
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Panes");
+            let layout = &mut self.layout;
+            for pane in Pane::ALL {
+                let mut on = layout.shown.contains(&pane);
+                if ui.checkbox(&mut on, pane.label()).changed() {
+                    layout.set_shown(pane, on);
+                }
+            }
+            ui.separator();
+            ui.selectable_value(&mut layout.arrangement, PaneArrangement::Stacked, "Stacked")
+                .on_hover_text("Drag the bar under a pane to resize it");
+            ui.selectable_value(&mut layout.arrangement, PaneArrangement::Tabs, "Tabs");
+        });
+        // Zoomed to a trip by "pause on trip"
+        let callout = self.trip_focus.and_then(|_| {
+            trip_callout(
+                &self.samples,
+                &self.live.engine.safety_cfg,
+                self.shown_trip_temp(),
+            )
+        });
+        if let Some(callout) = &callout {
+            if trip_callout_ui(ui, callout) {
+                self.trip_focus = None;
+                self.plot_reset = true;
+                ui.ctx().request_repaint();
+            }
+        }
+        let focus = callout
+            .as_ref()
+            .map(|c| (c.t - TRIP_FOCUS_BEFORE_S, c.t + TRIP_FOCUS_AFTER_S));
+        let voted = callout.as_ref().map_or([false; 3], |c| c.channels);
+        let focus_temp = focus.map(|window| {
+            let shown = samples_within(self.plotted(), window);
+            let temps = shown.iter().flat_map(|s| [s.true_temp, s.s1, s.s2, s.s3]);
+            padded_range(temps.chain([self.shown_trip_temp()]))
+        });
+        let vis = self.visible;
+        let follow = self.follow;
+        let reset = std::mem::take(&mut self.plot_reset);
+        let t_start = self.plotted().first().map_or(0.0, |s| s.t);
+        // Following, pin the x axis to the window and the y axis to what it shows; otherwise
+        // the plots keep their own (automatic or user-chosen) bounds
+        // Time picked in the "Events" panel (a replay moves its position there instead)
+        let cursor_t = self.cursor_t.filter(|_| !self.replay_loaded);
+        let cursor = |plot_ui: &mut PlotUi| {
+            if let Some(t) = cursor_t {
+                plot_ui.vline(VLine::new(t).name("Event").color(egui::Color32::LIGHT_BLUE));
+            }
+        };
+        let fit = |plot_ui: &mut PlotUi, (y0, y1): (f64, f64)| {
+            if let Some((x0, x1)) = focus {
+                plot_ui.set_plot_bounds(PlotBounds::from_min_max([x0, y0], [x1, y1]));
+            } else if follow {
+                let x0 = t_end - self.follow_window_s;
+                plot_ui.set_plot_bounds(PlotBounds::from_min_max([x0, y0], [t_end, y1]));
+            } else if reset {
+                plot_ui.set_auto_bounds(true.into());
+            }
+        };
+        let data = &self.plot_data;
+
+        let points = |line: &[[f64; 2]]| -> PlotPoints { line.to_vec().into() };
+        let has_setpoint = data.setpoint.iter().any(|[_, y]| y.is_finite());
+
+        // Comparison ("B"), shifted by its offset
+        let compare =
+            (vis.compare && !self.compare_plot.temp.is_empty()).then_some(&self.compare_plot);
+        let offset = self.compare_offset;
+        let b_points = |line: &[[f64; 2]]| -> PlotPoints {
+            line.iter().map(|[t, y]| [t + offset, *y]).collect()
+        };
+        let b_scram = compare
+            .and_then(|_| self.compare_visible().iter().find(|s| s.scram))
+            .map(|s| s.t + offset);
+        let b_scram_line = |plot_ui: &mut PlotUi| {
+            if let Some(t) = b_scram {
+                plot_ui.vline(
+                    VLine::new(t)
+                        .name("SCRAM (B)")
+                        .color(egui::Color32::RED)
+                        .style(LineStyle::dashed_loose()),
+                );
+            }
+        };
+
+        let levels = self.reference_levels(has_setpoint);
+
+        // Shown series only, so hiding a railed sensor rescales the view
+        let level_values: Vec<f64> = levels.iter().map(|(_, v)| *v).collect();
+        let (y0, y1) = temp_range(data, compare, &vis, &level_values);
+
+        // Post-trip region up to the end of the view, so it shrinks when scrubbing back
+        let scram_span = scram_time.map(|t| (t, t_end));
+        let alarm_spans = &data.alarms;
+
+        let trip_temp = self.shown_trip_temp();
+        let max_delta = self.live.engine.safety_cfg.max_sensor_delta_c;
+        let default_plant = self.replay_loaded && self.replay_plant.is_none();
+        let setpoint_now = data
+            .setpoint
+            .iter()
+            .rev()
+            .map(|[_, y]| *y)
+            .find(|y| y.is_finite())
+            .unwrap_or(self.setpoint);
+
+        // One pane's plot, returning the bounds it shows; the panes follow each other's zoom
+        // and pan along the time axis
+        let pane_plot = |ui: &mut egui::Ui, pane: Pane, height: f32| {
+            let plot = Plot::new(("pane", pane)).height(height);
+            // The phase plane's x axis is power rather than time
+            let plot = if pane == Pane::PhasePlane {
+                plot.x_axis_label("power (0..1)")
+                    .y_axis_label("true temp (°C)")
+            } else {
+                plot.link_axis("panes", [true, false])
+                    .link_cursor("panes", [true, false].into())
+            };
+            let shown = match pane {
+                Pane::Temperatures => plot.show(ui, |plot_ui| {
+                    if vis.true_temp {
+                        plot_ui.line(Line::new(points(&data.temp)).name("Temp (°C)"));
+                    }
+                    if vis.envelope {
+                        draw_envelope(plot_ui, &data.envelope, max_delta);
+                        plot_ui.line(Line::new(points(&data.median)).name("Sensor median"));
+                    }
+                    // Zoomed to a trip, the channels that voted for it stand out (over the
+                    // envelope too)
+                    for (i, line) in data.sensors.iter().enumerate() {
+                        if !vis.sensors[i] || (vis.envelope && !voted[i]) {
+                            continue;
+                        }
+                        let line = Line::new(points(line));
+                        plot_ui.line(if voted[i] {
+                            line.name(format!("Sensor {} (voted)", i + 1)).width(3.0)
+                        } else {
+                            line.name(format!("Sensor {}", i + 1))
+                        });
+                    }
+                    // The lines just break at dropouts; mark each one along the bottom edge
+                    for (i, times) in data.dropout_marks.iter().enumerate() {
+                        if vis.sensors[i] && !times.is_empty() {
+                            let marks: PlotPoints = times.iter().map(|t| [*t, y0]).collect();
+                            plot_ui.points(
+                                Points::new(marks)
+                                    .shape(MarkerShape::Cross)
+                                    .radius(4.0)
+                                    .name(format!("Sensor {} dropout", i + 1)),
+                            );
+                        }
+                    }
+                    if let Some(b) = compare {
+                        plot_ui.line(
+                            Line::new(b_points(&b.temp))
+                                .name("Temp (°C) (B)")
+                                .style(LineStyle::dashed_loose()),
+                        );
+                        b_scram_line(plot_ui);
+                    }
+                    if vis.setpoint && has_setpoint {
+                        plot_ui.line(Line::new(points(&data.setpoint)).name("Setpoint"));
+                    }
+                    if t_end > 0.0 {
+                        for (name, v) in &levels {
+                            let line: PlotPoints = vec![[t_start, *v], [t_end, *v]].into();
+                            plot_ui.line(Line::new(line).name(*name));
+                        }
+                    }
+
+                    shade_trip_and_alarms(plot_ui, scram_span, alarm_spans, (y0, y1));
+                    cursor(plot_ui);
+                    fit(plot_ui, focus_temp.unwrap_or((y0, y1)));
+                }),
+                Pane::Actuators => plot.show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(points(&data.power)).name("Power (0..1)"));
+                    plot_ui.line(
+                        Line::new(points(&data.power_sat))
+                            .name("Power (saturated)")
+                            .color(egui::Color32::from_rgb(255, 140, 0))
+                            .width(2.5),
+                    );
+                    plot_ui.line(Line::new(points(&data.coolant)).name("Coolant (0..1)"));
+                    shade_trip_and_alarms(plot_ui, scram_span, alarm_spans, (-0.05, 1.05));
+                    cursor(plot_ui);
+                    fit(plot_ui, (-0.05, 1.05));
+                    if let Some(b) = compare {
+                        plot_ui.line(
+                            Line::new(b_points(&b.power))
+                                .name("Power (0..1) (B)")
+                                .style(LineStyle::dashed_loose()),
+                        );
+                        plot_ui.line(
+                            Line::new(b_points(&b.coolant))
+                                .name("Coolant (0..1) (B)")
+                                .style(LineStyle::dashed_loose()),
+                        );
+                        b_scram_line(plot_ui);
+                    }
+                }),
+                Pane::ControllerTerms => plot.show(ui, |plot_ui| {
+                    plot_ui.line(Line::new(points(&data.p_term)).name("P term"));
+                    plot_ui.line(Line::new(points(&data.i_term)).name("I term"));
+                    plot_ui.line(Line::new(points(&data.d_term)).name("D term"));
+                    let terms = [&data.p_term, &data.i_term, &data.d_term];
+                    cursor(plot_ui);
+                    fit(
+                        plot_ui,
+                        padded_range(terms.into_iter().flatten().map(|[_, y]| *y)),
+                    );
+                }),
+                Pane::SafetyMargins => plot.show(ui, |plot_ui| {
+                    // Distances to the over-temperature and disagreement trips; either trips
+                    // at zero
+                    let margin = |line: &[[f64; 2]], limit: f64| -> Vec<[f64; 2]> {
+                        line.iter().map(|[t, y]| [*t, limit - y]).collect()
+                    };
+                    let trip_margin = margin(&data.voted, trip_temp);
+                    let spread_margin = margin(&data.spread, max_delta);
+                    plot_ui.line(Line::new(points(&trip_margin)).name("Trip margin (°C)"));
+                    plot_ui
+                        .line(Line::new(points(&spread_margin)).name("Disagreement margin (°C)"));
+                    if t_end > 0.0 {
+                        let zero: PlotPoints = vec![[t_start, 0.0], [t_end, 0.0]].into();
+                        plot_ui.line(
+                            Line::new(zero)
+                                .name("Trip")
+                                .color(egui::Color32::RED)
+                                .style(LineStyle::dashed_loose()),
+                        );
+                    }
+                    let range = padded_range(
+                        trip_margin
+                            .iter()
+                            .chain(&spread_margin)
+                            .map(|[_, y]| *y)
+                            .chain([0.0]),
+                    );
+                    shade_trip_and_alarms(plot_ui, scram_span, alarm_spans, range);
+                    cursor(plot_ui);
+                    fit(plot_ui, range);
+                }),
+                Pane::PhasePlane => plot.show(ui, |plot_ui| {
+                    draw_phase(plot_ui, &data.phase, data.phase_trip);
+                    if vis.setpoint {
+                        let name = "Setpoint";
+                        plot_ui.hline(HLine::new(setpoint_now).name(name));
+                    }
+                    if vis.levels {
+                        let trip = HLine::new(trip_temp).name("Trip temp");
+                        plot_ui.hline(trip.color(egui::Color32::RED));
+                    }
+                    if reset {
+                        plot_ui.set_auto_bounds(true.into());
+                    }
+                }),
+                Pane::HeatFlows => {
+                    if default_plant {
+                        ui.small("Trace header without plant parameters: drawn with the defaults");
+                    }
+                    plot.show(ui, |plot_ui| {
+                        plot_ui.line(Line::new(points(&data.heating)).name("Heating (°C/s)"));
+                        plot_ui.line(Line::new(points(&data.cooling)).name("Cooling (°C/s)"));
+                        plot_ui.line(Line::new(points(&data.net_heat)).name("Net (°C/s)"));
+                        let flows = [&data.heating, &data.cooling, &data.net_heat];
+                        cursor(plot_ui);
+                        fit(
+                            plot_ui,
+                            padded_range(flows.into_iter().flatten().map(|[_, y]| *y)),
+                        );
+                    })
+                }
+            };
+            *shown.transform.bounds()
+        };
+
+        let mut layout = self.layout.clone();
+        // The time range on screen, and the temperature range when that pane is shown
+        let mut x_view = None;
+        let mut temp_view = None;
+        let mut show = |ui: &mut egui::Ui, pane: Pane, height: f32| {
+            let bounds = pane_plot(ui, pane, height);
+            if pane != Pane::PhasePlane {
+                x_view.get_or_insert((bounds.min()[0], bounds.max()[0]));
+            }
+            if pane == Pane::Temperatures {
+                temp_view = Some((bounds.min()[1], bounds.max()[1]));
+            }
+        };
+        match layout.arrangement {
+            _ if layout.shown.is_empty() => {
+                ui.label("No plot panes shown; pick some under \"Panes\".");
+            }
+            PaneArrangement::Stacked => {
+                egui::ScrollArea::vertical()
+                    .id_salt("panes")
+                    .max_height((ui.available_height() - 80.0).max(160.0))
+                    .show(ui, |ui| {
+                        for pane in layout.shown.clone() {
+                            let mut height = layout.height(pane);
+                            show(ui, pane, height);
+                            if resize_handle(ui, &mut height) {
+                                layout.heights.insert(pane, height);
+                            }
+                        }
+                    });
+            }
+            PaneArrangement::Tabs => {
+                let mut tab = layout.tab();
+                ui.horizontal(|ui| {
+                    for pane in &layout.shown {
+                        ui.selectable_value(&mut tab, Some(*pane), pane.label());
+                    }
+                });
+                if let Some(pane) = tab {
+                    layout.tab = pane;
+                    show(ui, pane, (ui.available_height() - 80.0).max(160.0));
+                }
+            }
+        }
+        let plot_view = x_view.map(|(x0, x1)| {
+            let (bottom, top) = temp_view.unwrap_or((y0, y1));
+            ([x0, bottom], [x1, top])
+        });
+        self.layout = layout;
+
+        egui::CollapsingHeader::new("Sensor residuals")
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.small("Reading − true temperature over the time range shown above");
+                let range = plot_view.map_or((t_start, t_end), |(min, max)| (min[0], max[0]));
+                let shown = samples_within(self.plotted(), range);
+                ui.columns(3, |columns| {
+                    for (i, ui) in columns.iter_mut().enumerate() {
+                        residual_histogram(ui, i, &Residuals::of(shown, i));
+                    }
+                });
+            });
+        egui::CollapsingHeader::new("Controller metrics")
+            .default_open(false)
+            .show(ui, |ui| {
+                ui.small(
+                    "Against the setpoint over the time range shown above; steady-state \
+                     error over its last 10%",
+                );
+                let range = plot_view.map_or((t_start, t_end), |(min, max)| (min[0], max[0]));
+                let shown = samples_within(self.plotted(), range);
+                metrics_table(ui, control_metrics(shown).as_ref());
+            });
+
+        ui.separator();
+        // For "Export plot PNG", which draws what is on screen
+        self.plot_view = plot_view;
+        let last = self.samples.last().unwrap();
+        ui.label(format!(
+            "t={:.2}s  temp={:.2}°C  power={:.2}  coolant={:.2}",
+            last.t, last.true_temp, last.power, last.coolant
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    use safety::TripRecord;
+    use sim::{PlantParams, Sensor, SensorFault};
+
+    use crate::tests::sample;
+
+    #[test]
+    fn active_intervals_cover_each_run_until_the_next_sample() {
+        let samples: Vec<Sample> = [false, true, true, false, false, true]
+            .into_iter()
+            .enumerate()
+            .map(|(i, alarm)| Sample {
+                alarm,
+                ..sample(i as f64, false)
+            })
+            .collect();
+        assert_eq!(
+            active_intervals(&samples, |s| s.alarm),
+            [(1.0, 3.0), (5.0, 5.0)]
+        );
+        assert!(active_intervals(&samples[..1], |s| s.alarm).is_empty());
+        assert!(active_intervals(&[], |s| s.alarm).is_empty());
+    }
+
+    #[test]
+    fn hidden_series_do_not_stretch_the_temperature_range() {
+        let mut samples = vec![sample(0.0, false), sample(1.0, false)];
+        // Sensor 1 railed at the top of its range
+        samples[1].s1 = 2000.0;
+        let data = PlotData::build(&samples, DEFAULT_PLOT_POINTS, PlantParams::default());
+        let all = SeriesVisibility::default();
+        let (_, top) = temp_range(&data, None, &all, &[]);
+        assert!(top > 2000.0, "{top}");
+
+        let vis = SeriesVisibility {
+            sensors: [false; 3],
+            ..all
+        };
+        // True temperature 300..300.33, setpoint 380 and the trip line at 420
+        let (bottom, top) = temp_range(&data, None, &vis, &[420.0]);
+        assert!((bottom - 288.0).abs() < 1e-9, "{bottom}");
+        assert!((top - 432.0).abs() < 1e-9, "{top}");
+
+        let nothing = SeriesVisibility {
+            true_temp: false,
+            setpoint: false,
+            ..vis
+        };
+        assert_eq!(temp_range(&data, None, &nothing, &[]), (-1.0, 2.0));
+    }
+
+    #[test]
+    fn follow_window_keeps_the_last_seconds() {
+        // A 300 s run at 10 Hz with an early excursion
+        let mut samples: Vec<Sample> = (0..=3000)
+            .map(|i| sample(f64::from(i) / 10.0, false))
+            .collect();
+        samples[100].true_temp = 1000.0;
+
+        let window = follow_window(&samples, 30.0);
+        assert_eq!(window.len(), 301);
+        assert_eq!((window[0].t, window[300].t), (270.0, 300.0));
+        // The excursion is out of view, so it does not squash the y axis
+        let vis = SeriesVisibility {
+            sensors: [false; 3],
+            setpoint: false,
+            ..SeriesVisibility::default()
+        };
+        let (bottom, top) = temp_range(
+            &PlotData::build(window, 5000, PlantParams::default()),
+            None,
+            &vis,
+            &[],
+        );
+        assert!((bottom - 389.0).abs() < 1e-9, "{bottom}");
+        assert!((top - 401.0).abs() < 1e-9, "{top}");
+
+        // Shorter data and wider windows keep everything; scrubbing back moves the window
+        assert_eq!(follow_window(&samples[..50], 30.0).len(), 50);
+        assert_eq!(follow_window(&samples, 600.0).len(), samples.len());
+        assert_eq!(follow_window(&samples[..1001], 30.0)[0].t, 70.0);
+        assert!(follow_window(&[], 30.0).is_empty());
+    }
+
+    #[test]
+    fn decimation_keeps_each_buckets_extremes_in_time_order() {
+        // 0.0..=10.0 s, every 0.1 s: 101 samples, 10 buckets of 1 s for 20 points
+        let mut samples: Vec<Sample> = (0..=100)
+            .map(|i| sample(f64::from(i) / 10.0, false))
+            .collect();
+        // A one-sample spike and dip, and a bucket of dropouts
+        samples[42].true_temp = 900.0;
+        samples[47].true_temp = -5.0;
+        for s in &mut samples[70..80] {
+            s.true_temp = f64::NAN;
+        }
+        // Short data is drawn as is
+        assert!(bucket_ranges(&samples, 101).is_none());
+        assert_eq!(
+            PlotData::build(&samples, 101, PlantParams::default())
+                .temp
+                .len(),
+            101
+        );
+
+        let ranges = bucket_ranges(&samples, 20).unwrap();
+        assert_eq!(ranges.len(), 10);
+        assert_eq!((ranges[0].clone(), ranges[9].clone()), (0..10, 90..101));
+        let points = PlotData::build(&samples, 20, PlantParams::default()).temp;
+        let times: Vec<f64> = points.iter().map(|[t, _]| *t).collect();
+        let buckets = Buckets::new(&samples, 10);
+        // Bucket k holds [k, k + 1) s; the last one also its end, 10 s
+        assert_eq!(buckets.of(0.9999), 0);
+        assert_eq!(buckets.of(samples[10].t), 1);
+        assert_eq!(buckets.of(samples[100].t), 9);
+        let per_bucket = times.iter().fold([0; 10], |mut n, t| {
+            n[buckets.of(*t)] += 1;
+            n
+        });
+        assert_eq!(per_bucket, [2, 2, 2, 2, 2, 2, 2, 1, 2, 2]);
+        assert!(times.windows(2).all(|w| w[0] < w[1]), "{times:?}");
+        // Rising ramp: each bucket runs from its first sample to its last
+        assert_eq!(points[0], [0.0, samples[0].true_temp]);
+        assert_eq!(points[1], [samples[9].t, samples[9].true_temp]);
+        // The spike comes before the dip, and neither is averaged away
+        assert_eq!(points[8], [samples[42].t, 900.0]);
+        assert_eq!(points[9], [samples[47].t, -5.0]);
+        // A bucket with nothing to draw breaks the line once
+        assert_eq!(times[14], samples[70].t);
+        assert!(points[14][1].is_nan());
+        assert_eq!(points.len(), 19);
+    }
+
+    #[test]
+    fn plot_data_is_rebuilt_when_the_samples_change() {
+        let mut samples: Vec<Sample> = (0..100)
+            .map(|i| sample(f64::from(i) / 10.0, false))
+            .collect();
+        let mut data = PlotData::default();
+        data.refresh(&samples, 20, PlantParams::default());
+        assert_eq!(data.temp.len(), 20);
+        // Sensor 2 drops out throughout: 100 dropouts, marked once per bucket
+        assert_eq!(data.dropout_counts, [0, 100, 0]);
+        assert_eq!(data.dropout_marks[1].len(), 10);
+
+        samples[99].true_temp = 900.0;
+        data.refresh(&samples, 20, PlantParams::default());
+        assert_ne!(data.temp[19][1], 900.0, "same samples, same cache");
+        data.invalidate();
+        data.refresh(&samples, 20, PlantParams::default());
+        assert_eq!(data.temp[19][1], 900.0);
+
+        samples.truncate(50);
+        data.refresh(&samples, 20, PlantParams::default());
+        assert_eq!(data.temp.last().unwrap()[0], samples[49].t);
+        data.refresh(&samples, 1000, PlantParams::default());
+        assert_eq!(data.temp.len(), 50);
+        assert_eq!(data.dropout_marks[1].len(), 50);
+    }
+
+    #[test]
+    fn residuals_show_noise_and_bias_per_sensor() {
+        let mut sensors = [Sensor::new(1), Sensor::new(2), Sensor::new(3)];
+        for s in &mut sensors {
+            s.noise_std = 0.15;
+        }
+        sensors[1].fault = SensorFault::Bias { value: 20.0 };
+        sensors[2].fault = SensorFault::DropoutEvery { n: 10 };
+        let samples: Vec<Sample> = (0..4000)
+            .map(|i| {
+                let t = f64::from(i) * 0.05;
+                let true_temp = 350.0 + t / 10.0;
+                let [s1, s2, s3] = sensors.each_mut().map(|s| s.read_temp(true_temp, 0.05));
+                Sample {
+                    true_temp,
+                    s1,
+                    s2,
+                    s3,
+                    ..sample(t, false)
+                }
+            })
+            .collect();
+
+        let [s1, s2, s3] = [0, 1, 2].map(|i| Residuals::of(&samples, i));
+        assert!(s1.mean().unwrap().abs() < 0.01, "{:?}", s1.mean());
+        assert!(
+            (s1.std_dev().unwrap() - 0.15).abs() < 0.01,
+            "{:?}",
+            s1.std_dev()
+        );
+        assert!((s2.mean().unwrap() - 20.0).abs() < 0.01, "{:?}", s2.mean());
+        assert!(
+            (s2.std_dev().unwrap() - 0.15).abs() < 0.01,
+            "{:?}",
+            s2.std_dev()
+        );
+        assert_eq!((s3.values.len(), s3.missing), (3600, 400));
+
+        let (lo, width, counts) = s1.histogram().unwrap();
+        assert_eq!(counts.len(), RESIDUAL_MAX_BINS);
+        assert_eq!(counts.iter().sum::<usize>(), 4000);
+        let hi = lo + width * counts.len() as f64;
+        assert!(lo < -0.3 && hi > 0.3, "{lo} {hi}");
+        // Roughly Gaussian: the middle bins hold the most
+        let peak = counts
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, n)| **n)
+            .unwrap()
+            .0;
+        assert!((15..35).contains(&peak), "{counts:?}");
+        let (lo, _, _) = s2.histogram().unwrap();
+        assert!(lo > 19.0, "{lo}");
+
+        // A window of the samples, and a channel with nothing to bin
+        let shown = samples_within(&samples, (10.0, 20.0));
+        assert_eq!(
+            (shown.len(), shown[0].t, shown[shown.len() - 1].t),
+            (201, 10.0, 20.0)
+        );
+        let none = Residuals::of(&shown[..0], 0);
+        assert_eq!(
+            (none.mean(), none.std_dev(), none.histogram()),
+            (None, None, None)
+        );
+        let flat = Residuals {
+            values: vec![0.5; 4],
+            missing: 0,
+        };
+        assert_eq!(flat.histogram(), Some((0.45, 0.1, vec![4])));
+    }
+
+    #[test]
+    fn plot_layout_keeps_panes_in_order_and_survives_storage() {
+        let mut layout = PlotLayout::default();
+        layout.set_shown(Pane::SafetyMargins, true);
+        layout.set_shown(Pane::Actuators, false);
+        layout.set_shown(Pane::HeatFlows, true);
+        layout.set_shown(Pane::Temperatures, true);
+        assert_eq!(
+            layout.shown,
+            [Pane::Temperatures, Pane::SafetyMargins, Pane::HeatFlows]
+        );
+        layout.heights.insert(Pane::SafetyMargins, 300.0);
+        assert_eq!(layout.height(Pane::SafetyMargins), 300.0);
+        assert_eq!(layout.height(Pane::HeatFlows), 160.0);
+
+        // Tabs fall back to the first pane shown once the picked one is hidden
+        layout.arrangement = PaneArrangement::Tabs;
+        layout.tab = Pane::HeatFlows;
+        assert_eq!(layout.tab(), Some(Pane::HeatFlows));
+        layout.set_shown(Pane::HeatFlows, false);
+        assert_eq!(layout.tab(), Some(Pane::Temperatures));
+
+        let stored = serde_json::to_string(&layout).unwrap();
+        assert_eq!(serde_json::from_str::<PlotLayout>(&stored).unwrap(), layout);
+        // Missing fields (e.g. from an older version) take their defaults
+        let older: PlotLayout = serde_json::from_str(r#"{"arrangement":"Tabs"}"#).unwrap();
+        assert_eq!(older.shown, PlotLayout::default().shown);
+    }
+
+    #[test]
+    fn margin_and_heat_flow_lines_follow_the_samples() {
+        let mut samples: Vec<Sample> = (0..4).map(|i| sample(f64::from(i), false)).collect();
+        samples[1].s1 = 320.0;
+        samples[1].s2 = 310.0;
+        samples[1].s3 = f64::NAN;
+        samples[2].s3 = f64::NAN;
+        let plant = PlantParams::default();
+        let data = PlotData::build(&samples, DEFAULT_PLOT_POINTS, plant);
+
+        let s = &samples[1];
+        assert_eq!(data.voted[1], [1.0, 310.0]);
+        assert_eq!(data.spread[1], [1.0, 10.0]);
+        // Fewer than two valid readings: no vote, no spread
+        assert!(data.voted[2][1].is_nan() && data.spread[2][1].is_nan());
+
+        let heating = plant.k_power * s.power / plant.thermal_mass;
+        let cooling =
+            plant.k_cool * s.coolant * (s.true_temp - plant.ambient_c) / plant.thermal_mass;
+        assert_eq!(data.heating[1][1], heating);
+        assert_eq!(data.cooling[1][1], cooling);
+        assert_eq!(data.net_heat[1][1], heating - cooling);
+
+        // Other plant parameters rebuild the lines
+        let mut data = data;
+        let hotter = PlantParams {
+            k_power: 1200.0,
+            ..plant
+        };
+        data.refresh(&samples, DEFAULT_PLOT_POINTS, hotter);
+        assert_eq!(data.heating[1][1], 2.0 * heating);
+    }
+
+    #[test]
+    fn dropouts_are_counted_per_sensor() {
+        let mut samples: Vec<Sample> = (0..5).map(|i| sample(f64::from(i) * 0.1, false)).collect();
+        samples[1].s1 = f64::NAN;
+        samples[3].s1 = f64::INFINITY;
+        samples[4].s3 = f64::NAN;
+        let times = dropouts(&samples);
+        let counts = times.each_ref().map(Vec::len);
+        // The helper's sensor 2 reads NaN throughout
+        assert_eq!(counts, [2, 5, 1]);
+        assert_eq!(times[0], [samples[1].t, samples[3].t]);
+        assert_eq!(times[2], [samples[4].t]);
+        assert!(dropouts(&[]).iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn sensor_envelope_spans_the_valid_readings() {
+        // sample(): s1 = 300.1 + t, s2 a dropout, s3 = 299.9
+        let mut samples: Vec<Sample> = (0..8).map(|i| sample(f64::from(i), false)).collect();
+        samples[2].s2 = 301.0;
+        samples[5].s3 = f64::NAN;
+        let cfg = SafetyConfig::default();
+        let band = envelope_band(&samples, None, &cfg);
+        assert_eq!(band[0], [0.0, 299.9, 300.1]);
+        assert_eq!(band[2], [2.0, 299.9, 302.1]);
+        // One valid reading: no band
+        assert!(band[5][1].is_nan() && band[5][2].is_nan());
+
+        assert_eq!(valid_median(&cfg, samples[2].sensors()), 301.0);
+        assert_eq!(valid_median(&cfg, samples[0].sensors()), 300.0);
+        assert_eq!(valid_median(&cfg, samples[5].sensors()), 305.1);
+        assert!(valid_median(&cfg, [f64::NAN; 3]).is_nan());
+
+        // Decimated, each bucket keeps its extremes at its first and last time
+        let ranges = bucket_ranges(&samples, 4).unwrap();
+        let band = envelope_band(&samples, Some(&ranges), &cfg);
+        assert_eq!(band.len(), 2 * ranges.len());
+        assert_eq!(band[0], [0.0, 299.9, 303.1]);
+        assert_eq!(band[1], [3.0, 299.9, 303.1]);
+        let last = band[band.len() - 1];
+        assert_eq!(last, [7.0, 299.9, 307.1]);
+
+        let data = PlotData::build(&samples, DEFAULT_PLOT_POINTS, PlantParams::default());
+        assert_eq!(data.envelope.len(), samples.len());
+        assert_eq!(data.median[2], [2.0, 301.0]);
+    }
+
+    #[test]
+    fn phase_trajectory_pairs_power_with_temperature() {
+        // sample(): true_temp = 300 + t / 3
+        let mut samples: Vec<Sample> = (0..9).map(|i| sample(f64::from(i), i >= 6)).collect();
+        for (i, s) in samples.iter_mut().enumerate() {
+            s.power = if s.scram { 0.0 } else { 0.1 * i as f64 };
+        }
+        let phase = phase_trajectory(&samples, None);
+        assert_eq!(phase.len(), samples.len());
+        assert_eq!(phase[3], [3.0, 0.30000000000000004, 301.0]);
+
+        // Decimated, each bucket keeps its coolest and hottest sample, in time order
+        let ranges = bucket_ranges(&samples, 4).unwrap();
+        let phase = phase_trajectory(&samples, Some(&ranges));
+        assert!(phase.windows(2).all(|w| w[0][0] < w[1][0]));
+        for [t, power, temp] in &phase {
+            let s = &samples[*t as usize];
+            assert_eq!((*power, *temp), (s.power, s.true_temp));
+        }
+        assert_eq!(phase[0][0], 0.0);
+        assert_eq!(phase[phase.len() - 1][0], 8.0);
+
+        let data = PlotData::build(&samples, DEFAULT_PLOT_POINTS, PlantParams::default());
+        assert_eq!(data.phase.len(), samples.len());
+        assert_eq!(data.phase_trip, Some([0.0, 302.0]));
+    }
+
+    #[test]
+    fn pause_on_trip_zooms_once_per_trip_and_explains_it() {
+        // sample(): s1 = 300.1 + t, s2 a dropout, s3 = 299.9; disagreement trip at 8 s
+        let trip = TripRecord {
+            detail: safety::TripDetail::SensorDisagree {
+                spread_c: 8.2,
+                max_sensor_delta_c: 8.0,
+            },
+            channels: [true, false, true],
+        };
+        let reason = Arc::new(RowReason::Trip(trip));
+        let samples: Vec<Sample> = (0..12)
+            .map(|i| Sample {
+                reason: (i >= 8).then(|| reason.clone()),
+                ..sample(f64::from(i), i >= 8)
+            })
+            .collect();
+        let cfg = SafetyConfig {
+            max_sensor_delta_c: 8.0,
+            ..SafetyConfig::default()
+        };
+        let callout = trip_callout(&samples, &cfg, 310.0).unwrap();
+        assert_eq!(callout.t, 8.0);
+        assert_eq!(callout.reason, trip.to_string());
+        assert_eq!(callout.channels, [true, false, true]);
+        // Voted on the second-hottest reading, s3; 0.2 °C past the disagreement limit
+        assert!((callout.trip_margin.unwrap() - 10.1).abs() < 1e-9);
+        assert!((callout.spread_margin.unwrap() + 0.2).abs() < 1e-9);
+        assert_eq!(trip_callout(&samples[..8], &cfg, 310.0), None);
+
+        let mut app = App {
+            replay_loaded: true,
+            replay_playing: true,
+            replay_all: samples,
+            ..App::default()
+        };
+        let scrub = |app: &mut App, pos| {
+            app.seek_replay(pos);
+            app.focus_on_trip(app.scram_time_for_plot());
+        };
+        scrub(&mut app, 6);
+        assert_eq!(app.trip_focus, None);
+        scrub(&mut app, 10);
+        assert_eq!(app.trip_focus, Some(8.0));
+        assert!(!app.replay_playing);
+        // "Back to full view" sticks while the trip stays in view, until scrubbing past it again
+        app.trip_focus = None;
+        scrub(&mut app, 12);
+        assert_eq!(app.trip_focus, None);
+        scrub(&mut app, 4);
+        scrub(&mut app, 9);
+        assert_eq!(app.trip_focus, Some(8.0));
+
+        app.pause_on_trip = false;
+        scrub(&mut app, 4);
+        scrub(&mut app, 9);
+        assert_eq!(app.trip_focus, None);
+    }
+}
@@ -0,0 +1,616 @@
+//! "Export plot PNG…" and `--render`: the plots drawn offline with plotters, with text in egui's
+//! own font.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::TryRecvError;
+
+use eframe::egui;
+use plotters::coord::types::RangedCoordf64;
+use plotters::coord::Shift;
+use plotters::element::DashedPathElement;
+use plotters::prelude::{
+    BitMapBackend, Cartesian2d, ChartBuilder, ChartContext, Color, Cross, DrawingArea,
+    DrawingAreaErrorKind, DrawingBackend, FontStyle, IntoDrawingArea, PathElement, RGBColor,
+    Rectangle, SeriesLabelPosition, ShapeStyle, BLACK, RED, WHITE,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use trace::{HeaderView, RowReason};
+
+#[cfg(not(target_arch = "wasm32"))]
+use crate::plots::DEFAULT_PLOT_POINTS;
+use crate::plots::{temp_range, PlotData, SeriesVisibility};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::replay::load_trace_from_bytes;
+use crate::{spawn_dialog, App, DIALOG_POLL};
+
+/// Size of "Export plot PNG" images until changed: a 1080p slide
+pub(crate) const PNG_SIZE: [u32; 2] = [1920, 1080];
+
+/// What "Export plot PNG" draws: the temperature and actuator plots over the range on screen
+struct PlotImage<'a> {
+    title: String,
+    data: &'a PlotData,
+    // Comparison ("B") and the seconds added to its times
+    compare: Option<(&'a PlotData, f64)>,
+    vis: SeriesVisibility,
+    levels: Vec<(&'static str, f64)>,
+    scram: Option<(f64, f64)>,
+    // Time and temperature ranges of the temperature plot; the actuator plot shares the time
+    x_range: (f64, f64),
+    temp_range: (f64, f64),
+    panes: Vec<PngPane>,
+}
+
+/// A plot of the PNG image
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub(crate) enum PngPane {
+    Temperatures,
+    Actuators,
+}
+
+type PngChart<'a> =
+    ChartContext<'a, BitMapBackend<'a>, Cartesian2d<RangedCoordf64, RangedCoordf64>>;
+
+type PngError = DrawingAreaErrorKind<<BitMapBackend<'static> as DrawingBackend>::ErrorType>;
+
+const PNG_TEMP: RGBColor = RGBColor(31, 119, 180);
+
+const PNG_SENSORS: [RGBColor; 3] = [
+    RGBColor(255, 127, 14),
+    RGBColor(44, 160, 44),
+    RGBColor(148, 103, 189),
+];
+
+const PNG_SETPOINT: RGBColor = RGBColor(127, 127, 127);
+
+const PNG_LEVELS: [RGBColor; 3] = [
+    RGBColor(214, 39, 40),
+    RGBColor(140, 86, 75),
+    RGBColor(188, 189, 34),
+];
+
+const PNG_POWER_SAT: RGBColor = RGBColor(255, 140, 0);
+
+const PNG_COOLANT: RGBColor = RGBColor(23, 190, 207);
+
+/// Register egui's own font for the PNG text (plotters has no font of its own).
+fn register_png_font() {
+    static REGISTER: std::sync::Once = std::sync::Once::new();
+    REGISTER.call_once(|| {
+        // A font shipped with egui, so it parses
+        let _ = plotters::style::register_font(
+            "sans-serif",
+            FontStyle::Normal,
+            epaint_default_fonts::UBUNTU_LIGHT,
+        );
+    });
+}
+
+/// The unbroken runs of `line` within `x0..=x1`; like on screen, the line breaks at NaN.
+fn line_runs(line: &[[f64; 2]], (x0, x1): (f64, f64)) -> Vec<Vec<(f64, f64)>> {
+    line.split(|[t, y]| !y.is_finite() || *t < x0 || *t > x1)
+        .filter(|run| !run.is_empty())
+        .map(|run| run.iter().map(|[t, y]| (*t, *y)).collect())
+        .collect()
+}
+
+/// Draw `line` with a legend entry, dashed for a comparison; a line with nothing in range has none.
+fn png_line(
+    chart: &mut PngChart<'_>,
+    line: &[[f64; 2]],
+    x_range: (f64, f64),
+    name: &str,
+    style: ShapeStyle,
+    dashed: bool,
+) -> Result<(), PngError> {
+    let runs = line_runs(line, x_range);
+    if runs.is_empty() {
+        return Ok(());
+    }
+    let dash = 4 * style.stroke_width;
+    let series = if dashed {
+        let runs = runs.into_iter();
+        chart.draw_series(runs.map(|run| DashedPathElement::new(run, dash, dash, style)))?
+    } else {
+        chart.draw_series(runs.into_iter().map(|run| PathElement::new(run, style)))?
+    };
+    series
+        .label(name)
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], style));
+    Ok(())
+}
+
+/// Shade the post-trip region and alarm spans and mark the trip, as `shade_trip_and_alarms` does
+/// on screen.
+fn png_shading(
+    chart: &mut PngChart<'_>,
+    image: &PlotImage<'_>,
+    (y0, y1): (f64, f64),
+    stroke: u32,
+) -> Result<(), PngError> {
+    let (x0, x1) = image.x_range;
+    let clip = |(t0, t1): (f64, f64)| (t0.max(x0), t1.min(x1));
+    let spans: Vec<_> = image.data.alarms.iter().map(|span| clip(*span)).collect();
+    let spans = spans.into_iter().filter(|(t0, t1)| t0 < t1);
+    let alarm = RGBColor(255, 200, 0).mix(0.25).filled();
+    let mut bands = spans
+        .map(|(t0, t1)| Rectangle::new([(t0, y0), (t1, y1)], alarm))
+        .peekable();
+    if bands.peek().is_some() {
+        chart
+            .draw_series(bands)?
+            .label("Sensor alarm")
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], alarm));
+    }
+    if let Some((t0, t1)) = image.scram.map(clip).filter(|(t0, t1)| t0 <= t1) {
+        let post = RED.mix(0.15).filled();
+        chart
+            .draw_series([Rectangle::new([(t0, y0), (t1, y1)], post)])?
+            .label("Post-SCRAM")
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], post));
+        // The trip itself, unless the range starts after it
+        if image.scram.is_some_and(|(t, _)| t >= x0) {
+            let line = RED.stroke_width(stroke);
+            chart
+                .draw_series([PathElement::new(vec![(t0, y0), (t0, y1)], line)])?
+                .label("SCRAM")
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], line));
+        }
+    }
+    Ok(())
+}
+
+/// Draw the temperature plot (upper three fifths) and the actuator plot below it, or the one of
+/// them the image has, each with axis labels and a legend, under the image's title.
+fn draw_plot_image(
+    root: &DrawingArea<BitMapBackend<'_>, Shift>,
+    image: &PlotImage<'_>,
+) -> Result<(), PngError> {
+    let (_, height) = root.dim_in_pixel();
+    let font = |fraction: u32| ("sans-serif", (height / fraction).max(10));
+    let stroke = (height / 360).max(2);
+    let thin = |color: RGBColor| color.stroke_width(stroke);
+
+    root.fill(&WHITE)?;
+    let root = root
+        .margin(height / 80, 0, 0, 0)
+        .titled(&image.title, font(30))?;
+    let shown = |pane| image.panes.contains(&pane);
+    let (upper, lower) = match (shown(PngPane::Temperatures), shown(PngPane::Actuators)) {
+        (true, true) => {
+            let (upper, lower) = root.split_vertically(root.dim_in_pixel().1 * 3 / 5);
+            (Some(upper), Some(lower))
+        }
+        (true, false) => (Some(root), None),
+        (false, _) => (None, Some(root)),
+    };
+    let chart_on = |area, y_range: (f64, f64), y_desc: &str, x_desc: &str| {
+        let (x0, x1) = image.x_range;
+        let mut chart = ChartBuilder::on(area)
+            .margin(height / 60)
+            .x_label_area_size(height / 16)
+            .y_label_area_size(height / 12)
+            .build_cartesian_2d(x0..x1, y_range.0..y_range.1)?;
+        chart
+            .configure_mesh()
+            .x_desc(x_desc)
+            .y_desc(y_desc)
+            .label_style(font(60))
+            .axis_desc_style(font(50))
+            .draw()?;
+        Ok(chart)
+    };
+    let legend = |chart: &mut PngChart<'_>| {
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .label_font(font(60))
+            .background_style(WHITE.mix(0.85))
+            .border_style(BLACK)
+            .draw()
+    };
+    let x_range = image.x_range;
+    let data = image.data;
+    let vis = &image.vis;
+
+    if let Some(upper) = &upper {
+        let (y0, y1) = image.temp_range;
+        let x_desc = if lower.is_none() { "Time (s)" } else { "" };
+        let mut chart = chart_on(upper, (y0, y1), "Temperature (°C)", x_desc)?;
+        png_shading(&mut chart, image, (y0, y1), stroke)?;
+        if vis.true_temp {
+            png_line(
+                &mut chart,
+                &data.temp,
+                x_range,
+                "Temp (°C)",
+                thin(PNG_TEMP),
+                false,
+            )?;
+        }
+        for (i, line) in data.sensors.iter().enumerate() {
+            if vis.sensors[i] {
+                let name = format!("Sensor {}", i + 1);
+                png_line(
+                    &mut chart,
+                    line,
+                    x_range,
+                    &name,
+                    thin(PNG_SENSORS[i]),
+                    false,
+                )?;
+            }
+        }
+        for (i, times) in data.dropout_marks.iter().enumerate() {
+            let times = times
+                .iter()
+                .filter(|t| (x_range.0..=x_range.1).contains(*t));
+            let mut marks = times
+                .map(|t| Cross::new((*t, y0), 4 * stroke, thin(PNG_SENSORS[i])))
+                .peekable();
+            if vis.sensors[i] && marks.peek().is_some() {
+                let style = thin(PNG_SENSORS[i]);
+                chart
+                    .draw_series(marks)?
+                    .label(format!("Sensor {} dropout", i + 1))
+                    .legend(move |(x, y)| Cross::new((x + 10, y), 4, style));
+            }
+        }
+        if let Some((b, offset)) = image.compare {
+            let shifted: Vec<[f64; 2]> = b.temp.iter().map(|[t, y]| [t + offset, *y]).collect();
+            png_line(
+                &mut chart,
+                &shifted,
+                x_range,
+                "Temp (°C) (B)",
+                thin(PNG_TEMP),
+                true,
+            )?;
+        }
+        if vis.setpoint {
+            png_line(
+                &mut chart,
+                &data.setpoint,
+                x_range,
+                "Setpoint",
+                thin(PNG_SETPOINT),
+                false,
+            )?;
+        }
+        for ((name, v), color) in image.levels.iter().zip(PNG_LEVELS.iter().cycle()) {
+            let level = [[x_range.0, *v], [x_range.1, *v]];
+            png_line(&mut chart, &level, x_range, name, thin(*color), false)?;
+        }
+        legend(&mut chart)?;
+    }
+
+    if let Some(lower) = &lower {
+        let mut chart = chart_on(lower, (-0.05, 1.05), "Actuators (0..1)", "Time (s)")?;
+        png_shading(&mut chart, image, (-0.05, 1.05), stroke)?;
+        png_line(
+            &mut chart,
+            &data.power,
+            x_range,
+            "Power (0..1)",
+            thin(PNG_TEMP),
+            false,
+        )?;
+        let saturated = PNG_POWER_SAT.stroke_width(3 * stroke);
+        png_line(
+            &mut chart,
+            &data.power_sat,
+            x_range,
+            "Power (saturated)",
+            saturated,
+            false,
+        )?;
+        png_line(
+            &mut chart,
+            &data.coolant,
+            x_range,
+            "Coolant (0..1)",
+            thin(PNG_COOLANT),
+            false,
+        )?;
+        if let Some((b, offset)) = image.compare {
+            let shift = |line: &[[f64; 2]]| -> Vec<[f64; 2]> {
+                line.iter().map(|[t, y]| [t + offset, *y]).collect()
+            };
+            png_line(
+                &mut chart,
+                &shift(&b.power),
+                x_range,
+                "Power (0..1) (B)",
+                thin(PNG_TEMP),
+                true,
+            )?;
+            let coolant = shift(&b.coolant);
+            png_line(
+                &mut chart,
+                &coolant,
+                x_range,
+                "Coolant (0..1) (B)",
+                thin(PNG_COOLANT),
+                true,
+            )?;
+        }
+        legend(&mut chart)?;
+    }
+    Ok(())
+}
+
+/// `--render`: the plots of the whole trace at `trace`, as "Export plot PNG" draws them, written
+/// to `out`. The title names the file, and the scenario and seed when the header has them.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn render_trace(
+    trace: &Path,
+    out: &Path,
+    size: [u32; 2],
+    panes: &[PngPane],
+) -> Result<(), String> {
+    let bytes = fs::read(trace).map_err(|e| format!("Failed to read {}: {e}", trace.display()))?;
+    let name = trace
+        .file_name()
+        .map_or(trace.display().to_string(), |n| n.to_string_lossy().into());
+    let loaded = load_trace_from_bytes(&name, &bytes)?;
+    let meta = loaded.meta.as_ref();
+    let plant = meta.and_then(HeaderView::plant).unwrap_or_default();
+    let data = PlotData::build(&loaded.samples, DEFAULT_PLOT_POINTS, plant);
+
+    let vis = SeriesVisibility::default();
+    let has_setpoint = data.setpoint.iter().any(|[_, y]| y.is_finite());
+    let level = |name, v: Option<f64>| v.map(|v| (name, v));
+    let levels: Vec<(&'static str, f64)> = [
+        level(
+            "Setpoint",
+            meta.and_then(|m| m.setpoint).filter(|_| !has_setpoint),
+        ),
+        level("Trip temp", meta.and_then(|m| m.trip_temp)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let level_values: Vec<f64> = levels.iter().map(|(_, v)| *v).collect();
+
+    let mut title = name;
+    if let Some(m) = meta {
+        if let Some(scenario) = &m.scenario {
+            title += &format!(" — {scenario}");
+        }
+        if let Some(seed) = m.seed {
+            title += &format!(" (seed {seed})");
+        }
+    }
+    let trip = loaded.samples.iter().find(|s| s.scram);
+    if let Some(trip) = trip {
+        let reason = trip.reason.as_deref().or(loaded.first_reason.as_ref());
+        let reason = reason.map_or("—".to_string(), RowReason::to_string);
+        title += &format!(" — SCRAM: {reason}");
+    }
+    let t_start = loaded.samples.first().map_or(0.0, |s| s.t);
+    let t_end = loaded.samples.last().map_or(0.0, |s| s.t);
+    let image = PlotImage {
+        title,
+        data: &data,
+        compare: None,
+        vis,
+        temp_range: temp_range(&data, None, &vis, &level_values),
+        levels,
+        scram: trip.map(|s| (s.t, t_end)),
+        x_range: (t_start, t_end.max(t_start + 1.0)),
+        panes: panes.to_vec(),
+    };
+    render_plot_png(out, &image, size)
+}
+
+/// Render `image` at `width` × `height` pixels to the PNG file `path`.
+fn render_plot_png(
+    path: &Path,
+    image: &PlotImage<'_>,
+    [width, height]: [u32; 2],
+) -> Result<(), String> {
+    let fail = |e: &dyn std::fmt::Display| format!("Failed to write {}: {e}", path.display());
+    register_png_font();
+    let root = BitMapBackend::new(path, (width, height)).into_drawing_area();
+    draw_plot_image(&root, image).map_err(|e| fail(&e))?;
+    root.present().map_err(|e| fail(&e))
+}
+
+impl App {
+    /// "Export plot PNG…": pick where to save an image of the plots.
+    pub(crate) fn browse_png(&mut self) {
+        if self.png_dialog.is_some() {
+            return;
+        }
+        let dialog = rfd::AsyncFileDialog::new()
+            .set_title("Export plot PNG")
+            .set_file_name("plots.png")
+            .add_filter("PNG image", &["png"]);
+        self.png_dialog = Some(spawn_dialog(dialog.save_file()));
+    }
+
+    pub(crate) fn poll_png_dialog(&mut self, ctx: &egui::Context) {
+        let Some(rx) = &self.png_dialog else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(Some(path)) => {
+                self.png_dialog = None;
+                self.export_png_to(&path);
+            }
+            Ok(None) | Err(TryRecvError::Disconnected) => self.png_dialog = None,
+            Err(TryRecvError::Empty) => ctx.request_repaint_after(DIALOG_POLL),
+        }
+    }
+
+    /// Render the plots to `path`, adding the `.png` the image encoder goes by when it is missing.
+    fn export_png_to(&mut self, path: &Path) {
+        let is_png = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"));
+        let path = if is_png {
+            path.to_path_buf()
+        } else {
+            path.with_extension("png")
+        };
+        let [width, height] = self.png_size;
+        match render_plot_png(&path, &self.plot_image(), self.png_size) {
+            Ok(()) => {
+                self.last_error = None;
+                self.export_notice = Some(format!(
+                    "Exported a {width}×{height} plot image to {}",
+                    path.display()
+                ));
+            }
+            Err(e) => {
+                self.export_notice = None;
+                self.last_error = Some(e);
+            }
+        }
+    }
+
+    /// The plots as the PNG export draws them: the shown series over the range on screen.
+    fn plot_image(&self) -> PlotImage<'_> {
+        let data = &self.plot_data;
+        let vis = self.visible;
+        let has_setpoint = data.setpoint.iter().any(|[_, y]| y.is_finite());
+        let levels = self.reference_levels(has_setpoint);
+        let compare = (vis.compare && !self.compare_plot.temp.is_empty())
+            .then_some((&self.compare_plot, self.compare_offset));
+        let t_start = self.plotted().first().map_or(0.0, |s| s.t);
+        let t_end = self.samples.last().map_or(0.0, |s| s.t);
+        let (x_range, temp_range) = match self.plot_view {
+            Some(([x0, y0], [x1, y1])) => ((x0, x1), (y0, y1)),
+            None => {
+                let level_values: Vec<f64> = levels.iter().map(|(_, v)| *v).collect();
+                let shown = temp_range(data, compare.map(|(b, _)| b), &vis, &level_values);
+                ((t_start, t_end.max(t_start + 1.0)), shown)
+            }
+        };
+        let title = if self.replay_loaded {
+            let name = Path::new(&self.replay_path).file_name();
+            let name = name.map_or(self.replay_path.clone(), |n| n.to_string_lossy().into());
+            format!("Replay: {name}")
+        } else {
+            format!("{} (seed {})", self.scenario_label(), self.seed)
+        };
+        let title = if self.scram_now() {
+            format!("{title} — SCRAM: {}", self.reason_text())
+        } else {
+            title
+        };
+        PlotImage {
+            title,
+            data,
+            compare,
+            vis,
+            levels,
+            scram: self.scram_time_for_plot().map(|t| (t, t_end)),
+            x_range,
+            temp_range,
+            panes: vec![PngPane::Temperatures, PngPane::Actuators],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use sim::PlantParams;
+    use trace::Sample;
+
+    use crate::tests::{export_dir, sample, TRACE};
+    use crate::Args;
+
+    #[test]
+    fn plot_lines_break_at_nan_and_the_time_range() {
+        let line = [
+            [0.0, 1.0],
+            [1.0, 2.0],
+            [2.0, f64::NAN],
+            [3.0, 4.0],
+            [4.0, 5.0],
+            [5.0, 6.0],
+        ];
+        assert_eq!(
+            line_runs(&line, (0.5, 4.0)),
+            [vec![(1.0, 2.0)], vec![(3.0, 4.0), (4.0, 5.0)]]
+        );
+        assert!(line_runs(&line, (6.0, 7.0)).is_empty());
+    }
+
+    #[test]
+    fn plot_image_is_written_at_the_chosen_size() {
+        let dir = export_dir("png");
+        let samples: Vec<Sample> = (0..600)
+            .map(|i| sample(f64::from(i) * 0.1, i >= 450))
+            .collect();
+        let data = PlotData::build(&samples, DEFAULT_PLOT_POINTS, PlantParams::default());
+        let image = PlotImage {
+            title: "Overheat (low cooling) (seed 42) — SCRAM: over temperature".to_string(),
+            data: &data,
+            compare: Some((&data, 2.0)),
+            vis: SeriesVisibility::default(),
+            levels: vec![("Trip temp", 420.0)],
+            scram: Some((45.0, 59.9)),
+            x_range: (0.0, 59.9),
+            temp_range: (280.0, 440.0),
+            panes: vec![PngPane::Temperatures, PngPane::Actuators],
+        };
+        let path = dir.join("plots.png");
+        render_plot_png(&path, &image, PNG_SIZE).unwrap();
+        let png = fs::read(&path).unwrap();
+        assert_eq!(png[..8], *b"\x89PNG\r\n\x1a\n");
+        // IHDR: width and height, big-endian
+        assert_eq!(png[16..20], 1920_u32.to_be_bytes());
+        assert_eq!(png[20..24], 1080_u32.to_be_bytes());
+
+        let missing = dir.join("no such dir").join("plots.png");
+        let err = render_plot_png(&missing, &image, [640, 360]).unwrap_err();
+        assert!(err.starts_with("Failed to write"), "{err}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn render_mode_draws_a_trace_without_the_window() {
+        let dir = export_dir("render");
+        let trace = dir.join("run.jsonl");
+        fs::write(&trace, TRACE).unwrap();
+        let out = dir.join("run.png");
+        let args = Args::try_parse_from([
+            "gui",
+            "--render",
+            trace.to_str().unwrap(),
+            "--out",
+            out.to_str().unwrap(),
+            "--width",
+            "800",
+            "--height",
+            "600",
+            "--panes",
+            "actuators",
+        ])
+        .unwrap();
+        assert_eq!(args.panes, [PngPane::Actuators]);
+        let size = [args.width, args.height];
+        render_trace(args.render.as_deref().unwrap(), &out, size, &args.panes).unwrap();
+        let png = fs::read(&out).unwrap();
+        assert_eq!(png[..8], *b"\x89PNG\r\n\x1a\n");
+        assert_eq!(png[16..20], 800_u32.to_be_bytes());
+        assert_eq!(png[20..24], 600_u32.to_be_bytes());
+
+        // Without flags: the window, and both plots for a render
+        let args = Args::try_parse_from(["gui"]).unwrap();
+        assert!(args.render.is_none());
+        assert_eq!(args.panes, [PngPane::Temperatures, PngPane::Actuators]);
+        assert!(Args::try_parse_from(["gui", "--render", "run.jsonl"]).is_err());
+
+        let missing = dir.join("missing.jsonl");
+        let err = render_trace(&missing, &out, size, &args.panes).unwrap_err();
+        assert!(err.starts_with("Failed to read"), "{err}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,1075 @@
+//! Replays: traces loaded from files or dropped onto the window (parsed chunk by chunk, the rest
+//! in the background), their playback, and the comparison trace drawn over them.
+
+use std::fs;
+use std::io::{self, BufReader, Read};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+
+use cli::trace::{TraceReader, TraceRecord, TraceSource};
+use eframe::egui;
+use trace::{HeaderView, MetaLine, RowReason, Sample, TraceRow, TRACE_SCHEMA};
+
+use crate::controls::optional_level;
+use crate::{spawn_dialog, App, LogEntry, LogKind, DIALOG_POLL, HAS_FILES};
+
+/// Trace file types offered by the file dialogs
+const TRACE_EXTENSIONS: [&str; 3] = ["jsonl", "gz", "msgpack"];
+
+/// The samples of a trace shifted by `offset_s` that fall at or before `t_end`.
+pub(crate) fn samples_until(samples: &[Sample], offset_s: f64, t_end: f64) -> &[Sample] {
+    &samples[..samples.partition_point(|s| s.t + offset_s <= t_end)]
+}
+
+/// Longest wall-clock frame a time-based replay advances by, so a stalled window does not jump
+const MAX_FRAME_DT_S: f64 = 0.25;
+
+/// One frame of time-based replay: the playback clock advances by `wall_dt_s * speed` of trace
+/// time, and every sample from `pos` at or before the new clock is due. Returns the end of the
+/// due samples (`pos..end` get appended) and the new clock. Uneven steps in the trace work out,
+/// since samples are picked by time rather than counted.
+fn replay_window(
+    samples: &[Sample],
+    pos: usize,
+    clock_s: f64,
+    wall_dt_s: f64,
+    speed: f64,
+) -> (usize, f64) {
+    let clock = clock_s + wall_dt_s.max(0.0) * speed;
+    let pos = pos.min(samples.len());
+    let due = samples[pos..].partition_point(|s| s.t <= clock);
+    (pos + due, clock)
+}
+
+/// Whether a file name looks like a trace the replay loader reads (for dropped files)
+fn is_trace_name(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    let name = name.strip_suffix(".gz").unwrap_or(&name);
+    name.ends_with(".jsonl") || name.ends_with(".msgpack")
+}
+
+/// A trace as the replay view shows it
+#[derive(Debug)]
+pub(crate) struct LoadedTrace {
+    pub(crate) samples: Vec<Sample>,
+    // Reason of the first tripped row
+    pub(crate) first_reason: Option<RowReason>,
+    // Header, if the trace has one (schema 2 and later)
+    pub(crate) meta: Option<HeaderView>,
+    // What the rows tell of the run: SCRAM transitions and reason changes
+    pub(crate) events: Vec<LogEntry>,
+}
+
+/// Parse a trace (JSONL or MessagePack, possibly gzipped) already in memory, as a file dropped
+/// onto the window or a comparison run; `name` labels the errors.
+pub(crate) fn load_trace_from_bytes(name: &str, bytes: &[u8]) -> Result<LoadedTrace, String> {
+    let mut parser = TraceParser::new(name, Box::new(bytes))?;
+    let chunk = parser.chunk(usize::MAX);
+    if let Some(e) = chunk.error {
+        return Err(e);
+    }
+    if chunk.samples.is_empty() {
+        return Err(format!("No samples found in {name}"));
+    }
+    Ok(LoadedTrace {
+        samples: chunk.samples,
+        first_reason: chunk.first_reason,
+        meta: parser.meta,
+        events: chunk.events,
+    })
+}
+
+/// A trace parsed chunk by chunk, so a long one can be shown before the rest is read
+struct TraceParser<'a> {
+    name: String,
+    reader: TraceReader<'a>,
+    // Header, if the trace has one (schema 2 and later)
+    meta: Option<HeaderView>,
+    first_reason: Option<RowReason>,
+    rows: usize,
+    was_scram: bool,
+    last_reason: Option<String>,
+    // The reason of the rows since it last changed
+    shared_reason: Option<Arc<RowReason>>,
+}
+
+/// Rows parsed by one `TraceParser::chunk` call
+#[derive(Debug, Default)]
+struct TraceChunk {
+    samples: Vec<Sample>,
+    // What the rows tell of the run: SCRAM transitions and reason changes
+    events: Vec<LogEntry>,
+    // Reason of the first tripped row, once parsing has reached it
+    first_reason: Option<RowReason>,
+    // The trace ended, or parsing stopped at `error`
+    done: bool,
+    error: Option<String>,
+}
+
+impl<'a> TraceParser<'a> {
+    /// Start parsing `source`; `name` labels the errors.
+    fn new(name: &str, source: TraceSource<'a>) -> Result<Self, String> {
+        let reader = TraceReader::new(source).map_err(|e| format!("Failed to read {name}: {e}"))?;
+        Ok(Self {
+            name: name.to_string(),
+            reader,
+            meta: None,
+            first_reason: None,
+            rows: 0,
+            was_scram: false,
+            last_reason: None,
+            shared_reason: None,
+        })
+    }
+
+    /// Parse up to `max_rows` more rows, fewer at the end of the trace or at an error (the rows
+    /// before it are kept).
+    fn chunk(&mut self, max_rows: usize) -> TraceChunk {
+        let mut chunk = TraceChunk::default();
+        let outcome = loop {
+            if chunk.samples.len() >= max_rows {
+                break Ok(false);
+            }
+            let record = match self.reader.next() {
+                None => break Ok(true),
+                Some(Ok(record)) => record,
+                Some(Err(e)) => break Err(format!("Failed to read {}: {e:#}", self.name)),
+            };
+            if let Err(e) = self.parse(&record, &mut chunk) {
+                break Err(e);
+            }
+        };
+        match outcome {
+            Ok(done) => chunk.done = done,
+            Err(e) => {
+                chunk.done = true;
+                chunk.error = Some(e);
+            }
+        }
+        chunk.first_reason = self.first_reason.clone();
+        chunk
+    }
+
+    fn parse(&mut self, record: &TraceRecord, chunk: &mut TraceChunk) -> Result<(), String> {
+        // Optional `_meta` header before the first row; legacy traces start with data
+        if self.rows == 0 && self.meta.is_none() {
+            if let Ok(header) = record.decode::<MetaLine<HeaderView>>() {
+                if header.meta.schema > TRACE_SCHEMA {
+                    return Err(format!(
+                        "{} uses trace schema {}, newer than this GUI supports (up to {TRACE_SCHEMA})",
+                        self.name, header.meta.schema
+                    ));
+                }
+                self.meta = Some(header.meta);
+                return Ok(());
+            }
+        }
+
+        let mut row: TraceRow = record
+            .decode()
+            .map_err(|e| format!("Parse error at {}: {e:#}", record.position()))?;
+
+        if self.first_reason.is_none() {
+            self.first_reason = row.reason.clone();
+        }
+
+        let reason = row.reason.as_ref().map(RowReason::to_string);
+        let event = |text: String| LogEntry {
+            t_s: row.t_s,
+            kind: LogKind::Trip,
+            text,
+        };
+        if row.scram && !self.was_scram {
+            let reason = reason.as_deref().unwrap_or("—");
+            chunk.events.push(event(format!("TRIP {reason}")));
+        } else if !row.scram && self.was_scram {
+            chunk.events.push(event("SCRAM cleared".to_string()));
+        } else if row.scram && reason.is_some() && reason != self.last_reason {
+            chunk.events.push(event(format!(
+                "reason {}",
+                reason.as_deref().unwrap_or("—")
+            )));
+        }
+        let shared_reason = row.reason.take().map(|r| match &self.shared_reason {
+            Some(shared) if reason == self.last_reason => Arc::clone(shared),
+            _ => Arc::clone(self.shared_reason.insert(Arc::new(r))),
+        });
+        if reason.is_some() {
+            self.last_reason = reason;
+        }
+        self.was_scram = row.scram;
+        self.rows += 1;
+
+        chunk.samples.push(Sample {
+            reason: shared_reason,
+            ..Sample::from(row)
+        });
+        Ok(())
+    }
+}
+
+/// Rows of a trace file shown at once on loading; the rest are parsed in the background, in
+/// chunks of this size
+const LOAD_CHUNK_ROWS: usize = 20_000;
+
+/// Passes reads through, counting the bytes
+struct CountingReader<R> {
+    inner: R,
+    read: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// A trace file being parsed on a background thread (a task between frames in the browser), past
+/// the rows already in the replay
+pub(crate) struct TraceLoading {
+    chunks: Receiver<TraceChunk>,
+    // Bytes of the file read so far, out of its size
+    read: Arc<AtomicU64>,
+    size: u64,
+}
+
+impl TraceLoading {
+    /// Open a trace file for parsing, counting the bytes read from it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn open(path: &str) -> Result<(TraceParser<'static>, Arc<AtomicU64>, u64), String> {
+        let file = fs::File::open(path).map_err(|e| format!("Failed to read {path}: {e}"))?;
+        let size = file.metadata().map_or(0, |m| m.len());
+        let read = Arc::new(AtomicU64::new(0));
+        let source = BufReader::new(CountingReader {
+            inner: file,
+            read: Arc::clone(&read),
+        });
+        let parser = TraceParser::new(path, Box::new(source))?;
+        Ok((parser, read, size))
+    }
+
+    /// Parse a trace already in memory, such as one dropped onto a web page, the same way.
+    fn from_bytes(
+        name: &str,
+        bytes: Vec<u8>,
+    ) -> Result<(TraceParser<'static>, Arc<AtomicU64>, u64), String> {
+        let size = bytes.len() as u64;
+        let read = Arc::new(AtomicU64::new(0));
+        let source = BufReader::new(CountingReader {
+            inner: io::Cursor::new(bytes),
+            read: Arc::clone(&read),
+        });
+        let parser = TraceParser::new(name, Box::new(source))?;
+        Ok((parser, read, size))
+    }
+
+    /// Parse the rest of `parser`'s trace on a background thread, which stops once the receiving
+    /// end is dropped.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn spawn(mut parser: TraceParser<'static>, read: Arc<AtomicU64>, size: u64) -> Self {
+        let (tx, chunks) = mpsc::channel();
+        thread::spawn(move || loop {
+            let chunk = parser.chunk(LOAD_CHUNK_ROWS);
+            let done = chunk.done;
+            if tx.send(chunk).is_err() || done {
+                return;
+            }
+        });
+        Self { chunks, read, size }
+    }
+
+    /// Parse the rest of `parser`'s trace in a browser task, handing the page back for a frame
+    /// after each chunk; it stops once the receiving end is dropped.
+    #[cfg(target_arch = "wasm32")]
+    fn spawn(mut parser: TraceParser<'static>, read: Arc<AtomicU64>, size: u64) -> Self {
+        let (tx, chunks) = mpsc::channel();
+        wasm_bindgen_futures::spawn_local(async move {
+            loop {
+                let chunk = parser.chunk(LOAD_CHUNK_ROWS);
+                let done = chunk.done;
+                if tx.send(chunk).is_err() || done {
+                    return;
+                }
+                yield_to_browser().await;
+            }
+        });
+        Self { chunks, read, size }
+    }
+
+    /// Fraction of the file read so far.
+    fn progress(&self) -> f64 {
+        let read = self.read.load(Ordering::Relaxed);
+        (read as f64 / self.size.max(1) as f64).min(1.0)
+    }
+}
+
+/// Wait for a zero-delay timer, letting the browser draw and handle input meanwhile.
+#[cfg(target_arch = "wasm32")]
+async fn yield_to_browser() {
+    let timer = js_sys::Promise::new(&mut |resolve, _reject| {
+        if let Some(window) = eframe::web_sys::window() {
+            let _ = window.set_timeout_with_callback(&resolve);
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(timer).await;
+}
+
+/// What to tell of a parse error after `kept` rows of a trace.
+fn kept_prefix(error: &str, kept: usize) -> String {
+    format!("{error} (showing the {kept} samples before it)")
+}
+
+/// Hint while a file is dragged over the window
+pub(crate) fn drop_hint(ctx: &egui::Context) {
+    if ctx.input(|i| !i.raw.hovered_files.is_empty()) {
+        let painter = ctx.layer_painter(egui::LayerId::new(
+            egui::Order::Foreground,
+            egui::Id::new("drop_hint"),
+        ));
+        let screen = ctx.screen_rect();
+        painter.rect_filled(screen, 0.0, egui::Color32::from_black_alpha(160));
+        painter.text(
+            screen.center(),
+            egui::Align2::CENTER_CENTER,
+            "Drop trace to load",
+            egui::FontId::proportional(28.0),
+            egui::Color32::WHITE,
+        );
+    }
+}
+
+impl App {
+    pub(crate) fn clear_replay(&mut self) {
+        self.replay_loaded = false;
+        self.replay_all.clear();
+        self.replay_loading = None;
+        self.replay_pos = 0;
+        self.replay_playing = false;
+        self.replay_reason = None;
+        self.drop_notice = None;
+        self.last_error = None;
+    }
+
+    /// Enter replay mode with a trace file: its first rows at once, the rest as they are parsed in
+    /// the background (see `poll_replay_loading`).
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_trace(&mut self, path: &str) {
+        self.last_error = None;
+        let opened = TraceLoading::open(path);
+        self.load_opened(path, opened);
+    }
+
+    /// Web pages cannot read files by path: traces come in by drag and drop.
+    #[cfg(target_arch = "wasm32")]
+    fn load_trace(&mut self, path: &str) {
+        self.last_error = Some(format!(
+            "Cannot open {path} here: drop the trace onto the page"
+        ));
+    }
+
+    /// Enter replay mode with a trace already in memory, such as a dropped file, parsed like one
+    /// loaded by path.
+    fn load_trace_bytes(&mut self, name: &str, bytes: &[u8]) {
+        self.last_error = None;
+        let opened = TraceLoading::from_bytes(name, bytes.to_vec());
+        self.load_opened(name, opened);
+    }
+
+    /// Replay the first chunk of an opened trace and parse the rest in the background.
+    fn load_opened(
+        &mut self,
+        name: &str,
+        opened: Result<(TraceParser<'static>, Arc<AtomicU64>, u64), String>,
+    ) {
+        let (mut parser, read, size) = match opened {
+            Ok(opened) => opened,
+            Err(e) => {
+                self.last_error = Some(e);
+                return;
+            }
+        };
+        let first = parser.chunk(LOAD_CHUNK_ROWS);
+        if first.samples.is_empty() {
+            let error = first
+                .error
+                .unwrap_or_else(|| format!("No samples found in {name}"));
+            self.last_error = Some(error);
+            return;
+        }
+        self.enter_replay(LoadedTrace {
+            samples: first.samples,
+            first_reason: first.first_reason,
+            meta: parser.meta.take(),
+            events: first.events,
+        });
+        if let Some(e) = first.error {
+            self.last_error = Some(kept_prefix(&e, self.replay_all.len()));
+        } else if !first.done {
+            self.replay_loading = Some(TraceLoading::spawn(parser, read, size));
+        }
+    }
+
+    fn enter_replay(&mut self, trace: LoadedTrace) {
+        self.stop_live();
+        if self.compare_live {
+            self.clear_compare();
+        }
+        self.clear_replay();
+        self.replay_loaded = true;
+        self.replay_reason = trace.first_reason;
+        self.replay_setpoint = trace.meta.as_ref().and_then(|m| m.setpoint);
+        self.replay_trip_temp = trace.meta.as_ref().and_then(|m| m.trip_temp);
+        self.replay_plant = trace.meta.as_ref().and_then(HeaderView::plant);
+
+        self.replay_all = trace.samples;
+        self.replay_log = trace.events;
+        self.replay_pos = 0;
+        self.replay_playing = false;
+
+        // Start with an initial chunk so the plot isn't empty
+        self.samples.clear();
+        self.plot_data.invalidate();
+        let initial = self.replay_speed.min(self.replay_all.len()).max(1);
+        self.samples.extend_from_slice(&self.replay_all[..initial]);
+        self.replay_pos = initial;
+
+        // Sync time marker
+        self.t = self.samples.last().map(|s| s.t).unwrap_or(0.0);
+        self.replay_clock = self.t;
+    }
+
+    /// Take in the rows parsed in the background since the last frame.
+    pub(crate) fn poll_replay_loading(&mut self, ctx: &egui::Context) {
+        let Some(loading) = &self.replay_loading else {
+            return;
+        };
+        let mut done = false;
+        let mut chunks = Vec::new();
+        loop {
+            match loading.chunks.try_recv() {
+                Ok(chunk) => chunks.push(chunk),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    done = true;
+                    break;
+                }
+            }
+        }
+        for chunk in chunks {
+            self.replay_all.extend(chunk.samples);
+            self.replay_log.extend(chunk.events);
+            if self.replay_reason.is_none() {
+                self.replay_reason = chunk.first_reason;
+            }
+            if let Some(e) = chunk.error {
+                self.last_error = Some(kept_prefix(&e, self.replay_all.len()));
+            }
+            done |= chunk.done;
+        }
+        if done {
+            self.replay_loading = None;
+        } else {
+            ctx.request_repaint_after(DIALOG_POLL);
+        }
+    }
+
+    /// "Browse…": pick a trace in a native dialog, starting next to the current path.
+    pub(crate) fn browse_replay(&mut self) {
+        if self.open_dialog.is_some() {
+            return;
+        }
+        let mut dialog = rfd::AsyncFileDialog::new()
+            .set_title("Load trace")
+            .add_filter("Trace (.jsonl, .jsonl.gz, .msgpack)", &TRACE_EXTENSIONS);
+        if let Some(dir) = Path::new(&self.replay_path).parent().filter(|d| d.is_dir()) {
+            dialog = dialog.set_directory(dir);
+        }
+        self.open_dialog = Some(spawn_dialog(dialog.pick_file()));
+    }
+
+    /// Load the file picked in the open dialog once it closes; cancelling changes nothing.
+    pub(crate) fn poll_open_dialog(&mut self, ctx: &egui::Context) {
+        let Some(rx) = &self.open_dialog else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(Some(path)) => {
+                self.open_dialog = None;
+                self.replay_path = path.display().to_string();
+                let p = self.replay_path.clone();
+                self.load_trace(&p);
+            }
+            Ok(None) | Err(TryRecvError::Disconnected) => self.open_dialog = None,
+            Err(TryRecvError::Empty) => ctx.request_repaint_after(DIALOG_POLL),
+        }
+    }
+
+    /// "Load comparison…": pick the trace to draw as "B" over the current one.
+    fn browse_compare(&mut self) {
+        if self.compare_dialog.is_some() {
+            return;
+        }
+        let dialog = rfd::AsyncFileDialog::new()
+            .set_title("Load comparison trace")
+            .add_filter("Trace (.jsonl, .jsonl.gz, .msgpack)", &TRACE_EXTENSIONS);
+        self.compare_dialog = Some(spawn_dialog(dialog.pick_file()));
+    }
+
+    pub(crate) fn poll_compare_dialog(&mut self, ctx: &egui::Context) {
+        let Some(rx) = &self.compare_dialog else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(Some(path)) => {
+                self.compare_dialog = None;
+                self.load_compare(&path.display().to_string());
+            }
+            Ok(None) | Err(TryRecvError::Disconnected) => self.compare_dialog = None,
+            Err(TryRecvError::Empty) => ctx.request_repaint_after(DIALOG_POLL),
+        }
+    }
+
+    /// Load the comparison trace; the primary trace and mode stay as they are.
+    fn load_compare(&mut self, path: &str) {
+        let loaded = fs::read(path)
+            .map_err(|e| format!("Failed to read {path}: {e}"))
+            .and_then(|bytes| load_trace_from_bytes(path, &bytes));
+        match loaded {
+            Ok(trace) => {
+                self.last_error = None;
+                self.compare_path = Some(path.to_string());
+                self.compare_live = false;
+                self.compare_all = trace.samples;
+                self.compare_plot.invalidate();
+                self.compare_reason = trace.first_reason;
+            }
+            Err(e) => self.last_error = Some(e),
+        }
+    }
+
+    pub(crate) fn clear_compare(&mut self) {
+        self.compare_path = None;
+        self.compare_live = false;
+        self.compare_all.clear();
+        self.compare_plot.invalidate();
+        self.compare_reason = None;
+    }
+
+    /// Load a trace dropped onto the window: the first one with a trace extension, listing the
+    /// other dropped files in a notice. Web builds get the bytes rather than a path.
+    pub(crate) fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.clone());
+        if dropped.is_empty() {
+            return;
+        }
+        let name_of = |file: &egui::DroppedFile| match &file.path {
+            Some(path) => path.display().to_string(),
+            None => file.name.clone(),
+        };
+        let Some(index) = dropped.iter().position(|f| is_trace_name(&name_of(f))) else {
+            let names: Vec<String> = dropped.iter().map(name_of).collect();
+            self.last_error = Some(format!(
+                "Not a trace (.jsonl, .jsonl.gz or .msgpack): {}",
+                names.join(", ")
+            ));
+            return;
+        };
+        let others: Vec<String> = dropped
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| i != index)
+            .map(|(_, f)| name_of(f))
+            .collect();
+        let notice = (!others.is_empty()).then(|| format!("Not loaded: {}", others.join(", ")));
+
+        let file = &dropped[index];
+        match (&file.bytes, &file.path) {
+            (Some(bytes), _) => self.load_trace_bytes(&file.name, bytes),
+            #[cfg(not(target_arch = "wasm32"))]
+            (None, Some(path)) => {
+                self.replay_path = path.display().to_string();
+                let p = self.replay_path.clone();
+                self.load_trace(&p);
+            }
+            (None, _) => {
+                self.last_error = Some(format!("Dropped file {} has no contents", file.name));
+            }
+        }
+        self.drop_notice = notice;
+    }
+
+    /// Advance a playing replay by one frame, `wall_dt_s` after the previous one.
+    pub(crate) fn replay_tick(&mut self, wall_dt_s: f64) {
+        if !(self.replay_loaded && self.replay_playing) {
+            return;
+        }
+
+        if self.replay_pos >= self.replay_all.len() {
+            // Wait at the end of what is parsed so far
+            self.replay_playing = self.replay_loading.is_some();
+            return;
+        }
+
+        let end = if self.replay_max_speed {
+            (self.replay_pos + self.replay_speed.max(1)).min(self.replay_all.len())
+        } else {
+            let (end, clock) = replay_window(
+                &self.replay_all,
+                self.replay_pos,
+                self.replay_clock,
+                wall_dt_s.min(MAX_FRAME_DT_S),
+                self.replay_multiplier,
+            );
+            self.replay_clock = clock;
+            end
+        };
+        self.samples
+            .extend_from_slice(&self.replay_all[self.replay_pos..end]);
+        self.replay_pos = end;
+
+        self.t = self.samples.last().map(|s| s.t).unwrap_or(self.t);
+
+        if self.replay_pos >= self.replay_all.len() && self.replay_loading.is_none() {
+            self.replay_playing = false;
+        }
+    }
+
+    /// Scrub the replay: show the first `pos` samples, backwards or forwards; playing and
+    /// stepping continue from there.
+    pub(crate) fn seek_replay(&mut self, pos: usize) {
+        if !self.replay_loaded {
+            return;
+        }
+        let pos = pos.clamp(1, self.replay_all.len());
+        if pos < self.samples.len() {
+            self.samples.truncate(pos);
+        } else {
+            self.samples
+                .extend_from_slice(&self.replay_all[self.samples.len()..pos]);
+        }
+        self.replay_pos = pos;
+        self.t = self.samples.last().map(|s| s.t).unwrap_or(0.0);
+        self.replay_clock = self.t;
+    }
+
+    pub(crate) fn do_step_replay(&mut self) {
+        if !self.replay_loaded {
+            return;
+        }
+        if self.replay_pos >= self.replay_all.len() {
+            self.replay_playing = false;
+            return;
+        }
+        let end = (self.replay_pos + 1).min(self.replay_all.len());
+        self.samples
+            .extend_from_slice(&self.replay_all[self.replay_pos..end]);
+        self.replay_pos = end;
+        self.t = self.samples.last().map(|s| s.t).unwrap_or(self.t);
+        self.replay_clock = self.t;
+    }
+
+    /// "Replay" in the side panel: the trace and comparison to load, playback and position.
+    pub(crate) fn replay_controls(&mut self, ui: &mut egui::Ui) {
+        ui.separator();
+        ui.label("Replay (JSONL or MessagePack)");
+        if HAS_FILES {
+            ui.horizontal(|ui| {
+                ui.label("path:");
+                ui.text_edit_singleline(&mut self.replay_path);
+                if ui
+                    .add_enabled(self.open_dialog.is_none(), egui::Button::new("Browse…"))
+                    .clicked()
+                {
+                    self.browse_replay();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(
+                        self.compare_dialog.is_none(),
+                        egui::Button::new("Load comparison…"),
+                    )
+                    .clicked()
+                {
+                    self.browse_compare();
+                }
+                if let Some(path) = &self.compare_path {
+                    ui.small(format!("B: {path}"));
+                }
+            });
+        }
+        if self.compare_path.is_some() {
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::DragValue::new(&mut self.compare_offset)
+                        .speed(0.05)
+                        .prefix("B offset: ")
+                        .suffix(" s"),
+                );
+                if ui.button("Clear comparison").clicked() {
+                    self.clear_compare();
+                }
+            });
+        }
+
+        ui.horizontal(|ui| {
+            if HAS_FILES && ui.button("Load").clicked() {
+                let p = self.replay_path.clone();
+                self.load_trace(&p);
+            }
+
+            if ui
+                .button(if self.replay_playing {
+                    "Pause replay"
+                } else {
+                    "Play replay"
+                })
+                .clicked()
+                && self.replay_loaded
+            {
+                self.replay_playing = !self.replay_playing;
+                ui.ctx().request_repaint();
+            }
+
+            if ui.button("Step back").clicked() {
+                self.seek_replay(self.replay_pos.saturating_sub(1));
+            }
+            if ui.button("Step replay").clicked() && self.replay_loaded {
+                self.do_step_replay();
+            }
+        });
+
+        // Legacy traces: reference lines typed in where the header has none
+        let has_setpoint = self.replay_all.iter().any(|s| s.setpoint.is_finite());
+        let missing_setpoint = self.replay_setpoint.is_none() && !has_setpoint;
+        if self.replay_loaded && (missing_setpoint || self.replay_trip_temp.is_none()) {
+            ui.small("No reference levels in this trace:");
+            if missing_setpoint {
+                let default = self.setpoint;
+                optional_level(ui, "setpoint", &mut self.legacy_setpoint, default);
+            }
+            if self.replay_trip_temp.is_none() {
+                let default = self.trip_temp;
+                optional_level(ui, "trip temp", &mut self.legacy_trip_temp, default);
+            }
+        }
+
+        if self.replay_loaded {
+            let all = &self.replay_all;
+            let mut pos = self.replay_pos;
+            let scrub = ui.add(
+                egui::Slider::new(&mut pos, 1..=all.len())
+                    .text("position")
+                    .custom_formatter(|v, _| {
+                        let i = (v as usize).clamp(1, all.len()) - 1;
+                        format!("{:.2} s", all[i].t)
+                    }),
+            );
+            if scrub.changed() {
+                self.seek_replay(pos);
+            }
+            if self.replay_loading.is_some() && self.replay_pos >= self.replay_all.len() {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.small("Loading the rest of the trace…");
+                });
+            }
+        }
+
+        ui.add_enabled(
+            !self.replay_max_speed,
+            egui::Slider::new(&mut self.replay_multiplier, 0.1..=50.0)
+                .logarithmic(true)
+                .text("playback speed ×"),
+        );
+        ui.checkbox(&mut self.replay_max_speed, "max × (fast-forward)");
+        if self.replay_max_speed {
+            ui.add(egui::Slider::new(&mut self.replay_speed, 1..=500).text("samples/frame"));
+        }
+
+        if self.replay_loaded {
+            ui.horizontal(|ui| {
+                ui.small(format!(
+                    "Loaded: {}/{} samples",
+                    self.samples.len(),
+                    self.replay_all.len()
+                ));
+                if let Some(loading) = &self.replay_loading {
+                    ui.spinner();
+                    ui.small(format!("parsing, {:.0}%", loading.progress() * 100.0));
+                }
+            });
+        } else {
+            ui.small("No replay loaded.");
+        }
+        if let Some(notice) = &self.drop_notice {
+            ui.small(notice);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use eframe::egui;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use crate::plots::dropouts;
+    use crate::tests::{export_dir, sample, TRACE};
+
+    #[test]
+    fn replay_events_follow_scram_transitions_and_reason_changes() {
+        let rows = [
+            (0.0, false, "null"),
+            (0.1, true, r#""OverTemp""#),
+            (0.2, true, r#""OverTemp""#),
+            (0.3, true, r#""SensorDisagree""#),
+            (0.4, false, "null"),
+            (0.5, true, r#""Manual""#),
+        ];
+        let trace: String = rows
+            .iter()
+            .map(|(t, scram, reason)| {
+                format!(
+                    r#"{{"t_s":{t},"true_temp_c":300.0,"s1_c":300.0,"s2_c":300.0,"s3_c":300.0,"power":0.0,"coolant":0.2,"scram":{scram},"reason":{reason}}}"#
+                ) + "\n"
+            })
+            .collect();
+        let trace = load_trace_from_bytes("events.jsonl", trace.as_bytes()).unwrap();
+        let events: Vec<String> = trace.events.iter().map(LogEntry::to_string).collect();
+        assert_eq!(
+            events,
+            [
+                "t=0.10s  TRIP OverTemp",
+                "t=0.30s  reason SensorDisagree",
+                "t=0.40s  SCRAM cleared",
+                "t=0.50s  TRIP Manual",
+            ]
+        );
+        assert!(trace.events.iter().all(|e| e.kind == LogKind::Trip));
+    }
+
+    #[test]
+    fn gzipped_bytes_load_like_plain_ones() {
+        let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+        gz.write_all(TRACE.as_bytes()).unwrap();
+        let trace = load_trace_from_bytes("dropped.jsonl.gz", &gz.finish().unwrap()).unwrap();
+        assert_eq!(trace.samples.len(), 2);
+        assert_eq!(trace.samples[1].true_temp, 391.0);
+    }
+
+    #[test]
+    fn bad_bytes_report_where_and_what() {
+        let err = load_trace_from_bytes("x.jsonl", b"{\"t_s\": 0.0}\n").unwrap_err();
+        assert!(err.starts_with("Parse error at line 1"), "{err}");
+        let err = load_trace_from_bytes("x.jsonl", b"\n").unwrap_err();
+        assert_eq!(err, "No samples found in x.jsonl");
+        let newer = r#"{"_meta":{"schema":99}}"#;
+        let err = load_trace_from_bytes("x.jsonl", newer.as_bytes()).unwrap_err();
+        assert!(err.contains("trace schema 99"), "{err}");
+    }
+
+    #[test]
+    fn replay_window_picks_samples_by_trace_time() {
+        // Uneven steps: 50 ms, then 200 ms, then 50 ms
+        let samples: Vec<Sample> = [0.0, 0.05, 0.25, 0.3]
+            .into_iter()
+            .map(|t| sample(t, false))
+            .collect();
+        // Realtime at 60 fps: the 50 ms sample is due after three frames
+        let (end, clock) = replay_window(&samples, 1, 0.0, 1.0 / 60.0, 1.0);
+        assert_eq!(end, 1);
+        let (end, clock) = replay_window(&samples, end, clock, 1.0 / 60.0, 1.0);
+        assert_eq!(end, 1);
+        let (end, clock) = replay_window(&samples, end, clock, 1.0 / 60.0, 1.0);
+        assert_eq!((end, clock), (2, 0.05));
+        // At 10× one 20 ms frame covers 200 ms of trace time
+        assert_eq!(replay_window(&samples, 2, 0.05, 0.02, 10.0).0, 3);
+        // A long frame takes everything that is due, and no more than there is
+        assert_eq!(replay_window(&samples, 2, 0.05, 1.0, 10.0).0, 4);
+        assert_eq!(replay_window(&samples, 4, 0.3, 1.0, 1.0).0, 4);
+        // Paused time (or a clock ahead of the frame) appends nothing
+        assert_eq!(replay_window(&samples, 1, 0.0, 0.0, 1.0).0, 1);
+    }
+
+    #[test]
+    fn comparison_follows_the_primary_time_with_its_offset() {
+        let b: Vec<Sample> = (0..5).map(|i| sample(i as f64 * 0.5, false)).collect();
+        assert_eq!(samples_until(&b, 0.0, 1.0).len(), 3);
+        // Shifted 1 s later, only its first 0.5 s is due by t = 1.5
+        assert_eq!(samples_until(&b, 1.0, 1.5).len(), 2);
+        // Shifted earlier, all of it
+        assert_eq!(samples_until(&b, -2.0, 1.0).len(), 5);
+        assert!(samples_until(&b, 0.0, f64::NEG_INFINITY).is_empty());
+    }
+
+    #[test]
+    fn null_readings_load_as_dropouts() {
+        let trace = TRACE.replacen(r#""s1_c":391.2"#, r#""s1_c":null"#, 1);
+        let trace = load_trace_from_bytes("dropped.jsonl", trace.as_bytes()).unwrap();
+        assert!(trace.samples[1].s1.is_nan());
+        assert_eq!(dropouts(&trace.samples), [vec![0.05], vec![0.0], vec![]]);
+    }
+
+    #[test]
+    fn long_traces_are_shown_while_the_rest_is_parsed() {
+        let dir = export_dir("streamed");
+        let path = dir.join("long.jsonl");
+        let rows = 2 * LOAD_CHUNK_ROWS + 5;
+        let mut text = String::from("{\"_meta\":{\"schema\":3}}\n");
+        for i in 0..rows {
+            text += &format!(
+                "{{\"t_s\":{i},\"true_temp_c\":300.0,\"s1_c\":300.0,\"s2_c\":300.0,\
+                 \"s3_c\":300.0,\"power\":0.5,\"coolant\":0.2,\"scram\":false,\"reason\":null}}\n"
+            );
+        }
+        text += "{\"t_s\":\"late\"}\n";
+        fs::write(&path, &text).unwrap();
+
+        // The same trace dropped onto the web page, as bytes, is parsed the same way
+        let mut from_file = App::default();
+        from_file.load_trace(path.to_str().unwrap());
+        let mut dropped = App::default();
+        dropped.load_trace_bytes("long.jsonl", text.as_bytes());
+
+        let ctx = egui::Context::default();
+        for app in [&mut from_file, &mut dropped] {
+            assert!(app.replay_loaded);
+            assert_eq!(app.replay_all.len(), LOAD_CHUNK_ROWS);
+            assert!(app.replay_loading.is_some());
+
+            let give_up = Instant::now() + Duration::from_secs(30);
+            while app.replay_loading.is_some() {
+                assert!(Instant::now() < give_up, "parsing stalled");
+                app.poll_replay_loading(&ctx);
+                thread::sleep(Duration::from_millis(1));
+            }
+            assert_eq!(app.replay_all.len(), rows);
+            assert_eq!(app.replay_all[rows - 1].t, (rows - 1) as f64);
+            let err = app.last_error.clone().unwrap();
+            assert!(
+                err.starts_with(&format!("Parse error at line {}", rows + 2)),
+                "{err}"
+            );
+            assert!(
+                err.ends_with(&format!("(showing the {rows} samples before it)")),
+                "{err}"
+            );
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn binary_traces_are_read_record_by_record() {
+        let row = serde_json::json!({
+            "t_s": 0.5, "true_temp_c": 301.0, "s1_c": 301.1, "s2_c": 300.9, "s3_c": null,
+            "power": 0.6, "coolant": 0.2, "scram": false, "reason": null,
+        });
+        let mut bytes = cli::msgpack::MAGIC.to_vec();
+        for record in [
+            serde_json::json!({"_meta": {"schema": 3}}),
+            row.clone(),
+            row,
+        ] {
+            cli::msgpack::write_record(&mut bytes, &record).unwrap();
+        }
+        let trace = load_trace_from_bytes("x.msgpack", &bytes).unwrap();
+        assert_eq!(trace.samples.len(), 2);
+        assert!(trace.meta.is_some());
+        assert!(trace.samples[1].s3.is_nan());
+
+        bytes.truncate(bytes.len() - 3);
+        let err = load_trace_from_bytes("x.msgpack", &bytes).unwrap_err();
+        assert_eq!(err, "Failed to read x.msgpack: record 3 is cut off");
+    }
+
+    // Trips for OverTemp, clears, then trips again by hand
+    fn retrip_trace() -> String {
+        [
+            (false, "null"),
+            (true, "\"OverTemp\""),
+            (true, "\"OverTemp\""),
+            (false, "null"),
+        ]
+        .into_iter()
+        .chain([(true, "\"Manual\"")])
+        .enumerate()
+        .map(|(i, (scram, reason))| {
+            format!(
+                "{{\"t_s\":{i}.0,\"true_temp_c\":300.0,\"s1_c\":300.0,\"s2_c\":300.0,\
+                     \"s3_c\":300.0,\"power\":0.5,\"coolant\":0.2,\"scram\":{scram},\
+                     \"reason\":{reason}}}\n"
+            )
+        })
+        .collect()
+    }
+
+    #[test]
+    fn rows_keep_their_own_reason() {
+        let trace = load_trace_from_bytes("retrip.jsonl", retrip_trace().as_bytes()).unwrap();
+        let reasons: Vec<Option<String>> = trace
+            .samples
+            .iter()
+            .map(|s| s.reason.as_deref().map(RowReason::to_string))
+            .collect();
+        let [over, manual] = ["OverTemp", "Manual"].map(|r| Some(r.to_string()));
+        assert_eq!(reasons, [None, over.clone(), over, None, manual]);
+        let shared = |i: usize| trace.samples[i].reason.clone().unwrap();
+        assert!(Arc::ptr_eq(&shared(1), &shared(2)));
+        assert_eq!(
+            trace.first_reason.map(|r| r.to_string()).as_deref(),
+            Some("OverTemp")
+        );
+    }
+
+    #[test]
+    fn replay_reason_follows_the_samples_shown() {
+        let mut app = App::default();
+        app.load_trace_bytes("retrip.jsonl", retrip_trace().as_bytes());
+        assert!(app.replay_loaded, "{:?}", app.last_error);
+        let mut shown = Vec::new();
+        for pos in 1..=5 {
+            app.seek_replay(pos);
+            shown.push((app.scram_time_for_plot(), app.reason_text()));
+        }
+        let shown: Vec<(Option<f64>, &str)> = shown.iter().map(|(t, r)| (*t, r.as_str())).collect();
+        assert_eq!(
+            shown,
+            [
+                (None, "—"),
+                (Some(1.0), "OverTemp"),
+                (Some(1.0), "OverTemp"),
+                (Some(1.0), "—"),
+                (Some(1.0), "Manual"),
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_names_are_recognized_by_extension() {
+        for name in [
+            "a.jsonl",
+            "dir/B.JSONL",
+            "c.jsonl.gz",
+            "d.msgpack",
+            "e.msgpack.gz",
+        ] {
+            assert!(is_trace_name(name), "{name}");
+        }
+        for name in ["a.csv", "b.gz", "c.txt", "jsonl"] {
+            assert!(!is_trace_name(name), "{name}");
+        }
+    }
+}
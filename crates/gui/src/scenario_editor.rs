@@ -0,0 +1,508 @@
+//! The scenario editor: custom scenarios edited in a window, run live, and kept in the files
+//! `--scenario-file` reads.
+
+use std::fs;
+use std::path::Path;
+use std::sync::mpsc::TryRecvError;
+use std::sync::Arc;
+
+use cli::config::SENSOR_NAMES;
+use eframe::egui;
+use sim::{EventAction, FaultWindow, ScenarioSpec, SensorFault, TimedEvent};
+
+use crate::{spawn_dialog, App, DIALOG_POLL, HAS_FILES};
+
+/// Fault kinds offered by the "Faults" panel, each with the parameter it starts from
+const FAULT_KINDS: [(&str, SensorFault); 5] = [
+    ("None", SensorFault::None),
+    ("Bias", SensorFault::Bias { value: 30.0 }),
+    ("Drift", SensorFault::Drift { per_s: 1.0 }),
+    ("Stuck", SensorFault::Stuck { value: 350.0 }),
+    ("Dropout", SensorFault::DropoutEvery { n: 5 }),
+];
+
+fn fault_kind(fault: &SensorFault) -> &'static str {
+    match fault {
+        SensorFault::None => "None",
+        SensorFault::Bias { .. } => "Bias",
+        SensorFault::Drift { .. } => "Drift",
+        SensorFault::Stuck { .. } => "Stuck",
+        SensorFault::DropoutEvery { .. } => "Dropout",
+    }
+}
+
+pub(crate) fn fault_text(fault: &SensorFault) -> String {
+    match fault {
+        SensorFault::None => "healthy".to_string(),
+        SensorFault::Bias { value } => format!("bias {value:+} °C"),
+        SensorFault::Drift { per_s } => format!("drift {per_s:+} °C/s"),
+        SensorFault::Stuck { value } => format!("stuck at {value} °C"),
+        SensorFault::DropoutEvery { n } => format!("dropout every {n} readings"),
+    }
+}
+
+/// Scenario file types of the editor's dialogs: TOML, or YAML as `--scenario-file` also reads
+const SCENARIO_EXTENSIONS: [&str; 3] = ["toml", "yaml", "yml"];
+
+/// Timed event kinds of the scenario editor, with the values a new one starts from
+const EVENT_KINDS: [(&str, EventAction); 3] = [
+    ("set coolant", EventAction::SetCoolant(0.3)),
+    ("set setpoint", EventAction::SetSetpoint(350.0)),
+    ("SCRAM", EventAction::Scram),
+];
+
+fn event_kind(action: &EventAction) -> &'static str {
+    match action {
+        EventAction::SetCoolant(_) => "set coolant",
+        EventAction::SetSetpoint(_) => "set setpoint",
+        EventAction::Scram => "SCRAM",
+    }
+}
+
+fn is_yaml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml") || ext.eq_ignore_ascii_case("yml"))
+}
+
+/// A scenario as `--scenario-file` reads it: YAML, or TOML (the editor's preview).
+fn scenario_text(spec: &ScenarioSpec, yaml: bool) -> Result<String, String> {
+    if yaml {
+        serde_yaml::to_string(spec).map_err(|e| e.to_string())
+    } else {
+        toml::to_string_pretty(spec).map_err(|e| e.to_string())
+    }
+}
+
+/// Read a scenario file as `--scenario-file` does: YAML for `.yaml`/`.yml`, TOML otherwise. Its
+/// values are not checked, so the editor can show what is wrong with them.
+fn read_scenario(path: &Path) -> Result<ScenarioSpec, String> {
+    let fail = |e: &dyn std::fmt::Display| format!("Failed to load {}: {e}", path.display());
+    let text = fs::read_to_string(path).map_err(|e| fail(&e))?;
+    let parsed = if is_yaml(path) {
+        serde_yaml::from_str(&text).map_err(|e| e.to_string())
+    } else {
+        toml::from_str(&text).map_err(|e| e.to_string())
+    };
+    parsed.map_err(|e| fail(&e.trim_end()))
+}
+
+/// Save a scenario for `--scenario-file`, as YAML for `.yaml`/`.yml` and TOML otherwise.
+fn write_scenario(path: &Path, spec: &ScenarioSpec) -> Result<(), String> {
+    let fail = |e: &dyn std::fmt::Display| format!("Failed to write {}: {e}", path.display());
+    let text = scenario_text(spec, is_yaml(path)).map_err(|e| fail(&e))?;
+    fs::write(path, text).map_err(|e| fail(&e))
+}
+
+/// A fault's kind and its parameter, as set up in the "Faults" panel and the scenario editor.
+pub(crate) fn fault_editor(
+    ui: &mut egui::Ui,
+    id_salt: impl std::hash::Hash,
+    fault: &mut SensorFault,
+) {
+    egui::ComboBox::from_id_salt(id_salt)
+        .width(80.0)
+        .selected_text(fault_kind(fault))
+        .show_ui(ui, |ui| {
+            for (kind, default) in FAULT_KINDS {
+                if ui
+                    .selectable_label(fault_kind(fault) == kind, kind)
+                    .clicked()
+                {
+                    *fault = default;
+                }
+            }
+        });
+    match fault {
+        SensorFault::None => {}
+        SensorFault::Bias { value } => {
+            ui.add(egui::DragValue::new(value).speed(0.5).suffix(" °C"));
+        }
+        SensorFault::Drift { per_s } => {
+            ui.add(egui::DragValue::new(per_s).speed(0.05).suffix(" °C/s"));
+        }
+        SensorFault::Stuck { value } => {
+            ui.add(egui::DragValue::new(value).speed(1.0).suffix(" °C"));
+        }
+        SensorFault::DropoutEvery { n } => {
+            ui.add(egui::DragValue::new(n).range(1..=1000).prefix("every "));
+        }
+    }
+}
+
+impl App {
+    /// "Run this scenario": start a live run of the edited scenario in place of the built-in one.
+    fn run_custom_scenario(&mut self) {
+        let spec = ScenarioSpec {
+            events: self.scenario_edit.sorted_events(),
+            ..self.scenario_edit.clone()
+        };
+        self.custom_scenario = Some(Arc::new(spec));
+        self.running = false;
+        self.toggle_run();
+    }
+
+    /// "Load…" of the scenario editor: pick a scenario file, starting next to the last one.
+    fn browse_scenario_open(&mut self) {
+        if self.scenario_open_dialog.is_some() {
+            return;
+        }
+        let mut dialog = rfd::AsyncFileDialog::new()
+            .set_title("Load scenario")
+            .add_filter("Scenario (.toml, .yaml)", &SCENARIO_EXTENSIONS);
+        if let Some(dir) = self.scenario_dir() {
+            dialog = dialog.set_directory(dir);
+        }
+        self.scenario_open_dialog = Some(spawn_dialog(dialog.pick_file()));
+    }
+
+    /// "Save…" of the scenario editor: pick where to save it, as TOML or YAML.
+    fn browse_scenario_save(&mut self) {
+        if self.scenario_save_dialog.is_some() {
+            return;
+        }
+        let name = match self.scenario_edit.name.as_str() {
+            "" => "scenario",
+            name => name,
+        };
+        let mut dialog = rfd::AsyncFileDialog::new()
+            .set_title("Save scenario")
+            .set_file_name(format!("{name}.toml"))
+            .add_filter("TOML", &["toml"])
+            .add_filter("YAML", &["yaml", "yml"]);
+        if let Some(dir) = self.scenario_dir() {
+            dialog = dialog.set_directory(dir);
+        }
+        self.scenario_save_dialog = Some(spawn_dialog(dialog.save_file()));
+    }
+
+    fn scenario_dir(&self) -> Option<&Path> {
+        let path = self.scenario_path.as_deref()?;
+        path.parent().filter(|dir| dir.is_dir())
+    }
+
+    /// Load or save the edited scenario once its dialog closes; cancelling changes nothing.
+    pub(crate) fn poll_scenario_dialogs(&mut self, ctx: &egui::Context) {
+        for save in [false, true] {
+            let dialog = if save {
+                &mut self.scenario_save_dialog
+            } else {
+                &mut self.scenario_open_dialog
+            };
+            let Some(rx) = dialog else {
+                continue;
+            };
+            let path = match rx.try_recv() {
+                Ok(Some(path)) => path,
+                Ok(None) | Err(TryRecvError::Disconnected) => {
+                    *dialog = None;
+                    continue;
+                }
+                Err(TryRecvError::Empty) => {
+                    ctx.request_repaint_after(DIALOG_POLL);
+                    continue;
+                }
+            };
+            *dialog = None;
+            let done = if save {
+                write_scenario(&path, &self.scenario_edit)
+            } else {
+                read_scenario(&path).map(|spec| self.scenario_edit = spec)
+            };
+            match done {
+                Ok(()) => self.scenario_path = Some(path),
+                Err(e) => self.last_error = Some(e),
+            }
+        }
+    }
+
+    /// The "Scenario editor" window: initial conditions, fault windows per sensor and timed events
+    /// of a scenario, with its file as a preview. Values `--scenario-file` would reject are listed,
+    /// and keep it from being saved or run.
+    fn scenario_editor(&mut self, ui: &mut egui::Ui) {
+        let spec = &mut self.scenario_edit;
+        egui::Grid::new("scenario_about")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("name");
+                ui.text_edit_singleline(&mut spec.name);
+                ui.end_row();
+                ui.label("description");
+                ui.text_edit_singleline(&mut spec.description);
+                ui.end_row();
+            });
+
+        ui.separator();
+        ui.strong("Initial conditions");
+        ui.horizontal(|ui| {
+            let initial = &mut spec.initial;
+            ui.add(
+                egui::DragValue::new(&mut initial.temp_c)
+                    .speed(1.0)
+                    .prefix("temp ")
+                    .suffix(" °C"),
+            );
+            ui.add(
+                egui::DragValue::new(&mut initial.power)
+                    .speed(0.01)
+                    .prefix("power "),
+            );
+            ui.add(
+                egui::DragValue::new(&mut initial.coolant)
+                    .speed(0.01)
+                    .prefix("coolant "),
+            );
+        });
+
+        ui.separator();
+        ui.strong("Sensor faults");
+        let schedules = [
+            &mut spec.sensors.s1,
+            &mut spec.sensors.s2,
+            &mut spec.sensors.s3,
+        ];
+        for (i, schedule) in schedules.into_iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(SENSOR_NAMES[i]);
+                if schedule.windows.is_empty() {
+                    ui.weak("healthy");
+                }
+                if ui.small_button("+ fault").clicked() {
+                    let from_s = schedule.windows.last().map_or(0.0, |w| w.from_s);
+                    schedule.windows.push(FaultWindow {
+                        from_s,
+                        until_s: None,
+                        fault: FAULT_KINDS[1].1,
+                    });
+                }
+            });
+            let mut remove = None;
+            for (j, window) in schedule.windows.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.add_space(16.0);
+                    fault_editor(ui, ("scenario_fault", i, j), &mut window.fault);
+                    ui.add(
+                        egui::DragValue::new(&mut window.from_s)
+                            .speed(0.5)
+                            .prefix("from ")
+                            .suffix(" s"),
+                    );
+                    let mut until = window.until_s.is_some();
+                    if ui.checkbox(&mut until, "until").changed() {
+                        window.until_s = until.then_some(window.from_s + 10.0);
+                    }
+                    if let Some(until_s) = &mut window.until_s {
+                        ui.add(egui::DragValue::new(until_s).speed(0.5).suffix(" s"));
+                    }
+                    if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                        remove = Some(j);
+                    }
+                });
+            }
+            if let Some(j) = remove {
+                schedule.windows.remove(j);
+            }
+        }
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.strong("Timed events");
+            if ui.small_button("+ event").clicked() {
+                let t_s = spec.events.last().map_or(0.0, |e| e.t_s);
+                spec.events.push(TimedEvent {
+                    t_s,
+                    action: EVENT_KINDS[0].1,
+                });
+            }
+        });
+        if !spec.events.is_empty() {
+            let mut remove = None;
+            egui::Grid::new("scenario_events")
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.label("t (s)");
+                    ui.label("action");
+                    ui.label("value");
+                    ui.end_row();
+                    for (j, event) in spec.events.iter_mut().enumerate() {
+                        ui.add(egui::DragValue::new(&mut event.t_s).speed(0.5));
+                        let action = &mut event.action;
+                        egui::ComboBox::from_id_salt(("scenario_event", j))
+                            .width(100.0)
+                            .selected_text(event_kind(action))
+                            .show_ui(ui, |ui| {
+                                for (kind, default) in EVENT_KINDS {
+                                    let current = event_kind(action) == kind;
+                                    if ui.selectable_label(current, kind).clicked() && !current {
+                                        *action = default;
+                                    }
+                                }
+                            });
+                        match action {
+                            EventAction::SetCoolant(coolant) => {
+                                ui.add(egui::DragValue::new(coolant).speed(0.01));
+                            }
+                            EventAction::SetSetpoint(setpoint) => {
+                                ui.add(egui::DragValue::new(setpoint).speed(1.0).suffix(" °C"));
+                            }
+                            EventAction::Scram => {
+                                ui.label("");
+                            }
+                        }
+                        if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                            remove = Some(j);
+                        }
+                        ui.end_row();
+                    }
+                });
+            if let Some(j) = remove {
+                spec.events.remove(j);
+            }
+        }
+
+        ui.separator();
+        let errors = spec.validate().err().unwrap_or_default();
+        for e in &errors {
+            ui.colored_label(egui::Color32::RED, e.to_string());
+        }
+        egui::CollapsingHeader::new("TOML preview").show(ui, |ui| {
+            match scenario_text(spec, false) {
+                Ok(text) => {
+                    egui::ScrollArea::vertical()
+                        .max_height(240.0)
+                        .show(ui, |ui| ui.code(text));
+                }
+                Err(e) => {
+                    ui.colored_label(egui::Color32::RED, e);
+                }
+            }
+        });
+
+        let valid = errors.is_empty();
+        ui.horizontal(|ui| {
+            if HAS_FILES && ui.button("Load…").clicked() {
+                self.browse_scenario_open();
+            }
+            if HAS_FILES && ui.add_enabled(valid, egui::Button::new("Save…")).clicked() {
+                self.browse_scenario_save();
+            }
+            if ui
+                .add_enabled(valid, egui::Button::new("Run this scenario"))
+                .on_hover_text("Start a live run of it, in place of the built-in scenario")
+                .clicked()
+            {
+                self.run_custom_scenario();
+            }
+        });
+        if let Some(path) = &self.scenario_path {
+            ui.small(format!("file: {}", path.display()));
+        }
+    }
+
+    /// The scenario editor, in its own window while it is open.
+    pub(crate) fn scenario_editor_window(&mut self, ctx: &egui::Context) {
+        let mut editor_open = self.show_scenario_editor;
+        egui::Window::new("Scenario editor")
+            .open(&mut editor_open)
+            .default_width(420.0)
+            .show(ctx, |ui| self.scenario_editor(ui));
+        self.show_scenario_editor = editor_open;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use crate::tests::{export_dir, live_sim};
+    use crate::Scenario;
+
+    /// Drift on s1 from 2 s and a coolant step to 0.3 at 4 s, as built in the scenario editor
+    fn edited_scenario() -> ScenarioSpec {
+        let mut spec = ScenarioSpec {
+            name: "drift-and-coolant".to_string(),
+            description: "s1 drifts, then coolant drops".to_string(),
+            ..Default::default()
+        };
+        spec.sensors.s1.windows.push(FaultWindow {
+            from_s: 2.0,
+            until_s: None,
+            fault: SensorFault::Drift { per_s: 1.5 },
+        });
+        spec.events.push(TimedEvent {
+            t_s: 4.0,
+            action: EventAction::SetCoolant(0.3),
+        });
+        spec
+    }
+
+    #[test]
+    fn edited_scenarios_round_trip_through_their_files() {
+        let dir = export_dir("scenario");
+        let mut spec = edited_scenario();
+        spec.sensors.s2.windows.push(FaultWindow {
+            from_s: 1.0,
+            until_s: Some(3.5),
+            fault: SensorFault::Stuck { value: 300.0 },
+        });
+        spec.events.push(TimedEvent {
+            t_s: 5.0,
+            action: EventAction::Scram,
+        });
+        for name in ["edited.toml", "edited.yaml"] {
+            let path = dir.join(name);
+            write_scenario(&path, &spec).unwrap();
+            assert_eq!(read_scenario(&path).unwrap(), spec, "{name}");
+        }
+        let toml = fs::read_to_string(dir.join("edited.toml")).unwrap();
+        assert!(toml.contains("kind = \"drift\""), "{toml}");
+
+        // The CLI's examples load into the editor and save unchanged
+        let examples = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../examples");
+        let original = read_scenario(&examples.join("sensor_disagree.toml")).unwrap();
+        let path = dir.join("sensor_disagree.toml");
+        write_scenario(&path, &original).unwrap();
+        assert_eq!(read_scenario(&path).unwrap(), original);
+
+        fs::write(dir.join("bad.toml"), "name = 3\n").unwrap();
+        let err = read_scenario(&dir.join("bad.toml")).unwrap_err();
+        assert!(err.starts_with("Failed to load"), "{err}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn edited_scenario_drives_the_live_run() {
+        let mut sim = live_sim(Scenario::Normal);
+        sim.set_custom(Arc::new(edited_scenario()));
+        sim.engine.state.coolant = 0.6;
+        while sim.t() < 1.9 {
+            sim.step();
+        }
+        assert_eq!(sim.engine.sensors[0].fault, SensorFault::None);
+        while sim.t() < 3.95 {
+            sim.step();
+        }
+        assert_eq!(
+            sim.engine.sensors[0].fault,
+            SensorFault::Drift { per_s: 1.5 }
+        );
+        assert_eq!(sim.engine.state.coolant, 0.6);
+        while sim.t() < 4.05 {
+            sim.step();
+        }
+        assert_eq!(sim.engine.state.coolant, 0.3);
+
+        let log: Vec<String> = sim.log.iter().map(|e| e.text.clone()).collect();
+        assert_eq!(
+            log,
+            ["scenario: s1 drift +1.5 °C/s", "scenario: coolant 0.3"]
+        );
+
+        // An injected fault stays until the schedule changes
+        sim.inject_fault(0, SensorFault::None);
+        sim.step();
+        assert_eq!(sim.engine.sensors[0].fault, SensorFault::None);
+    }
+}
@@ -1,14 +1,127 @@
+use std::fmt;
+
+/// Version of this crate, as recorded in trace provenance.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TripReason {
     OverTemp,
     SensorInvalid,
     SensorDisagree,
+    /// Operator-initiated, not decided by `evaluate`.
+    Manual,
+}
+
+impl TripReason {
+    /// The `kind` of a serialized `TripRecord` with this reason.
+    pub fn kind(self) -> &'static str {
+        match self {
+            TripReason::OverTemp => "over_temp",
+            TripReason::SensorInvalid => "sensor_invalid",
+            TripReason::SensorDisagree => "sensor_disagree",
+            TripReason::Manual => "manual",
+        }
+    }
+}
+
+/// The measurements behind a trip, by reason. Serialized as `"kind"` plus a `"detail"` object.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(tag = "kind", content = "detail", rename_all = "snake_case")
+)]
+pub enum TripDetail {
+    OverTemp {
+        trip_temp_c: f64,
+        /// Hottest valid reading at the trip
+        hottest_c: f64,
+    },
+    SensorInvalid {
+        valid_sensors: usize,
+        valid_range_c: (f64, f64),
+    },
+    SensorDisagree {
+        /// Spread between the valid readings at the trip
+        spread_c: f64,
+        max_sensor_delta_c: f64,
+    },
+    Manual {},
+}
+
+impl TripDetail {
+    pub fn reason(&self) -> TripReason {
+        match self {
+            TripDetail::OverTemp { .. } => TripReason::OverTemp,
+            TripDetail::SensorInvalid { .. } => TripReason::SensorInvalid,
+            TripDetail::SensorDisagree { .. } => TripReason::SensorDisagree,
+            TripDetail::Manual {} => TripReason::Manual,
+        }
+    }
+}
+
+/// Why and on which sensors a SCRAM latched:
+/// `{"kind": "over_temp", "channels": [true, false, true], "detail": {...}}` when serialized.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TripRecord {
+    #[cfg_attr(feature = "serde", serde(flatten))]
+    pub detail: TripDetail,
+    /// Sensors s1..s3 that voted for the trip: at or above the limit (over_temp), invalid
+    /// (sensor_invalid), the lowest and highest valid reading (sensor_disagree); none for manual.
+    pub channels: [bool; 3],
+}
+
+impl TripRecord {
+    pub fn reason(&self) -> TripReason {
+        self.detail.reason()
+    }
+}
+
+impl fmt::Display for TripRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.reason())?;
+        let channels: Vec<String> = (0..3)
+            .filter(|&i| self.channels[i])
+            .map(|i| format!("s{}", i + 1))
+            .collect();
+        let channels = channels.join(", ");
+        match self.detail {
+            TripDetail::OverTemp {
+                trip_temp_c,
+                hottest_c,
+            } => write!(
+                f,
+                " ({channels} >= {trip_temp_c} °C, hottest {hottest_c:.1} °C)"
+            ),
+            TripDetail::SensorInvalid {
+                valid_sensors,
+                valid_range_c: (min, max),
+            } => write!(
+                f,
+                " ({channels} outside {min}..={max} °C, {valid_sensors} of 3 valid)"
+            ),
+            TripDetail::SensorDisagree {
+                spread_c,
+                max_sensor_delta_c,
+            } => write!(
+                f,
+                " ({channels} {spread_c:.1} °C apart, limit {max_sensor_delta_c} °C)"
+            ),
+            TripDetail::Manual {} => Ok(()),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
 pub struct SafetyConfig {
     pub trip_temp_c: f64,
     pub max_sensor_delta_c: f64,
+    /// Sensor spread (°C) that raises the disagreement alarm without tripping.
+    pub alarm_sensor_delta_c: f64,
     pub valid_range_c: (f64, f64),
 }
 
@@ -17,19 +130,170 @@ impl Default for SafetyConfig {
         Self {
             trip_temp_c: 420.0,
             max_sensor_delta_c: 10.0,
+            alarm_sensor_delta_c: 5.0,
             valid_range_c: (0.0, 2000.0),
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SafetyConfigError {
+    NonFinite {
+        field: &'static str,
+    },
+    NonPositive {
+        field: &'static str,
+        value: f64,
+    },
+    InvertedRange {
+        min: f64,
+        max: f64,
+    },
+    /// The trip threshold can never be reached by a reading the validity check accepts.
+    TripOutsideValidRange {
+        trip_temp_c: f64,
+        min: f64,
+        max: f64,
+    },
+    /// The alarm band is wider than the trip band, so the alarm can never be raised.
+    AlarmAboveTrip {
+        alarm: f64,
+        trip: f64,
+    },
+}
+
+impl fmt::Display for SafetyConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SafetyConfigError::NonFinite { field } => {
+                write!(f, "safety.{field} must be a finite number")
+            }
+            SafetyConfigError::NonPositive { field, value } => {
+                write!(f, "safety.{field} = {value} must be > 0")
+            }
+            SafetyConfigError::InvertedRange { min, max } => write!(
+                f,
+                "safety.valid_range_c: lower bound ({min}) must be less than upper bound ({max})"
+            ),
+            SafetyConfigError::TripOutsideValidRange {
+                trip_temp_c,
+                min,
+                max,
+            } => write!(
+                f,
+                "safety.trip_temp_c = {trip_temp_c} is outside safety.valid_range_c ({min}..={max}), \
+                 so the over-temperature trip can never fire"
+            ),
+            SafetyConfigError::AlarmAboveTrip { alarm, trip } => write!(
+                f,
+                "safety.alarm_sensor_delta_c = {alarm} must be below safety.max_sensor_delta_c \
+                 ({trip})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SafetyConfigError {}
+
+impl SafetyConfig {
+    /// Check for thresholds that would leave a protection function dead. Returns every problem
+    /// found, not just the first.
+    pub fn validate(&self) -> Result<(), Vec<SafetyConfigError>> {
+        let mut errors = Vec::new();
+        let (min, max) = self.valid_range_c;
+        let fields = [
+            ("trip_temp_c", self.trip_temp_c),
+            ("max_sensor_delta_c", self.max_sensor_delta_c),
+            ("alarm_sensor_delta_c", self.alarm_sensor_delta_c),
+            ("valid_range_c", min),
+            ("valid_range_c", max),
+        ];
+        for (field, value) in fields {
+            if !value.is_finite() && !errors.contains(&SafetyConfigError::NonFinite { field }) {
+                errors.push(SafetyConfigError::NonFinite { field });
+            }
+        }
+        for (field, value) in [
+            ("max_sensor_delta_c", self.max_sensor_delta_c),
+            ("alarm_sensor_delta_c", self.alarm_sensor_delta_c),
+        ] {
+            if value <= 0.0 {
+                errors.push(SafetyConfigError::NonPositive { field, value });
+            }
+        }
+        if min >= max {
+            errors.push(SafetyConfigError::InvertedRange { min, max });
+        } else if self.trip_temp_c < min || self.trip_temp_c > max {
+            errors.push(SafetyConfigError::TripOutsideValidRange {
+                trip_temp_c: self.trip_temp_c,
+                min,
+                max,
+            });
+        }
+        if self.alarm_sensor_delta_c > 0.0 && self.alarm_sensor_delta_c >= self.max_sensor_delta_c {
+            errors.push(SafetyConfigError::AlarmAboveTrip {
+                alarm: self.alarm_sensor_delta_c,
+                trip: self.max_sensor_delta_c,
+            });
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Whether a reading passes the validity check: a number within `valid_range_c`.
+    pub fn reading_valid(&self, v: f64) -> bool {
+        v.is_finite() && !v.is_nan() && v >= self.valid_range_c.0 && v <= self.valid_range_c.1
+    }
+
+    /// The temperature the over-temperature vote acts on: the second-hottest valid reading, since
+    /// the trip takes two channels at or above the limit. None with fewer than two valid readings
+    /// (which trips on sensor validity instead).
+    pub fn voted_temp(&self, temps: [f64; 3]) -> Option<f64> {
+        let mut valid: Vec<f64> = temps
+            .into_iter()
+            .filter(|v| self.reading_valid(*v))
+            .collect();
+        valid.sort_by(|a, b| b.total_cmp(a));
+        valid.get(1).copied()
+    }
+
+    /// Distance from the voted temperature down to `trip_temp_c`; negative once past it.
+    pub fn trip_margin(&self, temps: [f64; 3]) -> Option<f64> {
+        self.voted_temp(temps).map(|t| self.trip_temp_c - t)
+    }
+}
+
 #[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SafetyState {
     pub scram: bool,
     pub reason: Option<TripReason>,
+    /// Detail of the latched trip, set together with `reason`
+    pub trip: Option<TripRecord>,
 }
 
-fn is_valid(cfg: &SafetyConfig, v: f64) -> bool {
-    v.is_finite() && !v.is_nan() && v >= cfg.valid_range_c.0 && v <= cfg.valid_range_c.1
+impl SafetyState {
+    fn latch(&mut self, detail: TripDetail, channels: [bool; 3]) {
+        self.scram = true;
+        self.reason = Some(detail.reason());
+        self.trip = Some(TripRecord { detail, channels });
+    }
+}
+
+/// Outcome of one `evaluate` call: the latched trip state plus non-tripping alarms.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SafetyDecision {
+    pub scram: bool,
+    pub reason: Option<TripReason>,
+    /// Valid sensors disagree by more than `alarm_sensor_delta_c` (but not enough to trip).
+    pub sensor_disagree_alarm: bool,
+    /// Number of sensors whose reading passed the validity check.
+    pub valid_sensors: usize,
+    /// Which sensors (s1..s3) passed the validity check.
+    pub valid: [bool; 3],
 }
 
 fn two_out_of_three(flags: [bool; 3]) -> bool {
@@ -42,39 +306,93 @@ fn two_out_of_three(flags: [bool; 3]) -> bool {
     c >= 2
 }
 
+/// Latch an operator-initiated SCRAM. An already latched trip keeps its original reason.
+pub fn manual_scram(state: &mut SafetyState) {
+    if !state.scram {
+        state.latch(TripDetail::Manual {}, [false; 3]);
+        #[cfg(feature = "tracing")]
+        tracing::info!(reason = ?TripReason::Manual, "trip");
+    }
+}
+
 /// Evaluate safety conditions using three redundant sensor measurements.
-/// Returns updated state. Once SCRAM is asserted, it remains latched.
-pub fn evaluate(cfg: &SafetyConfig, state: &mut SafetyState, temps: [f64; 3]) {
-    if state.scram {
-        return;
+/// Updates the state and returns this step's decision. Once SCRAM is asserted, it remains latched.
+pub fn evaluate(cfg: &SafetyConfig, state: &mut SafetyState, temps: [f64; 3]) -> SafetyDecision {
+    #[cfg(feature = "tracing")]
+    let was_scram = state.scram;
+    let decision = check(cfg, state, temps);
+    #[cfg(feature = "tracing")]
+    if decision.scram && !was_scram {
+        tracing::info!(
+            reason = ?decision.reason,
+            ?temps,
+            valid_sensors = decision.valid_sensors,
+            "trip"
+        );
     }
+    decision
+}
 
+fn check(cfg: &SafetyConfig, state: &mut SafetyState, temps: [f64; 3]) -> SafetyDecision {
     // Validity
-    let valids = [
-        is_valid(cfg, temps[0]),
-        is_valid(cfg, temps[1]),
-        is_valid(cfg, temps[2]),
-    ];
+    let valids = temps.map(|v| cfg.reading_valid(v));
+    let mut decision = SafetyDecision {
+        valid: valids,
+        valid_sensors: valids.iter().filter(|v| **v).count(),
+        ..Default::default()
+    };
+
+    let decide = |state: &SafetyState, decision: SafetyDecision| SafetyDecision {
+        scram: state.scram,
+        reason: state.reason,
+        ..decision
+    };
+
+    if state.scram {
+        return decide(state, decision);
+    }
+
     if !two_out_of_three(valids) {
-        state.scram = true;
-        state.reason = Some(TripReason::SensorInvalid);
-        return;
+        state.latch(
+            TripDetail::SensorInvalid {
+                valid_sensors: decision.valid_sensors,
+                valid_range_c: cfg.valid_range_c,
+            },
+            valids.map(|v| !v),
+        );
+        return decide(state, decision);
     }
 
     // Disagreement check among valid sensors
     let mut min_v = f64::INFINITY;
     let mut max_v = f64::NEG_INFINITY;
+    let (mut lowest, mut highest) = (0, 0);
     for (i, v) in temps.iter().enumerate() {
         if valids[i] {
-            min_v = min_v.min(*v);
-            max_v = max_v.max(*v);
+            if *v < min_v {
+                min_v = *v;
+                lowest = i;
+            }
+            if *v > max_v {
+                max_v = *v;
+                highest = i;
+            }
         }
     }
     if (max_v - min_v) > cfg.max_sensor_delta_c {
-        state.scram = true;
-        state.reason = Some(TripReason::SensorDisagree);
-        return;
+        let mut channels = [false; 3];
+        channels[lowest] = true;
+        channels[highest] = true;
+        state.latch(
+            TripDetail::SensorDisagree {
+                spread_c: max_v - min_v,
+                max_sensor_delta_c: cfg.max_sensor_delta_c,
+            },
+            channels,
+        );
+        return decide(state, decision);
     }
+    decision.sensor_disagree_alarm = (max_v - min_v) > cfg.alarm_sensor_delta_c;
 
     // Over-temp vote
     let over = [
@@ -83,7 +401,13 @@ pub fn evaluate(cfg: &SafetyConfig, state: &mut SafetyState, temps: [f64; 3]) {
         valids[2] && temps[2] >= cfg.trip_temp_c,
     ];
     if two_out_of_three(over) {
-        state.scram = true;
-        state.reason = Some(TripReason::OverTemp);
+        state.latch(
+            TripDetail::OverTemp {
+                trip_temp_c: cfg.trip_temp_c,
+                hottest_c: max_v,
+            },
+            over,
+        );
     }
+    decide(state, decision)
 }
@@ -1,8 +1,22 @@
-use rand::rngs::StdRng;
+use std::fmt;
+
 use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
 use rand_distr::{Distribution, Normal};
 
-#[derive(Clone, Copy, Debug)]
+mod scenario;
+
+pub use scenario::{
+    EventAction, FaultSchedule, FaultWindow, InitialConditions, ScenarioError, ScenarioSpec,
+    SensorSchedules, TimedEvent,
+};
+
+/// Version of this crate, as recorded in trace provenance.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
 pub struct PlantParams {
     pub ambient_c: f64,
     pub thermal_mass: f64,
@@ -21,7 +35,68 @@ impl Default for PlantParams {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlantParamsError {
+    NonFinite { field: &'static str },
+    NonPositive { field: &'static str, value: f64 },
+    Negative { field: &'static str, value: f64 },
+}
+
+impl fmt::Display for PlantParamsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlantParamsError::NonFinite { field } => {
+                write!(f, "plant.{field} must be a finite number")
+            }
+            PlantParamsError::NonPositive { field, value } => {
+                write!(f, "plant.{field} = {value} must be > 0")
+            }
+            PlantParamsError::Negative { field, value } => {
+                write!(f, "plant.{field} = {value} must be >= 0")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PlantParamsError {}
+
+impl PlantParams {
+    /// Check for parameters that would make the model meaningless (no heat capacity, heat
+    /// flowing the wrong way). Returns every problem found, not just the first.
+    pub fn validate(&self) -> Result<(), Vec<PlantParamsError>> {
+        let mut errors = Vec::new();
+        let fields = [
+            ("ambient_c", self.ambient_c),
+            ("thermal_mass", self.thermal_mass),
+            ("k_power", self.k_power),
+            ("k_cool", self.k_cool),
+        ];
+        for (field, value) in fields {
+            if !value.is_finite() {
+                errors.push(PlantParamsError::NonFinite { field });
+            }
+        }
+        if self.thermal_mass.is_finite() && self.thermal_mass <= 0.0 {
+            errors.push(PlantParamsError::NonPositive {
+                field: "thermal_mass",
+                value: self.thermal_mass,
+            });
+        }
+        for (field, value) in [("k_power", self.k_power), ("k_cool", self.k_cool)] {
+            if value < 0.0 {
+                errors.push(PlantParamsError::Negative { field, value });
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlantState {
     pub temp_c: f64,
     /// 0..=1 power fraction
@@ -56,7 +131,9 @@ impl PlantState {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "kind", rename_all = "snake_case"))]
 pub enum SensorFault {
     None,
     Stuck { value: f64 },
@@ -66,12 +143,18 @@ pub enum SensorFault {
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Sensor {
     pub noise_std: f64,
     pub fault: SensorFault,
     pub valid_range: (f64, f64),
-    rng: StdRng,
+    /// The generator behind `rand::rngs::StdRng`, named so its state can be serialized
+    rng: ChaCha12Rng,
     step_count: u64,
+    /// Fault seen by the previous read and the number of reads since it took effect, so a drift
+    /// switched on mid-run starts from zero.
+    active_fault: SensorFault,
+    fault_steps: u64,
 }
 
 impl Sensor {
@@ -80,21 +163,30 @@ impl Sensor {
             noise_std: 0.25,
             fault: SensorFault::None,
             valid_range: (0.0, 2000.0),
-            rng: StdRng::seed_from_u64(seed),
+            rng: ChaCha12Rng::seed_from_u64(seed),
             step_count: 0,
+            active_fault: SensorFault::None,
+            fault_steps: 0,
         }
     }
 
     pub fn read_temp(&mut self, true_temp: f64, dt_s: f64) -> f64 {
         self.step_count += 1;
+        if self.fault != self.active_fault {
+            self.active_fault = self.fault;
+            self.fault_steps = 0;
+        }
+        self.fault_steps += 1;
 
         let mut v = match self.fault {
             SensorFault::None => true_temp,
             SensorFault::Stuck { value } => value,
             SensorFault::Bias { value } => true_temp + value,
-            SensorFault::Drift { per_s } => true_temp + per_s * (self.step_count as f64) * dt_s,
+            SensorFault::Drift { per_s } => true_temp + per_s * (self.fault_steps as f64) * dt_s,
             SensorFault::DropoutEvery { n } => {
                 if n > 0 && self.step_count.is_multiple_of(n) {
+                    #[cfg(feature = "tracing")]
+                    tracing::trace!(read = self.step_count, "sensor reading dropped");
                     return f64::NAN;
                 }
                 true_temp
@@ -0,0 +1,279 @@
+//! Declarative scenario description: initial plant conditions, sensor faults switched on and off
+//! over time, and timed actuator/setpoint/scram events.
+
+use std::fmt;
+
+use crate::{PlantState, SensorFault};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct ScenarioSpec {
+    pub name: String,
+    pub description: String,
+    pub initial: InitialConditions,
+    pub sensors: SensorSchedules,
+    /// Applied at the first step whose time is at or after the event time, in time order.
+    pub events: Vec<TimedEvent>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct InitialConditions {
+    pub temp_c: f64,
+    /// 0..=1 power fraction
+    pub power: f64,
+    /// 0..=1 cooling fraction
+    pub coolant: f64,
+}
+
+impl Default for InitialConditions {
+    fn default() -> Self {
+        let x = PlantState::default();
+        Self {
+            temp_c: x.temp_c,
+            power: x.power,
+            coolant: x.coolant,
+        }
+    }
+}
+
+impl InitialConditions {
+    pub fn state(&self) -> PlantState {
+        PlantState {
+            temp_c: self.temp_c,
+            power: self.power,
+            coolant: self.coolant,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct SensorSchedules {
+    pub s1: FaultSchedule,
+    pub s2: FaultSchedule,
+    pub s3: FaultSchedule,
+}
+
+impl SensorSchedules {
+    pub fn all(&self) -> [(&'static str, &FaultSchedule); 3] {
+        [("s1", &self.s1), ("s2", &self.s2), ("s3", &self.s3)]
+    }
+}
+
+/// Fault windows of one sensor. Where windows overlap the later one wins; outside every window
+/// the sensor is healthy.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct FaultSchedule {
+    pub windows: Vec<FaultWindow>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct FaultWindow {
+    /// Activation time (s)
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub from_s: f64,
+    /// Deactivation time (s); active until the end of the run if absent
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub until_s: Option<f64>,
+    pub fault: SensorFault,
+}
+
+impl FaultSchedule {
+    /// A single fault active for the whole run.
+    pub fn always(fault: SensorFault) -> Self {
+        Self {
+            windows: vec![FaultWindow {
+                from_s: 0.0,
+                until_s: None,
+                fault,
+            }],
+        }
+    }
+
+    /// Fault in effect at `t_s`.
+    pub fn fault_at(&self, t_s: f64) -> SensorFault {
+        self.windows
+            .iter()
+            .rev()
+            .find(|w| w.from_s <= t_s && w.until_s.is_none_or(|u| t_s < u))
+            .map_or(SensorFault::None, |w| w.fault)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(deny_unknown_fields))]
+pub struct TimedEvent {
+    pub t_s: f64,
+    pub action: EventAction,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum EventAction {
+    /// Set the coolant fraction (0..=1)
+    SetCoolant(f64),
+    /// Replace the setpoint (°C) for the rest of the run
+    SetSetpoint(f64),
+    /// Operator-initiated SCRAM
+    Scram,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ScenarioError {
+    NonFinite {
+        field: String,
+    },
+    Negative {
+        field: String,
+        value: f64,
+    },
+    OutOfRange {
+        field: String,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+    EmptyWindow {
+        field: String,
+        from_s: f64,
+        until_s: f64,
+    },
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::NonFinite { field } => write!(f, "{field} must be a finite number"),
+            ScenarioError::Negative { field, value } => {
+                write!(f, "{field} = {value} must be >= 0")
+            }
+            ScenarioError::OutOfRange {
+                field,
+                value,
+                min,
+                max,
+            } => write!(f, "{field} = {value} must be within {min}..={max}"),
+            ScenarioError::EmptyWindow {
+                field,
+                from_s,
+                until_s,
+            } => write!(
+                f,
+                "{field}: until_s ({until_s}) must be after from_s ({from_s})"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl ScenarioSpec {
+    /// Events sorted by time; events with equal times keep their order.
+    pub fn sorted_events(&self) -> Vec<TimedEvent> {
+        let mut events = self.events.clone();
+        events.sort_by(|a, b| a.t_s.total_cmp(&b.t_s));
+        events
+    }
+
+    /// Check for values the simulation cannot honour. Returns every problem found, not just the
+    /// first; fields are named by their path in the scenario file.
+    pub fn validate(&self) -> Result<(), Vec<ScenarioError>> {
+        let mut errors = Vec::new();
+
+        finite(&mut errors, "initial.temp_c".into(), self.initial.temp_c);
+        fraction(&mut errors, "initial.power".into(), self.initial.power);
+        fraction(&mut errors, "initial.coolant".into(), self.initial.coolant);
+
+        for (sensor, schedule) in self.sensors.all() {
+            for (i, w) in schedule.windows.iter().enumerate() {
+                let field = format!("sensors.{sensor}[{i}]");
+                time(&mut errors, format!("{field}.from_s"), w.from_s);
+                if let Some(until_s) = w.until_s {
+                    if !until_s.is_finite() {
+                        errors.push(ScenarioError::NonFinite {
+                            field: format!("{field}.until_s"),
+                        });
+                    } else if until_s <= w.from_s {
+                        errors.push(ScenarioError::EmptyWindow {
+                            field,
+                            from_s: w.from_s,
+                            until_s,
+                        });
+                    }
+                }
+                match w.fault {
+                    SensorFault::None | SensorFault::DropoutEvery { .. } => {}
+                    SensorFault::Stuck { value } | SensorFault::Bias { value } => finite(
+                        &mut errors,
+                        format!("sensors.{sensor}[{i}].fault.value"),
+                        value,
+                    ),
+                    SensorFault::Drift { per_s } => finite(
+                        &mut errors,
+                        format!("sensors.{sensor}[{i}].fault.per_s"),
+                        per_s,
+                    ),
+                }
+            }
+        }
+
+        for (i, e) in self.events.iter().enumerate() {
+            time(&mut errors, format!("events[{i}].t_s"), e.t_s);
+            match e.action {
+                EventAction::SetCoolant(v) => {
+                    fraction(&mut errors, format!("events[{i}].action.set_coolant"), v)
+                }
+                EventAction::SetSetpoint(v) => {
+                    finite(&mut errors, format!("events[{i}].action.set_setpoint"), v)
+                }
+                EventAction::Scram => {}
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn finite(errors: &mut Vec<ScenarioError>, field: String, value: f64) {
+    if !value.is_finite() {
+        errors.push(ScenarioError::NonFinite { field });
+    }
+}
+
+fn fraction(errors: &mut Vec<ScenarioError>, field: String, value: f64) {
+    if !value.is_finite() {
+        errors.push(ScenarioError::NonFinite { field });
+    } else if !(0.0..=1.0).contains(&value) {
+        errors.push(ScenarioError::OutOfRange {
+            field,
+            value,
+            min: 0.0,
+            max: 1.0,
+        });
+    }
+}
+
+fn time(errors: &mut Vec<ScenarioError>, field: String, value: f64) {
+    if !value.is_finite() {
+        errors.push(ScenarioError::NonFinite { field });
+    } else if value < 0.0 {
+        errors.push(ScenarioError::Negative { field, value });
+    }
+}
@@ -0,0 +1,350 @@
+//! The trace schema: the `_meta` header and the rows the CLI writes, the GUI exports, and both
+//! read back.
+//!
+//! Every writer and reader goes through these types, so a field added here reaches all of them:
+//! - JSONL and MessagePack traces are `MetaLine<header>` followed by serde-serialized `TraceRow`s;
+//! - CSV traces have the columns of `csv_header` and the cells of `TraceRow::csv_cells`;
+//! - the GUI plots a `Sample` per row (`Sample::from`).
+
+use std::fmt;
+use std::sync::Arc;
+
+use controller::ControlOutput;
+use safety::TripRecord;
+use serde::{Deserialize, Deserializer, Serialize};
+use sim::PlantParams;
+
+/// Version of this crate, as recorded in trace provenance.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Layout version of the traces written by this build. Version 1 is the original layout without
+/// a header line; version 2 added the header; version 3 turned the row `reason` from the reason
+/// name into a `TripRecord` object, repeated on every row after the trip.
+pub const TRACE_SCHEMA: u32 = 3;
+
+/// What every trace header records of the run that wrote it. Writers flatten it into their own
+/// header next to what only they know (the CLI its configuration, the GUI its operator actions).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TraceHeader {
+    pub schema: u32,
+    pub crate_version: String,
+    pub scenario: String,
+    pub seed: u64,
+    pub dt_ms: u64,
+    /// Setpoint at the start of the run (°C)
+    pub setpoint: f64,
+    pub trip_temp: f64,
+    /// Fingerprint of the settings the run used
+    pub config_hash: String,
+}
+
+/// A trace header as a viewer reads it: the `TraceHeader` fields, each optional so hand-written
+/// headers and those of older traces load too (only the schema is required), and the plant of the
+/// run's configuration.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct HeaderView {
+    pub schema: u32,
+    #[serde(default)]
+    pub crate_version: Option<String>,
+    #[serde(default)]
+    pub scenario: Option<String>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub dt_ms: Option<u64>,
+    #[serde(default)]
+    pub setpoint: Option<f64>,
+    #[serde(default)]
+    pub trip_temp: Option<f64>,
+    #[serde(default)]
+    pub config_hash: Option<String>,
+    #[serde(default)]
+    pub config: Option<ConfigView>,
+}
+
+/// What a `HeaderView` reads of the run's configuration.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+pub struct ConfigView {
+    #[serde(default)]
+    pub plant: Option<PlantParams>,
+}
+
+impl HeaderView {
+    /// The plant parameters of the run, if its header records its configuration.
+    pub fn plant(&self) -> Option<PlantParams> {
+        self.config.as_ref().and_then(|c| c.plant)
+    }
+}
+
+/// The header of a JSONL or binary trace, `{"_meta": {...}}`.
+#[derive(Serialize, Deserialize)]
+pub struct MetaLine<T> {
+    #[serde(rename = "_meta")]
+    pub meta: T,
+}
+
+/// One step of a run.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TraceRow {
+    pub t_s: f64,
+    /// Active setpoint at this step; NaN (and left out) in traces from before it was recorded
+    #[serde(
+        default = "nan",
+        deserialize_with = "null_as_nan",
+        skip_serializing_if = "is_nan"
+    )]
+    pub setpoint_c: f64,
+    pub true_temp_c: f64,
+    // Sensor dropouts are NaN, which serde_json writes as null
+    #[serde(deserialize_with = "null_as_nan")]
+    pub s1_c: f64,
+    #[serde(deserialize_with = "null_as_nan")]
+    pub s2_c: f64,
+    #[serde(deserialize_with = "null_as_nan")]
+    pub s3_c: f64,
+    pub power: f64,
+    pub coolant: f64,
+    pub scram: bool,
+    pub reason: Option<RowReason>,
+    /// Only in traces of runs with `--diagnostics`
+    #[serde(flatten)]
+    pub diag: Option<DiagColumns>,
+}
+
+/// The `reason` of a trace row: the trip record in JSONL traces since schema 3, the bare reason
+/// name (`"OverTemp"`) in older JSONL traces and in CSV and Parquet.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RowReason {
+    Trip(TripRecord),
+    Name(String),
+}
+
+impl RowReason {
+    /// The reason name, `OverTemp` .. `Manual`, whichever form the trace stores.
+    pub fn name(&self) -> String {
+        match self {
+            RowReason::Trip(trip) => format!("{:?}", trip.reason()),
+            RowReason::Name(name) => name.clone(),
+        }
+    }
+}
+
+impl fmt::Display for RowReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RowReason::Trip(trip) => write!(f, "{trip}"),
+            RowReason::Name(name) => f.write_str(name),
+        }
+    }
+}
+
+/// The controller columns of `--diagnostics`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DiagColumns {
+    pub p_term: f64,
+    pub i_term: f64,
+    pub d_term: f64,
+    pub ff_term: f64,
+    pub out_presat: f64,
+    pub saturated: bool,
+    /// Compact controller status code (see `ControlStatus::code`)
+    pub ctrl_status: String,
+}
+
+impl DiagColumns {
+    pub fn new(out: &ControlOutput) -> Self {
+        let t = out.terms;
+        Self {
+            p_term: t.p,
+            i_term: t.i,
+            d_term: t.d,
+            ff_term: t.ff,
+            out_presat: t.output_presat,
+            saturated: t.saturated,
+            ctrl_status: out.status.code(),
+        }
+    }
+
+    /// CSV column names, in field order.
+    pub const CSV_COLUMNS: [&'static str; 7] = [
+        "p_term",
+        "i_term",
+        "d_term",
+        "ff_term",
+        "out_presat",
+        "saturated",
+        "ctrl_status",
+    ];
+
+    /// CSV cells, in the order of `CSV_COLUMNS`.
+    pub fn csv_cells(&self) -> Vec<String> {
+        vec![
+            csv_f64(self.p_term),
+            csv_f64(self.i_term),
+            csv_f64(self.d_term),
+            csv_f64(self.ff_term),
+            csv_f64(self.out_presat),
+            self.saturated.to_string(),
+            csv_quoted(&self.ctrl_status),
+        ]
+    }
+}
+
+impl TraceRow {
+    /// CSV column names, in field order (the diagnostics columns follow when enabled).
+    pub const CSV_COLUMNS: [&'static str; 10] = [
+        "t_s",
+        "setpoint_c",
+        "true_temp_c",
+        "s1_c",
+        "s2_c",
+        "s3_c",
+        "power",
+        "coolant",
+        "scram",
+        "reason",
+    ];
+
+    /// Name of the trip reason, if the SCRAM has latched.
+    pub fn reason_name(&self) -> Option<String> {
+        self.reason.as_ref().map(RowReason::name)
+    }
+
+    /// Column names and values in canonical text form (shortest round-trip numbers, `NaN` as an
+    /// empty cell): two rows are equal exactly when every float is bit-for-bit the same.
+    pub fn cells(&self) -> Vec<(&'static str, String)> {
+        csv_header(self.diag.is_some())
+            .into_iter()
+            .zip(self.csv_cells())
+            .collect()
+    }
+
+    /// CSV cells, in the order of `csv_header`; the reason is quoted, and only its name.
+    pub fn csv_cells(&self) -> Vec<String> {
+        let mut cells = vec![
+            csv_f64(self.t_s),
+            csv_f64(self.setpoint_c),
+            csv_f64(self.true_temp_c),
+            csv_f64(self.s1_c),
+            csv_f64(self.s2_c),
+            csv_f64(self.s3_c),
+            csv_f64(self.power),
+            csv_f64(self.coolant),
+            self.scram.to_string(),
+            self.reason_name()
+                .map(|r| csv_quoted(&r))
+                .unwrap_or_default(),
+        ];
+        if let Some(d) = &self.diag {
+            cells.extend(d.csv_cells());
+        }
+        cells
+    }
+}
+
+/// Header columns for a trace with or without diagnostics.
+pub fn csv_header(diagnostics: bool) -> Vec<&'static str> {
+    let mut cols = TraceRow::CSV_COLUMNS.to_vec();
+    if diagnostics {
+        cols.extend(DiagColumns::CSV_COLUMNS);
+    }
+    cols
+}
+
+/// Shortest round-trip representation; NaN becomes an empty cell.
+fn csv_f64(v: f64) -> String {
+    if v.is_nan() {
+        String::new()
+    } else {
+        v.to_string()
+    }
+}
+
+fn csv_quoted(s: &str) -> String {
+    format!("\"{}\"", s.replace('"', "\"\""))
+}
+
+fn null_as_nan<'de, D: Deserializer<'de>>(d: D) -> Result<f64, D::Error> {
+    Ok(Option::<f64>::deserialize(d)?.unwrap_or(f64::NAN))
+}
+
+fn nan() -> f64 {
+    f64::NAN
+}
+
+fn is_nan(v: &f64) -> bool {
+    v.is_nan()
+}
+
+/// One step as the GUI plots it, from a live run or a trace row.
+#[derive(Clone, Debug)]
+pub struct Sample {
+    pub t: f64,
+    pub true_temp: f64,
+    pub s1: f64,
+    pub s2: f64,
+    pub s3: f64,
+    pub power: f64,
+    pub coolant: f64,
+    pub scram: bool,
+    /// Active setpoint at this step (NaN for traces recorded before they carried it)
+    pub setpoint: f64,
+    /// Controller term contributions (NaN when not available, e.g. a trace without diagnostics)
+    pub p_term: f64,
+    pub i_term: f64,
+    pub d_term: f64,
+    /// Controller output was clipped at a limit
+    pub saturated: bool,
+    /// Sensor-disagreement alarm raised (live runs only; traces do not record it)
+    pub alarm: bool,
+    /// Why the plant tripped, from the trip on (rows of a trace repeating a reason may share it)
+    pub reason: Option<Arc<RowReason>>,
+}
+
+impl Sample {
+    pub fn sensors(&self) -> [f64; 3] {
+        [self.s1, self.s2, self.s3]
+    }
+}
+
+impl From<TraceRow> for Sample {
+    fn from(row: TraceRow) -> Self {
+        // Every field named, so a new column does not go unplotted unnoticed
+        let TraceRow {
+            t_s,
+            setpoint_c,
+            true_temp_c,
+            s1_c,
+            s2_c,
+            s3_c,
+            power,
+            coolant,
+            scram,
+            reason,
+            diag,
+        } = row;
+        let nan = f64::NAN;
+        let (p_term, i_term, d_term) = diag
+            .as_ref()
+            .map_or((nan, nan, nan), |d| (d.p_term, d.i_term, d.d_term));
+        Self {
+            t: t_s,
+            true_temp: true_temp_c,
+            s1: s1_c,
+            s2: s2_c,
+            s3: s3_c,
+            power,
+            coolant,
+            scram,
+            setpoint: setpoint_c,
+            p_term,
+            i_term,
+            d_term,
+            // Clipped at a limit: the status code has H or L
+            saturated: diag.is_some_and(|d| d.ctrl_status.contains(['H', 'L'])),
+            alarm: false,
+            reason: reason.map(Arc::new),
+        }
+    }
+}
@@ -1,9 +1,5 @@
 $ErrorActionPreference = "Stop"
 
-New-Item -ItemType Directory -Force out | Out-Null
-
-# Overheat demo (should SCRAM)
-cargo run -p cli -- --scenario overheat --seconds 180 --dt-ms 50 --setpoint 380 --trip-temp 420 `
-  | Set-Content -Encoding utf8 out\demo_overheat.jsonl
-
-Write-Host "Wrote out\demo_overheat.jsonl"
+# Overheat demo (should SCRAM); --out creates out\ if needed
+cargo run -p cli -- run --scenario overheat --seconds 180 --dt-ms 50 --setpoint 380 --trip-temp 420 `
+  --out out\demo_overheat.jsonl --force
@@ -3,5 +3,11 @@
 //! This crate re-exports the main building blocks so integration tests can depend on a single crate.
 
 pub use controller::*;
+pub use engine::*;
 pub use safety::*;
 pub use sim::*;
+// As a module: its `Sample` (a trace row as plotted) would clash with the engine's
+pub use trace;
+
+/// Version of this crate; each re-exported crate's own is `<crate>::VERSION`.
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,94 @@
+use reactor_safety_sim as rss;
+
+fn output(command: f64, saturated_high: bool) -> rss::ControlOutput {
+    rss::ControlOutput {
+        command,
+        status: rss::ControlStatus {
+            saturated_high,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn power_may_not_rise_while_coolant_is_saturated() {
+    let mut arb = rss::Arbitrator::new(rss::ArbitratorConfig::default());
+
+    // Coolant has headroom: the power request passes unchanged.
+    let a = arb.arbitrate(&output(0.6, false), &output(0.9, false), None);
+    assert_eq!((a.power, a.coolant), (0.6, 0.9));
+    assert!(a.clips.is_empty());
+    assert_eq!(a.to_string(), "-");
+
+    // Coolant saturates high while the power loop keeps raising: power is held.
+    let a = arb.arbitrate(&output(0.7, false), &output(1.0, true), None);
+    assert_eq!(a.power, 0.6);
+    assert_eq!(
+        a.clips,
+        vec![rss::Clip {
+            rule: rss::ArbitrationRule::NoPowerRaiseWhileCoolantSaturated,
+            actuator: rss::Actuator::Power,
+            from: 0.7,
+            to: 0.6,
+        }]
+    );
+
+    // Decreasing power is always allowed.
+    let a = arb.arbitrate(&output(0.5, false), &output(1.0, true), None);
+    assert_eq!(a.power, 0.5);
+    assert!(a.clips.is_empty());
+}
+
+#[test]
+fn rules_apply_in_priority_order_and_explain_each_clip() {
+    let mut arb = rss::Arbitrator::new(rss::ArbitratorConfig {
+        rules: vec![
+            rss::ArbitrationRule::SafetyPowerCap,
+            rss::ArbitrationRule::NoPowerRaiseWhileCoolantSaturated,
+            rss::ArbitrationRule::CoolantFloor(0.3),
+        ],
+    });
+    arb.arbitrate(&output(0.4, false), &output(0.5, false), None);
+
+    // Runback to 0.8 first, then the saturated-coolant hold to 0.4, then the coolant floor.
+    let a = arb.arbitrate(&output(0.9, false), &output(0.1, true), Some(0.8));
+    assert_eq!((a.power, a.coolant), (0.4, 0.3));
+    let rules: Vec<_> = a.clips.iter().map(|c| (c.rule, c.actuator)).collect();
+    assert_eq!(
+        rules,
+        vec![
+            (rss::ArbitrationRule::SafetyPowerCap, rss::Actuator::Power),
+            (
+                rss::ArbitrationRule::NoPowerRaiseWhileCoolantSaturated,
+                rss::Actuator::Power
+            ),
+            (
+                rss::ArbitrationRule::CoolantFloor(0.3),
+                rss::Actuator::Coolant
+            ),
+        ]
+    );
+    assert_eq!(
+        a.to_string(),
+        "safety_power_cap:power 0.900->0.800; \
+         no_power_raise_coolant_saturated:power 0.800->0.400; \
+         coolant_floor:coolant 0.100->0.300"
+    );
+}
+
+#[test]
+fn safety_cap_wins_when_listed_after_the_hold() {
+    let mut arb = rss::Arbitrator::new(rss::ArbitratorConfig {
+        rules: vec![
+            rss::ArbitrationRule::NoPowerRaiseWhileCoolantSaturated,
+            rss::ArbitrationRule::SafetyPowerCap,
+        ],
+    });
+    arb.arbitrate(&output(0.9, false), &output(0.5, false), None);
+    let a = arb.arbitrate(&output(1.0, false), &output(1.0, true), Some(0.2));
+    assert_eq!(a.power, 0.2);
+    assert_eq!(a.clips.len(), 2);
+    assert_eq!(a.clips[0].to, 0.9);
+    assert_eq!(a.clips[1].rule, rss::ArbitrationRule::SafetyPowerCap);
+}
@@ -0,0 +1,108 @@
+use reactor_safety_sim as rss;
+
+/// SensorDisagree with a trip threshold wide enough that only the alarm fires: settle at the
+/// setpoint, then sensor 2 develops a +20 °C bias. Returns the true temperature at the end and
+/// whether the alarm was active.
+fn biased_sensor_run(use_trust: bool) -> (f64, bool) {
+    let p = rss::PlantParams::default();
+    let mut x = rss::PlantState {
+        coolant: 0.6,
+        ..Default::default()
+    };
+    let s_cfg = rss::SafetyConfig {
+        max_sensor_delta_c: 30.0,
+        ..Default::default()
+    };
+    let mut s_state = rss::SafetyState::default();
+    let mut sensors = [1, 2, 3].map(rss::Sensor::new);
+    for s in &mut sensors {
+        s.noise_std = 0.15;
+    }
+    let mut pid = rss::Pid::new(rss::PidConfig::default());
+    let dt = 0.05;
+    let mut alarm = false;
+
+    for k in 0..(2400.0 / dt) as usize {
+        if k == (1200.0 / dt) as usize {
+            sensors[1].fault = rss::SensorFault::Bias { value: 20.0 };
+        }
+        let y = [0, 1, 2].map(|i| sensors[i].read_temp(x.temp_c, dt));
+        let decision = rss::evaluate(&s_cfg, &mut s_state, y);
+        assert!(!decision.scram, "{:?}", decision.reason);
+        alarm = decision.sensor_disagree_alarm;
+        if use_trust {
+            pid.set_measurement_trust(if alarm {
+                rss::Trust::Suspect
+            } else {
+                rss::Trust::Full
+            });
+        }
+        x.power = pid.update(350.0, (y[0] + y[1] + y[2]) / 3.0, dt);
+        x.step(&p, dt);
+    }
+    (x.temp_c, alarm)
+}
+
+#[test]
+fn suspect_measurement_holds_output_instead_of_tracking_biased_average() {
+    let (baseline, alarm) = biased_sensor_run(false);
+    assert!(alarm);
+    // The biased average reads 20/3 °C high, so the loop drags the plant below the setpoint.
+    assert!(350.0 - baseline > 5.0, "baseline ended at {baseline}");
+
+    let (degraded, alarm) = biased_sensor_run(true);
+    assert!(alarm);
+    assert!(
+        (degraded - 350.0).abs() < 2.0,
+        "degraded mode ended at {degraded}"
+    );
+}
+
+#[test]
+fn degraded_mode_is_flagged_and_returns_bumplessly() {
+    let mut pid = rss::Pid::new(rss::PidConfig::default());
+    let dt = 0.05;
+    let mut u = 0.0;
+    for _ in 0..100 {
+        u = pid.update(350.0, 340.0, dt);
+    }
+
+    pid.set_measurement_trust(rss::Trust::Suspect);
+    let held = pid.update(350.0, 200.0, dt);
+    assert_eq!(held, u);
+    assert!(pid.status().degraded);
+    assert_eq!(pid.status().code(), "U");
+
+    pid.set_measurement_trust(rss::Trust::Full);
+    let resumed = pid.update(350.0, 350.0, dt);
+    assert!(
+        (resumed - held).abs() < 1e-12,
+        "bump on return: {held} -> {resumed}"
+    );
+    assert!(!pid.status().degraded);
+}
+
+#[test]
+fn lost_measurement_can_fall_back_to_feedforward() {
+    let mut pid = rss::Pid::new(rss::PidConfig {
+        on_lost_measurement: rss::DegradedAction::Feedforward,
+        ..Default::default()
+    });
+    pid.set_feedforward(0.42);
+    pid.set_measurement_trust(rss::Trust::None);
+    assert_eq!(pid.update(350.0, f64::NAN, 0.05), 0.42);
+    assert!(pid.status().degraded && pid.status().input_invalid);
+}
+
+#[test]
+fn safety_raises_disagreement_alarm_below_trip_threshold() {
+    let cfg = rss::SafetyConfig::default();
+    let mut state = rss::SafetyState::default();
+    let d = rss::evaluate(&cfg, &mut state, [350.0, 357.0, 350.0]);
+    assert!(d.sensor_disagree_alarm && !d.scram);
+    assert_eq!(d.valid_sensors, 3);
+
+    let d = rss::evaluate(&cfg, &mut state, [350.0, 362.0, 350.0]);
+    assert!(d.scram);
+    assert_eq!(d.reason, Some(rss::TripReason::SensorDisagree));
+}
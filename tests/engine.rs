@@ -0,0 +1,69 @@
+use reactor_safety_sim as rss;
+
+fn simulation(spec: rss::ScenarioSpec) -> rss::Simulation {
+    let sensors = [1, 2, 3].map(|seed| {
+        let mut sensor = rss::Sensor::new(seed);
+        sensor.noise_std = 0.15;
+        sensor
+    });
+    rss::Simulation::new(
+        spec,
+        rss::PlantParams::default(),
+        sensors,
+        rss::SafetyConfig::default(),
+        rss::Pid::new(rss::PidConfig::default()),
+        rss::SetpointProfile::constant(350.0),
+        0.05,
+    )
+}
+
+#[test]
+fn reset_repeats_the_run() {
+    let mut sim = simulation(rss::ScenarioSpec::default());
+    let first: Vec<rss::Sample> = (0..200).map(|_| sim.step()).collect();
+    sim.reset();
+    assert_eq!(sim.step, 0);
+    let again: Vec<rss::Sample> = (0..200).map(|_| sim.step()).collect();
+    assert_eq!(format!("{again:?}"), format!("{first:?}"));
+}
+
+#[test]
+fn scenario_events_and_faults_apply_on_their_step() {
+    let mut spec = rss::ScenarioSpec::default();
+    spec.events.push(rss::TimedEvent {
+        t_s: 1.0,
+        action: rss::EventAction::Scram,
+    });
+    spec.events.push(rss::TimedEvent {
+        t_s: 0.5,
+        action: rss::EventAction::SetSetpoint(360.0),
+    });
+    spec.sensors.s3 = rss::FaultSchedule::always(rss::SensorFault::Stuck { value: 300.0 });
+    let mut sim = simulation(spec);
+
+    let first = sim.step();
+    assert_eq!(
+        first.events,
+        [rss::StepEvent::Fault {
+            sensor: 2,
+            fault: rss::SensorFault::Stuck { value: 300.0 },
+        }]
+    );
+
+    let samples: Vec<rss::Sample> = (1..40).map(|_| sim.step()).collect();
+    let setpoint = samples.iter().find(|s| s.setpoint_c == 360.0).unwrap();
+    assert_eq!(setpoint.t_s, 0.5);
+    let trip = samples.iter().find(|s| s.scram).unwrap();
+    assert_eq!(trip.t_s, 1.0);
+    assert_eq!(
+        trip.events,
+        [
+            rss::StepEvent::Scenario(rss::EventAction::Scram),
+            rss::StepEvent::Trip
+        ]
+    );
+    assert_eq!(
+        (trip.power, trip.control),
+        (0.0, rss::ControlOutput::default())
+    );
+}
@@ -0,0 +1,60 @@
+use reactor_safety_sim as rss;
+use rss::Controller;
+
+#[test]
+fn closed_loop_poles_match_the_riccati_solution() {
+    let p = rss::PlantParams::default();
+    let coolant = 0.5;
+    let cfg = rss::LqrConfig::default();
+    let lqr = rss::Lqr::new(&p, coolant, cfg);
+
+    // Symmetric root locus for the integral-augmented first-order plant:
+    // s² + c1·s + c0 with c0 = b·sqrt(qi/r) and c1 = sqrt(a² + b²·q/r + 2·c0).
+    let a = -p.k_cool * coolant / p.thermal_mass;
+    let b = p.k_power / p.thermal_mass;
+    let c0 = b * (cfg.qi / cfg.r).sqrt();
+    let c1 = (a * a + b * b * cfg.q / cfg.r + 2.0 * c0).sqrt();
+
+    let [(re1, im1), (re2, im2)] = lqr.closed_loop_poles();
+    assert!(re1 < 0.0 && re2 < 0.0, "poles must be stable");
+    assert!((-(re1 + re2) - c1).abs() < 1e-12, "sum of poles");
+    let prod = re1 * re2 - im1 * im2;
+    assert!((prod - c0).abs() < 1e-12, "product of poles");
+    assert_eq!(
+        im1, 0.0,
+        "default weights give a real (non-oscillatory) pair"
+    );
+}
+
+#[test]
+fn reaches_setpoint_without_overshoot() {
+    let p = rss::PlantParams::default();
+    let mut x = rss::PlantState::default();
+    let mut lqr = rss::Lqr::new(&p, x.coolant, rss::LqrConfig::default());
+    let dt = 0.05;
+    let sp = 350.0;
+
+    let mut max_temp = f64::NEG_INFINITY;
+    for _ in 0..(600.0 / dt) as usize {
+        x.power = lqr.update(sp, x.temp_c, dt);
+        x.step(&p, dt);
+        max_temp = max_temp.max(x.temp_c);
+    }
+    assert!(max_temp <= sp + 0.05, "overshoot: max {max_temp}");
+    assert!((x.temp_c - sp).abs() < 0.05, "final temp {}", x.temp_c);
+}
+
+#[test]
+fn integral_action_rejects_model_mismatch() {
+    let p = rss::PlantParams::default();
+    let mut x = rss::PlantState::default();
+    let mut lqr = rss::Lqr::new(&p, x.coolant, rss::LqrConfig::default());
+    // Coolant differs from the design point, so the feedforward alone is wrong.
+    x.coolant = 0.6;
+    let dt = 0.05;
+    for _ in 0..(3000.0 / dt) as usize {
+        x.power = lqr.update(350.0, x.temp_c, dt);
+        x.step(&p, dt);
+    }
+    assert!((x.temp_c - 350.0).abs() < 0.1, "final temp {}", x.temp_c);
+}
@@ -0,0 +1,96 @@
+use reactor_safety_sim as rss;
+use rss::metrics::{self, ControlMetrics, ResponsePoint};
+
+fn point(t: f64, temp: f64, setpoint: f64) -> ResponsePoint {
+    ResponsePoint {
+        t,
+        temp,
+        setpoint,
+        saturated: false,
+    }
+}
+
+// 300 °C stepping to 380 °C as a first-order lag with a 10 s time constant, sampled every 10 ms
+// for 100 s.
+fn first_order() -> Vec<ResponsePoint> {
+    (0..=10_000)
+        .map(|i| {
+            let t = f64::from(i) * 0.01;
+            point(t, 300.0 + 80.0 * (1.0 - (-t / 10.0).exp()), 380.0)
+        })
+        .collect()
+}
+
+// Rises 9 °C/s from 300 °C to 390 °C at t = 10 s, falls back to the 380 °C setpoint by 20 s and
+// holds until 40 s, with the output saturated for the first 5 s. One point a second.
+fn overshooting() -> Vec<ResponsePoint> {
+    (0..=40)
+        .map(|i| {
+            let t = f64::from(i);
+            let temp = if t <= 10.0 {
+                300.0 + 9.0 * t
+            } else {
+                (390.0 - (t - 10.0)).max(380.0)
+            };
+            ResponsePoint {
+                saturated: t < 5.0,
+                ..point(t, temp, 380.0)
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn first_order_step_has_no_overshoot_and_settles_in_four_time_constants() {
+    let m = ControlMetrics::of(&first_order()).unwrap();
+    assert_eq!(m.overshoot_c, 0.0);
+    assert_eq!(m.overshoot_pct, Some(0.0));
+    // Within 2 % from t = 10 s · ln 50
+    let settling = m.settling_time_s.unwrap();
+    assert!(
+        (settling - 10.0 * 50.0_f64.ln()).abs() < 0.011,
+        "{settling}"
+    );
+    // Mean of 80·e^(-t/10) over the last 10 s
+    let sse = 80.0 * ((-9.0_f64).exp() - (-10.0_f64).exp());
+    assert!(
+        (m.steady_state_error_c - sse).abs() < 1e-4,
+        "{}",
+        m.steady_state_error_c
+    );
+    // ∫ 80·e^(-t/10) dt over 100 s
+    let iae = 800.0 * (1.0 - (-10.0_f64).exp());
+    assert!((m.iae - iae).abs() < 0.01, "{}", m.iae);
+    assert_eq!(m.saturated_s, 0.0);
+}
+
+#[test]
+fn overshooting_step_reports_its_peak_and_settling() {
+    let points = overshooting();
+    assert_eq!(metrics::overshoot(&points), (10.0, Some(12.5)));
+    // The band is ±1.6 °C: 382 °C at 18 s is outside, 381 °C at 19 s inside
+    assert_eq!(metrics::settling_time(&points, 0.02), Some(19.0));
+    assert_eq!(metrics::steady_state_error(&points, 0.1), 0.0);
+    assert_eq!(metrics::time_saturated(&points), 5.0);
+    // Trapezoids over |error| = 80, 71, .., 8, 1, 10, 9, .., 0: 352 below the setpoint up to 8 s,
+    // 10 across the crossing and 50 on the way back down
+    assert_eq!(metrics::integral_absolute_error(&points), 412.0);
+}
+
+#[test]
+fn unsettled_flat_and_short_runs() {
+    // Ends outside the band
+    let points = &overshooting()[..15];
+    assert_eq!(metrics::settling_time(points, 0.02), None);
+
+    // No step: no percentage, no settling time
+    let flat: Vec<ResponsePoint> = (0..10).map(|i| point(f64::from(i), 380.0, 380.0)).collect();
+    let m = ControlMetrics::of(&flat).unwrap();
+    assert_eq!((m.overshoot_c, m.overshoot_pct), (0.0, None));
+    assert_eq!(m.settling_time_s, None);
+    assert_eq!(m.iae, 0.0);
+
+    // Points without a setpoint are left out; one usable point is not enough
+    let short = [point(0.0, 300.0, f64::NAN), point(1.0, 300.0, 380.0)];
+    assert_eq!(ControlMetrics::of(&short), None);
+}
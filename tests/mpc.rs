@@ -0,0 +1,68 @@
+use reactor_safety_sim as rss;
+use rss::Controller;
+use std::time::{Duration, Instant};
+
+const TRIP_C: f64 = 420.0;
+
+fn constrained_mpc() -> rss::Mpc {
+    let cfg = rss::MpcConfig {
+        temp_limit_c: Some(TRIP_C - 5.0),
+        max_rate_per_s: Some(0.5),
+        ..Default::default()
+    };
+    rss::Mpc::new(&rss::PlantParams::default(), 0.2, cfg)
+}
+
+#[test]
+fn mpc_is_offset_free_under_model_mismatch() {
+    let p = rss::PlantParams::default();
+    let cfg = rss::MpcConfig {
+        model_mismatch: 1.3,
+        ..Default::default()
+    };
+    let mut mpc = rss::Mpc::new(&p, 0.5, cfg);
+    let mut x = rss::PlantState::default();
+    let dt = 0.05;
+    for _ in 0..(1200.0 / dt) as usize {
+        x.power = mpc.update(350.0, x.temp_c, dt);
+        x.step(&p, dt);
+    }
+    assert!((x.temp_c - 350.0).abs() < 0.5, "final temp {}", x.temp_c);
+}
+
+#[test]
+fn mpc_respects_output_and_rate_constraints() {
+    let p = rss::PlantParams::default();
+    let cfg = rss::MpcConfig {
+        max_rate_per_s: Some(0.1),
+        ..Default::default()
+    };
+    let mut mpc = rss::Mpc::new(&p, 0.5, cfg);
+    let mut x = rss::PlantState::default();
+    let dt = 0.05;
+    let mut prev = mpc.update(400.0, x.temp_c, dt);
+    for _ in 0..2000 {
+        x.power = prev;
+        x.step(&p, dt);
+        let u = mpc.update(400.0, x.temp_c, dt);
+        assert!((0.0..=1.0).contains(&u));
+        assert!((u - prev).abs() <= 0.1 * dt + 1e-12, "step {prev} -> {u}");
+        prev = u;
+    }
+}
+
+#[test]
+fn mpc_update_stays_within_time_budget() {
+    let mut mpc = constrained_mpc();
+    let n = 2000;
+    let start = Instant::now();
+    for k in 0..n {
+        mpc.update(410.0, 300.0 + (k % 100) as f64, 0.05);
+    }
+    let per_step = start.elapsed() / n;
+    // Generous so unoptimized test builds pass; real-time at 50 ms steps needs far less.
+    assert!(
+        per_step < Duration::from_millis(1),
+        "{per_step:?} per update"
+    );
+}
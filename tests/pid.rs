@@ -0,0 +1,954 @@
+use reactor_safety_sim as rss;
+
+#[test]
+fn default_pid_config_is_valid() {
+    assert_eq!(rss::PidConfig::default().validate(), Ok(()));
+}
+
+#[test]
+fn pid_config_validate_reports_each_error() {
+    let cfg = rss::PidConfig {
+        kp: f64::NAN,
+        ..Default::default()
+    };
+    assert_eq!(
+        cfg.validate(),
+        Err(vec![rss::PidConfigError::NonFinite { field: "kp" }])
+    );
+
+    let cfg = rss::PidConfig {
+        ki: -0.1,
+        ..Default::default()
+    };
+    assert_eq!(
+        cfg.validate(),
+        Err(vec![rss::PidConfigError::NegativeGain {
+            field: "ki",
+            value: -0.1
+        }])
+    );
+
+    let cfg = rss::PidConfig {
+        out_min: 1.0,
+        out_max: 0.0,
+        ..Default::default()
+    };
+    assert_eq!(
+        cfg.validate(),
+        Err(vec![rss::PidConfigError::InvertedLimits {
+            out_min: 1.0,
+            out_max: 0.0
+        }])
+    );
+
+    let cfg = rss::PidConfig {
+        d_filter_tau_s: -1.0,
+        ..Default::default()
+    };
+    assert_eq!(
+        cfg.validate(),
+        Err(vec![rss::PidConfigError::NegativeFilterTau { value: -1.0 }])
+    );
+}
+
+#[test]
+fn pid_config_validate_collects_all_errors() {
+    let cfg = rss::PidConfig {
+        kp: -1.0,
+        kd: f64::INFINITY,
+        out_min: 0.5,
+        out_max: 0.5,
+        ..Default::default()
+    };
+    let errs = cfg.validate().unwrap_err();
+    assert_eq!(errs.len(), 3);
+}
+
+#[test]
+fn reverse_acting_inverts_output_direction() {
+    let cfg = rss::PidConfig {
+        reverse_acting: true,
+        ..Default::default()
+    };
+    assert_eq!(cfg.validate(), Ok(()));
+
+    let mut pid = rss::Pid::new(cfg);
+    // Measurement above setpoint: a reverse-acting loop must push the output up.
+    assert!(pid.update(300.0, 320.0, 0.05) > 0.0);
+}
+
+fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-12, "{a} != {b}");
+}
+
+#[test]
+fn last_terms_match_hand_computed_values() {
+    let mut pid = rss::Pid::new(rss::PidConfig {
+        kp: 0.1,
+        ki: 0.05,
+        kd: 0.2,
+        ..Default::default()
+    });
+    let dt = 0.5;
+
+    // e = 2, integral = 1.0, no derivative history yet
+    let out = pid.update(10.0, 8.0, dt);
+    let t = pid.last_terms();
+    assert_close(t.p, 0.2);
+    assert_close(t.i, 0.05);
+    assert_close(t.d, 0.0);
+    assert_close(t.ff, 0.0);
+    assert_close(t.output_presat, 0.25);
+    assert!(!t.saturated);
+    assert_close(out, 0.25);
+
+    // e = 1, integral = 1.5, derivative = (1 - 2) / 0.5 = -2 -> sum is clipped at out_min
+    let out = pid.update(10.0, 9.0, dt);
+    let t = pid.last_terms();
+    assert_close(t.p, 0.1);
+    assert_close(t.i, 0.075);
+    assert_close(t.d, -0.4);
+    assert_close(t.output_presat, -0.225);
+    assert!(t.saturated);
+    assert_close(out, 0.0);
+
+    // e = 10, integral = 6.5, derivative = (10 - 1) / 0.5 = 18 -> clipped at out_max
+    pid.set_feedforward(0.1);
+    let out = pid.update(10.0, 0.0, dt);
+    let t = pid.last_terms();
+    assert_close(t.p, 1.0);
+    assert_close(t.i, 0.325);
+    assert_close(t.d, 3.6);
+    assert_close(t.ff, 0.1);
+    assert_close(t.output_presat, 5.025);
+    assert!(t.saturated);
+    assert_close(out, 1.0);
+}
+
+#[test]
+fn velocity_form_matches_positional_on_unsaturated_trajectory() {
+    let cfg = rss::PidConfig {
+        kp: 0.01,
+        ki: 0.002,
+        kd: 0.05,
+        out_min: -10.0,
+        out_max: 10.0,
+        ..Default::default()
+    };
+    let mut positional = rss::Pid::new(cfg);
+    let mut velocity = rss::Pid::new(rss::PidConfig {
+        form: rss::PidForm::Velocity,
+        ..cfg
+    });
+
+    let dt = 0.05;
+    for k in 0..400 {
+        let t = k as f64 * dt;
+        let meas = 300.0 + 20.0 * (1.0 - (-t / 5.0).exp());
+        let a = positional.update(330.0, meas, dt);
+        let b = velocity.update(330.0, meas, dt);
+        assert!((a - b).abs() < 1e-9, "step {k}: {a} vs {b}");
+    }
+}
+
+#[test]
+fn velocity_form_has_no_bump_on_gain_change() {
+    let cfg = rss::PidConfig {
+        form: rss::PidForm::Velocity,
+        ..Default::default()
+    };
+    let mut pid = rss::Pid::new(cfg);
+    let dt = 0.05;
+    let mut out = 0.0;
+    for _ in 0..100 {
+        out = pid.update(350.0, 340.0, dt);
+    }
+
+    pid.set_config(rss::PidConfig {
+        kp: cfg.kp * 3.0,
+        ki: cfg.ki * 3.0,
+        ..cfg
+    });
+    let after = pid.update(350.0, 340.0, dt);
+    // Only the new integral increment is added: no jump from rescaling accumulated state.
+    assert!((after - out).abs() <= 3.0 * cfg.ki * 10.0 * dt + 1e-12);
+}
+
+#[test]
+fn positional_form_gain_change_is_bumpless() {
+    let cfg = rss::PidConfig::default();
+    let dt = 0.05;
+    let run = |pid: &mut rss::Pid| {
+        let mut out = 0.0;
+        for _ in 0..100 {
+            out = pid.update(350.0, 340.0, dt);
+        }
+        out
+    };
+    let tighter = rss::PidConfig {
+        kp: cfg.kp * 3.0,
+        ki: cfg.ki * 2.0,
+        ..cfg
+    };
+
+    // Plain `set_config` jumps by the new proportional and rescaled integral contributions
+    let mut pid = rss::Pid::new(cfg);
+    let out = run(&mut pid);
+    assert!(out > 0.0 && out < 1.0, "{out}");
+    pid.set_config(tighter);
+    assert!(pid.update(350.0, 340.0, dt) - out > 0.3);
+
+    let mut pid = rss::Pid::new(cfg);
+    let out = run(&mut pid);
+    pid.set_config_bumpless(tighter);
+    let after = pid.update(350.0, 340.0, dt);
+    // Only the new integral increment is added
+    assert_close(after, out + tighter.ki * 10.0 * dt);
+    assert_eq!(pid.config().kp, tighter.kp);
+}
+
+#[test]
+fn bumpless_gain_change_before_the_first_update_only_replaces_the_config() {
+    let mut pid = rss::Pid::new(rss::PidConfig::default());
+    let cfg = rss::PidConfig {
+        kp: 0.05,
+        ..Default::default()
+    };
+    pid.set_config_bumpless(cfg);
+    assert_eq!(pid.integral(), 0.0);
+    assert_close(
+        pid.update(350.0, 340.0, 0.05),
+        0.05 * 10.0 + cfg.ki * 10.0 * 0.05,
+    );
+}
+
+#[test]
+fn velocity_form_reset_honors_keep_output_flag() {
+    let dt = 0.05;
+    for keep in [false, true] {
+        let mut pid = rss::Pid::new(rss::PidConfig {
+            form: rss::PidForm::Velocity,
+            keep_output_on_reset: keep,
+            ki: 0.0,
+            ..Default::default()
+        });
+        let before = pid.update(350.0, 340.0, dt);
+        assert!((before - 0.2).abs() < 1e-12);
+
+        pid.reset();
+        let after = pid.update(350.0, 345.0, dt);
+        if keep {
+            // Continues from the kept output; the first increment after reset is zero.
+            assert_eq!(after, before);
+        } else {
+            // Starts from scratch: pure proportional action on the new error.
+            assert!((after - 0.1).abs() < 1e-12);
+        }
+    }
+}
+
+/// Closed loop against the plant model: settle at 300 °C, step the setpoint to 400 °C, then drop
+/// the coolant as a load disturbance. Returns (overshoot after the step, final error).
+fn setpoint_step_with_disturbance(cfg: rss::PidConfig) -> (f64, f64) {
+    let p = rss::PlantParams::default();
+    let mut x = rss::PlantState {
+        coolant: 0.6,
+        ..Default::default()
+    };
+    let mut pid = rss::Pid::new(cfg);
+    let dt = 0.05;
+
+    let mut overshoot = f64::NEG_INFINITY;
+    for k in 0..90_000 {
+        let t = k as f64 * dt;
+        let sp = if t < 1500.0 { 300.0 } else { 400.0 };
+        if t >= 3000.0 {
+            x.coolant = 0.55;
+        }
+        x.power = pid.update(sp, x.temp_c, dt);
+        x.step(&p, dt);
+        if (1500.0..3000.0).contains(&t) {
+            overshoot = overshoot.max(x.temp_c - sp);
+        }
+    }
+    (overshoot, 400.0 - x.temp_c)
+}
+
+#[test]
+fn integral_separation_reduces_overshoot_and_still_rejects_disturbances() {
+    let cfg = rss::PidConfig {
+        kp: 0.01,
+        ki: 0.001,
+        ..Default::default()
+    };
+    let (overshoot_plain, err_plain) = setpoint_step_with_disturbance(cfg);
+    let (overshoot_sep, err_sep) = setpoint_step_with_disturbance(rss::PidConfig {
+        integral_separation_band: Some(20.0),
+        ..cfg
+    });
+
+    assert!(
+        overshoot_plain > 10.0,
+        "baseline overshoot {overshoot_plain}"
+    );
+    assert!(
+        overshoot_sep < 0.5 * overshoot_plain,
+        "separated {overshoot_sep} vs plain {overshoot_plain}"
+    );
+    assert!(err_plain.abs() < 0.05, "plain final error {err_plain}");
+    assert!(err_sep.abs() < 0.05, "separated final error {err_sep}");
+}
+
+#[test]
+fn integral_separation_band_is_validated() {
+    let cfg = rss::PidConfig {
+        integral_separation_band: Some(0.0),
+        ..Default::default()
+    };
+    assert_eq!(
+        cfg.validate(),
+        Err(vec![rss::PidConfigError::NonPositive {
+            field: "integral_separation_band",
+            value: 0.0
+        }])
+    );
+}
+
+#[test]
+fn reset_to_preloads_output_for_zero_error() {
+    for form in [rss::PidForm::Positional, rss::PidForm::Velocity] {
+        let mut pid = rss::Pid::new(rss::PidConfig {
+            form,
+            kd: 0.1,
+            ..Default::default()
+        });
+        for _ in 0..50 {
+            pid.update(350.0, 300.0, 0.05);
+        }
+        pid.reset_to(0.3);
+        let out = pid.update(350.0, 350.0, 0.05);
+        assert!((out - 0.3).abs() < 1e-12, "{form:?}: {out}");
+    }
+}
+
+#[test]
+fn restart_after_trip_is_continuous_with_preload() {
+    let p = rss::PlantParams::default();
+    let mut x = rss::PlantState {
+        coolant: 0.6,
+        ..Default::default()
+    };
+    let s_cfg = rss::SafetyConfig {
+        trip_temp_c: 380.0,
+        ..Default::default()
+    };
+    let mut s_state = rss::SafetyState::default();
+    let mut pid = rss::Pid::new(rss::PidConfig::default());
+    let dt = 0.05;
+
+    // Drive towards a setpoint above the trip level until the SCRAM latches.
+    let mut stale = None;
+    for _ in 0..20_000 {
+        let was_scram = s_state.scram;
+        rss::evaluate(&s_cfg, &mut s_state, [x.temp_c; 3]);
+        if s_state.scram && !was_scram {
+            stale = Some(pid.clone());
+            pid.reset_to(0.0);
+        }
+        if s_state.scram {
+            x.power = 0.0;
+            if x.temp_c <= 370.0 {
+                break;
+            }
+        } else {
+            x.power = pid.update(400.0, x.temp_c, dt);
+        }
+        x.step(&p, dt);
+    }
+    let mut stale = stale.expect("expected the trip to latch");
+
+    // Operator resets the trip and restarts at a setpoint close to the current temperature.
+    s_state = rss::SafetyState::default();
+    rss::evaluate(&s_cfg, &mut s_state, [x.temp_c; 3]);
+    assert!(!s_state.scram);
+    let restart = pid.update(370.0, x.temp_c, dt);
+    let bump = stale.update(370.0, x.temp_c, dt);
+    let cfg = rss::PidConfig::default();
+    let error = 370.0 - x.temp_c;
+    assert!(restart.abs() <= cfg.kp * error.abs() + cfg.ki * error.abs() * dt + 1e-9);
+    assert!(bump > 0.5, "stale controller restarted at {bump}");
+}
+
+#[test]
+fn tracking_keeps_integral_bounded_while_output_is_held() {
+    let cfg = rss::PidConfig {
+        tracking_tau_s: 0.1,
+        ..Default::default()
+    };
+    let mut pid = rss::Pid::new(cfg);
+    let dt = 0.05;
+
+    // The error demands full output, but the actuator is held at 0.2 for 10 s.
+    let mut integral_at_5s = 0.0;
+    for k in 0..200 {
+        pid.update(400.0, 350.0, dt);
+        pid.track(0.2, dt);
+        if k == 99 {
+            integral_at_5s = pid.integral();
+        }
+    }
+
+    // The integrator settles where P + I equals the applied output (plus the usual back-calculation
+    // offset ki * error * tau) instead of winding up.
+    let settled = (0.2 + cfg.ki * 50.0 * cfg.tracking_tau_s - cfg.kp * 50.0) / cfg.ki;
+    assert!((pid.integral() - settled).abs() < 5.0, "{}", pid.integral());
+    assert!((pid.integral() - integral_at_5s).abs() < 1.0);
+
+    let released = pid.update(400.0, 350.0, dt);
+    assert!(
+        (released - 0.2).abs() < 0.05,
+        "first free output {released}"
+    );
+}
+
+#[test]
+fn status_flags_follow_saturation_regimes() {
+    let mut pid = rss::Pid::new(rss::PidConfig::default());
+    let dt = 0.1;
+
+    pid.update(350.0, 350.0, dt);
+    assert_eq!(pid.status(), rss::ControlStatus::default());
+    assert_eq!(pid.status().code(), "-");
+
+    pid.update(400.0, 300.0, dt);
+    assert!(pid.status().saturated_high && !pid.status().saturated_low);
+    assert_eq!(pid.status().code(), "H");
+
+    pid.update(300.0, 400.0, dt);
+    assert!(pid.status().saturated_low && !pid.status().saturated_high);
+
+    // Transition back into the linear range
+    pid.reset();
+    pid.update(350.0, 345.0, dt);
+    assert_eq!(pid.status().code(), "-");
+}
+
+#[test]
+fn status_flags_report_rate_limiting_until_the_target_is_reached() {
+    let mut pid = rss::Pid::new(rss::PidConfig {
+        max_rate_per_s: Some(0.5),
+        ..Default::default()
+    });
+    let dt = 0.1;
+    pid.reset_to(0.0);
+
+    let mut outputs = Vec::new();
+    let mut rate_limited = Vec::new();
+    for _ in 0..25 {
+        outputs.push(pid.update(400.0, 350.0, dt));
+        rate_limited.push(pid.status().rate_limited);
+    }
+
+    assert!((outputs[0] - 0.05).abs() < 1e-12);
+    assert_eq!(pid.status().code(), "H");
+    assert!(rate_limited[0] && pid.status().saturated_high);
+    // Ramps at 0.05 per step and reaches the saturated output on the 20th step.
+    assert!(rate_limited[18]);
+    assert!((outputs[19] - 1.0).abs() < 1e-9);
+    assert!(!rate_limited[19]);
+}
+
+#[test]
+fn status_flags_report_manual_and_tracking() {
+    let mut pid = rss::Pid::new(rss::PidConfig::default());
+    let dt = 0.1;
+
+    pid.set_manual(Some(0.4));
+    let out = pid.update(400.0, 300.0, dt);
+    assert_eq!(out, 0.4);
+    let st = pid.status();
+    assert!(st.in_manual && st.tracking && st.saturated_high);
+    assert_eq!(st.code(), "HMT");
+
+    pid.set_manual(None);
+    pid.update(350.0, 349.0, dt);
+    assert!(!pid.status().in_manual);
+
+    pid.track(0.1, dt);
+    assert!(pid.status().tracking);
+    pid.update(350.0, 349.0, dt);
+    assert!(!pid.status().tracking);
+}
+
+#[test]
+fn non_finite_inputs_hold_the_previous_output() {
+    let cfg = rss::PidConfig {
+        kd: 0.05,
+        ..Default::default()
+    };
+    let mut pid = rss::Pid::new(cfg);
+    let mut reference = rss::Pid::new(cfg);
+    let dt = 0.05;
+
+    // Before any valid update there is no previous output: out_min is returned.
+    assert_eq!(pid.update(350.0, f64::NAN, dt), cfg.out_min);
+    assert!(pid.last_input_invalid());
+
+    let mut prev_out = cfg.out_min;
+    for (k, m) in [340.0, 342.0, 343.5, 344.0, 346.0, 347.0]
+        .iter()
+        .enumerate()
+    {
+        if k == 3 {
+            // Bad samples mid-sequence: state untouched, previous output held.
+            let integral = pid.integral();
+            assert_eq!(pid.update(350.0, f64::NAN, dt), prev_out);
+            assert!(pid.last_input_invalid());
+            assert_eq!(pid.status().code(), "I");
+            assert_eq!(pid.update(f64::INFINITY, 345.0, dt), prev_out);
+            assert_eq!(pid.integral(), integral);
+        }
+        // Valid updates behave as if the bad samples never happened.
+        prev_out = pid.update(350.0, *m, dt);
+        assert_eq!(prev_out, reference.update(350.0, *m, dt), "step {k}");
+        assert!(!pid.last_input_invalid());
+    }
+}
+
+#[test]
+fn irregular_dt_integrates_exactly() {
+    let mut pid = rss::Pid::new(rss::PidConfig {
+        ki: 1e-6,
+        ..Default::default()
+    });
+    let mut total = 0.0;
+    for dt in [0.01, 0.05, 0.2, 0.05, 0.01, 0.2, 0.2] {
+        pid.update(310.0, 300.0, dt);
+        total += dt;
+    }
+    assert!(
+        (pid.integral() - 10.0 * total).abs() < 1e-12,
+        "{}",
+        pid.integral()
+    );
+}
+
+#[test]
+fn non_positive_dt_leaves_state_untouched() {
+    let mut pid = rss::Pid::new(rss::PidConfig {
+        kd: 0.1,
+        d_filter_tau_s: 0.2,
+        ..Default::default()
+    });
+    let out = pid.update(350.0, 340.0, 0.05);
+    let integral = pid.integral();
+    let terms = pid.last_terms();
+
+    for dt in [0.0, -1.0, f64::NAN] {
+        assert_eq!(pid.update(350.0, 300.0, dt), out);
+        assert!(pid.status().dt_invalid);
+        assert_eq!(pid.status().code(), "D");
+        assert_eq!(pid.integral(), integral);
+        assert_eq!(pid.last_terms(), terms);
+    }
+
+    // The derivative history was not touched either: a valid step picks up where it left off.
+    let mut reference = rss::Pid::new(*pid.config());
+    reference.update(350.0, 340.0, 0.05);
+    assert_eq!(
+        pid.update(350.0, 339.0, 0.05),
+        reference.update(350.0, 339.0, 0.05)
+    );
+}
+
+#[test]
+fn filtered_derivative_degrades_gracefully_for_large_dt() {
+    let mut pid = rss::Pid::new(rss::PidConfig {
+        kp: 0.0,
+        ki: 0.0,
+        kd: 1.0,
+        d_filter_tau_s: 0.01,
+        out_min: -100.0,
+        out_max: 100.0,
+        ..Default::default()
+    });
+    pid.update(0.0, 0.0, 10.0);
+    // dt is 1000x the filter time constant: the filter has fully settled on the raw derivative.
+    let out = pid.update(0.0, -20.0, 10.0);
+    assert!((out - 2.0).abs() < 1e-12, "{out}");
+}
+
+#[test]
+fn suggest_gains_matches_hand_derived_values() {
+    // Default plant at coolant 0.5: tau = 100 / (1.5·0.5) = 133.3 s, b = 600/100 = 6.
+    // 60 s settling: kp = 4 / (6·60) = 1/90, ki = kp / tau = 1/12000.
+    let p = rss::PlantParams::default();
+    let cfg = rss::tuning::suggest_gains(&p, 0.5, 60.0);
+    assert_close(cfg.kp, 1.0 / 90.0);
+    assert_close(cfg.ki, 1.0 / 12_000.0);
+    assert_eq!(cfg.kd, 0.0);
+    assert!(cfg.validate().is_ok());
+
+    // Overheat's coolant 0.2: tau = 100 / (1.5·0.2) = 333.3 s, so ki = kp / tau = 1/30000.
+    let cfg = rss::tuning::suggest_gains(&p, 0.2, 60.0);
+    assert_close(cfg.kp, 1.0 / 90.0);
+    assert_close(cfg.ki, 1.0 / 30_000.0);
+}
+
+/// 2 % settling time of a 300 → 320 °C setpoint step with suggested gains, starting at equilibrium.
+fn settling_time_with_suggested_gains(settling_s: f64) -> f64 {
+    let p = rss::PlantParams::default();
+    let mut x = rss::PlantState::default();
+    let mut pid = rss::Pid::new(rss::tuning::suggest_gains(&p, x.coolant, settling_s));
+    let u_eq = p.k_cool * x.coolant * (x.temp_c - p.ambient_c) / p.k_power;
+    pid.reset_to(u_eq);
+
+    let dt = 0.05;
+    let (start, sp) = (x.temp_c, 320.0);
+    let band = 0.02 * (sp - start);
+    let mut last_outside = 0.0;
+    for k in 0..(10.0 * settling_s / dt) as usize {
+        x.power = pid.update(sp, x.temp_c, dt);
+        x.step(&p, dt);
+        if (x.temp_c - sp).abs() > band {
+            last_outside = (k + 1) as f64 * dt;
+        }
+    }
+    last_outside
+}
+
+#[test]
+fn suggested_gains_achieve_requested_settling_time() {
+    for target in [30.0, 90.0] {
+        let achieved = settling_time_with_suggested_gains(target);
+        assert!(
+            (achieved - target).abs() <= 0.3 * target,
+            "target {target} s, achieved {achieved} s"
+        );
+    }
+}
+
+/// Closed loop with a quantized actuator under a constant load. Returns the (output, temperature)
+/// trace over the last 500 s of a 3000 s run.
+fn quantized_closed_loop(cfg: rss::PidConfig) -> Vec<(f64, f64)> {
+    let p = rss::PlantParams::default();
+    let mut x = rss::PlantState {
+        coolant: 0.6,
+        ..Default::default()
+    };
+    let mut pid = rss::Pid::new(cfg);
+    let dt = 0.05;
+    let n = (3000.0 / dt) as usize;
+    let mut tail = Vec::new();
+    for k in 0..n {
+        x.power = pid.update(350.0, x.temp_c, dt);
+        x.step(&p, dt);
+        if k >= n - (500.0 / dt) as usize {
+            tail.push((x.power, x.temp_c));
+        }
+    }
+    tail
+}
+
+fn span(values: impl Iterator<Item = f64> + Clone) -> f64 {
+    let max = values.clone().fold(f64::NEG_INFINITY, f64::max);
+    let min = values.fold(f64::INFINITY, f64::min);
+    max - min
+}
+
+#[test]
+fn quantized_output_settles_on_a_single_level() {
+    let tail = quantized_closed_loop(rss::PidConfig {
+        output_resolution: Some(0.05),
+        // Tolerated error band 0.05 / (2·ki·tau) = 20 °C: wider than half a level, narrower than
+        // a whole one.
+        tracking_tau_s: 0.25,
+        ..Default::default()
+    });
+    let (u_end, t_end) = *tail.last().unwrap();
+    assert!(tail.iter().all(|&(u, _)| u == u_end), "output chatters");
+    assert!(((u_end / 0.05).round() * 0.05 - u_end).abs() < 1e-12);
+    // One 0.05 level moves the equilibrium by 0.05·k_power/(k_cool·coolant) = 33 °C, so the
+    // best constant level is within half of that.
+    assert!((t_end - 350.0).abs() < 17.0, "final temp {t_end}");
+}
+
+#[test]
+fn quantization_limit_cycle_amplitude_is_bounded() {
+    let cfg = rss::PidConfig {
+        output_resolution: Some(0.05),
+        ..Default::default()
+    };
+    assert!(cfg.validate().is_ok());
+    let tail = quantized_closed_loop(cfg);
+    let temp_amplitude = span(tail.iter().map(|&(_, t)| t));
+    let output_amplitude = span(tail.iter().map(|&(u, _)| u));
+    println!("quantization limit cycle: {temp_amplitude:.3} °C, {output_amplitude:.3} output");
+    assert!(
+        output_amplitude <= 0.05 + 1e-12,
+        "output spans {output_amplitude}"
+    );
+    assert!(temp_amplitude < 33.0, "temperature spans {temp_amplitude}");
+}
+
+#[test]
+fn output_resolution_is_validated() {
+    let cfg = rss::PidConfig {
+        output_resolution: Some(0.0),
+        ..Default::default()
+    };
+    assert_eq!(
+        cfg.validate(),
+        Err(vec![rss::PidConfigError::NonPositive {
+            field: "output_resolution",
+            value: 0.0
+        }])
+    );
+}
+
+fn asymmetric_rate_cfg() -> rss::PidConfig {
+    rss::PidConfig {
+        kp: 1.0,
+        ki: 0.0,
+        max_rate_per_s: Some(1.0),
+        max_rate_up_per_s: Some(0.1),
+        max_rate_down_per_s: None,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn setpoint_increase_ramps_at_the_up_rate() {
+    let mut pid = rss::Pid::new(asymmetric_rate_cfg());
+    let dt = 0.1;
+    let mut prev = pid.update(300.0, 300.0, dt);
+    assert_eq!(prev, 0.0);
+    for _ in 0..50 {
+        let u = pid.update(400.0, 300.0, dt);
+        assert_close(u - prev, 0.1 * dt);
+        prev = u;
+    }
+    assert!(pid.status().rate_limited);
+}
+
+#[test]
+fn setpoint_decrease_ramps_at_the_down_rate() {
+    let cfg = asymmetric_rate_cfg();
+    let mut pid = rss::Pid::new(cfg);
+    let dt = 0.1;
+    pid.reset_to(1.0);
+    let mut prev = 1.0;
+    for _ in 0..5 {
+        let u = pid.update(200.0, 300.0, dt);
+        // Falls back to the symmetric max_rate_per_s.
+        assert_close(prev - u, 1.0 * dt);
+        prev = u;
+    }
+}
+
+#[test]
+fn external_cut_bypasses_down_rate_and_resumes_without_windup() {
+    let p = rss::PlantParams::default();
+    let mut x = rss::PlantState {
+        coolant: 0.6,
+        ..Default::default()
+    };
+    let cfg = rss::PidConfig {
+        max_rate_up_per_s: Some(0.02),
+        max_rate_down_per_s: Some(0.02),
+        tracking_tau_s: 0.5,
+        ..Default::default()
+    };
+    let mut pid = rss::Pid::new(cfg);
+    let dt = 0.05;
+    let sp = 350.0;
+
+    // Settle, then cut power externally for 100 s while the controller keeps running.
+    for _ in 0..(2000.0 / dt) as usize {
+        x.power = pid.update(sp, x.temp_c, dt);
+        x.step(&p, dt);
+    }
+    let settled = x.power;
+    for _ in 0..(100.0 / dt) as usize {
+        pid.update(sp, x.temp_c, dt);
+        x.power = 0.0;
+        pid.track(0.0, dt);
+        x.step(&p, dt);
+    }
+    assert!(x.temp_c < sp - 20.0);
+
+    // Auto resumption: the output rises from zero at the up rate, and the integral did not wind
+    // up during the cut (it would otherwise drive a large overshoot).
+    let first = pid.update(sp, x.temp_c, dt);
+    assert_close(first, 0.02 * dt);
+    assert!(pid.integral() * pid.config().ki < settled + 0.5);
+    x.power = first;
+    let mut peak = f64::NEG_INFINITY;
+    for _ in 0..(1000.0 / dt) as usize {
+        x.step(&p, dt);
+        peak = peak.max(x.temp_c);
+        x.power = pid.update(sp, x.temp_c, dt);
+    }
+    assert!(peak < sp + 10.0, "overshoot after resumption: peak {peak}");
+}
+
+#[test]
+fn setpoint_change_resets_integral_only_above_threshold() {
+    let mut pid = rss::Pid::new(rss::PidConfig {
+        kp: 0.001,
+        ki: 0.001,
+        reset_integral_on_setpoint_change: Some(10.0),
+        ..Default::default()
+    });
+    pid.update(350.0, 340.0, 1.0);
+    pid.update(350.0, 340.0, 1.0);
+    assert_close(pid.integral(), 20.0);
+
+    pid.update(355.0, 340.0, 1.0);
+    assert_close(pid.integral(), 35.0);
+
+    // 50 °C move: the integral restarts from zero.
+    pid.update(405.0, 340.0, 1.0);
+    assert_close(pid.integral(), 65.0);
+}
+
+/// Settle at 300 °C, then step the setpoint by +50 °C. Returns the overshoot after the step.
+fn overshoot_after_large_step(cfg: rss::PidConfig) -> f64 {
+    let p = rss::PlantParams::default();
+    let mut x = rss::PlantState {
+        coolant: 0.6,
+        ..Default::default()
+    };
+    let mut pid = rss::Pid::new(cfg);
+    let dt = 0.05;
+    let mut overshoot = f64::NEG_INFINITY;
+    for k in 0..(3000.0 / dt) as usize {
+        let t = k as f64 * dt;
+        let sp = if t < 1500.0 { 300.0 } else { 350.0 };
+        x.power = pid.update(sp, x.temp_c, dt);
+        x.step(&p, dt);
+        if t >= 1500.0 {
+            overshoot = overshoot.max(x.temp_c - sp);
+        }
+    }
+    overshoot
+}
+
+#[test]
+fn large_setpoint_change_resets_integral_and_lowers_overshoot() {
+    let plain = overshoot_after_large_step(rss::PidConfig::default());
+    let reset = overshoot_after_large_step(rss::PidConfig {
+        reset_integral_on_setpoint_change: Some(10.0),
+        ..Default::default()
+    });
+    assert!(
+        reset < plain,
+        "overshoot {reset} with reset vs {plain} without"
+    );
+}
+
+#[test]
+fn setpoint_ramp_does_not_trigger_integral_reset() {
+    let run = |cfg: rss::PidConfig| {
+        let p = rss::PlantParams::default();
+        let mut x = rss::PlantState::default();
+        let profile =
+            rss::SetpointProfile::new(vec![(0.0, 300.0), (30.0, 300.0), (90.0, 380.0)]).unwrap();
+        let mut pid = rss::Pid::new(cfg);
+        let dt = 0.05;
+        (0..(200.0 / dt) as usize)
+            .map(|k| {
+                x.power = pid.update(profile.at(k as f64 * dt), x.temp_c, dt);
+                x.step(&p, dt);
+                x.power
+            })
+            .collect::<Vec<_>>()
+    };
+    let plain = run(rss::PidConfig::default());
+    let with_reset = run(rss::PidConfig {
+        reset_integral_on_setpoint_change: Some(10.0),
+        ..Default::default()
+    });
+    assert_eq!(plain, with_reset);
+}
+
+#[test]
+fn update_wrapper_matches_step_on_golden_sequence() {
+    let cfg = rss::PidConfig {
+        kd: 0.05,
+        d_filter_tau_s: 0.2,
+        ..Default::default()
+    };
+    let mut by_update = rss::Pid::new(cfg);
+    let mut by_step = rss::Pid::new(cfg);
+    let golden = [
+        (350.0, 300.0, 0.05),
+        (350.0, 310.0, 0.05),
+        (350.0, f64::NAN, 0.05),
+        (350.0, 330.0, 0.0),
+        (360.0, 345.0, 0.2),
+        (360.0, 370.0, 0.05),
+        (340.0, 355.0, 0.1),
+    ];
+    for (sp, meas, dt) in golden {
+        let u = by_update.update(sp, meas, dt);
+        let out = by_step.step(rss::ControlInput {
+            setpoint: sp,
+            measurement: meas,
+            feedforward: 0.0,
+            dt_s: dt,
+            applied_prev: None,
+        });
+        assert_eq!(u.to_bits(), out.command.to_bits());
+        assert_eq!(by_update.last_terms(), out.terms);
+        assert_eq!(by_update.status(), out.status);
+    }
+}
+
+#[test]
+fn step_exercises_every_input_and_output_field() {
+    let mut pid = rss::Pid::new(rss::PidConfig {
+        kp: 0.1,
+        ki: 0.01,
+        kd: 0.5,
+        ..Default::default()
+    });
+    let first = pid.step(rss::ControlInput {
+        setpoint: 350.0,
+        measurement: 345.0,
+        feedforward: 0.2,
+        dt_s: 1.0,
+        applied_prev: None,
+    });
+    // p = 0.1·5, i = 0.01·5, d = 0 on the first step, ff = 0.2
+    assert_close(first.terms.p, 0.5);
+    assert_close(first.terms.i, 0.05);
+    assert_eq!(first.terms.d, 0.0);
+    assert_eq!(first.terms.ff, 0.2);
+    assert_close(first.terms.output_presat, 0.75);
+    assert!(!first.terms.saturated);
+    assert_close(first.command, 0.75);
+    assert_eq!(first.status, rss::ControlStatus::default());
+
+    // The plant only got 0.5: the next step tracks it and reports it.
+    let second = pid.step(rss::ControlInput {
+        setpoint: 350.0,
+        measurement: 349.0,
+        feedforward: 0.2,
+        dt_s: 1.0,
+        applied_prev: Some(0.5),
+    });
+    assert!(second.status.tracking);
+    assert_close(second.terms.d, 0.5 * (1.0 - 5.0));
+    assert!(second.command < first.command);
+
+    // Applying exactly the previous command is not tracking.
+    let third = pid.step(rss::ControlInput {
+        setpoint: 350.0,
+        measurement: 349.0,
+        feedforward: 0.2,
+        dt_s: 1.0,
+        applied_prev: Some(second.command),
+    });
+    assert!(!third.status.tracking);
+}
@@ -0,0 +1,32 @@
+use reactor_safety_sim as rss;
+
+#[test]
+fn default_plant_params_are_valid() {
+    assert_eq!(rss::PlantParams::default().validate(), Ok(()));
+}
+
+#[test]
+fn validate_reports_every_problem() {
+    let p = rss::PlantParams {
+        ambient_c: f64::NAN,
+        thermal_mass: 0.0,
+        k_cool: -1.5,
+        ..Default::default()
+    };
+    let errs = p.validate().unwrap_err();
+    assert_eq!(
+        errs,
+        [
+            rss::PlantParamsError::NonFinite { field: "ambient_c" },
+            rss::PlantParamsError::NonPositive {
+                field: "thermal_mass",
+                value: 0.0
+            },
+            rss::PlantParamsError::Negative {
+                field: "k_cool",
+                value: -1.5
+            },
+        ]
+    );
+    assert_eq!(errs[1].to_string(), "plant.thermal_mass = 0 must be > 0");
+}
@@ -0,0 +1,149 @@
+use reactor_safety_sim as rss;
+
+#[test]
+fn default_safety_config_is_valid() {
+    assert_eq!(rss::SafetyConfig::default().validate(), Ok(()));
+}
+
+#[test]
+fn validate_reports_every_problem() {
+    let cfg = rss::SafetyConfig {
+        trip_temp_c: 2500.0,
+        max_sensor_delta_c: 4.0,
+        alarm_sensor_delta_c: f64::NAN,
+        ..Default::default()
+    };
+    let errs = cfg.validate().unwrap_err();
+    assert_eq!(
+        errs,
+        [
+            rss::SafetyConfigError::NonFinite {
+                field: "alarm_sensor_delta_c"
+            },
+            rss::SafetyConfigError::TripOutsideValidRange {
+                trip_temp_c: 2500.0,
+                min: 0.0,
+                max: 2000.0
+            },
+        ]
+    );
+    assert!(errs[1].to_string().contains("can never fire"));
+}
+
+#[test]
+fn alarm_band_must_sit_inside_the_trip_band() {
+    let cfg = rss::SafetyConfig {
+        alarm_sensor_delta_c: 12.0,
+        valid_range_c: (100.0, 50.0),
+        ..Default::default()
+    };
+    assert_eq!(
+        cfg.validate().unwrap_err(),
+        [
+            rss::SafetyConfigError::InvertedRange {
+                min: 100.0,
+                max: 50.0
+            },
+            rss::SafetyConfigError::AlarmAboveTrip {
+                alarm: 12.0,
+                trip: 10.0
+            },
+        ]
+    );
+}
+
+#[test]
+fn trip_record_names_the_sensors_behind_the_trip() {
+    let cfg = rss::SafetyConfig::default();
+    let trip = |temps: [f64; 3]| {
+        let mut state = rss::SafetyState::default();
+        rss::evaluate(&cfg, &mut state, temps);
+        let trip = state.trip.expect("tripped");
+        assert_eq!(Some(trip.reason()), state.reason);
+        trip
+    };
+
+    let t = trip([425.0, 419.0, 428.0]);
+    assert_eq!(
+        t.detail,
+        rss::TripDetail::OverTemp {
+            trip_temp_c: 420.0,
+            hottest_c: 428.0
+        }
+    );
+    assert_eq!(t.channels, [true, false, true]);
+    assert_eq!(
+        t.to_string(),
+        "OverTemp (s1, s3 >= 420 °C, hottest 428.0 °C)"
+    );
+
+    let t = trip([f64::NAN, 350.0, -5.0]);
+    assert_eq!(
+        t.detail,
+        rss::TripDetail::SensorInvalid {
+            valid_sensors: 1,
+            valid_range_c: (0.0, 2000.0)
+        }
+    );
+    assert_eq!(t.channels, [true, false, true]);
+
+    let t = trip([350.0, 362.5, 351.0]);
+    assert_eq!(
+        t.detail,
+        rss::TripDetail::SensorDisagree {
+            spread_c: 12.5,
+            max_sensor_delta_c: 10.0
+        }
+    );
+    assert_eq!(t.channels, [true, true, false]);
+
+    // latched: later steps keep the record of the trip step
+    let mut state = rss::SafetyState::default();
+    rss::evaluate(&cfg, &mut state, [425.0, 425.0, 425.0]);
+    let first = state.trip;
+    rss::evaluate(&cfg, &mut state, [350.0, 350.0, 350.0]);
+    assert_eq!(state.trip, first);
+
+    let mut state = rss::SafetyState::default();
+    rss::manual_scram(&mut state);
+    let t = state.trip.unwrap();
+    assert_eq!(
+        (t.detail, t.channels),
+        (rss::TripDetail::Manual {}, [false; 3])
+    );
+    assert_eq!(t.to_string(), "Manual");
+}
+
+#[test]
+fn decision_names_the_valid_channels() {
+    let cfg = rss::SafetyConfig::default();
+    let mut state = rss::SafetyState::default();
+    let d = rss::evaluate(&cfg, &mut state, [350.0, f64::NAN, 2100.0]);
+    assert_eq!(d.valid, [true, false, false]);
+    assert_eq!(d.valid_sensors, 1);
+    // Still reported once the trip has latched
+    let d = rss::evaluate(&cfg, &mut state, [350.0, 351.0, 349.0]);
+    assert!(d.scram);
+    assert_eq!(d.valid, [true; 3]);
+
+    assert!(cfg.reading_valid(0.0) && cfg.reading_valid(2000.0));
+    assert!(!cfg.reading_valid(-0.1) && !cfg.reading_valid(f64::INFINITY));
+}
+
+#[test]
+fn margin_is_measured_from_the_voted_temperature() {
+    let cfg = rss::SafetyConfig::default();
+    // One hot channel alone cannot trip: the vote acts on the second-hottest reading
+    assert_eq!(cfg.voted_temp([450.0, 380.0, 382.8]), Some(382.8));
+    assert_eq!(cfg.trip_margin([450.0, 380.0, 382.8]), Some(420.0 - 382.8));
+    // Invalid readings do not vote
+    assert_eq!(cfg.voted_temp([f64::NAN, 380.0, 2500.0]), None);
+    assert_eq!(cfg.voted_temp([f64::NAN, 380.0, 390.0]), Some(380.0));
+    assert_eq!(cfg.trip_margin([425.0, 430.0, 300.0]), Some(-5.0));
+
+    // Zero margin is exactly where the over-temperature trip latches
+    let temps = [420.0, 420.0, 300.0];
+    assert_eq!(cfg.trip_margin(temps), Some(0.0));
+    let mut state = rss::SafetyState::default();
+    assert!(rss::evaluate(&cfg, &mut state, temps).scram);
+}
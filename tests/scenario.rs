@@ -0,0 +1,129 @@
+use reactor_safety_sim as rss;
+
+fn window(from_s: f64, until_s: Option<f64>, fault: rss::SensorFault) -> rss::FaultWindow {
+    rss::FaultWindow {
+        from_s,
+        until_s,
+        fault,
+    }
+}
+
+#[test]
+fn fault_windows_switch_on_and_off() {
+    let schedule = rss::FaultSchedule {
+        windows: vec![
+            window(10.0, Some(20.0), rss::SensorFault::Bias { value: 5.0 }),
+            window(15.0, None, rss::SensorFault::Stuck { value: 300.0 }),
+        ],
+    };
+    assert_eq!(schedule.fault_at(0.0), rss::SensorFault::None);
+    assert_eq!(
+        schedule.fault_at(10.0),
+        rss::SensorFault::Bias { value: 5.0 }
+    );
+    // the later window wins where they overlap, and stays on without an until_s
+    assert_eq!(
+        schedule.fault_at(15.0),
+        rss::SensorFault::Stuck { value: 300.0 }
+    );
+    assert_eq!(
+        schedule.fault_at(1e6),
+        rss::SensorFault::Stuck { value: 300.0 }
+    );
+}
+
+#[test]
+fn fault_window_end_is_exclusive() {
+    let schedule = rss::FaultSchedule {
+        windows: vec![window(
+            1.0,
+            Some(2.0),
+            rss::SensorFault::DropoutEvery { n: 3 },
+        )],
+    };
+    assert_eq!(
+        schedule.fault_at(1.95),
+        rss::SensorFault::DropoutEvery { n: 3 }
+    );
+    assert_eq!(schedule.fault_at(2.0), rss::SensorFault::None);
+}
+
+#[test]
+fn events_sort_stably_by_time() {
+    let event = |t_s, action| rss::TimedEvent { t_s, action };
+    let spec = rss::ScenarioSpec {
+        events: vec![
+            event(5.0, rss::EventAction::Scram),
+            event(1.0, rss::EventAction::SetCoolant(0.1)),
+            event(1.0, rss::EventAction::SetSetpoint(320.0)),
+        ],
+        ..Default::default()
+    };
+    let actions: Vec<_> = spec.sorted_events().iter().map(|e| e.action).collect();
+    assert_eq!(
+        actions,
+        [
+            rss::EventAction::SetCoolant(0.1),
+            rss::EventAction::SetSetpoint(320.0),
+            rss::EventAction::Scram,
+        ]
+    );
+}
+
+#[test]
+fn validate_reports_every_problem() {
+    let spec = rss::ScenarioSpec {
+        initial: rss::InitialConditions {
+            coolant: 1.2,
+            ..Default::default()
+        },
+        sensors: rss::SensorSchedules {
+            s3: rss::FaultSchedule {
+                windows: vec![window(5.0, Some(5.0), rss::SensorFault::None)],
+            },
+            ..Default::default()
+        },
+        events: vec![rss::TimedEvent {
+            t_s: -1.0,
+            action: rss::EventAction::SetSetpoint(f64::NAN),
+        }],
+        ..Default::default()
+    };
+    let errs = spec.validate().unwrap_err();
+    let fields: Vec<String> = errs.iter().map(|e| e.to_string()).collect();
+    assert_eq!(errs.len(), 4, "{fields:?}");
+    assert!(fields[0].starts_with("initial.coolant"));
+    assert!(fields[1].starts_with("sensors.s3[0]"));
+    assert!(fields[2].starts_with("events[0].t_s"));
+    assert!(fields[3].starts_with("events[0].action.set_setpoint"));
+
+    assert_eq!(rss::ScenarioSpec::default().validate(), Ok(()));
+}
+
+#[test]
+fn manual_scram_keeps_an_earlier_reason() {
+    let mut state = rss::SafetyState::default();
+    rss::manual_scram(&mut state);
+    assert!(state.scram);
+    assert_eq!(state.reason, Some(rss::TripReason::Manual));
+
+    let mut state = rss::SafetyState {
+        scram: true,
+        reason: Some(rss::TripReason::OverTemp),
+        trip: None,
+    };
+    rss::manual_scram(&mut state);
+    assert_eq!(state.reason, Some(rss::TripReason::OverTemp));
+}
+
+#[test]
+fn drift_starts_when_the_fault_is_switched_on() {
+    let mut s = rss::Sensor::new(1);
+    s.noise_std = 0.0;
+    for _ in 0..100 {
+        assert_eq!(s.read_temp(300.0, 0.1), 300.0);
+    }
+    s.fault = rss::SensorFault::Drift { per_s: 2.0 };
+    assert!((s.read_temp(300.0, 0.1) - 300.2).abs() < 1e-9);
+    assert!((s.read_temp(300.0, 0.1) - 300.4).abs() < 1e-9);
+}
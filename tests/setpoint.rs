@@ -0,0 +1,81 @@
+use reactor_safety_sim as rss;
+
+fn hold_ramp_hold() -> rss::SetpointProfile {
+    // Hold 300 °C for 30 s, ramp to 380 °C over 60 s, hold.
+    rss::SetpointProfile::new(vec![(0.0, 300.0), (30.0, 300.0), (90.0, 380.0)]).unwrap()
+}
+
+#[test]
+fn profile_evaluates_at_boundaries_and_mid_ramp() {
+    let sp = hold_ramp_hold();
+    assert_eq!(sp.at(-5.0), 300.0);
+    assert_eq!(sp.at(0.0), 300.0);
+    assert_eq!(sp.at(30.0), 300.0);
+    assert_eq!(sp.at(60.0), 340.0);
+    assert_eq!(sp.at(90.0), 380.0);
+    assert_eq!(sp.at(1000.0), 380.0);
+}
+
+#[test]
+fn profile_step_takes_the_later_value_at_the_step_time() {
+    let sp: rss::SetpointProfile = "0:300, 10:300, 10:350".parse().unwrap();
+    assert_eq!(sp.at(9.999), 300.0);
+    assert_eq!(sp.at(10.0), 350.0);
+    assert_eq!(sp.at(20.0), 350.0);
+}
+
+#[test]
+fn profile_rejects_invalid_points() {
+    assert_eq!(
+        rss::SetpointProfile::new(vec![]),
+        Err(rss::SetpointProfileError::Empty)
+    );
+    assert_eq!(
+        rss::SetpointProfile::new(vec![(0.0, 300.0), (f64::NAN, 310.0)]),
+        Err(rss::SetpointProfileError::NonFinite { index: 1 })
+    );
+    assert_eq!(
+        rss::SetpointProfile::new(vec![(10.0, 300.0), (5.0, 310.0)]),
+        Err(rss::SetpointProfileError::TimeNotIncreasing { index: 1 })
+    );
+    assert!(matches!(
+        "0:300,30".parse::<rss::SetpointProfile>(),
+        Err(rss::SetpointProfileError::Parse { .. })
+    ));
+}
+
+#[test]
+fn pid_tracks_a_setpoint_ramp_without_tripping() {
+    let p = rss::PlantParams::default();
+    let mut x = rss::PlantState {
+        coolant: 0.6,
+        ..Default::default()
+    };
+    let mut pid = rss::Pid::new(rss::PidConfig::default());
+    let s_cfg = rss::SafetyConfig::default();
+    let mut s_state = rss::SafetyState::default();
+    let mut sensors = [1, 2, 3].map(rss::Sensor::new);
+    let profile = hold_ramp_hold();
+    let dt = 0.05;
+
+    let mut worst_ramp_error: f64 = 0.0;
+    for k in 0..(300.0 / dt) as usize {
+        let t = k as f64 * dt;
+        let sp = profile.at(t);
+        let y = [0, 1, 2].map(|i| sensors[i].read_temp(x.temp_c, dt));
+        rss::evaluate(&s_cfg, &mut s_state, y);
+        assert!(!s_state.scram, "tripped at t = {t} s: {:?}", s_state.reason);
+
+        x.power = pid.update(sp, (y[0] + y[1] + y[2]) / 3.0, dt);
+        x.step(&p, dt);
+        // Ignore the initial settling from 300 °C at zero power.
+        if (60.0..=90.0).contains(&t) {
+            worst_ramp_error = worst_ramp_error.max((x.temp_c - sp).abs());
+        }
+    }
+    assert!(
+        worst_ramp_error < 10.0,
+        "ramp tracking error {worst_ramp_error}"
+    );
+    assert!((x.temp_c - 380.0).abs() < 2.0, "final temp {}", x.temp_c);
+}
@@ -0,0 +1,68 @@
+use reactor_safety_sim as rss;
+use rss::Controller;
+use std::collections::VecDeque;
+
+const DT: f64 = 0.05;
+
+fn aggressive_pid() -> rss::Pid {
+    rss::Pid::new(rss::PidConfig {
+        kp: 0.2,
+        ki: 0.01,
+        ..Default::default()
+    })
+}
+
+/// Setpoint step with the measurement delayed by `delay_s`. Returns the true temperature trace.
+fn run_delayed(ctrl: &mut dyn Controller, delay_s: f64, seconds: f64) -> Vec<f64> {
+    let p = rss::PlantParams::default();
+    let mut x = rss::PlantState::default();
+    let delay_steps = (delay_s / DT).round() as usize;
+    let mut line: VecDeque<f64> = VecDeque::from(vec![x.temp_c; delay_steps]);
+
+    let mut temps = Vec::new();
+    for _ in 0..(seconds / DT) as usize {
+        line.push_back(x.temp_c);
+        let meas = line.pop_front().unwrap();
+        x.power = ctrl.update(350.0, meas, DT).clamp(0.0, 1.0);
+        x.step(&p, DT);
+        temps.push(x.temp_c);
+    }
+    temps
+}
+
+/// Peak-to-peak temperature over the last `seconds` of the trace.
+fn tail_amplitude(temps: &[f64], seconds: f64) -> f64 {
+    let tail = &temps[temps.len() - (seconds / DT) as usize..];
+    let max = tail.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    let min = tail.iter().cloned().fold(f64::INFINITY, f64::min);
+    max - min
+}
+
+#[test]
+fn smith_predictor_settles_where_plain_pid_oscillates_with_dead_time() {
+    let plain = run_delayed(&mut aggressive_pid(), 2.0, 300.0);
+    let amp = tail_amplitude(&plain, 100.0);
+    assert!(amp > 5.0, "plain PID should oscillate, amplitude {amp}");
+
+    let p = rss::PlantParams::default();
+    let coolant = rss::PlantState::default().coolant;
+    let mut smith = rss::SmithPredictor::new(aggressive_pid(), &p, coolant, 2.0);
+    let temps = run_delayed(&mut smith, 2.0, 300.0);
+    let amp = tail_amplitude(&temps, 100.0);
+    assert!(amp < 0.1, "Smith predictor amplitude {amp}");
+    assert!((temps.last().unwrap() - 350.0).abs() < 0.1);
+}
+
+#[test]
+fn smith_predictor_without_delay_matches_plain_pid() {
+    let plain = run_delayed(&mut aggressive_pid(), 0.0, 120.0);
+
+    let p = rss::PlantParams::default();
+    let coolant = rss::PlantState::default().coolant;
+    let mut smith = rss::SmithPredictor::new(aggressive_pid(), &p, coolant, 0.0);
+    let wrapped = run_delayed(&mut smith, 0.0, 120.0);
+
+    for (a, b) in plain.iter().zip(&wrapped) {
+        assert!((a - b).abs() < 1e-9, "{a} vs {b}");
+    }
+}
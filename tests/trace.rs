@@ -0,0 +1,99 @@
+use reactor_safety_sim as rss;
+use rss::trace::{HeaderView, MetaLine, RowReason, Sample, TraceHeader, TraceRow};
+
+const ROW: &str = r#"{"t_s":0.05,"setpoint_c":380.0,"true_temp_c":391.0,"s1_c":391.2,"s2_c":null,"s3_c":391.1,"power":0.0,"coolant":0.2,"scram":true,"reason":"OverTemp","p_term":0.1,"i_term":0.2,"d_term":0.0,"ff_term":0.0,"out_presat":0.3,"saturated":true,"ctrl_status":"L"}"#;
+
+#[test]
+fn rows_round_trip_with_dropouts_as_null() {
+    let row: TraceRow = serde_json::from_str(ROW).unwrap();
+    assert!(row.s2_c.is_nan());
+    assert_eq!(row.reason, Some(RowReason::Name("OverTemp".into())));
+    assert_eq!(row.diag.as_ref().map(|d| d.ctrl_status.as_str()), Some("L"));
+    assert_eq!(serde_json::to_string(&row).unwrap(), ROW);
+}
+
+#[test]
+fn legacy_rows_load_without_setpoint_or_diagnostics() {
+    let legacy = r#"{"t_s":0.0,"true_temp_c":300.0,"s1_c":300.1,"s2_c":300.0,"s3_c":299.9,"power":1.0,"coolant":0.2,"scram":false,"reason":null}"#;
+    let row: TraceRow = serde_json::from_str(legacy).unwrap();
+    assert!(row.setpoint_c.is_nan());
+    assert!(row.diag.is_none());
+    // Written back the way it was read: no setpoint, no diagnostics
+    assert_eq!(serde_json::to_string(&row).unwrap(), legacy);
+}
+
+#[test]
+fn trip_records_are_the_reason_since_schema_3() {
+    let mut state = rss::SafetyState::default();
+    rss::manual_scram(&mut state);
+    let trip = state.trip.expect("latched");
+    let reason = RowReason::Trip(trip);
+    let json = serde_json::to_string(&reason).unwrap();
+    assert_eq!(serde_json::from_str::<RowReason>(&json).unwrap(), reason);
+    assert_eq!(reason.name(), "Manual");
+    assert_eq!(reason.to_string(), trip.to_string());
+}
+
+#[test]
+fn rows_convert_to_plotted_samples() {
+    let sample = Sample::from(serde_json::from_str::<TraceRow>(ROW).unwrap());
+    assert_eq!(
+        (sample.t, sample.true_temp, sample.setpoint),
+        (0.05, 391.0, 380.0)
+    );
+    assert!(sample.s2.is_nan());
+    assert_eq!((sample.p_term, sample.i_term), (0.1, 0.2));
+    // Clipped at the low limit
+    assert!(sample.saturated);
+    assert!(!sample.alarm);
+    assert_eq!(
+        sample.reason.as_deref().map(RowReason::name).as_deref(),
+        Some("OverTemp")
+    );
+}
+
+#[test]
+fn headers_keep_the_shared_fields_beside_a_writers_own() {
+    let header = TraceHeader {
+        schema: rss::trace::TRACE_SCHEMA,
+        crate_version: "0.1.0".into(),
+        scenario: "overheat".into(),
+        seed: 42,
+        dt_ms: 50,
+        setpoint: 380.0,
+        trip_temp: 390.0,
+        config_hash: "0123456789abcdef".into(),
+    };
+    let line = serde_json::to_string(&MetaLine { meta: &header }).unwrap();
+    assert!(line.starts_with(r#"{"_meta":{"schema":3,"#), "{line}");
+
+    // What only one writer records (the CLI its configuration, the GUI its operator actions) is
+    // skipped
+    let mut value: serde_json::Value = serde_json::from_str(&line).unwrap();
+    value["_meta"]["operator_events"] = serde_json::json!([]);
+    let back: MetaLine<TraceHeader> = serde_json::from_value(value).unwrap();
+    assert_eq!(back.meta, header);
+}
+
+#[test]
+fn header_views_read_partial_headers_and_the_configured_plant() {
+    let partial = r#"{"_meta":{"schema":2,"setpoint":380.0}}"#;
+    let view = serde_json::from_str::<MetaLine<HeaderView>>(partial)
+        .unwrap()
+        .meta;
+    assert_eq!((view.schema, view.setpoint), (2, Some(380.0)));
+    assert_eq!((view.trip_temp, view.plant()), (None, None));
+    assert!(serde_json::from_str::<MetaLine<HeaderView>>(r#"{"_meta":{}}"#).is_err());
+
+    let plant = rss::PlantParams {
+        k_power: 500.0,
+        ..Default::default()
+    };
+    let header = serde_json::json!({
+        "_meta": {"schema": 3, "seed": 7, "config": {"plant": plant, "pid": {"kp": 0.1}}}
+    });
+    let view = serde_json::from_value::<MetaLine<HeaderView>>(header)
+        .unwrap()
+        .meta;
+    assert_eq!((view.seed, view.plant()), (Some(7), Some(plant)));
+}